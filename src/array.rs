@@ -0,0 +1,244 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArrayKey {
+    Int(i64),
+    Str(Rc<Vec<u8>>),
+}
+
+impl ArrayKey {
+    /// Builds a key from a string, applying PHP's rule that canonical
+    /// decimal integers ("12", "-3", but not "012" or "1.0") become int keys.
+    pub fn from_bytes(bytes: &[u8]) -> ArrayKey {
+        match canonical_int(bytes) {
+            Some(n) => ArrayKey::Int(n),
+            None => ArrayKey::Str(Rc::new(bytes.to_vec())),
+        }
+    }
+}
+
+impl From<&str> for ArrayKey {
+    fn from(s: &str) -> Self {
+        ArrayKey::from_bytes(s.as_bytes())
+    }
+}
+
+fn canonical_int(bytes: &[u8]) -> Option<i64> {
+    let digits = bytes.strip_prefix(b"-").unwrap_or(bytes);
+    if digits.is_empty() || digits.len() > 20 || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    if digits[0] == b'0' && (digits.len() > 1 || bytes.len() > 1) {
+        return None; // "0" is canonical, "00" and "-0" are not
+    }
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// A hash map that keeps its entries in insertion order: the entries plus
+/// an index of their keys. Arrays and variable scopes are built on it.
+#[derive(Debug, Clone)]
+pub(crate) struct OrderedMap<K, V> {
+    entries: Vec<(K, V)>,
+    index: HashMap<K, usize>,
+}
+
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        OrderedMap { entries: Vec::new(), index: HashMap::new() }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> OrderedMap<K, V> {
+    pub(crate) fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub(crate) fn get<Q: Eq + Hash + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        let i = *self.index.get(key)?;
+        Some(&self.entries[i].1)
+    }
+
+    pub(crate) fn get_mut<Q: Eq + Hash + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        let i = *self.index.get(key)?;
+        Some(&mut self.entries[i].1)
+    }
+
+    pub(crate) fn contains_key<Q: Eq + Hash + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.index.contains_key(key)
+    }
+
+    /// Sets the value of `key`, at the end if it is new, and returns the
+    /// value it replaced.
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(&i) = self.index.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Returns the slot for `key`, inserting `value` at the end if missing.
+    pub(crate) fn entry_or(&mut self, key: K, value: V) -> &mut V {
+        let i = match self.index.get(&key) {
+            Some(&i) => i,
+            None => {
+                self.insert(key, value);
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[i].1
+    }
+
+    pub(crate) fn remove<Q: Eq + Hash + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.index.remove(key)?;
+        let (_, v) = self.entries.remove(i);
+        for (k, _) in &self.entries[i..] {
+            *self.index.get_mut::<K>(k).unwrap() -= 1;
+        }
+        Some(v)
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<(K, V)> {
+        let (key, v) = self.entries.pop()?;
+        self.index.remove(&key);
+        Some((key, v))
+    }
+
+    /// The entry at a position in iteration order.
+    pub(crate) fn entry_at(&self, pos: usize) -> Option<(&K, &V)> {
+        self.entries.get(pos).map(|(k, v)| (k, v))
+    }
+
+    pub(crate) fn entry_at_mut(&mut self, pos: usize) -> Option<(&K, &mut V)> {
+        self.entries.get_mut(pos).map(|(k, v)| (&*k, v))
+    }
+
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub(crate) fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub(crate) fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+/// PHP's ordered hash map, with the next free integer key.
+#[derive(Debug, Clone, Default)]
+pub struct PhpArray {
+    map: OrderedMap<ArrayKey, Value>,
+    next_index: i64,
+}
+
+impl PhpArray {
+    pub fn new() -> Self {
+        PhpArray::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.len() == 0
+    }
+
+    pub fn get(&self, key: &ArrayKey) -> Option<&Value> {
+        self.map.get(key)
+    }
+
+    pub fn contains_key(&self, key: &ArrayKey) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn get_mut(&mut self, key: &ArrayKey) -> Option<&mut Value> {
+        self.map.get_mut(key)
+    }
+
+    pub fn remove(&mut self, key: &ArrayKey) -> Option<Value> {
+        self.map.remove(key)
+    }
+
+    /// Removes the last entry. The next free key goes back by one if the
+    /// entry had the key just before it, as in `array_pop()`.
+    pub fn pop(&mut self) -> Option<(ArrayKey, Value)> {
+        let (key, v) = self.map.pop()?;
+        if let ArrayKey::Int(n) = key
+            && n.wrapping_add(1) == self.next_index
+        {
+            self.next_index = n;
+        }
+        Some((key, v))
+    }
+
+    /// Returns the slot for `key`, inserting `Null` at the end if missing.
+    pub fn entry(&mut self, key: ArrayKey) -> &mut Value {
+        self.bump_next_index(&key);
+        self.map.entry_or(key, Value::Null)
+    }
+
+    pub fn insert(&mut self, key: ArrayKey, value: Value) {
+        self.bump_next_index(&key);
+        self.map.insert(key, value);
+    }
+
+    fn bump_next_index(&mut self, key: &ArrayKey) {
+        if let ArrayKey::Int(n) = *key
+            && n >= self.next_index {
+                self.next_index = n.wrapping_add(1);
+            }
+    }
+
+    /// Appends with the next free integer key (`$a[] = $v`). Fails when
+    /// that key would overflow, matching PHP's
+    /// "next element is already occupied" error.
+    pub fn push(&mut self, value: Value) -> Result<(), String> {
+        if self.next_index == i64::MIN {
+            return Err("Cannot add element to the array as the next element is already occupied".to_string());
+        }
+        let key = ArrayKey::Int(self.next_index);
+        self.insert(key, value);
+        Ok(())
+    }
+
+    /// Like `push`, but returns the new slot.
+    pub fn push_slot(&mut self) -> Result<&mut Value, String> {
+        self.push(Value::Null)?;
+        let last = self.map.len() - 1;
+        Ok(self.map.entry_at_mut(last).unwrap().1)
+    }
+
+    /// The entry at a position in iteration order.
+    pub fn entry_at(&self, pos: usize) -> Option<(&ArrayKey, &Value)> {
+        self.map.entry_at(pos)
+    }
+
+    pub fn entry_at_mut(&mut self, pos: usize) -> Option<(&ArrayKey, &mut Value)> {
+        self.map.entry_at_mut(pos)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&ArrayKey, &Value)> {
+        self.map.iter()
+    }
+}
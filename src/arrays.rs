@@ -0,0 +1,1350 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::{BinOp, UnaryOp};
+use crate::extension::{arg_array, arg_bool, arg_int, opt_int, set_ref, NativeFunction};
+use crate::math;
+use crate::strings::natural_compare;
+use crate::value::{loose_compare, loose_equals, strict_equals, Numeric, Value};
+use crate::vm::{ref_cell, Callee, Vm, VmError, VmResult};
+
+/// Array functions. Sorting is stable, as in PHP 8.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction { name: "count", params: &["Countable|array value", "int mode = COUNT_NORMAL"], func: count },
+    NativeFunction { name: "sizeof", params: &["Countable|array value", "int mode = COUNT_NORMAL"], func: count },
+    NativeFunction { name: "array_keys", params: &["array array", "mixed filter_value = UNKNOWN", "bool strict = false"], func: array_keys },
+    NativeFunction { name: "array_values", params: &["array array"], func: array_values },
+    NativeFunction { name: "array_merge", params: &["array ...arrays"], func: array_merge },
+    NativeFunction { name: "array_replace", params: &["array array", "array ...replacements"], func: array_replace },
+    NativeFunction { name: "array_combine", params: &["array keys", "array values"], func: array_combine },
+    NativeFunction {
+        name: "array_slice",
+        params: &["array array", "int offset", "?int length = null", "bool preserve_keys = false"],
+        func: array_slice,
+    },
+    NativeFunction {
+        name: "array_splice",
+        params: &["array &array", "int offset", "?int length = null", "mixed replacement = []"],
+        func: array_splice,
+    },
+    NativeFunction { name: "array_map", params: &["?callable callback", "array array", "array ...arrays"], func: array_map },
+    NativeFunction { name: "array_filter", params: &["array array", "?callable callback = null", "int mode = 0"], func: array_filter },
+    NativeFunction { name: "array_reduce", params: &["array array", "callable callback", "mixed initial = null"], func: array_reduce },
+    NativeFunction { name: "array_walk", params: &["array|object &array", "callable callback", "mixed arg = UNKNOWN"], func: array_walk },
+    NativeFunction { name: "in_array", params: &["mixed needle", "array haystack", "bool strict = false"], func: in_array },
+    NativeFunction { name: "array_search", params: &["mixed needle", "array haystack", "bool strict = false"], func: array_search },
+    NativeFunction { name: "array_key_exists", params: &["key", "array array"], func: array_key_exists },
+    NativeFunction { name: "key_exists", params: &["key", "array array"], func: array_key_exists },
+    NativeFunction { name: "array_key_first", params: &["array array"], func: array_key_first },
+    NativeFunction { name: "array_key_last", params: &["array array"], func: array_key_last },
+    NativeFunction { name: "array_is_list", params: &["array array"], func: array_is_list },
+    NativeFunction { name: "array_unique", params: &["array array", "int flags = SORT_STRING"], func: array_unique },
+    NativeFunction { name: "array_flip", params: &["array array"], func: array_flip },
+    NativeFunction { name: "array_count_values", params: &["array array"], func: array_count_values },
+    NativeFunction { name: "array_fill", params: &["int start_index", "int count", "mixed value"], func: array_fill },
+    NativeFunction { name: "array_fill_keys", params: &["array keys", "mixed value"], func: array_fill_keys },
+    NativeFunction { name: "array_pad", params: &["array array", "int length", "mixed value"], func: array_pad },
+    NativeFunction { name: "range", params: &["string|int|float start", "string|int|float end", "int|float step = 1"], func: range },
+    NativeFunction {
+        name: "array_column",
+        params: &["array array", "string|int|null column_key", "string|int|null index_key = null"],
+        func: array_column,
+    },
+    NativeFunction { name: "array_chunk", params: &["array array", "int length", "bool preserve_keys = false"], func: array_chunk },
+    NativeFunction { name: "array_reverse", params: &["array array", "bool preserve_keys = false"], func: array_reverse },
+    NativeFunction { name: "array_push", params: &["array &array", "mixed ...values"], func: array_push },
+    NativeFunction { name: "array_pop", params: &["array &array"], func: array_pop },
+    NativeFunction { name: "array_shift", params: &["array &array"], func: array_shift },
+    NativeFunction { name: "array_unshift", params: &["array &array", "mixed ...values"], func: array_unshift },
+    NativeFunction { name: "array_sum", params: &["array array"], func: array_sum },
+    NativeFunction { name: "array_product", params: &["array array"], func: array_product },
+    NativeFunction { name: "array_diff", params: &["array array", "array ...arrays"], func: array_diff },
+    NativeFunction { name: "array_diff_key", params: &["array array", "array ...arrays"], func: array_diff_key },
+    NativeFunction { name: "array_diff_assoc", params: &["array array", "array ...arrays"], func: array_diff_assoc },
+    NativeFunction { name: "array_intersect", params: &["array array", "array ...arrays"], func: array_intersect },
+    NativeFunction { name: "array_intersect_key", params: &["array array", "array ...arrays"], func: array_intersect_key },
+    NativeFunction { name: "array_intersect_assoc", params: &["array array", "array ...arrays"], func: array_intersect_assoc },
+    NativeFunction { name: "compact", params: &["array|string var_name", "array|string ...var_names"], func: compact },
+    NativeFunction { name: "extract", params: &["array &?array", "int flags = EXTR_OVERWRITE", "string prefix = \"\""], func: extract },
+    NativeFunction { name: "sort", params: &["array &array", "int flags = SORT_REGULAR"], func: sort },
+    NativeFunction { name: "rsort", params: &["array &array", "int flags = SORT_REGULAR"], func: rsort },
+    NativeFunction { name: "asort", params: &["array &array", "int flags = SORT_REGULAR"], func: asort },
+    NativeFunction { name: "arsort", params: &["array &array", "int flags = SORT_REGULAR"], func: arsort },
+    NativeFunction { name: "ksort", params: &["array &array", "int flags = SORT_REGULAR"], func: ksort },
+    NativeFunction { name: "krsort", params: &["array &array", "int flags = SORT_REGULAR"], func: krsort },
+    NativeFunction { name: "usort", params: &["array &array", "callable callback"], func: usort },
+    NativeFunction { name: "uasort", params: &["array &array", "callable callback"], func: uasort },
+    NativeFunction { name: "uksort", params: &["array &array", "callable callback"], func: uksort },
+    NativeFunction { name: "natsort", params: &["array &array"], func: natsort },
+    NativeFunction { name: "natcasesort", params: &["array &array"], func: natcasesort },
+    NativeFunction { name: "array_multisort", params: &["array &?array", "mixed &?...rest"], func: array_multisort },
+];
+
+const COUNT_RECURSIVE: i64 = 1;
+
+const SORT_REGULAR: i64 = 0;
+const SORT_NUMERIC: i64 = 1;
+const SORT_STRING: i64 = 2;
+const SORT_DESC: i64 = 3;
+const SORT_ASC: i64 = 4;
+const SORT_LOCALE_STRING: i64 = 5;
+const SORT_NATURAL: i64 = 6;
+const SORT_FLAG_CASE: i64 = 8;
+
+const ARRAY_FILTER_USE_BOTH: i64 = 1;
+const ARRAY_FILTER_USE_KEY: i64 = 2;
+
+const EXTR_OVERWRITE: i64 = 0;
+const EXTR_SKIP: i64 = 1;
+const EXTR_PREFIX_SAME: i64 = 2;
+const EXTR_PREFIX_ALL: i64 = 3;
+const EXTR_PREFIX_INVALID: i64 = 4;
+const EXTR_PREFIX_IF_EXISTS: i64 = 5;
+const EXTR_IF_EXISTS: i64 = 6;
+const EXTR_REFS: i64 = 256;
+
+pub(crate) const CONSTANTS: &[(&str, i64)] = &[
+    ("COUNT_NORMAL", 0),
+    ("COUNT_RECURSIVE", COUNT_RECURSIVE),
+    ("SORT_REGULAR", SORT_REGULAR),
+    ("SORT_NUMERIC", SORT_NUMERIC),
+    ("SORT_STRING", SORT_STRING),
+    ("SORT_DESC", SORT_DESC),
+    ("SORT_ASC", SORT_ASC),
+    ("SORT_LOCALE_STRING", SORT_LOCALE_STRING),
+    ("SORT_NATURAL", SORT_NATURAL),
+    ("SORT_FLAG_CASE", SORT_FLAG_CASE),
+    ("ARRAY_FILTER_USE_BOTH", ARRAY_FILTER_USE_BOTH),
+    ("ARRAY_FILTER_USE_KEY", ARRAY_FILTER_USE_KEY),
+    ("EXTR_OVERWRITE", EXTR_OVERWRITE),
+    ("EXTR_SKIP", EXTR_SKIP),
+    ("EXTR_PREFIX_SAME", EXTR_PREFIX_SAME),
+    ("EXTR_PREFIX_ALL", EXTR_PREFIX_ALL),
+    ("EXTR_PREFIX_INVALID", EXTR_PREFIX_INVALID),
+    ("EXTR_PREFIX_IF_EXISTS", EXTR_PREFIX_IF_EXISTS),
+    ("EXTR_IF_EXISTS", EXTR_IF_EXISTS),
+    ("EXTR_REFS", EXTR_REFS),
+];
+
+fn key_value(k: &ArrayKey) -> Value {
+    match k {
+        ArrayKey::Int(n) => Value::Int(*n),
+        ArrayKey::Str(s) => Value::Str(s.clone()),
+    }
+}
+
+/// The key a value becomes in `array_combine()` and the like: ints stay,
+/// anything else goes through its string form.
+fn to_key(vm: &mut Vm, v: &Value) -> VmResult<ArrayKey> {
+    match v.deref() {
+        Value::Int(n) => Ok(ArrayKey::Int(n)),
+        v => Ok(ArrayKey::from_bytes(&vm.stringify(&v)?)),
+    }
+}
+
+fn list(items: impl IntoIterator<Item = Value>) -> VmResult<Value> {
+    let mut arr = PhpArray::new();
+    for v in items {
+        arr.push(v).map_err(VmError::error)?;
+    }
+    Ok(Value::from(arr))
+}
+
+/// Adds an entry the way functions that renumber do: string keys stay,
+/// int keys are appended.
+fn append(arr: &mut PhpArray, k: &ArrayKey, v: Value) -> VmResult<()> {
+    match k {
+        ArrayKey::Str(_) => arr.insert(k.clone(), v),
+        ArrayKey::Int(_) => arr.push(v).map_err(VmError::error)?,
+    }
+    Ok(())
+}
+
+fn renumbered<'a>(entries: impl IntoIterator<Item = (&'a ArrayKey, &'a Value)>) -> VmResult<PhpArray> {
+    let mut arr = PhpArray::new();
+    for (k, v) in entries {
+        append(&mut arr, k, v.clone())?;
+    }
+    Ok(arr)
+}
+
+fn not_array(callee: &Callee, i: usize, v: Option<&Value>) -> VmError {
+    VmError::type_error(format!(
+        "{}(): Argument #{} (${}) must be of type array, {} given",
+        callee.func.name,
+        i + 1,
+        callee.func.params[i].name,
+        v.map_or_else(|| "null".to_string(), Value::type_name)
+    ))
+}
+
+/// A copy of the array passed to a by-reference parameter.
+fn ref_array(callee: &Callee, args: &[Value], i: usize) -> VmResult<PhpArray> {
+    match args.get(i).map(Value::deref) {
+        Some(Value::Array(a)) => Ok((*a).clone()),
+        v => Err(not_array(callee, i, v.as_ref())),
+    }
+}
+
+/// Changes the array passed to a by-reference parameter in place.
+fn with_ref_array<R>(callee: &Callee, args: &[Value], i: usize, f: impl FnOnce(&mut PhpArray) -> R) -> VmResult<R> {
+    if let Some(Value::Ref(cell)) = args.get(i)
+        && let Value::Array(a) = &mut *cell.borrow_mut()
+    {
+        return Ok(f(Rc::make_mut(a)));
+    }
+    let mut arr = ref_array(callee, args, i)?;
+    Ok(f(&mut arr))
+}
+
+/// A stable merge sort whose comparison may call PHP code, and so fail.
+/// It never panics on inconsistent comparisons.
+fn merge_sort<T>(
+    vm: &mut Vm,
+    mut items: Vec<T>,
+    cmp: &mut dyn FnMut(&mut Vm, &T, &T) -> VmResult<Ordering>,
+) -> VmResult<Vec<T>> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(vm, items, cmp)?;
+    let right = merge_sort(vm, right, cmp)?;
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        let next = if cmp(vm, a, b)? == Ordering::Greater { right.next() } else { left.next() };
+        out.extend(next);
+    }
+    out.extend(left);
+    out.extend(right);
+    Ok(out)
+}
+
+/// Compares two values under a `SORT_*` flag.
+fn flag_compare(vm: &mut Vm, a: &Value, b: &Value, flags: i64) -> VmResult<Ordering> {
+    let fold = flags & SORT_FLAG_CASE != 0;
+    Ok(match flags & !SORT_FLAG_CASE {
+        SORT_NUMERIC => {
+            let float = |v: &Value| match v.to_numeric() {
+                Numeric::Int(n) => n as f64,
+                Numeric::Float(f) => f,
+            };
+            float(a).partial_cmp(&float(b)).unwrap_or(Ordering::Greater)
+        }
+        SORT_STRING | SORT_LOCALE_STRING => {
+            let (x, y) = (vm.stringify(a)?, vm.stringify(b)?);
+            match fold {
+                true => x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase()),
+                false => x.cmp(&y),
+            }
+        }
+        SORT_NATURAL => natural_compare(&vm.stringify(a)?, &vm.stringify(b)?, fold).cmp(&0),
+        _ => loose_compare(a, b).unwrap_or(Ordering::Greater),
+    })
+}
+
+/// A comparison function passed to `usort()` and friends.
+struct UserCompare {
+    callback: Callee,
+    func: Rc<str>,
+    warned: bool,
+}
+
+impl UserCompare {
+    fn new(vm: &mut Vm, callee: &Callee, callback: &Value) -> VmResult<UserCompare> {
+        Ok(UserCompare { callback: vm.resolve_callable(callback)?, func: callee.func.name.clone(), warned: false })
+    }
+
+    /// Calls the function. One returning a bool is deprecated; `false`
+    /// is then retried with the operands swapped, as PHP does.
+    fn compare(&mut self, vm: &mut Vm, a: &Value, b: &Value) -> VmResult<Ordering> {
+        let result = vm.call(self.callback.clone(), vec![a.deref(), b.deref()])?;
+        if let Value::Bool(r) = result {
+            if !self.warned {
+                vm.deprecated(&format!(
+                    "{}(): Returning bool from comparison function is deprecated, return an integer less than, equal to, or greater than zero",
+                    self.func
+                ));
+                self.warned = true;
+            }
+            if !r {
+                let swapped = vm.call(self.callback.clone(), vec![b.deref(), a.deref()])?;
+                return Ok(0.cmp(&swapped.to_int()));
+            }
+        }
+        Ok(result.to_int().cmp(&0))
+    }
+}
+
+type Entry = (ArrayKey, Value);
+
+/// Sorts the array in a by-reference argument by its entries, then
+/// stores it back with its keys kept or renumbered.
+fn sort_entries(
+    vm: &mut Vm,
+    callee: &Callee,
+    args: &[Value],
+    keep_keys: bool,
+    cmp: &mut dyn FnMut(&mut Vm, &Entry, &Entry) -> VmResult<Ordering>,
+) -> VmResult<Value> {
+    let arr = ref_array(callee, args, 0)?;
+    let entries: Vec<Entry> = arr.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    let sorted = merge_sort(vm, entries, cmp)?;
+    let mut out = PhpArray::new();
+    for (k, v) in sorted {
+        match keep_keys {
+            true => out.insert(k, v),
+            false => out.push(v).map_err(VmError::error)?,
+        }
+    }
+    set_ref(args, 0, Value::from(out));
+    Ok(Value::Bool(true))
+}
+
+fn sort_by_value(vm: &mut Vm, callee: &Callee, args: &[Value], keep_keys: bool, reverse: bool) -> VmResult<Value> {
+    let flags = arg_int(args, 1, SORT_REGULAR);
+    sort_entries(vm, callee, args, keep_keys, &mut |vm, a, b| match reverse {
+        true => flag_compare(vm, &b.1, &a.1, flags),
+        false => flag_compare(vm, &a.1, &b.1, flags),
+    })
+}
+
+fn sort_by_key(vm: &mut Vm, callee: &Callee, args: &[Value], reverse: bool) -> VmResult<Value> {
+    let flags = arg_int(args, 1, SORT_REGULAR);
+    sort_entries(vm, callee, args, true, &mut |vm, a, b| {
+        let (a, b) = (key_value(&a.0), key_value(&b.0));
+        match reverse {
+            true => flag_compare(vm, &b, &a, flags),
+            false => flag_compare(vm, &a, &b, flags),
+        }
+    })
+}
+
+/// `sort(array &$array, int $flags = SORT_REGULAR): true`
+fn sort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_by_value(vm, callee, &args, false, false)
+}
+
+/// `rsort(array &$array, int $flags = SORT_REGULAR): true`
+fn rsort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_by_value(vm, callee, &args, false, true)
+}
+
+/// `asort(array &$array, int $flags = SORT_REGULAR): true`
+fn asort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_by_value(vm, callee, &args, true, false)
+}
+
+/// `arsort(array &$array, int $flags = SORT_REGULAR): true`
+fn arsort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_by_value(vm, callee, &args, true, true)
+}
+
+/// `ksort(array &$array, int $flags = SORT_REGULAR): true`
+fn ksort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_by_key(vm, callee, &args, false)
+}
+
+/// `krsort(array &$array, int $flags = SORT_REGULAR): true`
+fn krsort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_by_key(vm, callee, &args, true)
+}
+
+/// `usort(array &$array, callable $callback): true`
+fn usort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut user = UserCompare::new(vm, callee, &args[1])?;
+    sort_entries(vm, callee, &args, false, &mut |vm, a, b| user.compare(vm, &a.1, &b.1))
+}
+
+/// `uasort(array &$array, callable $callback): true`
+fn uasort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut user = UserCompare::new(vm, callee, &args[1])?;
+    sort_entries(vm, callee, &args, true, &mut |vm, a, b| user.compare(vm, &a.1, &b.1))
+}
+
+/// `uksort(array &$array, callable $callback): true`
+fn uksort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut user = UserCompare::new(vm, callee, &args[1])?;
+    sort_entries(vm, callee, &args, true, &mut |vm, a, b| user.compare(vm, &key_value(&a.0), &key_value(&b.0)))
+}
+
+/// `natsort(array &$array): true`
+fn natsort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_entries(vm, callee, &args, true, &mut |vm, a, b| flag_compare(vm, &a.1, &b.1, SORT_NATURAL))
+}
+
+/// `natcasesort(array &$array): true`
+fn natcasesort(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    sort_entries(vm, callee, &args, true, &mut |vm, a, b| flag_compare(vm, &a.1, &b.1, SORT_NATURAL | SORT_FLAG_CASE))
+}
+
+/// `array_multisort(array &$array, mixed &...$rest): true`
+///
+/// Sorts the arrays together, like the columns of a table: by the first,
+/// then by the next where those are equal. Each array may be followed by
+/// an order and a `SORT_*` flag.
+fn array_multisort(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    struct Column {
+        arg: usize,
+        entries: Vec<Entry>,
+        descending: bool,
+        flags: i64,
+    }
+    let mut columns: Vec<Column> = Vec::new();
+    let (mut order_set, mut flags_set) = (true, true);
+    for (i, arg) in args.iter().enumerate() {
+        let name = if i == 0 { " ($array)" } else { "" };
+        match arg.deref() {
+            Value::Array(a) => {
+                columns.push(Column {
+                    arg: i,
+                    entries: a.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                    descending: false,
+                    flags: SORT_REGULAR,
+                });
+                (order_set, flags_set) = (false, false);
+            }
+            Value::Int(n) => {
+                let already = VmError::type_error(format!(
+                    "array_multisort(): Argument #{}{} must be an array or a sort flag that has not already been specified",
+                    i + 1,
+                    name
+                ));
+                match n & !SORT_FLAG_CASE {
+                    SORT_ASC | SORT_DESC if !order_set => {
+                        columns.last_mut().unwrap().descending = n == SORT_DESC;
+                        order_set = true;
+                    }
+                    SORT_REGULAR | SORT_NUMERIC | SORT_STRING | SORT_LOCALE_STRING | SORT_NATURAL if !flags_set => {
+                        columns.last_mut().unwrap().flags = n;
+                        flags_set = true;
+                    }
+                    SORT_ASC | SORT_DESC | SORT_REGULAR | SORT_NUMERIC | SORT_STRING | SORT_LOCALE_STRING | SORT_NATURAL => {
+                        return Err(already);
+                    }
+                    _ => {
+                        return Err(VmError::value_error(format!(
+                            "array_multisort(): Argument #{}{} must be a valid sort flag",
+                            i + 1,
+                            name
+                        )));
+                    }
+                }
+            }
+            _ => {
+                return Err(VmError::type_error(format!(
+                    "array_multisort(): Argument #{}{} must be an array or a sort flag",
+                    i + 1,
+                    name
+                )));
+            }
+        }
+    }
+    let size = columns[0].entries.len();
+    if columns.iter().any(|c| c.entries.len() != size) {
+        return Err(VmError::value_error("Array sizes are inconsistent"));
+    }
+    let rows = merge_sort(vm, (0..size).collect(), &mut |vm, &a, &b| {
+        for c in &columns {
+            let (x, y) = if c.descending { (&c.entries[b].1, &c.entries[a].1) } else { (&c.entries[a].1, &c.entries[b].1) };
+            match flag_compare(vm, x, y, c.flags)? {
+                Ordering::Equal => {}
+                o => return Ok(o),
+            }
+        }
+        Ok(Ordering::Equal)
+    })?;
+    for c in &columns {
+        let sorted = renumbered(rows.iter().map(|&r| (&c.entries[r].0, &c.entries[r].1)))?;
+        set_ref(&args, c.arg, Value::from(sorted));
+    }
+    Ok(Value::Bool(true))
+}
+
+fn count_recursive(arr: &PhpArray) -> i64 {
+    arr.iter()
+        .map(|(_, v)| match v.deref() {
+            Value::Array(a) => 1 + count_recursive(&a),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// `count(Countable|array $value, int $mode = COUNT_NORMAL): int`
+fn count(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mode = arg_int(&args, 1, 0);
+    if mode != 0 && mode != COUNT_RECURSIVE {
+        return Err(VmError::value_error(format!(
+            "{}(): Argument #2 ($mode) must be either COUNT_NORMAL or COUNT_RECURSIVE",
+            callee.func.name
+        )));
+    }
+    match &args[0] {
+        Value::Array(a) if mode == COUNT_RECURSIVE => Ok(Value::Int(count_recursive(a))),
+        Value::Array(a) => Ok(Value::Int(a.len() as i64)),
+        obj => {
+            let method = vm.method_callee_of(obj.clone(), "count")?;
+            Ok(Value::Int(vm.call(method, Vec::new())?.to_int()))
+        }
+    }
+}
+
+/// `array_keys(array $array, mixed $filter_value = UNKNOWN, bool $strict = false): array`
+fn array_keys(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    let strict = arg_bool(&args, 2, false);
+    let keys = arr.iter().filter(|(_, v)| match args.get(1) {
+        Some(filter) if strict => strict_equals(v, filter),
+        Some(filter) => loose_equals(v, filter),
+        None => true,
+    });
+    list(keys.map(|(k, _)| key_value(k)))
+}
+
+/// `array_values(array $array): array`
+fn array_values(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    list(arg_array(&args, 0).iter().map(|(_, v)| v.clone()))
+}
+
+/// `array_merge(array ...$arrays): array`
+///
+/// Later string keys overwrite earlier ones; int keys are renumbered.
+fn array_merge(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arrays: Vec<_> = (0..args.len()).map(|i| arg_array(&args, i)).collect();
+    vm.reserve_array(arrays.iter().map(|a| a.len()).sum())?;
+    let mut out = PhpArray::new();
+    for arr in &arrays {
+        for (k, v) in arr.iter() {
+            append(&mut out, k, v.clone())?;
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_replace(array $array, array ...$replacements): array`
+fn array_replace(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut out = (*arg_array(&args, 0)).clone();
+    for i in 1..args.len() {
+        for (k, v) in arg_array(&args, i).iter() {
+            out.insert(k.clone(), v.clone());
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_combine(array $keys, array $values): array`
+fn array_combine(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (keys, values) = (arg_array(&args, 0), arg_array(&args, 1));
+    if keys.len() != values.len() {
+        return Err(VmError::value_error(
+            "array_combine(): Argument #1 ($keys) and argument #2 ($values) must have the same number of elements",
+        ));
+    }
+    let mut out = PhpArray::new();
+    for ((_, k), (_, v)) in keys.iter().zip(values.iter()) {
+        let k = to_key(vm, k)?;
+        out.insert(k, v.clone());
+    }
+    Ok(Value::from(out))
+}
+
+/// The range `offset` and `length` select in `len` entries, the way
+/// `array_slice()` and `array_splice()` read them.
+fn slice_bounds(len: usize, offset: i64, length: Option<i64>) -> (usize, usize) {
+    let len = len as i64;
+    let start = if offset < 0 { (len + offset).max(0) } else { offset.min(len) };
+    let end = match length {
+        None => len,
+        Some(n) if n < 0 => (len + n).max(start),
+        Some(n) => start.saturating_add(n).min(len),
+    };
+    (start as usize, end as usize)
+}
+
+/// `array_slice(array $array, int $offset, ?int $length = null, bool $preserve_keys = false): array`
+fn array_slice(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    let (start, end) = slice_bounds(arr.len(), arg_int(&args, 1, 0), opt_int(&args, 2));
+    let entries = arr.iter().skip(start).take(end - start);
+    if arg_bool(&args, 3, false) {
+        let mut out = PhpArray::new();
+        for (k, v) in entries {
+            out.insert(k.clone(), v.clone());
+        }
+        return Ok(Value::from(out));
+    }
+    Ok(Value::from(renumbered(entries)?))
+}
+
+/// `array_splice(array &$array, int $offset, ?int $length = null, mixed $replacement = []): array`
+///
+/// Removes a slice and puts the replacement in its place; returns the
+/// removed entries.
+fn array_splice(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = ref_array(callee, &args, 0)?;
+    let (start, end) = slice_bounds(arr.len(), arg_int(&args, 1, 0), opt_int(&args, 2));
+    let replacement = match args.get(3) {
+        Some(v) => vm.unary_op(UnaryOp::ArrayCast, v.deref())?,
+        None => Value::from(PhpArray::new()),
+    };
+    let Value::Array(replacement) = replacement else { unreachable!() };
+    let (mut out, mut removed) = (PhpArray::new(), PhpArray::new());
+    for (i, (k, v)) in arr.iter().enumerate() {
+        if i == start {
+            for (_, r) in replacement.iter() {
+                out.push(r.clone()).map_err(VmError::error)?;
+            }
+        }
+        match i >= start && i < end {
+            true => append(&mut removed, k, v.clone())?,
+            false => append(&mut out, k, v.clone())?,
+        }
+    }
+    if start == arr.len() {
+        for (_, r) in replacement.iter() {
+            out.push(r.clone()).map_err(VmError::error)?;
+        }
+    }
+    set_ref(&args, 0, Value::from(out));
+    Ok(Value::from(removed))
+}
+
+/// `array_map(?callable $callback, array $array, array ...$arrays): array`
+///
+/// With one array the keys are kept; with several, the callback gets an
+/// entry of each, padded with nulls, and the result is a list.
+fn array_map(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let callback = match &args[0] {
+        Value::Null => None,
+        v => Some(vm.resolve_callable(v)?),
+    };
+    let arrays: Vec<_> = (1..args.len()).map(|i| arg_array(&args, i)).collect();
+    if let [arr] = arrays.as_slice() {
+        let Some(callback) = callback else { return Ok(Value::Array(arr.clone())) };
+        let mut out = PhpArray::new();
+        for (k, v) in arr.iter() {
+            let v = vm.call(callback.clone(), vec![v.deref()])?;
+            out.insert(k.clone(), v);
+        }
+        return Ok(Value::from(out));
+    }
+    let len = arrays.iter().map(|a| a.len()).max().unwrap_or(0);
+    let mut out = PhpArray::new();
+    for i in 0..len {
+        let row: Vec<Value> = arrays.iter().map(|a| a.entry_at(i).map_or(Value::Null, |(_, v)| v.deref())).collect();
+        let v = match &callback {
+            Some(callback) => vm.call(callback.clone(), row)?,
+            None => list(row)?,
+        };
+        out.push(v).map_err(VmError::error)?;
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_filter(array $array, ?callable $callback = null, int $mode = 0): array`
+fn array_filter(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    let callback = match args.get(1) {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(vm.resolve_callable(v)?),
+    };
+    let mode = arg_int(&args, 2, 0);
+    let mut out = PhpArray::new();
+    for (k, v) in arr.iter() {
+        let keep = match &callback {
+            None => v.to_bool(),
+            Some(callback) => {
+                let call_args = match mode {
+                    ARRAY_FILTER_USE_KEY => vec![key_value(k)],
+                    ARRAY_FILTER_USE_BOTH => vec![v.deref(), key_value(k)],
+                    _ => vec![v.deref()],
+                };
+                vm.call(callback.clone(), call_args)?.to_bool()
+            }
+        };
+        if keep {
+            out.insert(k.clone(), v.clone());
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_reduce(array $array, callable $callback, mixed $initial = null): mixed`
+fn array_reduce(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let callback = vm.resolve_callable(&args[1])?;
+    let mut carry = args.get(2).cloned().unwrap_or(Value::Null);
+    for (_, v) in arg_array(&args, 0).iter() {
+        carry = vm.call(callback.clone(), vec![carry, v.deref()])?;
+    }
+    Ok(carry)
+}
+
+/// `array_walk(array|object &$array, callable $callback, mixed $arg = UNKNOWN): true`
+///
+/// The callback gets each value by reference, then its key.
+fn array_walk(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let callback = vm.resolve_callable(&args[1])?;
+    let mut arr = match args[0].deref() {
+        Value::Object(o) => o.borrow().props.clone(),
+        _ => ref_array(callee, &args, 0)?,
+    };
+    for i in 0..arr.len() {
+        let (k, slot) = arr.entry_at_mut(i).unwrap();
+        let k = key_value(k);
+        let v = Value::Ref(ref_cell(slot));
+        let mut call_args = vec![v, k];
+        call_args.extend(args.get(2).cloned());
+        vm.call(callback.clone(), call_args)?;
+    }
+    // values that only the callback referred to stop being references
+    for i in 0..arr.len() {
+        let (_, slot) = arr.entry_at_mut(i).unwrap();
+        if let Value::Ref(cell) = slot
+            && Rc::strong_count(cell) == 1
+        {
+            let v = cell.borrow().clone();
+            *slot = v;
+        }
+    }
+    match args[0].deref() {
+        Value::Object(o) => o.borrow_mut().props = arr,
+        _ => set_ref(&args, 0, Value::from(arr)),
+    }
+    Ok(Value::Bool(true))
+}
+
+fn search(args: &[Value]) -> Option<ArrayKey> {
+    let needle = &args[0];
+    let strict = arg_bool(args, 2, false);
+    let haystack = arg_array(args, 1);
+    let found = haystack.iter().find(|(_, v)| if strict { strict_equals(v, needle) } else { loose_equals(v, needle) });
+    found.map(|(k, _)| k.clone())
+}
+
+/// `in_array(mixed $needle, array $haystack, bool $strict = false): bool`
+fn in_array(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(search(&args).is_some()))
+}
+
+/// `array_search(mixed $needle, array $haystack, bool $strict = false): int|string|false`
+fn array_search(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(search(&args).map_or(Value::Bool(false), |k| key_value(&k)))
+}
+
+/// `array_key_exists($key, array $array): bool`
+fn array_key_exists(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let Some(key) = args[0].to_array_key() else {
+        return Err(VmError::type_error(format!(
+            "{}(): Argument #1 ($key) must be a valid array offset type",
+            callee.func.name
+        )));
+    };
+    Ok(Value::Bool(arg_array(&args, 1).contains_key(&key)))
+}
+
+/// `array_key_first(array $array): int|string|null`
+fn array_key_first(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(arg_array(&args, 0).iter().next().map_or(Value::Null, |(k, _)| key_value(k)))
+}
+
+/// `array_key_last(array $array): int|string|null`
+fn array_key_last(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(arg_array(&args, 0).iter().next_back().map_or(Value::Null, |(k, _)| key_value(k)))
+}
+
+/// `array_is_list(array $array): bool`
+fn array_is_list(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    Ok(Value::Bool(arr.iter().enumerate().all(|(i, (k, _))| *k == ArrayKey::Int(i as i64))))
+}
+
+/// `array_unique(array $array, int $flags = SORT_STRING): array`
+///
+/// Keeps the first of each run of equal values.
+fn array_unique(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    let flags = arg_int(&args, 1, SORT_STRING);
+    let mut out = PhpArray::new();
+    if flags == SORT_STRING {
+        let mut seen = HashSet::new();
+        for (k, v) in arr.iter() {
+            if seen.insert(vm.stringify(v)?) {
+                out.insert(k.clone(), v.clone());
+            }
+        }
+        return Ok(Value::from(out));
+    }
+    let entries: Vec<&Value> = arr.iter().map(|(_, v)| v).collect();
+    let sorted = merge_sort(vm, (0..entries.len()).collect(), &mut |vm, &a, &b| flag_compare(vm, entries[a], entries[b], flags))?;
+    let mut keep = vec![false; entries.len()];
+    let mut kept: Option<usize> = None;
+    for i in sorted {
+        if let Some(k) = kept
+            && flag_compare(vm, entries[k], entries[i], flags)? == Ordering::Equal
+        {
+            continue;
+        }
+        keep[i] = true;
+        kept = Some(i);
+    }
+    for ((k, v), keep) in arr.iter().zip(keep) {
+        if keep {
+            out.insert(k.clone(), v.clone());
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_flip(array $array): array`
+fn array_flip(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut out = PhpArray::new();
+    for (k, v) in arg_array(&args, 0).iter() {
+        match v.deref() {
+            Value::Int(n) => out.insert(ArrayKey::Int(n), key_value(k)),
+            Value::Str(s) => out.insert(ArrayKey::from_bytes(&s), key_value(k)),
+            _ => vm.warn("array_flip(): Can only flip string and integer values, entry skipped"),
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_count_values(array $array): array`
+fn array_count_values(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut out = PhpArray::new();
+    for (_, v) in arg_array(&args, 0).iter() {
+        let key = match v.deref() {
+            Value::Int(n) => ArrayKey::Int(n),
+            Value::Str(s) => ArrayKey::from_bytes(&s),
+            _ => {
+                vm.warn("array_count_values(): Can only count string and integer values, entry skipped");
+                continue;
+            }
+        };
+        let slot = out.entry(key);
+        *slot = Value::Int(slot.to_int() + 1);
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_fill(int $start_index, int $count, mixed $value): array`
+fn array_fill(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (start, count) = (arg_int(&args, 0, 0), arg_int(&args, 1, 0));
+    if count < 0 {
+        return Err(VmError::value_error("array_fill(): Argument #2 ($count) must be greater than or equal to 0"));
+    }
+    if count >= 1 << 31 {
+        return Err(VmError::value_error("array_fill(): Argument #2 ($count) is too large"));
+    }
+    vm.reserve_array(count as usize)?;
+    let mut out = PhpArray::new();
+    for i in 0..count {
+        out.insert(ArrayKey::Int(start.wrapping_add(i)), args[2].clone());
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_fill_keys(array $keys, mixed $value): array`
+fn array_fill_keys(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut out = PhpArray::new();
+    for (_, k) in arg_array(&args, 0).iter() {
+        let k = to_key(vm, k)?;
+        out.insert(k, args[1].clone());
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_pad(array $array, int $length, mixed $value): array`
+///
+/// A negative length pads at the front.
+fn array_pad(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    let length = arg_int(&args, 1, 0);
+    let missing = (length.unsigned_abs() as usize).saturating_sub(arr.len());
+    if missing == 0 {
+        return Ok(Value::Array(arr));
+    }
+    if length.unsigned_abs() >= 1 << 31 {
+        return Err(VmError::value_error("array_pad(): Argument #2 ($length) must not exceed the maximum allowed array size"));
+    }
+    vm.reserve_array(arr.len() + missing)?;
+    let mut out = PhpArray::new();
+    let pad = |out: &mut PhpArray| (0..missing).try_for_each(|_| out.push(args[2].clone())).map_err(VmError::error);
+    if length < 0 {
+        pad(&mut out)?;
+    }
+    for (k, v) in arr.iter() {
+        append(&mut out, k, v.clone())?;
+    }
+    if length > 0 {
+        pad(&mut out)?;
+    }
+    Ok(Value::from(out))
+}
+
+/// The most entries `range()` and the like may create.
+const MAX_SIZE: u64 = 1 << 31;
+
+/// `range(string|int|float $start, string|int|float $end, int|float $step = 1): array`
+///
+/// Single-byte non-numeric strings give a range of characters; a float
+/// bound or a fractional step give floats.
+fn range(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (step, float_step) = match args.get(2) {
+        Some(Value::Float(f)) => (*f, f.fract() != 0.0),
+        Some(v) => (v.to_int() as f64, false),
+        None => (1.0, false),
+    };
+    if !step.is_finite() {
+        return Err(VmError::value_error("range(): Argument #3 ($step) must be a finite number, INF provided"));
+    }
+    if step == 0.0 {
+        return Err(VmError::value_error("range(): Argument #3 ($step) cannot be 0"));
+    }
+    let mut bounds = [args[0].clone(), args[1].clone()];
+    for (i, (v, name)) in bounds.iter_mut().zip(["start", "end"]).enumerate() {
+        if let Value::Str(s) = v
+            && s.is_empty()
+        {
+            vm.warn(&format!("range(): Argument #{} (${}) must not be empty, casted to 0", i + 1, name));
+            *v = Value::Int(0);
+        }
+    }
+    let chars = match &bounds {
+        [Value::Str(a), Value::Str(b)] if !float_step && is_char(a) && is_char(b) => Some((a.clone(), b.clone())),
+        _ => None,
+    };
+    if let Some((a, b)) = chars {
+        for (i, (s, name)) in [(&a, "start"), (&b, "end")].into_iter().enumerate() {
+            if s.len() > 1 {
+                vm.warn(&format!(
+                    "range(): Argument #{} (${}) must be a single byte, subsequent bytes are ignored",
+                    i + 1,
+                    name
+                ));
+            }
+        }
+        let (low, high, step) = (a[0] as i64, b[0] as i64, step.abs() as i64);
+        if step > (high - low).abs() && low != high {
+            return Err(exceeds_range());
+        }
+        let chars = (0..=(high - low).abs() / step).map(|i| {
+            let c = if low <= high { low + i * step } else { low - i * step };
+            Value::from(vec![c as u8])
+        });
+        return list(chars);
+    }
+    let (start, end) = (bounds[0].to_numeric(), bounds[1].to_numeric());
+    if let (Numeric::Int(low), Numeric::Int(high), false) = (start, end, float_step) {
+        if step < 0.0 && low < high {
+            return Err(VmError::value_error("range(): Argument #3 ($step) must be greater than 0 for increasing ranges"));
+        }
+        let step = step.abs() as u64;
+        let span = low.abs_diff(high);
+        if step > span && span > 0 {
+            return Err(exceeds_range());
+        }
+        let size = span / step + 1;
+        if size >= MAX_SIZE {
+            return Err(VmError::value_error(format!(
+                "The supplied range exceeds the maximum array size: start={} end={} step={}",
+                low, high, step
+            )));
+        }
+        vm.reserve_array(size as usize)?;
+        let ints = (0..size as i64).map(|i| {
+            let offset = i.wrapping_mul(step as i64);
+            Value::Int(if low <= high { low.wrapping_add(offset) } else { low.wrapping_sub(offset) })
+        });
+        return list(ints);
+    }
+    let float = |n: Numeric| match n {
+        Numeric::Int(n) => n as f64,
+        Numeric::Float(f) => f,
+    };
+    let (low, high) = (float(start), float(end));
+    for (i, (f, name)) in [(low, "start"), (high, "end")].into_iter().enumerate() {
+        if !f.is_finite() {
+            return Err(VmError::value_error(format!(
+                "range(): Argument #{} (${}) must be a finite number, INF provided",
+                i + 1,
+                name
+            )));
+        }
+    }
+    if step < 0.0 && low < high {
+        return Err(VmError::value_error("range(): Argument #3 ($step) must be greater than 0 for increasing ranges"));
+    }
+    let step = step.abs();
+    let span = (high - low).abs();
+    if step > span && span > 0.0 {
+        return Err(exceeds_range());
+    }
+    let size = math::round(span / step, 0) + 1.0;
+    if size >= MAX_SIZE as f64 {
+        return Err(VmError::value_error(format!(
+            "The supplied range exceeds the maximum array size: start={:.1} end={:.1} step={:.1}",
+            low, high, step
+        )));
+    }
+    vm.reserve_array(size as usize)?;
+    let floats = (0..size as u64).map(|i| {
+        let offset = i as f64 * step;
+        if low <= high { low + offset } else { low - offset }
+    });
+    list(floats.take_while(|f| if low <= high { *f <= high } else { *f >= high }).map(Value::Float))
+}
+
+/// Whether a `range()` bound is a character rather than a number.
+fn is_char(s: &[u8]) -> bool {
+    !s.is_empty() && crate::value::numeric_string(s).is_none()
+}
+
+fn exceeds_range() -> VmError {
+    VmError::value_error("range(): Argument #3 ($step) must not exceed the specified range")
+}
+
+/// `array_column(array $array, int|string|null $column_key, int|string|null $index_key = null): array`
+///
+/// Rows may be arrays or objects.
+fn array_column(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let field = |row: &Value, key: &Value| -> Option<Value> {
+        let key = key.to_array_key()?;
+        match row.deref() {
+            Value::Array(a) => a.get(&key).map(Value::deref),
+            Value::Object(o) => o.borrow().props.get(&key).map(Value::deref),
+            _ => None,
+        }
+    };
+    let (column, index) = (&args[1], args.get(2).unwrap_or(&Value::Null));
+    let mut out = PhpArray::new();
+    for (_, row) in arg_array(&args, 0).iter() {
+        let value = match column {
+            Value::Null => row.deref(),
+            key => match field(row, key) {
+                Some(v) => v,
+                None => continue,
+            },
+        };
+        let index = match index {
+            Value::Null => None,
+            key => field(row, key).and_then(|k| k.to_array_key()),
+        };
+        match index {
+            Some(k) => out.insert(k, value),
+            None => out.push(value).map_err(VmError::error)?,
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_chunk(array $array, int $length, bool $preserve_keys = false): array`
+fn array_chunk(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    let length = arg_int(&args, 1, 0);
+    if length < 1 {
+        return Err(VmError::value_error("array_chunk(): Argument #2 ($length) must be greater than 0"));
+    }
+    let preserve = arg_bool(&args, 2, false);
+    let mut chunks = Vec::new();
+    let mut chunk = PhpArray::new();
+    for (k, v) in arr.iter() {
+        match preserve {
+            true => chunk.insert(k.clone(), v.clone()),
+            false => chunk.push(v.clone()).map_err(VmError::error)?,
+        }
+        if chunk.len() as i64 == length {
+            chunks.push(Value::from(std::mem::take(&mut chunk)));
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push(Value::from(chunk));
+    }
+    list(chunks)
+}
+
+/// `array_reverse(array $array, bool $preserve_keys = false): array`
+fn array_reverse(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = arg_array(&args, 0);
+    if arg_bool(&args, 1, false) {
+        let mut out = PhpArray::new();
+        for (k, v) in arr.iter().rev() {
+            out.insert(k.clone(), v.clone());
+        }
+        return Ok(Value::from(out));
+    }
+    Ok(Value::from(renumbered(arr.iter().rev())?))
+}
+
+/// `array_push(array &$array, mixed ...$values): int`
+fn array_push(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut values = args[1..].iter().map(Value::deref);
+    let len = with_ref_array(callee, &args, 0, |arr| {
+        values.try_for_each(|v| arr.push(v)).map(|_| arr.len())
+    })?;
+    Ok(Value::Int(len.map_err(VmError::error)? as i64))
+}
+
+/// `array_pop(array &$array): mixed`
+fn array_pop(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let popped = with_ref_array(callee, &args, 0, PhpArray::pop)?;
+    Ok(popped.map_or(Value::Null, |(_, v)| v.deref()))
+}
+
+/// `array_shift(array &$array): mixed`
+///
+/// Int keys of the rest are renumbered.
+fn array_shift(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = ref_array(callee, &args, 0)?;
+    let Some((_, first)) = arr.entry_at(0) else { return Ok(Value::Null) };
+    let first = first.deref();
+    set_ref(&args, 0, Value::from(renumbered(arr.iter().skip(1))?));
+    Ok(first)
+}
+
+/// `array_unshift(array &$array, mixed ...$values): int`
+fn array_unshift(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let arr = ref_array(callee, &args, 0)?;
+    let mut out = PhpArray::new();
+    for v in &args[1..] {
+        out.push(v.deref()).map_err(VmError::error)?;
+    }
+    for (k, v) in arr.iter() {
+        append(&mut out, k, v.clone())?;
+    }
+    let len = out.len();
+    set_ref(&args, 0, Value::from(out));
+    Ok(Value::Int(len as i64))
+}
+
+fn fold(vm: &mut Vm, args: &[Value], op: BinOp, initial: i64, name: &str) -> VmResult<Value> {
+    let mut acc = Value::Int(initial);
+    for (_, v) in arg_array(args, 0).iter() {
+        let v = match v.deref() {
+            Value::Array(_) => {
+                vm.warn(&format!("{}(): {} is not supported on type array", name, op_name(op)));
+                continue;
+            }
+            Value::Object(o) => {
+                let class = o.borrow().class.name.clone();
+                vm.warn(&format!("{}(): {} is not supported on type {}", name, op_name(op), class));
+                continue;
+            }
+            v => v.to_numeric().to_value(),
+        };
+        acc = vm.binary_op(op, acc, v)?;
+    }
+    Ok(acc)
+}
+
+fn op_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Mul => "Multiplication",
+        _ => "Addition",
+    }
+}
+
+/// `array_sum(array $array): int|float`
+fn array_sum(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    fold(vm, &args, BinOp::Add, 0, "array_sum")
+}
+
+/// `array_product(array $array): int|float`
+fn array_product(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    fold(vm, &args, BinOp::Mul, 1, "array_product")
+}
+
+/// What the `array_diff()` and `array_intersect()` families compare.
+#[derive(Clone, Copy, PartialEq)]
+enum Match {
+    Value,
+    Key,
+    Both,
+}
+
+/// Keeps the entries of the first array that some other array has
+/// (`intersect`) or none has (diff). Values compare as strings.
+fn set_operation(vm: &mut Vm, args: &[Value], by: Match, intersect: bool) -> VmResult<Value> {
+    let first = arg_array(args, 0);
+    let others: Vec<_> = (1..args.len()).map(|i| arg_array(args, i)).collect();
+    let mut other_values: Vec<HashSet<Vec<u8>>> = Vec::new();
+    if by == Match::Value {
+        for arr in &others {
+            let mut set = HashSet::new();
+            for (_, v) in arr.iter() {
+                set.insert(vm.stringify(v)?);
+            }
+            other_values.push(set);
+        }
+    }
+    let mut out = PhpArray::new();
+    for (k, v) in first.iter() {
+        let s = match by {
+            Match::Key => Vec::new(),
+            _ => vm.stringify(v)?,
+        };
+        let mut found = Vec::with_capacity(others.len());
+        for (i, arr) in others.iter().enumerate() {
+            found.push(match by {
+                Match::Value => other_values[i].contains(&s),
+                Match::Key => arr.contains_key(k),
+                Match::Both => match arr.get(k) {
+                    Some(w) => vm.stringify(w)? == s,
+                    None => false,
+                },
+            });
+        }
+        let keep = match intersect {
+            true => found.iter().all(|f| *f),
+            false => !found.iter().any(|f| *f),
+        };
+        if keep {
+            out.insert(k.clone(), v.clone());
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `array_diff(array $array, array ...$arrays): array`
+fn array_diff(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    set_operation(vm, &args, Match::Value, false)
+}
+
+/// `array_diff_key(array $array, array ...$arrays): array`
+fn array_diff_key(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    set_operation(vm, &args, Match::Key, false)
+}
+
+/// `array_diff_assoc(array $array, array ...$arrays): array`
+fn array_diff_assoc(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    set_operation(vm, &args, Match::Both, false)
+}
+
+/// `array_intersect(array $array, array ...$arrays): array`
+fn array_intersect(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    set_operation(vm, &args, Match::Value, true)
+}
+
+/// `array_intersect_key(array $array, array ...$arrays): array`
+fn array_intersect_key(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    set_operation(vm, &args, Match::Key, true)
+}
+
+/// `array_intersect_assoc(array $array, array ...$arrays): array`
+fn array_intersect_assoc(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    set_operation(vm, &args, Match::Both, true)
+}
+
+fn compact_into(vm: &mut Vm, out: &mut PhpArray, name: &Value, arg: usize) -> VmResult<()> {
+    match name.deref() {
+        Value::Str(s) => {
+            let name = String::from_utf8_lossy(&s);
+            match vm.frame().vars.get(name.as_ref()) {
+                Some(v) => out.insert(ArrayKey::from_bytes(&s), v.deref()),
+                None => vm.warn(&format!("compact(): Undefined variable ${}", name)),
+            }
+        }
+        Value::Array(a) => {
+            for (_, v) in a.iter() {
+                compact_into(vm, out, v, arg)?;
+            }
+        }
+        other => vm.warn(&format!(
+            "compact(): Argument #{} must be string or array of strings, {} given",
+            arg,
+            other.type_name()
+        )),
+    }
+    Ok(())
+}
+
+/// `compact(array|string $var_name, array|string ...$var_names): array`
+///
+/// Collects variables of the calling scope by name.
+fn compact(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut out = PhpArray::new();
+    for (i, name) in args.iter().enumerate() {
+        compact_into(vm, &mut out, name, i + 1)?;
+    }
+    Ok(Value::from(out))
+}
+
+fn is_identifier(name: &[u8]) -> bool {
+    let word = |c: &u8| c.is_ascii_alphanumeric() || *c == b'_' || *c >= 0x80;
+    name.first().is_some_and(|c| !c.is_ascii_digit() && word(c)) && name.iter().all(word)
+}
+
+/// `extract(array &$array, int $flags = EXTR_OVERWRITE, string $prefix = ""): int`
+///
+/// Sets variables of the calling scope from the entries; returns how
+/// many were set.
+fn extract(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut arr = ref_array(callee, &args, 0)?;
+    let flags = arg_int(&args, 1, EXTR_OVERWRITE);
+    let prefix = crate::extension::arg_str(&args, 2).to_vec();
+    let refs = flags & EXTR_REFS != 0;
+    let mode = flags & !EXTR_REFS;
+    if !(EXTR_OVERWRITE..=EXTR_IF_EXISTS).contains(&mode) {
+        return Err(VmError::value_error("extract(): Argument #2 ($flags) must be a valid extract type"));
+    }
+    if (EXTR_PREFIX_SAME..=EXTR_PREFIX_IF_EXISTS).contains(&mode) && args.len() < 3 {
+        return Err(VmError::value_error("extract(): Argument #3 ($prefix) is required when using this extract type"));
+    }
+    if !prefix.is_empty() && !is_identifier(&prefix) {
+        return Err(VmError::value_error("extract(): Argument #3 ($prefix) must be a valid identifier"));
+    }
+    let prefixed = |key: &[u8]| [&prefix[..], b"_", key].concat();
+    let mut count = 0;
+    for i in 0..arr.len() {
+        let (key, _) = arr.entry_at(i).unwrap();
+        let key = match key {
+            ArrayKey::Str(s) => s.to_vec(),
+            ArrayKey::Int(n) => n.to_string().into_bytes(),
+        };
+        let int_key = matches!(arr.entry_at(i), Some((ArrayKey::Int(_), _)));
+        let exists = !int_key && vm.frame().vars.contains_key(String::from_utf8_lossy(&key).as_ref());
+        let name = match mode {
+            _ if int_key => match mode {
+                EXTR_PREFIX_ALL | EXTR_PREFIX_INVALID => prefixed(&key),
+                _ => continue,
+            },
+            EXTR_OVERWRITE => key,
+            EXTR_SKIP if exists => continue,
+            EXTR_SKIP => key,
+            EXTR_PREFIX_SAME if exists => prefixed(&key),
+            EXTR_PREFIX_SAME => key,
+            EXTR_PREFIX_ALL => prefixed(&key),
+            EXTR_PREFIX_INVALID if !is_identifier(&key) => prefixed(&key),
+            EXTR_PREFIX_INVALID => key,
+            EXTR_PREFIX_IF_EXISTS if exists => prefixed(&key),
+            EXTR_IF_EXISTS if exists => key,
+            _ => continue,
+        };
+        if !is_identifier(&name) {
+            continue;
+        }
+        if name == b"this" {
+            return Err(VmError::error("Cannot re-assign $this"));
+        }
+        let name: Rc<str> = String::from_utf8_lossy(&name).into();
+        let (_, slot) = arr.entry_at_mut(i).unwrap();
+        let vars = &mut vm.frame_mut().vars;
+        if refs {
+            vars.insert(name, Value::Ref(ref_cell(slot)));
+        } else {
+            match vars.get(&name) {
+                Some(Value::Ref(cell)) => *cell.borrow_mut() = slot.deref(),
+                _ => {
+                    vars.insert(name, slot.deref());
+                }
+            }
+        }
+        count += 1;
+    }
+    if refs {
+        set_ref(&args, 0, Value::from(arr));
+    }
+    Ok(Value::Int(count))
+}
@@ -17,31 +17,174 @@ pub enum Expr {
         expr: Box<Expr>,
     },
 
+    /// `target` is one of `Variable`, `ArrayAccess`, `PropertyFetch` or
+    /// `StaticPropertyFetch`.
     Assign {
-        variable: String,
+        target: Box<Expr>,
         value: Box<Expr>,
     },
+    /// `$a = &$b`; both sides are places like an `Assign` target.
+    AssignRef {
+        target: Box<Expr>,
+        source: Box<Expr>,
+    },
 
     FunctionCall {
         name: String,
-        args: Vec<Expr>,
+        args: Vec<Arg>,
+    },
+    /// Call of a callable value: `$f(1)`, `$obj->handler(1)` is a method
+    /// call, but `($obj->handler)(1)` is this.
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Arg>,
     },
+    /// `strlen(...)`, `$obj->method(...)`, `Foo::bar(...)`: the wrapped
+    /// call (without arguments) is turned into a `Closure`.
+    FirstClassCallable(Box<Expr>),
+    Closure(Box<ClosureExpr>),
 
+    /// `index` is `None` for the append form `$a[]`, which is only valid
+    /// as an assignment target.
     ArrayAccess {
         array: Box<Expr>,
-        index: Box<Expr>,
+        index: Option<Box<Expr>>,
     },
 
     ArrayLiteral(Vec<ArrayEntry>),
+    /// A destructuring target, `[$a, 'k' => $b]` or `list($a, , $c)`;
+    /// `None` is a skipped element.
+    List(Vec<Option<ArrayEntry>>),
+
+    New {
+        class: ClassRef,
+        args: Vec<Arg>,
+    },
+    PropertyFetch {
+        object: Box<Expr>,
+        property: String,
+    },
+    StaticPropertyFetch {
+        class: ClassRef,
+        property: String,
+    },
+    MethodCall {
+        object: Box<Expr>,
+        method: String,
+        args: Vec<Arg>,
+    },
+    StaticCall {
+        class: ClassRef,
+        method: String,
+        args: Vec<Arg>,
+    },
+    ClassConstFetch {
+        class: ClassRef,
+        name: String,
+    },
+
+    /// `$a instanceof Foo`, `$a instanceof $b`; a string or object on the
+    /// right names the class.
+    InstanceOf {
+        expr: Box<Expr>,
+        class: ClassRef,
+    },
+
+    /// A constant name, resolved by the compiler.
+    ConstFetch(String),
+
+    Include(IncludeKind, Box<Expr>),
+
+    /// `isset($a, $b['k'])`: whether all the places are set and not null,
+    /// without warnings for missing ones.
+    Isset(Vec<Expr>),
+    /// `empty($a['k'])`, quiet like `isset` when given a place.
+    Empty(Box<Expr>),
+
+    /// `yield`, `yield $v` or `yield $k => $v`; makes the enclosing
+    /// function a generator.
+    Yield {
+        key: Option<Box<Expr>>,
+        value: Option<Box<Expr>>,
+    },
+    /// `yield from $iterable`, which evaluates to the return value of a
+    /// delegated generator.
+    YieldFrom(Box<Expr>),
+
+    /// `throw` is an expression, so it may appear in `??` and arrow
+    /// functions.
+    Throw(Box<Expr>),
+    Clone(Box<Expr>),
+}
+
+/// The class operand of `new`, `::` and friends. `self`, `parent` and
+/// `static` stay as names and are resolved against the calling scope.
+#[derive(Debug, Clone)]
+pub enum ClassRef {
+    Name(String),
+    Expr(Box<Expr>),
+}
+
+/// `function (...) use (...) { }` or `fn (...) => expr`. Arrow functions
+/// are stored with a body of a single `return`.
+#[derive(Debug, Clone)]
+pub struct ClosureExpr {
+    pub params: Vec<ParamDecl>,
+    pub return_type: Option<TypeHint>,
+    pub uses: Vec<ClosureUse>,
+    pub body: Block,
+    pub is_static: bool,
+    pub is_arrow: bool,
+}
+
+/// A call argument: positional, named (`f(limit: 10)`) or unpacked
+/// (`f(...$args)`).
+#[derive(Debug, Clone)]
+pub struct Arg {
+    pub value: Expr,
+    pub name: Option<String>,
+    pub unpack: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamDecl {
+    pub name: String,
+    pub ty: Option<TypeHint>,
+    pub default: Option<Expr>,
+    pub by_ref: bool,
+    pub variadic: bool,
+}
+
+/// A type declaration. Names are kept as written; built-in type names
+/// are matched case-insensitively.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeHint {
+    Named(String),
+    /// `?T`
+    Nullable(Box<TypeHint>),
+    /// `A|B`, whose members may be intersections (`(A&B)|null`).
+    Union(Vec<TypeHint>),
+    /// `A&B`
+    Intersection(Vec<TypeHint>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ClosureUse {
+    pub name: String,
+    pub by_ref: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct ArrayEntry {
     pub key: Option<Expr>,
     pub value: Expr,
+    /// `&$v`: the element is a reference to the place.
+    pub by_ref: bool,
+    /// `...$other`
+    pub unpack: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinOp {
     Add, Sub, Mul, Div, Mod,
     Concat,
@@ -50,16 +193,28 @@ pub enum BinOp {
     And, Or,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOp {
     Negate,
     Not,
+    IntCast,
+    FloatCast,
+    StringCast,
+    BoolCast,
+    ArrayCast,
+    ObjectCast,
 }
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
     Echo(Vec<Expr>),
-    ExprStmt(Expr),
+    Expr(Expr),
     If {
         condition: Expr,
         then_block: Block,
@@ -76,12 +231,233 @@ pub enum Stmt {
         update: Option<Expr>,
         body: Block,
     },
+    /// `foreach ($subject as $key => $value)`; `value` may be a `List`.
+    Foreach {
+        subject: Expr,
+        key: Option<Expr>,
+        value: Expr,
+        by_ref: bool,
+        body: Block,
+    },
     FunctionDef {
         name: String,
-        params: Vec<String>,
+        params: Vec<ParamDecl>,
+        return_type: Option<TypeHint>,
         body: Block,
     },
+    /// `declare(strict_types=1);`
+    Declare(Vec<(String, Expr)>),
+    ClassDecl(ClassDecl),
     Return(Option<Expr>),
+    Try {
+        body: Block,
+        catches: Vec<CatchClause>,
+        finally: Option<Block>,
+    },
+    /// `namespace A\B;` (no body) or `namespace A\B { ... }`; `name` is
+    /// `None` for the braced global namespace.
+    Namespace {
+        name: Option<String>,
+        body: Option<Block>,
+    },
+    /// `use` imports, with group uses expanded.
+    Use(Vec<UseItem>),
+    /// `const A = 1, B = A * 2;` at file level.
+    Const(Vec<(String, Expr)>),
+    /// `global $a, $b;`
+    Global(Vec<String>),
+    /// `static $a = 1, $b;`: variables keeping their value across calls;
+    /// the initializer runs on first use.
+    StaticVar(Vec<(String, Option<Expr>)>),
+    /// `unset($a, $b['k'], $o->p);`
+    Unset(Vec<Expr>),
+    /// `break n;`, the number of enclosing loops to leave.
+    Break(usize),
+    Continue(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncludeKind {
+    Include,
+    IncludeOnce,
+    Require,
+    RequireOnce,
+}
+
+impl IncludeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            IncludeKind::Include => "include",
+            IncludeKind::IncludeOnce => "include_once",
+            IncludeKind::Require => "require",
+            IncludeKind::RequireOnce => "require_once",
+        }
+    }
+
+    pub fn is_once(self) -> bool {
+        matches!(self, IncludeKind::IncludeOnce | IncludeKind::RequireOnce)
+    }
+
+    pub fn is_require(self) -> bool {
+        matches!(self, IncludeKind::Require | IncludeKind::RequireOnce)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UseKind {
+    Class,
+    Function,
+    Const,
+}
+
+#[derive(Debug, Clone)]
+pub struct UseItem {
+    pub kind: UseKind,
+    /// The imported name, without a leading `\`.
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    /// Class names of a `catch (A | B $e)`.
+    pub types: Vec<String>,
+    /// The variable is optional (`catch (Exception)`).
+    pub var: Option<String>,
+    pub body: Block,
+    pub line: usize,
+}
+
+pub type Block = Vec<Stmt>;
+
+// -- Classes ------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassKind {
+    Class,
+    Interface,
+    Trait,
+    Enum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    Public,
+    Protected,
+    Private,
 }
 
-pub type Block = Vec<Stmt>;
\ No newline at end of file
+#[derive(Debug, Clone)]
+pub struct ClassDecl {
+    pub name: String,
+    pub kind: ClassKind,
+    pub is_abstract: bool,
+    pub is_final: bool,
+    pub parent: Option<String>,
+    /// `implements` for classes and enums, `extends` for interfaces.
+    pub interfaces: Vec<String>,
+    /// Backing type of an enum (`enum Suit: string`).
+    pub backing_type: Option<String>,
+    pub members: Vec<ClassMember>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum ClassMember {
+    Constant {
+        name: String,
+        value: Expr,
+        visibility: Visibility,
+        is_final: bool,
+    },
+    Property {
+        name: String,
+        default: Option<Expr>,
+        visibility: Visibility,
+        is_static: bool,
+    },
+    Method(MethodDecl),
+    /// `case Hearts;` or `case Hearts = 'H';` in an enum.
+    Case {
+        name: String,
+        value: Option<Expr>,
+    },
+    TraitUse {
+        traits: Vec<String>,
+        adaptations: Vec<TraitAdaptation>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodDecl {
+    pub name: String,
+    pub params: Vec<ParamDecl>,
+    pub return_type: Option<TypeHint>,
+    /// `None` for abstract and interface methods.
+    pub body: Option<Block>,
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub is_abstract: bool,
+    pub is_final: bool,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum TraitAdaptation {
+    /// `A::foo insteadof B, C;`
+    Insteadof {
+        trait_name: String,
+        method: String,
+        excluded: Vec<String>,
+    },
+    /// `foo as protected bar;`, `A::foo as baz;`, `foo as private;`
+    Alias {
+        trait_name: Option<String>,
+        method: String,
+        alias: Option<String>,
+        visibility: Option<Visibility>,
+    },
+}
+
+impl std::fmt::Display for TypeHint {
+    /// Renders the type the way PHP's error messages do: `?int` for a
+    /// single type with null, intersections in unions parenthesized.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TypeHint::Named(n) => f.write_str(n),
+            TypeHint::Nullable(t) => write!(f, "?{}", t),
+            TypeHint::Union(ts) => {
+                let is_null = |t: &TypeHint| matches!(t, TypeHint::Named(n) if n.eq_ignore_ascii_case("null"));
+                if let [a, b] = ts.as_slice() {
+                    let other = match (is_null(a), is_null(b)) {
+                        (true, false) => Some(b),
+                        (false, true) => Some(a),
+                        _ => None,
+                    };
+                    if let Some(t @ TypeHint::Named(_)) = other {
+                        return write!(f, "?{}", t);
+                    }
+                }
+                for (i, t) in ts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("|")?;
+                    }
+                    match t {
+                        TypeHint::Intersection(_) => write!(f, "({})", t)?,
+                        t => write!(f, "{}", t)?,
+                    }
+                }
+                Ok(())
+            }
+            TypeHint::Intersection(ts) => {
+                for (i, t) in ts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("&")?;
+                    }
+                    write!(f, "{}", t)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
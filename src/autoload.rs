@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::array::PhpArray;
+use crate::ast::ClassKind;
+use crate::lexer::Lexer;
+use crate::token::{Spanned, Token};
+use crate::value::Value;
+use crate::vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
+
+pub const FUNCTIONS: &[(&str, &[&str], NativeFn)] = &[
+    ("spl_autoload_register", &["callback", "throw", "prepend"], spl_autoload_register),
+    ("spl_autoload_unregister", &["callback"], spl_autoload_unregister),
+    ("spl_autoload_functions", &[], spl_autoload_functions),
+    ("spl_autoload_call", &["class"], spl_autoload_call),
+    ("class_exists", &["class", "autoload"], class_exists),
+    ("interface_exists", &["interface", "autoload"], interface_exists),
+    ("trait_exists", &["trait", "autoload"], trait_exists),
+    ("enum_exists", &["enum", "autoload"], enum_exists),
+];
+
+/// The class maps Composer generates under `vendor/composer`, read
+/// straight from disk so classes load without running Composer's own
+/// bootstrap.
+#[derive(Debug, Default)]
+pub struct Composer {
+    classmap: HashMap<String, String>,
+    /// PSR-4 namespace prefixes and their directories, longest prefix
+    /// first so the most specific one wins.
+    psr4: Vec<(String, Vec<String>)>,
+}
+
+/// A value of the array literals in Composer's generated files.
+enum Data {
+    Str(String),
+    List(Vec<(Option<String>, Data)>),
+}
+
+impl Composer {
+    /// Reads `autoload_psr4.php` and `autoload_classmap.php` from a
+    /// vendor directory; either may be missing.
+    pub fn load(vendor: &Path) -> Result<Composer, String> {
+        let mut composer = Composer::default();
+        for (key, value) in read_map(&vendor.join("composer/autoload_classmap.php"), vendor)? {
+            if let (Some(class), Data::Str(file)) = (key, value) {
+                composer.classmap.insert(class, file);
+            }
+        }
+        for (key, value) in read_map(&vendor.join("composer/autoload_psr4.php"), vendor)? {
+            let dirs = match value {
+                Data::Str(dir) => vec![dir],
+                Data::List(items) => items
+                    .into_iter()
+                    .filter_map(|(_, d)| match d {
+                        Data::Str(dir) => Some(dir),
+                        Data::List(_) => None,
+                    })
+                    .collect(),
+            };
+            composer.psr4.push((key.unwrap_or_default(), dirs));
+        }
+        composer.psr4.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Ok(composer)
+    }
+
+    /// The file that declares a class, as Composer's `ClassLoader` finds
+    /// it.
+    fn find_file(&self, class: &str) -> Option<String> {
+        if let Some(file) = self.classmap.get(class) {
+            return Some(file.clone());
+        }
+        let logical = format!("{}.php", class.replace('\\', "/"));
+        let logical = logical.as_str();
+        self.psr4
+            .iter()
+            .filter(|(prefix, _)| class.starts_with(prefix.as_str()))
+            .flat_map(|(prefix, dirs)| dirs.iter().map(move |d| format!("{}/{}", d, &logical[prefix.len()..])))
+            .find(|file| Path::new(file).is_file())
+    }
+}
+
+/// Finds the `vendor` directory of the Composer project a script belongs
+/// to, looking in its directory and those above.
+pub fn find_vendor_dir(start: &Path) -> Option<PathBuf> {
+    start.ancestors().map(|dir| dir.join("vendor")).find(|vendor| {
+        let generated = vendor.join("composer");
+        generated.join("autoload_psr4.php").is_file() || generated.join("autoload_classmap.php").is_file()
+    })
+}
+
+/// Evaluates the `return array(...)` of a generated file. Its strings
+/// may be concatenated with `$vendorDir`, `$baseDir` and `__DIR__`.
+fn read_map(path: &Path, vendor: &Path) -> Result<Vec<(Option<String>, Data)>, String> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let tokens = Lexer::new(&source).tokenize().map_err(|e| format!("{}: {}", path.display(), e))?;
+    let Some(start) = tokens.iter().position(|t| t.token == Token::Return) else {
+        return Err(format!("{}: no return statement", path.display()));
+    };
+    let base = vendor.parent().unwrap_or(vendor);
+    let mut reader = MapReader {
+        tokens: &tokens[start + 1..],
+        pos: 0,
+        vendor_dir: vendor.display().to_string(),
+        base_dir: base.display().to_string(),
+        dir: vendor.join("composer").display().to_string(),
+    };
+    match reader.value() {
+        Some(Data::List(items)) => Ok(items),
+        _ => Err(format!("{}: unsupported syntax near token {}", path.display(), start + 1 + reader.pos)),
+    }
+}
+
+struct MapReader<'a> {
+    tokens: &'a [Spanned],
+    pos: usize,
+    vendor_dir: String,
+    base_dir: String,
+    dir: String,
+}
+
+impl MapReader<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn value(&mut self) -> Option<Data> {
+        let first = self.term()?;
+        if self.peek() != Some(&Token::Dot) {
+            return Some(first);
+        }
+        let Data::Str(mut s) = first else { return None };
+        while self.eat(&Token::Dot) {
+            match self.term()? {
+                Data::Str(part) => s.push_str(&part),
+                Data::List(_) => return None,
+            }
+        }
+        Some(Data::Str(s))
+    }
+
+    fn term(&mut self) -> Option<Data> {
+        let token = self.peek()?.clone();
+        self.pos += 1;
+        match token {
+            Token::StringLiteral(s) => Some(Data::Str(s)),
+            Token::Variable(v) if v == "vendorDir" => Some(Data::Str(self.vendor_dir.clone())),
+            Token::Variable(v) if v == "baseDir" => Some(Data::Str(self.base_dir.clone())),
+            Token::Identifier(i) if i == "__DIR__" => Some(Data::Str(self.dir.clone())),
+            Token::Identifier(i) if i.eq_ignore_ascii_case("array") && self.eat(&Token::OpenParen) => {
+                self.items(&Token::CloseParen)
+            }
+            Token::OpenBracket => self.items(&Token::CloseBracket),
+            _ => None,
+        }
+    }
+
+    fn items(&mut self, close: &Token) -> Option<Data> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            let first = self.value()?;
+            if self.eat(&Token::Arrow) {
+                let Data::Str(key) = first else { return None };
+                items.push((Some(key), self.value()?));
+            } else {
+                items.push((None, first));
+            }
+            if !self.eat(&Token::Comma) && self.peek() != Some(close) {
+                return None;
+            }
+        }
+        Some(Data::List(items))
+    }
+}
+
+impl Vm {
+    /// Uses a Composer vendor directory's class maps to autoload classes.
+    pub fn load_composer(&mut self, vendor: &Path) -> Result<(), String> {
+        self.composer = Some(Composer::load(vendor)?);
+        Ok(())
+    }
+
+    /// Tries to declare a missing class: first from the Composer class
+    /// maps, then with each registered autoloader until one declares it.
+    pub(crate) fn autoload(&mut self, name: &str) -> VmResult<()> {
+        let key = name.to_ascii_lowercase();
+        if !self.autoloading.insert(key.clone()) {
+            return Ok(());
+        }
+        let result = self.run_autoloaders(name);
+        self.autoloading.remove(&key);
+        result
+    }
+
+    fn run_autoloaders(&mut self, name: &str) -> VmResult<()> {
+        if let Some(file) = self.composer.as_ref().and_then(|c| c.find_file(name)) {
+            self.require_isolated(&file)?;
+            if self.find_class(name).is_some() {
+                return Ok(());
+            }
+        }
+        for loader in self.autoloaders.clone() {
+            let callee = self.resolve_callable(&loader)?;
+            self.call(callee, vec![Value::from(name)])?;
+            if self.find_class(name).is_some() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether two callables name the same function or method on the same
+    /// object.
+    fn same_callable(&mut self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Object(x), Value::Object(y)) => Rc::ptr_eq(x, y),
+            (Value::Object(_), _) | (_, Value::Object(_)) => false,
+            _ => match (self.resolve_callable(a), self.resolve_callable(b)) {
+                (Ok(x), Ok(y)) => {
+                    Rc::ptr_eq(&x.func, &y.func)
+                        && match (&x.this, &y.this) {
+                            (Some(p), Some(q)) => Rc::ptr_eq(p, q),
+                            (None, None) => true,
+                            _ => false,
+                        }
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+fn callback_error(func: &str, callback: &Value, e: VmError) -> VmError {
+    match e {
+        VmError::Error { message, .. } => {
+            let reason = match callback {
+                Value::Str(s) if !s.windows(2).any(|w| w == b"::") => {
+                    format!("function \"{}\" not found or invalid function name", String::from_utf8_lossy(s))
+                }
+                _ => message,
+            };
+            VmError::type_error(format!(
+                "{}(): Argument #1 ($callback) must be a valid callback or null, {}",
+                func, reason
+            ))
+        }
+        e => e,
+    }
+}
+
+/// `spl_autoload_register(?callable $callback = null, bool $throw = true, bool $prepend = false): bool`
+///
+/// Without a callback PHP registers its file-name based `spl_autoload()`,
+/// which is not provided; nothing is registered then.
+fn spl_autoload_register(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("spl_autoload_register", &args, 0, 3)?;
+    let mut args = args.into_iter();
+    let callback = args.next().unwrap_or(Value::Null).deref();
+    let _throw = args.next();
+    let prepend = match args.next() {
+        Some(v) => vm.coerce_arg(v, "bool", "spl_autoload_register", 3, "prepend")?.to_bool(),
+        None => false,
+    };
+    if let Value::Null = callback {
+        return Ok(Value::Bool(true));
+    }
+    if let Err(e) = vm.resolve_callable(&callback) {
+        return Err(callback_error("spl_autoload_register", &callback, e));
+    }
+    for loader in vm.autoloaders.clone() {
+        if vm.same_callable(&loader, &callback) {
+            return Ok(Value::Bool(true));
+        }
+    }
+    if prepend {
+        vm.autoloaders.insert(0, callback);
+    } else {
+        vm.autoloaders.push(callback);
+    }
+    Ok(Value::Bool(true))
+}
+
+/// `spl_autoload_unregister(callable $callback): bool`
+fn spl_autoload_unregister(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("spl_autoload_unregister", &args, 1, 1)?;
+    let callback = args.into_iter().next().unwrap().deref();
+    for (i, loader) in vm.autoloaders.clone().iter().enumerate() {
+        if vm.same_callable(loader, &callback) {
+            vm.autoloaders.remove(i);
+            return Ok(Value::Bool(true));
+        }
+    }
+    Ok(Value::Bool(false))
+}
+
+/// `spl_autoload_functions(): array`
+fn spl_autoload_functions(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("spl_autoload_functions", &args, 0, 0)?;
+    let mut list = PhpArray::new();
+    for loader in &vm.autoloaders {
+        let _ = list.push(loader.clone());
+    }
+    Ok(Value::from(list))
+}
+
+/// `spl_autoload_call(string $class): void`
+fn spl_autoload_call(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("spl_autoload_call", &args, 1, 1)?;
+    let class = vm.coerce_arg(args.into_iter().next().unwrap(), "string", "spl_autoload_call", 1, "class")?;
+    let class = String::from_utf8_lossy(&class.scalar_to_bytes().unwrap()).into_owned();
+    if vm.find_class(&class).is_none() {
+        vm.autoload(&class)?;
+    }
+    Ok(Value::Null)
+}
+
+/// Shared by `class_exists()` and its siblings: whether a class of one of
+/// `kinds` is declared, autoloading it unless told not to.
+fn exists(vm: &mut Vm, func: &str, param: &str, args: Vec<Value>, kinds: &[ClassKind]) -> VmResult<Value> {
+    expect_args(func, &args, 1, 2)?;
+    let mut args = args.into_iter();
+    let name = vm.coerce_arg(args.next().unwrap(), "string", func, 1, param)?;
+    let name = String::from_utf8_lossy(&name.scalar_to_bytes().unwrap()).into_owned();
+    let autoload = match args.next() {
+        Some(v) => vm.coerce_arg(v, "bool", func, 2, "autoload")?.to_bool(),
+        None => true,
+    };
+    let name = name.strip_prefix('\\').unwrap_or(&name);
+    if autoload && vm.find_class(name).is_none() {
+        vm.autoload(name)?;
+    }
+    Ok(Value::Bool(vm.find_class(name).is_some_and(|c| kinds.contains(&c.kind))))
+}
+
+/// `class_exists(string $class, bool $autoload = true): bool`
+fn class_exists(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    exists(vm, "class_exists", "class", args, &[ClassKind::Class, ClassKind::Enum])
+}
+
+/// `interface_exists(string $interface, bool $autoload = true): bool`
+fn interface_exists(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    exists(vm, "interface_exists", "interface", args, &[ClassKind::Interface])
+}
+
+/// `trait_exists(string $trait, bool $autoload = true): bool`
+fn trait_exists(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    exists(vm, "trait_exists", "trait", args, &[ClassKind::Trait])
+}
+
+/// `enum_exists(string $enum, bool $autoload = true): bool`
+fn enum_exists(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    exists(vm, "enum_exists", "enum", args, &[ClassKind::Enum])
+}
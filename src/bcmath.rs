@@ -0,0 +1,459 @@
+use std::cmp::Ordering;
+
+use crate::extension::{arg_str, opt_int, NativeFunction};
+use crate::value::Value;
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+/// Arbitrary precision decimal arithmetic on numeric strings, as PHP's
+/// bcmath extension has it: results are truncated, never rounded, to
+/// the scale asked for or the `bcmath.scale` default.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction { name: "bcadd", params: &["string num1", "string num2", "?int scale = null"], func: bcadd },
+    NativeFunction { name: "bcsub", params: &["string num1", "string num2", "?int scale = null"], func: bcsub },
+    NativeFunction { name: "bcmul", params: &["string num1", "string num2", "?int scale = null"], func: bcmul },
+    NativeFunction { name: "bcdiv", params: &["string num1", "string num2", "?int scale = null"], func: bcdiv },
+    NativeFunction { name: "bcmod", params: &["string num1", "string num2", "?int scale = null"], func: bcmod },
+    NativeFunction { name: "bcpow", params: &["string num", "string exponent", "?int scale = null"], func: bcpow },
+    NativeFunction { name: "bcsqrt", params: &["string num", "?int scale = null"], func: bcsqrt },
+    NativeFunction { name: "bccomp", params: &["string num1", "string num2", "?int scale = null"], func: bccomp },
+    NativeFunction {
+        name: "bcpowmod",
+        params: &["string num", "string exponent", "string modulus", "?int scale = null"],
+        func: bcpowmod,
+    },
+    NativeFunction { name: "bcscale", params: &["?int scale = null"], func: bcscale },
+];
+
+/// Decimal digits, most significant first, without leading zeros.
+type Digits = Vec<u8>;
+
+fn trim(mut d: Digits) -> Digits {
+    let zeros = d.iter().take_while(|&&b| b == 0).count();
+    d.drain(..zeros);
+    d
+}
+
+fn compare(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn add(a: &[u8], b: &[u8]) -> Digits {
+    let mut out = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let (mut i, mut j, mut carry) = (a.len(), b.len(), 0);
+    while i > 0 || j > 0 || carry > 0 {
+        let mut sum = carry;
+        if i > 0 {
+            i -= 1;
+            sum += a[i];
+        }
+        if j > 0 {
+            j -= 1;
+            sum += b[j];
+        }
+        out.push(sum % 10);
+        carry = sum / 10;
+    }
+    out.reverse();
+    trim(out)
+}
+
+/// `a - b` for `a >= b`.
+fn sub(a: &[u8], b: &[u8]) -> Digits {
+    let mut out = a.to_vec();
+    let mut borrow = 0;
+    for k in 0..out.len() {
+        let i = out.len() - 1 - k;
+        let take = borrow + if k < b.len() { b[b.len() - 1 - k] } else { 0 };
+        if out[i] >= take {
+            out[i] -= take;
+            borrow = 0;
+        } else {
+            out[i] = out[i] + 10 - take;
+            borrow = 1;
+        }
+    }
+    trim(out)
+}
+
+/// Schoolbook multiplication. Like the division below it counts its work
+/// against the VM's limits, so a huge `bcpow()` stops like a script would.
+fn mul(vm: &mut Vm, a: &[u8], b: &[u8]) -> VmResult<Digits> {
+    if a.is_empty() || b.is_empty() {
+        return Ok(Vec::new());
+    }
+    let len = a.len() + b.len();
+    vm.reserve(len * std::mem::size_of::<u64>())?;
+    let mut acc = vec![0u64; len];
+    for (i, &x) in a.iter().enumerate() {
+        for (j, &y) in b.iter().enumerate() {
+            acc[i + j + 1] += u64::from(x) * u64::from(y);
+        }
+        vm.tick(b.len() as u64)?;
+    }
+    for k in (1..acc.len()).rev() {
+        acc[k - 1] += acc[k] / 10;
+        acc[k] %= 10;
+    }
+    Ok(trim(acc.into_iter().map(|d| d as u8).collect()))
+}
+
+/// Long division of `a` by a non-zero `b`: the quotient and remainder.
+fn divmod(vm: &mut Vm, a: &[u8], b: &[u8]) -> VmResult<(Digits, Digits)> {
+    let mut quotient = Vec::with_capacity(a.len());
+    let mut rest: Digits = Vec::new();
+    for &d in a {
+        rest.push(d);
+        rest = trim(rest);
+        let mut q = 0;
+        while compare(&rest, b) != Ordering::Less {
+            rest = sub(&rest, b);
+            q += 1;
+        }
+        quotient.push(q);
+        vm.tick((u64::from(q) + 1) * b.len() as u64)?;
+    }
+    Ok((trim(quotient), rest))
+}
+
+fn shift(d: &[u8], places: usize) -> Digits {
+    if d.is_empty() {
+        return Vec::new();
+    }
+    let mut out = d.to_vec();
+    out.resize(d.len() + places, 0);
+    out
+}
+
+/// A decimal number: its digits with the decimal point `scale` digits
+/// from the right.
+#[derive(Clone)]
+struct Num {
+    negative: bool,
+    digits: Digits,
+    scale: usize,
+}
+
+impl Num {
+    fn int(digits: Digits) -> Num {
+        Num { negative: false, digits, scale: 0 }
+    }
+
+    /// Parses `[+-]digits[.digits]`, where either part may be empty, as
+    /// `bc_str2num()` accepts it.
+    fn parse(s: &[u8]) -> Option<Num> {
+        let (negative, s) = match s.first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (int, frac) = match s.iter().position(|&b| b == b'.') {
+            Some(dot) => (&s[..dot], &s[dot + 1..]),
+            None => (s, &s[s.len()..]),
+        };
+        if !int.iter().chain(frac).all(u8::is_ascii_digit) {
+            return None;
+        }
+        let digits = trim(int.iter().chain(frac).map(|b| b - b'0').collect());
+        Some(Num { negative: negative && !digits.is_empty(), digits, scale: frac.len() })
+    }
+
+    /// The digits at `scale`, truncated or padded with zeros.
+    fn at_scale(&self, scale: usize) -> Digits {
+        if scale >= self.scale {
+            shift(&self.digits, scale - self.scale)
+        } else {
+            let keep = self.digits.len().saturating_sub(self.scale - scale);
+            trim(self.digits[..keep].to_vec())
+        }
+    }
+
+    fn truncated(&self, scale: usize) -> Num {
+        let digits = self.at_scale(scale);
+        Num { negative: self.negative && !digits.is_empty(), digits, scale }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn has_fraction(&self) -> bool {
+        self.digits.iter().rev().take(self.scale).any(|&d| d != 0)
+    }
+
+    /// `bc_num2str_ex()`: exactly `scale` decimals.
+    fn format(&self, scale: usize) -> Vec<u8> {
+        let n = self.truncated(scale);
+        let mut digits: Vec<u8> = n.digits.iter().map(|d| d + b'0').collect();
+        if digits.len() <= scale {
+            let mut padded = vec![b'0'; scale + 1 - digits.len()];
+            padded.append(&mut digits);
+            digits = padded;
+        }
+        let mut out = Vec::with_capacity(digits.len() + 2);
+        if n.negative {
+            out.push(b'-');
+        }
+        let point = digits.len() - scale;
+        out.extend_from_slice(&digits[..point]);
+        if scale > 0 {
+            out.push(b'.');
+            out.extend_from_slice(&digits[point..]);
+        }
+        out
+    }
+
+    fn add(&self, other: &Num) -> Num {
+        let scale = self.scale.max(other.scale);
+        let (a, b) = (self.at_scale(scale), other.at_scale(scale));
+        let (negative, digits) = if self.negative == other.negative {
+            (self.negative, add(&a, &b))
+        } else if compare(&a, &b) != Ordering::Less {
+            (self.negative, sub(&a, &b))
+        } else {
+            (other.negative, sub(&b, &a))
+        };
+        Num { negative: negative && !digits.is_empty(), digits, scale }
+    }
+
+    fn negated(&self) -> Num {
+        Num { negative: !self.negative && !self.is_zero(), ..self.clone() }
+    }
+
+    fn mul(&self, vm: &mut Vm, other: &Num) -> VmResult<Num> {
+        let digits = mul(vm, &self.digits, &other.digits)?;
+        let negative = self.negative != other.negative && !digits.is_empty();
+        Ok(Num { negative, digits, scale: self.scale + other.scale })
+    }
+
+    /// The quotient truncated to `scale` decimals; `other` is not zero.
+    fn div(&self, vm: &mut Vm, other: &Num, scale: usize) -> VmResult<Num> {
+        vm.reserve(self.digits.len().saturating_add(scale).saturating_add(other.scale))?;
+        let numerator = shift(&self.digits, scale + other.scale);
+        let denominator = shift(&other.digits, self.scale);
+        let (digits, _) = divmod(vm, &numerator, &denominator)?;
+        Ok(Num { negative: self.negative != other.negative && !digits.is_empty(), digits, scale })
+    }
+
+    /// What is left after taking off `other` a whole number of times,
+    /// with the sign of `self`.
+    fn rem(&self, vm: &mut Vm, other: &Num) -> VmResult<Num> {
+        Ok(self.add(&self.div(vm, other, 0)?.mul(vm, other)?.negated()))
+    }
+
+    fn cmp(&self, other: &Num) -> Ordering {
+        let diff = self.add(&other.negated());
+        match (diff.is_zero(), diff.negative) {
+            (true, _) => Ordering::Equal,
+            (false, true) => Ordering::Less,
+            (false, false) => Ordering::Greater,
+        }
+    }
+
+    /// About how many digits `self` to the power `exponent` has, from the
+    /// logarithm of the digits.
+    fn pow_digits(&self, exponent: u64) -> usize {
+        let lead = self.digits.iter().take(15).fold(0.0, |n, &d| n * 10.0 + f64::from(d));
+        let magnitude = lead.log10() + self.digits.len().saturating_sub(15) as f64;
+        (magnitude * exponent as f64).ceil() as usize + 1
+    }
+
+    fn pow(&self, vm: &mut Vm, mut exponent: u64) -> VmResult<Num> {
+        vm.reserve(self.pow_digits(exponent))?;
+        let mut result = Num::int(vec![1]);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(vm, &base)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(vm, &base)?;
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The result as a string of `scale` decimals, once the memory for it is
+/// reserved.
+fn formatted(vm: &mut Vm, n: &Num, scale: usize) -> VmResult<Value> {
+    vm.reserve(n.digits.len().max(scale.saturating_add(1)).saturating_add(2))?;
+    Ok(Value::from(n.format(scale)))
+}
+
+fn default_scale(vm: &Vm) -> usize {
+    vm.ini.get("bcmath.scale").and_then(|s| s.trim().parse().ok()).unwrap_or(0)
+}
+
+/// The scale argument at `i`, or the default.
+fn scale_arg(vm: &Vm, func: &str, args: &[Value], i: usize) -> VmResult<usize> {
+    match opt_int(args, i) {
+        None => Ok(default_scale(vm)),
+        Some(scale @ 0..=0x7fff_ffff) => Ok(scale as usize),
+        Some(_) => Err(VmError::value_error(format!(
+            "{}(): Argument #{} ($scale) must be between 0 and 2147483647",
+            func,
+            i + 1
+        ))),
+    }
+}
+
+fn num_arg(func: &str, args: &[Value], i: usize, param: &str) -> VmResult<Num> {
+    Num::parse(arg_str(args, i))
+        .ok_or_else(|| VmError::value_error(format!("{}(): Argument #{} (${}) is not well-formed", func, i + 1, param)))
+}
+
+/// The two operands and scale that most of the functions take.
+fn operands(vm: &Vm, func: &str, args: &[Value]) -> VmResult<(Num, Num, usize)> {
+    Ok((num_arg(func, args, 0, "num1")?, num_arg(func, args, 1, "num2")?, scale_arg(vm, func, args, 2)?))
+}
+
+fn division_by_zero(message: &str) -> VmError {
+    VmError::Error { class: "DivisionByZeroError", message: message.to_string() }
+}
+
+/// `bcadd(string $num1, string $num2, ?int $scale = null): string`
+fn bcadd(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b, scale) = operands(vm, "bcadd", &args)?;
+    formatted(vm, &a.add(&b), scale)
+}
+
+/// `bcsub(string $num1, string $num2, ?int $scale = null): string`
+fn bcsub(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b, scale) = operands(vm, "bcsub", &args)?;
+    formatted(vm, &a.add(&b.negated()), scale)
+}
+
+/// `bcmul(string $num1, string $num2, ?int $scale = null): string`
+fn bcmul(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b, scale) = operands(vm, "bcmul", &args)?;
+    let product = a.mul(vm, &b)?;
+    formatted(vm, &product, scale)
+}
+
+/// `bcdiv(string $num1, string $num2, ?int $scale = null): string`
+fn bcdiv(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b, scale) = operands(vm, "bcdiv", &args)?;
+    if b.is_zero() {
+        return Err(division_by_zero("Division by zero"));
+    }
+    let quotient = a.div(vm, &b, scale)?;
+    formatted(vm, &quotient, scale)
+}
+
+/// `bcmod(string $num1, string $num2, ?int $scale = null): string`
+fn bcmod(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b, scale) = operands(vm, "bcmod", &args)?;
+    if b.is_zero() {
+        return Err(division_by_zero("Modulo by zero"));
+    }
+    let rest = a.rem(vm, &b)?;
+    formatted(vm, &rest, scale)
+}
+
+/// `bcpow(string $num, string $exponent, ?int $scale = null): string`
+fn bcpow(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let base = num_arg("bcpow", &args, 0, "num")?;
+    let exponent = num_arg("bcpow", &args, 1, "exponent")?;
+    let scale = scale_arg(vm, "bcpow", &args, 2)?;
+    if exponent.has_fraction() {
+        return Err(VmError::value_error("bcpow(): Argument #2 ($exponent) cannot have a fractional part"));
+    }
+    let power = exponent.truncated(0).digits.iter().try_fold(0u64, |n, &d| n.checked_mul(10)?.checked_add(u64::from(d)));
+    let Some(power) = power.filter(|&p| p <= i64::MAX as u64) else {
+        return Err(VmError::value_error("bcpow(): Argument #2 ($exponent) is too large"));
+    };
+    let power = base.pow(vm, power)?;
+    if !exponent.negative {
+        return formatted(vm, &power, scale);
+    }
+    if power.is_zero() {
+        return Err(division_by_zero("Negative power of zero"));
+    }
+    let inverse = Num::int(vec![1]).div(vm, &power, scale)?;
+    formatted(vm, &inverse, scale)
+}
+
+/// The integer square root of `n`, by Newton's method.
+fn isqrt(vm: &mut Vm, n: &[u8]) -> VmResult<Digits> {
+    if n.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut x = shift(&[1], n.len().div_ceil(2));
+    loop {
+        let (q, _) = divmod(vm, n, &x)?;
+        let (y, _) = divmod(vm, &add(&x, &q), &[2])?;
+        if compare(&y, &x) != Ordering::Less {
+            return Ok(x);
+        }
+        x = y;
+    }
+}
+
+/// `bcsqrt(string $num, ?int $scale = null): string`
+fn bcsqrt(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let num = num_arg("bcsqrt", &args, 0, "num")?;
+    let scale = scale_arg(vm, "bcsqrt", &args, 1)?;
+    if num.negative {
+        return Err(VmError::value_error("bcsqrt(): Argument #1 ($num) must be greater than or equal to 0"));
+    }
+    let rscale = scale.max(num.scale);
+    vm.reserve(rscale.saturating_mul(2).saturating_add(num.digits.len()))?;
+    let digits = isqrt(vm, &shift(&num.digits, 2 * rscale - num.scale))?;
+    let root = Num { negative: false, digits, scale: rscale };
+    formatted(vm, &root, scale)
+}
+
+/// `bccomp(string $num1, string $num2, ?int $scale = null): int`
+fn bccomp(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b, scale) = operands(vm, "bccomp", &args)?;
+    Ok(Value::Int(match a.truncated(scale).cmp(&b.truncated(scale)) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    }))
+}
+
+/// `bcpowmod(string $num, string $exponent, string $modulus, ?int $scale = null): string`
+fn bcpowmod(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut operands = Vec::new();
+    for (i, param) in ["num", "exponent", "modulus"].into_iter().enumerate() {
+        let n = num_arg("bcpowmod", &args, i, param)?;
+        if n.has_fraction() {
+            return Err(VmError::value_error(format!("bcpowmod(): Argument #{} (${}) cannot have a fractional part", i + 1, param)));
+        }
+        operands.push(n.truncated(0));
+    }
+    let scale = scale_arg(vm, "bcpowmod", &args, 3)?;
+    let (base, exponent, modulus) = (&operands[0], &operands[1], &operands[2]);
+    if exponent.negative {
+        return Err(VmError::value_error("bcpowmod(): Argument #2 ($exponent) must be greater than or equal to 0"));
+    }
+    if modulus.is_zero() {
+        return Err(division_by_zero("Modulo by zero"));
+    }
+    let mut result = Num::int(vec![1]).rem(vm, modulus)?;
+    let mut base = base.rem(vm, modulus)?;
+    let mut exponent = exponent.digits.clone();
+    while !exponent.is_empty() {
+        let (half, bit) = divmod(vm, &exponent, &[2])?;
+        if !bit.is_empty() {
+            result = result.mul(vm, &base)?.rem(vm, modulus)?;
+        }
+        exponent = half;
+        if !exponent.is_empty() {
+            base = base.mul(vm, &base)?.rem(vm, modulus)?;
+        }
+    }
+    formatted(vm, &result, scale)
+}
+
+/// `bcscale(?int $scale = null): int`
+fn bcscale(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let old = default_scale(vm);
+    if opt_int(&args, 0).is_some() {
+        let scale = scale_arg(vm, "bcscale", &args, 0)?;
+        vm.ini.insert("bcmath.scale".to_string(), scale.to_string());
+    }
+    Ok(Value::Int(old as i64))
+}
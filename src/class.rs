@@ -0,0 +1,777 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::rc::{Rc, Weak};
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::closure::Closure;
+use crate::ast::{ClassKind, TraitAdaptation, Visibility};
+use crate::compiler::{ClassDef, Function};
+use crate::fiber::Fiber;
+use crate::generator::Generator;
+use crate::value::{ObjRef, Value};
+
+/// A constant expression that is evaluated on first use, so that it may
+/// refer to constants declared later (`const A = self::B;`).
+#[derive(Debug)]
+pub enum Lazy {
+    Pending(Rc<Function>),
+    Evaluating,
+    Done(Value),
+}
+
+#[derive(Debug)]
+pub struct ClassConst {
+    pub name: Rc<str>,
+    /// Name of the declaring class.
+    pub class: Rc<str>,
+    pub visibility: Visibility,
+    pub is_final: bool,
+    pub is_case: bool,
+    pub value: RefCell<Lazy>,
+}
+
+#[derive(Debug)]
+pub struct PropInfo {
+    pub name: Rc<str>,
+    pub class: Rc<str>,
+    pub visibility: Visibility,
+    pub default: Rc<RefCell<Lazy>>,
+}
+
+#[derive(Debug)]
+pub struct StaticProp {
+    pub name: Rc<str>,
+    pub class: Rc<str>,
+    pub visibility: Visibility,
+    /// Shared with subclasses unless they redeclare the property.
+    pub cell: Rc<RefCell<Value>>,
+    pub init: Option<Rc<Function>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub name: Rc<str>,
+    /// Declaring class. Trait methods belong to the class using the trait.
+    pub class: Rc<str>,
+    /// Trait the method was imported from, for conflict messages.
+    pub origin: Option<Rc<str>>,
+    pub func: Rc<Function>,
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub is_abstract: bool,
+    pub is_final: bool,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: Rc<str>,
+    pub kind: ClassKind,
+    pub is_abstract: bool,
+    pub is_final: bool,
+    pub parent: Option<Rc<Class>>,
+    /// Every interface implemented, directly or through inheritance.
+    pub interfaces: Vec<Rc<Class>>,
+    pub constants: Vec<Rc<ClassConst>>,
+    pub props: Vec<PropInfo>,
+    pub static_props: Vec<Rc<StaticProp>>,
+    /// Methods in PHP's function-table order, indexed by lowercase name.
+    pub methods: Vec<Rc<Method>>,
+    method_index: HashMap<String, usize>,
+    pub decl: Rc<ClassDef>,
+}
+
+impl Class {
+    pub fn find_method(&self, name: &str) -> Option<&Rc<Method>> {
+        let i = self.method_index.get(&name.to_ascii_lowercase())?;
+        Some(&self.methods[*i])
+    }
+
+    pub fn find_constant(&self, name: &str) -> Option<&Rc<ClassConst>> {
+        self.constants.iter().find(|c| &*c.name == name)
+    }
+
+    pub fn find_prop(&self, name: &str) -> Option<&PropInfo> {
+        self.props.iter().find(|p| &*p.name == name)
+    }
+
+    pub(crate) fn find_prop_key(&self, key: &ArrayKey) -> Option<&PropInfo> {
+        match key {
+            ArrayKey::Str(name) => self.props.iter().find(|p| p.name.as_bytes() == name.as_slice()),
+            ArrayKey::Int(_) => None,
+        }
+    }
+
+    pub fn find_static_prop(&self, name: &str) -> Option<&Rc<StaticProp>> {
+        self.static_props.iter().find(|p| &*p.name == name)
+    }
+
+    /// `instanceof` against a class or interface name (case-insensitive).
+    pub fn is_subclass_of(&self, name: &str) -> bool {
+        if self.name.eq_ignore_ascii_case(name) {
+            return true;
+        }
+        if self.interfaces.iter().any(|i| i.name.eq_ignore_ascii_case(name)) {
+            return true;
+        }
+        match &self.parent {
+            Some(p) => p.is_subclass_of(name),
+            None => false,
+        }
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        kind_name(self.kind)
+    }
+
+    fn add_method(&mut self, method: Rc<Method>) {
+        let key = method.name.to_ascii_lowercase();
+        match self.method_index.get(&key) {
+            Some(&i) => self.methods[i] = method,
+            None => {
+                self.method_index.insert(key, self.methods.len());
+                self.methods.push(method);
+            }
+        }
+    }
+}
+
+/// The objects of one VM: the handles to hand out, the released objects
+/// waiting for `__destruct()` and, for shutdown, the live objects that
+/// have a destructor.
+#[derive(Default)]
+pub(crate) struct ObjectStore {
+    last_id: Cell<u32>,
+    free_ids: RefCell<Vec<u32>>,
+    destruct_queue: RefCell<VecDeque<Object>>,
+    destruct_pending: Cell<bool>,
+    destructible: RefCell<BTreeMap<u32, Weak<RefCell<Object>>>>,
+    /// Set once the VM is gone; objects released after that are freed
+    /// without their destructor.
+    closed: Cell<bool>,
+}
+
+impl std::fmt::Debug for ObjectStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectStore").field("last_id", &self.last_id).finish_non_exhaustive()
+    }
+}
+
+impl ObjectStore {
+    /// Whether objects are waiting for their destructor.
+    pub(crate) fn destruct_pending(&self) -> bool {
+        self.destruct_pending.get()
+    }
+
+    /// Takes the next object waiting for its destructor.
+    pub(crate) fn next_destructed(&self) -> Option<Object> {
+        let obj = self.destruct_queue.borrow_mut().pop_front();
+        if obj.is_none() {
+            self.destruct_pending.set(false);
+        }
+        obj
+    }
+
+    /// The live objects whose destructor has not run, oldest handle first.
+    pub(crate) fn destructible(&self) -> Vec<ObjRef> {
+        self.destructible.borrow().values().filter_map(Weak::upgrade).collect()
+    }
+
+    /// Drops the objects waiting for their destructor and stops queuing
+    /// more, when the VM goes away.
+    pub(crate) fn close(&self) {
+        self.closed.set(true);
+        let queue = std::mem::take(&mut *self.destruct_queue.borrow_mut());
+        drop(queue);
+    }
+}
+
+/// Engine state of instances of built-in classes.
+#[derive(Debug, Default)]
+pub enum Internal {
+    #[default]
+    None,
+    Closure(Rc<Closure>),
+    Generator(Rc<RefCell<Generator>>),
+    Fiber(Rc<RefCell<Fiber>>),
+}
+
+impl Internal {
+    /// Whether code has to run when it is released: the `finally` blocks
+    /// a generator is suspended in, or the unwinding of a suspended fiber.
+    fn runs_on_release(&self) -> bool {
+        match self {
+            Internal::Generator(g) => g.try_borrow().is_ok_and(|g| g.has_pending_finally()),
+            Internal::Fiber(f) => f.try_borrow().is_ok_and(|f| f.is_suspended()),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Object {
+    /// Object handle as shown by `var_dump` (`#3`); reused once freed.
+    pub id: u32,
+    pub class: Rc<Class>,
+    pub props: PhpArray,
+    pub internal: Internal,
+    /// `__destruct()` was queued or called for it already.
+    pub(crate) destructed: bool,
+    store: Rc<ObjectStore>,
+}
+
+impl Object {
+    /// A new object with a handle from `store`.
+    pub(crate) fn new(store: &Rc<ObjectStore>, class: Rc<Class>, props: PhpArray) -> ObjRef {
+        let id = store.free_ids.borrow_mut().pop().unwrap_or_else(|| {
+            let id = store.last_id.get() + 1;
+            store.last_id.set(id);
+            id
+        });
+        let has_destructor = class.find_method("__destruct").is_some();
+        let obj = Rc::new(RefCell::new(Object {
+            id,
+            class,
+            props,
+            internal: Internal::None,
+            destructed: false,
+            store: store.clone(),
+        }));
+        if has_destructor {
+            store.destructible.borrow_mut().insert(id, Rc::downgrade(&obj));
+        }
+        obj
+    }
+}
+
+impl Object {
+    /// The properties as `(array)` gives them: private names are mangled
+    /// as `"\0Class\0name"`, protected ones as `"\0*\0name"`.
+    pub fn to_array(&self) -> PhpArray {
+        let mut out = PhpArray::new();
+        for (k, v) in self.props.iter() {
+            let key = match (k, self.class.find_prop_key(k)) {
+                (ArrayKey::Str(name), Some(info)) if info.visibility != Visibility::Public => {
+                    let prefix = match info.visibility {
+                        Visibility::Private => info.class.as_bytes(),
+                        _ => b"*".as_slice(),
+                    };
+                    let mut mangled = vec![0];
+                    mangled.extend_from_slice(prefix);
+                    mangled.push(0);
+                    mangled.extend_from_slice(name);
+                    ArrayKey::Str(Rc::new(mangled))
+                }
+                _ => k.clone(),
+            };
+            out.insert(key, v.clone());
+        }
+        out
+    }
+}
+
+/// An object with a destructor, or a generator or fiber with code to run
+/// when released, is moved to the destruct queue of its VM when its last
+/// reference goes away, keeping its handle; the VM then calls
+/// `__destruct()` on it or runs that code.
+impl Drop for Object {
+    fn drop(&mut self) {
+        let store = self.store.clone();
+        if !self.destructed {
+            store.destructible.borrow_mut().remove(&self.id);
+            if !store.closed.get() && (self.class.find_method("__destruct").is_some() || self.internal.runs_on_release()) {
+                let obj = Object {
+                    id: self.id,
+                    class: self.class.clone(),
+                    props: std::mem::take(&mut self.props),
+                    internal: std::mem::take(&mut self.internal),
+                    destructed: true,
+                    store: store.clone(),
+                };
+                store.destruct_queue.borrow_mut().push_back(obj);
+                store.destruct_pending.set(true);
+                return;
+            }
+        }
+        // What the object holds goes first, so its own handle is the next
+        // one reused, as in PHP.
+        drop(std::mem::take(&mut self.props));
+        drop(std::mem::take(&mut self.internal));
+        store.free_ids.borrow_mut().push(self.id);
+    }
+}
+
+pub fn kind_name(kind: ClassKind) -> &'static str {
+    match kind {
+        ClassKind::Class => "class",
+        ClassKind::Interface => "interface",
+        ClassKind::Trait => "trait",
+        ClassKind::Enum => "enum",
+    }
+}
+
+pub fn visibility_name(v: Visibility) -> &'static str {
+    match v {
+        Visibility::Public => "public",
+        Visibility::Protected => "protected",
+        Visibility::Private => "private",
+    }
+}
+
+// -- Linking ------------------------------------------------
+
+/// The classes a declaration depends on, already resolved by the VM.
+pub struct LinkInputs {
+    pub parent: Option<Rc<Class>>,
+    pub interfaces: Vec<Rc<Class>>,
+    pub traits: Vec<Rc<Class>>,
+}
+
+/// Builds a runtime class from its compiled declaration, applying
+/// inheritance, interfaces and trait composition, and enforcing the rules
+/// PHP checks when a class is linked. Errors carry PHP's messages.
+pub fn link(def: &Rc<ClassDef>, inputs: LinkInputs) -> Result<Class, String> {
+    let name = def.name.clone();
+
+    if let Some(parent) = &inputs.parent {
+        if parent.is_final {
+            return Err(format!("Class {} cannot extend final class {}", name, parent.name));
+        }
+        if parent.kind != ClassKind::Class {
+            return Err(format!("Class {} cannot extend {} {}", name, parent.kind_name(), parent.name));
+        }
+    }
+    for iface in &inputs.interfaces {
+        if iface.kind != ClassKind::Interface {
+            return Err(format!("{} cannot implement {} - it is not an interface", name, iface.name));
+        }
+    }
+    for t in &inputs.traits {
+        if t.kind != ClassKind::Trait {
+            return Err(format!("{} cannot use {} - it is not a trait", name, t.name));
+        }
+    }
+
+    let mut class = Class {
+        name: name.clone(),
+        kind: def.kind,
+        is_abstract: def.is_abstract,
+        is_final: def.is_final,
+        parent: inputs.parent.clone(),
+        interfaces: Vec::new(),
+        constants: Vec::new(),
+        props: Vec::new(),
+        static_props: Vec::new(),
+        methods: Vec::new(),
+        method_index: HashMap::new(),
+        decl: def.clone(),
+    };
+
+    // Own methods come first in the function table, inherited ones after.
+    for m in &def.methods {
+        if class.method_index.contains_key(&m.name.to_ascii_lowercase()) {
+            return Err(format!("Cannot redeclare {}::{}()", name, m.name));
+        }
+        class.add_method(Rc::new(Method {
+            name: m.name.clone(),
+            class: name.clone(),
+            origin: None,
+            func: m.func.clone(),
+            visibility: m.visibility,
+            is_static: m.is_static,
+            is_abstract: m.is_abstract,
+            is_final: m.is_final,
+        }));
+    }
+
+    apply_traits(&mut class, def, &inputs.traits)?;
+
+    if let Some(parent) = &inputs.parent {
+        inherit_from_parent(&mut class, parent)?;
+    }
+
+    // Interfaces: everything the parent implements, plus each listed
+    // interface and the interfaces it extends.
+    if let Some(parent) = &inputs.parent {
+        class.interfaces.extend(parent.interfaces.iter().cloned());
+    }
+    for iface in &inputs.interfaces {
+        for i in std::iter::once(iface).chain(iface.interfaces.iter()) {
+            if !class.interfaces.iter().any(|c| Rc::ptr_eq(c, i)) {
+                class.interfaces.push(i.clone());
+            }
+        }
+    }
+    for iface in class.interfaces.clone() {
+        for c in &iface.constants {
+            if class.find_constant(&c.name).is_none() {
+                class.constants.push(c.clone());
+            }
+        }
+        for m in &iface.methods {
+            match class.find_method(&m.name).cloned() {
+                Some(existing) => {
+                    if !Rc::ptr_eq(&existing, m) {
+                        check_override(&class, &existing, m)?;
+                    }
+                }
+                None => class.add_method(m.clone()),
+            }
+        }
+    }
+
+    if class.kind != ClassKind::Interface
+        && class.is_subclass_of("Traversable")
+        && !class.is_subclass_of("Iterator")
+        && !class.is_subclass_of("IteratorAggregate")
+    {
+        return Err(format!(
+            "Class {} must implement interface Traversable as part of either Iterator or IteratorAggregate",
+            name
+        ));
+    }
+
+    add_own_members(&mut class, def, &inputs)?;
+
+    if matches!(class.kind, ClassKind::Class | ClassKind::Enum) && !class.is_abstract {
+        verify_abstract_class(&class)?;
+    }
+
+    Ok(class)
+}
+
+fn apply_traits(class: &mut Class, def: &ClassDef, traits: &[Rc<Class>]) -> Result<(), String> {
+    let name = class.name.clone();
+    let find_trait = |t: &str| traits.iter().find(|c| c.name.eq_ignore_ascii_case(t)).cloned();
+    let adaptations: Vec<&TraitAdaptation> = def.trait_uses.iter().flat_map(|u| u.adaptations.iter()).collect();
+
+    // Validate adaptations before composing.
+    let mut excluded: Vec<(Rc<str>, String)> = Vec::new(); // (trait, lowercase method)
+    for a in &adaptations {
+        match a {
+            TraitAdaptation::Insteadof { trait_name, method, excluded: others } => {
+                let t = find_trait(trait_name)
+                    .ok_or_else(|| format!("Required Trait {} wasn't added to {}", trait_name, name))?;
+                if t.find_method(method).is_none() {
+                    return Err(format!(
+                        "A precedence rule was defined for {}::{} but this method does not exist",
+                        t.name, method
+                    ));
+                }
+                for other in others {
+                    let o = find_trait(other)
+                        .ok_or_else(|| format!("Required Trait {} wasn't added to {}", other, name))?;
+                    excluded.push((o.name.clone(), method.to_ascii_lowercase()));
+                }
+            }
+            TraitAdaptation::Alias { trait_name: Some(trait_name), method, .. } => {
+                let t = find_trait(trait_name)
+                    .ok_or_else(|| format!("Required Trait {} wasn't added to {}", trait_name, name))?;
+                if t.find_method(method).is_none() {
+                    return Err(format!("An alias was defined for {}::{} but this method does not exist", t.name, method));
+                }
+            }
+            TraitAdaptation::Alias { trait_name: None, method, .. } => {
+                let owners: Vec<_> = traits.iter().filter(|t| t.find_method(method).is_some()).collect();
+                match owners.len() {
+                    0 => return Err(format!("An alias was defined for {} but this method does not exist", method)),
+                    1 => {}
+                    _ => {
+                        return Err(format!(
+                            "An alias was defined for method {}(), which exists in both {} and {}. Use {}::{} or {}::{} to resolve the ambiguity",
+                            method, owners[0].name, owners[1].name, owners[0].name, method, owners[1].name, method
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    let own: Vec<String> = class.methods.iter().map(|m| m.name.to_ascii_lowercase()).collect();
+    let mut imported: HashMap<String, Rc<Method>> = HashMap::new();
+    let mut import = |class: &mut Class, m: Method| -> Result<(), String> {
+        let key = m.name.to_ascii_lowercase();
+        if own.contains(&key) {
+            return Ok(()); // the class's own method wins
+        }
+        if let Some(prev) = imported.get(&key) {
+            if m.is_abstract {
+                return Ok(());
+            }
+            if !prev.is_abstract {
+                return Err(format!(
+                    "Trait method {}::{} has not been applied as {}::{}, because of collision with {}::{}",
+                    m.origin.as_deref().unwrap_or(""),
+                    m.name,
+                    class.name,
+                    m.name,
+                    prev.origin.as_deref().unwrap_or(""),
+                    prev.name
+                ));
+            }
+        }
+        let m = Rc::new(m);
+        imported.insert(key, m.clone());
+        class.add_method(m);
+        Ok(())
+    };
+
+    for t in traits {
+        for m in &t.methods {
+            let key = m.name.to_ascii_lowercase();
+            let origin = m.origin.clone().unwrap_or_else(|| t.name.clone());
+            let base = Method { class: name.clone(), origin: Some(origin), ..(**m).clone() };
+
+            for a in &adaptations {
+                if let TraitAdaptation::Alias { trait_name, method, alias, visibility } = a {
+                    let applies = method.eq_ignore_ascii_case(&m.name)
+                        && trait_name.as_ref().is_none_or(|tn| tn.eq_ignore_ascii_case(&t.name));
+                    if let (true, Some(alias)) = (applies, alias) {
+                        import(class, Method {
+                            name: alias.as_str().into(),
+                            visibility: visibility.unwrap_or(base.visibility),
+                            ..base.clone()
+                        })?;
+                    }
+                }
+            }
+
+            if excluded.iter().any(|(tn, mn)| Rc::ptr_eq(tn, &t.name) && *mn == key) {
+                continue;
+            }
+            let mut method = base;
+            for a in &adaptations {
+                if let TraitAdaptation::Alias { trait_name, method: mname, alias: None, visibility: Some(v) } = a
+                    && mname.eq_ignore_ascii_case(&m.name)
+                        && trait_name.as_ref().is_none_or(|tn| tn.eq_ignore_ascii_case(&t.name))
+                    {
+                        method.visibility = *v;
+                    }
+            }
+            import(class, method)?;
+        }
+
+        for p in &t.props {
+            if class.find_prop(&p.name).is_none() {
+                class.props.push(PropInfo {
+                    name: p.name.clone(),
+                    class: name.clone(),
+                    visibility: p.visibility,
+                    default: p.default.clone(),
+                });
+            }
+        }
+        for p in &t.static_props {
+            if class.find_static_prop(&p.name).is_none() {
+                // every using class gets its own copy of a trait's statics
+                class.static_props.push(Rc::new(StaticProp {
+                    name: p.name.clone(),
+                    class: name.clone(),
+                    visibility: p.visibility,
+                    cell: Rc::new(RefCell::new(Value::Null)),
+                    init: p.init.clone(),
+                }));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn inherit_from_parent(class: &mut Class, parent: &Rc<Class>) -> Result<(), String> {
+    for m in &parent.methods {
+        match class.find_method(&m.name).cloned() {
+            Some(child) => check_override(class, &child, m)?,
+            None => class.add_method(m.clone()),
+        }
+    }
+    for c in &parent.constants {
+        class.constants.push(c.clone());
+    }
+    // Parent properties come first, in the parent's order.
+    for p in &parent.props {
+        class.props.push(PropInfo {
+            name: p.name.clone(),
+            class: p.class.clone(),
+            visibility: p.visibility,
+            default: p.default.clone(),
+        });
+    }
+    for p in &parent.static_props {
+        if class.find_static_prop(&p.name).is_none() {
+            class.static_props.push(p.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Checks `child` against the method it overrides or implements.
+fn check_override(class: &Class, child: &Method, proto: &Method) -> Result<(), String> {
+    if proto.visibility == Visibility::Private && !proto.is_abstract {
+        return Ok(());
+    }
+    if proto.is_final {
+        return Err(format!("Cannot override final method {}::{}()", proto.class, proto.name));
+    }
+    if proto.is_static && !child.is_static {
+        return Err(format!(
+            "Cannot make static method {}::{}() non static in class {}",
+            proto.class, proto.name, class.name
+        ));
+    }
+    if !proto.is_static && child.is_static {
+        return Err(format!(
+            "Cannot make non static method {}::{}() static in class {}",
+            proto.class, proto.name, class.name
+        ));
+    }
+    if child.is_abstract && !proto.is_abstract {
+        return Err(format!(
+            "Cannot make non abstract method {}::{}() abstract in class {}",
+            proto.class, proto.name, class.name
+        ));
+    }
+    let weaker = match proto.visibility {
+        Visibility::Public => child.visibility != Visibility::Public,
+        Visibility::Protected => child.visibility == Visibility::Private,
+        Visibility::Private => false,
+    };
+    if weaker {
+        return Err(format!(
+            "Access level to {}::{}() must be {} (as in class {}){}",
+            child.class,
+            child.name,
+            visibility_name(proto.visibility),
+            proto.class,
+            if proto.visibility == Visibility::Public { "" } else { " or weaker" }
+        ));
+    }
+    // Constructors are only checked against abstract or interface prototypes.
+    if proto.name.eq_ignore_ascii_case("__construct") && !proto.is_abstract {
+        return Ok(());
+    }
+    if !child.func.is_compatible_with(&proto.func) {
+        return Err(format!(
+            "Declaration of {}::{} must be compatible with {}::{}",
+            child.class,
+            child.func.signature(&child.name),
+            proto.class,
+            proto.func.signature(&proto.name)
+        ));
+    }
+    Ok(())
+}
+
+fn add_own_members(class: &mut Class, def: &ClassDef, inputs: &LinkInputs) -> Result<(), String> {
+    let name = class.name.clone();
+    for (i, c) in def.constants.iter().enumerate() {
+        if def.constants[..i].iter().any(|o| o.name == c.name) {
+            return Err(format!("Cannot redefine class constant {}::{}", name, c.name));
+        }
+        if let Some(existing) = class.find_constant(&c.name)
+            && existing.is_final {
+                return Err(format!("{}::{} cannot override final constant {}::{}", name, c.name, existing.class, c.name));
+            }
+        class.constants.retain(|e| e.name != c.name);
+        class.constants.push(Rc::new(ClassConst {
+            name: c.name.clone(),
+            class: name.clone(),
+            visibility: c.visibility,
+            is_final: c.is_final,
+            is_case: c.is_case,
+            value: RefCell::new(Lazy::Pending(c.value.clone())),
+        }));
+    }
+
+    if def.kind == ClassKind::Enum {
+        let mut names = vec!["name"];
+        if def.backing_type.is_some() {
+            names.push("value");
+        }
+        for prop in names {
+            class.props.push(PropInfo {
+                name: prop.into(),
+                class: name.clone(),
+                visibility: Visibility::Public,
+                default: Rc::new(RefCell::new(Lazy::Done(Value::Null))),
+            });
+        }
+    }
+
+    for p in &def.props {
+        if p.is_static {
+            if class.find_prop(&p.name).is_some() {
+                return Err(format!("Cannot redeclare non static {}::${} as static {}::${}", inherited_owner(inputs, &p.name), p.name, name, p.name));
+            }
+            class.static_props.retain(|e| e.name != p.name);
+            class.static_props.push(Rc::new(StaticProp {
+                name: p.name.clone(),
+                class: name.clone(),
+                visibility: p.visibility,
+                cell: Rc::new(RefCell::new(Value::Null)),
+                init: p.default.clone(),
+            }));
+        } else {
+            if let Some(existing) = class.find_static_prop(&p.name) {
+                return Err(format!("Cannot redeclare static {}::${} as non static {}::${}", existing.class, p.name, name, p.name));
+            }
+            let default = Rc::new(RefCell::new(match &p.default {
+                Some(f) => Lazy::Pending(f.clone()),
+                None => Lazy::Done(Value::Null),
+            }));
+            let info = PropInfo { name: p.name.clone(), class: name.clone(), visibility: p.visibility, default };
+            match class.props.iter().position(|e| e.name == p.name) {
+                Some(i) => {
+                    let prev = &class.props[i];
+                    let weaker = match prev.visibility {
+                        Visibility::Public => p.visibility != Visibility::Public,
+                        Visibility::Protected => p.visibility == Visibility::Private,
+                        Visibility::Private => false,
+                    };
+                    if weaker && *prev.class != *name {
+                        return Err(format!(
+                            "Access level to {}::${} must be {} (as in class {}){}",
+                            name,
+                            p.name,
+                            visibility_name(prev.visibility),
+                            prev.class,
+                            if prev.visibility == Visibility::Public { "" } else { " or weaker" }
+                        ));
+                    }
+                    class.props[i] = info;
+                }
+                None => class.props.push(info),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn inherited_owner(inputs: &LinkInputs, prop: &str) -> Rc<str> {
+    inputs
+        .parent
+        .as_ref()
+        .and_then(|p| p.find_prop(prop).map(|i| i.class.clone()))
+        .unwrap_or_else(|| "".into())
+}
+
+fn verify_abstract_class(class: &Class) -> Result<(), String> {
+    let abstract_methods: Vec<&Rc<Method>> = class.methods.iter().filter(|m| m.is_abstract).collect();
+    if abstract_methods.is_empty() {
+        return Ok(());
+    }
+    let mut list: Vec<String> = abstract_methods
+        .iter()
+        .take(3)
+        .map(|m| format!("{}::{}", m.class, m.name))
+        .collect();
+    if abstract_methods.len() > 3 {
+        list.push("...".to_string());
+    }
+    Err(format!(
+        "{} {} contains {} abstract method{} and must therefore be declared abstract or implement the remaining methods ({})",
+        if class.kind == ClassKind::Enum { "Enum" } else { "Class" },
+        class.name,
+        abstract_methods.len(),
+        if abstract_methods.len() == 1 { "" } else { "s" },
+        list.join(", ")
+    ))
+}
@@ -0,0 +1,289 @@
+use std::rc::Rc;
+
+use crate::array::ArrayKey;
+use crate::ast::{ClassKind, Visibility};
+use crate::class::{Internal, Object};
+use crate::compiler::{ClassDef, ClosureDef, Function, MethodDef};
+use crate::value::{ObjRef, Value};
+use crate::vm::{expect_args, ref_cell, Callee, NativeFn, Vm, VmError, VmResult};
+
+/// State of a `Closure` object.
+#[derive(Debug)]
+pub struct Closure {
+    pub callee: Callee,
+    /// Declared `static`, or created from a static method: no `$this`
+    /// may be bound.
+    pub is_static: bool,
+}
+
+/// The built-in `Closure` class.
+pub fn class_def() -> ClassDef {
+    let methods: [(&str, &[&str], NativeFn, bool); 5] = [
+        ("bind", &["closure", "newThis", "newScope"], bind, true),
+        ("bindTo", &["newThis", "newScope"], bind_to, false),
+        ("call", &["newThis", "...args"], call, false),
+        ("fromCallable", &["callback"], from_callable, true),
+        ("__invoke", &["...args"], invoke, false),
+    ];
+    ClassDef {
+        name: "Closure".into(),
+        kind: ClassKind::Class,
+        is_abstract: false,
+        is_final: true,
+        parent: None,
+        interfaces: Vec::new(),
+        backing_type: None,
+        constants: Vec::new(),
+        props: Vec::new(),
+        methods: methods
+            .into_iter()
+            .map(|(name, params, f, is_static)| MethodDef {
+                name: name.into(),
+                func: Rc::new(Function::native(name, params, f)),
+                visibility: Visibility::Public,
+                is_static,
+                is_abstract: false,
+                is_final: false,
+            })
+            .collect(),
+        trait_uses: Vec::new(),
+        line: 0,
+    }
+}
+
+fn closure_of(v: &Value) -> Option<Rc<Closure>> {
+    match v {
+        Value::Object(o) => match &o.borrow().internal {
+            Internal::Closure(c) => Some(c.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn this_closure(callee: &Callee) -> Rc<Closure> {
+    let this = Value::Object(callee.this.clone().expect("Closure method called without $this"));
+    closure_of(&this).expect("Closure method called on a non-closure")
+}
+
+impl Vm {
+    /// Wraps a callee in a new `Closure` object.
+    pub fn new_closure(&mut self, callee: Callee, is_static: bool) -> VmResult<Value> {
+        let class = self.lookup_class("Closure")?;
+        let obj = Object::new(&self.objects, class, Default::default());
+        obj.borrow_mut().internal = Internal::Closure(Rc::new(Closure { callee, is_static }));
+        Ok(Value::Object(obj))
+    }
+
+    /// Evaluates a closure expression in the current frame, capturing its
+    /// `use` variables and, unless static, `$this`.
+    pub fn create_closure(&mut self, def: &ClosureDef) -> VmResult<Value> {
+        let mut bound = Vec::new();
+        for (name, by_ref) in &def.uses {
+            if *by_ref {
+                let cell = ref_cell(self.frame_mut().vars.entry_or(name.clone(), Value::Null));
+                bound.push((name.clone(), Value::Ref(cell)));
+                continue;
+            }
+            match self.frame().vars.get(name) {
+                Some(v) => bound.push((name.clone(), v.deref())),
+                None if def.is_arrow => {}
+                None => {
+                    self.warn(&format!("Undefined variable ${}", name));
+                    bound.push((name.clone(), Value::Null));
+                }
+            }
+        }
+        let frame = self.frame();
+        let this = match frame.vars.get("this") {
+            Some(Value::Object(o)) if !def.is_static => Some(o.clone()),
+            _ => None,
+        };
+        let callee = Callee {
+            func: def.func.clone(),
+            this,
+            scope: frame.scope.clone(),
+            static_class: frame.static_class.clone(),
+            bound,
+            magic_name: None,
+        };
+        self.new_closure(callee, def.is_static)
+    }
+
+    /// Resolves a callable value: a closure, a function name,
+    /// `"Class::method"`, `[$object or "Class", "method"]` or an object
+    /// with `__invoke`.
+    pub fn resolve_callable(&mut self, v: &Value) -> VmResult<Callee> {
+        match v {
+            Value::Object(o) => {
+                if let Some(c) = closure_of(v) {
+                    return Ok(c.callee.clone());
+                }
+                let class = o.borrow().class.clone();
+                match class.find_method("__invoke").cloned() {
+                    Some(m) => self.method_callee(&m, Some(o.clone()), class),
+                    None => Err(VmError::error(format!("Object of type {} is not callable", class.name))),
+                }
+            }
+            Value::Str(s) => {
+                let name = String::from_utf8_lossy(s);
+                match name.split_once("::") {
+                    Some((class, method)) => {
+                        let class = self.resolve_class(class)?;
+                        self.static_callee(class, method, false)
+                    }
+                    None => Ok(Callee::function(self.lookup_function(&name)?)),
+                }
+            }
+            Value::Array(a) => {
+                let (Some(target), Some(method), 2) = (a.get(&ArrayKey::Int(0)), a.get(&ArrayKey::Int(1)), a.len())
+                else {
+                    return Err(VmError::error("Array callback must have exactly two elements"));
+                };
+                let Value::Str(method) = method.deref() else {
+                    return Err(VmError::error("Method name must be a string"));
+                };
+                let method = String::from_utf8_lossy(&method).into_owned();
+                match target.deref() {
+                    obj @ Value::Object(_) => self.method_callee_of(obj, &method),
+                    Value::Str(class) => {
+                        let class = self.resolve_class(&String::from_utf8_lossy(&class))?;
+                        self.static_callee(class, &method, false)
+                    }
+                    _ => Err(VmError::error("First array member is not a valid class name or object")),
+                }
+            }
+            Value::Ref(r) => {
+                let v = r.borrow().clone();
+                self.resolve_callable(&v)
+            }
+            other => Err(VmError::error(format!("Value of type {} is not callable", other.type_name()))),
+        }
+    }
+
+    /// Shared by `bind()` and `bindTo()`.
+    fn rebind(&mut self, closure: &Closure, new_this: Value, new_scope: Option<Value>, func: &str) -> VmResult<Value> {
+        let this = match new_this {
+            Value::Null => None,
+            Value::Object(o) => Some(o),
+            other => {
+                return Err(VmError::type_error(format!(
+                    "{}(): Argument #1 ($newThis) must be of type ?object, {} given",
+                    func,
+                    other.type_name()
+                )));
+            }
+        };
+        if this.is_some() && closure.is_static {
+            self.warn("Cannot bind an instance to a static closure");
+            return Ok(Value::Null);
+        }
+        let scope = match new_scope {
+            None => closure.callee.scope.clone(),
+            Some(Value::Object(o)) => Some(o.borrow().class.clone()),
+            Some(Value::Null) => None,
+            Some(v) => {
+                let name = String::from_utf8_lossy(&self.stringify(&v)?).into_owned();
+                if name == "static" {
+                    closure.callee.scope.clone()
+                } else {
+                    match self.lookup_class(&name) {
+                        Ok(c) => Some(c),
+                        Err(_) => {
+                            self.warn(&format!("Class \"{}\" not found", name));
+                            return Ok(Value::Null);
+                        }
+                    }
+                }
+            }
+        };
+        let static_class = match &this {
+            Some(o) => Some(o.borrow().class.clone()),
+            None => scope.clone(),
+        };
+        let callee = Callee { this, scope, static_class, ..closure.callee.clone() };
+        self.new_closure(callee, closure.is_static)
+    }
+}
+
+/// `Closure::bind(Closure $closure, ?object $newThis, object|string|null $newScope = "static")`
+fn bind(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Closure::bind", &args, 2, 3)?;
+    let Some(closure) = closure_of(&args[0]) else {
+        return Err(VmError::type_error(format!(
+            "Closure::bind(): Argument #1 ($closure) must be of type Closure, {} given",
+            args[0].type_name()
+        )));
+    };
+    let mut args = args.into_iter().skip(1);
+    let new_this = args.next().unwrap();
+    vm.rebind(&closure, new_this, args.next(), "Closure::bind")
+}
+
+/// `$closure->bindTo(?object $newThis, object|string|null $newScope = "static")`
+fn bind_to(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Closure::bindTo", &args, 1, 2)?;
+    let closure = this_closure(callee);
+    let mut args = args.into_iter();
+    let new_this = args.next().unwrap();
+    vm.rebind(&closure, new_this, args.next(), "Closure::bindTo")
+}
+
+/// `$closure->call(object $newThis, mixed ...$args)`: calls the closure
+/// bound to `$newThis` and its class.
+fn call(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Closure::call", &args, 1, usize::MAX)?;
+    let closure = this_closure(callee);
+    let mut args = args.into_iter();
+    let this: ObjRef = match args.next().unwrap() {
+        Value::Object(o) => o,
+        other => {
+            return Err(VmError::type_error(format!(
+                "Closure::call(): Argument #1 ($newThis) must be of type object, {} given",
+                other.type_name()
+            )));
+        }
+    };
+    if closure.is_static {
+        vm.warn("Cannot bind an instance to a static closure");
+        return Ok(Value::Null);
+    }
+    let class = this.borrow().class.clone();
+    let callee = Callee {
+        this: Some(this),
+        scope: Some(class.clone()),
+        static_class: Some(class),
+        ..closure.callee.clone()
+    };
+    vm.call(callee, args.collect())
+}
+
+/// `Closure::fromCallable(callable $callback)`
+fn from_callable(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Closure::fromCallable", &args, 1, 1)?;
+    let callback = args.into_iter().next().unwrap();
+    if closure_of(&callback).is_some() {
+        return Ok(callback);
+    }
+    let callee = match vm.resolve_callable(&callback) {
+        Ok(c) => c,
+        Err(VmError::Error { message, .. }) => {
+            let reason = match &callback {
+                Value::Str(s) if !s.windows(2).any(|w| w == b"::") => {
+                    format!("function \"{}\" not found or invalid function name", String::from_utf8_lossy(s))
+                }
+                _ => message,
+            };
+            return Err(VmError::type_error(format!("Failed to create closure from callable: {}", reason)));
+        }
+        Err(e) => return Err(e),
+    };
+    let is_static = callee.this.is_none() && callee.scope.is_some();
+    vm.new_closure(callee, is_static)
+}
+
+/// `$closure->__invoke(...$args)`
+fn invoke(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let closure = this_closure(callee);
+    vm.call(closure.callee.clone(), args)
+}
@@ -0,0 +1,2145 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::*;
+use crate::limits;
+use crate::value::{format_float, strict_equals, Value};
+use crate::vm::NativeFn;
+
+// -- Bytecode -----------------------------------------------
+
+/// Class operand of an instruction. `Dynamic` takes the class (a name or
+/// an object) from the operand stack.
+#[derive(Debug, Clone)]
+pub enum ClassOperand {
+    Named(Rc<str>),
+    Dynamic,
+}
+
+/// Where an assignment writes to. Any dynamic parts are evaluated onto
+/// the stack before the value, in source order: the class for a dynamic
+/// static property, the object for a `Stack` base, then one key per `Dim`.
+#[derive(Debug, Clone)]
+pub struct LValue {
+    pub base: LBase,
+    pub steps: Vec<LStep>,
+}
+
+#[derive(Debug, Clone)]
+pub enum LBase {
+    Var(Rc<str>),
+    StaticProp(ClassOperand, Rc<str>),
+    /// An object produced by an arbitrary expression, e.g. `f()->x = 1`.
+    Stack,
+}
+
+#[derive(Debug, Clone)]
+pub enum LStep {
+    Dim,
+    Append,
+    Prop(Rc<str>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Op {
+    Const(Value),
+    LoadVar(Rc<str>),
+    Assign(Rc<LValue>),
+    /// Makes the target a reference to the source; the source's dynamic
+    /// parts are on top of the target's.
+    AssignRef(Rc<LValue>, Rc<LValue>),
+    /// `global $name`
+    BindGlobal(Rc<str>),
+    /// Binds a `static` variable, jumping over its initializer unless
+    /// this is its first use.
+    BindStatic(Rc<str>, usize),
+    Unset(Rc<LValue>),
+    /// Pushes whether the place is set and not null, without warnings.
+    Isset(Rc<LValue>),
+    /// Pushes whether the place is unset or falsy, without warnings.
+    Empty(Rc<LValue>),
+    Pop,
+    Binary(BinOp),
+    Unary(UnaryOp),
+    Jump(usize),
+    JumpIfFalse(usize),
+    JumpIfTrue(usize),
+    Echo,
+
+    NewArray,
+    /// `[array, value]` -> `[array]`
+    ArrayPush,
+    /// `[array, key, value]` -> `[array]`
+    ArrayInsert,
+    /// `[...$other]`: `[array, other]` -> `[array]`
+    ArrayUnpack,
+    /// An element referencing the place, with a key below the place's
+    /// dynamic parts if `true`.
+    ArrayRef(Rc<LValue>, bool),
+    /// `[container, key]` -> `[value]`
+    FetchDim,
+    /// Like `FetchDim`, but anything other than an array gives null, as
+    /// when destructuring.
+    FetchListDim,
+    FetchProp(Rc<str>),
+    FetchStaticProp(ClassOperand, Rc<str>),
+    FetchClassConst(ClassOperand, Rc<str>),
+    /// A constant, with the global name to fall back to like `InitCall`.
+    FetchConst(Rc<str>, Option<Rc<str>>),
+
+    // Calls are set up by an Init*/New op, receive their arguments through
+    // Send, and run on DoCall, which pushes the return value.
+    /// Calls a function by name; an unqualified name in a namespace
+    /// falls back to the global function.
+    InitCall(Rc<str>, Option<Rc<str>>),
+    InitMethodCall(Rc<str>),
+    InitStaticCall(ClassOperand, Rc<str>),
+    /// Calls the callable value on the stack (closure, function name,
+    /// `[$obj, 'method']`, invokable object).
+    InitDynamicCall,
+    /// Pushes the new object and sets up its constructor call.
+    New(ClassOperand),
+    /// `[value, class?]` -> `[bool]`
+    InstanceOf(ClassOperand),
+    /// Sends the value on the stack, as a named argument if given.
+    Send(Option<Rc<str>>),
+    /// Sends a reference to the place, for a by-reference parameter.
+    SendRef(Rc<LValue>, Option<Rc<str>>),
+    /// `...$args`: int keys are sent positionally, string keys by name.
+    SendUnpack,
+    /// Jumps unless the parameter the next argument binds to is taken by
+    /// reference; arguments that can be referenced are compiled both ways.
+    JumpIfArgByVal(Option<Rc<str>>, usize),
+    DoCall,
+    /// Turns the call just set up into a `Closure` instead of making it
+    /// (first-class callable syntax).
+    ClosureFromCall,
+    Return,
+    /// Skips the default value of an optional parameter that was passed.
+    JumpIfPassed(Rc<str>, usize),
+    /// Binds the default value on the stack to the parameter.
+    InitParam(usize),
+
+    Closure(Rc<ClosureDef>),
+
+    /// Throws the exception on the stack.
+    Throw,
+    /// Jumps unless the exception on the stack is an instance of one of
+    /// the classes.
+    Catch(Rc<[Rc<str>]>, usize),
+    /// Runs a `finally` block on normal completion of its `try`.
+    EnterFinally(usize),
+    /// Runs the file named on the stack in the current scope.
+    Include(IncludeKind),
+    /// Resumes whatever the `finally` block interrupted.
+    FinallyEnd,
+    /// A `break` or `continue` out of a `try` with a `finally` block,
+    /// which runs first.
+    Leave(usize),
+
+    DeclareFunction(Rc<Function>),
+    DeclareClass(Rc<ClassDef>),
+    /// `[key?, value]`: suspends the generator; it resumes with the sent
+    /// value on the stack.
+    Yield(bool),
+    /// `[iterable]`: suspends the generator, which delegates to the
+    /// iterable and resumes with the delegate's return value.
+    YieldFrom,
+
+    // `foreach` loops keep their iterator in a numbered slot of the frame.
+    /// Starts iterating the value on the stack, by reference if flagged,
+    /// or warns and jumps if it cannot be iterated.
+    IterInit(usize, bool, usize),
+    /// Starts iterating a place by reference.
+    IterInitRef(usize, Rc<LValue>, usize),
+    /// Moves to the next element, fetching the key if asked to, or jumps
+    /// when done.
+    IterFetch(usize, usize, bool),
+    IterValue(usize),
+    IterKey(usize),
+    /// Binds the place to the current element by reference.
+    IterBind(usize, Rc<LValue>),
+    IterFree(usize),
+
+    /// `clone` of the object on the stack.
+    Clone,
+
+    /// Defines the global constant to the value on the stack.
+    DeclareConst(Rc<str>),
+
+    // Bodies of the methods every enum gets; they run in the enum's scope.
+    /// Creates the case object for a case constant. Backed cases take
+    /// their value from the stack.
+    NewEnumCase(Rc<str>),
+    /// `Enum::cases()`
+    EnumCases,
+    /// `Enum::from($value)`, or `Enum::tryFrom($value)` when `true`.
+    EnumFrom(bool),
+}
+
+#[derive(Debug)]
+pub struct Param {
+    pub name: Rc<str>,
+    pub ty: Option<TypeHint>,
+    /// The default value as PHP renders it in signatures; its code is at
+    /// the start of the function body.
+    pub default: Option<String>,
+    pub by_ref: bool,
+    /// A by-reference parameter of a built-in function that takes values
+    /// too, without a notice, like PHP's prefer-ref parameters.
+    pub prefer_ref: bool,
+    pub variadic: bool,
+}
+
+impl Param {
+    fn new(name: &str) -> Param {
+        Param { name: name.into(), ty: None, default: None, by_ref: false, prefer_ref: false, variadic: false }
+    }
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: Rc<str>,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeHint>,
+    pub code: Vec<Op>,
+    /// Source line of each instruction.
+    pub lines: Vec<usize>,
+    pub file: Rc<str>,
+    /// Engine-provided code; diagnostics point at the caller instead.
+    pub builtin: bool,
+    /// Set for functions implemented in Rust, which have no code.
+    pub native: Option<NativeFn>,
+    /// A native function whose argument count and parameter types the
+    /// engine checks before calling it.
+    pub checks_args: bool,
+    /// Declared in a `declare(strict_types=1)` file.
+    pub strict: bool,
+    /// `try` statements, innermost first.
+    pub try_blocks: Vec<TryBlock>,
+    /// Contains `yield`: calling it creates a `Generator`.
+    pub is_generator: bool,
+}
+
+/// The instruction ranges of a `try` statement: the `try` body, the
+/// `catch` clauses and the `finally` block follow each other.
+#[derive(Debug)]
+pub struct TryBlock {
+    pub start: usize,
+    pub catch_start: usize,
+    pub finally_start: usize,
+    pub end: usize,
+    pub has_catch: bool,
+    pub has_finally: bool,
+}
+
+impl Function {
+    /// A function implemented in Rust. Parameter names may be prefixed
+    /// with `&` for by-reference parameters, `&?` for prefer-ref ones, and
+    /// `...` for variadic parameters.
+    pub fn native(name: &str, params: &[&str], f: NativeFn) -> Function {
+        let params = params
+            .iter()
+            .map(|p| {
+                let (by_ref, p) = match p.strip_prefix('&') {
+                    Some(p) => (true, p),
+                    None => (false, *p),
+                };
+                let (prefer_ref, p) = match p.strip_prefix('?') {
+                    Some(p) if by_ref => (true, p),
+                    _ => (false, p),
+                };
+                let (variadic, p) = match p.strip_prefix("...") {
+                    Some(p) => (true, p),
+                    None => (false, p),
+                };
+                Param { by_ref, prefer_ref, variadic, ..Param::new(p) }
+            })
+            .collect();
+        Function {
+            name: name.into(),
+            params,
+            return_type: None,
+            code: Vec::new(),
+            lines: Vec::new(),
+            file: "".into(),
+            builtin: true,
+            native: Some(f),
+            checks_args: false,
+            strict: false,
+            try_blocks: Vec::new(),
+            is_generator: false,
+        }
+    }
+
+    /// A thunk returning a fixed value, for the constants and property
+    /// defaults of built-in classes.
+    pub fn constant(v: Value) -> Function {
+        Function {
+            name: "{constant}".into(),
+            params: Vec::new(),
+            return_type: None,
+            code: vec![Op::Const(v), Op::Return],
+            lines: vec![0, 0],
+            file: "".into(),
+            builtin: true,
+            native: None,
+            checks_args: false,
+            strict: false,
+            try_blocks: Vec::new(),
+            is_generator: false,
+        }
+    }
+
+    /// Number of arguments a call must pass: optional parameters before
+    /// a required one are required too.
+    pub fn required_params(&self) -> usize {
+        self.params.iter().rposition(|p| p.default.is_none() && !p.variadic).map_or(0, |i| i + 1)
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        self.params.last().is_some_and(|p| p.variadic)
+    }
+
+    /// Renders the declaration the way PHP's inheritance errors do,
+    /// e.g. `foo(int $a, $b = 5): string`.
+    pub fn signature(&self, name: &str) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| {
+                let mut s = String::new();
+                if let Some(ty) = &p.ty {
+                    s += &format!("{} ", ty);
+                }
+                if p.by_ref {
+                    s.push('&');
+                }
+                if p.variadic {
+                    s += "...";
+                }
+                s += &format!("${}", p.name);
+                if let Some(d) = &p.default {
+                    s += &format!(" = {}", d);
+                }
+                s
+            })
+            .collect();
+        let mut sig = format!("{}({})", name, params.join(", "));
+        if let Some(ty) = &self.return_type {
+            sig += &format!(": {}", ty);
+        }
+        sig
+    }
+
+    /// Whether this function may override `proto`: it must not require
+    /// more arguments nor accept fewer, parameter types may only widen and
+    /// the return type may only narrow.
+    pub fn is_compatible_with(&self, proto: &Function) -> bool {
+        if self.required_params() > proto.required_params() {
+            return false;
+        }
+        if proto.is_variadic() && !self.is_variadic() {
+            return false;
+        }
+        for (i, pp) in proto.params.iter().enumerate() {
+            let Some(cp) = self.params.get(i).or_else(|| self.params.last().filter(|p| p.variadic)) else {
+                return false;
+            };
+            if cp.by_ref != pp.by_ref || !type_covers(cp.ty.as_ref(), pp.ty.as_ref()) {
+                return false;
+            }
+        }
+        match (&self.return_type, &proto.return_type) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(c), Some(p)) => type_covers(Some(p), Some(c)),
+        }
+    }
+}
+
+pub const BUILTIN_TYPES: &[&str] = &[
+    "int", "float", "string", "bool", "false", "true", "null", "void", "never", "mixed", "array", "object",
+    "iterable", "callable", "static", "self", "parent",
+];
+
+/// The members of a type as lowercased names; an intersection counts as
+/// one member.
+pub fn type_members(ty: &TypeHint) -> Vec<String> {
+    match ty {
+        TypeHint::Named(n) => vec![n.to_ascii_lowercase()],
+        TypeHint::Nullable(t) => {
+            let mut m = type_members(t);
+            m.push("null".to_string());
+            m
+        }
+        TypeHint::Union(ts) => ts.iter().flat_map(type_members).collect(),
+        TypeHint::Intersection(_) => vec![ty.to_string().to_ascii_lowercase()],
+    }
+}
+
+/// Whether every value of `narrow` is also a `wide` (no type means
+/// `mixed`). Class types are not resolved here, so any class is taken to
+/// fit any other class or `object`.
+fn type_covers(wide: Option<&TypeHint>, narrow: Option<&TypeHint>) -> bool {
+    let Some(wide) = wide else { return true };
+    let wide = type_members(wide);
+    if wide.iter().any(|w| w == "mixed") {
+        return true;
+    }
+    let Some(narrow) = narrow else { return false };
+    let is_class = |t: &str| !BUILTIN_TYPES.contains(&t) || matches!(t, "self" | "static" | "parent");
+    type_members(narrow).iter().all(|n| {
+        wide.contains(n)
+            || (matches!(n.as_str(), "false" | "true") && wide.iter().any(|w| w == "bool"))
+            || (n == "array" && wide.iter().any(|w| w == "iterable"))
+            || (is_class(n) && wide.iter().any(|w| w == "object" || w == "iterable" || is_class(w)))
+    })
+}
+
+#[derive(Debug)]
+pub struct ClosureDef {
+    pub func: Rc<Function>,
+    /// Captured variables, `true` when captured by reference.
+    pub uses: Vec<(Rc<str>, bool)>,
+    pub is_static: bool,
+    /// Arrow functions capture implicitly and skip undefined variables.
+    pub is_arrow: bool,
+}
+
+#[derive(Debug)]
+pub struct ConstDef {
+    pub name: Rc<str>,
+    pub value: Rc<Function>,
+    pub visibility: Visibility,
+    pub is_final: bool,
+    /// Enum cases are constants holding the case object.
+    pub is_case: bool,
+}
+
+#[derive(Debug)]
+pub struct PropDef {
+    pub name: Rc<str>,
+    pub default: Option<Rc<Function>>,
+    pub visibility: Visibility,
+    pub is_static: bool,
+}
+
+#[derive(Debug)]
+pub struct MethodDef {
+    pub name: Rc<str>,
+    pub func: Rc<Function>,
+    pub visibility: Visibility,
+    pub is_static: bool,
+    pub is_abstract: bool,
+    pub is_final: bool,
+}
+
+#[derive(Debug)]
+pub struct TraitUseDef {
+    pub traits: Vec<Rc<str>>,
+    pub adaptations: Vec<TraitAdaptation>,
+}
+
+/// A compiled class declaration, linked into a runtime `Class` when the
+/// declaration is executed.
+#[derive(Debug)]
+pub struct ClassDef {
+    pub name: Rc<str>,
+    pub kind: ClassKind,
+    pub is_abstract: bool,
+    pub is_final: bool,
+    pub parent: Option<Rc<str>>,
+    pub interfaces: Vec<Rc<str>>,
+    /// `int` or `string` for backed enums.
+    pub backing_type: Option<Rc<str>>,
+    pub constants: Vec<ConstDef>,
+    pub props: Vec<PropDef>,
+    pub methods: Vec<MethodDef>,
+    pub trait_uses: Vec<TraitUseDef>,
+    pub line: usize,
+}
+
+/// A compiled file. Top-level functions and classes are listed so the VM
+/// can declare them before running `main`, as PHP does.
+#[derive(Debug)]
+pub struct Script {
+    pub main: Rc<Function>,
+    pub functions: Vec<Rc<Function>>,
+    pub classes: Vec<Rc<ClassDef>>,
+}
+
+// -- Compiler -----------------------------------------------
+
+pub fn compile_script(program: &Block, file: &str) -> Result<Script, String> {
+    let mut c = Compiler::new(file.into());
+    let mut functions = Vec::new();
+    let mut classes = Vec::new();
+    let (mut seen_code, mut seen_namespace) = (false, false);
+    for (i, stmt) in program.iter().enumerate() {
+        c.line = stmt.line;
+        match &stmt.kind {
+            StmtKind::Declare(directives) => c.declare(directives, i == 0)?,
+            StmtKind::Namespace { name, body } => {
+                if seen_code && !seen_namespace {
+                    return Err(
+                        "Namespace declaration statement has to be the very first statement or after any declare call in the script"
+                            .to_string(),
+                    );
+                }
+                seen_namespace = true;
+                c.names = Rc::new(Names { namespace: name.clone().unwrap_or_default(), ..Names::default() });
+                if let Some(body) = body {
+                    c.compile_top_level(body, &mut functions, &mut classes)?;
+                    c.names = Rc::default();
+                }
+            }
+            _ => {
+                seen_code = true;
+                c.compile_top_level(std::slice::from_ref(stmt), &mut functions, &mut classes)?;
+            }
+        }
+    }
+    // an included file evaluates to 1 unless it returns a value
+    c.emit(Op::Const(Value::Int(1)));
+    c.emit(Op::Return);
+    let main = c.finish("{main}", Vec::new());
+    Ok(Script { main, functions, classes })
+}
+
+/// The namespace being compiled and its imports, which turn the names
+/// written in the source into fully qualified ones.
+#[derive(Debug, Clone, Default)]
+struct Names {
+    namespace: String,
+    /// Class and namespace imports, by lowercase alias.
+    classes: HashMap<String, String>,
+    /// Function imports, by lowercase alias.
+    functions: HashMap<String, String>,
+    /// Constant imports; their aliases are case-sensitive.
+    constants: HashMap<String, String>,
+}
+
+impl Names {
+    /// The name of something declared in the current namespace.
+    fn declare(&self, name: &str) -> String {
+        if self.namespace.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}\\{}", self.namespace, name)
+        }
+    }
+
+    /// Resolves a fully qualified, relative or qualified name; `None`
+    /// for an unqualified one.
+    fn qualify(&self, name: &str) -> Option<String> {
+        if let Some(n) = name.strip_prefix('\\') {
+            return Some(n.to_string());
+        }
+        let (first, rest) = name.split_once('\\')?;
+        if first.eq_ignore_ascii_case("namespace") {
+            return Some(self.declare(rest));
+        }
+        Some(match self.classes.get(&first.to_ascii_lowercase()) {
+            Some(import) => format!("{}\\{}", import, rest),
+            None => self.declare(name),
+        })
+    }
+
+    fn class(&self, name: &str) -> String {
+        if matches!(name.to_ascii_lowercase().as_str(), "self" | "parent" | "static") {
+            return name.to_string();
+        }
+        self.qualify(name).unwrap_or_else(|| match self.classes.get(&name.to_ascii_lowercase()) {
+            Some(import) => import.clone(),
+            None => self.declare(name),
+        })
+    }
+
+    /// A function or constant name, and the global name an unqualified
+    /// one falls back to in a namespace.
+    fn function_or_const(&self, name: &str, is_const: bool) -> (Rc<str>, Option<Rc<str>>) {
+        if let Some(q) = self.qualify(name) {
+            return (q.into(), None);
+        }
+        let import = if is_const {
+            self.constants.get(name)
+        } else {
+            self.functions.get(&name.to_ascii_lowercase())
+        };
+        match import {
+            Some(import) => (import.as_str().into(), None),
+            None if self.namespace.is_empty() => (name.into(), None),
+            None => (self.declare(name).into(), Some(name.into())),
+        }
+    }
+
+    /// Resolves the class names in a type.
+    fn resolve_type(&self, ty: &TypeHint) -> TypeHint {
+        match ty {
+            TypeHint::Named(n) if BUILTIN_TYPES.contains(&n.to_ascii_lowercase().as_str()) => ty.clone(),
+            TypeHint::Named(n) => TypeHint::Named(self.class(n)),
+            TypeHint::Nullable(t) => TypeHint::Nullable(Box::new(self.resolve_type(t))),
+            TypeHint::Union(ts) => TypeHint::Union(ts.iter().map(|t| self.resolve_type(t)).collect()),
+            TypeHint::Intersection(ts) => TypeHint::Intersection(ts.iter().map(|t| self.resolve_type(t)).collect()),
+        }
+    }
+
+    fn import(&mut self, item: &UseItem) -> Result<(), String> {
+        let alias = item.alias.clone().unwrap_or_else(|| match item.name.rsplit_once('\\') {
+            Some((_, last)) => last.to_string(),
+            None => item.name.clone(),
+        });
+        let (map, key) = match item.kind {
+            UseKind::Class => (&mut self.classes, alias.to_ascii_lowercase()),
+            UseKind::Function => (&mut self.functions, alias.to_ascii_lowercase()),
+            UseKind::Const => (&mut self.constants, alias.clone()),
+        };
+        if map.contains_key(&key) {
+            let what = match item.kind {
+                UseKind::Class => "",
+                UseKind::Function => "function ",
+                UseKind::Const => "const ",
+            };
+            return Err(format!("Cannot use {}{} as {} because the name is already in use", what, item.name, alias));
+        }
+        map.insert(key, item.name.clone());
+        Ok(())
+    }
+}
+
+struct Compiler {
+    file: Rc<str>,
+    code: Vec<Op>,
+    lines: Vec<usize>,
+    line: usize,
+    strict: bool,
+    /// Return type of the function being compiled.
+    return_type: Option<TypeHint>,
+    try_blocks: Vec<TryBlock>,
+    /// Enclosing loops, innermost last.
+    loops: Vec<Loop>,
+    /// Number of enclosing `try` statements that have a `finally` block.
+    finally_depth: usize,
+    names: Rc<Names>,
+    /// Hidden variables allocated so far, for destructuring.
+    temps: usize,
+    /// The class being compiled, for `__CLASS__` and `__METHOD__`.
+    class: Option<Rc<str>>,
+    in_trait: bool,
+    /// The function being compiled, for `__FUNCTION__`; empty at file level.
+    function: Rc<str>,
+    is_generator: bool,
+    /// A `return` that does not match the return type, reported once the
+    /// whole body is compiled: generators may return anything.
+    return_error: Option<String>,
+    /// `foreach` iterator slots allocated so far.
+    iters: usize,
+}
+
+/// A loop being compiled: the `break` and `continue` jumps to patch.
+struct Loop {
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+    finally_depth: usize,
+}
+
+impl Compiler {
+    fn new(file: Rc<str>) -> Self {
+        Compiler {
+            file,
+            code: Vec::new(),
+            lines: Vec::new(),
+            line: 0,
+            strict: false,
+            return_type: None,
+            try_blocks: Vec::new(),
+            loops: Vec::new(),
+            finally_depth: 0,
+            names: Rc::default(),
+            temps: 0,
+            class: None,
+            in_trait: false,
+            function: "".into(),
+            is_generator: false,
+            return_error: None,
+            iters: 0,
+        }
+    }
+
+    /// A compiler for a function or initializer nested in this one.
+    fn nested(&self) -> Compiler {
+        Compiler {
+            line: self.line,
+            strict: self.strict,
+            names: self.names.clone(),
+            class: self.class.clone(),
+            in_trait: self.in_trait,
+            ..Compiler::new(self.file.clone())
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.lines.push(self.line);
+        self.code.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.code.len()
+    }
+
+    fn patch(&mut self, at: usize) {
+        self.patch_to(at, self.here());
+    }
+
+    fn patch_to(&mut self, at: usize, target: usize) {
+        match &mut self.code[at] {
+            Op::Jump(t)
+            | Op::JumpIfFalse(t)
+            | Op::JumpIfTrue(t)
+            | Op::JumpIfPassed(_, t)
+            | Op::JumpIfArgByVal(_, t)
+            | Op::BindStatic(_, t)
+            | Op::IterInit(_, _, t)
+            | Op::IterInitRef(_, _, t)
+            | Op::IterFetch(_, t, _)
+            | Op::Catch(_, t)
+            | Op::EnterFinally(t)
+            | Op::Leave(t) => *t = target,
+            op => unreachable!("patching non-jump {:?}", op),
+        }
+    }
+
+    fn finish(mut self, name: &str, params: Vec<Param>) -> Rc<Function> {
+        self.emit(Op::Const(Value::Null));
+        self.emit(Op::Return);
+        Rc::new(Function {
+            name: name.into(),
+            params,
+            return_type: self.return_type,
+            code: self.code,
+            lines: self.lines,
+            file: self.file,
+            builtin: false,
+            native: None,
+            checks_args: false,
+            strict: self.strict,
+            try_blocks: self.try_blocks,
+            is_generator: self.is_generator,
+        })
+    }
+
+    /// Compiles file-level statements, collecting the unconditional
+    /// function and class declarations.
+    fn compile_top_level(
+        &mut self,
+        stmts: &[Stmt],
+        functions: &mut Vec<Rc<Function>>,
+        classes: &mut Vec<Rc<ClassDef>>,
+    ) -> Result<(), String> {
+        for stmt in stmts {
+            self.line = stmt.line;
+            match &stmt.kind {
+                StmtKind::FunctionDef { name, params, return_type, body } => {
+                    let f = self.compile_function(&self.names.declare(name), params, return_type.as_ref(), body)?;
+                    functions.push(f.clone());
+                    self.emit(Op::DeclareFunction(f));
+                }
+                StmtKind::ClassDecl(decl) => {
+                    let def = self.compile_class(decl)?;
+                    classes.push(def.clone());
+                    self.emit(Op::DeclareClass(def));
+                }
+                StmtKind::Const(consts) => {
+                    for (name, value) in consts {
+                        check_const_expr(value, true)?;
+                        self.compile_expr(value)?;
+                        self.emit(Op::DeclareConst(self.names.declare(name).into()));
+                    }
+                }
+                _ => self.compile_stmt(stmt)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn declare(&mut self, directives: &[(String, Expr)], first: bool) -> Result<(), String> {
+        for (name, value) in directives {
+            if !name.eq_ignore_ascii_case("strict_types") {
+                continue;
+            }
+            if !first {
+                return Err("strict_types declaration must be the very first statement in the script".to_string());
+            }
+            self.strict = match value {
+                Expr::Integer(0) => false,
+                Expr::Integer(1) => true,
+                _ => return Err("strict_types declaration must have 0 or 1 as its value".to_string()),
+            };
+        }
+        Ok(())
+    }
+
+    /// Compiles a function body. Default values of optional parameters
+    /// are evaluated at the start of the body when not passed.
+    fn compile_function(
+        &mut self,
+        name: &str,
+        params: &[ParamDecl],
+        return_type: Option<&TypeHint>,
+        body: &Block,
+    ) -> Result<Rc<Function>, String> {
+        let mut c = self.nested();
+        c.function = match name.ends_with("{closure}") {
+            true => "{closure}".into(),
+            false => name.into(),
+        };
+        let return_type = return_type.map(|t| self.names.resolve_type(t));
+        if let Some(ty) = &return_type {
+            check_type(ty, true)?;
+        }
+        c.return_type = return_type;
+        let mut compiled = Vec::new();
+        for (i, p) in params.iter().enumerate() {
+            if params[..i].iter().any(|o| o.name == p.name) {
+                return Err(format!("Redefinition of parameter ${}", p.name));
+            }
+            if p.variadic && i + 1 != params.len() {
+                return Err("Only the last parameter can be variadic".to_string());
+            }
+            let mut ty = p.ty.as_ref().map(|t| self.names.resolve_type(t));
+            if let Some(t) = &ty {
+                check_type(t, false)?;
+            }
+            if let Some(default) = &p.default {
+                if p.variadic {
+                    return Err("Variadic parameter cannot have a default value".to_string());
+                }
+                if let Some(t) = &ty {
+                    check_default(t, default, &p.name)?;
+                }
+                // `T $x = null` implicitly accepts null
+                if matches!(default, Expr::Null) {
+                    ty = ty.map(nullable);
+                }
+                let skip = c.emit(Op::JumpIfPassed(p.name.as_str().into(), 0));
+                c.compile_expr(default)?;
+                c.emit(Op::InitParam(i));
+                c.patch(skip);
+            }
+            compiled.push(Param {
+                name: p.name.as_str().into(),
+                ty,
+                default: p.default.as_ref().map(render_default),
+                by_ref: p.by_ref,
+                prefer_ref: false,
+                variadic: p.variadic,
+            });
+        }
+        for stmt in body {
+            c.compile_stmt(stmt)?;
+        }
+        if c.is_generator {
+            check_generator_type(c.return_type.as_ref())?;
+        } else if let Some(e) = c.return_error.take() {
+            return Err(e);
+        }
+        Ok(c.finish(name, compiled))
+    }
+
+    /// Compiles a constant expression into a function returning its value.
+    fn compile_thunk(&mut self, expr: &Expr) -> Result<Rc<Function>, String> {
+        check_const_expr(expr, false)?;
+        let mut c = self.nested();
+        c.compile_expr(expr)?;
+        c.emit(Op::Return);
+        Ok(c.finish("{constant}", Vec::new()))
+    }
+
+    fn compile_class(&mut self, decl: &ClassDecl) -> Result<Rc<ClassDef>, String> {
+        let line = self.line;
+        self.line = decl.line;
+        let mut def = ClassDef {
+            name: self.names.declare(&decl.name).into(),
+            kind: decl.kind,
+            is_abstract: decl.is_abstract,
+            // enums are implicitly final
+            is_final: decl.is_final || decl.kind == ClassKind::Enum,
+            parent: decl.parent.as_deref().map(|p| self.names.class(p).into()),
+            interfaces: decl.interfaces.iter().map(|i| self.names.class(i).into()).collect(),
+            backing_type: None,
+            constants: Vec::new(),
+            props: Vec::new(),
+            methods: Vec::new(),
+            trait_uses: Vec::new(),
+            line: decl.line,
+        };
+        if let Some(ty) = &decl.backing_type {
+            let ty = ty.to_ascii_lowercase();
+            if ty != "int" && ty != "string" {
+                return Err(format!("Enum backing type must be int or string, {} given", ty));
+            }
+            def.backing_type = Some(ty.into());
+        }
+        let outer = (self.class.replace(def.name.clone()), self.in_trait);
+        self.in_trait = decl.kind == ClassKind::Trait;
+        let mut case_values = Vec::new();
+        for member in &decl.members {
+            match member {
+                ClassMember::Constant { name, value, visibility, is_final } => {
+                    def.constants.push(ConstDef {
+                        name: name.as_str().into(),
+                        value: self.compile_thunk(value)?,
+                        visibility: *visibility,
+                        is_final: *is_final,
+                        is_case: false,
+                    });
+                }
+                ClassMember::Case { name, value } => {
+                    let func = self.compile_enum_case(&def, name, value.as_ref(), &mut case_values)?;
+                    def.constants.push(ConstDef {
+                        name: name.as_str().into(),
+                        value: func,
+                        visibility: Visibility::Public,
+                        is_final: false,
+                        is_case: true,
+                    });
+                }
+                ClassMember::Property { name, default, visibility, is_static } => {
+                    if def.props.iter().any(|p| &*p.name == name) {
+                        return Err(format!("Cannot redeclare {}::${}", decl.name, name));
+                    }
+                    let default = match default {
+                        Some(e) => Some(self.compile_thunk(e)?),
+                        None => None,
+                    };
+                    def.props.push(PropDef {
+                        name: name.as_str().into(),
+                        default,
+                        visibility: *visibility,
+                        is_static: *is_static,
+                    });
+                }
+                ClassMember::Method(m) => {
+                    self.line = m.line;
+                    let body = m.body.clone().unwrap_or_default();
+                    let func = self.compile_function(&m.name, &m.params, m.return_type.as_ref(), &body)?;
+                    def.methods.push(MethodDef {
+                        name: m.name.as_str().into(),
+                        func,
+                        visibility: m.visibility,
+                        is_static: m.is_static,
+                        is_abstract: m.is_abstract,
+                        is_final: m.is_final,
+                    });
+                }
+                ClassMember::TraitUse { traits, adaptations } => {
+                    def.trait_uses.push(TraitUseDef {
+                        traits: traits.iter().map(|t| self.names.class(t).into()).collect(),
+                        adaptations: adaptations.iter().map(|a| self.resolve_adaptation(a)).collect(),
+                    });
+                }
+            }
+        }
+        if decl.kind == ClassKind::Enum {
+            self.add_enum_methods(&mut def)?;
+        }
+        // declaring `__toString()` implements `Stringable`
+        let stringable = decl.kind != ClassKind::Trait
+            && def.methods.iter().any(|m| m.name.eq_ignore_ascii_case("__toString"))
+            && !def.name.eq_ignore_ascii_case("Stringable")
+            && !def.interfaces.iter().any(|i| i.eq_ignore_ascii_case("Stringable"));
+        if stringable {
+            def.interfaces.push("Stringable".into());
+        }
+        (self.class, self.in_trait) = outer;
+        self.line = line;
+        Ok(Rc::new(def))
+    }
+
+    fn resolve_adaptation(&self, a: &TraitAdaptation) -> TraitAdaptation {
+        match a {
+            TraitAdaptation::Insteadof { trait_name, method, excluded } => TraitAdaptation::Insteadof {
+                trait_name: self.names.class(trait_name),
+                method: method.clone(),
+                excluded: excluded.iter().map(|t| self.names.class(t)).collect(),
+            },
+            TraitAdaptation::Alias { trait_name, method, alias, visibility } => TraitAdaptation::Alias {
+                trait_name: trait_name.as_ref().map(|t| self.names.class(t)),
+                method: method.clone(),
+                alias: alias.clone(),
+                visibility: *visibility,
+            },
+        }
+    }
+
+    /// Compiles the constant initializer of an enum case, checking the
+    /// case value against the backing type where it is a literal.
+    fn compile_enum_case(
+        &mut self,
+        def: &ClassDef,
+        name: &str,
+        value: Option<&Expr>,
+        seen: &mut Vec<(Value, String)>,
+    ) -> Result<Rc<Function>, String> {
+        let mut c = self.nested();
+        match (&def.backing_type, value) {
+            (None, Some(_)) => {
+                return Err(format!("Case {} of non-backed enum {} must not have a value", name, def.name));
+            }
+            (Some(_), None) => {
+                return Err(format!("Case {} of backed enum {} must have a value", name, def.name));
+            }
+            (Some(ty), Some(e)) => {
+                let literal = match e {
+                    Expr::Integer(n) => Some((Value::Int(*n), "int")),
+                    Expr::String(s) => Some((Value::from(s.as_str()), "string")),
+                    _ => None,
+                };
+                if let Some((v, case_ty)) = literal {
+                    if case_ty != &**ty {
+                        return Err(format!("Enum case type {} does not match enum backing type {}", case_ty, ty));
+                    }
+                    if let Some((_, other)) = seen.iter().find(|(w, _)| strict_equals(&v, w)) {
+                        return Err(format!("Duplicate value in enum {} for cases {} and {}", def.name, other, name));
+                    }
+                    seen.push((v, name.to_string()));
+                }
+                c.compile_expr(e)?;
+            }
+            (None, None) => {}
+        }
+        c.emit(Op::NewEnumCase(name.into()));
+        c.emit(Op::Return);
+        Ok(c.finish("{constant}", Vec::new()))
+    }
+
+    /// Adds `cases()`, and `from()`/`tryFrom()` for backed enums, and
+    /// rejects the magic methods enums may not declare.
+    fn add_enum_methods(&mut self, def: &mut ClassDef) -> Result<(), String> {
+        const FORBIDDEN: &[&str] = &[
+            "__construct", "__destruct", "__clone", "__get", "__set", "__unset", "__isset",
+            "__tostring", "__debuginfo", "__serialize", "__unserialize", "__sleep", "__wakeup",
+            "__set_state",
+        ];
+        for m in &def.methods {
+            if FORBIDDEN.contains(&m.name.to_ascii_lowercase().as_str()) {
+                return Err(format!("Enum {} cannot include magic method {}", def.name, m.name));
+            }
+        }
+
+        let mut builtins = vec![("cases", vec![Op::EnumCases])];
+        if def.backing_type.is_some() {
+            builtins.push(("from", vec![Op::LoadVar("value".into()), Op::EnumFrom(false)]));
+            builtins.push(("tryFrom", vec![Op::LoadVar("value".into()), Op::EnumFrom(true)]));
+        }
+        for (name, ops) in builtins {
+            let mut c = self.nested();
+            let params = if ops.len() > 1 { vec![Param::new("value")] } else { Vec::new() };
+            for op in ops {
+                c.emit(op);
+            }
+            c.emit(Op::Return);
+            let mut func = c.finish(name, params);
+            Rc::get_mut(&mut func).unwrap().builtin = true;
+            def.methods.push(MethodDef {
+                name: name.into(),
+                func,
+                visibility: Visibility::Public,
+                is_static: true,
+                is_abstract: false,
+                is_final: false,
+            });
+        }
+        Ok(())
+    }
+
+    // -- Statements -------------------------------------
+
+    fn compile_block(&mut self, block: &Block) -> Result<(), String> {
+        for stmt in block {
+            self.compile_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn compile_stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        if limits::stack_exhausted() {
+            return Err("Maximum nesting level reached".to_string());
+        }
+        self.line = stmt.line;
+        match &stmt.kind {
+            StmtKind::Echo(exprs) => {
+                for e in exprs {
+                    self.compile_expr(e)?;
+                    self.emit(Op::Echo);
+                }
+            }
+            StmtKind::Expr(e) => {
+                self.compile_expr(e)?;
+                self.emit(Op::Pop);
+            }
+            StmtKind::If { condition, then_block, elseif_blocks, else_block } => {
+                let mut end_jumps = Vec::new();
+                self.compile_expr(condition)?;
+                let mut next = self.emit(Op::JumpIfFalse(0));
+                self.compile_block(then_block)?;
+                for (cond, block) in elseif_blocks {
+                    end_jumps.push(self.emit(Op::Jump(0)));
+                    self.patch(next);
+                    self.compile_expr(cond)?;
+                    next = self.emit(Op::JumpIfFalse(0));
+                    self.compile_block(block)?;
+                }
+                if let Some(block) = else_block {
+                    end_jumps.push(self.emit(Op::Jump(0)));
+                    self.patch(next);
+                    self.compile_block(block)?;
+                } else {
+                    self.patch(next);
+                }
+                for j in end_jumps {
+                    self.patch(j);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                let start = self.here();
+                self.compile_expr(condition)?;
+                let exit = self.emit(Op::JumpIfFalse(0));
+                self.compile_loop_body(body)?;
+                self.emit(Op::Jump(start));
+                self.patch(exit);
+                self.end_loop(start);
+            }
+            StmtKind::For { init, condition, update, body } => {
+                if let Some(e) = init {
+                    self.compile_expr(e)?;
+                    self.emit(Op::Pop);
+                }
+                let start = self.here();
+                let exit = match condition {
+                    Some(e) => {
+                        self.compile_expr(e)?;
+                        Some(self.emit(Op::JumpIfFalse(0)))
+                    }
+                    None => None,
+                };
+                self.compile_loop_body(body)?;
+                let next = self.here();
+                if let Some(e) = update {
+                    self.compile_expr(e)?;
+                    self.emit(Op::Pop);
+                }
+                self.emit(Op::Jump(start));
+                if let Some(exit) = exit {
+                    self.patch(exit);
+                }
+                self.end_loop(next);
+            }
+            StmtKind::Foreach { subject, key, value, by_ref, body } => {
+                self.compile_foreach(subject, key.as_ref(), value, *by_ref, body)?;
+            }
+            StmtKind::FunctionDef { name, params, return_type, body } => {
+                let f = self.compile_function(&self.names.declare(name), params, return_type.as_ref(), body)?;
+                self.emit(Op::DeclareFunction(f));
+            }
+            StmtKind::Namespace { .. } => return Err("Namespace declarations cannot be nested".to_string()),
+            StmtKind::Use(items) => {
+                let names = Rc::make_mut(&mut self.names);
+                for item in items {
+                    names.import(item)?;
+                }
+            }
+            StmtKind::Declare(directives) => self.declare(directives, false)?,
+            StmtKind::ClassDecl(decl) => {
+                let def = self.compile_class(decl)?;
+                self.emit(Op::DeclareClass(def));
+            }
+            StmtKind::Return(e) => {
+                if let Err(e) = self.check_return(e.as_ref()) {
+                    self.return_error.get_or_insert(e);
+                }
+                match e {
+                    Some(e) => self.compile_expr(e)?,
+                    None => { self.emit(Op::Const(Value::Null)); }
+                }
+                self.emit(Op::Return);
+            }
+            StmtKind::Try { body, catches, finally } => self.compile_try(body, catches, finally.as_ref())?,
+            StmtKind::Const(_) => return Err("syntax error, unexpected token \"const\"".to_string()),
+            StmtKind::Global(names) => {
+                for name in names {
+                    self.emit(Op::BindGlobal(name.as_str().into()));
+                }
+            }
+            StmtKind::StaticVar(vars) => {
+                for (name, init) in vars {
+                    let name: Rc<str> = name.as_str().into();
+                    let bind = self.emit(Op::BindStatic(name.clone(), 0));
+                    if let Some(init) = init {
+                        self.compile_expr(init)?;
+                        self.emit(Op::Assign(Rc::new(LValue { base: LBase::Var(name), steps: Vec::new() })));
+                        self.emit(Op::Pop);
+                    }
+                    self.patch(bind);
+                }
+            }
+            StmtKind::Unset(places) => {
+                for place in places {
+                    let lv = match place {
+                        Expr::Variable(name) if name == "this" => return Err("Cannot unset $this".to_string()),
+                        Expr::ArrayAccess { index: None, .. } => return Err("Cannot use [] for unsetting".to_string()),
+                        _ if is_referenceable(place) => self.compile_place(place)?,
+                        _ => return Err("Cannot use unset() on the result of an expression".to_string()),
+                    };
+                    self.emit(Op::Unset(Rc::new(lv)));
+                }
+            }
+            StmtKind::Break(levels) => self.compile_break(*levels, true)?,
+            StmtKind::Continue(levels) => self.compile_break(*levels, false)?,
+        }
+        Ok(())
+    }
+
+    /// Compiles a loop body; the loop stays open for `break` and
+    /// `continue` until `end_loop`.
+    /// `foreach`: the value is assigned before the key, and a destructuring
+    /// target takes the elements of the current value.
+    fn compile_foreach(
+        &mut self,
+        subject: &Expr,
+        key: Option<&Expr>,
+        value: &Expr,
+        by_ref: bool,
+        body: &Block,
+    ) -> Result<(), String> {
+        if let Expr::List(items) = value {
+            check_list(items)?;
+        }
+        let slot = self.iters;
+        self.iters += 1;
+        let init = if by_ref && is_place(subject) {
+            let lv = self.compile_place(subject)?;
+            self.emit(Op::IterInitRef(slot, Rc::new(lv), 0))
+        } else {
+            self.compile_expr(subject)?;
+            self.emit(Op::IterInit(slot, by_ref, 0))
+        };
+        let start = self.emit(Op::IterFetch(slot, 0, key.is_some()));
+        match value {
+            Expr::List(items) if by_ref || list_has_ref(items) => {
+                let tmp = self.temp_name();
+                let lv = Rc::new(LValue { base: LBase::Var(tmp.clone()), steps: Vec::new() });
+                self.emit(Op::IterBind(slot, lv.clone()));
+                self.destructure_ref(items, &Expr::Variable(tmp.to_string()))?;
+                self.emit(Op::Unset(lv));
+            }
+            Expr::List(items) => {
+                self.emit(Op::IterValue(slot));
+                let tmp = self.temp_var();
+                self.destructure(items, &tmp)?;
+                self.emit(Op::Unset(Rc::new(LValue { base: LBase::Var(tmp), steps: Vec::new() })));
+            }
+            target if by_ref => {
+                let lv = self.compile_lvalue(target)?;
+                self.emit(Op::IterBind(slot, Rc::new(lv)));
+            }
+            target => {
+                let lv = self.compile_lvalue(target)?;
+                self.emit(Op::IterValue(slot));
+                self.emit(Op::Assign(Rc::new(lv)));
+                self.emit(Op::Pop);
+            }
+        }
+        if let Some(key) = key {
+            let lv = self.compile_lvalue(key)?;
+            self.emit(Op::IterKey(slot));
+            self.emit(Op::Assign(Rc::new(lv)));
+            self.emit(Op::Pop);
+        }
+        self.compile_loop_body(body)?;
+        self.emit(Op::Jump(start));
+        self.patch(init);
+        self.patch(start);
+        self.end_loop(start);
+        self.emit(Op::IterFree(slot));
+        Ok(())
+    }
+
+    fn compile_loop_body(&mut self, body: &Block) -> Result<(), String> {
+        self.loops.push(Loop { breaks: Vec::new(), continues: Vec::new(), finally_depth: self.finally_depth });
+        self.compile_block(body)
+    }
+
+    /// Patches the loop's `break`s to here and its `continue`s to `next`.
+    fn end_loop(&mut self, next: usize) {
+        let l = self.loops.pop().unwrap();
+        for at in l.breaks {
+            self.patch(at);
+        }
+        for at in l.continues {
+            self.patch_to(at, next);
+        }
+    }
+
+    fn compile_break(&mut self, levels: usize, is_break: bool) -> Result<(), String> {
+        let keyword = if is_break { "break" } else { "continue" };
+        if levels == 0 {
+            return Err(format!("'{}' operator accepts only positive integers", keyword));
+        }
+        if self.loops.is_empty() {
+            return Err(format!("'{}' not in the 'loop' or 'switch' context", keyword));
+        }
+        if levels > self.loops.len() {
+            return Err(format!("Cannot '{}' {} level{}", keyword, levels, if levels == 1 { "" } else { "s" }));
+        }
+        let i = self.loops.len() - levels;
+        let op = if self.finally_depth > self.loops[i].finally_depth { Op::Leave(0) } else { Op::Jump(0) };
+        let at = self.emit(op);
+        let l = &mut self.loops[i];
+        if is_break { l.breaks.push(at) } else { l.continues.push(at) }
+        Ok(())
+    }
+
+    /// The `try` body is followed by the `catch` clauses, which test the
+    /// thrown exception in turn and rethrow it if none matches, and the
+    /// `finally` block. Normal completion of the body or a clause enters
+    /// the `finally` block; the VM routes everything else using the
+    /// function's `TryBlock`s.
+    fn compile_try(&mut self, body: &Block, catches: &[CatchClause], finally: Option<&Block>) -> Result<(), String> {
+        let has_finally = finally.is_some();
+        let exit = || if has_finally { Op::EnterFinally(0) } else { Op::Jump(0) };
+        self.finally_depth += has_finally as usize;
+        let start = self.here();
+        self.compile_block(body)?;
+        let mut exits = vec![self.emit(exit())];
+        let catch_start = self.here();
+        for c in catches {
+            self.line = c.line;
+            let next = self.emit(Op::Catch(c.types.iter().map(|t| self.names.class(t).into()).collect(), 0));
+            if let Some(var) = &c.var {
+                self.emit(Op::Assign(Rc::new(LValue { base: LBase::Var(var.as_str().into()), steps: Vec::new() })));
+            }
+            self.emit(Op::Pop);
+            self.compile_block(&c.body)?;
+            exits.push(self.emit(exit()));
+            self.patch(next);
+        }
+        if !catches.is_empty() {
+            self.emit(Op::Throw);
+        }
+        let finally_start = self.here();
+        for at in exits {
+            self.patch(at);
+        }
+        if let Some(block) = finally {
+            self.compile_block(block)?;
+            self.emit(Op::FinallyEnd);
+        }
+        self.finally_depth -= has_finally as usize;
+        self.try_blocks.push(TryBlock {
+            start,
+            catch_start,
+            finally_start,
+            end: self.here(),
+            has_catch: !catches.is_empty(),
+            has_finally,
+        });
+        Ok(())
+    }
+
+    // -- Expressions ------------------------------------
+
+    /// Checks a `return` statement against the declared return type.
+    fn check_return(&self, value: Option<&Expr>) -> Result<(), String> {
+        let Some(ty) = &self.return_type else { return Ok(()) };
+        let members = type_members(ty);
+        if members == ["never"] {
+            return Err("A never-returning function must not return".to_string());
+        }
+        match value {
+            Some(e) if members == ["void"] => Err(if matches!(e, Expr::Null) {
+                "A void function must not return a value (did you mean \"return;\" instead of \"return null;\"?)"
+                    .to_string()
+            } else {
+                "A void function must not return a value".to_string()
+            }),
+            None if members != ["void"] => Err(if members.iter().any(|m| m == "null") {
+                "A function with return type must return a value (did you mean \"return null;\" instead of \"return;\"?)"
+                    .to_string()
+            } else {
+                "A function with return type must return a value".to_string()
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn compile_args(&mut self, args: &[Arg]) -> Result<(), String> {
+        for a in args {
+            let name: Option<Rc<str>> = a.name.as_deref().map(Into::into);
+            if a.unpack {
+                self.compile_expr(&a.value)?;
+                self.emit(Op::SendUnpack);
+            } else if matches!(a.value, Expr::ArrayAccess { index: None, .. }) {
+                // `$a[]` can only be passed by reference
+                let lv = self.compile_lvalue(&a.value)?;
+                self.emit(Op::SendRef(Rc::new(lv), name));
+            } else if is_referenceable(&a.value) {
+                let by_val = self.emit(Op::JumpIfArgByVal(name.clone(), 0));
+                let lv = self.compile_lvalue(&a.value)?;
+                self.emit(Op::SendRef(Rc::new(lv), name.clone()));
+                let end = self.emit(Op::Jump(0));
+                self.patch(by_val);
+                self.compile_expr(&a.value)?;
+                self.emit(Op::Send(name));
+                self.patch(end);
+            } else {
+                self.compile_expr(&a.value)?;
+                self.emit(Op::Send(name));
+            }
+        }
+        self.emit(Op::DoCall);
+        Ok(())
+    }
+
+    fn class_operand(&mut self, class: &ClassRef) -> Result<ClassOperand, String> {
+        Ok(match class {
+            ClassRef::Name(n) => ClassOperand::Named(self.names.class(n).into()),
+            ClassRef::Expr(e) => {
+                self.compile_expr(e)?;
+                ClassOperand::Dynamic
+            }
+        })
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), String> {
+        if limits::stack_exhausted() {
+            return Err("Maximum nesting level reached".to_string());
+        }
+        match expr {
+            Expr::Integer(n) => { self.emit(Op::Const(Value::Int(*n))); }
+            Expr::Float(f) => { self.emit(Op::Const(Value::Float(*f))); }
+            Expr::String(s) => { self.emit(Op::Const(Value::from(s.as_str()))); }
+            Expr::Bool(b) => { self.emit(Op::Const(Value::Bool(*b))); }
+            Expr::Null => { self.emit(Op::Const(Value::Null)); }
+            Expr::Variable(name) => { self.emit(Op::LoadVar(name.as_str().into())); }
+
+            Expr::BinaryOp { left, op: op @ (BinOp::And | BinOp::Or), right } => {
+                // short-circuit; the result is always a bool
+                let (jump, short): (fn(usize) -> Op, bool) = match op {
+                    BinOp::And => (Op::JumpIfFalse, false),
+                    _ => (Op::JumpIfTrue, true),
+                };
+                self.compile_expr(left)?;
+                let j1 = self.emit(jump(0));
+                self.compile_expr(right)?;
+                let j2 = self.emit(jump(0));
+                self.emit(Op::Const(Value::Bool(!short)));
+                let end = self.emit(Op::Jump(0));
+                self.patch(j1);
+                self.patch(j2);
+                self.emit(Op::Const(Value::Bool(short)));
+                self.patch(end);
+            }
+            Expr::BinaryOp { left, op, right } => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.emit(Op::Binary(*op));
+            }
+            Expr::UnaryOp { op, expr } => {
+                self.compile_expr(expr)?;
+                self.emit(Op::Unary(*op));
+            }
+
+            Expr::Assign { target, value } => match &**target {
+                Expr::List(items) => self.compile_destructure(items, value)?,
+                _ => {
+                    let lv = self.compile_lvalue(target)?;
+                    self.compile_expr(value)?;
+                    self.emit(Op::Assign(Rc::new(lv)));
+                }
+            },
+            Expr::List(items) if items.iter().any(Option::is_none) => {
+                return Err("Cannot use empty array elements in arrays".to_string());
+            }
+            Expr::List(_) => return Err("Cannot use list() as standalone expression".to_string()),
+
+            Expr::AssignRef { target, source } => {
+                let target = self.compile_lvalue(target)?;
+                let source = self.compile_lvalue(source)?;
+                self.emit(Op::AssignRef(Rc::new(target), Rc::new(source)));
+            }
+
+            Expr::FunctionCall { name, args } => {
+                let (name, fallback) = self.names.function_or_const(name, false);
+                self.emit(Op::InitCall(name, fallback));
+                self.compile_args(args)?;
+            }
+            Expr::Call { callee, args } => {
+                self.compile_expr(callee)?;
+                self.emit(Op::InitDynamicCall);
+                self.compile_args(args)?;
+            }
+            Expr::FirstClassCallable(call) => {
+                match &**call {
+                    Expr::FunctionCall { name, .. } => {
+                        let (name, fallback) = self.names.function_or_const(name, false);
+                        self.emit(Op::InitCall(name, fallback));
+                    }
+                    Expr::Call { callee, .. } => {
+                        self.compile_expr(callee)?;
+                        self.emit(Op::InitDynamicCall);
+                    }
+                    Expr::MethodCall { object, method, .. } => {
+                        self.compile_expr(object)?;
+                        self.emit(Op::InitMethodCall(method.as_str().into()));
+                    }
+                    Expr::StaticCall { class, method, .. } => {
+                        let class = self.class_operand(class)?;
+                        self.emit(Op::InitStaticCall(class, method.as_str().into()));
+                    }
+                    _ => unreachable!("first-class callable of a non-call"),
+                }
+                self.emit(Op::ClosureFromCall);
+            }
+            Expr::Closure(c) => {
+                let func = self.compile_function(&self.names.declare("{closure}"), &c.params, c.return_type.as_ref(), &c.body)?;
+                let uses = if c.is_arrow {
+                    arrow_captures(c).into_iter().map(|v| (v.as_str().into(), false)).collect()
+                } else {
+                    c.uses.iter().map(|u| (u.name.as_str().into(), u.by_ref)).collect()
+                };
+                self.emit(Op::Closure(Rc::new(ClosureDef {
+                    func,
+                    uses,
+                    is_static: c.is_static,
+                    is_arrow: c.is_arrow,
+                })));
+            }
+
+            Expr::ArrayAccess { array, index } => {
+                let index = index.as_ref().ok_or("Cannot use [] for reading")?;
+                self.compile_expr(array)?;
+                self.compile_expr(index)?;
+                self.emit(Op::FetchDim);
+            }
+
+            Expr::ArrayLiteral(entries) => {
+                self.emit(Op::NewArray);
+                for entry in entries {
+                    if entry.unpack {
+                        self.compile_expr(&entry.value)?;
+                        self.emit(Op::ArrayUnpack);
+                        continue;
+                    }
+                    if entry.by_ref {
+                        if !is_place(&entry.value) {
+                            return Err("Cannot assign reference to non referenceable value".to_string());
+                        }
+                        if let Some(k) = &entry.key {
+                            self.compile_expr(k)?;
+                        }
+                        let lv = self.compile_lvalue(&entry.value)?;
+                        self.emit(Op::ArrayRef(Rc::new(lv), entry.key.is_some()));
+                        continue;
+                    }
+                    match &entry.key {
+                        Some(k) => {
+                            self.compile_expr(k)?;
+                            self.compile_expr(&entry.value)?;
+                            self.emit(Op::ArrayInsert);
+                        }
+                        None => {
+                            self.compile_expr(&entry.value)?;
+                            self.emit(Op::ArrayPush);
+                        }
+                    }
+                }
+            }
+
+            Expr::InstanceOf { expr, class } => {
+                if matches!(**expr, Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null) {
+                    return Err("instanceof expects an object instance, constant given".to_string());
+                }
+                self.compile_expr(expr)?;
+                let class = self.class_operand(class)?;
+                self.emit(Op::InstanceOf(class));
+            }
+            Expr::New { class, args } => {
+                let class = self.class_operand(class)?;
+                self.emit(Op::New(class));
+                self.compile_args(args)?;
+                self.emit(Op::Pop); // constructor result
+            }
+            Expr::PropertyFetch { object, property } => {
+                self.compile_expr(object)?;
+                self.emit(Op::FetchProp(property.as_str().into()));
+            }
+            Expr::StaticPropertyFetch { class, property } => {
+                let class = self.class_operand(class)?;
+                self.emit(Op::FetchStaticProp(class, property.as_str().into()));
+            }
+            Expr::MethodCall { object, method, args } => {
+                self.compile_expr(object)?;
+                self.emit(Op::InitMethodCall(method.as_str().into()));
+                self.compile_args(args)?;
+            }
+            Expr::StaticCall { class, method, args } => {
+                let class = self.class_operand(class)?;
+                self.emit(Op::InitStaticCall(class, method.as_str().into()));
+                self.compile_args(args)?;
+            }
+            Expr::ClassConstFetch { class, name } => {
+                let class = self.class_operand(class)?;
+                self.emit(Op::FetchClassConst(class, name.as_str().into()));
+            }
+            Expr::ConstFetch(name) => match self.magic_constant(name) {
+                Some(v) => {
+                    self.emit(Op::Const(v));
+                }
+                // in a trait, the class using it
+                None if name.eq_ignore_ascii_case("__CLASS__") => {
+                    self.emit(Op::FetchClassConst(ClassOperand::Named("self".into()), "class".into()));
+                }
+                None => {
+                    let (name, fallback) = self.names.function_or_const(name, true);
+                    self.emit(Op::FetchConst(name, fallback));
+                }
+            },
+            Expr::Include(kind, path) => {
+                self.compile_expr(path)?;
+                self.emit(Op::Include(*kind));
+            }
+            Expr::Clone(e) => {
+                self.compile_expr(e)?;
+                self.emit(Op::Clone);
+            }
+            Expr::Throw(e) => {
+                self.compile_expr(e)?;
+                self.emit(Op::Throw);
+            }
+            Expr::Yield { key, value } => {
+                self.start_generator()?;
+                if let Some(k) = key {
+                    self.compile_expr(k)?;
+                }
+                match value {
+                    Some(v) => self.compile_expr(v)?,
+                    None => {
+                        self.emit(Op::Const(Value::Null));
+                    }
+                }
+                self.emit(Op::Yield(key.is_some()));
+            }
+            Expr::YieldFrom(e) => {
+                self.start_generator()?;
+                self.compile_expr(e)?;
+                self.emit(Op::YieldFrom);
+            }
+            Expr::Isset(places) => {
+                let mut unset_jumps = Vec::new();
+                for (i, place) in places.iter().enumerate() {
+                    if !is_place(place) {
+                        return Err("Cannot use isset() on the result of an expression \
+                            (you can use \"null !== expression\" instead)"
+                            .to_string());
+                    }
+                    let lv = self.compile_place(place)?;
+                    self.emit(Op::Isset(Rc::new(lv)));
+                    if i + 1 < places.len() {
+                        unset_jumps.push(self.emit(Op::JumpIfFalse(0)));
+                    }
+                }
+                if !unset_jumps.is_empty() {
+                    let end = self.emit(Op::Jump(0));
+                    for j in unset_jumps {
+                        self.patch(j);
+                    }
+                    self.emit(Op::Const(Value::Bool(false)));
+                    self.patch(end);
+                }
+            }
+            Expr::Empty(e) if is_place(e) => {
+                let lv = self.compile_place(e)?;
+                self.emit(Op::Empty(Rc::new(lv)));
+            }
+            Expr::Empty(e) => {
+                self.compile_expr(e)?;
+                self.emit(Op::Unary(UnaryOp::Not));
+            }
+        }
+        Ok(())
+    }
+
+    /// `[$a, 'k' => [$b, &$c]] = value`, which evaluates to the value.
+    /// The value is kept in a hidden variable while its elements are
+    /// assigned, unless references into it are taken.
+    fn compile_destructure(&mut self, items: &[Option<ArrayEntry>], value: &Expr) -> Result<(), String> {
+        check_list(items)?;
+        if list_has_ref(items) {
+            if !is_place(value) {
+                return Err("Cannot assign reference to non referenceable value".to_string());
+            }
+            self.destructure_ref(items, value)?;
+            return self.compile_expr(value);
+        }
+        self.compile_expr(value)?;
+        let tmp = self.temp_var();
+        self.destructure(items, &tmp)?;
+        self.emit(Op::LoadVar(tmp.clone()));
+        self.emit(Op::Unset(Rc::new(LValue { base: LBase::Var(tmp), steps: Vec::new() })));
+        Ok(())
+    }
+
+    fn start_generator(&mut self) -> Result<(), String> {
+        if self.function.is_empty() {
+            return Err("The \"yield\" expression can only be used inside a function".to_string());
+        }
+        self.is_generator = true;
+        Ok(())
+    }
+
+    /// A new hidden variable.
+    fn temp_name(&mut self) -> Rc<str> {
+        let name: Rc<str> = format!("\0tmp{}", self.temps).into();
+        self.temps += 1;
+        name
+    }
+
+    /// Stores the value on the stack in a new hidden variable.
+    fn temp_var(&mut self) -> Rc<str> {
+        let name = self.temp_name();
+        self.emit(Op::Assign(Rc::new(LValue { base: LBase::Var(name.clone()), steps: Vec::new() })));
+        self.emit(Op::Pop);
+        name
+    }
+
+    /// Assigns the elements of the array in `tmp` to the items.
+    fn destructure(&mut self, items: &[Option<ArrayEntry>], tmp: &Rc<str>) -> Result<(), String> {
+        for (i, item) in items.iter().enumerate() {
+            let Some(entry) = item else { continue };
+            let target = match &entry.value {
+                Expr::List(_) => None,
+                target => Some(self.compile_lvalue(target)?),
+            };
+            self.emit(Op::LoadVar(tmp.clone()));
+            match &entry.key {
+                Some(k) => self.compile_expr(k)?,
+                None => {
+                    self.emit(Op::Const(Value::Int(i as i64)));
+                }
+            }
+            self.emit(Op::FetchListDim);
+            match (target, &entry.value) {
+                (Some(lv), _) => {
+                    self.emit(Op::Assign(Rc::new(lv)));
+                    self.emit(Op::Pop);
+                }
+                (None, Expr::List(inner)) => {
+                    let inner_tmp = self.temp_var();
+                    self.destructure(inner, &inner_tmp)?;
+                    self.emit(Op::Unset(Rc::new(LValue { base: LBase::Var(inner_tmp), steps: Vec::new() })));
+                }
+                (None, _) => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Destructuring with `&` items, as assignments to and references of
+    /// the elements of the place.
+    fn destructure_ref(&mut self, items: &[Option<ArrayEntry>], source: &Expr) -> Result<(), String> {
+        for (i, item) in items.iter().enumerate() {
+            let Some(entry) = item else { continue };
+            let key = entry.key.clone().unwrap_or(Expr::Integer(i as i64));
+            let elem = Expr::ArrayAccess { array: Box::new(source.clone()), index: Some(Box::new(key)) };
+            let (target, value) = (Box::new(entry.value.clone()), Box::new(elem));
+            match &entry.value {
+                Expr::List(inner) => {
+                    self.destructure_ref(inner, &value)?;
+                    continue;
+                }
+                _ if entry.by_ref => self.compile_expr(&Expr::AssignRef { target, source: value })?,
+                _ => self.compile_expr(&Expr::Assign { target, value })?,
+            }
+            self.emit(Op::Pop);
+        }
+        Ok(())
+    }
+
+    /// The place read by `isset`, `empty` or `unset`, which unlike an
+    /// assignment target may be `$this`.
+    fn compile_place(&mut self, expr: &Expr) -> Result<LValue, String> {
+        match expr {
+            Expr::Variable(name) => Ok(LValue { base: LBase::Var(name.as_str().into()), steps: Vec::new() }),
+            Expr::ArrayAccess { index: None, .. } => Err("Cannot use [] for reading".to_string()),
+            _ => self.compile_lvalue(expr),
+        }
+    }
+
+    /// The value of a magic constant known at compile time.
+    fn magic_constant(&self, name: &str) -> Option<Value> {
+        match name.to_ascii_uppercase().as_str() {
+            "__FILE__" => Some(Value::from(&*self.file)),
+            "__DIR__" => {
+                let dir = std::path::Path::new(&*self.file).parent().map(|d| d.display().to_string());
+                Some(Value::from(dir.unwrap_or_default().as_str()))
+            }
+            "__NAMESPACE__" => Some(Value::from(self.names.namespace.as_str())),
+            "__FUNCTION__" => Some(Value::from(&*self.function)),
+            "__METHOD__" => Some(match &self.class {
+                Some(class) if !self.function.is_empty() && &*self.function != "{closure}" => {
+                    Value::from(format!("{}::{}", class, self.function))
+                }
+                _ => Value::from(&*self.function),
+            }),
+            "__CLASS__" if !self.in_trait => Some(Value::from(self.class.as_deref().unwrap_or(""))),
+            "__TRAIT__" => Some(Value::from(if self.in_trait { self.class.as_deref().unwrap_or("") } else { "" })),
+            _ => None,
+        }
+    }
+
+    /// Emits the dynamic parts of an assignment target and describes the
+    /// rest as an `LValue`.
+    fn compile_lvalue(&mut self, target: &Expr) -> Result<LValue, String> {
+        match target {
+            Expr::Variable(name) => {
+                if name == "this" {
+                    return Err("Cannot re-assign $this".to_string());
+                }
+                Ok(LValue { base: LBase::Var(name.as_str().into()), steps: Vec::new() })
+            }
+            Expr::ArrayAccess { array, index } => {
+                let mut lv = self.compile_lvalue_container(array)?;
+                match index {
+                    Some(i) => {
+                        self.compile_expr(i)?;
+                        lv.steps.push(LStep::Dim);
+                    }
+                    None => lv.steps.push(LStep::Append),
+                }
+                Ok(lv)
+            }
+            Expr::PropertyFetch { object, property } => {
+                let mut lv = self.compile_lvalue_container(object)?;
+                lv.steps.push(LStep::Prop(property.as_str().into()));
+                Ok(lv)
+            }
+            Expr::StaticPropertyFetch { class, property } => {
+                let class = self.class_operand(class)?;
+                Ok(LValue { base: LBase::StaticProp(class, property.as_str().into()), steps: Vec::new() })
+            }
+            _ => Err("Cannot assign to this expression".to_string()),
+        }
+    }
+
+    /// Like `compile_lvalue`, but for the container being written into,
+    /// which may also be any expression yielding an object (or `$this`).
+    fn compile_lvalue_container(&mut self, expr: &Expr) -> Result<LValue, String> {
+        match expr {
+            Expr::Variable(name) if name == "this" => {
+                Ok(LValue { base: LBase::Var(name.as_str().into()), steps: Vec::new() })
+            }
+            Expr::Variable(_)
+            | Expr::ArrayAccess { .. }
+            | Expr::PropertyFetch { .. }
+            | Expr::StaticPropertyFetch { .. } => self.compile_lvalue(expr),
+            _ => {
+                self.compile_expr(expr)?;
+                Ok(LValue { base: LBase::Stack, steps: Vec::new() })
+            }
+        }
+    }
+}
+
+/// Variables an arrow function captures: those its body uses, including
+/// through nested arrow functions, other than its own parameters.
+fn arrow_captures(c: &ClosureExpr) -> Vec<String> {
+    let mut vars = Vec::new();
+    for stmt in &c.body {
+        if let StmtKind::Return(Some(e)) = &stmt.kind {
+            collect_vars(e, &mut vars);
+        }
+    }
+    vars.retain(|v| v != "this" && !c.params.iter().any(|p| &p.name == v));
+    vars
+}
+
+fn collect_vars(expr: &Expr, out: &mut Vec<String>) {
+    fn add(out: &mut Vec<String>, name: &str) {
+        if !out.iter().any(|v| v == name) {
+            out.push(name.to_string());
+        }
+    }
+    fn class_ref(class: &ClassRef, out: &mut Vec<String>) {
+        if let ClassRef::Expr(e) = class {
+            collect_vars(e, out);
+        }
+    }
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null | Expr::ConstFetch(_) => {}
+        Expr::Variable(name) => add(out, name),
+        Expr::BinaryOp { left, right, .. } => {
+            collect_vars(left, out);
+            collect_vars(right, out);
+        }
+        Expr::UnaryOp { expr, .. } => collect_vars(expr, out),
+        Expr::Assign { target, value: other } | Expr::AssignRef { target, source: other } => {
+            collect_vars(target, out);
+            collect_vars(other, out);
+        }
+        Expr::FunctionCall { args, .. } => args.iter().for_each(|a| collect_vars(&a.value, out)),
+        Expr::Call { callee, args } => {
+            collect_vars(callee, out);
+            args.iter().for_each(|a| collect_vars(&a.value, out));
+        }
+        Expr::FirstClassCallable(call) => collect_vars(call, out),
+        Expr::Closure(c) if c.is_arrow => {
+            for v in arrow_captures(c) {
+                add(out, &v);
+            }
+        }
+        Expr::Closure(c) => {
+            for u in &c.uses {
+                add(out, &u.name);
+            }
+        }
+        Expr::ArrayAccess { array, index } => {
+            collect_vars(array, out);
+            if let Some(i) = index {
+                collect_vars(i, out);
+            }
+        }
+        Expr::List(items) => {
+            for e in items.iter().flatten() {
+                if let Some(k) = &e.key {
+                    collect_vars(k, out);
+                }
+                collect_vars(&e.value, out);
+            }
+        }
+        Expr::ArrayLiteral(entries) => {
+            for e in entries {
+                if let Some(k) = &e.key {
+                    collect_vars(k, out);
+                }
+                collect_vars(&e.value, out);
+            }
+        }
+        Expr::New { class, args } => {
+            class_ref(class, out);
+            args.iter().for_each(|a| collect_vars(&a.value, out));
+        }
+        Expr::InstanceOf { expr, class } => {
+            collect_vars(expr, out);
+            class_ref(class, out);
+        }
+        Expr::Yield { key, value } => {
+            key.iter().chain(value).for_each(|e| collect_vars(e, out));
+        }
+        Expr::YieldFrom(e) => collect_vars(e, out),
+        Expr::PropertyFetch { object, .. } => collect_vars(object, out),
+        Expr::StaticPropertyFetch { class, .. } | Expr::ClassConstFetch { class, .. } => class_ref(class, out),
+        Expr::MethodCall { object, args, .. } => {
+            collect_vars(object, out);
+            args.iter().for_each(|a| collect_vars(&a.value, out));
+        }
+        Expr::StaticCall { class, args, .. } => {
+            class_ref(class, out);
+            args.iter().for_each(|a| collect_vars(&a.value, out));
+        }
+        Expr::Include(_, e) | Expr::Throw(e) | Expr::Empty(e) | Expr::Clone(e) => collect_vars(e, out),
+        Expr::Isset(places) => places.iter().for_each(|p| collect_vars(p, out)),
+    }
+}
+
+/// Whether an argument names a place a reference can be taken to.
+fn is_referenceable(e: &Expr) -> bool {
+    match e {
+        Expr::Variable(name) => name != "this",
+        Expr::ArrayAccess { .. } | Expr::PropertyFetch { .. } | Expr::StaticPropertyFetch { .. } => true,
+        _ => false,
+    }
+}
+
+/// Rejects destructuring targets PHP does not allow.
+fn check_list(items: &[Option<ArrayEntry>]) -> Result<(), String> {
+    let entries: Vec<&ArrayEntry> = items.iter().flatten().collect();
+    if entries.is_empty() {
+        return Err("Cannot use empty list".to_string());
+    }
+    if entries.iter().any(|e| e.key.is_some()) && entries.iter().any(|e| e.key.is_none()) {
+        return Err("Cannot mix keyed and unkeyed array entries in assignments".to_string());
+    }
+    for e in entries {
+        match &e.value {
+            _ if e.unpack => return Err("Spread operator is not supported in assignments".to_string()),
+            Expr::List(inner) => check_list(inner)?,
+            Expr::Variable(name) if name == "this" => return Err("Cannot re-assign $this".to_string()),
+            v if is_place(v) => {}
+            _ => return Err("Assignments can only happen to writable values".to_string()),
+        }
+    }
+    Ok(())
+}
+
+fn list_has_ref(items: &[Option<ArrayEntry>]) -> bool {
+    items.iter().flatten().any(|e| match &e.value {
+        Expr::List(inner) => list_has_ref(inner),
+        _ => e.by_ref,
+    })
+}
+
+/// A generator's declared return type must accept the `Generator`.
+fn check_generator_type(ty: Option<&TypeHint>) -> Result<(), String> {
+    let Some(ty) = ty else { return Ok(()) };
+    let supertypes = ["generator", "iterator", "traversable", "iterable", "mixed"];
+    if type_members(ty).iter().any(|m| supertypes.contains(&m.as_str())) {
+        return Ok(());
+    }
+    Err(format!("Generator return type must be a supertype of Generator, {} given", ty))
+}
+
+/// Checks that an initializer is a constant expression. `new` is only
+/// allowed in some contexts, such as global constants.
+fn check_const_expr(e: &Expr, allow_new: bool) -> Result<(), String> {
+    let check = |e: &Expr| check_const_expr(e, allow_new);
+    match e {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null | Expr::ConstFetch(_) => Ok(()),
+        Expr::BinaryOp { left, right, .. } => check(left).and_then(|_| check(right)),
+        Expr::UnaryOp { op: UnaryOp::Negate | UnaryOp::Not, expr } => check(expr),
+        Expr::ArrayLiteral(entries) if !entries.iter().any(|e| e.by_ref) => {
+            for entry in entries {
+                if let Some(k) = &entry.key {
+                    check(k)?;
+                }
+                check(&entry.value)?;
+            }
+            Ok(())
+        }
+        Expr::ArrayAccess { array, index: Some(index) } => check(array).and_then(|_| check(index)),
+        Expr::ClassConstFetch { class: ClassRef::Name(n), .. } if n.eq_ignore_ascii_case("static") => {
+            Err("\"static::\" is not allowed in compile-time constants".to_string())
+        }
+        Expr::ClassConstFetch { class: ClassRef::Name(_), .. } => Ok(()),
+        // enum case properties, `Suit::Hearts->value`
+        Expr::PropertyFetch { object, .. } => check(object),
+        Expr::New { class: ClassRef::Name(_), args } if allow_new => {
+            if args.iter().any(|a| a.unpack) {
+                return Err("Argument unpacking in constant expressions is not supported".to_string());
+            }
+            args.iter().try_for_each(|a| check(&a.value))
+        }
+        Expr::New { .. } if !allow_new => Err("New expressions are not supported in this context".to_string()),
+        _ => Err("Constant expression contains invalid operations".to_string()),
+    }
+}
+
+/// Whether an expression names a variable, element or property.
+fn is_place(e: &Expr) -> bool {
+    matches!(
+        e,
+        Expr::Variable(_) | Expr::ArrayAccess { .. } | Expr::PropertyFetch { .. } | Expr::StaticPropertyFetch { .. }
+    )
+}
+
+/// Rejects type declarations PHP does not allow.
+fn check_type(ty: &TypeHint, is_return: bool) -> Result<(), String> {
+    let members = type_members(ty);
+    for (i, m) in members.iter().enumerate() {
+        if members[..i].contains(m) {
+            return Err(format!("Duplicate type {} is redundant", m));
+        }
+    }
+    for standalone in ["void", "never", "mixed"] {
+        if !members.iter().any(|m| m == standalone) {
+            continue;
+        }
+        if !is_return && standalone != "mixed" {
+            return Err(format!("{} cannot be used as a parameter type", standalone));
+        }
+        if members.len() > 1 {
+            return Err(match (standalone, ty) {
+                ("mixed", TypeHint::Nullable(_)) => {
+                    "Type mixed cannot be marked as nullable since mixed already includes null".to_string()
+                }
+                ("mixed", _) => "Type mixed can only be used as a standalone type".to_string(),
+                ("void", _) => "Void can only be used as a standalone type".to_string(),
+                _ => "never can only be used as a standalone type".to_string(),
+            });
+        }
+    }
+    let intersections: Vec<&TypeHint> = match ty {
+        TypeHint::Union(ts) => ts.iter().collect(),
+        t => vec![t],
+    };
+    for t in intersections {
+        if let TypeHint::Intersection(parts) = t {
+            for p in parts {
+                let name = p.to_string();
+                if BUILTIN_TYPES.contains(&name.to_ascii_lowercase().as_str()) {
+                    return Err(format!("Type {} cannot be part of an intersection type", name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks a literal default value against the parameter type.
+fn check_default(ty: &TypeHint, default: &Expr, param: &str) -> Result<(), String> {
+    let given = match default {
+        Expr::Integer(_) => "int",
+        Expr::Float(_) => "float",
+        Expr::String(_) => "string",
+        Expr::Bool(_) => "bool",
+        Expr::ArrayLiteral(_) => "array",
+        _ => return Ok(()),
+    };
+    let fits = type_members(ty).iter().any(|m| {
+        m == "mixed"
+            || m == given
+            || (given == "int" && m == "float")
+            || (given == "array" && m == "iterable")
+            || matches!((default, m.as_str()), (Expr::Bool(false), "false") | (Expr::Bool(true), "true"))
+    });
+    if fits {
+        return Ok(());
+    }
+    Err(format!("Cannot use {} as default value for parameter ${} of type {}", given, param, ty))
+}
+
+/// `ty` extended to accept null.
+fn nullable(ty: TypeHint) -> TypeHint {
+    if type_members(&ty).iter().any(|m| m == "null" || m == "mixed") {
+        return ty;
+    }
+    match ty {
+        TypeHint::Named(_) => TypeHint::Nullable(Box::new(ty)),
+        TypeHint::Union(mut ts) => {
+            ts.push(TypeHint::Named("null".to_string()));
+            TypeHint::Union(ts)
+        }
+        t => TypeHint::Union(vec![t, TypeHint::Named("null".to_string())]),
+    }
+}
+
+/// Renders a default value the way PHP shows it in signatures.
+fn render_default(e: &Expr) -> String {
+    match e {
+        Expr::Null => "null".to_string(),
+        Expr::Bool(b) => b.to_string(),
+        Expr::Integer(n) => n.to_string(),
+        Expr::Float(f) => format_float(*f, -1),
+        Expr::String(s) if s.len() > 10 => format!("'{}...'", &s[..s.floor_char_boundary(10)]),
+        Expr::String(s) => format!("'{}'", s),
+        Expr::ArrayLiteral(entries) if entries.is_empty() => "[]".to_string(),
+        Expr::ArrayLiteral(_) => "[...]".to_string(),
+        Expr::UnaryOp { op: UnaryOp::Negate, expr } if matches!(**expr, Expr::Integer(_) | Expr::Float(_)) => {
+            format!("-{}", render_default(expr))
+        }
+        Expr::ClassConstFetch { class: ClassRef::Name(c), name } => format!("{}::{}", c, name),
+        _ => "<expression>".to_string(),
+    }
+}
@@ -0,0 +1,158 @@
+use std::f64::consts;
+
+use crate::compiler::ClassOperand;
+use crate::vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
+use crate::value::Value;
+
+/// Constant names are case-sensitive, but their namespace is not.
+pub fn constant_key(name: &str) -> String {
+    let name = name.strip_prefix('\\').unwrap_or(name);
+    match name.rsplit_once('\\') {
+        Some((ns, short)) => format!("{}\\{}", ns.to_ascii_lowercase(), short),
+        None => name.to_string(),
+    }
+}
+
+/// `PHP_OS` and `PHP_OS_FAMILY` for the host.
+fn os_names() -> (&'static str, &'static str) {
+    match std::env::consts::OS {
+        "linux" => ("Linux", "Linux"),
+        "macos" => ("Darwin", "Darwin"),
+        "windows" => ("WINNT", "Windows"),
+        "freebsd" => ("FreeBSD", "BSD"),
+        "netbsd" => ("NetBSD", "BSD"),
+        "openbsd" => ("OpenBSD", "BSD"),
+        "solaris" => ("SunOS", "Solaris"),
+        _ => ("Unknown", "Unknown"),
+    }
+}
+
+impl Vm {
+    pub(crate) fn define_builtin_constants(&mut self) {
+        let (os, os_family) = os_names();
+        let constants = [
+            ("PHP_EOL", Value::from("\n")),
+            ("PHP_INT_MAX", Value::Int(i64::MAX)),
+            ("PHP_INT_MIN", Value::Int(i64::MIN)),
+            ("PHP_INT_SIZE", Value::Int(8)),
+            ("PHP_FLOAT_EPSILON", Value::Float(f64::EPSILON)),
+            ("PHP_FLOAT_MAX", Value::Float(f64::MAX)),
+            ("PHP_FLOAT_MIN", Value::Float(f64::MIN_POSITIVE)),
+            ("PHP_FLOAT_DIG", Value::Int(15)),
+            ("NAN", Value::Float(f64::NAN)),
+            ("INF", Value::Float(f64::INFINITY)),
+            ("PHP_VERSION", Value::from("8.3.0")),
+            ("PHP_MAJOR_VERSION", Value::Int(8)),
+            ("PHP_MINOR_VERSION", Value::Int(3)),
+            ("PHP_RELEASE_VERSION", Value::Int(0)),
+            ("PHP_VERSION_ID", Value::Int(80300)),
+            ("PHP_OS", Value::from(os)),
+            ("PHP_OS_FAMILY", Value::from(os_family)),
+            ("DIRECTORY_SEPARATOR", Value::from(std::path::MAIN_SEPARATOR_STR)),
+            ("PATH_SEPARATOR", Value::from(if cfg!(windows) { ";" } else { ":" })),
+            ("E_ERROR", Value::Int(1)),
+            ("E_WARNING", Value::Int(2)),
+            ("E_PARSE", Value::Int(4)),
+            ("E_NOTICE", Value::Int(8)),
+            ("E_CORE_ERROR", Value::Int(16)),
+            ("E_CORE_WARNING", Value::Int(32)),
+            ("E_COMPILE_ERROR", Value::Int(64)),
+            ("E_COMPILE_WARNING", Value::Int(128)),
+            ("E_USER_ERROR", Value::Int(256)),
+            ("E_USER_WARNING", Value::Int(512)),
+            ("E_USER_NOTICE", Value::Int(1024)),
+            ("E_STRICT", Value::Int(2048)),
+            ("E_RECOVERABLE_ERROR", Value::Int(4096)),
+            ("E_DEPRECATED", Value::Int(8192)),
+            ("E_USER_DEPRECATED", Value::Int(16384)),
+            ("E_ALL", Value::Int(32767)),
+            ("M_PI", Value::Float(consts::PI)),
+            ("M_E", Value::Float(consts::E)),
+            ("M_LOG2E", Value::Float(consts::LOG2_E)),
+            ("M_LOG10E", Value::Float(consts::LOG10_E)),
+            ("M_LN2", Value::Float(consts::LN_2)),
+            ("M_LN10", Value::Float(consts::LN_10)),
+            ("M_PI_2", Value::Float(consts::FRAC_PI_2)),
+            ("M_PI_4", Value::Float(consts::FRAC_PI_4)),
+            ("M_1_PI", Value::Float(consts::FRAC_1_PI)),
+            ("M_2_PI", Value::Float(consts::FRAC_2_PI)),
+            ("M_SQRTPI", Value::Float(1.772_453_850_905_516)),
+            ("M_2_SQRTPI", Value::Float(consts::FRAC_2_SQRT_PI)),
+            ("M_SQRT2", Value::Float(consts::SQRT_2)),
+            ("M_SQRT3", Value::Float(1.732_050_807_568_877_2)),
+            ("M_SQRT1_2", Value::Float(consts::FRAC_1_SQRT_2)),
+            ("M_LNPI", Value::Float(1.144_729_885_849_400_2)),
+            ("M_EULER", Value::Float(0.577_215_664_901_532_9)),
+        ];
+        for (name, v) in constants {
+            self.constants.insert(constant_key(name), v);
+        }
+    }
+
+    /// Defines a global constant; warns and returns false when it exists.
+    pub(crate) fn define_constant(&mut self, name: &str, value: Value) -> bool {
+        let key = constant_key(name);
+        if self.constants.contains_key(&key) {
+            self.warn(&format!("Constant {} already defined", name.strip_prefix('\\').unwrap_or(name)));
+            return false;
+        }
+        self.constants.insert(key, value);
+        true
+    }
+}
+
+pub const FUNCTIONS: &[(&str, &[&str], NativeFn)] = &[
+    ("define", &["constant_name", "value", "case_insensitive"], define),
+    ("defined", &["constant_name"], defined),
+    ("constant", &["name"], constant),
+];
+
+fn string_arg(vm: &mut Vm, v: Value, func: &str, param: &str) -> VmResult<String> {
+    let v = vm.coerce_arg(v, "string", func, 1, param)?;
+    Ok(String::from_utf8_lossy(&v.scalar_to_bytes().unwrap()).into_owned())
+}
+
+/// `define(string $constant_name, mixed $value, bool $case_insensitive = false): bool`
+fn define(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("define", &args, 2, 3)?;
+    let mut args = args.into_iter();
+    let name = string_arg(vm, args.next().unwrap(), "define", "constant_name")?;
+    let value = args.next().unwrap();
+    if args.next().is_some_and(|v| v.to_bool()) {
+        vm.warn(
+            "define(): Argument #3 ($case_insensitive) is ignored since declaration of case-insensitive constants is no longer supported",
+        );
+    }
+    if name.contains("::") {
+        return Err(VmError::Error {
+            class: "ValueError",
+            message: "define(): Argument #1 ($constant_name) cannot be a class constant".to_string(),
+        });
+    }
+    Ok(Value::Bool(vm.define_constant(&name, value)))
+}
+
+/// `defined(string $constant_name): bool`
+fn defined(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("defined", &args, 1, 1)?;
+    let name = string_arg(vm, args.into_iter().next().unwrap(), "defined", "constant_name")?;
+    let found = match name.split_once("::") {
+        Some((class, name)) => vm.lookup_class(class).is_ok_and(|c| c.find_constant(name).is_some()),
+        None => vm.constants.contains_key(&constant_key(&name)),
+    };
+    Ok(Value::Bool(found))
+}
+
+/// `constant(string $name): mixed`, which also reads class constants
+/// (`"Foo::BAR"`).
+fn constant(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("constant", &args, 1, 1)?;
+    let name = string_arg(vm, args.into_iter().next().unwrap(), "constant", "name")?;
+    if let Some((class, name)) = name.split_once("::") {
+        return vm.class_constant(&ClassOperand::Named(class.into()), name);
+    }
+    match vm.constants.get(&constant_key(&name)) {
+        Some(v) => Ok(v.clone()),
+        None => Err(VmError::error(format!("Undefined constant \"{}\"", name))),
+    }
+}
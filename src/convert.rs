@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::value::Value;
+
+/// Rust values that can be passed to PHP code.
+pub trait IntoPhp {
+    fn into_php(self) -> Value;
+}
+
+/// Rust values that can be read back from PHP values. Conversions are
+/// strict: only integral floats convert to integers, and nothing converts
+/// to a string but a string.
+pub trait FromPhp: Sized {
+    /// The error is a message such as `expected int, string given`.
+    fn from_php(v: &Value) -> Result<Self, String>;
+}
+
+fn expected<T>(ty: &str, v: &Value) -> Result<T, String> {
+    Err(format!("expected {}, {} given", ty, v.type_name()))
+}
+
+impl IntoPhp for Value {
+    fn into_php(self) -> Value {
+        self
+    }
+}
+
+impl IntoPhp for () {
+    fn into_php(self) -> Value {
+        Value::Null
+    }
+}
+
+impl IntoPhp for bool {
+    fn into_php(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl IntoPhp for i64 {
+    fn into_php(self) -> Value {
+        Value::Int(self)
+    }
+}
+
+impl IntoPhp for i32 {
+    fn into_php(self) -> Value {
+        Value::Int(self.into())
+    }
+}
+
+impl IntoPhp for f64 {
+    fn into_php(self) -> Value {
+        Value::Float(self)
+    }
+}
+
+impl IntoPhp for &str {
+    fn into_php(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl IntoPhp for String {
+    fn into_php(self) -> Value {
+        Value::from(self)
+    }
+}
+
+impl<T: IntoPhp> IntoPhp for Option<T> {
+    fn into_php(self) -> Value {
+        self.map_or(Value::Null, IntoPhp::into_php)
+    }
+}
+
+/// A list: an array with keys 0, 1, 2...
+impl<T: IntoPhp> IntoPhp for Vec<T> {
+    fn into_php(self) -> Value {
+        let mut arr = PhpArray::new();
+        for v in self {
+            arr.push(v.into_php()).expect("a new list has room");
+        }
+        Value::from(arr)
+    }
+}
+
+/// An array with string keys; numeric strings become integer keys as in
+/// PHP. The order is that of the map's iteration.
+impl<T: IntoPhp> IntoPhp for HashMap<String, T> {
+    fn into_php(self) -> Value {
+        let mut arr = PhpArray::new();
+        for (k, v) in self {
+            arr.insert(ArrayKey::from(k.as_str()), v.into_php());
+        }
+        Value::from(arr)
+    }
+}
+
+impl FromPhp for Value {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        Ok(v.deref())
+    }
+}
+
+impl FromPhp for () {
+    fn from_php(_: &Value) -> Result<Self, String> {
+        Ok(())
+    }
+}
+
+impl FromPhp for bool {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        match v.deref() {
+            Value::Bool(b) => Ok(b),
+            v => expected("bool", &v),
+        }
+    }
+}
+
+impl FromPhp for i64 {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        match v.deref() {
+            Value::Int(n) => Ok(n),
+            Value::Float(f) if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 => Ok(f as i64),
+            v => expected("int", &v),
+        }
+    }
+}
+
+impl FromPhp for i32 {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        let n = i64::from_php(v)?;
+        i32::try_from(n).map_err(|_| format!("{} is out of range for i32", n))
+    }
+}
+
+impl FromPhp for f64 {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        match v.deref() {
+            Value::Float(f) => Ok(f),
+            Value::Int(n) => Ok(n as f64),
+            v => expected("float", &v),
+        }
+    }
+}
+
+impl FromPhp for String {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        match v.deref() {
+            Value::Str(s) => String::from_utf8(s.to_vec()).map_err(|_| "expected UTF-8 string".to_string()),
+            v => expected("string", &v),
+        }
+    }
+}
+
+impl<T: FromPhp> FromPhp for Option<T> {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        match v.deref() {
+            Value::Null => Ok(None),
+            v => T::from_php(&v).map(Some),
+        }
+    }
+}
+
+/// The values of an array, in order.
+impl<T: FromPhp> FromPhp for Vec<T> {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        match v.deref() {
+            Value::Array(arr) => arr.iter().map(|(_, v)| T::from_php(v)).collect(),
+            v => expected("array", &v),
+        }
+    }
+}
+
+/// The entries of an array; integer keys become their decimal strings.
+impl<T: FromPhp> FromPhp for HashMap<String, T> {
+    fn from_php(v: &Value) -> Result<Self, String> {
+        let Value::Array(arr) = v.deref() else { return expected("array", v) };
+        arr.iter()
+            .map(|(k, v)| {
+                let k = match k {
+                    ArrayKey::Int(n) => n.to_string(),
+                    ArrayKey::Str(s) => String::from_utf8_lossy(s).into_owned(),
+                };
+                Ok((k, T::from_php(v)?))
+            })
+            .collect()
+    }
+}
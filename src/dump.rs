@@ -0,0 +1,459 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::ClassKind;
+use crate::class::Object;
+use crate::extension::{arg_bool, NativeFunction};
+use crate::limits::Growth;
+use crate::value::{format_float, ObjRef, Value};
+use crate::vm::{Callee, Vm, VmResult};
+
+/// `var_dump()`, `print_r()`, `var_export()` and `debug_zval_dump()`,
+/// writing exactly what PHP's do.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction { name: "var_dump", params: &["mixed value", "mixed ...values"], func: var_dump },
+    NativeFunction { name: "print_r", params: &["mixed value", "bool return = false"], func: print_r },
+    NativeFunction { name: "var_export", params: &["mixed value", "bool return = false"], func: var_export },
+    NativeFunction { name: "debug_zval_dump", params: &["mixed value", "mixed ...values"], func: debug_zval_dump },
+];
+
+/// Splits a mangled property name into the class part (`*` for protected
+/// ones) and the name.
+fn unmangle(key: &[u8]) -> (Option<&[u8]>, &[u8]) {
+    if let Some(rest) = key.strip_prefix(b"\0")
+        && let Some(end) = rest.iter().position(|c| *c == 0)
+    {
+        return (Some(&rest[..end]), &rest[end + 1..]);
+    }
+    (None, key)
+}
+
+/// Escapes a string for a single-quoted PHP literal. NUL bytes are
+/// written as `' . "\0" . '`, as PHP does.
+fn quote(s: &[u8]) -> Vec<u8> {
+    let mut out = vec![b'\''];
+    for &c in s {
+        match c {
+            b'\'' | b'\\' => out.extend_from_slice(&[b'\\', c]),
+            0 => out.extend_from_slice(b"' . \"\\0\" . '"),
+            c => out.push(c),
+        }
+    }
+    out.push(b'\'');
+    out
+}
+
+fn enum_case(obj: &ObjRef) -> Option<Vec<u8>> {
+    let o = obj.borrow();
+    if o.class.kind != ClassKind::Enum {
+        return None;
+    }
+    o.props.get(&ArrayKey::from("name")).and_then(Value::scalar_to_bytes)
+}
+
+/// Walks values for the dumping functions, tracking the arrays and
+/// objects being written to detect recursion.
+struct Dumper {
+    out: Vec<u8>,
+    /// Written as it goes rather than returned, so output of
+    /// `__debugInfo()` lands where PHP puts it.
+    echo: bool,
+    arrays: Vec<*const PhpArray>,
+    objects: Vec<*const RefCell<Object>>,
+    /// The size of the result when it is returned.
+    growth: Growth,
+}
+
+/// Bytes collected before they are written out.
+const FLUSH_SIZE: usize = 65536;
+
+impl Dumper {
+    fn new(echo: bool) -> Dumper {
+        Dumper { out: Vec::new(), echo, arrays: Vec::new(), objects: Vec::new(), growth: Growth::new() }
+    }
+
+    /// Writes out what was collected when echoing, or counts it against
+    /// the memory limit when it is returned.
+    fn grow(&mut self, vm: &mut Vm) -> VmResult<()> {
+        if self.echo {
+            if self.out.len() >= FLUSH_SIZE {
+                self.flush(vm);
+            }
+            return Ok(());
+        }
+        self.growth.grow_to(vm, self.out.len())
+    }
+
+    fn write(&mut self, bytes: impl AsRef<[u8]>) {
+        self.out.extend_from_slice(bytes.as_ref());
+    }
+
+    fn spaces(&mut self, n: usize) {
+        self.out.resize(self.out.len() + n, b' ');
+    }
+
+    fn flush(&mut self, vm: &mut Vm) {
+        if self.echo {
+            vm.echo(&std::mem::take(&mut self.out));
+        }
+    }
+
+    /// The properties shown for an object, flushing first as the class
+    /// may print from `__debugInfo()`.
+    fn properties(&mut self, vm: &mut Vm, obj: &ObjRef) -> VmResult<PhpArray> {
+        self.flush(vm);
+        vm.debug_properties(obj)
+    }
+
+    /// Enters an array, or returns false if it is being written already.
+    fn enter_array(&mut self, arr: &Rc<PhpArray>) -> bool {
+        let p = Rc::as_ptr(arr);
+        if self.arrays.contains(&p) {
+            return false;
+        }
+        self.arrays.push(p);
+        true
+    }
+
+    fn enter_object(&mut self, obj: &ObjRef) -> bool {
+        let p = Rc::as_ptr(obj);
+        if self.objects.contains(&p) {
+            return false;
+        }
+        self.objects.push(p);
+        true
+    }
+
+    /// The `[key]=>` line of `var_dump()`; object keys show visibility.
+    fn dump_key(&mut self, k: &ArrayKey, level: usize, is_object: bool) {
+        self.spaces(level + 1);
+        match k {
+            ArrayKey::Int(n) => self.write(format!("[{}]=>\n", n)),
+            ArrayKey::Str(s) => {
+                self.write("[\"");
+                match unmangle(s) {
+                    (Some(b"*"), name) if is_object => {
+                        self.write(name);
+                        self.write("\":protected");
+                    }
+                    (Some(class), name) if is_object => {
+                        self.write(name);
+                        self.write("\":\"");
+                        self.write(class);
+                        self.write("\":private");
+                    }
+                    _ => {
+                        self.write(s.as_slice());
+                        self.write("\"");
+                    }
+                }
+                self.write("]=>\n");
+            }
+        }
+    }
+
+    /// `var_dump()`, or with `refcounts` `debug_zval_dump()`; `level`
+    /// starts at 1 and grows by 2 per nesting.
+    fn dump(&mut self, vm: &mut Vm, v: &Value, level: usize, refcounts: bool) -> VmResult<()> {
+        vm.check_stack()?;
+        self.grow(vm)?;
+        if level > 1 {
+            self.spaces(level - 1);
+        }
+        let (v, amp) = match v {
+            Value::Ref(cell) if refcounts => {
+                self.write(format!("reference refcount({}) {{\n", Rc::strong_count(cell)));
+                let inner = cell.borrow().clone();
+                self.dump(vm, &inner, level + 2, refcounts)?;
+                if level > 1 {
+                    self.spaces(level - 1);
+                }
+                self.write("}\n");
+                return Ok(());
+            }
+            Value::Ref(cell) => (cell.borrow().clone(), if Rc::strong_count(cell) > 1 { "&" } else { "" }),
+            v => (v.clone(), ""),
+        };
+        match v {
+            Value::Null => self.write(format!("{}NULL\n", amp)),
+            Value::Bool(b) => self.write(format!("{}bool({})\n", amp, b)),
+            Value::Int(n) => self.write(format!("{}int({})\n", amp, n)),
+            Value::Float(f) => self.write(format!("{}float({})\n", amp, format_float(f, -1))),
+            Value::Resource(r) => self.write(format!("{}resource({}) of type (stream)\n", amp, r.id())),
+            Value::Str(s) => {
+                self.write(format!("{}string({}) \"", amp, s.len()));
+                self.write(s.as_slice());
+                match refcounts {
+                    true => self.write(format!("\" refcount({})\n", Rc::strong_count(&s) - 1)),
+                    false => self.write("\"\n"),
+                }
+            }
+            Value::Array(arr) => {
+                if !self.enter_array(&arr) {
+                    self.write("*RECURSION*\n");
+                    return Ok(());
+                }
+                match refcounts {
+                    true if arr.is_empty() => self.write("array(0) interned {\n"),
+                    true => self.write(format!("array({}) refcount({}){{\n", arr.len(), Rc::strong_count(&arr) - 1)),
+                    false => self.write(format!("{}array({}) {{\n", amp, arr.len())),
+                }
+                for (k, v) in arr.iter() {
+                    self.dump_key(k, level, false);
+                    self.dump(vm, v, level + 2, refcounts)?;
+                }
+                self.arrays.pop();
+                if level > 1 {
+                    self.spaces(level - 1);
+                }
+                self.write("}\n");
+            }
+            Value::Object(obj) => {
+                let class = obj.borrow().class.clone();
+                if let (Some(case), false) = (enum_case(&obj), refcounts) {
+                    self.write(format!("{}enum({}::", amp, class.name));
+                    self.write(case);
+                    self.write(")\n");
+                    return Ok(());
+                }
+                if !self.enter_object(&obj) {
+                    self.write("*RECURSION*\n");
+                    return Ok(());
+                }
+                let props = self.properties(vm, &obj)?;
+                let id = obj.borrow().id;
+                match refcounts {
+                    true => self.write(format!(
+                        "object({})#{} ({}) refcount({}){{\n",
+                        class.name,
+                        id,
+                        props.len(),
+                        Rc::strong_count(&obj) - 1
+                    )),
+                    false => self.write(format!("{}object({})#{} ({}) {{\n", amp, class.name, id, props.len())),
+                }
+                for (k, v) in props.iter() {
+                    self.dump_key(k, level, true);
+                    self.dump(vm, v, level + 2, refcounts)?;
+                }
+                self.objects.pop();
+                if level > 1 {
+                    self.spaces(level - 1);
+                }
+                self.write("}\n");
+            }
+            Value::Ref(_) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// The entries of an array or object in `print_r()`.
+    fn print_hash(&mut self, vm: &mut Vm, entries: &PhpArray, indent: usize, is_object: bool) -> VmResult<()> {
+        self.spaces(indent);
+        self.write("(\n");
+        for (k, v) in entries.iter() {
+            self.spaces(indent + 4);
+            self.write("[");
+            match k {
+                ArrayKey::Int(n) => self.write(n.to_string()),
+                ArrayKey::Str(s) if is_object => match unmangle(s) {
+                    (Some(b"*"), name) => {
+                        self.write(name);
+                        self.write(":protected");
+                    }
+                    (Some(class), name) => {
+                        self.write(name);
+                        self.write(":");
+                        self.write(class);
+                        self.write(":private");
+                    }
+                    (None, name) => self.write(name),
+                },
+                ArrayKey::Str(s) => self.write(s.as_slice()),
+            }
+            self.write("] => ");
+            self.print_r(vm, v, indent + 8)?;
+            self.write("\n");
+        }
+        self.spaces(indent);
+        self.write(")\n");
+        Ok(())
+    }
+
+    fn print_r(&mut self, vm: &mut Vm, v: &Value, indent: usize) -> VmResult<()> {
+        vm.check_stack()?;
+        self.grow(vm)?;
+        match v.deref() {
+            Value::Array(arr) => {
+                self.write("Array\n");
+                if !self.enter_array(&arr) {
+                    self.write(" *RECURSION*");
+                    return Ok(());
+                }
+                self.print_hash(vm, &arr, indent, false)?;
+                self.arrays.pop();
+            }
+            Value::Object(obj) => {
+                let class = obj.borrow().class.clone();
+                self.write(class.name.as_bytes());
+                match (&class.kind, &class.decl.backing_type) {
+                    (ClassKind::Enum, Some(ty)) => self.write(format!(" Enum:{}\n", ty)),
+                    (ClassKind::Enum, None) => self.write(" Enum\n"),
+                    _ => self.write(" Object\n"),
+                }
+                if !self.enter_object(&obj) {
+                    self.write(" *RECURSION*");
+                    return Ok(());
+                }
+                let props = self.properties(vm, &obj)?;
+                self.print_hash(vm, &props, indent, true)?;
+                self.objects.pop();
+            }
+            v => {
+                let s = vm.stringify(&v)?;
+                self.write(s);
+            }
+        }
+        Ok(())
+    }
+
+    /// `var_export()`: PHP code that evaluates to the value.
+    fn export(&mut self, vm: &mut Vm, v: &Value, level: usize) -> VmResult<()> {
+        vm.check_stack()?;
+        self.grow(vm)?;
+        match v.deref() {
+            Value::Null => self.write("NULL"),
+            Value::Bool(b) => self.write(if b { "true" } else { "false" }),
+            Value::Int(i64::MIN) => self.write("-9223372036854775807-1"),
+            Value::Int(n) => self.write(n.to_string()),
+            Value::Float(f) => {
+                let s = format_float(f, -1);
+                let whole = f.is_finite() && !s.contains(['.', 'E']);
+                self.write(s);
+                if whole {
+                    self.write(".0");
+                }
+            }
+            Value::Str(s) => self.write(quote(&s)),
+            Value::Resource(_) => {
+                vm.warn("var_export does not handle resources");
+                self.write("NULL");
+            }
+            Value::Array(arr) => {
+                if !self.enter_array(&arr) {
+                    vm.warn("var_export does not handle circular references");
+                    self.write("NULL");
+                    return Ok(());
+                }
+                if level > 1 {
+                    self.write("\n");
+                    self.spaces(level - 1);
+                }
+                self.write("array (\n");
+                for (k, v) in arr.iter() {
+                    self.spaces(level + 1);
+                    match k {
+                        ArrayKey::Int(n) => self.write(n.to_string()),
+                        ArrayKey::Str(s) => self.write(quote(s)),
+                    }
+                    self.write(" => ");
+                    self.export(vm, v, level + 2)?;
+                    self.write(",\n");
+                }
+                self.arrays.pop();
+                if level > 1 {
+                    self.spaces(level - 1);
+                }
+                self.write(")");
+            }
+            Value::Object(obj) => {
+                if !self.enter_object(&obj) {
+                    vm.warn("var_export does not handle circular references");
+                    self.write("NULL");
+                    return Ok(());
+                }
+                if level > 1 {
+                    self.write("\n");
+                    self.spaces(level - 1);
+                }
+                let class = obj.borrow().class.clone();
+                if let Some(case) = enum_case(&obj) {
+                    self.write(format!("\\{}::", class.name));
+                    self.write(case);
+                    self.objects.pop();
+                    return Ok(());
+                }
+                let std_class = &*class.name == "stdClass";
+                match std_class {
+                    true => self.write("(object) array(\n"),
+                    false => self.write(format!("\\{}::__set_state(array(\n", class.name)),
+                }
+                let props = obj.borrow().to_array();
+                for (k, v) in props.iter() {
+                    self.spaces(level + 2);
+                    match k {
+                        ArrayKey::Int(n) => self.write(n.to_string()),
+                        ArrayKey::Str(s) => self.write(quote(unmangle(s).1)),
+                    }
+                    self.write(" => ");
+                    self.export(vm, v, level + 2)?;
+                    self.write(",\n");
+                }
+                self.objects.pop();
+                if level > 1 {
+                    self.spaces(level - 1);
+                }
+                self.write(if std_class { ")" } else { "))" });
+            }
+            Value::Ref(_) => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+/// `var_dump(mixed $value, mixed ...$values): void`
+fn var_dump(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut d = Dumper::new(true);
+    for v in &args {
+        d.dump(vm, v, 1, false)?;
+    }
+    d.flush(vm);
+    Ok(Value::Null)
+}
+
+/// `debug_zval_dump(mixed $value, mixed ...$values): void`
+///
+/// Like `var_dump()`, with the engine's reference counts of strings,
+/// arrays and objects, not counting the argument itself.
+fn debug_zval_dump(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut d = Dumper::new(true);
+    for v in &args {
+        d.dump(vm, v, 1, true)?;
+    }
+    d.flush(vm);
+    Ok(Value::Null)
+}
+
+/// `print_r(mixed $value, bool $return = false): string|true`
+fn print_r(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let ret = arg_bool(&args, 1, false);
+    let mut d = Dumper::new(!ret);
+    d.print_r(vm, &args[0], 0)?;
+    if ret {
+        return Ok(Value::from(d.out));
+    }
+    d.flush(vm);
+    Ok(Value::Bool(true))
+}
+
+/// `var_export(mixed $value, bool $return = false): ?string`
+fn var_export(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut d = Dumper::new(false);
+    d.export(vm, &args[0], 1)?;
+    if arg_bool(&args, 1, false) {
+        return Ok(Value::from(d.out));
+    }
+    vm.echo(&d.out);
+    Ok(Value::Null)
+}
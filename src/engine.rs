@@ -0,0 +1,197 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+
+use crate::array::ArrayKey;
+use crate::compiler;
+use crate::convert::{FromPhp, IntoPhp};
+use crate::lexer::Lexer;
+use crate::limits::StackScope;
+use crate::parser::Parser;
+use crate::value::Value;
+use crate::vm::{Vm, VmError};
+
+/// Why running PHP code from Rust failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The script file could not be read.
+    Io { path: String, error: std::io::Error },
+    Parse { file: String, message: String },
+    /// The code parses but is invalid, e.g. a `break` outside of a loop.
+    Compile { file: String, message: String },
+    Fatal { file: String, line: usize, message: String },
+    /// An exception nobody caught; `report` is PHP's rendering of it,
+    /// with the stack trace.
+    Uncaught { class: String, message: String, file: String, line: usize, report: String },
+    /// A value handed to Rust does not have the requested type.
+    Conversion(String),
+}
+
+/// Formats the error as the PHP command line reports it.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, error } => write!(f, "Could not open input file: {} ({})", path, error),
+            Error::Parse { file, message } => write!(f, "PHP Parse error:  {} in {}", message, file),
+            Error::Compile { file, message } => write!(f, "PHP Fatal error:  {} in {}", message, file),
+            Error::Fatal { file, line, message } => {
+                write!(f, "PHP Fatal error:  {} in {} on line {}", message, file, line)
+            }
+            Error::Uncaught { report, .. } => write!(f, "PHP Fatal error:  {}", report),
+            Error::Conversion(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Output collected in memory by `Engine::capturing`.
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A PHP interpreter embedded in a Rust program. Scripts run one after
+/// the other share functions, classes, constants and global variables.
+pub struct Engine {
+    vm: Vm,
+    captured: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    /// An engine printing to standard output.
+    pub fn new() -> Engine {
+        Engine::with_output(Box::new(BufWriter::new(std::io::stdout())))
+    }
+
+    pub fn with_output(out: Box<dyn Write>) -> Engine {
+        Engine { vm: Vm::new(out), captured: None }
+    }
+
+    /// An engine keeping its output for `take_output`.
+    pub fn capturing() -> Engine {
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::with_output(Box::new(Captured(buffer.clone())));
+        engine.captured = Some(buffer);
+        engine
+    }
+
+    /// The output printed since the last call; always empty unless the
+    /// engine was created with `capturing`.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.vm.flush();
+        self.captured.as_ref().map(|b| std::mem::take(&mut *b.borrow_mut())).unwrap_or_default()
+    }
+
+    /// The interpreter, to register functions and extensions with.
+    pub fn vm(&mut self) -> &mut Vm {
+        &mut self.vm
+    }
+
+    /// The syntax tree of a source file, as `--ast` prints it.
+    pub fn dump_ast(&self, source: &str, file: &str) -> Result<String, Error> {
+        let _stack = StackScope::enter(self.vm.limits.max_stack_size);
+        let parse_error = |message: String| Error::Parse { file: file.to_string(), message };
+        let tokens = Lexer::new(source).tokenize().map_err(parse_error)?;
+        let ast = Parser::new(tokens).parse().map_err(parse_error)?;
+        Ok(ast.iter().map(|stmt| format!("{:#?}\n", stmt)).collect())
+    }
+
+    pub fn run_file(&mut self, path: &str) -> Result<(), Error> {
+        let source = std::fs::read_to_string(path).map_err(|error| Error::Io { path: path.to_string(), error })?;
+        let file = std::fs::canonicalize(path).map(|p| p.display().to_string()).unwrap_or_else(|_| path.to_string());
+        self.run_source(&source, &file)
+    }
+
+    /// Runs a script, which starts with `<?php` or inline HTML. `file` is
+    /// the name errors and `__FILE__` report.
+    pub fn run_source(&mut self, source: &str, file: &str) -> Result<(), Error> {
+        let _stack = StackScope::enter(self.vm.limits.max_stack_size);
+        let parse_error = |message: String| Error::Parse { file: file.to_string(), message };
+        let tokens = Lexer::new(source).tokenize().map_err(parse_error)?;
+        let ast = Parser::new(tokens).parse().map_err(parse_error)?;
+        let script = compiler::compile_script(&ast, file)
+            .map_err(|message| Error::Compile { file: file.to_string(), message })?;
+        self.vm.error_pos = None;
+        let result = self.vm.run_script(&script);
+        self.vm.flush();
+        result.map_err(|e| self.error(e))
+    }
+
+    /// Calls a PHP function, or a static method as `"Class::method"`.
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        self.vm.error_pos = None;
+        let result = self.vm.call_function(name, args);
+        self.vm.flush();
+        result.map_err(|e| self.error(e))
+    }
+
+    /// Calls a PHP function and converts its result.
+    pub fn call_as<T: FromPhp>(&mut self, name: &str, args: Vec<Value>) -> Result<T, Error> {
+        let v = self.call(name, args)?;
+        T::from_php(&v).map_err(|e| Error::Conversion(format!("{}(): {}", name, e)))
+    }
+
+    /// The value of a global variable, converted.
+    pub fn global<T: FromPhp>(&self, name: &str) -> Result<Option<T>, Error> {
+        let Some(v) = self.vm.global(name) else { return Ok(None) };
+        T::from_php(&v).map(Some).map_err(|e| Error::Conversion(format!("${}: {}", name, e)))
+    }
+
+    pub fn set_global(&mut self, name: &str, v: impl IntoPhp) {
+        self.vm.set_global(name, v.into_php());
+    }
+
+    /// Ends the request as PHP does when a script is done: the global
+    /// variables are released and destructors run.
+    pub fn shutdown(&mut self) -> Result<(), Error> {
+        self.vm.error_pos = None;
+        let result = self.vm.shutdown();
+        self.vm.flush();
+        result.map_err(|e| self.error(e))
+    }
+
+    fn error(&mut self, e: VmError) -> Error {
+        let (file, line) = self.vm.error_pos().unwrap_or_else(|| ("Unknown".into(), 0));
+        let (file, line) = (file.to_string(), line);
+        match e {
+            VmError::Fatal(message) => Error::Fatal { file, line, message },
+            VmError::Exception(e) => {
+                let prop = |name: &str| e.borrow().props.get(&ArrayKey::from(name)).map_or(Value::Null, Value::deref);
+                let text = |v: Value| String::from_utf8_lossy(&v.scalar_to_bytes().unwrap_or_default()).into_owned();
+                Error::Uncaught {
+                    class: e.borrow().class.name.to_string(),
+                    message: text(prop("message")),
+                    file: text(prop("file")),
+                    line: prop("line").to_int() as usize,
+                    report: self.vm.describe_uncaught(&e),
+                }
+            }
+            VmError::Error { class, message } => Error::Uncaught {
+                report: format!(
+                    "Uncaught {}: {} in {}:{}\nStack trace:\n#0 {{main}}\n  thrown in {} on line {}",
+                    class, message, file, line, file, line
+                ),
+                class: class.to_string(),
+                message,
+                file,
+                line,
+            },
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::rc::Rc;
+
+use crate::array::ArrayKey;
+use crate::ast::{ClassKind, Visibility};
+use crate::compiler::{ClassDef, Function, MethodDef, PropDef};
+use crate::value::{format_float, ObjRef, Value};
+use crate::vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
+
+/// Built-in exception classes below `Exception` and `Error`, with their
+/// parents.
+const SUBCLASSES: &[(&str, &str)] = &[
+    ("ErrorException", "Exception"),
+    ("TypeError", "Error"),
+    ("ValueError", "Error"),
+    ("ArithmeticError", "Error"),
+    ("DivisionByZeroError", "ArithmeticError"),
+    ("ArgumentCountError", "TypeError"),
+    ("AssertionError", "Error"),
+    ("UnhandledMatchError", "Error"),
+    ("FiberError", "Error"),
+    ("CompileError", "Error"),
+    ("ParseError", "CompileError"),
+    ("LogicException", "Exception"),
+    ("BadFunctionCallException", "LogicException"),
+    ("BadMethodCallException", "BadFunctionCallException"),
+    ("DomainException", "LogicException"),
+    ("InvalidArgumentException", "LogicException"),
+    ("LengthException", "LogicException"),
+    ("OutOfRangeException", "LogicException"),
+    ("RuntimeException", "Exception"),
+    ("OutOfBoundsException", "RuntimeException"),
+    ("OverflowException", "RuntimeException"),
+    ("RangeException", "RuntimeException"),
+    ("UnderflowException", "RuntimeException"),
+    ("UnexpectedValueException", "RuntimeException"),
+    ("JsonException", "Exception"),
+];
+
+/// The `Exception` and `Error` classes, which implement `Throwable`, and
+/// their built-in subclasses, in declaration order.
+pub fn class_defs() -> Vec<ClassDef> {
+    let mut defs = vec![base_class("Exception"), base_class("Error")];
+    defs.extend(SUBCLASSES.iter().map(|(name, parent)| ClassDef {
+        parent: Some((*parent).into()),
+        ..empty_class(name)
+    }));
+    defs
+}
+
+fn empty_class(name: &str) -> ClassDef {
+    ClassDef {
+        name: name.into(),
+        kind: ClassKind::Class,
+        is_abstract: false,
+        is_final: false,
+        parent: None,
+        interfaces: Vec::new(),
+        backing_type: None,
+        constants: Vec::new(),
+        props: Vec::new(),
+        methods: Vec::new(),
+        trait_uses: Vec::new(),
+        line: 0,
+    }
+}
+
+fn base_class(name: &str) -> ClassDef {
+    let props = [
+        ("message", Value::from(""), Visibility::Protected),
+        ("string", Value::from(""), Visibility::Private),
+        ("code", Value::Int(0), Visibility::Protected),
+        ("file", Value::from(""), Visibility::Protected),
+        ("line", Value::Int(0), Visibility::Protected),
+        ("trace", Value::from(crate::array::PhpArray::new()), Visibility::Private),
+        ("previous", Value::Null, Visibility::Private),
+    ];
+    let methods: [(&str, &[&str], NativeFn, bool); 9] = [
+        ("__construct", &["message", "code", "previous"], construct, false),
+        ("getMessage", &[], get_message, true),
+        ("getCode", &[], get_code, true),
+        ("getPrevious", &[], get_previous, true),
+        ("getFile", &[], get_file, true),
+        ("getLine", &[], get_line, true),
+        ("getTrace", &[], get_trace, true),
+        ("getTraceAsString", &[], get_trace_as_string, true),
+        ("__toString", &[], to_string, false),
+    ];
+    ClassDef {
+        interfaces: vec!["Throwable".into()],
+        props: props
+            .into_iter()
+            .map(|(name, v, visibility)| PropDef {
+                name: name.into(),
+                default: Some(Rc::new(Function::constant(v))),
+                visibility,
+                is_static: false,
+            })
+            .collect(),
+        methods: methods
+            .into_iter()
+            .map(|(name, params, f, is_final)| MethodDef {
+                name: name.into(),
+                func: Rc::new(Function::native(name, params, f)),
+                visibility: Visibility::Public,
+                is_static: false,
+                is_abstract: false,
+                is_final,
+            })
+            .collect(),
+        ..empty_class(name)
+    }
+}
+
+fn this(callee: &Callee) -> ObjRef {
+    callee.this.clone().expect("Throwable method called without $this")
+}
+
+fn prop(obj: &ObjRef, name: &str) -> Value {
+    obj.borrow().props.get(&ArrayKey::from(name)).map_or(Value::Null, Value::deref)
+}
+
+fn set_prop(obj: &ObjRef, name: &str, v: Value) {
+    obj.borrow_mut().props.insert(ArrayKey::from(name), v);
+}
+
+/// Appends `previous` to the end of the chain of exceptions before `e`,
+/// unless it is already part of it.
+pub(crate) fn set_previous(e: &ObjRef, previous: ObjRef) {
+    let mut ancestor = Some(previous.clone());
+    while let Some(a) = ancestor {
+        if Rc::ptr_eq(&a, e) {
+            return;
+        }
+        ancestor = match prop(&a, "previous") {
+            Value::Object(o) => Some(o),
+            _ => None,
+        };
+    }
+    let mut base = e.clone();
+    while let Value::Object(prev) = prop(&base, "previous") {
+        base = prev;
+    }
+    set_prop(&base, "previous", Value::Object(previous));
+}
+
+fn text(v: &Value) -> String {
+    String::from_utf8_lossy(&v.scalar_to_bytes().unwrap_or_default()).into_owned()
+}
+
+/// Name of the method as PHP reports it: `Exception::getMessage`.
+fn method_name(callee: &Callee) -> String {
+    match &callee.scope {
+        Some(c) => format!("{}::{}", c.name, callee.func.name),
+        None => callee.func.name.to_string(),
+    }
+}
+
+impl Vm {
+    /// Creates an exception of a built-in class, as the engine throws
+    /// them. It is located where the error was raised.
+    pub fn create_exception(&mut self, class: &str, message: &str) -> VmResult<ObjRef> {
+        let class = self.lookup_class(class)?;
+        let obj = self.instantiate(&class)?;
+        set_prop(&obj, "message", Value::from(message));
+        if let Some((file, line)) = self.error_pos.take() {
+            set_prop(&obj, "file", Value::from(&*file));
+            set_prop(&obj, "line", Value::Int(line as i64));
+        }
+        Ok(obj)
+    }
+
+    /// PHP's report of an uncaught exception, built from its
+    /// `__toString()`.
+    pub fn describe_uncaught(&mut self, e: &ObjRef) -> String {
+        let class = e.borrow().class.clone();
+        let method = class.find_method("__toString").cloned();
+        let result = match method {
+            Some(m) => self.method_callee(&m, Some(e.clone()), class).and_then(|c| self.call(c, Vec::new())),
+            None => Ok(Value::Null),
+        };
+        let report = match result {
+            Ok(Value::Str(s)) => String::from_utf8_lossy(&s).into_owned(),
+            _ => throwable_string(e),
+        };
+        format!(
+            "Uncaught {}\n  thrown in {} on line {}",
+            report,
+            text(&prop(e, "file")),
+            prop(e, "line").to_int()
+        )
+    }
+}
+
+/// `Exception::__toString()`: the exception and those before it, oldest
+/// first.
+fn throwable_string(e: &ObjRef) -> String {
+    let mut chain = vec![e.clone()];
+    while let Value::Object(prev) = prop(chain.last().unwrap(), "previous") {
+        if chain.iter().any(|c| Rc::ptr_eq(c, &prev)) {
+            break;
+        }
+        chain.push(prev);
+    }
+    let parts: Vec<String> = chain
+        .iter()
+        .rev()
+        .map(|e| {
+            let message = text(&prop(e, "message"));
+            let class = e.borrow().class.name.clone();
+            let head = if message.is_empty() { class.to_string() } else { format!("{}: {}", class, message) };
+            format!(
+                "{} in {}:{}\nStack trace:\n{}",
+                head,
+                text(&prop(e, "file")),
+                prop(e, "line").to_int(),
+                trace_string(&prop(e, "trace"))
+            )
+        })
+        .collect();
+    parts.join("\n\nNext ")
+}
+
+/// Renders a trace like `getTraceAsString()`.
+fn trace_string(trace: &Value) -> String {
+    let mut out = String::new();
+    let mut n = 0;
+    if let Value::Array(frames) = trace {
+        for (_, frame) in frames.iter() {
+            let Value::Array(f) = frame.deref() else { continue };
+            let get = |k: &str| f.get(&ArrayKey::from(k)).map(Value::deref);
+            let field = |k: &str| get(k).map_or(String::new(), |v| text(&v));
+            let location = match get("file") {
+                Some(_) => format!("{}({})", field("file"), field("line")),
+                None => "[internal function]".to_string(),
+            };
+            let args: Vec<String> = match get("args") {
+                Some(Value::Array(args)) => args.iter().map(|(_, v)| trace_arg(&v.deref())).collect(),
+                _ => Vec::new(),
+            };
+            out += &format!(
+                "#{} {}: {}{}{}({})\n",
+                n,
+                location,
+                field("class"),
+                field("type"),
+                field("function"),
+                args.join(", ")
+            );
+            n += 1;
+        }
+    }
+    out += &format!("#{} {{main}}", n);
+    out
+}
+
+fn trace_arg(v: &Value) -> String {
+    match v {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => format_float(*f, 14),
+        Value::Str(s) => {
+            let s = String::from_utf8_lossy(s);
+            match s.char_indices().nth(15) {
+                Some((i, _)) => format!("'{}...'", &s[..i]),
+                None => format!("'{}'", s),
+            }
+        }
+        Value::Array(_) => "Array".to_string(),
+        Value::Object(o) => format!("Object({})", o.borrow().class.name),
+        Value::Resource(r) => format!("Resource id #{}", r.id()),
+        Value::Ref(r) => trace_arg(&r.borrow()),
+    }
+}
+
+/// `__construct(string $message = "", int $code = 0, ?Throwable $previous = null)`
+fn construct(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let name = method_name(callee);
+    expect_args(&name, &args, 0, 3)?;
+    let obj = this(callee);
+    let mut args = args.into_iter();
+    if let Some(message) = args.next() {
+        let message = vm.coerce_arg(message, "string", &name, 1, "message")?;
+        set_prop(&obj, "message", message);
+    }
+    if let Some(code) = args.next() {
+        let code = vm.coerce_arg(code, "int", &name, 2, "code")?;
+        set_prop(&obj, "code", code);
+    }
+    match args.next() {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(o)) if o.borrow().class.is_subclass_of("Throwable") => {
+            set_prop(&obj, "previous", Value::Object(o));
+        }
+        Some(other) => {
+            return Err(VmError::type_error(format!(
+                "{}(): Argument #3 ($previous) must be of type ?Throwable, {} given",
+                name,
+                other.type_name()
+            )));
+        }
+    }
+    Ok(Value::Null)
+}
+
+fn getter(callee: &Callee, args: &[Value], name: &str) -> VmResult<Value> {
+    expect_args(&method_name(callee), args, 0, 0)?;
+    Ok(prop(&this(callee), name))
+}
+
+fn get_message(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    getter(callee, &args, "message")
+}
+
+fn get_code(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    getter(callee, &args, "code")
+}
+
+fn get_previous(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    getter(callee, &args, "previous")
+}
+
+fn get_file(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    getter(callee, &args, "file")
+}
+
+fn get_line(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    getter(callee, &args, "line")
+}
+
+fn get_trace(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    getter(callee, &args, "trace")
+}
+
+fn get_trace_as_string(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let trace = getter(callee, &args, "trace")?;
+    Ok(Value::from(trace_string(&trace).as_str()))
+}
+
+fn to_string(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args(&method_name(callee), &args, 0, 0)?;
+    Ok(Value::from(throwable_string(&this(callee)).as_str()))
+}
@@ -0,0 +1,400 @@
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::TypeHint;
+use crate::compiler::{type_members, ClassDef, Function};
+use crate::arrays;
+use crate::bcmath;
+use crate::dump;
+use crate::json;
+use crate::math;
+use crate::preg;
+use crate::printf;
+use crate::scanf;
+use crate::strings;
+use crate::value::{Stream, Value};
+use crate::vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
+
+/// A Rust function exposed to PHP. Unlike the engine's own tables, the
+/// engine checks the argument count and coerces arguments to the
+/// declared parameter types before the function runs, the way PHP's
+/// internal functions behave.
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    /// Parameters written as in PHP, without the `$`: an optional type,
+    /// `&` for by-reference (`&?` if values will do too) and `...` for
+    /// variadic parameters, and `= default` for optional ones, as in
+    /// `"?int count = null"`.
+    pub params: &'static [&'static str],
+    pub func: NativeFn,
+}
+
+/// Functions, classes, constants and ini settings registered together,
+/// like one of PHP's `ext/` modules.
+pub struct Extension {
+    pub name: &'static str,
+    pub functions: Vec<NativeFunction>,
+    pub classes: Vec<ClassDef>,
+    pub constants: Vec<(&'static str, Value)>,
+    /// Settings and their default values, for `ini_get()` and `ini_set()`.
+    pub ini: Vec<(&'static str, &'static str)>,
+}
+
+impl NativeFunction {
+    fn to_function(&self) -> Function {
+        let names: Vec<&str> = self.params.iter().map(|p| param_name(p)).collect();
+        let mut func = Function::native(self.name, &names, self.func);
+        for (param, spec) in func.params.iter_mut().zip(self.params) {
+            let (decl, default) = match spec.split_once('=') {
+                Some((decl, default)) => (decl.trim(), Some(default.trim().to_string())),
+                None => (spec.trim(), None),
+            };
+            param.default = default;
+            param.ty = decl.rsplit_once(' ').map(|(ty, _)| parse_type(ty.trim()));
+        }
+        func.checks_args = true;
+        func
+    }
+}
+
+/// The name with its `&` or `...` prefix, as `Function::native` takes it.
+fn param_name(spec: &str) -> &str {
+    let decl = spec.split_once('=').map_or(spec, |(decl, _)| decl).trim();
+    decl.rsplit_once(' ').map_or(decl, |(_, name)| name)
+}
+
+fn parse_type(ty: &str) -> TypeHint {
+    if let Some(inner) = ty.strip_prefix('?') {
+        return TypeHint::Nullable(Box::new(parse_type(inner)));
+    }
+    match ty.split('|').collect::<Vec<_>>().as_slice() {
+        [one] => TypeHint::Named(one.to_string()),
+        many => TypeHint::Union(many.iter().map(|t| TypeHint::Named(t.to_string())).collect()),
+    }
+}
+
+impl Vm {
+    /// Makes a Rust function callable from PHP, replacing any built-in
+    /// function of the same name, unless the limits exclude it.
+    pub fn register_function(&mut self, f: &NativeFunction) {
+        if !self.limits.allows_function(f.name) {
+            return;
+        }
+        self.functions.insert(f.name.to_ascii_lowercase(), Rc::new(f.to_function()));
+    }
+
+    /// Registers everything an extension provides. Fails if one of its
+    /// classes does not link, e.g. because its parent is unknown.
+    pub fn register_extension(&mut self, ext: Extension) -> VmResult<()> {
+        for f in &ext.functions {
+            self.register_function(f);
+        }
+        for (name, v) in ext.constants {
+            self.define_constant(name, v);
+        }
+        for (name, v) in ext.ini {
+            self.ini.insert(name.to_string(), v.to_string());
+            self.ini_defaults.insert(name.to_string(), v.to_string());
+        }
+        for def in ext.classes {
+            self.declare_class(&Rc::new(def))?;
+        }
+        self.extensions.push(ext.name);
+        Ok(())
+    }
+
+    /// Checks and coerces the arguments of a call to a native function
+    /// registered through `register_function`.
+    pub(crate) fn check_native_args(&mut self, callee: &Callee, args: Vec<Value>) -> VmResult<Vec<Value>> {
+        let func = &callee.func;
+        let max = if func.is_variadic() { usize::MAX } else { func.params.len() };
+        expect_args(&func.name, &args, func.required_params(), max)?;
+        let strict = self.caller_is_strict();
+        let mut checked = Vec::with_capacity(args.len());
+        for (i, v) in args.into_iter().enumerate() {
+            let param = &func.params[i.min(func.params.len() - 1)];
+            let Some(ty) = param.ty.as_ref().filter(|_| !param.by_ref) else {
+                checked.push(v);
+                continue;
+            };
+            let v = v.deref();
+            if let (Value::Null, false) = (&v, strict) {
+                let scalar = type_members(ty).into_iter().find(|m| ["int", "float", "string", "bool"].contains(&m.as_str()));
+                if let (Some(scalar), false) = (scalar, self.has_type(&v, ty, None, None)) {
+                    self.deprecated(&format!(
+                        "{}(): Passing null to parameter #{} (${}) of type {} is deprecated",
+                        func.name,
+                        i + 1,
+                        param.name,
+                        ty
+                    ));
+                    checked.push(match scalar.as_str() {
+                        "int" => Value::Int(0),
+                        "float" => Value::Float(0.0),
+                        "string" => Value::from(""),
+                        _ => Value::Bool(false),
+                    });
+                    continue;
+                }
+            }
+            match self.coerce_to_type(v.clone(), ty, None, None, strict)? {
+                Some(v) => checked.push(v),
+                None if type_members(ty).iter().any(|m| m == "callable") => {
+                    return Err(VmError::type_error(format!(
+                        "{}(): Argument #{} (${}) must be a valid callback{}, {}",
+                        func.name,
+                        i + 1,
+                        param.name,
+                        if matches!(ty, TypeHint::Nullable(_)) { " or null" } else { "" },
+                        self.invalid_callback(&v)
+                    )));
+                }
+                None => {
+                    return Err(VmError::type_error(format!(
+                        "{}(): Argument #{} (${}) must be of type {}, {} given",
+                        func.name,
+                        i + 1,
+                        param.name,
+                        ty,
+                        v.type_name()
+                    )));
+                }
+            }
+        }
+        Ok(checked)
+    }
+}
+
+impl Vm {
+    /// Why a value is not callable, as PHP words it in argument errors.
+    fn invalid_callback(&mut self, v: &Value) -> String {
+        let method = |vm: &mut Vm, class: &[u8], method: &[u8]| {
+            let class = String::from_utf8_lossy(class);
+            match vm.find_class(&class) {
+                None => format!("class \"{}\" not found", class),
+                Some(c) => format!("class {} does not have a method \"{}\"", c.name, String::from_utf8_lossy(method)),
+            }
+        };
+        match v {
+            Value::Str(s) => match s.windows(2).position(|w| w == b"::") {
+                Some(i) => method(self, &s[..i], &s[i + 2..]),
+                None => format!("function \"{}\" not found or invalid function name", String::from_utf8_lossy(s)),
+            },
+            Value::Array(a) if a.len() != 2 => "array callback must have exactly two members".to_string(),
+            Value::Array(a) => {
+                let (Some(target), Some(Value::Str(name))) = (a.get(&ArrayKey::Int(0)), a.get(&ArrayKey::Int(1)).map(Value::deref)) else {
+                    return "array callback must have exactly two members".to_string();
+                };
+                match target.deref() {
+                    Value::Str(class) => method(self, &class, &name),
+                    Value::Object(o) => {
+                        let class = o.borrow().class.name.clone();
+                        format!("class {} does not have a method \"{}\"", class, String::from_utf8_lossy(&name))
+                    }
+                    _ => "first array member is not a valid class name or object".to_string(),
+                }
+            }
+            _ => "no array or string given".to_string(),
+        }
+    }
+}
+
+/// The engine's own extension: ini settings and introspection of the
+/// registered extensions.
+pub fn core() -> Extension {
+    Extension {
+        name: "Core",
+        functions: vec![
+            NativeFunction { name: "ini_get", params: &["string option"], func: ini_get },
+            NativeFunction { name: "ini_set", params: &["string option", "string|int|float|bool|null value"], func: ini_set },
+            NativeFunction { name: "ini_restore", params: &["string option"], func: ini_restore },
+            NativeFunction { name: "extension_loaded", params: &["string extension"], func: extension_loaded },
+            NativeFunction { name: "get_loaded_extensions", params: &["bool zend_extensions = false"], func: get_loaded_extensions },
+        ],
+        classes: Vec::new(),
+        constants: Vec::new(),
+        ini: vec![
+            ("precision", "14"),
+            ("serialize_precision", "-1"),
+            ("memory_limit", "-1"),
+            ("max_execution_time", "0"),
+            ("display_errors", "1"),
+            ("error_reporting", "32767"),
+            ("default_charset", "UTF-8"),
+            ("date.timezone", "UTC"),
+            ("include_path", "."),
+        ],
+    }
+}
+
+/// PHP's standard library: strings, arrays, math and the like.
+pub fn standard() -> Extension {
+    Extension {
+        name: "standard",
+        functions: strings::FUNCTIONS
+            .iter()
+            .chain(printf::FUNCTIONS)
+            .chain(scanf::FUNCTIONS)
+            .chain(arrays::FUNCTIONS)
+            .chain(dump::FUNCTIONS)
+            .chain(math::FUNCTIONS)
+            .cloned()
+            .collect(),
+        classes: Vec::new(),
+        constants: strings::CONSTANTS
+            .iter()
+            .chain(arrays::CONSTANTS)
+            .chain(math::CONSTANTS)
+            .map(|(name, v)| (*name, Value::Int(*v)))
+            .chain([("STDOUT", Value::Resource(Stream::Stdout)), ("STDERR", Value::Resource(Stream::Stderr))])
+            .collect(),
+        ini: Vec::new(),
+    }
+}
+
+/// Arbitrary precision decimal arithmetic through the `bc*` functions.
+pub fn bcmath() -> Extension {
+    Extension {
+        name: "bcmath",
+        functions: bcmath::FUNCTIONS.to_vec(),
+        classes: Vec::new(),
+        constants: Vec::new(),
+        ini: vec![("bcmath.scale", "0")],
+    }
+}
+
+/// JSON encoding and decoding.
+pub fn json() -> Extension {
+    Extension {
+        name: "json",
+        functions: json::FUNCTIONS.to_vec(),
+        classes: Vec::new(),
+        constants: json::CONSTANTS.iter().map(|(name, v)| (*name, Value::Int(*v))).collect(),
+        ini: Vec::new(),
+    }
+}
+
+/// Regular expressions through the `preg_*` functions.
+pub fn pcre() -> Extension {
+    let mut constants: Vec<(&'static str, Value)> = preg::CONSTANTS.iter().map(|(name, v)| (*name, Value::Int(*v))).collect();
+    constants.push(("PCRE_VERSION", Value::from(preg::PCRE_VERSION)));
+    constants.push(("PCRE_JIT_SUPPORT", Value::Bool(false)));
+    Extension {
+        name: "pcre",
+        functions: preg::FUNCTIONS.to_vec(),
+        classes: Vec::new(),
+        constants,
+        ini: vec![("pcre.backtrack_limit", "1000000"), ("pcre.recursion_limit", "100000"), ("pcre.jit", "1")],
+    }
+}
+
+// Accessors for the arguments of functions registered through
+// `register_function`, which the engine already coerced to their
+// parameter types. Optional arguments that were not passed are missing.
+
+pub(crate) fn arg_str(args: &[Value], i: usize) -> &[u8] {
+    opt_str(args, i).unwrap_or_default()
+}
+
+/// A string argument, or `None` if it was not passed or is null.
+pub(crate) fn opt_str(args: &[Value], i: usize) -> Option<&[u8]> {
+    match args.get(i) {
+        Some(Value::Str(s)) => Some(s),
+        _ => None,
+    }
+}
+
+pub(crate) fn arg_int(args: &[Value], i: usize, default: i64) -> i64 {
+    opt_int(args, i).unwrap_or(default)
+}
+
+pub(crate) fn opt_int(args: &[Value], i: usize) -> Option<i64> {
+    match args.get(i) {
+        Some(Value::Int(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+pub(crate) fn arg_float(args: &[Value], i: usize) -> f64 {
+    match args.get(i) {
+        Some(Value::Float(f)) => *f,
+        Some(Value::Int(n)) => *n as f64,
+        _ => 0.0,
+    }
+}
+
+pub(crate) fn arg_array(args: &[Value], i: usize) -> Rc<PhpArray> {
+    match args.get(i) {
+        Some(Value::Array(a)) => a.clone(),
+        _ => Rc::default(),
+    }
+}
+
+pub(crate) fn arg_bool(args: &[Value], i: usize, default: bool) -> bool {
+    args.get(i).map_or(default, Value::to_bool)
+}
+
+/// Assigns to a by-reference argument, if one was passed.
+pub(crate) fn set_ref(args: &[Value], i: usize, v: Value) {
+    if let Some(Value::Ref(cell)) = args.get(i) {
+        *cell.borrow_mut() = v;
+    }
+}
+
+fn string_arg(v: &Value) -> String {
+    String::from_utf8_lossy(&v.scalar_to_bytes().unwrap_or_default()).into_owned()
+}
+
+/// `ini_get(string $option): string|false`
+fn ini_get(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let name = string_arg(&args[0]);
+    if name == "include_path" {
+        return Ok(Value::from(vm.include_path.as_str()));
+    }
+    Ok(vm.ini.get(&name).map_or(Value::Bool(false), |v| Value::from(v.as_str())))
+}
+
+/// `ini_set(string $option, string|int|float|bool|null $value): string|false`
+fn ini_set(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let old = ini_get(vm, callee, vec![args[0].clone()])?;
+    if let Value::Bool(false) = old {
+        return Ok(old);
+    }
+    let name = string_arg(&args[0]);
+    let value = string_arg(&args[1]);
+    if name == "include_path" {
+        vm.include_path = value.clone();
+    }
+    vm.ini.insert(name, value);
+    Ok(old)
+}
+
+/// `ini_restore(string $option): void`
+fn ini_restore(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let name = string_arg(&args[0]);
+    if let Some(v) = vm.ini_defaults.get(&name).cloned() {
+        if name == "include_path" {
+            vm.include_path = v.clone();
+        }
+        vm.ini.insert(name, v);
+    }
+    Ok(Value::Null)
+}
+
+/// `extension_loaded(string $extension): bool`
+fn extension_loaded(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let name = string_arg(&args[0]);
+    Ok(Value::Bool(vm.extensions.iter().any(|e| e.eq_ignore_ascii_case(&name))))
+}
+
+/// `get_loaded_extensions(bool $zend_extensions = false): array`
+fn get_loaded_extensions(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut names = PhpArray::new();
+    if !args.first().is_some_and(Value::to_bool) {
+        for e in &vm.extensions {
+            names.push(Value::from(*e)).map_err(VmError::error)?;
+        }
+    }
+    Ok(Value::from(names))
+}
@@ -0,0 +1,395 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+
+use crate::ast::{ClassKind, Visibility};
+use crate::class::Internal;
+use crate::compiler::{ClassDef, Function, MethodDef};
+use crate::limits::StackScope;
+use crate::value::{ObjRef, Value};
+use crate::vm::{expect_args, Callee, Frame, NativeFn, Vm, VmError, VmResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Init,
+    Running,
+    Suspended,
+    Returned,
+    Threw,
+}
+
+/// State of a `Fiber` object.
+pub struct Fiber {
+    callback: Callee,
+    state: State,
+    /// The fiber's own call stack while it is suspended, outermost first.
+    frames: Vec<Frame>,
+    /// The thread running the fiber's code, from its start to its end.
+    thread: Option<Thread>,
+    retval: Value,
+}
+
+impl Fiber {
+    /// The call stack while the fiber is suspended.
+    pub(crate) fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Whether it is suspended, so its code has to be unwound when the
+    /// fiber is released.
+    pub(crate) fn is_suspended(&self) -> bool {
+        self.state == State::Suspended
+    }
+}
+
+impl std::fmt::Debug for Fiber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Fiber").field("state", &self.state).finish_non_exhaustive()
+    }
+}
+
+/// A fiber that is running, with the call stack of the code that started
+/// or resumed it.
+pub(crate) struct ActiveFiber {
+    obj: ObjRef,
+    caller_frames: Vec<Frame>,
+}
+
+impl ActiveFiber {
+    pub(crate) fn caller_frames(&self) -> &[Frame] {
+        &self.caller_frames
+    }
+
+    pub(crate) fn caller_frames_mut(&mut self) -> &mut [Frame] {
+        &mut self.caller_frames
+    }
+}
+
+/// What the fiber's `Fiber::suspend()` call evaluates to when it resumes.
+enum Input {
+    Start,
+    Value(Value),
+    Throw(ObjRef),
+    /// The fiber was released while suspended: its code is unwound, as
+    /// by a fatal error, to end its thread.
+    Destroy,
+}
+
+/// How the fiber's code hands control back.
+enum Outcome {
+    Suspended(Value),
+    Finished(VmResult<Value>),
+}
+
+/// Each fiber runs on a thread of its own, so it can suspend from any depth
+/// of native code calling back into PHP code, e.g. a `usort()` callback.
+/// The thread and the one that switched to it take turns: one waits on its
+/// channel while the other runs the VM.
+struct Thread {
+    resume: Sender<Handoff<Input>>,
+    suspended: Receiver<Handoff<Outcome>>,
+    handle: JoinHandle<()>,
+}
+
+/// The fiber thread's ends of the channels.
+struct Link {
+    resume: Receiver<Handoff<Input>>,
+    suspended: Sender<Handoff<Outcome>>,
+}
+
+/// A value passed between a fiber's thread and the thread switching to it.
+struct Handoff<T>(T);
+
+// SAFETY: the VM and the values reachable from it are only used by the
+// thread whose turn it is. A thread hands its turn over by sending on a
+// channel and then only waits on its own one, so no two threads ever use
+// them at once, and the channels order each turn after the previous one.
+unsafe impl<T> Send for Handoff<T> {}
+
+thread_local! {
+    /// In a fiber's thread, where it waits to resume and where it hands
+    /// control back.
+    static LINK: RefCell<Option<Link>> = const { RefCell::new(None) };
+}
+
+/// The message the code of a released fiber is unwound with.
+const DESTROYED: &str = "Fiber destroyed";
+
+/// The built-in `Fiber` class.
+pub fn class_def() -> ClassDef {
+    let methods: [(&str, &[&str], NativeFn, bool); 11] = [
+        ("__construct", &["callback"], construct, false),
+        ("start", &["...args"], start, false),
+        ("resume", &["value"], resume, false),
+        ("throw", &["exception"], throw, false),
+        ("getReturn", &[], get_return, false),
+        ("isStarted", &[], is_started, false),
+        ("isSuspended", &[], is_suspended, false),
+        ("isRunning", &[], is_running, false),
+        ("isTerminated", &[], is_terminated, false),
+        ("suspend", &["value"], suspend, true),
+        ("getCurrent", &[], get_current, true),
+    ];
+    let methods = methods
+        .into_iter()
+        .map(|(name, params, f, is_static)| MethodDef {
+            name: name.into(),
+            func: Rc::new(Function::native(name, params, f)),
+            visibility: Visibility::Public,
+            is_static,
+            is_abstract: false,
+            is_final: false,
+        })
+        .collect();
+    ClassDef {
+        name: "Fiber".into(),
+        kind: ClassKind::Class,
+        is_abstract: false,
+        is_final: true,
+        parent: None,
+        interfaces: Vec::new(),
+        backing_type: None,
+        constants: Vec::new(),
+        props: Vec::new(),
+        methods,
+        trait_uses: Vec::new(),
+        line: 0,
+    }
+}
+
+fn fiber_error(message: &str) -> VmError {
+    VmError::Error { class: "FiberError", message: message.to_string() }
+}
+
+fn this_fiber(callee: &Callee) -> VmResult<Rc<RefCell<Fiber>>> {
+    let this = callee.this.as_ref().expect("Fiber method called without $this");
+    match &this.borrow().internal {
+        Internal::Fiber(f) => Ok(f.clone()),
+        _ => Err(VmError::error("Fiber has not been constructed")),
+    }
+}
+
+impl Vm {
+    /// Runs the fiber until it suspends or ends, and returns the value it
+    /// suspended with, or null if it ended.
+    fn switch_to(&mut self, obj: ObjRef, fiber: Rc<RefCell<Fiber>>, input: Input) -> VmResult<Value> {
+        self.check_stack()?;
+        let (frames, thread) = {
+            let mut f = fiber.borrow_mut();
+            f.state = State::Running;
+            (std::mem::take(&mut f.frames), f.thread.take().expect("started fiber has a thread"))
+        };
+        let caller_frames = std::mem::replace(&mut self.frames, frames);
+        self.fibers.push(ActiveFiber { obj, caller_frames });
+        let outcome = match thread.resume.send(Handoff(input)) {
+            Ok(()) => thread.suspended.recv().ok(),
+            Err(_) => None,
+        };
+        let active = self.fibers.pop().unwrap();
+        let frames = std::mem::replace(&mut self.frames, active.caller_frames);
+        let mut f = fiber.borrow_mut();
+        match outcome.map(|o| o.0) {
+            Some(Outcome::Suspended(v)) => {
+                f.frames = frames;
+                f.thread = Some(thread);
+                f.state = State::Suspended;
+                Ok(v)
+            }
+            Some(Outcome::Finished(result)) => {
+                let _ = thread.handle.join();
+                f.state = if result.is_ok() { State::Returned } else { State::Threw };
+                f.retval = result?;
+                Ok(Value::Null)
+            }
+            None => match thread.handle.join() {
+                Err(panic) => std::panic::resume_unwind(panic),
+                Ok(()) => unreachable!("fiber thread ended without finishing"),
+            },
+        }
+    }
+
+    /// Unwinds the code of a fiber released while suspended.
+    pub(crate) fn destroy_fiber(&mut self, obj: ObjRef, fiber: Rc<RefCell<Fiber>>) {
+        if !fiber.borrow().is_suspended() {
+            return;
+        }
+        let error_pos = self.error_pos.take();
+        let _ = self.switch_to(obj, fiber, Input::Destroy);
+        self.error_pos = error_pos;
+    }
+}
+
+/// Runs a fiber's callback on its thread, once it is switched to.
+fn run_thread(vm: Handoff<*mut Vm>, call: Handoff<(Callee, Vec<Value>)>, stack_size: usize, link: Link) {
+    if link.resume.recv().is_err() {
+        // Released before it started: the values it was given belong to
+        // the VM's thread, and are left alone.
+        std::mem::forget(call);
+        return;
+    }
+    LINK.set(Some(link));
+    // SAFETY: it is this thread's turn, see `Handoff`.
+    let vm = unsafe { &mut *vm.0 };
+    let _stack = StackScope::enter(stack_size);
+    let (callback, args) = call.0;
+    let result = vm.call(callback, args);
+    LINK.with_borrow(|link| {
+        let _ = link.as_ref().unwrap().suspended.send(Handoff(Outcome::Finished(result)));
+    });
+}
+
+/// `new Fiber(callable $callback)`
+fn construct(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Fiber::__construct", &args, 1, 1)?;
+    let callback = match vm.resolve_callable(&args[0]) {
+        Ok(c) => c,
+        Err(VmError::Error { message, .. }) => {
+            return Err(VmError::type_error(format!(
+                "Fiber::__construct(): Argument #1 ($callback) must be a valid callback, {}",
+                lowercase_first(&message)
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+    let this = callee.this.as_ref().expect("Fiber constructed without $this");
+    this.borrow_mut().internal = Internal::Fiber(Rc::new(RefCell::new(Fiber {
+        callback,
+        state: State::Init,
+        frames: Vec::new(),
+        thread: None,
+        retval: Value::Null,
+    })));
+    Ok(Value::Null)
+}
+
+fn lowercase_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `Fiber::start(mixed ...$args): mixed`
+fn start(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let fiber = this_fiber(callee)?;
+    if fiber.borrow().state != State::Init {
+        return Err(fiber_error("Cannot start a fiber that has already been started"));
+    }
+    let callback = fiber.borrow().callback.clone();
+    let (resume, started) = channel();
+    let (finished, suspended) = channel();
+    let vm_ptr = Handoff(vm as *mut Vm);
+    let call = Handoff((callback, args));
+    let stack_size = vm.limits.max_stack_size;
+    let handle = std::thread::Builder::new()
+        .stack_size(stack_size.saturating_mul(2))
+        .spawn(move || run_thread(vm_ptr, call, stack_size, Link { resume: started, suspended: finished }))
+        .map_err(|e| VmError::Fatal(format!("Unable to create the fiber's stack: {}", e)))?;
+    fiber.borrow_mut().thread = Some(Thread { resume, suspended, handle });
+    vm.switch_to(callee.this.clone().unwrap(), fiber, Input::Start)
+}
+
+/// `Fiber::resume(mixed $value = null): mixed`
+fn resume(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Fiber::resume", &args, 0, 1)?;
+    let fiber = this_fiber(callee)?;
+    if fiber.borrow().state != State::Suspended {
+        return Err(fiber_error("Cannot resume a fiber that is not suspended"));
+    }
+    let v = args.into_iter().next().unwrap_or(Value::Null);
+    vm.switch_to(callee.this.clone().unwrap(), fiber, Input::Value(v))
+}
+
+/// `Fiber::throw(Throwable $exception): mixed`
+fn throw(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Fiber::throw", &args, 1, 1)?;
+    let e = match args.into_iter().next().unwrap() {
+        Value::Object(o) if o.borrow().class.is_subclass_of("Throwable") => o,
+        v => {
+            return Err(VmError::type_error(format!(
+                "Fiber::throw(): Argument #1 ($exception) must be of type Throwable, {} given",
+                v.type_name()
+            )));
+        }
+    };
+    let fiber = this_fiber(callee)?;
+    if fiber.borrow().state != State::Suspended {
+        return Err(fiber_error("Cannot resume a fiber that is not suspended"));
+    }
+    vm.switch_to(callee.this.clone().unwrap(), fiber, Input::Throw(e))
+}
+
+/// `Fiber::suspend(mixed $value = null): mixed`
+fn suspend(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Fiber::suspend", &args, 0, 1)?;
+    if vm.fibers.is_empty() {
+        return Err(fiber_error("Cannot suspend outside of fiber"));
+    }
+    let value = args.into_iter().next().unwrap_or(Value::Null);
+    // The innermost fiber is the one running on this thread.
+    let input = LINK.with_borrow(|link| {
+        let link = link.as_ref().expect("suspending fiber runs on its thread");
+        link.suspended.send(Handoff(Outcome::Suspended(value))).ok()?;
+        link.resume.recv().ok()
+    });
+    match input.map(|i| i.0) {
+        Some(Input::Value(v)) => Ok(v),
+        Some(Input::Throw(e)) => Err(VmError::Exception(e)),
+        Some(Input::Destroy) => Err(VmError::Fatal(DESTROYED.to_string())),
+        Some(Input::Start) => unreachable!("fiber started twice"),
+        // The VM went away with the fiber suspended: what this thread
+        // holds belongs to it, so the thread waits for good.
+        None => loop {
+            std::thread::park();
+        },
+    }
+}
+
+/// `Fiber::getReturn(): mixed`
+fn get_return(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Fiber::getReturn", &args, 0, 0)?;
+    let fiber = this_fiber(callee)?;
+    let f = fiber.borrow();
+    let reason = match f.state {
+        State::Returned => return Ok(f.retval.clone()),
+        State::Init => "The fiber has not been started",
+        State::Threw => "The fiber threw an exception",
+        State::Running | State::Suspended => "The fiber has not returned",
+    };
+    Err(fiber_error(&format!("Cannot get fiber return value: {}", reason)))
+}
+
+fn state_is(callee: &Callee, args: &[Value], name: &str, test: fn(State) -> bool) -> VmResult<Value> {
+    expect_args(name, args, 0, 0)?;
+    let fiber = this_fiber(callee)?;
+    let state = fiber.borrow().state;
+    Ok(Value::Bool(test(state)))
+}
+
+/// `Fiber::isStarted(): bool`
+fn is_started(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    state_is(callee, &args, "Fiber::isStarted", |s| s != State::Init)
+}
+
+/// `Fiber::isSuspended(): bool`
+fn is_suspended(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    state_is(callee, &args, "Fiber::isSuspended", |s| s == State::Suspended)
+}
+
+/// `Fiber::isRunning(): bool`
+fn is_running(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    state_is(callee, &args, "Fiber::isRunning", |s| s == State::Running)
+}
+
+/// `Fiber::isTerminated(): bool`
+fn is_terminated(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    state_is(callee, &args, "Fiber::isTerminated", |s| matches!(s, State::Returned | State::Threw))
+}
+
+/// `Fiber::getCurrent(): ?Fiber`
+fn get_current(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Fiber::getCurrent", &args, 0, 0)?;
+    Ok(vm.fibers.last().map_or(Value::Null, |f| Value::Object(f.obj.clone())))
+}
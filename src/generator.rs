@@ -0,0 +1,418 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ast::{ClassKind, Visibility};
+use crate::class::{Internal, Object};
+use crate::compiler::{ClassDef, Function, MethodDef, Op};
+use crate::iterator::Iter;
+use crate::value::{ObjRef, Value};
+use crate::vm::{expect_args, Callee, Frame, NativeFn, Vm, VmError, VmResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Not run yet: the body starts at the first use.
+    Created,
+    Suspended,
+    Running,
+    Done,
+}
+
+/// State of a `Generator` object.
+pub struct Generator {
+    /// The suspended frame of the generator function.
+    frame: Option<Frame>,
+    state: State,
+    /// Key and value of the current `yield`.
+    current: Option<(Value, Value)>,
+    /// Largest integer key yielded so far, for automatic keys.
+    largest_key: i64,
+    /// What a running `yield from` delegates to.
+    delegate: Option<Iter>,
+    retval: Option<Value>,
+    /// Resumed past its first `yield`, so it can no longer be rewound.
+    advanced: bool,
+}
+
+impl Generator {
+    /// Whether the generator function returned or threw.
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// The generator function's frame while it is suspended.
+    pub(crate) fn frame(&self) -> Option<&Frame> {
+        self.frame.as_ref()
+    }
+
+    /// Whether it is suspended in a `try` block with a `finally` block,
+    /// which has to run when the generator is released.
+    pub(crate) fn has_pending_finally(&self) -> bool {
+        let Some(frame) = self.frame.as_ref().filter(|_| self.state == State::Suspended) else { return false };
+        let ip = frame.ip - 1;
+        frame.func.try_blocks.iter().any(|tb| tb.has_finally && (tb.start..tb.finally_start).contains(&ip))
+    }
+}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Generator").field("state", &self.state).finish_non_exhaustive()
+    }
+}
+
+/// What the suspended `yield` evaluates to when the generator resumes.
+enum Input {
+    Start,
+    Send(Value),
+    Throw(ObjRef),
+}
+
+/// One step of a `yield from`.
+enum Step {
+    Yield(Value, Value),
+    Return(Value),
+}
+
+/// The built-in `Generator` class.
+pub fn class_def() -> ClassDef {
+    let methods: [(&str, &[&str], NativeFn); 8] = [
+        ("current", &[], current),
+        ("key", &[], key),
+        ("next", &[], next),
+        ("send", &["value"], send),
+        ("throw", &["exception"], throw),
+        ("valid", &[], valid),
+        ("rewind", &[], rewind),
+        ("getReturn", &[], get_return),
+    ];
+    ClassDef {
+        name: "Generator".into(),
+        kind: ClassKind::Class,
+        is_abstract: false,
+        is_final: true,
+        parent: None,
+        interfaces: vec!["Iterator".into()],
+        backing_type: None,
+        constants: Vec::new(),
+        props: Vec::new(),
+        methods: methods
+            .into_iter()
+            .map(|(name, params, f)| MethodDef {
+                name: name.into(),
+                func: Rc::new(Function::native(name, params, f)),
+                visibility: Visibility::Public,
+                is_static: false,
+                is_abstract: false,
+                is_final: false,
+            })
+            .collect(),
+        trait_uses: Vec::new(),
+        line: 0,
+    }
+}
+
+fn generator_of(obj: &ObjRef) -> Option<Rc<RefCell<Generator>>> {
+    match &obj.borrow().internal {
+        Internal::Generator(g) => Some(g.clone()),
+        _ => None,
+    }
+}
+
+fn this_generator(callee: &Callee) -> Rc<RefCell<Generator>> {
+    let this = callee.this.as_ref().expect("Generator method called without $this");
+    generator_of(this).expect("Generator method called on a non-generator")
+}
+
+impl Vm {
+    /// Wraps the frame of a call to a generator function, which does not
+    /// run until the generator is first used.
+    pub(crate) fn new_generator(&mut self, frame: Frame) -> VmResult<Value> {
+        let class = self.lookup_class("Generator")?;
+        let obj = Object::new(&self.objects, class, Default::default());
+        obj.borrow_mut().internal = Internal::Generator(Rc::new(RefCell::new(Generator {
+            frame: Some(frame),
+            state: State::Created,
+            current: None,
+            largest_key: -1,
+            delegate: None,
+            retval: None,
+            advanced: false,
+        })));
+        Ok(Value::Object(obj))
+    }
+
+    /// Runs a generator that was not used yet to its first `yield`.
+    fn ensure_started(&mut self, generator: &Rc<RefCell<Generator>>) -> VmResult<()> {
+        if generator.borrow().state == State::Created {
+            self.resume(generator, Input::Start)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the generator until its next `yield` or its end.
+    fn resume(&mut self, generator: &Rc<RefCell<Generator>>, mut input: Input) -> VmResult<()> {
+        self.check_stack()?;
+        match generator.borrow().state {
+            State::Running => return Err(VmError::error("Cannot resume an already running generator")),
+            State::Done => {
+                return match input {
+                    Input::Throw(e) => Err(VmError::Exception(e)),
+                    _ => Ok(()),
+                };
+            }
+            State::Created | State::Suspended => {}
+        }
+        if !matches!(input, Input::Start) {
+            generator.borrow_mut().advanced = true;
+        }
+        loop {
+            let delegate = generator.borrow_mut().delegate.take();
+            if let Some(mut d) = delegate {
+                generator.borrow_mut().state = State::Running;
+                let step = self.delegate_step(&mut d, input);
+                generator.borrow_mut().state = State::Suspended;
+                input = match step {
+                    Ok(Step::Yield(k, v)) => {
+                        let mut g = generator.borrow_mut();
+                        g.delegate = Some(d);
+                        g.current = Some((k, v));
+                        return Ok(());
+                    }
+                    Ok(Step::Return(v)) => Input::Send(v),
+                    Err(e) => match self.throwable(e) {
+                        VmError::Exception(e) => Input::Throw(e),
+                        e => {
+                            finish(generator, None);
+                            return Err(e);
+                        }
+                    },
+                };
+            }
+            let frame = {
+                let mut g = generator.borrow_mut();
+                g.state = State::Running;
+                g.current = None;
+                g.frame.take().expect("suspended generator has a frame")
+            };
+            let base = self.frames.len();
+            self.frames.push(frame);
+            let result = match input {
+                Input::Start => self.run(base),
+                Input::Send(v) => {
+                    self.push(v);
+                    self.run(base)
+                }
+                Input::Throw(e) => self.throw(base, e).and_then(|_| self.run(base)),
+            };
+            match result {
+                Ok(_) if self.frames.len() > base => {}
+                Ok(v) => {
+                    finish(generator, Some(v));
+                    return Ok(());
+                }
+                Err(e) => {
+                    finish(generator, None);
+                    return Err(e);
+                }
+            }
+            // suspended at a `yield` or `yield from`
+            let frame = self.frame();
+            let has_key = match &frame.func.code[frame.ip - 1] {
+                Op::Yield(has_key) => Some(*has_key),
+                _ => None,
+            };
+            let operand = self.pop();
+            let Some(has_key) = has_key else {
+                let delegate = match self.iter_new(operand) {
+                    Ok(Some(iter)) => Ok(iter),
+                    Ok(None) => Err(VmError::error("Can use \"yield from\" only with arrays and Traversables")),
+                    Err(e) => Err(e),
+                };
+                let delegate = delegate.map_err(|e| self.throwable(e));
+                let frame = self.frames.pop().unwrap();
+                let mut g = generator.borrow_mut();
+                g.frame = Some(frame);
+                g.state = State::Suspended;
+                input = match delegate {
+                    Ok(iter) => {
+                        g.delegate = Some(iter);
+                        Input::Start
+                    }
+                    Err(VmError::Exception(e)) => Input::Throw(e),
+                    Err(e) => {
+                        drop(g);
+                        finish(generator, None);
+                        return Err(e);
+                    }
+                };
+                continue;
+            };
+            let key = match has_key {
+                true => self.pop(),
+                false => Value::Int(generator.borrow().largest_key.wrapping_add(1)),
+            };
+            let frame = self.frames.pop().unwrap();
+            let mut g = generator.borrow_mut();
+            g.state = State::Suspended;
+            if let Value::Int(n) = key
+                && n > g.largest_key
+            {
+                g.largest_key = n;
+            }
+            g.current = Some((key, operand));
+            g.frame = Some(frame);
+            return Ok(());
+        }
+    }
+
+    /// Runs the `finally` blocks a released generator is suspended in, as
+    /// if its `yield` returned.
+    pub(crate) fn destroy_generator(&mut self, generator: &Rc<RefCell<Generator>>) -> VmResult<()> {
+        if !generator.borrow().has_pending_finally() {
+            return Ok(());
+        }
+        let frame = {
+            let mut g = generator.borrow_mut();
+            g.state = State::Running;
+            g.current = None;
+            g.delegate = None;
+            g.frame.take().expect("suspended generator has a frame")
+        };
+        let base = self.frames.len();
+        self.frames.push(frame);
+        let result = self.return_from(base);
+        finish(generator, None);
+        result
+    }
+
+    /// Advances the iterable a `yield from` delegates to. Values sent to
+    /// an inner generator are passed on; other iterables ignore them.
+    fn delegate_step(&mut self, d: &mut Iter, input: Input) -> VmResult<Step> {
+        if let Iter::Object { obj, .. } = d
+            && let Some(inner) = generator_of(obj)
+        {
+            match input {
+                Input::Start if inner.borrow().state == State::Running => {
+                    return Err(VmError::error("Impossible to yield from the Generator being currently run"));
+                }
+                Input::Start => self.ensure_started(&inner)?,
+                input => self.resume(&inner, input)?,
+            }
+            let g = inner.borrow();
+            return Ok(match &g.current {
+                Some((k, v)) => Step::Yield(k.clone(), v.clone()),
+                None => Step::Return(g.retval.clone().unwrap_or(Value::Null)),
+            });
+        }
+        if let Input::Throw(e) = input {
+            return Err(VmError::Exception(e));
+        }
+        Ok(match self.iter_next(d, true)? {
+            Some((k, v)) => Step::Yield(k, v),
+            None => Step::Return(Value::Null),
+        })
+    }
+}
+
+fn finish(generator: &Rc<RefCell<Generator>>, retval: Option<Value>) {
+    let mut g = generator.borrow_mut();
+    g.state = State::Done;
+    g.frame = None;
+    g.current = None;
+    g.delegate = None;
+    g.retval = retval;
+}
+
+fn current_value(generator: &Rc<RefCell<Generator>>) -> Value {
+    generator.borrow().current.as_ref().map_or(Value::Null, |(_, v)| v.clone())
+}
+
+/// `Generator::current(): mixed`
+fn current(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::current", &args, 0, 0)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    Ok(current_value(&generator))
+}
+
+/// `Generator::key(): mixed`
+fn key(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::key", &args, 0, 0)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    Ok(generator.borrow().current.as_ref().map_or(Value::Null, |(k, _)| k.clone()))
+}
+
+/// `Generator::next(): void`
+fn next(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::next", &args, 0, 0)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    vm.resume(&generator, Input::Send(Value::Null))?;
+    Ok(Value::Null)
+}
+
+/// `Generator::send(mixed $value): mixed`: the value becomes the result
+/// of the current `yield`.
+fn send(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::send", &args, 1, 1)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    vm.resume(&generator, Input::Send(args.into_iter().next().unwrap()))?;
+    Ok(current_value(&generator))
+}
+
+/// `Generator::throw(Throwable $exception): mixed`: throws the exception
+/// at the current `yield`.
+fn throw(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::throw", &args, 1, 1)?;
+    let e = match args.into_iter().next().unwrap() {
+        Value::Object(o) if o.borrow().class.is_subclass_of("Throwable") => o,
+        v => {
+            return Err(VmError::type_error(format!(
+                "Generator::throw(): Argument #1 ($exception) must be of type Throwable, {} given",
+                v.type_name()
+            )));
+        }
+    };
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    vm.resume(&generator, Input::Throw(e))?;
+    Ok(current_value(&generator))
+}
+
+/// `Generator::valid(): bool`
+fn valid(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::valid", &args, 0, 0)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    Ok(Value::Bool(generator.borrow().current.is_some()))
+}
+
+/// `Generator::rewind(): void`, which only starts the generator.
+fn rewind(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::rewind", &args, 0, 0)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    if generator.borrow().advanced {
+        return Err(VmError::Error {
+            class: "Exception",
+            message: "Cannot rewind a generator that was already run".to_string(),
+        });
+    }
+    Ok(Value::Null)
+}
+
+/// `Generator::getReturn(): mixed`
+fn get_return(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("Generator::getReturn", &args, 0, 0)?;
+    let generator = this_generator(callee);
+    vm.ensure_started(&generator)?;
+    let g = generator.borrow();
+    match (&g.state, &g.retval) {
+        (State::Done, Some(v)) => Ok(v.clone()),
+        _ => Err(VmError::Error {
+            class: "Exception",
+            message: "Cannot get return value of a generator that hasn't returned".to_string(),
+        }),
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use crate::ast::IncludeKind;
+use crate::compiler;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::value::Value;
+use crate::vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
+
+impl Vm {
+    /// Runs `include`, `require` and their `_once` forms. Returns the
+    /// value of the expression, or `None` when the file's code was
+    /// pushed to run in the current scope; its return value follows.
+    pub(crate) fn include(&mut self, kind: IncludeKind, path: &str) -> VmResult<Option<Value>> {
+        self.include_in(kind, path, true)
+    }
+
+    /// Runs a file to completion in a variable scope of its own, the way
+    /// Composer's class loader includes files.
+    pub(crate) fn require_isolated(&mut self, path: &str) -> VmResult<()> {
+        let base = self.frames.len();
+        if self.include_in(IncludeKind::RequireOnce, path, false)?.is_none() {
+            self.run(base)?;
+        }
+        Ok(())
+    }
+
+    fn include_in(&mut self, kind: IncludeKind, path: &str, shares_scope: bool) -> VmResult<Option<Value>> {
+        if path.is_empty() {
+            return Err(VmError::Error { class: "ValueError", message: "Path cannot be empty".to_string() });
+        }
+        let Some(file) = self.resolve_include(path) else {
+            self.warn(&format!("{}({}): Failed to open stream: No such file or directory", kind.name(), path));
+            return self.include_failed(kind, path);
+        };
+        let file = file.display().to_string();
+        if kind.is_once() && self.included.contains(&file) {
+            return Ok(Some(Value::Bool(true)));
+        }
+        let source = match std::fs::read(&file) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                self.warn(&format!("{}({}): Failed to open stream: {}", kind.name(), path, e));
+                return self.include_failed(kind, path);
+            }
+        };
+        let ast = Lexer::new(&source).tokenize().and_then(|tokens| Parser::new(tokens).parse());
+        let ast = match ast {
+            Ok(ast) => ast,
+            Err(message) => {
+                // parse errors end in "at line:col"
+                let line = message
+                    .rsplit_once(" at ")
+                    .and_then(|(_, pos)| pos.split(':').next()?.parse().ok())
+                    .unwrap_or(0);
+                self.error_pos = Some((file.as_str().into(), line));
+                return Err(VmError::Error { class: "ParseError", message });
+            }
+        };
+        let script = match compiler::compile_script(&ast, &file) {
+            Ok(script) => script,
+            Err(message) => {
+                self.error_pos = Some((file.as_str().into(), 0));
+                return Err(VmError::Fatal(message));
+            }
+        };
+        self.included.insert(file);
+        self.declare_hoisted(&script)?;
+        self.push_included(Some(kind), script.main.clone(), shares_scope);
+        Ok(None)
+    }
+
+    fn include_failed(&mut self, kind: IncludeKind, path: &str) -> VmResult<Option<Value>> {
+        if kind.is_require() {
+            return Err(VmError::error(format!(
+                "Failed opening required '{}' (include_path='{}')",
+                path, self.include_path
+            )));
+        }
+        self.warn(&format!(
+            "{}(): Failed opening '{}' for inclusion (include_path='{}')",
+            kind.name(),
+            path,
+            self.include_path
+        ));
+        Ok(Some(Value::Bool(false)))
+    }
+
+    /// Finds an included file as PHP does: paths that are absolute or
+    /// start with `./` or `../` are taken as they are, others are looked
+    /// up in the include path and then in the including file's directory.
+    fn resolve_include(&self, path: &str) -> Option<PathBuf> {
+        let p = Path::new(path);
+        let candidates: Vec<PathBuf> = if p.is_absolute() || path.starts_with("./") || path.starts_with("../") {
+            vec![p.to_path_buf()]
+        } else {
+            let current = self.current_file();
+            let own_dir = Path::new(&*current).parent().map(Path::to_path_buf);
+            self.include_path
+                .split(':')
+                .filter(|d| !d.is_empty())
+                .map(|d| Path::new(d).join(p))
+                .chain(own_dir.map(|d| d.join(p)))
+                .collect()
+        };
+        candidates.into_iter().filter(|c| c.is_file()).find_map(|c| c.canonicalize().ok())
+    }
+}
+
+pub const FUNCTIONS: &[(&str, &[&str], NativeFn)] = &[
+    ("get_include_path", &[], get_include_path),
+    ("set_include_path", &["include_path"], set_include_path),
+];
+
+/// `get_include_path(): string`
+fn get_include_path(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("get_include_path", &args, 0, 0)?;
+    Ok(Value::from(vm.include_path.as_str()))
+}
+
+/// `set_include_path(string $include_path): string|false`
+fn set_include_path(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("set_include_path", &args, 1, 1)?;
+    let path = args.into_iter().next().unwrap();
+    let path = vm.coerce_arg(path, "string", "set_include_path", 1, "include_path")?;
+    let path = String::from_utf8_lossy(&path.scalar_to_bytes().unwrap()).into_owned();
+    if path.is_empty() {
+        return Ok(Value::Bool(false));
+    }
+    let old = std::mem::replace(&mut vm.include_path, path);
+    Ok(Value::from(old.as_str()))
+}
@@ -0,0 +1,225 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::class::Internal;
+use crate::value::{ObjRef, Value};
+use crate::vm::{ref_cell, Vm, VmError, VmResult};
+
+/// What `foreach`, `yield from` and unpacking walk over.
+pub(crate) enum Iter {
+    /// A snapshot of an array, or of the visible properties of an object.
+    Array { arr: Rc<PhpArray>, pos: usize },
+    /// An array iterated by reference, which sees the loop's changes.
+    ArrayRef { cell: Rc<RefCell<Value>>, pos: usize },
+    /// A `Traversable` object, driven through the `Iterator` methods.
+    Object { obj: ObjRef, started: bool },
+}
+
+/// A running `foreach` loop: its iterator and the current element.
+pub(crate) struct Foreach {
+    iter: Iter,
+    key: Value,
+    value: Value,
+}
+
+pub(crate) fn key_value(k: &ArrayKey) -> Value {
+    match k {
+        ArrayKey::Int(n) => Value::Int(*n),
+        ArrayKey::Str(s) => Value::Str(s.clone()),
+    }
+}
+
+impl Vm {
+    /// Starts iterating a value; `None` if it is not an array or object.
+    pub(crate) fn iter_new(&mut self, v: Value) -> VmResult<Option<Iter>> {
+        match v.deref() {
+            Value::Array(arr) => Ok(Some(Iter::Array { arr, pos: 0 })),
+            Value::Object(obj) => self.object_iter(obj).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// `Iterator` objects are used as they are and `IteratorAggregate`
+    /// ones are asked for their iterator; other objects iterate over the
+    /// properties visible in the current scope.
+    fn object_iter(&mut self, mut obj: ObjRef) -> VmResult<Iter> {
+        loop {
+            let class = obj.borrow().class.clone();
+            if class.is_subclass_of("Iterator") {
+                return Ok(Iter::Object { obj, started: false });
+            }
+            if !class.is_subclass_of("IteratorAggregate") {
+                break;
+            }
+            let callee = self.method_callee_of(Value::Object(obj.clone()), "getIterator")?;
+            match self.call(callee, Vec::new())? {
+                Value::Object(o) if o.borrow().class.is_subclass_of("Traversable") => obj = o,
+                _ => {
+                    return Err(VmError::Error {
+                        class: "Exception",
+                        message: format!(
+                            "Objects returned by {}::getIterator() must be traversable or implement interface Iterator",
+                            class.name
+                        ),
+                    });
+                }
+            }
+        }
+        let o = obj.borrow();
+        let mut props = PhpArray::new();
+        for (k, v) in o.props.iter() {
+            let visible = match o.class.find_prop_key(k) {
+                Some(info) => self.can_access(info.visibility, &info.class),
+                None => true,
+            };
+            if visible {
+                props.insert(k.clone(), v.deref());
+            }
+        }
+        Ok(Iter::Array { arr: Rc::new(props), pos: 0 })
+    }
+
+    /// Moves to the next element and returns it, or `None` at the end.
+    /// Keys of iterator objects are only fetched when asked for.
+    pub(crate) fn iter_next(&mut self, iter: &mut Iter, want_key: bool) -> VmResult<Option<(Value, Value)>> {
+        match iter {
+            Iter::Array { arr, pos } => {
+                let Some((k, v)) = arr.entry_at(*pos) else { return Ok(None) };
+                *pos += 1;
+                Ok(Some((key_value(k), v.deref())))
+            }
+            Iter::ArrayRef { cell, pos } => {
+                let mut target = cell.borrow_mut();
+                let Value::Array(arr) = &mut *target else { return Ok(None) };
+                let Some((k, v)) = Rc::make_mut(arr).entry_at_mut(*pos) else { return Ok(None) };
+                *pos += 1;
+                Ok(Some((key_value(k), Value::Ref(ref_cell(v)))))
+            }
+            Iter::Object { obj, started } => {
+                let this = Value::Object(obj.clone());
+                let step = if *started { "next" } else { "rewind" };
+                *started = true;
+                self.call_method(&this, step)?;
+                if !self.call_method(&this, "valid")?.to_bool() {
+                    return Ok(None);
+                }
+                let value = self.call_method(&this, "current")?;
+                let key = if want_key { self.call_method(&this, "key")? } else { Value::Null };
+                Ok(Some((key, value)))
+            }
+        }
+    }
+
+    fn call_method(&mut self, obj: &Value, name: &str) -> VmResult<Value> {
+        let callee = self.method_callee_of(obj.clone(), name)?;
+        self.call(callee, Vec::new())
+    }
+
+    /// The key/value pairs spread by `...`: `what` is "array" or
+    /// "argument" for the error about keys of other types.
+    pub(crate) fn unpack_pairs(&mut self, v: Value, what: &str) -> VmResult<Option<Vec<(ArrayKey, Value)>>> {
+        match v {
+            Value::Array(arr) => Ok(Some(arr.iter().map(|(k, v)| (k.clone(), v.deref())).collect())),
+            Value::Object(o) if o.borrow().class.is_subclass_of("Traversable") => {
+                let Some(mut iter) = self.iter_new(Value::Object(o))? else { unreachable!() };
+                let mut pairs = Vec::new();
+                while let Some((k, v)) = self.iter_next(&mut iter, true)? {
+                    let key = match k {
+                        Value::Int(n) => ArrayKey::Int(n),
+                        Value::Str(s) => ArrayKey::from_bytes(&s),
+                        _ => {
+                            return Err(VmError::error(format!(
+                                "Keys must be of type int|string during {} unpacking",
+                                what
+                            )));
+                        }
+                    };
+                    pairs.push((key, v));
+                }
+                Ok(Some(pairs))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Starts the `foreach` loop in `slot`; warns and returns false if
+    /// the value cannot be iterated.
+    pub(crate) fn foreach_init(&mut self, slot: usize, v: Value, by_ref: bool) -> VmResult<bool> {
+        if let Value::Object(o) = &v
+            && let Internal::Generator(g) = &o.borrow().internal
+            && g.borrow().is_finished()
+        {
+            return Err(VmError::Error {
+                class: "Exception",
+                message: "Cannot traverse an already closed generator".to_string(),
+            });
+        }
+        if by_ref && let Value::Object(o) = &v {
+            let o = o.borrow();
+            if matches!(o.internal, Internal::Generator(_)) {
+                return Err(VmError::Error {
+                    class: "Exception",
+                    message: "You can only iterate a generator by-reference if it declared that it yields by-reference"
+                        .to_string(),
+                });
+            }
+            if o.class.is_subclass_of("Traversable") {
+                return Err(VmError::error("An iterator cannot be used with foreach by reference"));
+            }
+        }
+        let type_name = v.type_name();
+        let Some(iter) = self.iter_new(v)? else {
+            self.warn(&format!("foreach() argument must be of type array|object, {} given", type_name));
+            return Ok(false);
+        };
+        self.set_foreach(slot, iter);
+        Ok(true)
+    }
+
+    /// Starts a by-reference `foreach` over the array in a place.
+    pub(crate) fn foreach_init_ref(&mut self, slot: usize, cell: Rc<RefCell<Value>>) -> VmResult<bool> {
+        let v = cell.borrow().clone();
+        match v {
+            Value::Array(_) => {
+                self.set_foreach(slot, Iter::ArrayRef { cell, pos: 0 });
+                Ok(true)
+            }
+            v => self.foreach_init(slot, v, true),
+        }
+    }
+
+    fn set_foreach(&mut self, slot: usize, iter: Iter) {
+        let iters = &mut self.frame_mut().iters;
+        if iters.len() <= slot {
+            iters.resize_with(slot + 1, || None);
+        }
+        iters[slot] = Some(Foreach { iter, key: Value::Null, value: Value::Null });
+    }
+
+    /// Advances the loop in `slot`; false when it is done.
+    pub(crate) fn foreach_fetch(&mut self, slot: usize, want_key: bool) -> VmResult<bool> {
+        let mut f = self.frame_mut().iters[slot].take().expect("foreach loop started");
+        let next = self.iter_next(&mut f.iter, want_key)?;
+        let more = next.is_some();
+        if let Some((k, v)) = next {
+            (f.key, f.value) = (k, v);
+        }
+        self.frame_mut().iters[slot] = Some(f);
+        Ok(more)
+    }
+
+    pub(crate) fn foreach_key(&self, slot: usize) -> Value {
+        self.frame().iters[slot].as_ref().unwrap().key.clone()
+    }
+
+    pub(crate) fn foreach_value(&self, slot: usize) -> Value {
+        self.frame().iters[slot].as_ref().unwrap().value.deref()
+    }
+
+    /// The cell of the current element, for by-reference loops.
+    pub(crate) fn foreach_cell(&mut self, slot: usize) -> Rc<RefCell<Value>> {
+        let f = self.frame_mut().iters[slot].as_mut().unwrap();
+        ref_cell(&mut f.value)
+    }
+}
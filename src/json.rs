@@ -0,0 +1,728 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::ClassKind;
+use crate::class::Object;
+use crate::extension::{arg_int, arg_str, NativeFunction};
+use crate::limits::Growth;
+use crate::value::{format_float, parse_numeric_prefix, Numeric, ObjRef, Value};
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+/// `json_encode()`, `json_decode()` and their error reporting, as PHP's
+/// json extension has them.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction { name: "json_encode", params: &["mixed value", "int flags = 0", "int depth = 512"], func: json_encode },
+    NativeFunction {
+        name: "json_decode",
+        params: &["string json", "?bool associative = null", "int depth = 512", "int flags = 0"],
+        func: json_decode,
+    },
+    NativeFunction { name: "json_last_error", params: &[], func: json_last_error },
+    NativeFunction { name: "json_last_error_msg", params: &[], func: json_last_error_msg },
+];
+
+const HEX_TAG: i64 = 1;
+const HEX_AMP: i64 = 2;
+const HEX_APOS: i64 = 4;
+const HEX_QUOT: i64 = 8;
+const FORCE_OBJECT: i64 = 16;
+const NUMERIC_CHECK: i64 = 32;
+const UNESCAPED_SLASHES: i64 = 64;
+const PRETTY_PRINT: i64 = 128;
+const UNESCAPED_UNICODE: i64 = 256;
+const PARTIAL_OUTPUT_ON_ERROR: i64 = 512;
+const PRESERVE_ZERO_FRACTION: i64 = 1024;
+const UNESCAPED_LINE_TERMINATORS: i64 = 2048;
+const OBJECT_AS_ARRAY: i64 = 1;
+const BIGINT_AS_STRING: i64 = 2;
+const INVALID_UTF8_IGNORE: i64 = 1 << 20;
+const INVALID_UTF8_SUBSTITUTE: i64 = 1 << 21;
+const THROW_ON_ERROR: i64 = 1 << 22;
+
+const ERROR_NONE: i64 = 0;
+const ERROR_DEPTH: i64 = 1;
+const ERROR_STATE_MISMATCH: i64 = 2;
+const ERROR_CTRL_CHAR: i64 = 3;
+const ERROR_SYNTAX: i64 = 4;
+const ERROR_UTF8: i64 = 5;
+const ERROR_RECURSION: i64 = 6;
+const ERROR_INF_OR_NAN: i64 = 7;
+const ERROR_UNSUPPORTED_TYPE: i64 = 8;
+const ERROR_INVALID_PROPERTY_NAME: i64 = 9;
+const ERROR_UTF16: i64 = 10;
+const ERROR_NON_BACKED_ENUM: i64 = 11;
+
+pub(crate) const CONSTANTS: &[(&str, i64)] = &[
+    ("JSON_HEX_TAG", HEX_TAG),
+    ("JSON_HEX_AMP", HEX_AMP),
+    ("JSON_HEX_APOS", HEX_APOS),
+    ("JSON_HEX_QUOT", HEX_QUOT),
+    ("JSON_FORCE_OBJECT", FORCE_OBJECT),
+    ("JSON_NUMERIC_CHECK", NUMERIC_CHECK),
+    ("JSON_UNESCAPED_SLASHES", UNESCAPED_SLASHES),
+    ("JSON_PRETTY_PRINT", PRETTY_PRINT),
+    ("JSON_UNESCAPED_UNICODE", UNESCAPED_UNICODE),
+    ("JSON_PARTIAL_OUTPUT_ON_ERROR", PARTIAL_OUTPUT_ON_ERROR),
+    ("JSON_PRESERVE_ZERO_FRACTION", PRESERVE_ZERO_FRACTION),
+    ("JSON_UNESCAPED_LINE_TERMINATORS", UNESCAPED_LINE_TERMINATORS),
+    ("JSON_OBJECT_AS_ARRAY", OBJECT_AS_ARRAY),
+    ("JSON_BIGINT_AS_STRING", BIGINT_AS_STRING),
+    ("JSON_INVALID_UTF8_IGNORE", INVALID_UTF8_IGNORE),
+    ("JSON_INVALID_UTF8_SUBSTITUTE", INVALID_UTF8_SUBSTITUTE),
+    ("JSON_THROW_ON_ERROR", THROW_ON_ERROR),
+    ("JSON_ERROR_NONE", ERROR_NONE),
+    ("JSON_ERROR_DEPTH", ERROR_DEPTH),
+    ("JSON_ERROR_STATE_MISMATCH", ERROR_STATE_MISMATCH),
+    ("JSON_ERROR_CTRL_CHAR", ERROR_CTRL_CHAR),
+    ("JSON_ERROR_SYNTAX", ERROR_SYNTAX),
+    ("JSON_ERROR_UTF8", ERROR_UTF8),
+    ("JSON_ERROR_RECURSION", ERROR_RECURSION),
+    ("JSON_ERROR_INF_OR_NAN", ERROR_INF_OR_NAN),
+    ("JSON_ERROR_UNSUPPORTED_TYPE", ERROR_UNSUPPORTED_TYPE),
+    ("JSON_ERROR_INVALID_PROPERTY_NAME", ERROR_INVALID_PROPERTY_NAME),
+    ("JSON_ERROR_UTF16", ERROR_UTF16),
+    ("JSON_ERROR_NON_BACKED_ENUM", ERROR_NON_BACKED_ENUM),
+];
+
+fn error_message(code: i64) -> &'static str {
+    match code {
+        ERROR_NONE => "No error",
+        ERROR_DEPTH => "Maximum stack depth exceeded",
+        ERROR_STATE_MISMATCH => "State mismatch (invalid or malformed JSON)",
+        ERROR_CTRL_CHAR => "Control character error, possibly incorrectly encoded",
+        ERROR_SYNTAX => "Syntax error",
+        ERROR_UTF8 => "Malformed UTF-8 characters, possibly incorrectly encoded",
+        ERROR_RECURSION => "Recursion detected",
+        ERROR_INF_OR_NAN => "Inf and NaN cannot be JSON encoded",
+        ERROR_UNSUPPORTED_TYPE => "Type is not supported",
+        ERROR_INVALID_PROPERTY_NAME => "The decoded property name is invalid",
+        ERROR_UTF16 => "Single unpaired UTF-16 surrogate in unicode escape",
+        ERROR_NON_BACKED_ENUM => "Non-backed enums have no default serialization",
+        _ => "Unknown error",
+    }
+}
+
+fn json_exception(vm: &mut Vm, code: i64) -> VmError {
+    match vm.create_exception("JsonException", error_message(code)) {
+        Ok(obj) => {
+            obj.borrow_mut().props.insert(ArrayKey::from("code"), Value::Int(code));
+            VmError::Exception(obj)
+        }
+        Err(e) => e,
+    }
+}
+
+/// Writes JSON. An error stops the encoding unless partial output was
+/// asked for, in which case it is recorded and a stand-in written.
+struct Encoder {
+    out: Vec<u8>,
+    flags: i64,
+    depth: i64,
+    max_depth: i64,
+    error: i64,
+    /// The arrays and objects being written, to detect recursion.
+    arrays: Vec<*const PhpArray>,
+    objects: Vec<*const RefCell<Object>>,
+    growth: Growth,
+}
+
+type Encoded = Result<(), i64>;
+
+impl Encoder {
+    fn has(&self, flag: i64) -> bool {
+        self.flags & flag != 0
+    }
+
+    fn fail(&mut self, code: i64, stand_in: &[u8]) -> Encoded {
+        if !self.has(PARTIAL_OUTPUT_ON_ERROR) {
+            return Err(code);
+        }
+        self.error = code;
+        self.out.extend_from_slice(stand_in);
+        Ok(())
+    }
+
+    fn raw(&mut self, text: &[u8]) -> Encoded {
+        self.out.extend_from_slice(text);
+        Ok(())
+    }
+
+    fn newline(&mut self) {
+        if self.has(PRETTY_PRINT) {
+            self.out.push(b'\n');
+            for _ in 0..self.depth {
+                self.out.extend_from_slice(b"    ");
+            }
+        }
+    }
+
+    fn float(&mut self, f: f64) -> Encoded {
+        if !f.is_finite() {
+            return self.fail(ERROR_INF_OR_NAN, b"0");
+        }
+        let s = format_float(f, -1).replace('E', "e");
+        self.out.extend_from_slice(s.as_bytes());
+        if self.has(PRESERVE_ZERO_FRACTION) && !s.contains(['.', 'e']) {
+            self.out.extend_from_slice(b".0");
+        }
+        Ok(())
+    }
+
+    fn escape_unit(&mut self, unit: u32) {
+        self.out.extend_from_slice(format!("\\u{:04x}", unit).as_bytes());
+    }
+
+    fn string(&mut self, s: &[u8], numeric_check: bool) -> Encoded {
+        if numeric_check && let Some((n, true)) = parse_numeric_prefix(s) {
+            return match n {
+                Numeric::Int(n) => self.raw(n.to_string().as_bytes()),
+                Numeric::Float(f) => self.float(f),
+            };
+        }
+        let start = self.out.len();
+        self.out.push(b'"');
+        for chunk in s.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                self.char(c);
+            }
+            if chunk.invalid().is_empty() {
+                continue;
+            }
+            if self.has(INVALID_UTF8_SUBSTITUTE) {
+                self.char('\u{fffd}');
+            } else if !self.has(INVALID_UTF8_IGNORE) {
+                self.out.truncate(start);
+                return self.fail(ERROR_UTF8, b"null");
+            }
+        }
+        self.out.push(b'"');
+        Ok(())
+    }
+
+    fn char(&mut self, c: char) {
+        let escaped: &[u8] = match c {
+            '"' if self.has(HEX_QUOT) => b"\\u0022",
+            '"' => b"\\\"",
+            '\\' => b"\\\\",
+            '/' if !self.has(UNESCAPED_SLASHES) => b"\\/",
+            '\u{8}' => b"\\b",
+            '\u{c}' => b"\\f",
+            '\n' => b"\\n",
+            '\r' => b"\\r",
+            '\t' => b"\\t",
+            '<' if self.has(HEX_TAG) => b"\\u003C",
+            '>' if self.has(HEX_TAG) => b"\\u003E",
+            '&' if self.has(HEX_AMP) => b"\\u0026",
+            '\'' if self.has(HEX_APOS) => b"\\u0027",
+            c if c < ' ' => return self.escape_unit(c as u32),
+            c if c.is_ascii() => return self.out.push(c as u8),
+            '\u{2028}' | '\u{2029}' if self.has(UNESCAPED_UNICODE) && !self.has(UNESCAPED_LINE_TERMINATORS) => {
+                return self.escape_unit(c as u32);
+            }
+            c if self.has(UNESCAPED_UNICODE) => {
+                let mut buf = [0; 4];
+                return self.out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    self.escape_unit(u32::from(*unit));
+                }
+                return;
+            }
+        };
+        self.out.extend_from_slice(escaped);
+    }
+
+    fn value(&mut self, vm: &mut Vm, v: &Value) -> VmResult<Encoded> {
+        vm.check_stack()?;
+        self.growth.grow_to(vm, self.out.len())?;
+        let result = match v.deref() {
+            Value::Null => self.raw(b"null"),
+            Value::Bool(b) => self.raw(if b { b"true" } else { b"false" }),
+            Value::Int(n) => self.raw(n.to_string().as_bytes()),
+            Value::Float(f) => self.float(f),
+            Value::Str(s) => self.string(&s, self.has(NUMERIC_CHECK)),
+            Value::Array(arr) => {
+                if self.arrays.contains(&Rc::as_ptr(&arr)) {
+                    return Ok(self.fail(ERROR_RECURSION, b"null"));
+                }
+                let as_object = self.has(FORCE_OBJECT) || !arr.iter().enumerate().all(|(i, (k, _))| *k == ArrayKey::Int(i as i64));
+                self.arrays.push(Rc::as_ptr(&arr));
+                let result = self.entries(vm, &arr, as_object, false);
+                self.arrays.pop();
+                result?
+            }
+            Value::Object(obj) => self.object(vm, &obj)?,
+            Value::Resource(_) => self.fail(ERROR_UNSUPPORTED_TYPE, b"null"),
+            Value::Ref(_) => unreachable!(),
+        };
+        Ok(result)
+    }
+
+    fn object(&mut self, vm: &mut Vm, obj: &ObjRef) -> VmResult<Encoded> {
+        if self.objects.contains(&Rc::as_ptr(obj)) {
+            return Ok(self.fail(ERROR_RECURSION, b"null"));
+        }
+        let class = obj.borrow().class.clone();
+        if class.is_subclass_of("JsonSerializable") {
+            self.objects.push(Rc::as_ptr(obj));
+            let data = vm.method_callee_of(Value::Object(obj.clone()), "jsonSerialize").and_then(|m| vm.call(m, Vec::new()));
+            let data = match data {
+                Ok(data) => data,
+                Err(e) => {
+                    self.objects.pop();
+                    return Err(e);
+                }
+            };
+            // `return $this;` is written as a plain object.
+            if let Value::Object(o) = data.deref()
+                && Rc::ptr_eq(&o, obj)
+            {
+                self.objects.pop();
+                return self.properties(vm, obj);
+            }
+            let result = self.value(vm, &data);
+            self.objects.pop();
+            return result;
+        }
+        if class.kind == ClassKind::Enum {
+            let value = match class.decl.backing_type {
+                Some(_) => obj.borrow().props.get(&ArrayKey::from("value")).cloned(),
+                None => None,
+            };
+            return match value {
+                Some(v) => self.value(vm, &v),
+                None => Ok(self.fail(ERROR_NON_BACKED_ENUM, b"0")),
+            };
+        }
+        self.properties(vm, obj)
+    }
+
+    /// An object's public properties.
+    fn properties(&mut self, vm: &mut Vm, obj: &ObjRef) -> VmResult<Encoded> {
+        let props = obj.borrow().to_array();
+        self.objects.push(Rc::as_ptr(obj));
+        let result = self.entries(vm, &props, true, true);
+        self.objects.pop();
+        result
+    }
+
+    fn entries(&mut self, vm: &mut Vm, arr: &PhpArray, as_object: bool, skip_mangled: bool) -> VmResult<Encoded> {
+        self.out.push(if as_object { b'{' } else { b'[' });
+        self.depth += 1;
+        if self.depth > self.max_depth
+            && let Err(code) = self.fail(ERROR_DEPTH, b"")
+        {
+            return Ok(Err(code));
+        }
+        let mut first = true;
+        for (k, v) in arr.iter() {
+            if skip_mangled && matches!(k, ArrayKey::Str(s) if s.first() == Some(&0)) {
+                continue;
+            }
+            if !first {
+                self.out.push(b',');
+            }
+            first = false;
+            self.newline();
+            if as_object {
+                match k {
+                    ArrayKey::Int(n) => self.out.extend_from_slice(format!("\"{}\"", n).as_bytes()),
+                    ArrayKey::Str(s) => {
+                        if let Err(code) = self.string(s, false) {
+                            return Ok(Err(code));
+                        }
+                    }
+                }
+                self.out.push(b':');
+                if self.has(PRETTY_PRINT) {
+                    self.out.push(b' ');
+                }
+            }
+            if let Err(code) = self.value(vm, v)? {
+                return Ok(Err(code));
+            }
+        }
+        self.depth -= 1;
+        if !first {
+            self.newline();
+        }
+        self.out.push(if as_object { b'}' } else { b']' });
+        Ok(Ok(()))
+    }
+}
+
+/// `json_encode(mixed $value, int $flags = 0, int $depth = 512): string|false`
+fn json_encode(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let flags = arg_int(&args, 1, 0);
+    let mut e = Encoder {
+        out: Vec::new(),
+        flags,
+        depth: 0,
+        max_depth: arg_int(&args, 2, 512),
+        error: ERROR_NONE,
+        arrays: Vec::new(),
+        objects: Vec::new(),
+        growth: Growth::new(),
+    };
+    let error = match e.value(vm, &args[0])? {
+        Ok(()) => e.error,
+        Err(code) => code,
+    };
+    if flags & THROW_ON_ERROR != 0 && flags & PARTIAL_OUTPUT_ON_ERROR == 0 {
+        if error != ERROR_NONE {
+            return Err(json_exception(vm, error));
+        }
+    } else {
+        vm.json_error = error;
+        if error != ERROR_NONE && flags & PARTIAL_OUTPUT_ON_ERROR == 0 {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::from(e.out))
+}
+
+/// Reads JSON. Errors are the `JSON_ERROR_*` code PHP's parser reports.
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    flags: i64,
+    assoc: bool,
+    depth: i64,
+    max_depth: i64,
+}
+
+type Decoded<T> = Result<T, i64>;
+
+impl Decoder<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.data.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    /// The next significant byte, which is left unread.
+    fn peek(&mut self) -> Decoded<Option<u8>> {
+        self.skip_whitespace();
+        match self.data.get(self.pos) {
+            Some(0) => Err(ERROR_CTRL_CHAR),
+            c => Ok(c.copied()),
+        }
+    }
+
+    fn literal(&mut self, word: &[u8], v: Value) -> Decoded<Value> {
+        if !self.data[self.pos..].starts_with(word) {
+            return Err(ERROR_SYNTAX);
+        }
+        self.pos += word.len();
+        Ok(v)
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`
+    fn number(&mut self) -> Decoded<Value> {
+        let start = self.pos;
+        if self.data.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+        match self.data.get(self.pos) {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                self.digits();
+            }
+            _ => return Err(ERROR_SYNTAX),
+        }
+        let mut is_float = false;
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            if self.digits() == 0 {
+                return Err(ERROR_SYNTAX);
+            }
+            is_float = true;
+        }
+        if let Some(b'e' | b'E') = self.data.get(self.pos) {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.data.get(self.pos) {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return Err(ERROR_SYNTAX);
+            }
+            is_float = true;
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).expect("numbers are ASCII");
+        if !is_float {
+            if let Ok(n) = text.parse() {
+                return Ok(Value::Int(n));
+            }
+            if self.flags & BIGINT_AS_STRING != 0 {
+                return Ok(Value::from(text));
+            }
+        }
+        Ok(Value::Float(text.parse().expect("JSON numbers parse as floats")))
+    }
+
+    fn hex4(&mut self) -> Decoded<u32> {
+        let hex = self.data.get(self.pos..self.pos + 4).ok_or(ERROR_SYNTAX)?;
+        let unit = std::str::from_utf8(hex).ok().filter(|h| h.bytes().all(|c| c.is_ascii_hexdigit()));
+        let unit = unit.and_then(|h| u32::from_str_radix(h, 16).ok()).ok_or(ERROR_SYNTAX)?;
+        self.pos += 4;
+        Ok(unit)
+    }
+
+    /// A `\uXXXX` escape, with the low half of a surrogate pair.
+    fn unicode_escape(&mut self) -> Decoded<char> {
+        let unit = self.hex4()?;
+        let c = match unit {
+            0xd800..=0xdbff => {
+                if !self.data[self.pos..].starts_with(b"\\u") {
+                    return Err(ERROR_UTF16);
+                }
+                self.pos += 2;
+                let low = self.hex4().map_err(|_| ERROR_UTF16)?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(ERROR_UTF16);
+                }
+                0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)
+            }
+            0xdc00..=0xdfff => return Err(ERROR_UTF16),
+            unit => unit,
+        };
+        Ok(char::from_u32(c).expect("surrogates were combined"))
+    }
+
+    fn string(&mut self) -> Decoded<Vec<u8>> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let start = self.pos;
+            while let Some(&c) = self.data.get(self.pos) {
+                if c == b'"' || c == b'\\' || c < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            for chunk in self.data[start..self.pos].utf8_chunks() {
+                out.extend_from_slice(chunk.valid().as_bytes());
+                if chunk.invalid().is_empty() {
+                    continue;
+                }
+                if self.flags & INVALID_UTF8_SUBSTITUTE != 0 {
+                    out.extend_from_slice("\u{fffd}".as_bytes());
+                } else if self.flags & INVALID_UTF8_IGNORE == 0 {
+                    return Err(ERROR_UTF8);
+                }
+            }
+            let Some(&c) = self.data.get(self.pos) else { return Err(ERROR_CTRL_CHAR) };
+            self.pos += 1;
+            let escaped = match c {
+                b'"' => return Ok(out),
+                b'\\' => self.data.get(self.pos).copied(),
+                _ => return Err(ERROR_CTRL_CHAR),
+            };
+            self.pos += 1;
+            let c = match escaped {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => self.unicode_escape()?,
+                _ => return Err(ERROR_SYNTAX),
+            };
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+
+    fn enter(&mut self) -> Decoded<()> {
+        self.pos += 1;
+        self.depth += 1;
+        if self.depth > self.max_depth {
+            return Err(ERROR_DEPTH);
+        }
+        Ok(())
+    }
+
+    /// After a member: `,` to go on, or `close` to end. The other kind of
+    /// bracket is a state mismatch.
+    fn next_member(&mut self, close: u8) -> Decoded<bool> {
+        match self.peek()? {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(c) if c == close => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b']' | b'}') => Err(ERROR_STATE_MISMATCH),
+            _ => Err(ERROR_SYNTAX),
+        }
+    }
+
+    fn array(&mut self, vm: &mut Vm) -> VmResult<Decoded<Value>> {
+        if let Err(code) = self.enter() {
+            return Ok(Err(code));
+        }
+        let mut arr = PhpArray::new();
+        match self.peek() {
+            Ok(Some(b']')) => self.pos += 1,
+            Ok(Some(b'}')) => return Ok(Err(ERROR_STATE_MISMATCH)),
+            Err(code) => return Ok(Err(code)),
+            Ok(_) => loop {
+                match self.value(vm)? {
+                    Ok(v) => arr.push(v).map_err(VmError::error)?,
+                    Err(code) => return Ok(Err(code)),
+                }
+                match self.next_member(b']') {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(code) => return Ok(Err(code)),
+                }
+            },
+        }
+        self.depth -= 1;
+        Ok(Ok(Value::from(arr)))
+    }
+
+    /// Like PHP's parser, makes the object once its first member is read,
+    /// so objects inside that member are numbered before it.
+    fn object(&mut self, vm: &mut Vm) -> VmResult<Decoded<Value>> {
+        if let Err(code) = self.enter() {
+            return Ok(Err(code));
+        }
+        let mut props = PhpArray::new();
+        let mut obj = None;
+        match self.peek() {
+            Ok(Some(b'}')) => self.pos += 1,
+            Ok(Some(b']')) => return Ok(Err(ERROR_STATE_MISMATCH)),
+            Err(code) => return Ok(Err(code)),
+            Ok(_) => loop {
+                let key = match self.key() {
+                    Ok(key) => key,
+                    Err(code) => return Ok(Err(code)),
+                };
+                let v = match self.value(vm)? {
+                    Ok(v) => v,
+                    Err(code) => return Ok(Err(code)),
+                };
+                if self.assoc {
+                    props.insert(ArrayKey::from_bytes(&key), v);
+                } else {
+                    obj.get_or_insert_with(|| std_class(vm)).borrow_mut().props.insert(ArrayKey::from_bytes(&key), v);
+                }
+                match self.next_member(b'}') {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(code) => return Ok(Err(code)),
+                }
+            },
+        }
+        self.depth -= 1;
+        if self.assoc {
+            return Ok(Ok(Value::from(props)));
+        }
+        Ok(Ok(Value::Object(obj.unwrap_or_else(|| std_class(vm)))))
+    }
+
+    /// `"name":`
+    fn key(&mut self) -> Decoded<Vec<u8>> {
+        if self.peek()? != Some(b'"') {
+            return Err(ERROR_SYNTAX);
+        }
+        let key = self.string()?;
+        if !self.assoc && key.first() == Some(&0) {
+            return Err(ERROR_INVALID_PROPERTY_NAME);
+        }
+        if self.peek()? != Some(b':') {
+            return Err(ERROR_SYNTAX);
+        }
+        self.pos += 1;
+        Ok(key)
+    }
+
+    fn value(&mut self, vm: &mut Vm) -> VmResult<Decoded<Value>> {
+        vm.check_stack()?;
+        let c = match self.peek() {
+            Ok(Some(c)) => c,
+            Ok(None) => return Ok(Err(ERROR_SYNTAX)),
+            Err(code) => return Ok(Err(code)),
+        };
+        Ok(match c {
+            b'[' => return self.array(vm),
+            b'{' => return self.object(vm),
+            b'"' => self.string().map(Value::from),
+            b't' => self.literal(b"true", Value::Bool(true)),
+            b'f' => self.literal(b"false", Value::Bool(false)),
+            b'n' => self.literal(b"null", Value::Null),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(ERROR_SYNTAX),
+        })
+    }
+}
+
+fn std_class(vm: &Vm) -> ObjRef {
+    let class = vm.find_class("stdClass").expect("stdClass is declared by the prelude");
+    Object::new(&vm.objects, class, PhpArray::new())
+}
+
+/// `json_decode(string $json, ?bool $associative = null, int $depth = 512, int $flags = 0): mixed`
+fn json_decode(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let depth = arg_int(&args, 2, 512);
+    if depth <= 0 {
+        return Err(VmError::value_error("json_decode(): Argument #3 ($depth) must be greater than 0"));
+    }
+    if depth > i64::from(i32::MAX) {
+        return Err(VmError::value_error(format!("json_decode(): Argument #3 ($depth) must be less than {}", i32::MAX)));
+    }
+    let flags = arg_int(&args, 3, 0);
+    let assoc = match args.get(1) {
+        Some(Value::Bool(b)) => *b,
+        _ => flags & OBJECT_AS_ARRAY != 0,
+    };
+    let mut d = Decoder { data: arg_str(&args, 0), pos: 0, flags, assoc, depth: 0, max_depth: depth };
+    let result = match d.value(vm)? {
+        Ok(v) => match d.peek() {
+            Ok(None) => Ok(v),
+            Ok(Some(_)) => Err(ERROR_SYNTAX),
+            Err(code) => Err(code),
+        },
+        Err(code) => Err(code),
+    };
+    match result {
+        Ok(v) => {
+            if flags & THROW_ON_ERROR == 0 {
+                vm.json_error = ERROR_NONE;
+            }
+            Ok(v)
+        }
+        Err(code) if flags & THROW_ON_ERROR != 0 => Err(json_exception(vm, code)),
+        Err(code) => {
+            vm.json_error = code;
+            Ok(Value::Null)
+        }
+    }
+}
+
+/// `json_last_error(): int`
+fn json_last_error(vm: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(vm.json_error))
+}
+
+/// `json_last_error_msg(): string`
+fn json_last_error_msg(vm: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(error_message(vm.json_error)))
+}
@@ -39,6 +39,61 @@ impl Lexer {
         ch
     }
 
+    /// A cast such as `( int )`, read after the `(`. Only spaces and tabs
+    /// may surround the type, as in PHP's lexer.
+    fn read_cast(&mut self) -> Option<Token> {
+        let is_blank = |c: char| c == ' ' || c == '\t';
+        let mut i = self.pos;
+        while self.source.get(i).is_some_and(|c| is_blank(*c)) {
+            i += 1;
+        }
+        let start = i;
+        while self.source.get(i).is_some_and(|c| c.is_ascii_alphabetic()) {
+            i += 1;
+        }
+        let ty: String = self.source[start..i].iter().collect::<String>().to_ascii_lowercase();
+        while self.source.get(i).is_some_and(|c| is_blank(*c)) {
+            i += 1;
+        }
+        if self.source.get(i) != Some(&')') {
+            return None;
+        }
+        let token = match ty.as_str() {
+            "int" | "integer" => Token::IntCast,
+            "float" | "double" => Token::FloatCast,
+            "string" | "binary" => Token::StringCast,
+            "bool" | "boolean" => Token::BoolCast,
+            "array" => Token::ArrayCast,
+            "object" => Token::ObjectCast,
+            _ => return None,
+        };
+        while self.pos <= i {
+            self.advance();
+        }
+        Some(token)
+    }
+
+    /// Consumes the `from` of `yield from`, which may be separated from
+    /// the `yield` by any whitespace.
+    fn read_from_keyword(&mut self) -> bool {
+        let mut i = self.pos;
+        while self.source.get(i).is_some_and(|c| c.is_whitespace()) {
+            i += 1;
+        }
+        if i == self.pos || i + 4 > self.source.len() {
+            return false;
+        }
+        let word: String = self.source[i..i + 4].iter().collect();
+        let ends = !self.source.get(i + 4).is_some_and(|c| c.is_alphanumeric() || *c == '_');
+        if !word.eq_ignore_ascii_case("from") || !ends {
+            return false;
+        }
+        while self.pos < i + 4 {
+            self.advance();
+        }
+        true
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek() {
             if c.is_whitespace() {
@@ -106,7 +161,8 @@ impl Lexer {
         Token::StringLiteral(s)
     }
 
-    fn read_number(&mut self, first: char) -> Token {
+    /// Integers too large for an `int` become floats, as in PHP.
+    fn read_number(&mut self, first: char) -> Result<Token, String> {
         let mut num = String::new();
         num.push(first);
         let mut is_float = false;
@@ -117,7 +173,7 @@ impl Lexer {
                 self.advance();
             } else if c == '.' && !is_float {
                 // check if it is ..
-                if self.peek_next().map_or(false, |n| n.is_ascii_digit()) {
+                if self.peek_next().is_some_and(|n| n.is_ascii_digit()) {
                     is_float = true;
                     num.push(c);
                     self.advance();
@@ -129,11 +185,10 @@ impl Lexer {
             }
         }
 
-        if is_float {
-            Token::Float(num.parse().unwrap())
-        } else {
-            Token::Integer(num.parse().unwrap())
+        if !is_float && let Ok(n) = num.parse() {
+            return Ok(Token::Integer(n));
         }
+        num.parse().map(Token::Float).map_err(|_| format!("Invalid numeric literal {}", num))
     }
 
     fn read_identifier(&mut self, first: char) -> String {
@@ -150,8 +205,20 @@ impl Lexer {
         ident
     }
 
-    fn keyword_or_ident(&self, ident: &str) -> Token {
-        match ident {
+    /// Continues a name with its `\Segment`s, as PHP lexes qualified
+    /// names (`Foo\Bar`, `namespace\Foo`) into a single token.
+    fn read_qualified(&mut self, name: &mut String) {
+        while self.peek() == Some('\\') && self.peek_next().is_some_and(|c| c.is_alphabetic() || c == '_') {
+            self.advance();
+            let first = self.advance().unwrap();
+            name.push('\\');
+            name.push_str(&self.read_identifier(first));
+        }
+    }
+
+    fn keyword_or_ident(&mut self, ident: &str) -> Token {
+        // PHP keywords are case-insensitive
+        match ident.to_ascii_lowercase().as_str() {
             "echo" => Token::Echo,
             "if" => Token::If,
             "else" => Token::Else,
@@ -160,9 +227,51 @@ impl Lexer {
             "for" => Token::For,
             "function" => Token::Function,
             "return" => Token::Return,
-            "true" | "TRUE" => Token::True,
-            "false" | "FALSE" => Token::False,
-            "null" | "NULL" => Token::Null,
+            "true" => Token::True,
+            "false" => Token::False,
+            "null" => Token::Null,
+            "class" => Token::Class,
+            "interface" => Token::Interface,
+            "trait" => Token::Trait,
+            "extends" => Token::Extends,
+            "implements" => Token::Implements,
+            "abstract" => Token::Abstract,
+            "final" => Token::Final,
+            "public" => Token::Public,
+            "protected" => Token::Protected,
+            "private" => Token::Private,
+            "static" => Token::Static,
+            "var" => Token::Var,
+            "const" => Token::Const,
+            "new" => Token::New,
+            "use" => Token::Use,
+            "insteadof" => Token::Insteadof,
+            "as" => Token::As,
+            "case" => Token::Case,
+            "fn" => Token::Fn,
+            "declare" => Token::Declare,
+            "try" => Token::Try,
+            "catch" => Token::Catch,
+            "finally" => Token::Finally,
+            "throw" => Token::Throw,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "namespace" => Token::Namespace,
+            "include" => Token::Include,
+            "include_once" => Token::IncludeOnce,
+            "require" => Token::Require,
+            "require_once" => Token::RequireOnce,
+            "global" => Token::Global,
+            "unset" => Token::Unset,
+            "isset" => Token::Isset,
+            "empty" => Token::Empty,
+            "instanceof" => Token::Instanceof,
+            "clone" => Token::Clone,
+            "foreach" => Token::Foreach,
+            "yield" if self.read_from_keyword() => Token::YieldFrom,
+            "yield" => Token::Yield,
+            // the only magic constant the lexer knows the value of
+            "__line__" => Token::Integer(self.line as i64),
             _ => Token::Identifier(ident.to_string()),
         }
     }
@@ -203,11 +312,15 @@ impl Lexer {
 
             let token = match ch {
                 '+' => Token::Plus,
+                '-' if self.peek() == Some('>') => {
+                    self.advance();
+                    Token::ObjectOperator
+                }
                 '-' => Token::Minus,
                 '*' => Token::Star,
                 '/' => Token::Slash,
                 '%' => Token::Percent,
-                '(' => Token::OpenParen,
+                '(' => self.read_cast().unwrap_or(Token::OpenParen),
                 ')' => Token::CloseParen,
                 '{' => Token::OpenBrace,
                 '}' => Token::CloseBrace,
@@ -215,7 +328,17 @@ impl Lexer {
                 ']' => Token::CloseBracket,
                 ';' => Token::Semicolon,
                 ',' => Token::Comma,
+                '.' if self.peek() == Some('.') && self.peek_next() == Some('.') => {
+                    self.advance();
+                    self.advance();
+                    Token::Ellipsis
+                }
                 '.' => Token::Dot,
+                ':' if self.peek() == Some(':') => {
+                    self.advance();
+                    Token::DoubleColon
+                }
+                ':' => Token::Colon,
                 '=' => {
                     if self.peek() == Some('=') {
                         self.advance();
@@ -269,11 +392,16 @@ impl Lexer {
                     Token::And
                 }
 
+                '&' => Token::Ampersand,
+
                 '|' if self.peek() == Some('|') => {
                     self.advance();
                     Token::Or
                 }
 
+                '|' => Token::Pipe,
+                '?' => Token::Question,
+
                 '$' => {
                     if let Some(c) = self.peek() {
                         if c.is_alphabetic() || c == '_' {
@@ -284,18 +412,27 @@ impl Lexer {
                             return Err(format!("Invalid variable name at {}:{}", line, col));
                         }
                     } else {
-                        return Err(format!("Unexpected $ at end of input"));
+                        return Err("Unexpected $ at end of input".to_string());
                     }
                 }
 
                 '\'' | '"' => self.read_string(ch),
 
-                c if c.is_ascii_digit() => self.read_number(c),
+                c if c.is_ascii_digit() => self.read_number(c)?,
 
                 c if c.is_alphabetic() || c == '_' => {
-                    let ident = self.read_identifier(c);
-                    self.keyword_or_ident(&ident)
+                    let mut ident = self.read_identifier(c);
+                    self.read_qualified(&mut ident);
+                    if ident.contains('\\') { Token::Identifier(ident) } else { self.keyword_or_ident(&ident) }
+                }
+
+                '\\' if self.peek().is_some_and(|c| c.is_alphabetic() || c == '_') => {
+                    let first = self.advance().unwrap();
+                    let mut name = format!("\\{}", self.read_identifier(first));
+                    self.read_qualified(&mut name);
+                    Token::Identifier(name)
                 }
+                '\\' => Token::Backslash,
 
                 c => return Err(format!("Unexpected character '{}' at {}:{}", c, line, col)),
             };
@@ -0,0 +1,48 @@
+//! A PHP interpreter. `Engine` runs scripts and calls into them from
+//! Rust; `Vm::register_extension` exposes Rust functions to PHP.
+
+mod array;
+mod arrays;
+mod ast;
+mod autoload;
+mod bcmath;
+mod class;
+mod closure;
+mod compiler;
+mod constants;
+mod convert;
+mod dump;
+mod engine;
+mod exception;
+mod extension;
+mod fiber;
+mod generator;
+mod include;
+mod iterator;
+mod json;
+mod lexer;
+mod limits;
+mod magic;
+mod math;
+mod operators;
+mod parser;
+mod preg;
+mod printf;
+mod regex;
+mod scanf;
+mod serialize;
+mod strings;
+mod token;
+mod value;
+mod vm;
+
+pub use array::{ArrayKey, PhpArray};
+pub use ast::{ClassKind, TraitAdaptation, Visibility};
+pub use autoload::find_vendor_dir;
+pub use compiler::{ClassDef, ConstDef, Function, MethodDef, PropDef, TraitUseDef};
+pub use convert::{FromPhp, IntoPhp};
+pub use engine::{Engine, Error};
+pub use extension::{Extension, NativeFunction};
+pub use limits::Limits;
+pub use value::{ObjRef, Stream, Value};
+pub use vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
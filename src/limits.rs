@@ -0,0 +1,432 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::class::Internal;
+use crate::value::{format_float, Value};
+use crate::vm::{Frame, Vm, VmError, VmResult};
+
+/// Resource limits for running untrusted scripts. Exceeding one is a
+/// fatal error, except for the stack size: that throws an `Error`, as in
+/// PHP.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Instructions a request may execute. Built-in functions that loop
+    /// over their input, like `levenshtein()`, count their work as steps.
+    pub max_steps: Option<u64>,
+    pub max_execution_time: Option<Duration>,
+    /// Bytes of strings, arrays, objects and call frames alive at once.
+    /// Sizes are estimated, not measured.
+    pub memory_limit: Option<usize>,
+    /// Function calls running at once.
+    pub max_call_depth: Option<usize>,
+    /// Bytes of the native stack the parser, the compiler and nested runs
+    /// of PHP code from native functions may use; keep it well below the
+    /// size of the thread's stack.
+    pub max_stack_size: usize,
+    /// Bytes a request may print; the output is cut off there.
+    pub max_output: Option<usize>,
+    /// Built-in functions to remove, like PHP's `disable_functions`.
+    pub disable_functions: Vec<String>,
+    /// The only built-in functions to keep, if set.
+    pub allow_functions: Option<Vec<String>>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_steps: None,
+            max_execution_time: None,
+            memory_limit: None,
+            max_call_depth: None,
+            max_stack_size: 1 << 20,
+            max_output: None,
+            disable_functions: Vec::new(),
+            allow_functions: None,
+        }
+    }
+}
+
+impl Limits {
+    /// Whether a built-in function is available under these limits.
+    pub(crate) fn allows_function(&self, name: &str) -> bool {
+        let listed = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        !listed(&self.disable_functions) && self.allow_functions.as_deref().is_none_or(listed)
+    }
+}
+
+/// What the current request used so far.
+pub(crate) struct Usage {
+    steps: u64,
+    started: Instant,
+    /// Estimated bytes in use: the last measurement plus what was created
+    /// since.
+    memory: usize,
+    /// Bytes in use at the last measurement.
+    measured: usize,
+    /// Step at which memory is measured next.
+    measure_at: u64,
+    output: usize,
+    /// Step at which the limits are checked next.
+    pub(crate) next_check: u64,
+    /// Set when any memory limit applies, so values are charged.
+    pub(crate) metering: bool,
+}
+
+/// Steps between checks of the clock.
+const CLOCK_INTERVAL: u64 = 1024;
+/// Minimum steps between measurements of memory.
+const MEASURE_INTERVAL: u64 = 65536;
+
+/// Estimated sizes of values and call frames, in bytes.
+const STRING_SIZE: usize = 32;
+const ARRAY_SIZE: usize = 64;
+const ENTRY_SIZE: usize = 48;
+const OBJECT_SIZE: usize = 96;
+const FRAME_SIZE: usize = 256;
+
+/// The estimated size of a frame, without the values it holds.
+fn frame_size(frame: &Frame) -> usize {
+    FRAME_SIZE + frame.vars.len() * ENTRY_SIZE
+}
+
+impl Usage {
+    pub(crate) fn new(limits: &Limits) -> Usage {
+        let mut usage = Usage {
+            steps: 0,
+            started: Instant::now(),
+            memory: 0,
+            measured: 0,
+            measure_at: MEASURE_INTERVAL,
+            output: 0,
+            next_check: 0,
+            metering: limits.memory_limit.is_some(),
+        };
+        usage.schedule(limits);
+        usage
+    }
+
+    fn schedule(&mut self, limits: &Limits) {
+        let mut next = u64::MAX;
+        if let Some(max) = limits.max_steps {
+            next = next.min(max);
+        }
+        if limits.max_execution_time.is_some() {
+            next = next.min(self.steps + CLOCK_INTERVAL);
+        }
+        if limits.memory_limit.is_some() {
+            next = next.min(self.measure_at);
+        }
+        self.next_check = next;
+    }
+
+    /// Counts an instruction; true when the limits need checking.
+    pub(crate) fn step(&mut self) -> bool {
+        self.steps += 1;
+        self.steps > self.next_check
+    }
+
+    /// Counts a value created, checking the limits before the next
+    /// instruction if it goes over the memory limit.
+    pub(crate) fn charge(&mut self, v: &Value, limits: &Limits) {
+        let size = match v {
+            Value::Str(s) => STRING_SIZE + s.len(),
+            Value::Array(a) => ARRAY_SIZE + a.len() * ENTRY_SIZE,
+            Value::Object(o) => OBJECT_SIZE + o.borrow().props.len() * ENTRY_SIZE,
+            _ => return,
+        };
+        self.charge_bytes(size, limits);
+    }
+
+    /// Counts a call frame pushed.
+    pub(crate) fn charge_frame(&mut self, frame: &Frame, limits: &Limits) {
+        self.charge_bytes(frame_size(frame), limits);
+    }
+
+    fn charge_bytes(&mut self, size: usize, limits: &Limits) {
+        self.memory += size;
+        if limits.memory_limit.is_some_and(|limit| self.memory > limit) {
+            self.next_check = 0;
+        }
+    }
+}
+
+thread_local! {
+    /// Stack position when the outermost entry into the engine started,
+    /// and how far from it the stack may grow.
+    static STACK: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+fn stack_position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Marks the start of the engine's use of the stack until dropped, unless
+/// an outer entry did already.
+pub(crate) struct StackScope {
+    outermost: bool,
+}
+
+impl StackScope {
+    pub(crate) fn enter(limit: usize) -> StackScope {
+        let outermost = STACK.get().is_none();
+        if outermost {
+            STACK.set(Some((stack_position(), limit)));
+        }
+        StackScope { outermost }
+    }
+}
+
+impl Drop for StackScope {
+    fn drop(&mut self) {
+        if self.outermost {
+            STACK.set(None);
+        }
+    }
+}
+
+/// Whether the stack grew past the limit since the engine was entered;
+/// the parser and compiler stop there.
+pub(crate) fn stack_exhausted() -> bool {
+    STACK.get().is_some_and(|(base, limit)| stack_position().abs_diff(base) > limit)
+}
+
+/// Collects the estimated size of everything reachable from some values.
+#[derive(Default)]
+struct Meter {
+    bytes: usize,
+    values: usize,
+    seen: HashSet<usize>,
+    pending: Vec<Value>,
+}
+
+impl Meter {
+    fn first_visit<T>(&mut self, rc: &Rc<T>) -> bool {
+        self.seen.insert(Rc::as_ptr(rc) as *const u8 as usize)
+    }
+
+    fn add_frame(&mut self, frame: &Frame) {
+        self.bytes += frame_size(frame);
+        for v in frame.values() {
+            self.add(v);
+        }
+    }
+
+    /// Walks without recursion, so deeply nested arrays are fine.
+    fn add(&mut self, v: &Value) {
+        self.pending.push(v.clone());
+        while let Some(v) = self.pending.pop() {
+            self.values += 1;
+            match &v {
+                Value::Str(s) if self.first_visit(s) => self.bytes += STRING_SIZE + s.len(),
+                Value::Array(a) if self.first_visit(a) => {
+                    self.bytes += ARRAY_SIZE + a.len() * ENTRY_SIZE;
+                    for (_, v) in a.iter() {
+                        self.pending.push(v.clone());
+                    }
+                }
+                Value::Object(o) if self.first_visit(o) => {
+                    let o = o.borrow();
+                    self.bytes += OBJECT_SIZE + o.props.len() * ENTRY_SIZE;
+                    self.pending.extend(o.props.iter().map(|(_, v)| v.clone()));
+                    match &o.internal {
+                        Internal::Closure(c) => self.pending.extend(c.callee.bound.iter().map(|(_, v)| v.clone())),
+                        Internal::Generator(g) => {
+                            if let Some(frame) = g.borrow().frame() {
+                                self.bytes += frame_size(frame);
+                                self.pending.extend(frame.values().cloned());
+                            }
+                        }
+                        Internal::Fiber(f) => {
+                            for frame in f.borrow().frames() {
+                                self.bytes += frame_size(frame);
+                                self.pending.extend(frame.values().cloned());
+                            }
+                        }
+                        Internal::None => {}
+                    }
+                }
+                Value::Ref(cell) if self.first_visit(cell) => self.pending.push(cell.borrow().clone()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Bytes a result may grow by between checks of a `Growth`.
+const GROWTH_STEP: usize = 65536;
+
+/// The size of a result a native function builds piece by piece, checked
+/// against the memory limit as it grows, before each piece is added.
+pub(crate) struct Growth {
+    bytes: usize,
+    next_check: usize,
+}
+
+impl Growth {
+    pub(crate) fn new() -> Growth {
+        Growth { bytes: 0, next_check: GROWTH_STEP }
+    }
+
+    /// Counts `bytes` more, failing if the result would no longer fit.
+    pub(crate) fn add(&mut self, vm: &mut Vm, bytes: usize) -> VmResult<()> {
+        self.bytes = self.bytes.saturating_add(bytes);
+        if self.bytes >= self.next_check {
+            vm.reserve(self.bytes)?;
+            self.next_check = self.bytes.saturating_add(GROWTH_STEP);
+        }
+        Ok(())
+    }
+
+    /// Counts a result written to one buffer as now `len` bytes long.
+    pub(crate) fn grow_to(&mut self, vm: &mut Vm, len: usize) -> VmResult<()> {
+        self.add(vm, len.saturating_sub(self.bytes))
+    }
+
+    /// Counts `count` more array entries holding strings of `bytes` bytes
+    /// in all.
+    pub(crate) fn add_strings(&mut self, vm: &mut Vm, count: usize, bytes: usize) -> VmResult<()> {
+        self.add(vm, count.saturating_mul(ENTRY_SIZE + STRING_SIZE).saturating_add(bytes))
+    }
+}
+
+impl Vm {
+    /// Applies limits to the requests run from now on. Built-in functions
+    /// they exclude are removed.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.functions.retain(|name, f| !f.builtin || limits.allows_function(name));
+        let memory = limits.memory_limit.map_or("-1".to_string(), |limit| limit.to_string());
+        let time = limits.max_execution_time.map_or(0, |time| time.as_secs());
+        let settings = [
+            ("memory_limit", memory),
+            ("max_execution_time", time.to_string()),
+            ("disable_functions", limits.disable_functions.join(",")),
+        ];
+        for (name, v) in settings {
+            self.ini.insert(name.to_string(), v.clone());
+            self.ini_defaults.insert(name.to_string(), v);
+        }
+        self.usage = Usage::new(&limits);
+        self.limits = limits;
+    }
+
+    /// Starts counting for a new request.
+    pub(crate) fn start_request(&mut self) {
+        self.usage = Usage::new(&self.limits);
+    }
+
+    /// Fails with the limit the request went over, if any.
+    pub(crate) fn check_usage(&mut self) -> VmResult<()> {
+        if let Some(max) = self.limits.max_steps
+            && self.usage.steps >= max
+        {
+            return Err(VmError::Fatal(format!("Maximum execution steps of {} exceeded", max)));
+        }
+        if let Some(time) = self.limits.max_execution_time
+            && self.usage.started.elapsed() > time
+        {
+            let secs = time.as_secs_f64();
+            return Err(VmError::Fatal(format!(
+                "Maximum execution time of {} second{} exceeded",
+                format_float(secs, -1),
+                if secs == 1.0 { "" } else { "s" }
+            )));
+        }
+        if let Some(max) = self.limits.max_output
+            && self.usage.output > max
+        {
+            return Err(VmError::Fatal(format!("Output size of {} bytes exceeded", max)));
+        }
+        if let Some(limit) = self.limits.memory_limit
+            && (self.usage.steps >= self.usage.measure_at || self.usage.memory > limit)
+        {
+            let before = self.usage.measured;
+            let values = self.measure_memory();
+            self.usage.measure_at = self.usage.steps + MEASURE_INTERVAL.max(values as u64 * 2);
+            if self.usage.memory > limit {
+                return Err(VmError::Fatal(format!(
+                    "Allowed memory size of {} bytes exhausted (tried to allocate {} bytes)",
+                    limit,
+                    self.usage.memory.saturating_sub(before)
+                )));
+            }
+            self.usage.measured = self.usage.memory;
+        }
+        self.usage.schedule(&self.limits);
+        Ok(())
+    }
+
+    /// Fails before a native function builds a value of `bytes` bytes
+    /// that would not fit under the memory limit.
+    pub(crate) fn reserve(&mut self, bytes: usize) -> VmResult<()> {
+        let Some(limit) = self.limits.memory_limit else { return Ok(()) };
+        if self.usage.memory.saturating_add(bytes) > limit {
+            self.measure_memory();
+            if self.usage.memory.saturating_add(bytes) > limit {
+                return Err(VmError::Fatal(format!(
+                    "Allowed memory size of {} bytes exhausted (tried to allocate {} bytes)",
+                    limit, bytes
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts `steps` of work a built-in function does in a loop, failing
+    /// once a limit is exceeded, so long computations stop like scripts.
+    pub(crate) fn tick(&mut self, steps: u64) -> VmResult<()> {
+        self.usage.steps = self.usage.steps.saturating_add(steps);
+        if self.usage.steps > self.usage.next_check {
+            return self.check_usage();
+        }
+        Ok(())
+    }
+
+    /// Like `reserve`, for an array of `len` entries.
+    pub(crate) fn reserve_array(&mut self, len: usize) -> VmResult<()> {
+        self.reserve(len.saturating_mul(ENTRY_SIZE).saturating_add(ARRAY_SIZE))
+    }
+
+    /// Re-estimates the memory in use from what is reachable; returns the
+    /// number of values visited.
+    fn measure_memory(&mut self) -> usize {
+        let mut meter = Meter::default();
+        for frame in self.all_frames() {
+            meter.add_frame(frame);
+        }
+        for v in self.globals.values().chain(self.constants.values()) {
+            meter.add(v);
+        }
+        for cell in self.static_cells() {
+            meter.add(&Value::Ref(cell));
+        }
+        self.usage.memory = meter.bytes;
+        meter.values
+    }
+
+    /// Counts output, cutting it off at the limit. Returns how much of
+    /// `len` bytes may still be written.
+    pub(crate) fn charge_output(&mut self, len: usize) -> usize {
+        let Some(max) = self.limits.max_output else { return len };
+        let allowed = max.saturating_sub(self.usage.output).min(len);
+        self.usage.output += len;
+        if allowed < len {
+            self.usage.next_check = 0;
+        }
+        allowed
+    }
+
+    /// Throws when native code nested too deeply, e.g. `__toString()`
+    /// methods calling each other.
+    pub(crate) fn check_stack(&self) -> VmResult<()> {
+        if stack_exhausted() {
+            return Err(VmError::error(format!(
+                "Maximum call stack size of {} bytes reached. Infinite recursion?",
+                self.limits.max_stack_size
+            )));
+        }
+        Ok(())
+    }
+}
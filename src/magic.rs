@@ -0,0 +1,180 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::ClassKind;
+use crate::class::{visibility_name, Internal, Object};
+use crate::value::{ObjRef, Value};
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+impl Vm {
+    /// Calls `__get`, `__set`, `__isset` or `__unset` for a property,
+    /// unless the class has no such method or it is already running for
+    /// the same object and property; then `None` is returned and the
+    /// property is accessed directly.
+    pub(crate) fn call_magic_prop(
+        &mut self,
+        obj: &ObjRef,
+        method: &'static str,
+        name: &str,
+        value: Option<Value>,
+    ) -> VmResult<Option<Value>> {
+        let class = obj.borrow().class.clone();
+        let Some(m) = class.find_method(method).cloned() else { return Ok(None) };
+        let guard = (Rc::as_ptr(obj) as usize, Rc::<str>::from(name), method);
+        if !self.magic_guards.insert(guard.clone()) {
+            return Ok(None);
+        }
+        let mut args = vec![Value::from(name)];
+        args.extend(value);
+        let result = self.method_callee(&m, Some(obj.clone()), class).and_then(|c| self.call(c, args));
+        self.magic_guards.remove(&guard);
+        result.map(Some)
+    }
+
+    /// Whether accessing a property goes through the magic methods: it is
+    /// not set, or not accessible from the current scope.
+    pub(crate) fn is_magic_prop(&self, obj: &ObjRef, name: &str) -> bool {
+        let o = obj.borrow();
+        match o.class.find_prop(name) {
+            Some(info) if !self.can_access(info.visibility, &info.class) => true,
+            _ => !o.props.contains_key(&ArrayKey::from(name)),
+        }
+    }
+
+    /// The result of `__toString()`, or `None` if the class has none.
+    pub(crate) fn object_to_string(&mut self, obj: &ObjRef) -> VmResult<Option<Vec<u8>>> {
+        let class = obj.borrow().class.clone();
+        let Some(m) = class.find_method("__toString").cloned() else { return Ok(None) };
+        let callee = self.method_callee(&m, Some(obj.clone()), class.clone())?;
+        match self.call(callee, Vec::new())? {
+            Value::Str(s) => Ok(Some(s.to_vec())),
+            v => Err(VmError::type_error(format!(
+                "{}::__toString(): Return value must be of type string, {} returned",
+                class.name,
+                v.type_name()
+            ))),
+        }
+    }
+
+    /// `clone $v`: a shallow copy of the object, on which `__clone()` is
+    /// then called.
+    pub(crate) fn clone_object(&mut self, v: Value) -> VmResult<Value> {
+        let Value::Object(obj) = v else {
+            return Err(VmError::error("__clone method called on non-object"));
+        };
+        let o = obj.borrow();
+        let uncloneable = matches!(o.internal, Internal::Generator(_) | Internal::Fiber(_)) || o.class.kind == ClassKind::Enum;
+        if uncloneable {
+            return Err(VmError::error(format!("Trying to clone an uncloneable object of class {}", o.class.name)));
+        }
+        let class = o.class.clone();
+        let hook = class.find_method("__clone").cloned();
+        if let Some(m) = &hook
+            && !self.can_access(m.visibility, &m.class)
+        {
+            return Err(VmError::error(format!(
+                "Call to {} {}::__clone() from {}",
+                visibility_name(m.visibility),
+                class.name,
+                self.scope_description()
+            )));
+        }
+        let copy = Object::new(&self.objects, class.clone(), o.props.clone());
+        if let Internal::Closure(c) = &o.internal {
+            copy.borrow_mut().internal = Internal::Closure(c.clone());
+        }
+        drop(o);
+        if let Some(m) = hook {
+            let callee = self.method_callee(&m, Some(copy.clone()), class)?;
+            self.call(callee, Vec::new())?;
+        }
+        Ok(Value::Object(copy))
+    }
+
+    /// Runs `__destruct()` for the objects released since the last call.
+    pub(crate) fn run_destructors(&mut self) -> VmResult<()> {
+        while let Some(obj) = self.objects.next_destructed() {
+            self.destruct(Rc::new(RefCell::new(obj)))?;
+        }
+        Ok(())
+    }
+
+    /// Calls `__destruct()` on an object, or runs the `finally` blocks of a
+    /// suspended generator or unwinds a suspended fiber, then does the same
+    /// for those released meanwhile.
+    pub(crate) fn destruct(&mut self, obj: ObjRef) -> VmResult<()> {
+        let (generator, fiber) = match &obj.borrow().internal {
+            Internal::Generator(g) => (Some(g.clone()), None),
+            Internal::Fiber(f) => (None, Some(f.clone())),
+            _ => (None, None),
+        };
+        if let Some(g) = generator {
+            self.destroy_generator(&g)?;
+            return self.run_destructors();
+        }
+        if let Some(f) = fiber {
+            self.destroy_fiber(obj, f);
+            return self.run_destructors();
+        }
+        let class = obj.borrow().class.clone();
+        let Some(m) = class.find_method("__destruct").cloned() else { return Ok(()) };
+        let callee = self.method_callee(&m, Some(obj), class)?;
+        self.call(callee, Vec::new())?;
+        self.run_destructors()
+    }
+
+    /// The array `__serialize()` returns.
+    pub(crate) fn magic_serialize(&mut self, obj: &ObjRef) -> VmResult<Option<Rc<PhpArray>>> {
+        let class = obj.borrow().class.clone();
+        let Some(m) = class.find_method("__serialize").cloned() else { return Ok(None) };
+        let callee = self.method_callee(&m, Some(obj.clone()), class.clone())?;
+        match self.call(callee, Vec::new())? {
+            Value::Array(arr) => Ok(Some(arr)),
+            _ => Err(VmError::type_error(format!("{}::__serialize() must return an array", class.name))),
+        }
+    }
+
+    /// The properties `var_dump()` and `print_r()` show, with private and
+    /// protected names mangled as in `(array)`: what `__debugInfo()`
+    /// returns, if the class has it.
+    pub(crate) fn debug_properties(&mut self, obj: &ObjRef) -> VmResult<PhpArray> {
+        if let Internal::Closure(c) = &obj.borrow().internal {
+            return Ok(closure_debug_info(&c.callee));
+        }
+        let class = obj.borrow().class.clone();
+        let Some(m) = class.find_method("__debugInfo").cloned() else { return Ok(obj.borrow().to_array()) };
+        let callee = self.method_callee(&m, Some(obj.clone()), class)?;
+        match self.call(callee, Vec::new())? {
+            Value::Array(arr) => Ok((*arr).clone()),
+            Value::Null => Ok(PhpArray::new()),
+            _ => Err(VmError::Fatal("__debuginfo() must return an array".to_string())),
+        }
+    }
+}
+
+/// What a closure shows in `var_dump()`: its captured variables, `$this`
+/// and its parameters.
+fn closure_debug_info(callee: &Callee) -> PhpArray {
+    let mut info = PhpArray::new();
+    if !callee.bound.is_empty() {
+        let mut vars = PhpArray::new();
+        for (name, v) in &callee.bound {
+            vars.insert(ArrayKey::from(&**name), v.clone());
+        }
+        info.insert(ArrayKey::from("static"), Value::from(vars));
+    }
+    if let Some(this) = &callee.this {
+        info.insert(ArrayKey::from("this"), Value::Object(this.clone()));
+    }
+    if !callee.func.params.is_empty() {
+        let mut params = PhpArray::new();
+        for p in &callee.func.params {
+            let name = format!("{}${}", if p.by_ref { "&" } else { "" }, p.name);
+            let kind = if p.default.is_some() || p.variadic { "<optional>" } else { "<required>" };
+            params.insert(ArrayKey::from(name.as_str()), Value::from(kind));
+        }
+        info.insert(ArrayKey::from("parameter"), Value::from(params));
+    }
+    info
+}
@@ -1,44 +1,69 @@
-mod lexer;
-mod token;
-mod ast;
-mod parser;
+use std::process::ExitCode;
 
-use lexer::Lexer;
-use parser::Parser;
+use phpvm::{find_vendor_dir, Engine, Error, Limits};
 
-fn main() {
+/// Stack of the thread running the script, so deeply recursive code can
+/// run; the engine stops at half of it.
+const STACK_SIZE: usize = 256 << 20;
 
-    let source = r#"<?php
-$x = 10;
-$y = 20;
-echo $x + $y;
+/// PHP's default `memory_limit` of 128M.
+const MEMORY_LIMIT: usize = 128 << 20;
 
-if ($x > $y) {
-echo "x is bigger";
-} else {
-    echo "y is bigger";
-}
-
-function add($a, $b) {
-    return $a + $b;
+fn main() -> ExitCode {
+    let runner = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(run);
+    match runner.map(|r| r.join()) {
+        Ok(Ok(code)) => code,
+        _ => ExitCode::FAILURE,
+    }
 }
 
-echo add(5, 3);
-"#;
-
-    let mut lexer = Lexer::new(source);
-    let tokens = match lexer.tokenize() {
-        Ok(t) => t,
-        Err(e) => { eprintln!("Lexer err: {}", e); return; }
+fn run() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (dump_ast, path) = match args.as_slice() {
+        [flag, path] if flag == "--ast" => (true, path),
+        [path] => (false, path),
+        _ => {
+            eprintln!("usage: phpvm [--ast] <file.php>");
+            return ExitCode::from(64);
+        }
     };
 
-    let mut parser = Parser::new(tokens);
-    match parser.parse() {
-        Ok(ast) => {
-            for stmt in &ast {
-                println!("{:#?}", stmt);
+    let mut engine = Engine::new();
+    engine.vm().set_limits(Limits { max_stack_size: STACK_SIZE / 2, memory_limit: Some(MEMORY_LIMIT), ..Limits::default() });
+
+    if dump_ast {
+        let source = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Could not open input file: {} ({})", path, e);
+                return ExitCode::FAILURE;
             }
+        };
+        let file = std::fs::canonicalize(path).map(|p| p.display().to_string()).unwrap_or_else(|_| path.clone());
+        return match engine.dump_ast(&source, &file) {
+            Ok(ast) => {
+                print!("{}", ast);
+                ExitCode::SUCCESS
+            }
+            Err(e) => { eprintln!("{}", e); ExitCode::from(255) }
+        };
+    }
+
+    let file = std::fs::canonicalize(path).unwrap_or_else(|_| path.into());
+    if let Some(vendor) = file.parent().and_then(find_vendor_dir)
+        && let Err(e) = engine.vm().load_composer(&vendor)
+    {
+        eprintln!("PHP Warning:  Could not read the Composer class maps: {}", e);
+    }
+    match engine.run_file(path).and_then(|_| engine.shutdown()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e @ Error::Io { .. }) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(255)
         }
-        Err(e) => eprintln!("Parse error: {}", e),
     }
 }
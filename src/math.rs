@@ -0,0 +1,681 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::extension::{arg_float, arg_int, arg_str, opt_int, opt_str, NativeFunction};
+use crate::value::{loose_compare, parse_numeric_prefix, Numeric, Value};
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+/// The math functions and the Mt19937 generator behind `mt_rand()`.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction {
+        name: "number_format",
+        params: &["float num", "int decimals = 0", "?string decimal_separator = \".\"", "?string thousands_separator = \",\""],
+        func: number_format,
+    },
+    NativeFunction { name: "abs", params: &["int|float num"], func: abs },
+    NativeFunction { name: "ceil", params: &["int|float num"], func: ceil },
+    NativeFunction { name: "floor", params: &["int|float num"], func: floor },
+    NativeFunction { name: "round", params: &["int|float num", "int precision = 0", "int mode = PHP_ROUND_HALF_UP"], func: round_fn },
+    NativeFunction { name: "intdiv", params: &["int num1", "int num2"], func: intdiv },
+    NativeFunction { name: "fmod", params: &["float num1", "float num2"], func: fmod },
+    NativeFunction { name: "fdiv", params: &["float num1", "float num2"], func: fdiv },
+    NativeFunction { name: "pow", params: &["mixed num", "mixed exponent"], func: pow },
+    NativeFunction { name: "sqrt", params: &["float num"], func: float_function },
+    NativeFunction { name: "exp", params: &["float num"], func: float_function },
+    NativeFunction { name: "expm1", params: &["float num"], func: float_function },
+    NativeFunction { name: "log", params: &["float num", "float base = M_E"], func: log },
+    NativeFunction { name: "log10", params: &["float num"], func: float_function },
+    NativeFunction { name: "log2", params: &["float num"], func: float_function },
+    NativeFunction { name: "log1p", params: &["float num"], func: float_function },
+    NativeFunction { name: "sin", params: &["float num"], func: float_function },
+    NativeFunction { name: "cos", params: &["float num"], func: float_function },
+    NativeFunction { name: "tan", params: &["float num"], func: float_function },
+    NativeFunction { name: "asin", params: &["float num"], func: float_function },
+    NativeFunction { name: "acos", params: &["float num"], func: float_function },
+    NativeFunction { name: "atan", params: &["float num"], func: float_function },
+    NativeFunction { name: "atan2", params: &["float y", "float x"], func: atan2 },
+    NativeFunction { name: "sinh", params: &["float num"], func: float_function },
+    NativeFunction { name: "cosh", params: &["float num"], func: float_function },
+    NativeFunction { name: "tanh", params: &["float num"], func: float_function },
+    NativeFunction { name: "asinh", params: &["float num"], func: float_function },
+    NativeFunction { name: "acosh", params: &["float num"], func: float_function },
+    NativeFunction { name: "atanh", params: &["float num"], func: float_function },
+    NativeFunction { name: "hypot", params: &["float x", "float y"], func: hypot },
+    NativeFunction { name: "deg2rad", params: &["float num"], func: float_function },
+    NativeFunction { name: "rad2deg", params: &["float num"], func: float_function },
+    NativeFunction { name: "pi", params: &[], func: pi },
+    NativeFunction { name: "is_nan", params: &["float num"], func: is_nan },
+    NativeFunction { name: "is_finite", params: &["float num"], func: is_finite },
+    NativeFunction { name: "is_infinite", params: &["float num"], func: is_infinite },
+    NativeFunction { name: "min", params: &["mixed value", "mixed ...values"], func: min },
+    NativeFunction { name: "max", params: &["mixed value", "mixed ...values"], func: max },
+    NativeFunction { name: "base_convert", params: &["string num", "int from_base", "int to_base"], func: base_convert },
+    NativeFunction { name: "bindec", params: &["string binary_string"], func: bindec },
+    NativeFunction { name: "hexdec", params: &["string hex_string"], func: hexdec },
+    NativeFunction { name: "octdec", params: &["string octal_string"], func: octdec },
+    NativeFunction { name: "decbin", params: &["int num"], func: decbin },
+    NativeFunction { name: "dechex", params: &["int num"], func: dechex },
+    NativeFunction { name: "decoct", params: &["int num"], func: decoct },
+    NativeFunction { name: "mt_srand", params: &["?int seed = null", "int mode = MT_RAND_MT19937"], func: mt_srand },
+    NativeFunction { name: "srand", params: &["?int seed = null", "int mode = MT_RAND_MT19937"], func: mt_srand },
+    NativeFunction { name: "mt_rand", params: &["int min = UNKNOWN", "int max = UNKNOWN"], func: mt_rand },
+    NativeFunction { name: "rand", params: &["int min = UNKNOWN", "int max = UNKNOWN"], func: rand },
+    NativeFunction { name: "mt_getrandmax", params: &[], func: mt_getrandmax },
+    NativeFunction { name: "getrandmax", params: &[], func: mt_getrandmax },
+    NativeFunction { name: "random_int", params: &["int min", "int max"], func: random_int },
+];
+
+const ROUND_HALF_UP: i64 = 1;
+const ROUND_HALF_DOWN: i64 = 2;
+const ROUND_HALF_EVEN: i64 = 3;
+const ROUND_HALF_ODD: i64 = 4;
+const MT_RAND_MT19937: i64 = 0;
+const MT_RAND_PHP: i64 = 1;
+
+pub(crate) const CONSTANTS: &[(&str, i64)] = &[
+    ("PHP_ROUND_HALF_UP", ROUND_HALF_UP),
+    ("PHP_ROUND_HALF_DOWN", ROUND_HALF_DOWN),
+    ("PHP_ROUND_HALF_EVEN", ROUND_HALF_EVEN),
+    ("PHP_ROUND_HALF_ODD", ROUND_HALF_ODD),
+    ("MT_RAND_MT19937", MT_RAND_MT19937),
+    ("MT_RAND_PHP", MT_RAND_PHP),
+];
+
+fn pow10(power: i32) -> f64 {
+    10f64.powi(power)
+}
+
+/// Rounds to a whole number, settling halves by `mode`.
+fn round_helper(value: f64, mode: i64) -> f64 {
+    let half = (value - value.trunc()).abs() == 0.5;
+    match mode {
+        ROUND_HALF_DOWN if value >= 0.0 => (value - 0.5).ceil(),
+        ROUND_HALF_DOWN => (value + 0.5).floor(),
+        ROUND_HALF_EVEN => value.round_ties_even(),
+        ROUND_HALF_ODD if half => {
+            let away = value.round();
+            if away % 2.0 == 0.0 { value.trunc() } else { away }
+        }
+        ROUND_HALF_ODD => value.round(),
+        _ if value >= 0.0 => (value + 0.5).floor(),
+        _ => (value - 0.5).ceil(),
+    }
+}
+
+/// Rounds half away from zero to `places` decimals like PHP's
+/// `_php_math_round()`: the value is first rounded to the 15 significant
+/// digits a double holds, so `1.005` rounds to `1.01`.
+pub(crate) fn round(value: f64, places: i32) -> f64 {
+    round_mode(value, places, ROUND_HALF_UP)
+}
+
+fn round_mode(value: f64, places: i32, mode: i64) -> f64 {
+    if !value.is_finite() || value == 0.0 {
+        return value;
+    }
+    let places = places.max(i32::MIN + 1);
+    let precision_places = 14 - value.abs().log10().floor() as i32;
+    let f1 = pow10(places.abs());
+    let mut tmp = if precision_places > places && precision_places - 15 < places {
+        let use_precision = precision_places.max(-4 * 15);
+        let f2 = pow10(use_precision.abs());
+        let pre = if use_precision >= 0 { value * f2 } else { value / f2 };
+        let shift = (places - use_precision).max(-4 * 15);
+        round_helper(pre, mode) / pow10(shift.abs())
+    } else {
+        let tmp = if places >= 0 { value * f1 } else { value / f1 };
+        if tmp.abs() >= 1e15 {
+            return value;
+        }
+        tmp
+    };
+    tmp = round_helper(tmp, mode);
+    if places.abs() < 23 {
+        if places > 0 { tmp / f1 } else { tmp * f1 }
+    } else {
+        let shifted: f64 = format!("{:.15}e{}", tmp, -places).parse().unwrap_or(f64::NAN);
+        if shifted.is_finite() { shifted } else { value }
+    }
+}
+
+/// The most decimals `number_format()` computes, as PHP's `printf()`.
+const MAX_DECIMALS: usize = 318;
+
+/// `number_format(float $num, int $decimals = 0, ?string $decimal_separator = ".", ?string $thousands_separator = ","): string`
+///
+/// As in PHP, at most `MAX_DECIMALS` decimals are formatted and the rest
+/// are padded with zeros.
+fn number_format(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let num = arg_float(&args, 0);
+    let dec = arg_int(&args, 1, 0).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    let dec_point = opt_str(&args, 2).unwrap_or(b".");
+    let thousands = opt_str(&args, 3).unwrap_or(b",");
+    let rounded = round(num, dec);
+    let dec = dec.max(0) as usize;
+    if !rounded.is_finite() {
+        return Ok(Value::from(if rounded.is_nan() { "nan" } else { "inf" }));
+    }
+    let int_digits = rounded.abs().log10().max(0.0) as usize + 1;
+    let len = int_digits + int_digits / 3 * thousands.len() + dec_point.len() + dec + 1;
+    vm.reserve(len)?;
+    let digits = format!("{:.*}", dec.min(MAX_DECIMALS), rounded.abs());
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((&digits, ""));
+    let mut out = Vec::with_capacity(len);
+    if rounded < 0.0 {
+        out.push(b'-');
+    }
+    for (i, c) in int_part.bytes().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            out.extend_from_slice(thousands);
+        }
+        out.push(c);
+    }
+    if dec > 0 {
+        out.extend_from_slice(dec_point);
+        out.extend_from_slice(frac_part.as_bytes());
+        out.resize(out.len() + dec - frac_part.len(), b'0');
+    }
+    Ok(Value::from(out))
+}
+
+fn float(v: f64) -> VmResult<Value> {
+    Ok(Value::Float(v))
+}
+
+/// `abs(int|float $num): int|float`
+fn abs(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(match args[0] {
+        Value::Int(n) => n.checked_abs().map_or(Value::Float(-(n as f64)), Value::Int),
+        _ => Value::Float(arg_float(&args, 0).abs()),
+    })
+}
+
+/// `ceil(int|float $num): float`
+fn ceil(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    float(arg_float(&args, 0).ceil())
+}
+
+/// `floor(int|float $num): float`
+fn floor(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    float(arg_float(&args, 0).floor())
+}
+
+/// `round(int|float $num, int $precision = 0, int $mode = PHP_ROUND_HALF_UP): float`
+fn round_fn(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let places = arg_int(&args, 1, 0).clamp(i32::MIN as i64 + 1, i32::MAX as i64) as i32;
+    let mode = arg_int(&args, 2, ROUND_HALF_UP);
+    if !(ROUND_HALF_UP..=ROUND_HALF_ODD).contains(&mode) {
+        return Err(VmError::value_error("round(): Argument #3 ($mode) must be a valid rounding mode (PHP_ROUND_*)"));
+    }
+    match args[0] {
+        Value::Int(n) if places >= 0 => float(n as f64),
+        _ => float(round_mode(arg_float(&args, 0), places, mode)),
+    }
+}
+
+/// `intdiv(int $num1, int $num2): int`
+fn intdiv(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b) = (arg_int(&args, 0, 0), arg_int(&args, 1, 0));
+    if b == 0 {
+        return Err(VmError::Error { class: "DivisionByZeroError", message: "Division by zero".to_string() });
+    }
+    match a.checked_div(b) {
+        Some(q) => Ok(Value::Int(q)),
+        None => Err(VmError::Error { class: "ArithmeticError", message: "Division of PHP_INT_MIN by -1 is not an integer".to_string() }),
+    }
+}
+
+/// `fmod(float $num1, float $num2): float`
+fn fmod(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    float(arg_float(&args, 0) % arg_float(&args, 1))
+}
+
+/// `fdiv(float $num1, float $num2): float`, which follows IEEE 754 for
+/// division by zero.
+fn fdiv(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    float(arg_float(&args, 0) / arg_float(&args, 1))
+}
+
+/// An operand of `pow()`, converted as arithmetic operators convert them.
+fn pow_operand(vm: &mut Vm, v: &Value, base: &Value, exponent: &Value) -> VmResult<Numeric> {
+    match v {
+        Value::Str(s) => match parse_numeric_prefix(s) {
+            Some((n, true)) => Ok(n),
+            Some((n, false)) => {
+                vm.warn("A non-numeric value encountered");
+                Ok(n)
+            }
+            None => Err(unsupported_pow(base, exponent)),
+        },
+        Value::Array(_) | Value::Object(_) => Err(unsupported_pow(base, exponent)),
+        v => Ok(v.to_numeric()),
+    }
+}
+
+fn unsupported_pow(base: &Value, exponent: &Value) -> VmError {
+    VmError::type_error(format!("Unsupported operand types: {} ** {}", base.type_name(), exponent.type_name()))
+}
+
+fn to_f64(n: Numeric) -> f64 {
+    match n {
+        Numeric::Int(i) => i as f64,
+        Numeric::Float(f) => f,
+    }
+}
+
+/// `pow(mixed $num, mixed $exponent): int|float|object`
+///
+/// Integer powers stay integers until they overflow.
+fn pow(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let base = pow_operand(vm, &args[0], &args[0], &args[1])?;
+    let exponent = pow_operand(vm, &args[1], &args[0], &args[1])?;
+    if let (Numeric::Int(b), Numeric::Int(e)) = (base, exponent)
+        && e >= 0
+        && let Some(r) = u32::try_from(e).ok().and_then(|e| b.checked_pow(e))
+    {
+        return Ok(Value::Int(r));
+    }
+    float(to_f64(base).powf(to_f64(exponent)))
+}
+
+type FloatFn = fn(f64) -> f64;
+
+/// The functions of one float that are plain `f64` methods.
+const FLOAT_FUNCTIONS: &[(&str, FloatFn)] = &[
+    ("sqrt", f64::sqrt),
+    ("exp", f64::exp),
+    ("expm1", f64::exp_m1),
+    ("log10", f64::log10),
+    ("log2", f64::log2),
+    ("log1p", f64::ln_1p),
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+    ("sinh", f64::sinh),
+    ("cosh", f64::cosh),
+    ("tanh", f64::tanh),
+    ("asinh", f64::asinh),
+    ("acosh", f64::acosh),
+    ("atanh", f64::atanh),
+    ("deg2rad", f64::to_radians),
+    ("rad2deg", f64::to_degrees),
+];
+
+/// `sqrt(float $num): float` and the other functions of `FLOAT_FUNCTIONS`.
+fn float_function(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (_, f) = FLOAT_FUNCTIONS.iter().find(|(name, _)| **name == *callee.func.name).expect("a float function");
+    float(f(arg_float(&args, 0)))
+}
+
+/// `log(float $num, float $base = M_E): float`
+fn log(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let num = arg_float(&args, 0);
+    let Some(base) = args.get(1).map(|_| arg_float(&args, 1)) else { return float(num.ln()) };
+    if base == 2.0 {
+        return float(num.log2());
+    }
+    if base == 10.0 {
+        return float(num.log10());
+    }
+    if base == 1.0 {
+        return float(f64::NAN);
+    }
+    if base <= 0.0 {
+        return Err(VmError::value_error("log(): Argument #2 ($base) must be greater than 0"));
+    }
+    float(num.ln() / base.ln())
+}
+
+/// `atan2(float $y, float $x): float`
+fn atan2(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    float(arg_float(&args, 0).atan2(arg_float(&args, 1)))
+}
+
+/// `hypot(float $x, float $y): float`
+fn hypot(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    float(arg_float(&args, 0).hypot(arg_float(&args, 1)))
+}
+
+/// `pi(): float`
+fn pi(_: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    float(std::f64::consts::PI)
+}
+
+/// `is_nan(float $num): bool`
+fn is_nan(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(arg_float(&args, 0).is_nan()))
+}
+
+/// `is_finite(float $num): bool`
+fn is_finite(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(arg_float(&args, 0).is_finite()))
+}
+
+/// `is_infinite(float $num): bool`
+fn is_infinite(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(arg_float(&args, 0).is_infinite()))
+}
+
+/// The values `min()` and `max()` choose from: their arguments, or the
+/// elements of a single array argument.
+fn candidates(func: &str, args: Vec<Value>) -> VmResult<Vec<Value>> {
+    if args.len() > 1 {
+        return Ok(args);
+    }
+    let Value::Array(arr) = &args[0] else {
+        return Err(VmError::type_error(format!(
+            "{}(): Argument #1 ($value) must be of type array, {} given",
+            func,
+            args[0].type_name()
+        )));
+    };
+    if arr.is_empty() {
+        return Err(VmError::value_error(format!("{}(): Argument #1 ($value) must contain at least one element", func)));
+    }
+    Ok(arr.iter().map(|(_, v)| v.deref()).collect())
+}
+
+/// The first value that compares as `wanted` to all before it, using the
+/// loose comparison of `<` and `>`.
+fn extreme(values: Vec<Value>, wanted: Ordering) -> Value {
+    let mut values = values.into_iter();
+    let mut best = values.next().expect("at least one value");
+    for v in values {
+        if loose_compare(&v, &best).unwrap_or(Ordering::Greater) == wanted {
+            best = v;
+        }
+    }
+    best
+}
+
+/// `min(mixed $value, mixed ...$values): mixed`
+fn min(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(extreme(candidates("min", args)?, Ordering::Less))
+}
+
+/// `max(mixed $value, mixed ...$values): mixed`
+fn max(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(extreme(candidates("max", args)?, Ordering::Greater))
+}
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Reads a number in `base`, like `_php_math_basetozval()`: whitespace
+/// around it and a `0x`, `0o` or `0b` prefix matching the base are
+/// skipped, other invalid characters are ignored with a deprecation, and
+/// the result becomes a float once it overflows.
+fn from_base(vm: &mut Vm, s: &[u8], base: u32) -> Numeric {
+    let s = s.trim_ascii();
+    let s = match s {
+        [b'0', b'x' | b'X', rest @ ..] if base == 16 => rest,
+        [b'0', b'o' | b'O', rest @ ..] if base == 8 => rest,
+        [b'0', b'b' | b'B', rest @ ..] if base == 2 => rest,
+        s => s,
+    };
+    let mut n = Numeric::Int(0);
+    let mut invalid = false;
+    for &c in s {
+        let Some(d) = (c as char).to_digit(36).filter(|&d| d < base) else {
+            invalid = true;
+            continue;
+        };
+        n = match n {
+            Numeric::Int(i) => match i.checked_mul(i64::from(base)).and_then(|i| i.checked_add(i64::from(d))) {
+                Some(i) => Numeric::Int(i),
+                None => Numeric::Float(i as f64 * f64::from(base) + f64::from(d)),
+            },
+            Numeric::Float(f) => Numeric::Float(f * f64::from(base) + f64::from(d)),
+        };
+    }
+    if invalid {
+        vm.deprecated("Invalid characters passed for attempted conversion, these have been ignored");
+    }
+    n
+}
+
+/// Writes an integer in `base`, treating it as unsigned.
+fn to_base(mut n: u64, base: u32) -> Vec<u8> {
+    let base = u64::from(base);
+    let mut out = Vec::new();
+    loop {
+        out.push(DIGITS[(n % base) as usize]);
+        n /= base;
+        if n == 0 {
+            break;
+        }
+    }
+    out.reverse();
+    out
+}
+
+fn check_base(func: &str, n: usize, param: &str, base: i64) -> VmResult<u32> {
+    match u32::try_from(base) {
+        Ok(base @ 2..=36) => Ok(base),
+        _ => Err(VmError::value_error(format!("{}(): Argument #{} (${}) must be between 2 and 36 (inclusive)", func, n, param))),
+    }
+}
+
+/// `base_convert(string $num, int $from_base, int $to_base): string`
+fn base_convert(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let from = check_base("base_convert", 2, "from_base", arg_int(&args, 1, 10))?;
+    let to = check_base("base_convert", 3, "to_base", arg_int(&args, 2, 10))?;
+    Ok(Value::from(match from_base(vm, arg_str(&args, 0), from) {
+        Numeric::Int(n) => to_base(n as u64, to),
+        Numeric::Float(mut f) => {
+            if !f.is_finite() {
+                return Err(VmError::value_error(format!("An infinite value cannot be converted to base {}", to)));
+            }
+            let mut out = Vec::new();
+            loop {
+                out.push(DIGITS[(f % f64::from(to)) as usize]);
+                f /= f64::from(to);
+                if f.abs() < 1.0 {
+                    break;
+                }
+            }
+            out.reverse();
+            out
+        }
+    }))
+}
+
+/// `bindec(string $binary_string): int|float`
+fn bindec(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(from_base(vm, arg_str(&args, 0), 2).to_value())
+}
+
+/// `hexdec(string $hex_string): int|float`
+fn hexdec(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(from_base(vm, arg_str(&args, 0), 16).to_value())
+}
+
+/// `octdec(string $octal_string): int|float`
+fn octdec(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(from_base(vm, arg_str(&args, 0), 8).to_value())
+}
+
+/// `decbin(int $num): string`
+fn decbin(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(to_base(arg_int(&args, 0, 0) as u64, 2)))
+}
+
+/// `dechex(int $num): string`
+fn dechex(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(to_base(arg_int(&args, 0, 0) as u64, 16)))
+}
+
+/// `decoct(int $num): string`
+fn decoct(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(to_base(arg_int(&args, 0, 0) as u64, 8)))
+}
+
+const MT_N: usize = 624;
+const MT_M: usize = 397;
+const MT_RAND_MAX: i64 = 0x7fff_ffff;
+
+/// PHP's Mt19937, which gives the same sequences as PHP for the same
+/// seed. It is seeded randomly on first use unless `mt_srand()` was
+/// called.
+pub(crate) struct MtRand {
+    state: [u32; MT_N],
+    next: usize,
+    seeded: bool,
+    /// `MT_RAND_PHP`: the flawed twist and range scaling of PHP 5.
+    legacy: bool,
+}
+
+impl Default for MtRand {
+    fn default() -> Self {
+        MtRand { state: [0; MT_N], next: MT_N, seeded: false, legacy: false }
+    }
+}
+
+/// A random number from the system's hasher keys, for seeding and
+/// `random_int()`.
+fn os_random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    hasher.write_u128(nanos);
+    hasher.finish()
+}
+
+impl MtRand {
+    fn seed(&mut self, seed: u32, legacy: bool) {
+        self.state[0] = seed;
+        for i in 1..MT_N {
+            let prev = self.state[i - 1];
+            self.state[i] = 1_812_433_253u32.wrapping_mul(prev ^ (prev >> 30)).wrapping_add(i as u32);
+        }
+        self.legacy = legacy;
+        self.seeded = true;
+        self.reload();
+    }
+
+    fn twist(&self, m: u32, u: u32, v: u32) -> u32 {
+        let mixed = (u & 0x8000_0000) | (v & 0x7fff_ffff);
+        let low = if self.legacy { u } else { v };
+        m ^ (mixed >> 1) ^ ((low & 1).wrapping_neg() & 0x9908_b0df)
+    }
+
+    fn reload(&mut self) {
+        for i in 0..MT_N {
+            let m = self.state[(i + MT_M) % MT_N];
+            self.state[i] = self.twist(m, self.state[i], self.state[(i + 1) % MT_N]);
+        }
+        self.next = 0;
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if !self.seeded {
+            self.seed(os_random() as u32, false);
+        }
+        if self.next == MT_N {
+            self.reload();
+        }
+        let mut s = self.state[self.next];
+        self.next += 1;
+        s ^= s >> 11;
+        s ^= (s << 7) & 0x9d2c_5680;
+        s ^= (s << 15) & 0xefc6_0000;
+        s ^ (s >> 18)
+    }
+
+    /// A uniform number in `0..=umax`, rejecting draws that would skew it.
+    fn below(&mut self, umax: u64) -> u64 {
+        if umax > u64::from(u32::MAX) {
+            let mut draw = || (u64::from(self.next_u32()) << 32) | u64::from(self.next_u32());
+            let mut result = draw();
+            if umax == u64::MAX {
+                return result;
+            }
+            let range = umax + 1;
+            if range & umax != 0 {
+                let limit = u64::MAX - (u64::MAX % range) - 1;
+                while result > limit {
+                    result = draw();
+                }
+            }
+            return result % range;
+        }
+        let mut result = self.next_u32();
+        if umax == u64::from(u32::MAX) {
+            return u64::from(result);
+        }
+        let range = umax as u32 + 1;
+        if range & (umax as u32) != 0 {
+            let limit = u32::MAX - (u32::MAX % range) - 1;
+            while result > limit {
+                result = self.next_u32();
+            }
+        }
+        u64::from(result % range)
+    }
+
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        if self.legacy {
+            let n = f64::from(self.next_u32() >> 1);
+            return min.wrapping_add(((max as f64 - min as f64 + 1.0) * (n / (MT_RAND_MAX as f64 + 1.0))) as i64);
+        }
+        min.wrapping_add(self.below(max.wrapping_sub(min) as u64) as i64)
+    }
+}
+
+/// `mt_srand(?int $seed = null, int $mode = MT_RAND_MT19937): void`
+fn mt_srand(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let seed = opt_int(&args, 0).map_or_else(|| os_random() as u32, |s| s as u32);
+    vm.mt_rand.seed(seed, arg_int(&args, 1, MT_RAND_MT19937) == MT_RAND_PHP);
+    Ok(Value::Null)
+}
+
+/// Both bounds of `mt_rand()` and `rand()`, which take none or two.
+fn bounds(func: &str, args: &[Value]) -> VmResult<Option<(i64, i64)>> {
+    match args.len() {
+        0 => Ok(None),
+        2 => Ok(Some((arg_int(args, 0, 0), arg_int(args, 1, 0)))),
+        n => Err(VmError::Error { class: "ArgumentCountError", message: format!("{}() expects exactly 2 arguments, {} given", func, n) }),
+    }
+}
+
+/// `mt_rand(int $min = UNKNOWN, int $max = UNKNOWN): int`
+fn mt_rand(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let Some((min, max)) = bounds("mt_rand", &args)? else { return Ok(Value::Int(i64::from(vm.mt_rand.next_u32() >> 1))) };
+    if max < min {
+        return Err(VmError::value_error("mt_rand(): Argument #2 ($max) must be greater than or equal to argument #1 ($min)"));
+    }
+    Ok(Value::Int(vm.mt_rand.range(min, max)))
+}
+
+/// `rand(int $min = UNKNOWN, int $max = UNKNOWN): int`, which unlike
+/// `mt_rand()` accepts its bounds either way round.
+fn rand(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let Some((min, max)) = bounds("rand", &args)? else { return Ok(Value::Int(i64::from(vm.mt_rand.next_u32() >> 1))) };
+    Ok(Value::Int(if max < min { vm.mt_rand.range(max, min) } else { vm.mt_rand.range(min, max) }))
+}
+
+/// `mt_getrandmax(): int`
+fn mt_getrandmax(_: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(MT_RAND_MAX))
+}
+
+/// `random_int(int $min, int $max): int`
+fn random_int(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (min, max) = (arg_int(&args, 0, 0), arg_int(&args, 1, 0));
+    if min > max {
+        return Err(VmError::value_error("random_int(): Argument #1 ($min) must be less than or equal to argument #2 ($max)"));
+    }
+    let umax = max.wrapping_sub(min) as u64;
+    if umax == u64::MAX {
+        return Ok(Value::Int(os_random() as i64));
+    }
+    let range = umax + 1;
+    let limit = u64::MAX - (u64::MAX % range) - 1;
+    let mut result = os_random();
+    while result > limit {
+        result = os_random();
+    }
+    Ok(Value::Int(min.wrapping_add((result % range) as i64)))
+}
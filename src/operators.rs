@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::{BinOp, UnaryOp};
+use crate::class::Object;
+use crate::value::{loose_compare, loose_equals, parse_numeric_prefix, strict_equals, Numeric, Value};
+use crate::vm::{Vm, VmError, VmResult};
+
+fn op_symbol(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "%",
+        BinOp::Concat => ".",
+        BinOp::Equal => "==",
+        BinOp::Identical => "===",
+        BinOp::NotEqual => "!=",
+        BinOp::NotIdentical => "!==",
+        BinOp::Less => "<",
+        BinOp::LessEqual => "<=",
+        BinOp::Greater => ">",
+        BinOp::GreaterEqual => ">=",
+        BinOp::And => "&&",
+        BinOp::Or => "||",
+    }
+}
+
+fn as_f64(n: Numeric) -> f64 {
+    match n {
+        Numeric::Int(i) => i as f64,
+        Numeric::Float(f) => f,
+    }
+}
+
+/// `<=>` with uncomparable operands ordered as "greater", like PHP.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    loose_compare(a, b).unwrap_or(Ordering::Greater)
+}
+
+impl Vm {
+    /// Converts an arithmetic operand, warning about leading-numeric
+    /// strings and rejecting non-numeric ones as PHP 8 does.
+    fn arith_operand(&mut self, v: &Value, op: BinOp, a: &Value, b: &Value) -> VmResult<Numeric> {
+        match v {
+            Value::Str(s) => match parse_numeric_prefix(s) {
+                Some((n, true)) => Ok(n),
+                Some((n, false)) => {
+                    self.warn("A non-numeric value encountered");
+                    Ok(n)
+                }
+                None => Err(unsupported(op, a, b)),
+            },
+            Value::Array(_) | Value::Object(_) => Err(unsupported(op, a, b)),
+            _ => Ok(v.to_numeric()),
+        }
+    }
+
+    pub fn binary_op(&mut self, op: BinOp, a: Value, b: Value) -> VmResult<Value> {
+        Ok(match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+                if let (BinOp::Add, Value::Array(x), Value::Array(y)) = (op, &a, &b) {
+                    // array union: keys of the left operand win
+                    let mut result = (**x).clone();
+                    for (k, v) in y.iter() {
+                        if !result.contains_key(k) {
+                            result.insert(k.clone(), v.clone());
+                        }
+                    }
+                    return Ok(Value::Array(Rc::new(result)));
+                }
+                let x = self.arith_operand(&a, op, &a, &b)?;
+                let y = self.arith_operand(&b, op, &a, &b)?;
+                arithmetic(op, x, y)?
+            }
+            BinOp::Concat => {
+                let mut x = self.stringify(&a)?;
+                let y = self.stringify(&b)?;
+                x.extend_from_slice(&y);
+                Value::from(x)
+            }
+            BinOp::Equal => Value::Bool(loose_equals(&a, &b)),
+            BinOp::NotEqual => Value::Bool(!loose_equals(&a, &b)),
+            BinOp::Identical => Value::Bool(strict_equals(&a, &b)),
+            BinOp::NotIdentical => Value::Bool(!strict_equals(&a, &b)),
+            BinOp::Less => Value::Bool(compare(&a, &b) == Ordering::Less),
+            BinOp::LessEqual => Value::Bool(compare(&a, &b) != Ordering::Greater),
+            // PHP evaluates `a > b` as `b < a`
+            BinOp::Greater => Value::Bool(compare(&b, &a) == Ordering::Less),
+            BinOp::GreaterEqual => Value::Bool(compare(&b, &a) != Ordering::Greater),
+            BinOp::And => Value::Bool(a.to_bool() && b.to_bool()),
+            BinOp::Or => Value::Bool(a.to_bool() || b.to_bool()),
+        })
+    }
+
+    pub fn unary_op(&mut self, op: UnaryOp, a: Value) -> VmResult<Value> {
+        match op {
+            UnaryOp::Not => Ok(Value::Bool(!a.to_bool())),
+            // PHP compiles `-$x` as `$x * -1`
+            UnaryOp::Negate => self.binary_op(BinOp::Mul, a, Value::Int(-1)),
+            UnaryOp::IntCast => {
+                self.warn_object_cast(&a, "int");
+                Ok(Value::Int(a.to_int()))
+            }
+            UnaryOp::FloatCast => {
+                self.warn_object_cast(&a, "float");
+                Ok(Value::Float(as_f64(a.to_numeric())))
+            }
+            UnaryOp::StringCast => Ok(Value::from(self.stringify(&a)?)),
+            UnaryOp::BoolCast => Ok(Value::Bool(a.to_bool())),
+            UnaryOp::ArrayCast => Ok(match a {
+                Value::Array(_) => a,
+                Value::Null => Value::from(PhpArray::new()),
+                Value::Object(o) => Value::from(o.borrow().to_array()),
+                v => {
+                    let mut arr = PhpArray::new();
+                    arr.insert(ArrayKey::Int(0), v);
+                    Value::from(arr)
+                }
+            }),
+            UnaryOp::ObjectCast => {
+                let props = match a {
+                    Value::Object(_) => return Ok(a),
+                    Value::Array(arr) => (*arr).clone(),
+                    Value::Null => PhpArray::new(),
+                    v => {
+                        let mut props = PhpArray::new();
+                        props.insert(ArrayKey::from("scalar"), v);
+                        props
+                    }
+                };
+                let class = self.find_class("stdClass").expect("stdClass is declared by the prelude");
+                Ok(Value::Object(Object::new(&self.objects, class, props)))
+            }
+        }
+    }
+
+    fn warn_object_cast(&mut self, v: &Value, ty: &str) {
+        if let Value::Object(o) = v {
+            let class = o.borrow().class.name.clone();
+            self.warn(&format!("Object of class {} could not be converted to {}", class, ty));
+        }
+    }
+}
+
+fn unsupported(op: BinOp, a: &Value, b: &Value) -> VmError {
+    VmError::type_error(format!(
+        "Unsupported operand types: {} {} {}",
+        a.type_name(),
+        op_symbol(op),
+        b.type_name()
+    ))
+}
+
+/// Integer arithmetic overflows into floats; division and modulo by zero
+/// throw `DivisionByZeroError`.
+fn arithmetic(op: BinOp, x: Numeric, y: Numeric) -> VmResult<Value> {
+    if op == BinOp::Mod {
+        let (a, b) = (x.to_value().to_int(), y.to_value().to_int());
+        if b == 0 {
+            return Err(VmError::Error { class: "DivisionByZeroError", message: "Modulo by zero".to_string() });
+        }
+        return Ok(Value::Int(a.wrapping_rem(b)));
+    }
+    if op == BinOp::Div {
+        if as_f64(y) == 0.0 {
+            return Err(VmError::Error { class: "DivisionByZeroError", message: "Division by zero".to_string() });
+        }
+        if let (Numeric::Int(a), Numeric::Int(b)) = (x, y)
+            && let Some(0) = a.checked_rem(b)
+                && let Some(q) = a.checked_div(b) {
+                    return Ok(Value::Int(q));
+                }
+        return Ok(Value::Float(as_f64(x) / as_f64(y)));
+    }
+    if let (Numeric::Int(a), Numeric::Int(b)) = (x, y) {
+        let r = match op {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            _ => a.checked_mul(b),
+        };
+        if let Some(r) = r {
+            return Ok(Value::Int(r));
+        }
+    }
+    let (a, b) = (as_f64(x), as_f64(y));
+    Ok(Value::Float(match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        _ => a * b,
+    }))
+}
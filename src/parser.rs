@@ -1,5 +1,6 @@
 use crate::token::{Token, Spanned};
 use crate::ast::*;
+use crate::limits;
 
 pub struct Parser {
     tokens: Vec<Spanned>,
@@ -12,21 +13,33 @@ impl Parser {
     }
 
     fn peek(&self) -> &Token {
-        &self.tokens[self.pos].token
+        self.peek_at(0)
+    }
+
+    /// The token `n` places ahead. Past the end this is the final `Eof`,
+    /// so lookahead on truncated input never runs off the token list.
+    fn peek_at(&self, n: usize) -> &Token {
+        &self.spanned_at(n).token
+    }
+
+    fn spanned_at(&self, n: usize) -> &Spanned {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + n).min(last)]
     }
 
     fn advance(&mut self) -> &Token {
-        let tok = &self.tokens[self.pos].token;
+        let pos = self.pos;
         self.pos += 1;
-        tok
+        &self.tokens[pos.min(self.tokens.len() - 1)].token
     }
 
     fn expect(&mut self, expected: &Token) -> Result<(), String> {
-        let tok = self.advance().clone();
-        if &tok == expected {
+        let Spanned { token, line, col } = self.spanned_at(0).clone();
+        self.pos += 1;
+        if &token == expected {
             Ok(())
         } else {
-            Err(format!("Expected {:?}, got {:?} at token {}", expected, tok, self.pos))
+            Err(format!("Expected {:?}, got {:?} at {}:{}", expected, token, line, col))
         }
     }
 
@@ -34,6 +47,45 @@ impl Parser {
         self.peek() == token
     }
 
+    /// Stops before deeply nested code overflows the stack.
+    fn check_depth(&self) -> Result<(), String> {
+        if limits::stack_exhausted() {
+            let Spanned { line, col, .. } = self.spanned_at(0);
+            return Err(format!("Maximum nesting level reached at {}:{}", line, col));
+        }
+        Ok(())
+    }
+
+    fn line(&self) -> usize {
+        self.spanned_at(0).line
+    }
+
+    /// Accepts an identifier or a reserved word, for positions where PHP
+    /// allows keywords as names (members, `::class`).
+    fn expect_name(&mut self, what: &str) -> Result<String, String> {
+        match self.advance().clone() {
+            Token::Identifier(n) => Ok(n),
+            t => match t.keyword_name() {
+                Some(k) => Ok(k.to_string()),
+                None => Err(format!("Expected {}, got {:?}", what, t)),
+            },
+        }
+    }
+
+    fn expect_variable(&mut self) -> Result<String, String> {
+        match self.advance().clone() {
+            Token::Variable(n) => Ok(n),
+            t => Err(format!("Expected variable, got {:?}", t)),
+        }
+    }
+
+    fn expect_identifier(&mut self, what: &str) -> Result<String, String> {
+        match self.advance().clone() {
+            Token::Identifier(n) => Ok(n),
+            t => Err(format!("Expected {}, got {:?}", what, t)),
+        }
+    }
+
     // -- Entry point ------------------------------------
 
     pub fn parse(&mut self) -> Result<Block, String> {
@@ -49,22 +101,52 @@ impl Parser {
     // -- Statements -------------------------------------
 
     fn parse_stmt(&mut self) -> Result<Stmt, String> {
-        match self.peek().clone() {
-            Token::Echo => self.parse_echo(),
-            Token::If => self.parse_if(),
-            Token::While => self.parse_while(),
-            Token::For => self.parse_for(),
-            Token::Function => self.parse_function_def(),
-            Token::Return => self.parse_return(),
+        self.check_depth()?;
+        let line = self.line();
+        let kind = match self.peek().clone() {
+            Token::Echo => self.parse_echo()?,
+            Token::If => self.parse_if()?,
+            Token::While => self.parse_while()?,
+            Token::For => self.parse_for()?,
+            Token::Foreach => self.parse_foreach()?,
+            Token::Function if matches!(self.peek_at(1), Token::Identifier(_)) => {
+                self.parse_function_def()?
+            }
+            Token::Return => self.parse_return()?,
+            Token::Try => self.parse_try()?,
+            Token::Namespace => self.parse_namespace()?,
+            Token::Use => self.parse_use()?,
+            Token::Break | Token::Continue => self.parse_break()?,
+            Token::Global => self.parse_global()?,
+            Token::Const => self.parse_const()?,
+            Token::Static if matches!(self.peek_at(1), Token::Variable(_)) => self.parse_static_var()?,
+            Token::Unset => {
+                self.advance();
+                let places = self.parse_place_list()?;
+                self.expect(&Token::Semicolon)?;
+                StmtKind::Unset(places)
+            }
+            Token::Declare => self.parse_declare()?,
+            Token::Abstract | Token::Final | Token::Class | Token::Interface | Token::Trait => {
+                StmtKind::ClassDecl(self.parse_class_decl()?)
+            }
+            // `enum` is only a keyword when followed by a name
+            Token::Identifier(ref n)
+                if n.eq_ignore_ascii_case("enum")
+                    && matches!(self.peek_at(1), Token::Identifier(_)) =>
+            {
+                StmtKind::ClassDecl(self.parse_class_decl()?)
+            }
             _ => {
                 let expr = self.parse_expr()?;
                 self.expect(&Token::Semicolon)?;
-                Ok(Stmt::ExprStmt(expr))
+                StmtKind::Expr(expr)
             }
-        }
+        };
+        Ok(Stmt { kind, line })
     }
 
-    fn parse_echo(&mut self) -> Result<Stmt, String> {
+    fn parse_echo(&mut self) -> Result<StmtKind, String> {
         self.advance();
         let mut exprs = vec![self.parse_expr()?];
         while self.at(&Token::Comma) {
@@ -72,10 +154,10 @@ impl Parser {
             exprs.push(self.parse_expr()?);
         }
         self.expect(&Token::Semicolon)?;
-        Ok(Stmt::Echo(exprs))
+        Ok(StmtKind::Echo(exprs))
     }
 
-    fn parse_if(&mut self) -> Result<Stmt, String> {
+    fn parse_if(&mut self) -> Result<StmtKind, String> {
         self.advance(); // consume 'if'
         self.expect(&Token::OpenParen)?;
         let condition = self.parse_expr()?;
@@ -102,19 +184,19 @@ impl Parser {
             }
         }
 
-        Ok(Stmt::If { condition, then_block, elseif_blocks, else_block })
+        Ok(StmtKind::If { condition, then_block, elseif_blocks, else_block })
     }
 
-    fn parse_while(&mut self) -> Result<Stmt, String> {
+    fn parse_while(&mut self) -> Result<StmtKind, String> {
         self.advance(); // consume 'while'
         self.expect(&Token::OpenParen)?;
         let condition = self.parse_expr()?;
         self.expect(&Token::CloseParen)?;
         let body = self.parse_block()?;
-        Ok(Stmt::While { condition, body })
+        Ok(StmtKind::While { condition, body })
     }
 
-    fn parse_for(&mut self) -> Result<Stmt, String> {
+    fn parse_for(&mut self) -> Result<StmtKind, String> {
         self.advance(); // consume 'for'
         self.expect(&Token::OpenParen)?;
 
@@ -128,45 +210,360 @@ impl Parser {
         self.expect(&Token::CloseParen)?;
 
         let body = self.parse_block()?;
-        Ok(Stmt::For { init, condition, update, body })
+        Ok(StmtKind::For { init, condition, update, body })
     }
 
-    fn parse_function_def(&mut self) -> Result<Stmt, String> {
-        self.advance(); // consume 'function'
-        let name = match self.advance().clone() {
-            Token::Identifier(n) => n,
-            t => return Err(format!("Expected function name, got {:?}", t)),
-        };
+    fn parse_foreach(&mut self) -> Result<StmtKind, String> {
+        self.advance(); // consume 'foreach'
         self.expect(&Token::OpenParen)?;
+        let subject = self.parse_expr()?;
+        self.expect(&Token::As)?;
+        let (mut value, mut by_ref) = self.parse_foreach_target()?;
+        let mut key = None;
+        if self.at(&Token::Arrow) {
+            self.advance();
+            if by_ref {
+                return Err("Key element cannot be a reference".to_string());
+            }
+            if matches!(value, Expr::List(_)) {
+                return Err("Cannot use list as key element".to_string());
+            }
+            key = Some(value);
+            (value, by_ref) = self.parse_foreach_target()?;
+        }
+        self.expect(&Token::CloseParen)?;
+        let body = self.parse_block()?;
+        Ok(StmtKind::Foreach { subject, key, value, by_ref, body })
+    }
+
+    /// `$v`, `&$v` or a destructuring `[$a, $b]` after `as` or `=>`.
+    fn parse_foreach_target(&mut self) -> Result<(Expr, bool), String> {
+        let by_ref = self.at(&Token::Ampersand);
+        if by_ref {
+            self.advance();
+        }
+        let target = into_list(self.parse_unary()?);
+        if !matches!(
+            target,
+            Expr::Variable(_) | Expr::ArrayAccess { .. } | Expr::PropertyFetch { .. } | Expr::StaticPropertyFetch { .. } | Expr::List(_)
+        ) {
+            return Err("Cannot use temporary expression in write context".to_string());
+        }
+        Ok((target, by_ref))
+    }
 
+    fn parse_function_def(&mut self) -> Result<StmtKind, String> {
+        self.advance(); // consume 'function'
+        let name = self.expect_identifier("function name")?;
+        let params = self.parse_params()?;
+        let return_type = self.parse_return_type()?;
+        let body = self.parse_block()?;
+        Ok(StmtKind::FunctionDef { name, params, return_type, body })
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<ParamDecl>, String> {
+        self.expect(&Token::OpenParen)?;
         let mut params = Vec::new();
-        if !self.at(&Token::CloseParen) {
-            loop {
-                match self.advance().clone() {
-                    Token::Variable(p) => params.push(p),
-                    t => return Err(format!("Expected parameter name, got {:?}", t))
-                }
-                if self.at(&Token::Comma) {
-                    self.advance();
-                } else {
-                    break;
-                }
+        while !self.at(&Token::CloseParen) {
+            let ty = match self.peek() {
+                Token::Variable(_) | Token::Ampersand | Token::Ellipsis => None,
+                _ => Some(self.parse_type()?),
+            };
+            let by_ref = self.at(&Token::Ampersand);
+            if by_ref {
+                self.advance();
             }
+            let variadic = self.at(&Token::Ellipsis);
+            if variadic {
+                self.advance();
+            }
+            let name = match self.advance().clone() {
+                Token::Variable(p) => p,
+                t => return Err(format!("Expected parameter name, got {:?}", t)),
+            };
+            let default = if self.at(&Token::Assign) {
+                self.advance();
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            params.push(ParamDecl { name, ty, default, by_ref, variadic });
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
         }
         self.expect(&Token::CloseParen)?;
-        let body = self.parse_block()?;
-        Ok(Stmt::FunctionDef { name, params, body })
+        Ok(params)
+    }
+
+    fn parse_return_type(&mut self) -> Result<Option<TypeHint>, String> {
+        if !self.at(&Token::Colon) {
+            return Ok(None);
+        }
+        self.advance();
+        Ok(Some(self.parse_type()?))
+    }
+
+    /// Parses a type declaration: `T`, `?T`, `A|B`, `A&B` or `(A&B)|C`.
+    fn parse_type(&mut self) -> Result<TypeHint, String> {
+        if self.at(&Token::Question) {
+            self.advance();
+            return Ok(TypeHint::Nullable(Box::new(TypeHint::Named(self.parse_type_name()?))));
+        }
+        let mut members = vec![self.parse_intersection_type()?];
+        while self.at(&Token::Pipe) {
+            self.advance();
+            members.push(self.parse_intersection_type()?);
+        }
+        Ok(if members.len() == 1 { members.pop().unwrap() } else { TypeHint::Union(members) })
+    }
+
+    fn parse_intersection_type(&mut self) -> Result<TypeHint, String> {
+        let parenthesized = self.at(&Token::OpenParen);
+        if parenthesized {
+            self.advance();
+        }
+        let mut members = vec![TypeHint::Named(self.parse_type_name()?)];
+        // `&` followed by the parameter name is a by-reference marker
+        while self.at(&Token::Ampersand)
+            && !matches!(self.peek_at(1), Token::Variable(_) | Token::Ellipsis)
+        {
+            self.advance();
+            members.push(TypeHint::Named(self.parse_type_name()?));
+        }
+        if parenthesized {
+            self.expect(&Token::CloseParen)?;
+        }
+        Ok(if members.len() == 1 { members.pop().unwrap() } else { TypeHint::Intersection(members) })
+    }
+
+    fn parse_type_name(&mut self) -> Result<String, String> {
+        match self.advance().clone() {
+            Token::Identifier(n) => Ok(n),
+            Token::Static => Ok("static".to_string()),
+            Token::Null => Ok("null".to_string()),
+            Token::False => Ok("false".to_string()),
+            Token::True => Ok("true".to_string()),
+            t => Err(format!("Expected type, got {:?}", t)),
+        }
+    }
+
+    fn parse_declare(&mut self) -> Result<StmtKind, String> {
+        self.advance(); // consume 'declare'
+        self.expect(&Token::OpenParen)?;
+        let mut directives = Vec::new();
+        loop {
+            let name = self.expect_identifier("declare directive")?;
+            self.expect(&Token::Assign)?;
+            directives.push((name, self.parse_expr()?));
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(&Token::CloseParen)?;
+        self.expect(&Token::Semicolon)?;
+        Ok(StmtKind::Declare(directives))
     }
 
-    fn parse_return(&mut self) -> Result<Stmt, String> {
+    fn parse_return(&mut self) -> Result<StmtKind, String> {
         self.advance(); // consume 'return'
         if self.at(&Token::Semicolon) {
             self.advance();
-            return Ok(Stmt::Return(None));
+            return Ok(StmtKind::Return(None));
         }
         let expr = self.parse_expr()?;
         self.expect(&Token::Semicolon)?;
-        Ok(Stmt::Return(Some(expr)))
+        Ok(StmtKind::Return(Some(expr)))
+    }
+
+    fn parse_namespace(&mut self) -> Result<StmtKind, String> {
+        self.advance(); // consume 'namespace'
+        let name = match self.peek().clone() {
+            Token::Identifier(n) if !n.starts_with('\\') => {
+                self.advance();
+                Some(n)
+            }
+            _ => None,
+        };
+        if self.at(&Token::OpenBrace) {
+            return Ok(StmtKind::Namespace { name, body: Some(self.parse_block()?) });
+        }
+        let Some(name) = name else {
+            return Err(format!("Expected namespace name, got {:?}", self.peek()));
+        };
+        self.expect(&Token::Semicolon)?;
+        Ok(StmtKind::Namespace { name: Some(name), body: None })
+    }
+
+    /// Parses a `use` import statement, expanding group uses
+    /// (`use A\{B, function c, const D as E};`).
+    fn parse_use(&mut self) -> Result<StmtKind, String> {
+        self.advance(); // consume 'use'
+        let kind = self.parse_use_kind().unwrap_or(UseKind::Class);
+        let mut items = Vec::new();
+        loop {
+            let name = self.expect_identifier("import name")?;
+            let name = name.trim_start_matches('\\').to_string();
+            if self.at(&Token::Backslash) {
+                self.advance();
+                self.expect(&Token::OpenBrace)?;
+                while !self.at(&Token::CloseBrace) {
+                    let item_kind = self.parse_use_kind().unwrap_or(kind);
+                    let rest = self.expect_identifier("import name")?;
+                    let alias = self.parse_use_alias()?;
+                    items.push(UseItem { kind: item_kind, name: format!("{}\\{}", name, rest), alias });
+                    if !self.at(&Token::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.expect(&Token::CloseBrace)?;
+            } else {
+                let alias = self.parse_use_alias()?;
+                items.push(UseItem { kind, name, alias });
+            }
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(&Token::Semicolon)?;
+        Ok(StmtKind::Use(items))
+    }
+
+    fn parse_use_kind(&mut self) -> Option<UseKind> {
+        let kind = match self.peek() {
+            Token::Function => UseKind::Function,
+            Token::Const => UseKind::Const,
+            _ => return None,
+        };
+        self.advance();
+        Some(kind)
+    }
+
+    fn parse_use_alias(&mut self) -> Result<Option<String>, String> {
+        if !self.at(&Token::As) {
+            return Ok(None);
+        }
+        self.advance();
+        Ok(Some(self.expect_identifier("import alias")?))
+    }
+
+    fn parse_try(&mut self) -> Result<StmtKind, String> {
+        self.advance(); // consume 'try'
+        let body = self.parse_block()?;
+        let mut catches = Vec::new();
+        while self.at(&Token::Catch) {
+            let line = self.line();
+            self.advance();
+            self.expect(&Token::OpenParen)?;
+            let mut types = vec![self.expect_identifier("exception class")?];
+            while self.at(&Token::Pipe) {
+                self.advance();
+                types.push(self.expect_identifier("exception class")?);
+            }
+            let var = match self.peek().clone() {
+                Token::Variable(v) => {
+                    self.advance();
+                    Some(v)
+                }
+                _ => None,
+            };
+            self.expect(&Token::CloseParen)?;
+            catches.push(CatchClause { types, var, body: self.parse_block()?, line });
+        }
+        let finally = if self.at(&Token::Finally) {
+            self.advance();
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+        if catches.is_empty() && finally.is_none() {
+            return Err("Cannot use try without catch or finally".to_string());
+        }
+        Ok(StmtKind::Try { body, catches, finally })
+    }
+
+    fn parse_break(&mut self) -> Result<StmtKind, String> {
+        let is_break = self.advance() == &Token::Break;
+        let levels = match self.peek().clone() {
+            Token::Integer(n) => {
+                self.advance();
+                usize::try_from(n).unwrap_or(0)
+            }
+            _ => 1,
+        };
+        self.expect(&Token::Semicolon)?;
+        Ok(if is_break { StmtKind::Break(levels) } else { StmtKind::Continue(levels) })
+    }
+
+    fn parse_global(&mut self) -> Result<StmtKind, String> {
+        self.advance();
+        let mut names = Vec::new();
+        loop {
+            names.push(self.expect_variable()?);
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(&Token::Semicolon)?;
+        Ok(StmtKind::Global(names))
+    }
+
+    fn parse_const(&mut self) -> Result<StmtKind, String> {
+        self.advance();
+        let mut consts = Vec::new();
+        loop {
+            let name = self.expect_identifier("constant name")?;
+            self.expect(&Token::Assign)?;
+            consts.push((name, self.parse_expr()?));
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(&Token::Semicolon)?;
+        Ok(StmtKind::Const(consts))
+    }
+
+    fn parse_static_var(&mut self) -> Result<StmtKind, String> {
+        self.advance();
+        let mut vars = Vec::new();
+        loop {
+            let name = self.expect_variable()?;
+            let init = if self.at(&Token::Assign) {
+                self.advance();
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            vars.push((name, init));
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(&Token::Semicolon)?;
+        Ok(StmtKind::StaticVar(vars))
+    }
+
+    /// The parenthesized arguments of `isset` and `unset`, which allow a
+    /// trailing comma.
+    fn parse_place_list(&mut self) -> Result<Vec<Expr>, String> {
+        self.expect(&Token::OpenParen)?;
+        let mut places = vec![self.parse_expr()?];
+        while self.at(&Token::Comma) {
+            self.advance();
+            if self.at(&Token::CloseParen) {
+                break;
+            }
+            places.push(self.parse_expr()?);
+        }
+        self.expect(&Token::CloseParen)?;
+        Ok(places)
     }
 
     fn parse_block(&mut self) -> Result<Block, String> {
@@ -186,22 +583,79 @@ impl Parser {
     }
 
     fn parse_assignment(&mut self) -> Result<Expr, String> {
+        self.check_depth()?;
+        if self.at(&Token::Throw) {
+            self.advance();
+            return Ok(Expr::Throw(Box::new(self.parse_assignment()?)));
+        }
+        if self.at(&Token::YieldFrom) {
+            self.advance();
+            return Ok(Expr::YieldFrom(Box::new(self.parse_assignment()?)));
+        }
+        if self.at(&Token::Yield) {
+            self.advance();
+            // a bare `yield` ends where its operand would start
+            if matches!(
+                self.peek(),
+                Token::Semicolon | Token::CloseParen | Token::CloseBracket | Token::Comma | Token::Eof
+            ) {
+                return Ok(Expr::Yield { key: None, value: None });
+            }
+            let value = self.parse_assignment()?;
+            if !self.at(&Token::Arrow) {
+                return Ok(Expr::Yield { key: None, value: Some(Box::new(value)) });
+            }
+            self.advance();
+            let key = Some(Box::new(value));
+            return Ok(Expr::Yield { key, value: Some(Box::new(self.parse_assignment()?)) });
+        }
+        let include = match self.peek() {
+            Token::Include => Some(IncludeKind::Include),
+            Token::IncludeOnce => Some(IncludeKind::IncludeOnce),
+            Token::Require => Some(IncludeKind::Require),
+            Token::RequireOnce => Some(IncludeKind::RequireOnce),
+            _ => None,
+        };
+        if let Some(kind) = include {
+            self.advance();
+            return Ok(Expr::Include(kind, Box::new(self.parse_assignment()?)));
+        }
         let expr = self.parse_or()?;
 
+        if self.at(&Token::Assign) && *self.peek_at(1) == Token::Ampersand {
+            self.advance();
+            self.advance();
+            let source = self.parse_assignment()?;
+            let is_place = |e: &Expr| {
+                matches!(
+                    e,
+                    Expr::Variable(_) | Expr::ArrayAccess { .. } | Expr::PropertyFetch { .. } | Expr::StaticPropertyFetch { .. }
+                )
+            };
+            if !is_place(&expr) {
+                return Err("Invalid assignment target".to_string());
+            }
+            if !is_place(&source) {
+                return Err("Cannot assign reference to non referenceable value".to_string());
+            }
+            return Ok(Expr::AssignRef { target: Box::new(expr), source: Box::new(source) });
+        }
+
         if self.at(&Token::Assign) {
             self.advance();
             let value = self.parse_assignment()?; // right-associative
             match expr {
-                Expr::Variable(name) => Ok(Expr::Assign {
-                    variable: name,
+                Expr::Variable(_)
+                | Expr::ArrayAccess { .. }
+                | Expr::PropertyFetch { .. }
+                | Expr::StaticPropertyFetch { .. } => Ok(Expr::Assign {
+                    target: Box::new(expr),
+                    value: Box::new(value),
+                }),
+                Expr::ArrayLiteral(_) | Expr::List(_) => Ok(Expr::Assign {
+                    target: Box::new(into_list(expr)),
                     value: Box::new(value),
                 }),
-                Expr::ArrayAccess { array, index } => {
-                    Ok(Expr::Assign {
-                        variable: format!("__array_set"),
-                        value: Box::new(value),
-                    })
-                }
                 _ => Err("Invalid assignment target".to_string()),
             }
         } else {
@@ -317,19 +771,59 @@ impl Parser {
     }
 
     fn parse_unary(&mut self) -> Result<Expr, String> {
+        self.check_depth()?;
         match self.peek().clone() {
-            Token::Minus => {
-                self.advance();
-                let expr = self.parse_unary()?;
-                Ok(Expr::UnaryOp { op: UnaryOp::Negate, expr: Box::new(expr) })
-            }
             Token::Not => {
                 self.advance();
                 let expr = self.parse_unary()?;
                 Ok(Expr::UnaryOp { op: UnaryOp::Not, expr: Box::new(expr) })
             }
-            _ => self.parse_postfix(),
+            _ => self.parse_instanceof(),
+        }
+    }
+
+    /// `instanceof` binds tighter than `!` but looser than casts and
+    /// unary minus.
+    fn parse_instanceof(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_cast()?;
+        while self.at(&Token::Instanceof) {
+            self.advance();
+            let class = match self.peek().clone() {
+                Token::Identifier(name) => {
+                    self.advance();
+                    ClassRef::Name(name)
+                }
+                Token::Static => {
+                    self.advance();
+                    ClassRef::Name("static".to_string())
+                }
+                _ => ClassRef::Expr(Box::new(self.parse_postfix()?)),
+            };
+            expr = Expr::InstanceOf { expr: Box::new(expr), class };
         }
+        Ok(expr)
+    }
+
+    fn parse_cast(&mut self) -> Result<Expr, String> {
+        self.check_depth()?;
+        let op = match self.peek() {
+            Token::IntCast => UnaryOp::IntCast,
+            Token::FloatCast => UnaryOp::FloatCast,
+            Token::StringCast => UnaryOp::StringCast,
+            Token::BoolCast => UnaryOp::BoolCast,
+            Token::ArrayCast => UnaryOp::ArrayCast,
+            Token::ObjectCast => UnaryOp::ObjectCast,
+            Token::Minus => UnaryOp::Negate,
+            Token::Clone => {
+                self.advance();
+                return Ok(Expr::Clone(Box::new(self.parse_cast()?)));
+            }
+            _ => return self.parse_postfix(),
+        };
+        self.advance();
+        // `(int)!$a` is `(int)(!$a)`
+        let expr = if self.at(&Token::Not) { self.parse_unary()? } else { self.parse_cast()? };
+        Ok(Expr::UnaryOp { op, expr: Box::new(expr) })
     }
 
     fn parse_postfix(&mut self) -> Result<Expr, String> {
@@ -338,12 +832,30 @@ impl Parser {
         loop {
             if self.at(&Token::OpenBracket) {
                 self.advance();
-                let index = self.parse_expr()?;
+                let index = if self.at(&Token::CloseBracket) {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expr()?))
+                };
                 self.expect(&Token::CloseBracket)?;
                 expr = Expr::ArrayAccess {
                     array: Box::new(expr),
-                    index: Box::new(index),
+                    index,
                 };
+            } else if self.at(&Token::ObjectOperator) {
+                self.advance();
+                let name = self.expect_name("property name")?;
+                let object = Box::new(expr);
+                if self.at(&Token::OpenParen) {
+                    expr = self.parse_call(|args| Expr::MethodCall { object, method: name, args })?;
+                } else {
+                    expr = Expr::PropertyFetch { object, property: name };
+                }
+            } else if self.at(&Token::DoubleColon) {
+                expr = self.parse_static_member(ClassRef::Expr(Box::new(expr)))?;
+            } else if self.at(&Token::OpenParen) {
+                let callee = Box::new(expr);
+                expr = self.parse_call(|args| Expr::Call { callee, args })?;
             } else {
                 break;
             }
@@ -366,30 +878,57 @@ impl Parser {
                 Ok(Expr::Variable(name))
             }
 
+            Token::Identifier(name)
+                if matches!(name.to_ascii_lowercase().as_str(), "array" | "list")
+                    && *self.peek_at(1) == Token::OpenParen =>
+            {
+                self.advance();
+                self.advance();
+                match self.parse_array_items(&Token::CloseParen)? {
+                    Expr::ArrayLiteral(entries) if name.eq_ignore_ascii_case("list") => {
+                        Ok(Expr::List(entries.into_iter().map(Some).collect()))
+                    }
+                    e => Ok(e),
+                }
+            }
+
             Token::Identifier(name) => {
                 self.advance();
                 // function call
                 if self.at(&Token::OpenParen) {
-                    self.advance();
-                    let mut args = Vec::new();
-                    if !self.at(&Token::CloseParen) {
-                        loop {
-                            args.push(self.parse_expr()?);
-                            if self.at(&Token::Comma) {
-                                self.advance();
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                    self.expect(&Token::CloseParen)?;
-                    Ok(Expr::FunctionCall { name, args })
+                    self.parse_call(|args| Expr::FunctionCall { name, args })
+                } else if self.at(&Token::DoubleColon) {
+                    self.parse_static_member(ClassRef::Name(name))
                 } else {
-                    // bare identifier — treat as string constant or error
-                    Err(format!("Unexpected identifier '{}' (not a function call)", name))
+                    Ok(Expr::ConstFetch(name))
                 }
             }
 
+            Token::Static if *self.peek_at(1) == Token::DoubleColon => {
+                self.advance();
+                self.parse_static_member(ClassRef::Name("static".to_string()))
+            }
+
+            Token::New => self.parse_new(),
+
+            Token::Isset => {
+                self.advance();
+                Ok(Expr::Isset(self.parse_place_list()?))
+            }
+            Token::Empty => {
+                self.advance();
+                self.expect(&Token::OpenParen)?;
+                let expr = self.parse_expr()?;
+                self.expect(&Token::CloseParen)?;
+                Ok(Expr::Empty(Box::new(expr)))
+            }
+
+            Token::Function | Token::Fn => self.parse_closure(false),
+            Token::Static if matches!(self.peek_at(1), Token::Function | Token::Fn) => {
+                self.advance();
+                self.parse_closure(true)
+            }
+
             Token::OpenParen => {
                 self.advance();
                 let expr = self.parse_expr()?;
@@ -399,32 +938,462 @@ impl Parser {
 
             Token::OpenBracket => {
                 self.advance();
-                let mut entries = Vec::new();
-                if !self.at(&Token::CloseBracket) {
-                    loop {
-                        let first = self.parse_expr()?;
-                        if self.at(&Token::Arrow) {
-                            self.advance();
-                            let value = self.parse_expr()?;
-                            entries.push(ArrayEntry { key: Some(first), value });
-                        } else {
-                            entries.push(ArrayEntry { key: None, value: first });
-                        }
-                        if self.at(&Token::Comma) {
-                            self.advance();
-                            // allow trailing comma
-                            if self.at(&Token::CloseBracket) { break; }
-                        } else {
-                            break;
-                        }
+                self.parse_array_items(&Token::CloseBracket)
+            }
+
+            t => Err(format!("Unexpected token {:?}", t)),
+        }
+    }
+
+    /// The elements of an array literal or `list()`, after the opening
+    /// bracket. Skipped elements make it a `List`.
+    fn parse_array_items(&mut self, close: &Token) -> Result<Expr, String> {
+        let mut items = Vec::new();
+        while !self.at(close) {
+            if self.at(&Token::Comma) {
+                self.advance();
+                items.push(None);
+                continue;
+            }
+            items.push(Some(self.parse_array_entry()?));
+            if !self.at(&Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect(close)?;
+        if items.iter().all(Option::is_some) {
+            Ok(Expr::ArrayLiteral(items.into_iter().flatten().collect()))
+        } else {
+            Ok(Expr::List(items))
+        }
+    }
+
+    fn parse_array_entry(&mut self) -> Result<ArrayEntry, String> {
+        if self.at(&Token::Ellipsis) {
+            self.advance();
+            let value = self.parse_expr()?;
+            return Ok(ArrayEntry { key: None, value, by_ref: false, unpack: true });
+        }
+        let mut by_ref = self.at(&Token::Ampersand);
+        if by_ref {
+            self.advance();
+        }
+        let first = self.parse_expr()?;
+        if by_ref || !self.at(&Token::Arrow) {
+            return Ok(ArrayEntry { key: None, value: first, by_ref, unpack: false });
+        }
+        self.advance();
+        by_ref = self.at(&Token::Ampersand);
+        if by_ref {
+            self.advance();
+        }
+        let value = self.parse_expr()?;
+        Ok(ArrayEntry { key: Some(first), value, by_ref, unpack: false })
+    }
+
+    fn parse_args(&mut self) -> Result<Vec<Arg>, String> {
+        self.expect(&Token::OpenParen)?;
+        let mut args: Vec<Arg> = Vec::new();
+        if !self.at(&Token::CloseParen) {
+            loop {
+                let is_named = *self.peek_at(1) == Token::Colon
+                    && (matches!(self.peek(), Token::Identifier(_)) || self.peek().keyword_name().is_some());
+                let arg = if is_named {
+                    let name = self.expect_name("parameter name")?;
+                    self.advance(); // consume ':'
+                    Arg { value: self.parse_expr()?, name: Some(name), unpack: false }
+                } else if self.at(&Token::Ellipsis) {
+                    self.advance();
+                    if args.iter().any(|a| a.name.is_some()) {
+                        return Err("Cannot use argument unpacking after named arguments".to_string());
+                    }
+                    Arg { value: self.parse_expr()?, name: None, unpack: true }
+                } else {
+                    if args.iter().any(|a| a.name.is_some()) {
+                        return Err("Cannot use positional argument after named argument".to_string());
+                    }
+                    if args.iter().any(|a| a.unpack) {
+                        return Err("Cannot use positional argument after argument unpacking".to_string());
                     }
+                    Arg { value: self.parse_expr()?, name: None, unpack: false }
+                };
+                args.push(arg);
+                if self.at(&Token::Comma) {
+                    self.advance();
+                    if self.at(&Token::CloseParen) { break; }
+                } else {
+                    break;
                 }
-                self.expect(&Token::CloseBracket)?;
-                Ok(Expr::ArrayLiteral(entries))
             }
+        }
+        self.expect(&Token::CloseParen)?;
+        Ok(args)
+    }
 
-            t => Err(format!("Unexpected token {:?}", t)),
+    /// Parses an argument list and builds the call with `make`, or, for
+    /// the first-class callable syntax `(...)`, the closure creation.
+    fn parse_call(&mut self, make: impl FnOnce(Vec<Arg>) -> Expr) -> Result<Expr, String> {
+        let is_callable_syntax = *self.peek_at(1) == Token::Ellipsis
+            && *self.peek_at(2) == Token::CloseParen;
+        if is_callable_syntax {
+            self.pos += 3;
+            return Ok(Expr::FirstClassCallable(Box::new(make(Vec::new()))));
         }
+        let args = self.parse_args()?;
+        Ok(make(args))
+    }
+
+    /// Parses `function (...) use (...) { }` or `fn (...) => expr`, after
+    /// any `static`.
+    fn parse_closure(&mut self, is_static: bool) -> Result<Expr, String> {
+        let is_arrow = self.advance() == &Token::Fn;
+        let params = self.parse_params()?;
+        let mut uses = Vec::new();
+        let return_type;
+        let body = if is_arrow {
+            return_type = self.parse_return_type()?;
+            self.expect(&Token::Arrow)?;
+            let line = self.line();
+            let value = self.parse_expr()?;
+            vec![Stmt { kind: StmtKind::Return(Some(value)), line }]
+        } else {
+            if self.at(&Token::Use) {
+                self.advance();
+                self.expect(&Token::OpenParen)?;
+                while !self.at(&Token::CloseParen) {
+                    let by_ref = self.at(&Token::Ampersand);
+                    if by_ref {
+                        self.advance();
+                    }
+                    let name = match self.advance().clone() {
+                        Token::Variable(n) => n,
+                        t => return Err(format!("Expected variable in use list, got {:?}", t)),
+                    };
+                    if name == "this" {
+                        return Err("Cannot use $this as lexical variable".to_string());
+                    }
+                    if params.iter().any(|p| p.name == name) {
+                        return Err(format!("Cannot use lexical variable ${} as a parameter name", name));
+                    }
+                    uses.push(ClosureUse { name, by_ref });
+                    if !self.at(&Token::Comma) {
+                        break;
+                    }
+                    self.advance();
+                }
+                self.expect(&Token::CloseParen)?;
+            }
+            return_type = self.parse_return_type()?;
+            self.parse_block()?
+        };
+        Ok(Expr::Closure(Box::new(ClosureExpr { params, return_type, uses, body, is_static, is_arrow })))
     }
 
-}
\ No newline at end of file
+    /// Parses what follows `Class::` — a static call, a static property or
+    /// a class constant (including `::class`).
+    fn parse_static_member(&mut self, class: ClassRef) -> Result<Expr, String> {
+        self.expect(&Token::DoubleColon)?;
+        if let Token::Variable(property) = self.peek().clone() {
+            self.advance();
+            return Ok(Expr::StaticPropertyFetch { class, property });
+        }
+        let name = self.expect_name("member name after '::'")?;
+        if self.at(&Token::OpenParen) {
+            self.parse_call(|args| Expr::StaticCall { class, method: name, args })
+        } else {
+            Ok(Expr::ClassConstFetch { class, name })
+        }
+    }
+
+    fn parse_new(&mut self) -> Result<Expr, String> {
+        self.advance(); // consume 'new'
+        let class = match self.advance().clone() {
+            Token::Identifier(name) => ClassRef::Name(name),
+            Token::Static => ClassRef::Name("static".to_string()),
+            Token::Variable(name) => ClassRef::Expr(Box::new(Expr::Variable(name))),
+            t => return Err(format!("Expected class name after 'new', got {:?}", t)),
+        };
+        let args = if self.at(&Token::OpenParen) { self.parse_args()? } else { Vec::new() };
+        Ok(Expr::New { class, args })
+    }
+
+    // -- Classes ----------------------------------------------
+
+    fn parse_class_decl(&mut self) -> Result<ClassDecl, String> {
+        let line = self.line();
+        let mut is_abstract = false;
+        let mut is_final = false;
+        loop {
+            match self.peek() {
+                Token::Abstract => is_abstract = true,
+                Token::Final => is_final = true,
+                _ => break,
+            }
+            self.advance();
+        }
+        if is_abstract && is_final {
+            return Err("Cannot use the final modifier on an abstract class".to_string());
+        }
+
+        let kind = match self.advance().clone() {
+            Token::Class => ClassKind::Class,
+            Token::Interface => ClassKind::Interface,
+            Token::Trait => ClassKind::Trait,
+            Token::Identifier(n) if n.eq_ignore_ascii_case("enum") => ClassKind::Enum,
+            t => return Err(format!("Expected 'class', got {:?}", t)),
+        };
+        if kind != ClassKind::Class && (is_abstract || is_final) {
+            let what = match kind {
+                ClassKind::Interface => "an interface",
+                ClassKind::Trait => "a trait",
+                _ => "an enum",
+            };
+            return Err(format!("Cannot use 'abstract' or 'final' on {}", what));
+        }
+        let name = self.expect_identifier("class name")?;
+
+        let mut backing_type = None;
+        if kind == ClassKind::Enum && self.at(&Token::Colon) {
+            self.advance();
+            backing_type = Some(self.expect_name("enum backing type")?);
+        }
+
+        let mut parent = None;
+        let mut interfaces = Vec::new();
+        if self.at(&Token::Extends) {
+            self.advance();
+            if kind == ClassKind::Interface {
+                interfaces = self.parse_name_list()?;
+            } else if kind == ClassKind::Class {
+                parent = Some(self.expect_identifier("parent class name")?);
+            } else {
+                return Err(format!("{}s cannot extend other classes", if kind == ClassKind::Trait { "Trait" } else { "Enum" }));
+            }
+        }
+        if self.at(&Token::Implements) {
+            if !matches!(kind, ClassKind::Class | ClassKind::Enum) {
+                return Err("'implements' is only allowed on classes".to_string());
+            }
+            self.advance();
+            interfaces = self.parse_name_list()?;
+        }
+
+        self.expect(&Token::OpenBrace)?;
+        let mut members = Vec::new();
+        while !self.at(&Token::CloseBrace) {
+            self.parse_class_member(&name, kind, &mut members)?;
+        }
+        self.expect(&Token::CloseBrace)?;
+
+        Ok(ClassDecl { name, kind, is_abstract, is_final, parent, interfaces, backing_type, members, line })
+    }
+
+    fn parse_name_list(&mut self) -> Result<Vec<String>, String> {
+        let mut names = vec![self.expect_identifier("class name")?];
+        while self.at(&Token::Comma) {
+            self.advance();
+            names.push(self.expect_identifier("class name")?);
+        }
+        Ok(names)
+    }
+
+    fn parse_class_member(&mut self, class: &str, kind: ClassKind, members: &mut Vec<ClassMember>) -> Result<(), String> {
+        if self.at(&Token::Use) {
+            members.push(self.parse_trait_use()?);
+            return Ok(());
+        }
+        if self.at(&Token::Case) {
+            if kind != ClassKind::Enum {
+                return Err("Case can only be used in enums".to_string());
+            }
+            self.advance();
+            let name = self.expect_name("case name")?;
+            let value = if self.at(&Token::Assign) {
+                self.advance();
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            self.expect(&Token::Semicolon)?;
+            members.push(ClassMember::Case { name, value });
+            return Ok(());
+        }
+
+        let line = self.line();
+        let mut visibility = None;
+        let mut is_static = false;
+        let mut is_abstract = false;
+        let mut is_final = false;
+        loop {
+            let vis = match self.peek() {
+                Token::Public | Token::Var => Some(Visibility::Public),
+                Token::Protected => Some(Visibility::Protected),
+                Token::Private => Some(Visibility::Private),
+                Token::Static => { is_static = true; None }
+                Token::Abstract => { is_abstract = true; None }
+                Token::Final => { is_final = true; None }
+                _ => break,
+            };
+            if vis.is_some() {
+                if visibility.is_some() {
+                    return Err("Multiple access type modifiers are not allowed".to_string());
+                }
+                visibility = vis;
+            }
+            self.advance();
+        }
+        let explicit_visibility = visibility.is_some();
+        let visibility = visibility.unwrap_or(Visibility::Public);
+
+        match self.peek().clone() {
+            Token::Const => {
+                self.advance();
+                loop {
+                    let name = self.expect_name("constant name")?;
+                    self.expect(&Token::Assign)?;
+                    let value = self.parse_expr()?;
+                    members.push(ClassMember::Constant { name, value, visibility, is_final });
+                    if !self.at(&Token::Comma) { break; }
+                    self.advance();
+                }
+                self.expect(&Token::Semicolon)?;
+            }
+            Token::Function => {
+                self.advance();
+                let name = self.expect_name("method name")?;
+                let params = self.parse_params()?;
+                let return_type = self.parse_return_type()?;
+                if is_abstract && is_final {
+                    return Err("Cannot use the final modifier on an abstract method".to_string());
+                }
+                if kind == ClassKind::Interface && explicit_visibility && visibility != Visibility::Public {
+                    return Err(format!("Access type for interface method {}::{}() must be public", class, name));
+                }
+                let body = if self.at(&Token::Semicolon) {
+                    self.advance();
+                    if kind != ClassKind::Interface && !is_abstract {
+                        return Err(format!("Non-abstract method {}::{}() must contain body", class, name));
+                    }
+                    None
+                } else {
+                    if kind == ClassKind::Interface {
+                        return Err(format!("Interface function {}::{}() cannot contain body", class, name));
+                    }
+                    if is_abstract {
+                        return Err(format!("Abstract function {}::{}() cannot contain body", class, name));
+                    }
+                    Some(self.parse_block()?)
+                };
+                members.push(ClassMember::Method(MethodDecl {
+                    name,
+                    params,
+                    return_type,
+                    is_abstract: is_abstract || kind == ClassKind::Interface,
+                    body,
+                    visibility,
+                    is_static,
+                    is_final,
+                    line,
+                }));
+            }
+            Token::Variable(_) => {
+                if kind == ClassKind::Interface {
+                    return Err("Interfaces may not include properties".to_string());
+                }
+                if kind == ClassKind::Enum {
+                    return Err(format!("Enum {} cannot include properties", class));
+                }
+                if is_abstract {
+                    return Err("Properties cannot be declared abstract".to_string());
+                }
+                loop {
+                    let name = match self.advance().clone() {
+                        Token::Variable(n) => n,
+                        t => return Err(format!("Expected property name, got {:?}", t)),
+                    };
+                    let default = if self.at(&Token::Assign) {
+                        self.advance();
+                        Some(self.parse_expr()?)
+                    } else {
+                        None
+                    };
+                    members.push(ClassMember::Property { name, default, visibility, is_static });
+                    if !self.at(&Token::Comma) { break; }
+                    self.advance();
+                }
+                self.expect(&Token::Semicolon)?;
+            }
+            t => return Err(format!("Unexpected token {:?} in class body", t)),
+        }
+        Ok(())
+    }
+
+    fn parse_trait_use(&mut self) -> Result<ClassMember, String> {
+        self.advance(); // consume 'use'
+        let traits = self.parse_name_list()?;
+        let mut adaptations = Vec::new();
+        if self.at(&Token::Semicolon) {
+            self.advance();
+            return Ok(ClassMember::TraitUse { traits, adaptations });
+        }
+
+        self.expect(&Token::OpenBrace)?;
+        while !self.at(&Token::CloseBrace) {
+            // `Trait::method` or bare `method`
+            let first = self.expect_name("trait method")?;
+            let (trait_name, method) = if self.at(&Token::DoubleColon) {
+                self.advance();
+                (Some(first), self.expect_name("trait method")?)
+            } else {
+                (None, first)
+            };
+
+            match self.advance().clone() {
+                Token::Insteadof => {
+                    let trait_name = trait_name
+                        .ok_or_else(|| "insteadof requires an explicit Trait::method".to_string())?;
+                    let excluded = self.parse_name_list()?;
+                    adaptations.push(TraitAdaptation::Insteadof { trait_name, method, excluded });
+                }
+                Token::As => {
+                    let visibility = match self.peek() {
+                        Token::Public => Some(Visibility::Public),
+                        Token::Protected => Some(Visibility::Protected),
+                        Token::Private => Some(Visibility::Private),
+                        _ => None,
+                    };
+                    if visibility.is_some() {
+                        self.advance();
+                    }
+                    let alias = if self.at(&Token::Semicolon) {
+                        None
+                    } else {
+                        Some(self.expect_name("alias name")?)
+                    };
+                    adaptations.push(TraitAdaptation::Alias { trait_name, method, alias, visibility });
+                }
+                t => return Err(format!("Expected 'insteadof' or 'as', got {:?}", t)),
+            }
+            self.expect(&Token::Semicolon)?;
+        }
+        self.expect(&Token::CloseBrace)?;
+        Ok(ClassMember::TraitUse { traits, adaptations })
+    }
+}
+
+/// Turns an array literal on the left of `=` into a destructuring
+/// target, including the arrays nested in it.
+fn into_list(expr: Expr) -> Expr {
+    let items = match expr {
+        Expr::ArrayLiteral(entries) => entries.into_iter().map(Some).collect(),
+        Expr::List(items) => items,
+        e => return e,
+    };
+    Expr::List(
+        items
+            .into_iter()
+            .map(|item| item.map(|entry| ArrayEntry { value: into_list(entry.value), ..entry }))
+            .collect(),
+    )
+}
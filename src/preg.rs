@@ -0,0 +1,758 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::extension::{arg_array, arg_int, arg_str, opt_str, set_ref, NativeFunction};
+use crate::limits::Growth;
+use crate::regex::{MatchError, Options, Regex};
+use crate::value::Value;
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+/// The `preg_*` functions, on the engine's own PCRE-compatible regex
+/// engine.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction {
+        name: "preg_match",
+        params: &["string pattern", "string subject", "&matches = null", "int flags = 0", "int offset = 0"],
+        func: preg_match,
+    },
+    NativeFunction {
+        name: "preg_match_all",
+        params: &["string pattern", "string subject", "&matches = null", "int flags = 0", "int offset = 0"],
+        func: preg_match_all,
+    },
+    NativeFunction {
+        name: "preg_replace",
+        params: &["string|array pattern", "string|array replacement", "string|array subject", "int limit = -1", "&count = null"],
+        func: preg_replace,
+    },
+    NativeFunction {
+        name: "preg_filter",
+        params: &["string|array pattern", "string|array replacement", "string|array subject", "int limit = -1", "&count = null"],
+        func: preg_filter,
+    },
+    NativeFunction {
+        name: "preg_replace_callback",
+        params: &["string|array pattern", "callable callback", "string|array subject", "int limit = -1", "&count = null", "int flags = 0"],
+        func: preg_replace_callback,
+    },
+    NativeFunction {
+        name: "preg_replace_callback_array",
+        params: &["array pattern", "string|array subject", "int limit = -1", "&count = null", "int flags = 0"],
+        func: preg_replace_callback_array,
+    },
+    NativeFunction { name: "preg_split", params: &["string pattern", "string subject", "int limit = -1", "int flags = 0"], func: preg_split },
+    NativeFunction { name: "preg_quote", params: &["string str", "?string delimiter = null"], func: preg_quote },
+    NativeFunction { name: "preg_grep", params: &["string pattern", "array array", "int flags = 0"], func: preg_grep },
+    NativeFunction { name: "preg_last_error", params: &[], func: preg_last_error },
+    NativeFunction { name: "preg_last_error_msg", params: &[], func: preg_last_error_msg },
+];
+
+const PATTERN_ORDER: i64 = 1;
+const SET_ORDER: i64 = 2;
+const OFFSET_CAPTURE: i64 = 256;
+const UNMATCHED_AS_NULL: i64 = 512;
+const SPLIT_NO_EMPTY: i64 = 1;
+const SPLIT_DELIM_CAPTURE: i64 = 2;
+const SPLIT_OFFSET_CAPTURE: i64 = 4;
+const GREP_INVERT: i64 = 1;
+
+const NO_ERROR: i64 = 0;
+const INTERNAL_ERROR: i64 = 1;
+const BACKTRACK_LIMIT_ERROR: i64 = 2;
+const RECURSION_LIMIT_ERROR: i64 = 3;
+const BAD_UTF8_ERROR: i64 = 4;
+const BAD_UTF8_OFFSET_ERROR: i64 = 5;
+const JIT_STACKLIMIT_ERROR: i64 = 6;
+
+pub(crate) const CONSTANTS: &[(&str, i64)] = &[
+    ("PREG_PATTERN_ORDER", PATTERN_ORDER),
+    ("PREG_SET_ORDER", SET_ORDER),
+    ("PREG_OFFSET_CAPTURE", OFFSET_CAPTURE),
+    ("PREG_UNMATCHED_AS_NULL", UNMATCHED_AS_NULL),
+    ("PREG_SPLIT_NO_EMPTY", SPLIT_NO_EMPTY),
+    ("PREG_SPLIT_DELIM_CAPTURE", SPLIT_DELIM_CAPTURE),
+    ("PREG_SPLIT_OFFSET_CAPTURE", SPLIT_OFFSET_CAPTURE),
+    ("PREG_GREP_INVERT", GREP_INVERT),
+    ("PREG_NO_ERROR", NO_ERROR),
+    ("PREG_INTERNAL_ERROR", INTERNAL_ERROR),
+    ("PREG_BACKTRACK_LIMIT_ERROR", BACKTRACK_LIMIT_ERROR),
+    ("PREG_RECURSION_LIMIT_ERROR", RECURSION_LIMIT_ERROR),
+    ("PREG_BAD_UTF8_ERROR", BAD_UTF8_ERROR),
+    ("PREG_BAD_UTF8_OFFSET_ERROR", BAD_UTF8_OFFSET_ERROR),
+    ("PREG_JIT_STACKLIMIT_ERROR", JIT_STACKLIMIT_ERROR),
+    ("PCRE_VERSION_MAJOR", 10),
+    ("PCRE_VERSION_MINOR", 42),
+];
+
+pub(crate) const PCRE_VERSION: &str = "10.42 2022-12-11";
+
+/// Compiled patterns are kept until this many have been cached.
+const CACHE_SIZE: usize = 4096;
+
+/// Compiled patterns by their source, and the last error, kept on the
+/// VM between calls.
+#[derive(Default)]
+pub(crate) struct PregState {
+    cache: HashMap<Vec<u8>, Rc<Regex>>,
+    pub(crate) error: i64,
+}
+
+/// Compiles a pattern with its delimiters and modifiers, warning and
+/// returning `None` if it is not valid.
+fn compile(vm: &mut Vm, func: &str, pattern: &[u8]) -> Option<Rc<Regex>> {
+    if let Some(re) = vm.preg.cache.get(pattern) {
+        return Some(re.clone());
+    }
+    match parse(pattern) {
+        Ok(re) => {
+            let re = Rc::new(re);
+            if vm.preg.cache.len() >= CACHE_SIZE {
+                vm.preg.cache.clear();
+            }
+            vm.preg.cache.insert(pattern.to_vec(), re.clone());
+            Some(re)
+        }
+        Err(message) => {
+            vm.warn(&format!("{}(): {}", func, message));
+            vm.preg.error = INTERNAL_ERROR;
+            None
+        }
+    }
+}
+
+fn closing_delimiter(open: u8) -> u8 {
+    match open {
+        b'(' => b')',
+        b'[' => b']',
+        b'{' => b'}',
+        b'<' => b'>',
+        c => c,
+    }
+}
+
+/// Splits `/regex/flags` into its parts and compiles the regex.
+fn parse(pattern: &[u8]) -> Result<Regex, String> {
+    let start = pattern.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(pattern.len());
+    let Some(&open) = pattern.get(start) else { return Err("Empty regular expression".to_string()) };
+    if open.is_ascii_alphanumeric() || open == b'\\' || open == 0 {
+        return Err("Delimiter must not be alphanumeric, backslash, or NUL".to_string());
+    }
+    let close = closing_delimiter(open);
+    let body = start + 1;
+    let mut i = body;
+    let mut depth = 1;
+    loop {
+        let Some(&b) = pattern.get(i) else {
+            return Err(if close == open {
+                format!("No ending delimiter '{}' found", close as char)
+            } else {
+                format!("No ending matching delimiter '{}' found", close as char)
+            });
+        };
+        if b == b'\\' && i + 1 < pattern.len() {
+            i += 2;
+            continue;
+        }
+        if b == close {
+            depth -= 1;
+            if depth == 0 {
+                break;
+            }
+        } else if b == open && close != open {
+            depth += 1;
+        }
+        i += 1;
+    }
+    let mut options = Options::default();
+    for &m in &pattern[i + 1..] {
+        match m {
+            b'i' => options.caseless = true,
+            b'm' => options.multiline = true,
+            b's' => options.dotall = true,
+            b'x' => options.extended = true,
+            b'A' => options.anchored = true,
+            b'D' => options.dollar_endonly = true,
+            b'U' => options.ungreedy = true,
+            b'u' => options.utf = true,
+            b'n' => options.no_auto_capture = true,
+            b'J' => options.dupnames = true,
+            b'S' | b'X' | b' ' | b'\n' | b'\r' => {}
+            0 => return Err("NUL is not a valid modifier".to_string()),
+            m => return Err(format!("Unknown modifier '{}'", m as char)),
+        }
+    }
+    Regex::new(&pattern[body..i], options).map_err(|e| format!("Compilation failed: {} at offset {}", e.message, e.offset))
+}
+
+/// Checks a subject for a UTF-8 pattern, setting the error if it or the
+/// offset into it are not valid.
+fn check_subject(vm: &mut Vm, re: &Regex, subject: &[u8], offset: usize) -> bool {
+    if !re.utf {
+        return true;
+    }
+    if std::str::from_utf8(subject).is_err() {
+        vm.preg.error = BAD_UTF8_ERROR;
+        return false;
+    }
+    if subject.get(offset).is_some_and(|b| b & 0xc0 == 0x80) {
+        vm.preg.error = BAD_UTF8_OFFSET_ERROR;
+        return false;
+    }
+    true
+}
+
+fn backtrack_limit(vm: &Vm) -> u64 {
+    vm.ini.get("pcre.backtrack_limit").and_then(|v| v.trim().parse().ok()).unwrap_or(1_000_000)
+}
+
+type Slots = Vec<Option<usize>>;
+
+/// Runs one match, recording a failure as the last error.
+fn find(vm: &mut Vm, re: &Regex, subject: &[u8], start: usize, not_empty: bool) -> Result<Option<Slots>, ()> {
+    let limit = backtrack_limit(vm);
+    re.find_at(subject, start, not_empty, not_empty, limit).map_err(|e| {
+        vm.preg.error = match e {
+            MatchError::BacktrackLimit => BACKTRACK_LIMIT_ERROR,
+            MatchError::RecursionLimit => RECURSION_LIMIT_ERROR,
+        };
+    })
+}
+
+/// The length of the character at `pos`, to step past an empty match.
+fn unit_len(re: &Regex, subject: &[u8], pos: usize) -> usize {
+    match subject.get(pos) {
+        Some(&b) if re.utf && b >= 0xc0 => (b.leading_ones() as usize).min(subject.len() - pos),
+        _ => 1,
+    }
+}
+
+/// Each match in turn from `start`, the way Perl's `/g` finds them: after
+/// an empty match the next is tried at the same place but must not be
+/// empty, and otherwise one character on.
+struct Matches {
+    start: usize,
+    not_empty: bool,
+}
+
+impl Matches {
+    fn next(&mut self, vm: &mut Vm, re: &Regex, subject: &[u8]) -> Result<Option<Slots>, ()> {
+        loop {
+            if self.start > subject.len() {
+                return Ok(None);
+            }
+            match find(vm, re, subject, self.start, self.not_empty)? {
+                Some(slots) => {
+                    let (from, to) = span(&slots, 0);
+                    self.start = to;
+                    self.not_empty = from == to;
+                    return Ok(Some(slots));
+                }
+                None if self.not_empty && self.start < subject.len() => {
+                    self.start += unit_len(re, subject, self.start);
+                    self.not_empty = false;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Where group `n` matched, if it did.
+fn group(slots: &Slots, n: usize) -> Option<(usize, usize)> {
+    match (slots.get(2 * n)?, slots.get(2 * n + 1)?) {
+        (Some(from), Some(to)) => Some((*from, (*to).max(*from))),
+        _ => None,
+    }
+}
+
+fn span(slots: &Slots, n: usize) -> (usize, usize) {
+    group(slots, n).unwrap_or((0, 0))
+}
+
+/// The number of groups up to the last one that matched, as PCRE
+/// reports it.
+fn matched_count(slots: &Slots) -> usize {
+    (0..slots.len() / 2).rev().find(|&n| group(slots, n).is_some()).map_or(0, |n| n + 1)
+}
+
+fn piece(subject: &[u8], at: Option<(usize, usize)>, flags: i64) -> Value {
+    let text = match at {
+        Some((from, to)) => Value::from(subject[from..to].to_vec()),
+        None if flags & UNMATCHED_AS_NULL != 0 => Value::Null,
+        None => Value::from(""),
+    };
+    if flags & OFFSET_CAPTURE == 0 {
+        return text;
+    }
+    let offset = at.map_or(-1, |(from, _)| from as i64);
+    let mut pair = PhpArray::new();
+    pair.insert(ArrayKey::Int(0), text);
+    pair.insert(ArrayKey::Int(1), Value::Int(offset));
+    Value::from(pair)
+}
+
+/// A match as `$matches` has it: each group by name and by number.
+fn groups_array(re: &Regex, subject: &[u8], slots: &Slots, flags: i64) -> PhpArray {
+    let count = if flags & UNMATCHED_AS_NULL != 0 { re.captures + 1 } else { matched_count(slots) };
+    let mut out = PhpArray::new();
+    for n in 0..count {
+        let v = piece(subject, group(slots, n), flags);
+        if let Some(name) = &re.names[n] {
+            out.insert(ArrayKey::from_bytes(name), v.clone());
+        }
+        out.insert(ArrayKey::Int(n as i64), v);
+    }
+    out
+}
+
+/// The offset argument, counted from the end if negative.
+fn start_offset(offset: i64, len: usize) -> Option<usize> {
+    let offset = if offset < 0 { (offset + len as i64).max(0) } else { offset };
+    usize::try_from(offset).ok().filter(|&o| o <= len)
+}
+
+/// `preg_match(string $pattern, string $subject, &$matches = null, int $flags = 0, int $offset = 0): int|false`
+fn preg_match(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    vm.preg.error = NO_ERROR;
+    let Some(re) = compile(vm, "preg_match", arg_str(&args, 0)) else { return Ok(Value::Bool(false)) };
+    let subject = arg_str(&args, 1);
+    let flags = arg_int(&args, 3, 0);
+    let found = match start_offset(arg_int(&args, 4, 0), subject.len()) {
+        Some(start) if check_subject(vm, &re, subject, start) => find(vm, &re, subject, start, false),
+        Some(_) => Err(()),
+        None => {
+            vm.preg.error = INTERNAL_ERROR;
+            Err(())
+        }
+    };
+    let (matches, result) = match found {
+        Ok(Some(slots)) => (groups_array(&re, subject, &slots, flags), Value::Int(1)),
+        Ok(None) => (PhpArray::new(), Value::Int(0)),
+        Err(()) => (PhpArray::new(), Value::Bool(false)),
+    };
+    set_ref(&args, 2, Value::from(matches));
+    Ok(result)
+}
+
+/// `preg_match_all(string $pattern, string $subject, &$matches = null, int $flags = 0, int $offset = 0): int|false`
+fn preg_match_all(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    vm.preg.error = NO_ERROR;
+    let flags = arg_int(&args, 3, 0);
+    let order = match flags & (PATTERN_ORDER | SET_ORDER) {
+        0 => PATTERN_ORDER,
+        SET_ORDER if flags & PATTERN_ORDER == 0 => SET_ORDER,
+        PATTERN_ORDER => PATTERN_ORDER,
+        _ => return Err(VmError::value_error("preg_match_all(): Argument #4 ($flags) must be a PREG_* constant")),
+    };
+    let Some(re) = compile(vm, "preg_match_all", arg_str(&args, 0)) else { return Ok(Value::Bool(false)) };
+    let subject = arg_str(&args, 1);
+    let Some(start) = start_offset(arg_int(&args, 4, 0), subject.len()) else {
+        vm.preg.error = INTERNAL_ERROR;
+        set_ref(&args, 2, Value::from(PhpArray::new()));
+        return Ok(Value::Bool(false));
+    };
+    if !check_subject(vm, &re, subject, start) {
+        set_ref(&args, 2, Value::from(PhpArray::new()));
+        return Ok(Value::Bool(false));
+    }
+    let mut sets: Vec<PhpArray> = vec![PhpArray::new(); re.captures + 1];
+    let mut list = PhpArray::new();
+    let mut count = 0;
+    let mut growth = Growth::new();
+    let mut matches = Matches { start, not_empty: false };
+    let failed = loop {
+        let slots = match matches.next(vm, &re, subject) {
+            Ok(Some(slots)) => slots,
+            Ok(None) => break false,
+            Err(()) => break true,
+        };
+        count += 1;
+        let (from, to) = span(&slots, 0);
+        growth.add_strings(vm, re.captures + 2, (to - from) * matched_count(&slots))?;
+        if order == SET_ORDER {
+            let _ = list.push(Value::from(groups_array(&re, subject, &slots, flags)));
+            continue;
+        }
+        let matched = matched_count(&slots);
+        for (n, set) in sets.iter_mut().enumerate() {
+            let at = group(&slots, n).filter(|_| n < matched);
+            let _ = set.push(piece(subject, at, flags));
+        }
+    };
+    if order == PATTERN_ORDER {
+        for (n, set) in sets.into_iter().enumerate() {
+            let set = Value::from(set);
+            if let Some(name) = &re.names[n] {
+                list.insert(ArrayKey::from_bytes(name), set.clone());
+            }
+            list.insert(ArrayKey::Int(n as i64), set);
+        }
+    }
+    if failed {
+        set_ref(&args, 2, Value::from(PhpArray::new()));
+        return Ok(Value::Bool(false));
+    }
+    set_ref(&args, 2, Value::from(list));
+    Ok(Value::Int(count))
+}
+
+/// What replaces each match.
+enum Replacement {
+    /// A replacement string with `\n`, `$n` and `${n}` references.
+    Template(Vec<u8>),
+    Callback(Callee, i64),
+}
+
+/// A group reference at the start of `s`, which is at a `\` or `$`,
+/// and its length.
+fn backref(s: &[u8]) -> Option<(usize, usize)> {
+    let braced = s[0] == b'$' && s.get(1) == Some(&b'{');
+    let mut i = 1 + usize::from(braced);
+    let mut n = match s.get(i) {
+        Some(d @ b'0'..=b'9') => usize::from(d - b'0'),
+        _ => return None,
+    };
+    i += 1;
+    if let Some(d @ b'0'..=b'9') = s.get(i) {
+        n = n * 10 + usize::from(d - b'0');
+        i += 1;
+    }
+    if braced {
+        if s.get(i) != Some(&b'}') {
+            return None;
+        }
+        i += 1;
+    }
+    Some((n, i))
+}
+
+/// Appends the replacement for a match to `out`, counting what it adds
+/// in `growth` first.
+fn expand(vm: &mut Vm, growth: &mut Growth, template: &[u8], subject: &[u8], slots: &Slots, out: &mut Vec<u8>) -> VmResult<()> {
+    let count = matched_count(slots);
+    let mut i = 0;
+    let mut escaped = false;
+    while i < template.len() {
+        let c = template[i];
+        if c == b'\\' || c == b'$' {
+            if escaped {
+                *out.last_mut().expect("the backslash") = c;
+                escaped = false;
+                i += 1;
+                continue;
+            }
+            if let Some((n, len)) = backref(&template[i..]) {
+                if n < count && let Some((from, to)) = group(slots, n) {
+                    growth.add(vm, to - from)?;
+                    out.extend_from_slice(&subject[from..to]);
+                }
+                i += len;
+                continue;
+            }
+        }
+        growth.add(vm, 1)?;
+        out.push(c);
+        escaped = c == b'\\';
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Replaces up to `limit` matches of one pattern, adding to `count`.
+/// `None` means matching failed.
+fn replace_one(
+    vm: &mut Vm,
+    growth: &mut Growth,
+    re: &Regex,
+    replacement: &Replacement,
+    subject: &[u8],
+    limit: i64,
+    count: &mut i64,
+) -> VmResult<Option<Vec<u8>>> {
+    if !check_subject(vm, re, subject, 0) {
+        return Ok(None);
+    }
+    let mut out = Vec::with_capacity(subject.len());
+    let mut last = 0;
+    let mut left = limit;
+    let mut matches = Matches { start: 0, not_empty: false };
+    while left != 0 {
+        let slots = match matches.next(vm, re, subject) {
+            Ok(Some(slots)) => slots,
+            Ok(None) => break,
+            Err(()) => return Ok(None),
+        };
+        left -= 1;
+        *count += 1;
+        let (from, to) = span(&slots, 0);
+        out.extend_from_slice(&subject[last.min(from)..from]);
+        match replacement {
+            Replacement::Template(template) => expand(vm, growth, template, subject, &slots, &mut out)?,
+            Replacement::Callback(callback, flags) => {
+                let groups = groups_array(re, subject, &slots, *flags);
+                let result = vm.call(callback.clone(), vec![Value::from(groups)])?;
+                let result = vm.stringify(&result)?;
+                growth.add(vm, result.len())?;
+                out.extend(result);
+            }
+        }
+        last = to;
+    }
+    out.extend_from_slice(&subject[last.min(subject.len())..]);
+    Ok(Some(out))
+}
+
+/// Applies each pattern in turn to one subject.
+fn replace_all(
+    vm: &mut Vm,
+    growth: &mut Growth,
+    func: &str,
+    pairs: &[(Vec<u8>, Replacement)],
+    subject: Vec<u8>,
+    limit: i64,
+    count: &mut i64,
+) -> VmResult<Option<Vec<u8>>> {
+    let mut subject = subject;
+    for (pattern, replacement) in pairs {
+        let Some(re) = compile(vm, func, pattern) else { return Ok(None) };
+        match replace_one(vm, growth, &re, replacement, &subject, limit, count)? {
+            Some(s) => subject = s,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(subject))
+}
+
+/// Runs a replacement over a string or each string of an array; with
+/// `filter`, only subjects something matched in are returned.
+fn replace_subjects(vm: &mut Vm, func: &str, pairs: &[(Vec<u8>, Replacement)], args: &[Value], subject: usize, filter: bool) -> VmResult<Value> {
+    vm.preg.error = NO_ERROR;
+    let limit = match arg_int(args, subject + 1, -1) {
+        0 => -1,
+        n => n,
+    };
+    let mut total = 0;
+    let mut growth = Growth::new();
+    let result = match &args[subject] {
+        Value::Array(subjects) => {
+            let mut out = PhpArray::new();
+            for (k, v) in subjects.iter() {
+                let s = vm.stringify(&v.deref())?;
+                let mut count = 0;
+                if let Some(s) = replace_all(vm, &mut growth, func, pairs, s, limit, &mut count)?
+                    && (!filter || count > 0)
+                {
+                    out.insert(k.clone(), Value::from(s));
+                }
+                total += count;
+            }
+            Value::from(out)
+        }
+        v => {
+            let s = vm.stringify(v)?;
+            match replace_all(vm, &mut growth, func, pairs, s, limit, &mut total)? {
+                Some(s) if !filter || total > 0 => Value::from(s),
+                _ => Value::Null,
+            }
+        }
+    };
+    set_ref(args, subject + 2, Value::Int(total));
+    Ok(result)
+}
+
+/// The pattern and replacement pairs of `preg_replace()` and
+/// `preg_filter()`.
+fn template_pairs(vm: &mut Vm, func: &str, args: &[Value]) -> VmResult<Vec<(Vec<u8>, Replacement)>> {
+    match (&args[0], &args[1]) {
+        (Value::Array(patterns), replacement) => {
+            let mut replacements = match replacement {
+                Value::Array(r) => r.iter().map(|(_, v)| v.deref()).collect(),
+                _ => Vec::new(),
+            }
+            .into_iter();
+            let mut pairs = Vec::new();
+            for (_, p) in patterns.iter() {
+                let pattern = vm.stringify(&p.deref())?;
+                let template = match replacement {
+                    Value::Array(_) => match replacements.next() {
+                        Some(r) => vm.stringify(&r)?,
+                        None => Vec::new(),
+                    },
+                    r => vm.stringify(r)?,
+                };
+                pairs.push((pattern, Replacement::Template(template)));
+            }
+            Ok(pairs)
+        }
+        (_, Value::Array(_)) => Err(VmError::type_error(format!(
+            "{}(): Argument #1 ($pattern) must be of type array when argument #2 ($replacement) is an array, string given",
+            func
+        ))),
+        (pattern, replacement) => Ok(vec![(vm.stringify(pattern)?, Replacement::Template(vm.stringify(replacement)?))]),
+    }
+}
+
+/// `preg_replace(string|array $pattern, string|array $replacement, string|array $subject, int $limit = -1, &$count = null): string|array|null`
+fn preg_replace(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let pairs = template_pairs(vm, "preg_replace", &args)?;
+    replace_subjects(vm, "preg_replace", &pairs, &args, 2, false)
+}
+
+/// `preg_filter(string|array $pattern, string|array $replacement, string|array $subject, int $limit = -1, &$count = null): string|array|null`
+fn preg_filter(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let pairs = template_pairs(vm, "preg_filter", &args)?;
+    replace_subjects(vm, "preg_filter", &pairs, &args, 2, true)
+}
+
+fn callback_flags(args: &[Value], i: usize) -> i64 {
+    arg_int(args, i, 0) & (OFFSET_CAPTURE | UNMATCHED_AS_NULL)
+}
+
+/// `preg_replace_callback(string|array $pattern, callable $callback, string|array $subject, int $limit = -1, &$count = null, int $flags = 0): string|array|null`
+fn preg_replace_callback(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let callback = vm.resolve_callable(&args[1])?;
+    let flags = callback_flags(&args, 5);
+    let patterns = match &args[0] {
+        Value::Array(patterns) => patterns.iter().map(|(_, p)| vm.stringify(&p.deref())).collect::<VmResult<Vec<_>>>()?,
+        pattern => vec![vm.stringify(pattern)?],
+    };
+    let pairs: Vec<_> = patterns.into_iter().map(|p| (p, Replacement::Callback(callback.clone(), flags))).collect();
+    replace_subjects(vm, "preg_replace_callback", &pairs, &args, 2, false)
+}
+
+/// `preg_replace_callback_array(array $pattern, string|array $subject, int $limit = -1, &$count = null, int $flags = 0): string|array|null`
+fn preg_replace_callback_array(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let flags = callback_flags(&args, 4);
+    let mut pairs = Vec::new();
+    for (k, v) in arg_array(&args, 0).iter() {
+        let pattern = match k {
+            ArrayKey::Int(n) => n.to_string().into_bytes(),
+            ArrayKey::Str(s) => s.to_vec(),
+        };
+        pairs.push((pattern, Replacement::Callback(vm.resolve_callable(&v.deref())?, flags)));
+    }
+    replace_subjects(vm, "preg_replace_callback_array", &pairs, &args, 1, false)
+}
+
+/// `preg_split(string $pattern, string $subject, int $limit = -1, int $flags = 0): array|false`
+fn preg_split(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    vm.preg.error = NO_ERROR;
+    let Some(re) = compile(vm, "preg_split", arg_str(&args, 0)) else { return Ok(Value::Bool(false)) };
+    let subject = arg_str(&args, 1);
+    let mut limit = match arg_int(&args, 2, -1) {
+        0 => -1,
+        n => n,
+    };
+    let flags = arg_int(&args, 3, 0);
+    let no_empty = flags & SPLIT_NO_EMPTY != 0;
+    let piece_flags = if flags & SPLIT_OFFSET_CAPTURE != 0 { OFFSET_CAPTURE } else { 0 };
+    if !check_subject(vm, &re, subject, 0) {
+        return Ok(Value::Bool(false));
+    }
+    let mut out = PhpArray::new();
+    let mut growth = Growth::new();
+    let mut start = 0;
+    let mut last = 0;
+    let mut not_empty = false;
+    while limit == -1 || limit > 1 {
+        let slots = match find(vm, &re, subject, start, not_empty) {
+            Ok(Some(slots)) => slots,
+            Ok(None) if not_empty && start < subject.len() => {
+                start += unit_len(&re, subject, start);
+                not_empty = false;
+                continue;
+            }
+            Ok(None) => break,
+            Err(()) => return Ok(Value::Bool(false)),
+        };
+        let (from, to) = span(&slots, 0);
+        growth.add_strings(vm, matched_count(&slots), from.saturating_sub(last) + (to - from))?;
+        if !no_empty || from != last {
+            let _ = out.push(piece(subject, Some((last, from)), piece_flags));
+            if limit != -1 {
+                limit -= 1;
+            }
+        }
+        if flags & SPLIT_DELIM_CAPTURE != 0 {
+            for n in 1..matched_count(&slots) {
+                let at = group(&slots, n);
+                if !no_empty || at.is_some_and(|(a, b)| a != b) {
+                    let _ = out.push(piece(subject, Some(at.unwrap_or((from, from))), piece_flags));
+                }
+            }
+        }
+        start = to;
+        last = to;
+        not_empty = from == to;
+        if not_empty && limit != -1 && limit <= 1 {
+            break;
+        }
+    }
+    if !no_empty || last < subject.len() {
+        let _ = out.push(piece(subject, Some((last, subject.len())), piece_flags));
+    }
+    Ok(Value::from(out))
+}
+
+/// `preg_quote(string $str, ?string $delimiter = null): string`
+fn preg_quote(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let delimiter = opt_str(&args, 1).and_then(|d| d.first().copied());
+    let mut out = Vec::new();
+    for &c in arg_str(&args, 0) {
+        match c {
+            0 => out.extend_from_slice(b"\\000"),
+            b'.' | b'\\' | b'+' | b'*' | b'?' | b'[' | b'^' | b']' | b'$' | b'(' | b')' | b'{' | b'}' | b'=' | b'!' | b'<' | b'>' | b'|'
+            | b':' | b'-' | b'#' => {
+                out.push(b'\\');
+                out.push(c);
+            }
+            c if Some(c) == delimiter => {
+                out.push(b'\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `preg_grep(string $pattern, array $array, int $flags = 0): array|false`
+fn preg_grep(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    vm.preg.error = NO_ERROR;
+    let Some(re) = compile(vm, "preg_grep", arg_str(&args, 0)) else { return Ok(Value::Bool(false)) };
+    let invert = arg_int(&args, 2, 0) & GREP_INVERT != 0;
+    let mut out = PhpArray::new();
+    for (k, v) in arg_array(&args, 1).iter() {
+        let v = v.deref();
+        let s = vm.stringify(&v)?;
+        if !check_subject(vm, &re, &s, 0) {
+            continue;
+        }
+        match find(vm, &re, &s, 0, false) {
+            Ok(found) if found.is_some() != invert => out.insert(k.clone(), v),
+            Ok(_) => {}
+            Err(()) => break,
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// `preg_last_error(): int`
+fn preg_last_error(vm: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(vm.preg.error))
+}
+
+/// `preg_last_error_msg(): string`
+fn preg_last_error_msg(vm: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(match vm.preg.error {
+        NO_ERROR => "No error",
+        INTERNAL_ERROR => "Internal error",
+        BACKTRACK_LIMIT_ERROR => "Backtrack limit exhausted",
+        RECURSION_LIMIT_ERROR => "Recursion limit exhausted",
+        BAD_UTF8_ERROR => "Malformed UTF-8 characters, possibly incorrectly encoded",
+        BAD_UTF8_OFFSET_ERROR => "The offset did not correspond to the beginning of a valid UTF-8 code point",
+        JIT_STACKLIMIT_ERROR => "JIT stack limit exhausted",
+        _ => "Unknown error",
+    }))
+}
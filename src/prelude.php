@@ -0,0 +1,56 @@
+<?php
+// Classes and interfaces the engine provides to every script.
+
+class stdClass {
+}
+
+// What unserialize() makes of an object whose class is unknown.
+class __PHP_Incomplete_Class {
+}
+
+interface UnitEnum {
+    public static function cases();
+}
+
+interface BackedEnum extends UnitEnum {
+    public static function from($value);
+    public static function tryFrom($value);
+}
+
+interface Stringable {
+    public function __toString();
+}
+
+interface Throwable {
+    public function getMessage();
+    public function getCode();
+    public function getFile();
+    public function getLine();
+    public function getTrace();
+    public function getPrevious();
+    public function getTraceAsString();
+    public function __toString();
+}
+
+interface Countable {
+    public function count();
+}
+
+interface JsonSerializable {
+    public function jsonSerialize();
+}
+
+interface Traversable {
+}
+
+interface Iterator extends Traversable {
+    public function current();
+    public function key();
+    public function next();
+    public function rewind();
+    public function valid();
+}
+
+interface IteratorAggregate extends Traversable {
+    public function getIterator();
+}
@@ -0,0 +1,399 @@
+use std::io::Write;
+
+use crate::extension::{arg_str, NativeFunction};
+use crate::limits::Growth;
+use crate::value::{format_float, Numeric, Stream, Value};
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction { name: "sprintf", params: &["string format", "mixed ...values"], func: sprintf },
+    NativeFunction { name: "vsprintf", params: &["string format", "array values"], func: vsprintf },
+    NativeFunction { name: "printf", params: &["string format", "mixed ...values"], func: printf },
+    NativeFunction { name: "vprintf", params: &["string format", "array values"], func: vprintf },
+    NativeFunction { name: "fprintf", params: &["mixed stream", "string format", "mixed ...values"], func: fprintf },
+    NativeFunction { name: "vfprintf", params: &["mixed stream", "string format", "array values"], func: vfprintf },
+];
+
+/// Digits `%e` and `%f` print when no precision is given.
+const DEFAULT_PRECISION: usize = 6;
+/// Precision beyond this is cut down, with a notice.
+const MAX_PRECISION: usize = 53;
+const INT_MAX: i64 = i32::MAX as i64;
+
+/// A conversion's flags, width and precision.
+#[derive(Clone, Copy)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    padding: u8,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Pads `s` to the width. A zero padding goes after the sign.
+    fn pad(&self, out: &mut Vec<u8>, s: &[u8], negative: bool) {
+        let npad = self.width.saturating_sub(s.len());
+        let mut s = s;
+        if !self.left {
+            if (negative || self.plus) && self.padding == b'0' && !s.is_empty() {
+                out.push(s[0]);
+                s = &s[1..];
+            }
+            out.extend(std::iter::repeat_n(self.padding, npad));
+        }
+        out.extend_from_slice(s);
+        if self.left {
+            out.extend(std::iter::repeat_n(self.padding, npad));
+        }
+    }
+
+    fn string(&self, out: &mut Vec<u8>, s: &[u8]) {
+        let s = match self.precision {
+            Some(p) => &s[..s.len().min(p)],
+            None => s,
+        };
+        self.pad(out, s, false);
+    }
+
+    fn int(&self, out: &mut Vec<u8>, n: i64) {
+        let mut s = n.to_string();
+        if self.plus && n >= 0 {
+            s.insert(0, '+');
+        }
+        self.pad(out, s.as_bytes(), n < 0);
+    }
+
+    /// `%b`, `%o`, `%x` and `%X` print the bits of the integer, so
+    /// negative numbers come out as two's complement. As in PHP, any
+    /// precision cuts the digits down to nothing.
+    fn unsigned(&self, out: &mut Vec<u8>, s: String) {
+        let digits = if self.precision.is_some() { &[][..] } else { s.as_bytes() };
+        self.pad(out, digits, false);
+    }
+
+    fn float(&self, vm: &mut Vm, func: &str, out: &mut Vec<u8>, f: f64, conversion: u8) {
+        let precision = match self.precision {
+            None => DEFAULT_PRECISION,
+            Some(p) if p > MAX_PRECISION => {
+                vm.diagnostic(
+                    "Notice",
+                    &format!(
+                        "{}(): Requested precision of {} digits was truncated to PHP maximum of {} digits",
+                        func, p, MAX_PRECISION
+                    ),
+                );
+                MAX_PRECISION
+            }
+            Some(p) => p,
+        };
+        if f.is_nan() {
+            let spec = Spec { width: 3, ..*self };
+            spec.pad(out, b"NaN", false);
+            return;
+        }
+        if f.is_infinite() {
+            let s: &[u8] = match (f < 0.0, self.plus) {
+                (true, _) => b"-Inf",
+                (false, true) => b"+Inf",
+                (false, false) => b"Inf",
+            };
+            self.pad(out, s, f < 0.0);
+            return;
+        }
+        let negative = f < 0.0;
+        let mut s = match conversion {
+            b'e' | b'E' => {
+                let sci = format!("{:.*e}", precision, f.abs());
+                let (mantissa, exp) = sci.split_once('e').unwrap();
+                let e = if conversion == b'E' { 'E' } else { 'e' };
+                match exp.strip_prefix('-') {
+                    Some(exp) => format!("{}{}-{}", mantissa, e, exp),
+                    None => format!("{}{}+{}", mantissa, e, exp),
+                }
+            }
+            b'g' | b'G' => {
+                let s = format_float(f.abs(), precision.max(1) as i32);
+                if conversion == b'g' { s.replace('E', "e") } else { s }
+            }
+            _ => format!("{:.*}", precision, f.abs()),
+        };
+        if negative {
+            s.insert(0, '-');
+        } else if self.plus {
+            s.insert(0, '+');
+        }
+        self.pad(out, s.as_bytes(), negative);
+    }
+}
+
+fn to_float(v: &Value) -> f64 {
+    match v.to_numeric() {
+        Numeric::Int(n) => n as f64,
+        Numeric::Float(f) => f,
+    }
+}
+
+/// Reads a decimal number in the format; `None` if it overflows an int.
+fn number(format: &[u8], pos: &mut usize) -> Option<i64> {
+    let start = *pos;
+    while format.get(*pos).is_some_and(u8::is_ascii_digit) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&format[start..*pos]).ok()?.parse().ok().filter(|n| *n <= INT_MAX)
+}
+
+/// An explicit `n$` argument number at `pos`, if there is one.
+fn argnum(format: &[u8], pos: &mut usize) -> VmResult<Option<usize>> {
+    let mut end = *pos;
+    while format.get(end).is_some_and(u8::is_ascii_digit) {
+        end += 1;
+    }
+    if end == *pos || format.get(end) != Some(&b'$') {
+        return Ok(None);
+    }
+    match number(format, pos) {
+        Some(n) if n > 0 => {
+            *pos += 1;
+            Ok(Some(n as usize - 1))
+        }
+        _ => Err(VmError::value_error(format!(
+            "Argument number specifier must be greater than zero and less than {}",
+            INT_MAX
+        ))),
+    }
+}
+
+/// What went wrong with the arguments; how to report it depends on
+/// whether they came as parameters or as an array.
+enum Missing {
+    /// The highest argument number the format needs.
+    Args(usize),
+    Error(VmError),
+}
+
+impl From<VmError> for Missing {
+    fn from(e: VmError) -> Self {
+        Missing::Error(e)
+    }
+}
+
+/// Formats like PHP's `php_formatted_print()`.
+fn format(vm: &mut Vm, func: &str, format: &[u8], args: &[Value]) -> Result<Vec<u8>, Missing> {
+    let mut out = Vec::with_capacity(format.len());
+    let mut growth = Growth::new();
+    let mut pos = 0;
+    let mut next_arg = 0;
+    let mut max_missing: Option<usize> = None;
+    // The argument at an explicit index, or the next one in turn.
+    let mut take = |index: Option<usize>| {
+        let i = index.unwrap_or_else(|| {
+            next_arg += 1;
+            next_arg - 1
+        });
+        let v = args.get(i).map(Value::deref);
+        if v.is_none() {
+            max_missing = max_missing.max(Some(i));
+        }
+        v
+    };
+    while pos < format.len() {
+        let c = format[pos];
+        pos += 1;
+        if c != b'%' {
+            out.push(c);
+            continue;
+        }
+        if format.get(pos) == Some(&b'%') {
+            out.push(b'%');
+            pos += 1;
+            continue;
+        }
+        let index = argnum(format, &mut pos)?;
+        let mut spec = Spec { left: false, plus: false, padding: b' ', width: 0, precision: None };
+        loop {
+            match format.get(pos) {
+                Some(b' ' | b'0') => spec.padding = format[pos],
+                Some(b'-') => spec.left = true,
+                Some(b'+') => spec.plus = true,
+                Some(b'\'') => {
+                    pos += 1;
+                    match format.get(pos) {
+                        Some(c) => spec.padding = *c,
+                        None => return Err(VmError::value_error("Missing padding character").into()),
+                    }
+                }
+                _ => break,
+            }
+            pos += 1;
+        }
+        if format.get(pos) == Some(&b'*') {
+            pos += 1;
+            let index = argnum(format, &mut pos)?;
+            match take(index) {
+                Some(Value::Int(n)) if (0..=INT_MAX).contains(&n) => spec.width = n as usize,
+                Some(Value::Int(_)) => {
+                    return Err(VmError::value_error(format!(
+                        "Width must be greater than or equal to zero and less than {}",
+                        INT_MAX
+                    ))
+                    .into());
+                }
+                Some(_) => return Err(VmError::value_error("Width must be an integer").into()),
+                None => {}
+            }
+        } else if format.get(pos).is_some_and(u8::is_ascii_digit) {
+            let Some(width) = number(format, &mut pos) else {
+                return Err(VmError::value_error(format!("Width must be greater than zero and less than {}", INT_MAX)).into());
+            };
+            spec.width = width as usize;
+        }
+        if format.get(pos) == Some(&b'.') {
+            pos += 1;
+            if format.get(pos) == Some(&b'*') {
+                pos += 1;
+                let index = argnum(format, &mut pos)?;
+                match take(index) {
+                    Some(Value::Int(-1)) => {}
+                    Some(Value::Int(n)) if (0..=INT_MAX).contains(&n) => spec.precision = Some(n as usize),
+                    Some(Value::Int(_)) => {
+                        return Err(VmError::value_error(format!("Precision must be between -1 and {}", INT_MAX)).into());
+                    }
+                    Some(_) => return Err(VmError::value_error("Precision must be an integer").into()),
+                    None => {}
+                }
+            } else {
+                let Some(precision) = number(format, &mut pos) else {
+                    return Err(VmError::value_error(format!(
+                        "Precision must be greater than zero and less than {}",
+                        INT_MAX
+                    ))
+                    .into());
+                };
+                spec.precision = Some(precision as usize);
+            }
+        }
+        if format.get(pos) == Some(&b'l') {
+            pos += 1;
+        }
+        let Some(&conversion) = format.get(pos) else {
+            return Err(VmError::value_error("Missing format specifier at end of string").into());
+        };
+        pos += 1;
+        if !b"bcdeEfFgGosuxX".contains(&conversion) {
+            return Err(VmError::value_error(format!("Unknown format specifier \"{}\"", conversion as char)).into());
+        }
+        let Some(v) = take(index) else { continue };
+        growth.add(vm, spec.width)?;
+        match conversion {
+            b's' => {
+                let s = vm.stringify(&v)?;
+                spec.string(&mut out, &s);
+            }
+            b'd' => spec.int(&mut out, v.to_int()),
+            b'u' => spec.pad(&mut out, (v.to_int() as u64).to_string().as_bytes(), false),
+            b'c' => out.push(v.to_int() as u8),
+            b'b' => spec.unsigned(&mut out, format!("{:b}", v.to_int())),
+            b'o' => spec.unsigned(&mut out, format!("{:o}", v.to_int())),
+            b'x' => spec.unsigned(&mut out, format!("{:x}", v.to_int())),
+            b'X' => spec.unsigned(&mut out, format!("{:X}", v.to_int())),
+            _ => spec.float(vm, func, &mut out, to_float(&v), conversion),
+        }
+    }
+    match max_missing {
+        Some(i) => Err(Missing::Args(i + 1)),
+        None => Ok(out),
+    }
+}
+
+/// Formats with the arguments passed after the format, which is argument
+/// `at`.
+fn format_args(vm: &mut Vm, func: &str, args: &[Value], at: usize) -> VmResult<Vec<u8>> {
+    format(vm, func, arg_str(args, at), &args[at + 1..]).map_err(|e| match e {
+        Missing::Args(n) => VmError::Error {
+            class: "ArgumentCountError",
+            message: format!("{} arguments are required, {} given", n + at + 1, args.len()),
+        },
+        Missing::Error(e) => e,
+    })
+}
+
+/// Formats with the values of the array after the format, which is
+/// argument `at`.
+fn format_array(vm: &mut Vm, func: &str, args: &[Value], at: usize) -> VmResult<Vec<u8>> {
+    let values: Vec<Value> = match &args[at + 1] {
+        Value::Array(a) => a.iter().map(|(_, v)| v.clone()).collect(),
+        _ => Vec::new(),
+    };
+    format(vm, func, arg_str(args, at), &values).map_err(|e| match e {
+        Missing::Args(n) => {
+            VmError::value_error(format!("The arguments array must contain {} items, {} given", n, values.len()))
+        }
+        Missing::Error(e) => e,
+    })
+}
+
+/// `sprintf(string $format, mixed ...$values): string`
+fn sprintf(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(format_args(vm, &callee.func.name, &args, 0)?))
+}
+
+/// `vsprintf(string $format, array $values): string`
+fn vsprintf(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(format_array(vm, &callee.func.name, &args, 0)?))
+}
+
+/// `printf(string $format, mixed ...$values): int`
+fn printf(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = format_args(vm, &callee.func.name, &args, 0)?;
+    vm.echo(&s);
+    Ok(Value::Int(s.len() as i64))
+}
+
+/// `vprintf(string $format, array $values): int`
+fn vprintf(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = format_array(vm, &callee.func.name, &args, 0)?;
+    vm.echo(&s);
+    Ok(Value::Int(s.len() as i64))
+}
+
+/// The stream an `fprintf()` family function writes to.
+fn stream(callee: &Callee, args: &[Value]) -> VmResult<Stream> {
+    match &args[0] {
+        Value::Resource(stream) => Ok(*stream),
+        v => Err(VmError::type_error(format!(
+            "{}(): Argument #1 ($stream) must be of type resource, {} given",
+            callee.func.name,
+            v.type_name()
+        ))),
+    }
+}
+
+/// Writes to `STDOUT` through the script's output, or straight to the
+/// process's standard error.
+fn write(vm: &mut Vm, stream: Stream, bytes: &[u8]) {
+    match stream {
+        Stream::Stdout => vm.echo(bytes),
+        Stream::Stderr => {
+            vm.flush();
+            let allowed = vm.charge_output(bytes.len());
+            let _ = std::io::stderr().write_all(&bytes[..allowed]);
+        }
+    }
+}
+
+/// `fprintf(resource $stream, string $format, mixed ...$values): int`
+fn fprintf(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let stream = stream(callee, &args)?;
+    let s = format_args(vm, &callee.func.name, &args, 1)?;
+    write(vm, stream, &s);
+    Ok(Value::Int(s.len() as i64))
+}
+
+/// `vfprintf(resource $stream, string $format, array $values): int`
+fn vfprintf(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let stream = stream(callee, &args)?;
+    let s = format_array(vm, &callee.func.name, &args, 1)?;
+    write(vm, stream, &s);
+    Ok(Value::Int(s.len() as i64))
+}
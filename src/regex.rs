@@ -0,0 +1,2086 @@
+use std::rc::Rc;
+
+/// What a pattern's modifiers ask for.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Options {
+    pub caseless: bool,
+    pub multiline: bool,
+    pub dotall: bool,
+    pub extended: bool,
+    pub anchored: bool,
+    pub dollar_endonly: bool,
+    pub ungreedy: bool,
+    pub utf: bool,
+    pub no_auto_capture: bool,
+    pub dupnames: bool,
+}
+
+/// Why a pattern did not compile, with the offset PCRE reports.
+pub(crate) struct CompileError {
+    pub message: &'static str,
+    pub offset: usize,
+}
+
+#[derive(Debug)]
+pub(crate) enum MatchError {
+    BacktrackLimit,
+    RecursionLimit,
+}
+
+/// Unbounded repetition.
+const MANY: u32 = u32::MAX;
+/// The largest count PCRE accepts in a `{}` quantifier.
+const MAX_REPEAT: u32 = 65535;
+/// Limit on compiled instructions, which counted repeats multiply.
+const MAX_PROGRAM: usize = 1 << 20;
+/// Limit on nested assertions, atomic groups and subroutine calls
+/// while matching.
+const MAX_NESTING: usize = 2500;
+
+/// A test for a class of characters, told whether Unicode properties
+/// apply.
+type Test = fn(u32, bool) -> bool;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Greed {
+    Greedy,
+    Lazy,
+    Possessive,
+}
+
+#[derive(Clone, Copy)]
+enum Assertion {
+    /// `\A`, and `^` outside multiline mode.
+    StartText,
+    StartLine,
+    /// `\Z`, and `$` outside multiline mode.
+    EndTextNewline,
+    /// `\z`, and `$` with the `D` modifier.
+    EndText,
+    EndLine,
+    WordBoundary,
+    NotWordBoundary,
+    /// `\G`: where the search started.
+    SearchStart,
+}
+
+pub(crate) struct Class {
+    negated: bool,
+    caseless: bool,
+    ranges: Vec<(u32, u32)>,
+    sets: Vec<(Test, bool)>,
+}
+
+impl Class {
+    fn of(test: Test, negated: bool) -> Class {
+        Class { negated: false, caseless: false, ranges: Vec::new(), sets: vec![(test, negated)] }
+    }
+
+    fn contains(&self, c: u32, ucp: bool) -> bool {
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) || self.sets.iter().any(|&(test, negated)| test(c, ucp) != negated)
+    }
+
+    fn matches(&self, c: u32, utf: bool) -> bool {
+        let hit = self.contains(c, utf) || (self.caseless && case_variants(c, utf).into_iter().flatten().any(|v| self.contains(v, utf)));
+        hit != self.negated
+    }
+}
+
+enum Condition {
+    Group(usize),
+    Named(Rc<[u8]>, usize),
+    /// `(?(DEFINE)...)`, which never holds.
+    Define,
+    Look(Box<Node>),
+}
+
+enum Node {
+    Empty,
+    Char(u32, bool),
+    /// `.`, which matches a newline with the `s` modifier.
+    Any(bool),
+    Class(Rc<Class>),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, u32, u32, Greed),
+    Backref(usize, bool, usize),
+    NamedBackref(Rc<[u8]>, bool, usize),
+    /// An assertion: lookbehind, negated, and the offset for errors.
+    Look(Box<Node>, bool, bool, usize),
+    Atomic(Box<Node>),
+    Cond(Condition, Box<Node>, Box<Node>),
+    Call(usize, usize),
+    NamedCall(Rc<[u8]>, usize),
+    Keep,
+    Fail,
+}
+
+/// One character's worth of matching, which repeats run without
+/// backtracking points of their own.
+enum Single {
+    Char(u32),
+    /// A character compared case-insensitively, stored folded.
+    Fold(u32),
+    Any,
+    AnyNewline,
+    Class(Rc<Class>),
+}
+
+enum Inst {
+    One(Single),
+    Repeat { item: Single, min: u32, max: u32, greed: Greed },
+    Assert(Assertion),
+    /// Tries the first target, backtracking to the second.
+    Split(usize, usize),
+    Jmp(usize),
+    Save(usize),
+    /// Records the position where an iteration of a loop that can match
+    /// nothing began, so `Progress` can leave it, for the target, once
+    /// an iteration matches nothing, as PCRE does.
+    SetReg(usize),
+    Progress(usize, usize),
+    /// The first of the groups that is set; several with duplicate names.
+    Backref { groups: Box<[usize]>, caseless: bool },
+    /// An assertion whose body follows, up to a `Succeed`; then the
+    /// program goes on at `end`, or at `no` for a failed condition.
+    Look { behind: Option<usize>, negate: bool, end: usize, no: Option<usize> },
+    Atomic { end: usize },
+    IfGroup { groups: Box<[usize]>, no: usize },
+    Call(usize),
+    Keep,
+    Fail,
+    Succeed,
+    Match,
+}
+
+/// A compiled pattern.
+pub(crate) struct Regex {
+    prog: Vec<Inst>,
+    /// Where each group's body is compiled for subroutine calls.
+    subs: Vec<Option<usize>>,
+    regs: usize,
+    pub(crate) captures: usize,
+    /// Group names, by group number.
+    pub(crate) names: Vec<Option<Rc<[u8]>>>,
+    pub(crate) utf: bool,
+    anchored: bool,
+    /// The pattern only matches at the start of the subject.
+    start_only: bool,
+    /// A byte every match starts with.
+    first_byte: Option<u8>,
+}
+
+// -- Characters ----------------------------------------------
+
+fn utf8_len(b: u8) -> usize {
+    match b {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1,
+    }
+}
+
+/// The character at `pos`, which must be the start of one.
+fn decode(s: &[u8], pos: usize, utf: bool) -> Option<(u32, usize)> {
+    let b = *s.get(pos)?;
+    if !utf || b < 0x80 {
+        return Some((u32::from(b), 1));
+    }
+    let len = utf8_len(b).min(s.len() - pos);
+    let mut c = u32::from(b) & (0x7f >> len);
+    for &b in &s[pos + 1..pos + len] {
+        c = (c << 6) | u32::from(b & 0x3f);
+    }
+    Some((c, len))
+}
+
+fn simple_case(c: u32, upper: bool) -> Option<u32> {
+    let ch = char::from_u32(c)?;
+    let mut mapped = if upper { ch.to_uppercase().collect::<Vec<_>>() } else { ch.to_lowercase().collect() };
+    match (mapped.pop(), mapped.is_empty()) {
+        (Some(m), true) if m != ch => Some(m as u32),
+        _ => None,
+    }
+}
+
+/// The character a caseless comparison uses.
+fn fold(c: u32, utf: bool) -> u32 {
+    if c < 0x80 {
+        return u32::from((c as u8).to_ascii_lowercase());
+    }
+    if !utf {
+        return c;
+    }
+    match c {
+        0x17f => 's' as u32,
+        0x3c2 => 0x3c3,
+        0x1e9b => 0x1e61,
+        _ => simple_case(c, false).unwrap_or(c),
+    }
+}
+
+/// The other cases of a character.
+fn case_variants(c: u32, utf: bool) -> [Option<u32>; 3] {
+    if c < 0x80 || !utf {
+        let b = c as u8;
+        let other = if b.is_ascii_lowercase() {
+            Some(u32::from(b.to_ascii_uppercase()))
+        } else if b.is_ascii_uppercase() {
+            Some(u32::from(b.to_ascii_lowercase()))
+        } else {
+            None
+        };
+        return [other.filter(|_| c < 0x80), None, None];
+    }
+    let folded = Some(fold(c, utf)).filter(|&f| f != c);
+    [simple_case(c, false), simple_case(c, true), folded]
+}
+
+fn in_ranges(c: u32, ranges: &[(u32, u32)]) -> bool {
+    ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi)
+}
+
+fn unicode(c: u32) -> char {
+    char::from_u32(c).unwrap_or('\0')
+}
+
+const DIGIT_ZEROS: &[u32] = &[
+    0x30, 0x660, 0x6f0, 0x7c0, 0x966, 0x9e6, 0xa66, 0xae6, 0xb66, 0xbe6, 0xc66, 0xce6, 0xd66, 0xde6, 0xe50, 0xed0, 0xf20, 0x1040,
+    0x1090, 0x17e0, 0x1810, 0x1946, 0x19d0, 0x1a80, 0x1a90, 0x1b50, 0x1bb0, 0x1c40, 0x1c50, 0xa620, 0xa8d0, 0xa900, 0xa9d0,
+    0xa9f0, 0xaa50, 0xabf0, 0xff10, 0x104a0, 0x11066, 0x1d7ce, 0x1d7d8, 0x1d7e2, 0x1d7ec, 0x1d7f6,
+];
+const MARKS: &[(u32, u32)] = &[
+    (0x300, 0x36f), (0x483, 0x489), (0x591, 0x5bd), (0x5bf, 0x5bf), (0x5c1, 0x5c2), (0x5c4, 0x5c5), (0x5c7, 0x5c7), (0x610, 0x61a),
+    (0x64b, 0x65f), (0x670, 0x670), (0x6d6, 0x6dc), (0x6df, 0x6e4), (0x6e7, 0x6e8), (0x6ea, 0x6ed), (0x900, 0x903), (0x93a, 0x93c),
+    (0x93e, 0x94f), (0x951, 0x957), (0x962, 0x963), (0x981, 0x983), (0x9bc, 0x9bc), (0x9be, 0x9cd), (0xe31, 0xe31), (0xe34, 0xe3a),
+    (0xe47, 0xe4e), (0x1ab0, 0x1aff), (0x1dc0, 0x1dff), (0x20d0, 0x20ff), (0x302a, 0x302f), (0x3099, 0x309a), (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f), (0xe0100, 0xe01ef),
+];
+const SPACE_SEPARATORS: &[(u32, u32)] = &[(0x20, 0x20), (0xa0, 0xa0), (0x1680, 0x1680), (0x2000, 0x200a), (0x202f, 0x202f), (0x205f, 0x205f), (0x3000, 0x3000)];
+const FORMAT: &[(u32, u32)] = &[
+    (0xad, 0xad), (0x600, 0x605), (0x61c, 0x61c), (0x6dd, 0x6dd), (0x70f, 0x70f), (0x200b, 0x200f), (0x202a, 0x202e), (0x2060, 0x2064),
+    (0x2066, 0x206f), (0xfeff, 0xfeff), (0xfff9, 0xfffb), (0xe0001, 0xe0001), (0xe0020, 0xe007f),
+];
+const PRIVATE_USE: &[(u32, u32)] = &[(0xe000, 0xf8ff), (0xf0000, 0xffffd), (0x100000, 0x10fffd)];
+const DASHES: &[(u32, u32)] = &[(0x2d, 0x2d), (0x58a, 0x58a), (0x5be, 0x5be), (0x2010, 0x2015), (0x2e3a, 0x2e3b), (0x301c, 0x301c), (0x3030, 0x3030), (0xfe58, 0xfe58), (0xfe63, 0xfe63), (0xff0d, 0xff0d)];
+const OPEN_PUNCTUATION: &[(u32, u32)] = &[(0x28, 0x28), (0x5b, 0x5b), (0x7b, 0x7b), (0x201a, 0x201a), (0x201e, 0x201e), (0x2045, 0x2045), (0x207d, 0x207d), (0x208d, 0x208d), (0x2329, 0x2329), (0x3008, 0x3008), (0x300a, 0x300a), (0x300c, 0x300c), (0x300e, 0x300e), (0x3010, 0x3010), (0xff08, 0xff08), (0xff3b, 0xff3b), (0xff5b, 0xff5b)];
+const CLOSE_PUNCTUATION: &[(u32, u32)] = &[(0x29, 0x29), (0x5d, 0x5d), (0x7d, 0x7d), (0x2046, 0x2046), (0x207e, 0x207e), (0x208e, 0x208e), (0x232a, 0x232a), (0x3009, 0x3009), (0x300b, 0x300b), (0x300d, 0x300d), (0x300f, 0x300f), (0x3011, 0x3011), (0xff09, 0xff09), (0xff3d, 0xff3d), (0xff5d, 0xff5d)];
+const INITIAL_QUOTES: &[(u32, u32)] = &[(0xab, 0xab), (0x2018, 0x2018), (0x201b, 0x201c), (0x201f, 0x201f), (0x2039, 0x2039)];
+const FINAL_QUOTES: &[(u32, u32)] = &[(0xbb, 0xbb), (0x2019, 0x2019), (0x201d, 0x201d), (0x203a, 0x203a)];
+const CONNECTORS: &[(u32, u32)] = &[(0x5f, 0x5f), (0x203f, 0x2040), (0x2054, 0x2054), (0xfe33, 0xfe34), (0xfe4d, 0xfe4f), (0xff3f, 0xff3f)];
+const OTHER_PUNCTUATION: &[(u32, u32)] = &[
+    (0x21, 0x23), (0x25, 0x27), (0x2a, 0x2a), (0x2c, 0x2c), (0x2e, 0x2f), (0x3a, 0x3b), (0x3f, 0x40), (0x5c, 0x5c), (0xa1, 0xa1),
+    (0xa7, 0xa7), (0xb6, 0xb7), (0xbf, 0xbf), (0x37e, 0x37e), (0x387, 0x387), (0x55a, 0x55f), (0x589, 0x589), (0x5c0, 0x5c0),
+    (0x5c3, 0x5c3), (0x5c6, 0x5c6), (0x5f3, 0x5f4), (0x609, 0x60a), (0x60c, 0x60d), (0x61b, 0x61b), (0x61d, 0x61f), (0x66a, 0x66d),
+    (0x6d4, 0x6d4), (0x964, 0x965), (0x970, 0x970), (0xe4f, 0xe4f), (0xe5a, 0xe5b), (0x2016, 0x2017), (0x2020, 0x2027),
+    (0x2030, 0x2038), (0x203b, 0x203e), (0x2041, 0x2043), (0x2047, 0x2051), (0x2053, 0x2053), (0x2055, 0x205e), (0x3001, 0x3003),
+    (0x303d, 0x303d), (0x30fb, 0x30fb), (0xff01, 0xff03), (0xff05, 0xff07), (0xff0a, 0xff0a), (0xff0c, 0xff0c), (0xff0e, 0xff0f),
+    (0xff1a, 0xff1b), (0xff1f, 0xff20), (0xff3c, 0xff3c), (0xff61, 0xff61), (0xff64, 0xff65),
+];
+const MATH_SYMBOLS: &[(u32, u32)] = &[
+    (0x2b, 0x2b), (0x3c, 0x3e), (0x7c, 0x7c), (0x7e, 0x7e), (0xac, 0xac), (0xb1, 0xb1), (0xd7, 0xd7), (0xf7, 0xf7), (0x3f6, 0x3f6),
+    (0x2044, 0x2044), (0x2052, 0x2052), (0x2190, 0x2194), (0x2200, 0x22ff), (0x2320, 0x2321), (0x27c0, 0x27c4), (0x27c7, 0x27e5),
+    (0x2980, 0x2982), (0x2a00, 0x2aff), (0xff0b, 0xff0b), (0xff1c, 0xff1e), (0xff5c, 0xff5c), (0xff5e, 0xff5e),
+];
+const CURRENCY_SYMBOLS: &[(u32, u32)] = &[(0x24, 0x24), (0xa2, 0xa5), (0x58f, 0x58f), (0x60b, 0x60b), (0x9f2, 0x9f3), (0xe3f, 0xe3f), (0x20a0, 0x20c0), (0xfdfc, 0xfdfc), (0xff04, 0xff04), (0xffe0, 0xffe1), (0xffe5, 0xffe6)];
+const MODIFIER_SYMBOLS: &[(u32, u32)] = &[(0x5e, 0x5e), (0x60, 0x60), (0xa8, 0xa8), (0xaf, 0xaf), (0xb4, 0xb4), (0xb8, 0xb8), (0x2c2, 0x2c5), (0x2d2, 0x2df), (0x2e5, 0x2eb), (0x2ed, 0x2ed), (0x2ef, 0x2ff), (0x375, 0x375), (0x384, 0x385), (0x309b, 0x309c), (0xff3e, 0xff3e), (0xff40, 0xff40), (0x1f3fb, 0x1f3ff)];
+const OTHER_SYMBOLS: &[(u32, u32)] = &[
+    (0xa6, 0xa6), (0xa9, 0xa9), (0xae, 0xae), (0xb0, 0xb0), (0x482, 0x482), (0x2100, 0x2101), (0x2103, 0x2106), (0x2108, 0x2109),
+    (0x2114, 0x2114), (0x2116, 0x2117), (0x211e, 0x2123), (0x2125, 0x2125), (0x2127, 0x2127), (0x2129, 0x2129), (0x212e, 0x212e),
+    (0x2195, 0x21ff), (0x2300, 0x231f), (0x2322, 0x2328), (0x232b, 0x23ff), (0x2400, 0x2426), (0x2440, 0x244a), (0x249c, 0x24e9),
+    (0x2500, 0x25ff), (0x2600, 0x2767), (0x2794, 0x27bf), (0x2800, 0x28ff), (0x2b00, 0x2bff), (0x2e80, 0x2fdf), (0x3004, 0x3004),
+    (0x3012, 0x3013), (0x3020, 0x3020), (0x3200, 0x32ff), (0x1f000, 0x1f3fa), (0x1f400, 0x1faff),
+];
+const LATIN: &[(u32, u32)] = &[(0x41, 0x5a), (0x61, 0x7a), (0xaa, 0xaa), (0xba, 0xba), (0xc0, 0xd6), (0xd8, 0xf6), (0xf8, 0x2b8), (0x2e0, 0x2e4), (0x1d00, 0x1d25), (0x1e00, 0x1eff), (0x2071, 0x2071), (0x207f, 0x207f), (0x212a, 0x212b), (0x2c60, 0x2c7f), (0xa722, 0xa7ff), (0xfb00, 0xfb06), (0xff21, 0xff3a), (0xff41, 0xff5a)];
+const GREEK: &[(u32, u32)] = &[(0x370, 0x373), (0x375, 0x377), (0x37a, 0x37d), (0x37f, 0x37f), (0x384, 0x384), (0x386, 0x386), (0x388, 0x3e1), (0x3f0, 0x3ff), (0x1d26, 0x1d2a), (0x1f00, 0x1ffe), (0x2126, 0x2126)];
+const CYRILLIC: &[(u32, u32)] = &[(0x400, 0x52f), (0x1c80, 0x1c88), (0x2de0, 0x2dff), (0xa640, 0xa69f)];
+const ARMENIAN: &[(u32, u32)] = &[(0x531, 0x556), (0x559, 0x58a), (0x58d, 0x58f), (0xfb13, 0xfb17)];
+const HEBREW: &[(u32, u32)] = &[(0x591, 0x5c7), (0x5d0, 0x5ea), (0x5ef, 0x5f4), (0xfb1d, 0xfb4f)];
+const ARABIC: &[(u32, u32)] = &[(0x600, 0x604), (0x606, 0x60b), (0x60d, 0x61a), (0x61c, 0x61e), (0x620, 0x63f), (0x641, 0x64a), (0x656, 0x66f), (0x671, 0x6dc), (0x6de, 0x6ff), (0x750, 0x77f), (0x8a0, 0x8ff), (0xfb50, 0xfdff), (0xfe70, 0xfefc)];
+const DEVANAGARI: &[(u32, u32)] = &[(0x900, 0x950), (0x955, 0x963), (0x966, 0x97f), (0xa8e0, 0xa8ff)];
+const THAI: &[(u32, u32)] = &[(0xe01, 0xe3a), (0xe40, 0xe5b)];
+const GEORGIAN: &[(u32, u32)] = &[(0x10a0, 0x10ff), (0x1c90, 0x1cbf), (0x2d00, 0x2d2d)];
+const HANGUL: &[(u32, u32)] = &[(0x1100, 0x11ff), (0x302e, 0x302f), (0x3131, 0x318e), (0x3200, 0x321e), (0x3260, 0x327e), (0xa960, 0xa97c), (0xac00, 0xd7a3), (0xd7b0, 0xd7fb), (0xffa0, 0xffdc)];
+const HIRAGANA: &[(u32, u32)] = &[(0x3041, 0x3096), (0x309d, 0x309f), (0x1b001, 0x1b11f), (0x1f200, 0x1f200)];
+const KATAKANA: &[(u32, u32)] = &[(0x30a1, 0x30fa), (0x30fd, 0x30ff), (0x31f0, 0x31ff), (0x32d0, 0x32fe), (0x3300, 0x3357), (0xff66, 0xff6f), (0xff71, 0xff9d), (0x1b000, 0x1b000)];
+const HAN: &[(u32, u32)] = &[(0x2e80, 0x2e99), (0x2e9b, 0x2ef3), (0x2f00, 0x2fd5), (0x3005, 0x3005), (0x3007, 0x3007), (0x3021, 0x3029), (0x3038, 0x303b), (0x3400, 0x4dbf), (0x4e00, 0x9fff), (0xf900, 0xfa6d), (0xfa70, 0xfad9), (0x20000, 0x2a6df), (0x2a700, 0x2ebe0), (0x2f800, 0x2fa1d), (0x30000, 0x3134a)];
+
+fn is_mark(c: u32) -> bool {
+    in_ranges(c, MARKS)
+}
+
+fn is_letter(c: u32) -> bool {
+    unicode(c).is_alphabetic() && !is_mark(c) && !is_number(c)
+}
+
+fn is_number(c: u32) -> bool {
+    unicode(c).is_numeric()
+}
+
+fn is_decimal(c: u32) -> bool {
+    DIGIT_ZEROS.iter().any(|&zero| zero <= c && c < zero + 10)
+}
+
+fn is_punctuation(c: u32) -> bool {
+    [DASHES, OPEN_PUNCTUATION, CLOSE_PUNCTUATION, INITIAL_QUOTES, FINAL_QUOTES, CONNECTORS, OTHER_PUNCTUATION].iter().any(|r| in_ranges(c, r))
+}
+
+fn is_symbol(c: u32) -> bool {
+    [MATH_SYMBOLS, CURRENCY_SYMBOLS, MODIFIER_SYMBOLS, OTHER_SYMBOLS].iter().any(|r| in_ranges(c, r))
+}
+
+fn is_control(c: u32) -> bool {
+    c < 0x20 || (0x7f..=0x9f).contains(&c)
+}
+
+fn is_separator(c: u32) -> bool {
+    in_ranges(c, SPACE_SEPARATORS) || c == 0x2028 || c == 0x2029
+}
+
+fn is_digit(c: u32, ucp: bool) -> bool {
+    if ucp { is_decimal(c) } else { (0x30..=0x39).contains(&c) }
+}
+
+fn is_word(c: u32, ucp: bool) -> bool {
+    if ucp {
+        c == 0x5f || is_letter(c) || is_number(c) || is_mark(c) || in_ranges(c, CONNECTORS)
+    } else {
+        c < 0x80 && (c as u8).is_ascii_alphanumeric() || c == 0x5f
+    }
+}
+
+fn is_space(c: u32, ucp: bool) -> bool {
+    matches!(c, 0x09..=0x0d | 0x20) || (ucp && (c == 0x85 || is_separator(c)))
+}
+
+fn is_hspace(c: u32, utf: bool) -> bool {
+    matches!(c, 0x09 | 0x20 | 0xa0) || (utf && in_ranges(c, SPACE_SEPARATORS))
+}
+
+fn is_vspace(c: u32, utf: bool) -> bool {
+    matches!(c, 0x0a..=0x0d | 0x85) || (utf && (c == 0x2028 || c == 0x2029))
+}
+
+fn ascii(c: u32, test: fn(&u8) -> bool) -> bool {
+    c < 0x80 && test(&(c as u8))
+}
+
+/// `[:name:]` classes, with what they become with Unicode properties.
+const POSIX_CLASSES: &[(&str, Test)] = &[
+    ("alpha", |c, ucp| if ucp { is_letter(c) } else { ascii(c, u8::is_ascii_alphabetic) }),
+    ("alnum", |c, ucp| if ucp { is_letter(c) || is_number(c) } else { ascii(c, u8::is_ascii_alphanumeric) }),
+    ("ascii", |c, _| c < 0x80),
+    ("blank", |c, ucp| is_hspace(c, ucp)),
+    ("cntrl", |c, ucp| if ucp { is_control(c) } else { ascii(c, u8::is_ascii_control) }),
+    ("digit", is_digit),
+    ("graph", |c, ucp| if ucp { c > 0x20 && !is_control(c) && !is_separator(c) } else { ascii(c, u8::is_ascii_graphic) }),
+    ("lower", |c, ucp| if ucp { unicode(c).is_lowercase() } else { ascii(c, u8::is_ascii_lowercase) }),
+    ("print", |c, ucp| if ucp { c >= 0x20 && !is_control(c) } else { ascii(c, |b| b.is_ascii_graphic() || *b == b' ') }),
+    ("punct", |c, _| ascii(c, u8::is_ascii_punctuation)),
+    ("space", is_space),
+    ("upper", |c, ucp| if ucp { unicode(c).is_uppercase() } else { ascii(c, u8::is_ascii_uppercase) }),
+    ("word", is_word),
+    ("xdigit", |c, _| ascii(c, u8::is_ascii_hexdigit)),
+];
+
+/// Names for `\p{...}`: general categories, scripts and PCRE's own.
+const PROPERTIES: &[(&str, Test)] = &[
+    ("Any", |_, _| true),
+    ("L", |c, _| is_letter(c)),
+    ("L&", |c, _| unicode(c).is_uppercase() || unicode(c).is_lowercase()),
+    ("Lu", |c, _| unicode(c).is_uppercase()),
+    ("Ll", |c, _| unicode(c).is_lowercase()),
+    ("Lt", |c, _| matches!(c, 0x1c5 | 0x1c8 | 0x1cb | 0x1f2 | 0x1f88..=0x1f8f | 0x1f98..=0x1f9f | 0x1fa8..=0x1faf | 0x1fbc | 0x1fcc | 0x1ffc)),
+    ("Lm", |c, _| in_ranges(c, &[(0x2b0, 0x2c1), (0x2c6, 0x2d1), (0x2e0, 0x2e4), (0x2ec, 0x2ec), (0x2ee, 0x2ee), (0x374, 0x374), (0x3005, 0x3005), (0x309d, 0x309e), (0x30fc, 0x30fe), (0xff70, 0xff70), (0xff9e, 0xff9f)])),
+    ("Lo", |c, _| is_letter(c) && !unicode(c).is_uppercase() && !unicode(c).is_lowercase()),
+    ("M", |c, _| is_mark(c)),
+    ("Mn", |c, _| is_mark(c)),
+    ("Mc", |c, _| matches!(c, 0x903 | 0x93b | 0x93e..=0x940 | 0x949..=0x94c | 0x94e..=0x94f | 0x982..=0x983)),
+    ("Me", |c, _| matches!(c, 0x488..=0x489 | 0x20dd..=0x20e0 | 0x20e2..=0x20e4)),
+    ("N", |c, _| is_number(c)),
+    ("Nd", |c, _| is_decimal(c)),
+    ("Nl", |c, _| in_ranges(c, &[(0x16ee, 0x16f0), (0x2160, 0x2182), (0x2185, 0x2188), (0x3007, 0x3007), (0x3021, 0x3029), (0x3038, 0x303a)])),
+    ("No", |c, _| is_number(c) && !is_decimal(c)),
+    ("P", |c, _| is_punctuation(c)),
+    ("Pc", |c, _| in_ranges(c, CONNECTORS)),
+    ("Pd", |c, _| in_ranges(c, DASHES)),
+    ("Ps", |c, _| in_ranges(c, OPEN_PUNCTUATION)),
+    ("Pe", |c, _| in_ranges(c, CLOSE_PUNCTUATION)),
+    ("Pi", |c, _| in_ranges(c, INITIAL_QUOTES)),
+    ("Pf", |c, _| in_ranges(c, FINAL_QUOTES)),
+    ("Po", |c, _| in_ranges(c, OTHER_PUNCTUATION)),
+    ("S", |c, _| is_symbol(c)),
+    ("Sm", |c, _| in_ranges(c, MATH_SYMBOLS)),
+    ("Sc", |c, _| in_ranges(c, CURRENCY_SYMBOLS)),
+    ("Sk", |c, _| in_ranges(c, MODIFIER_SYMBOLS)),
+    ("So", |c, _| in_ranges(c, OTHER_SYMBOLS)),
+    ("Z", |c, _| is_separator(c)),
+    ("Zs", |c, _| in_ranges(c, SPACE_SEPARATORS)),
+    ("Zl", |c, _| c == 0x2028),
+    ("Zp", |c, _| c == 0x2029),
+    ("C", |c, _| is_control(c) || in_ranges(c, FORMAT) || in_ranges(c, PRIVATE_USE)),
+    ("Cc", |c, _| is_control(c)),
+    ("Cf", |c, _| in_ranges(c, FORMAT)),
+    ("Co", |c, _| in_ranges(c, PRIVATE_USE)),
+    ("Cs", |c, _| (0xd800..=0xdfff).contains(&c)),
+    ("Cn", |c, _| char::from_u32(c).is_none()),
+    ("Xan", |c, _| is_letter(c) || is_number(c)),
+    ("Xsp", |c, _| is_space(c, true)),
+    ("Xps", |c, _| is_space(c, true)),
+    ("Xwd", |c, _| is_word(c, true)),
+    ("Xuc", |c, _| matches!(c, 0x24 | 0x40 | 0x60) || (c >= 0xa0 && !(0xd800..=0xdfff).contains(&c))),
+    ("Latin", |c, _| in_ranges(c, LATIN)),
+    ("Greek", |c, _| in_ranges(c, GREEK)),
+    ("Cyrillic", |c, _| in_ranges(c, CYRILLIC)),
+    ("Armenian", |c, _| in_ranges(c, ARMENIAN)),
+    ("Hebrew", |c, _| in_ranges(c, HEBREW)),
+    ("Arabic", |c, _| in_ranges(c, ARABIC)),
+    ("Devanagari", |c, _| in_ranges(c, DEVANAGARI)),
+    ("Thai", |c, _| in_ranges(c, THAI)),
+    ("Georgian", |c, _| in_ranges(c, GEORGIAN)),
+    ("Hangul", |c, _| in_ranges(c, HANGUL)),
+    ("Hiragana", |c, _| in_ranges(c, HIRAGANA)),
+    ("Katakana", |c, _| in_ranges(c, KATAKANA)),
+    ("Han", |c, _| in_ranges(c, HAN)),
+    ("Common", |c, _| !is_letter(c) && !is_mark(c)),
+];
+
+fn property(name: &[u8]) -> Option<Test> {
+    let name: Vec<u8> = name.iter().filter(|c| !matches!(c, b' ' | b'_' | b'-')).copied().collect();
+    PROPERTIES.iter().find(|(n, _)| n.as_bytes().eq_ignore_ascii_case(&name)).map(|(_, t)| *t)
+}
+
+// -- Parser --------------------------------------------------
+
+#[derive(Clone, Copy)]
+struct Flags {
+    caseless: bool,
+    multiline: bool,
+    dotall: bool,
+    extended: bool,
+    ungreedy: bool,
+    no_auto_capture: bool,
+    dupnames: bool,
+    dollar_endonly: bool,
+}
+
+/// An escape sequence, in or out of a class.
+enum Escape {
+    Char(u32),
+    Set(Test, bool),
+    Node(Node),
+    /// `\Q`: the rest up to `\E` is literal.
+    Quote,
+    /// `\E` without `\Q`, which does nothing.
+    Nothing,
+}
+
+struct Parser<'a> {
+    pat: &'a [u8],
+    pos: usize,
+    utf: bool,
+    flags: Flags,
+    /// Capture groups opened so far.
+    groups: usize,
+    /// Capture groups in the whole pattern, which decides whether `\12`
+    /// is a back-reference or an octal escape.
+    total_groups: usize,
+    names: Vec<(Rc<[u8]>, usize)>,
+}
+
+fn error<T>(message: &'static str, offset: usize) -> Result<T, CompileError> {
+    Err(CompileError { message, offset })
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.pat.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<u8> {
+        self.pat.get(self.pos + ahead).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_char(&mut self) -> Option<u32> {
+        let (c, len) = decode(self.pat, self.pos, self.utf)?;
+        self.pos += len;
+        Some(c)
+    }
+
+    /// Skips whitespace and `#` comments in extended mode.
+    fn skip_extended(&mut self) {
+        if !self.flags.extended {
+            return;
+        }
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c') => self.pos += 1,
+                Some(b'#') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        let mut n: u32 = 0;
+        while let Some(d @ b'0'..=b'9') = self.peek() {
+            n = n.saturating_mul(10).saturating_add(u32::from(d - b'0'));
+            self.pos += 1;
+        }
+        (self.pos > start).then_some(n)
+    }
+
+    fn alternation(&mut self, branch_reset: bool) -> Result<Node, CompileError> {
+        let start = self.groups;
+        let mut most = start;
+        let mut branches = Vec::new();
+        loop {
+            if branch_reset {
+                self.groups = start;
+            }
+            branches.push(self.sequence()?);
+            most = most.max(self.groups);
+            if !self.eat(b'|') {
+                break;
+            }
+        }
+        self.groups = most;
+        Ok(if branches.len() == 1 { branches.pop().expect("one branch") } else { Node::Alt(branches) })
+    }
+
+    fn sequence(&mut self) -> Result<Node, CompileError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_extended();
+            if matches!(self.peek(), None | Some(b'|' | b')')) {
+                break;
+            }
+            let Some((atom, repeatable)) = self.atom(&mut items)? else { continue };
+            let node = self.quantified(atom, repeatable)?;
+            items.push(node);
+        }
+        Ok(match items.len() {
+            0 => Node::Empty,
+            1 => items.pop().expect("one item"),
+            _ => Node::Concat(items),
+        })
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}` at the current position, or `None` if the
+    /// brace is a literal.
+    fn brace_quantifier(&mut self) -> Result<Option<(u32, u32)>, CompileError> {
+        let start = self.pos;
+        self.pos += 1;
+        let min = self.number();
+        let max = if self.eat(b',') { self.number() } else { min };
+        if min.is_none() || !self.eat(b'}') {
+            self.pos = start;
+            return Ok(None);
+        }
+        let (min, max) = (min.expect("checked"), if self.pat[self.pos - 2] == b',' { MANY } else { max.expect("checked") });
+        if min > MAX_REPEAT || (max != MANY && max > MAX_REPEAT) {
+            return error("number too big in {} quantifier", self.pos - 1);
+        }
+        if max < min {
+            return error("numbers out of order in {} quantifier", self.pos - 1);
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn quantified(&mut self, atom: Node, repeatable: bool) -> Result<Node, CompileError> {
+        self.skip_extended();
+        let at = self.pos;
+        let (min, max) = match self.peek() {
+            Some(b'*') => (0, MANY),
+            Some(b'+') => (1, MANY),
+            Some(b'?') => (0, 1),
+            Some(b'{') => match self.brace_quantifier()? {
+                Some(range) => {
+                    self.pos -= 1;
+                    range
+                }
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        if !repeatable {
+            return error("quantifier does not follow a repeatable item", at);
+        }
+        self.pos += 1;
+        let greed = if self.eat(b'+') {
+            Greed::Possessive
+        } else if self.eat(b'?') != self.flags.ungreedy {
+            Greed::Lazy
+        } else {
+            Greed::Greedy
+        };
+        Ok(Node::Repeat(Box::new(atom), min, max, greed))
+    }
+
+    /// The next item, which is repeatable unless it is an assertion, or
+    /// `None` for one that matches nothing, like an option setting.
+    /// Quoted text goes straight into `items`, but for its last character.
+    fn atom(&mut self, items: &mut Vec<Node>) -> Result<Option<(Node, bool)>, CompileError> {
+        let at = self.pos;
+        let c = self.next_char().expect("not at the end");
+        let node = match c {
+            0x28 => return self.group(at),
+            0x5b => Node::Class(Rc::new(self.class()?)),
+            0x2e => Node::Any(self.flags.dotall),
+            0x5e => return Ok(Some((Node::Assert(if self.flags.multiline { Assertion::StartLine } else { Assertion::StartText }), false))),
+            0x24 => {
+                let assertion = match (self.flags.multiline, self.flags.dollar_endonly) {
+                    (true, _) => Assertion::EndLine,
+                    (false, true) => Assertion::EndText,
+                    (false, false) => Assertion::EndTextNewline,
+                };
+                return Ok(Some((Node::Assert(assertion), false)));
+            }
+            0x2a | 0x2b | 0x3f => return error("quantifier does not follow a repeatable item", at),
+            0x7b => {
+                self.pos = at;
+                if self.brace_quantifier()?.is_some() {
+                    return error("quantifier does not follow a repeatable item", at);
+                }
+                self.pos = at + 1;
+                Node::Char(c, false)
+            }
+            0x5c => match self.escape(false)? {
+                Escape::Char(c) => Node::Char(c, self.flags.caseless),
+                Escape::Set(test, negated) => Node::Class(Rc::new(Class::of(test, negated))),
+                Escape::Node(node @ Node::Assert(_)) => return Ok(Some((node, false))),
+                Escape::Node(node) => node,
+                Escape::Quote => return self.quoted(items),
+                Escape::Nothing => return Ok(None),
+            },
+            c => Node::Char(c, self.flags.caseless),
+        };
+        Ok(Some((node, true)))
+    }
+
+    /// `\Q...\E`
+    fn quoted(&mut self, items: &mut Vec<Node>) -> Result<Option<(Node, bool)>, CompileError> {
+        let mut last = None;
+        while self.peek().is_some() {
+            if self.pat[self.pos..].starts_with(b"\\E") {
+                self.pos += 2;
+                break;
+            }
+            let c = self.next_char().expect("not at the end");
+            items.extend(last.replace(Node::Char(c, self.flags.caseless)));
+        }
+        Ok(last.map(|node| (node, true)))
+    }
+
+    fn hex_digits(&mut self, max: usize) -> u32 {
+        let mut n = 0;
+        for _ in 0..max {
+            match self.peek().and_then(|b| (b as char).to_digit(16)) {
+                Some(d) => {
+                    n = n * 16 + d;
+                    self.pos += 1;
+                }
+                None => break,
+            }
+        }
+        n
+    }
+
+    fn octal_digits(&mut self, max: usize) -> u32 {
+        let mut n = 0;
+        for _ in 0..max {
+            match self.peek() {
+                Some(d @ b'0'..=b'7') => {
+                    n = n * 8 + u32::from(d - b'0');
+                    self.pos += 1;
+                }
+                _ => break,
+            }
+        }
+        n
+    }
+
+    fn check_code_point(&self, c: u32, at: usize) -> Result<u32, CompileError> {
+        if (!self.utf && c > 0xff) || c > 0x10ffff {
+            return error("character code point value in \\x{} or \\o{} is too large", at);
+        }
+        if self.utf && (0xd800..=0xdfff).contains(&c) {
+            return error("disallowed Unicode code point (>= 0xd800 && <= 0xdfff)", at);
+        }
+        Ok(c)
+    }
+
+    /// A `{...}` argument to `\x` or `\o`.
+    fn braced_number(&mut self, radix: u32) -> Result<u32, CompileError> {
+        let start = self.pos;
+        let mut n: u32 = 0;
+        while let Some(d) = self.peek().and_then(|b| (b as char).to_digit(radix)) {
+            n = n.saturating_mul(radix).saturating_add(d);
+            self.pos += 1;
+        }
+        if self.pos == start || !self.eat(b'}') {
+            return error("digits missing in \\x{} or \\o{} or \\N{U+}", self.pos);
+        }
+        self.check_code_point(n, self.pos)
+    }
+
+    fn name(&mut self, end: u8) -> Result<Rc<[u8]>, CompileError> {
+        let start = self.pos;
+        if self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            return error("subpattern name must start with a non-digit", self.pos);
+        }
+        while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            self.pos += 1;
+        }
+        if self.pos - start > 32 {
+            return error("subpattern name is too long (maximum 32 code units)", self.pos);
+        }
+        if self.pos == start {
+            return error("subpattern name expected", self.pos);
+        }
+        let name = Rc::from(&self.pat[start..self.pos]);
+        if !self.eat(end) {
+            return error("syntax error in subpattern name (missing terminator?)", self.pos);
+        }
+        Ok(name)
+    }
+
+    /// After a backslash.
+    fn escape(&mut self, in_class: bool) -> Result<Escape, CompileError> {
+        let at = self.pos - 1;
+        let Some(c) = self.next_char() else { return error("\\ at end of pattern", at + 1) };
+        let caseless = self.flags.caseless;
+        let ch = |c: u8| Ok(Escape::Char(u32::from(c)));
+        let Ok(b) = u8::try_from(c) else { return Ok(Escape::Char(c)) };
+        match b {
+            b'a' => ch(0x07),
+            b'e' => ch(0x1b),
+            b'f' => ch(0x0c),
+            b'n' => ch(b'\n'),
+            b'r' => ch(b'\r'),
+            b't' => ch(b'\t'),
+            b'b' if in_class => ch(0x08),
+            b'0' => Ok(Escape::Char(self.octal_digits(2))),
+            b'1'..=b'9' if in_class => {
+                self.pos -= 1;
+                match self.octal_digits(3) {
+                    0 => Ok(Escape::Char(u32::from(self.pat[at + 1]))).inspect(|_| self.pos += 1),
+                    n => Ok(Escape::Char(self.check_code_point(n, self.pos)?)),
+                }
+            }
+            b'1'..=b'9' => {
+                self.pos -= 1;
+                let start = self.pos;
+                let n = self.number().expect("a digit");
+                if n < 10 || b >= b'8' || n as usize <= self.total_groups {
+                    return Ok(Escape::Node(Node::Backref(n as usize, caseless, at)));
+                }
+                self.pos = start;
+                let n = self.octal_digits(3);
+                Ok(Escape::Char(self.check_code_point(n, self.pos)?))
+            }
+            b'o' if self.eat(b'{') => Ok(Escape::Char(self.braced_number(8)?)),
+            b'x' if self.eat(b'{') => Ok(Escape::Char(self.braced_number(16)?)),
+            b'x' => Ok(Escape::Char(self.hex_digits(2))),
+            b'c' => match self.peek() {
+                Some(c) if c.is_ascii() => {
+                    self.pos += 1;
+                    ch(c.to_ascii_uppercase() ^ 0x40)
+                }
+                _ => error("\\c must be followed by a printable ASCII character", self.pos),
+            },
+            b'd' => Ok(Escape::Set(is_digit, false)),
+            b'D' => Ok(Escape::Set(is_digit, true)),
+            b'w' => Ok(Escape::Set(is_word, false)),
+            b'W' => Ok(Escape::Set(is_word, true)),
+            b's' => Ok(Escape::Set(is_space, false)),
+            b'S' => Ok(Escape::Set(is_space, true)),
+            b'h' => Ok(Escape::Set(is_hspace, false)),
+            b'H' => Ok(Escape::Set(is_hspace, true)),
+            b'v' => Ok(Escape::Set(is_vspace, false)),
+            b'V' => Ok(Escape::Set(is_vspace, true)),
+            b'p' | b'P' => {
+                let mut negated = b == b'P';
+                let name = if self.eat(b'{') {
+                    negated ^= self.eat(b'^');
+                    let start = self.pos;
+                    while self.peek().is_some_and(|b| b != b'}') {
+                        self.pos += 1;
+                    }
+                    let name = &self.pat[start..self.pos];
+                    if !self.eat(b'}') {
+                        return error("malformed \\P or \\p sequence", self.pos);
+                    }
+                    name
+                } else {
+                    let start = self.pos;
+                    self.pos += usize::from(self.peek().is_some());
+                    &self.pat[start..self.pos]
+                };
+                match property(name) {
+                    Some(test) => Ok(Escape::Set(test, negated)),
+                    None => error("unknown property name after \\P or \\p", self.pos),
+                }
+            }
+            b'Q' => Ok(Escape::Quote),
+            b'E' => Ok(Escape::Nothing),
+            _ if in_class && b.is_ascii_alphanumeric() => error("escape sequence is invalid in character class", self.pos),
+            b'b' => Ok(Escape::Node(Node::Assert(Assertion::WordBoundary))),
+            b'B' => Ok(Escape::Node(Node::Assert(Assertion::NotWordBoundary))),
+            b'A' => Ok(Escape::Node(Node::Assert(Assertion::StartText))),
+            b'z' => Ok(Escape::Node(Node::Assert(Assertion::EndText))),
+            b'Z' => Ok(Escape::Node(Node::Assert(Assertion::EndTextNewline))),
+            b'G' => Ok(Escape::Node(Node::Assert(Assertion::SearchStart))),
+            b'K' => Ok(Escape::Node(Node::Keep)),
+            b'N' => Ok(Escape::Node(Node::Any(false))),
+            b'R' => {
+                let crlf = Node::Concat(vec![Node::Char(0x0d, false), Node::Char(0x0a, false)]);
+                let single = Node::Class(Rc::new(Class::of(is_vspace, false)));
+                Ok(Escape::Node(Node::Atomic(Box::new(Node::Alt(vec![crlf, single])))))
+            }
+            b'X' => {
+                let base = Node::Any(true);
+                let extend = Class { negated: false, caseless: false, ranges: vec![(0x200c, 0x200d)], sets: vec![(|c, _| is_mark(c), false)] };
+                let marks = Node::Repeat(Box::new(Node::Class(Rc::new(extend))), 0, MANY, Greed::Greedy);
+                Ok(Escape::Node(Node::Atomic(Box::new(Node::Concat(vec![base, marks])))))
+            }
+            b'g' => self.g_escape(at),
+            b'k' => {
+                let end = match self.peek() {
+                    Some(b'<') => b'>',
+                    Some(b'\'') => b'\'',
+                    Some(b'{') => b'}',
+                    _ => return error("\\k is not followed by a braced, angle-bracketed, or quoted name", self.pos),
+                };
+                self.pos += 1;
+                Ok(Escape::Node(Node::NamedBackref(self.name(end)?, caseless, at)))
+            }
+            b'u' | b'U' | b'l' | b'L' => error("PCRE2 does not support \\F, \\L, \\l, \\N{name}, \\U, or \\u", self.pos),
+            b if b.is_ascii_alphanumeric() => error("unrecognized character follows \\", self.pos),
+            _ => Ok(Escape::Char(c)),
+        }
+    }
+
+    /// `\g{n}`, `\g{-n}`, `\gn`, `\g{name}`, and subroutine calls `\g<n>`
+    /// and `\g<name>`.
+    fn g_escape(&mut self, at: usize) -> Result<Escape, CompileError> {
+        let caseless = self.flags.caseless;
+        let (end, call) = match self.peek() {
+            Some(b'{') => (Some(b'}'), false),
+            Some(b'<') => (Some(b'>'), true),
+            Some(b'\'') => (Some(b'\''), true),
+            _ => (None, false),
+        };
+        if end.is_some() {
+            self.pos += 1;
+        }
+        let relative = if self.eat(b'-') {
+            Some(-1)
+        } else if self.eat(b'+') {
+            Some(1)
+        } else {
+            None
+        };
+        let node = match self.number() {
+            Some(n) => {
+                if end.is_some_and(|end| !self.eat(end)) {
+                    return error("\\g is not followed by a braced, angle-bracketed, or quoted name/number or by a plain number", self.pos);
+                }
+                let n = match relative {
+                    Some(-1) if n as usize <= self.groups && n > 0 => self.groups + 1 - n as usize,
+                    Some(1) if n > 0 => self.groups + n as usize,
+                    Some(_) => return error("reference to non-existent subpattern", self.pos),
+                    None if n == 0 && !call => return error("a numbered reference must not be zero", self.pos),
+                    None => n as usize,
+                };
+                if call { Node::Call(n, at) } else { Node::Backref(n, caseless, at) }
+            }
+            None => match end {
+                Some(end) if relative.is_none() => {
+                    let name = self.name(end)?;
+                    if call { Node::NamedCall(name, at) } else { Node::NamedBackref(name, caseless, at) }
+                }
+                _ => return error("\\g is not followed by a braced, angle-bracketed, or quoted name/number or by a plain number", self.pos),
+            },
+        };
+        Ok(Escape::Node(node))
+    }
+
+    fn class(&mut self) -> Result<Class, CompileError> {
+        let mut class = Class { negated: self.eat(b'^'), caseless: self.flags.caseless, ranges: Vec::new(), sets: Vec::new() };
+        let mut first = true;
+        loop {
+            let Some(c) = self.next_char() else { return error("missing terminating ] for character class", self.pat.len()) };
+            if c == 0x5d && !first {
+                return Ok(class);
+            }
+            first = false;
+            if c == 0x5b && matches!(self.peek(), Some(b':' | b'.' | b'=')) && let Some(set) = self.posix_class()? {
+                class.sets.push(set);
+                continue;
+            }
+            let lo = match self.class_char(c)? {
+                Some(Escape::Char(c)) => c,
+                Some(Escape::Set(test, negated)) => {
+                    class.sets.push((test, negated));
+                    continue;
+                }
+                Some(Escape::Quote) => {
+                    while self.peek().is_some() && !self.pat[self.pos..].starts_with(b"\\E") {
+                        let c = self.next_char().expect("not at the end");
+                        class.ranges.push((c, c));
+                    }
+                    self.pos = (self.pos + 2).min(self.pat.len());
+                    continue;
+                }
+                _ => continue,
+            };
+            if self.peek() == Some(b'-') && !matches!(self.peek_at(1), None | Some(b']')) {
+                let dash = self.pos;
+                self.pos += 1;
+                let c = self.next_char().expect("checked");
+                let posix = c == 0x5b && self.peek() == Some(b':');
+                match self.class_char(c)? {
+                    Some(Escape::Char(hi)) if !posix => {
+                        if hi < lo {
+                            return error("range out of order in character class", self.pos - 1);
+                        }
+                        class.ranges.push((lo, hi));
+                        continue;
+                    }
+                    _ => return error("invalid range in character class", dash + 1),
+                }
+            }
+            class.ranges.push((lo, lo));
+        }
+    }
+
+    /// A character of a class, or what its escape stands for.
+    fn class_char(&mut self, c: u32) -> Result<Option<Escape>, CompileError> {
+        if c != 0x5c {
+            return Ok(Some(Escape::Char(c)));
+        }
+        match self.escape(true)? {
+            Escape::Nothing => Ok(None),
+            e => Ok(Some(e)),
+        }
+    }
+
+    /// `[:name:]` after the `[`, or `None` if it is not one.
+    fn posix_class(&mut self) -> Result<Option<(Test, bool)>, CompileError> {
+        let kind = self.pat[self.pos];
+        let start = self.pos + 1;
+        let Some(len) = self.pat[start..].windows(2).position(|w| w[0] == kind && w[1] == b']') else { return Ok(None) };
+        if kind != b':' {
+            return error("POSIX collating elements are not supported", self.pos);
+        }
+        let name = &self.pat[start..start + len];
+        let (negated, name) = match name.strip_prefix(b"^") {
+            Some(name) => (true, name),
+            None => (false, name),
+        };
+        if name.iter().any(|b| !b.is_ascii_alphabetic()) {
+            return Ok(None);
+        }
+        let Some((_, test)) = POSIX_CLASSES.iter().find(|(n, _)| n.as_bytes() == name) else {
+            return error("unknown POSIX class name", start);
+        };
+        self.pos = start + len + 2;
+        Ok(Some((*test, negated)))
+    }
+
+    fn add_name(&mut self, name: Rc<[u8]>, group: usize, at: usize) -> Result<(), CompileError> {
+        if let Some((_, other)) = self.names.iter().find(|(n, _)| *n == name)
+            && *other != group
+            && !self.flags.dupnames
+        {
+            return error("two named subpatterns have the same name (PCRE2_DUPNAMES not set)", at);
+        }
+        self.names.push((name, group));
+        Ok(())
+    }
+
+    /// The body of a group and its `)`, in a scope of its own for option
+    /// settings.
+    fn group_body(&mut self, branch_reset: bool) -> Result<Node, CompileError> {
+        let saved = self.flags;
+        let node = self.alternation(branch_reset)?;
+        self.flags = saved;
+        if !self.eat(b')') {
+            return error("missing closing parenthesis", self.pat.len());
+        }
+        Ok(node)
+    }
+
+    fn capture(&mut self, name: Option<Rc<[u8]>>, at: usize) -> Result<Option<(Node, bool)>, CompileError> {
+        self.groups += 1;
+        let group = self.groups;
+        if let Some(name) = name {
+            self.add_name(name, group, at)?;
+        }
+        let body = self.group_body(false)?;
+        Ok(Some((Node::Group(Box::new(body), Some(group)), true)))
+    }
+
+    /// After `(`.
+    fn group(&mut self, at: usize) -> Result<Option<(Node, bool)>, CompileError> {
+        if self.eat(b'*') {
+            return self.verb(at);
+        }
+        if !self.eat(b'?') {
+            if self.flags.no_auto_capture {
+                return Ok(Some((Node::Group(Box::new(self.group_body(false)?), None), true)));
+            }
+            return self.capture(None, at);
+        }
+        let node = match self.peek() {
+            Some(b'#') => {
+                while !matches!(self.peek(), None | Some(b')')) {
+                    self.pos += 1;
+                }
+                if !self.eat(b')') {
+                    return error("missing ) at end of (?# comment", self.pat.len());
+                }
+                return Ok(None);
+            }
+            Some(b':') => {
+                self.pos += 1;
+                Node::Group(Box::new(self.group_body(false)?), None)
+            }
+            Some(b'|') => {
+                self.pos += 1;
+                Node::Group(Box::new(self.group_body(true)?), None)
+            }
+            Some(b'>') => {
+                self.pos += 1;
+                Node::Atomic(Box::new(self.group_body(false)?))
+            }
+            Some(b'=') | Some(b'!') => {
+                let negate = self.pat[self.pos] == b'!';
+                self.pos += 1;
+                Node::Look(Box::new(self.group_body(false)?), false, negate, at)
+            }
+            Some(b'<') if matches!(self.peek_at(1), Some(b'=' | b'!')) => {
+                let negate = self.pat[self.pos + 1] == b'!';
+                self.pos += 2;
+                Node::Look(Box::new(self.group_body(false)?), true, negate, at)
+            }
+            Some(b'<') => {
+                self.pos += 1;
+                let name = self.name(b'>')?;
+                return self.capture(Some(name), at);
+            }
+            Some(b'\'') => {
+                self.pos += 1;
+                let name = self.name(b'\'')?;
+                return self.capture(Some(name), at);
+            }
+            Some(b'P') => {
+                self.pos += 1;
+                match self.peek() {
+                    Some(b'<') => {
+                        self.pos += 1;
+                        let name = self.name(b'>')?;
+                        return self.capture(Some(name), at);
+                    }
+                    Some(b'=') => {
+                        self.pos += 1;
+                        Node::NamedBackref(self.name(b')')?, self.flags.caseless, at)
+                    }
+                    Some(b'>') => {
+                        self.pos += 1;
+                        Node::NamedCall(self.name(b')')?, at)
+                    }
+                    _ => return error("unrecognized character after (?P", self.pos),
+                }
+            }
+            Some(b'&') => {
+                self.pos += 1;
+                Node::NamedCall(self.name(b')')?, at)
+            }
+            Some(b'R') if self.peek_at(1) == Some(b')') => {
+                self.pos += 2;
+                Node::Call(0, at)
+            }
+            Some(b'0'..=b'9' | b'+' | b'-') if self.peek_at(usize::from(!self.peek().expect("checked").is_ascii_digit())).is_some_and(|b| b.is_ascii_digit()) => {
+                let sign = match self.peek() {
+                    Some(b'+') => 1,
+                    Some(b'-') => -1,
+                    _ => 0,
+                };
+                if sign != 0 {
+                    self.pos += 1;
+                }
+                let n = self.number().expect("checked") as usize;
+                let group = match sign {
+                    1 => self.groups + n,
+                    -1 if n > 0 && n <= self.groups => self.groups + 1 - n,
+                    -1 => return error("reference to non-existent subpattern", self.pos),
+                    _ => n,
+                };
+                if !self.eat(b')') {
+                    return error("missing closing parenthesis", self.pos);
+                }
+                Node::Call(group, at)
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                return self.conditional();
+            }
+            _ => return self.options(),
+        };
+        Ok(Some((node, true)))
+    }
+
+    /// `(?imsx-imsx)` for the rest of the group, or `(?imsx-imsx:...)`.
+    fn options(&mut self) -> Result<Option<(Node, bool)>, CompileError> {
+        let mut flags = self.flags;
+        let mut on = true;
+        if self.eat(b'^') {
+            flags = Flags { caseless: false, multiline: false, dotall: false, extended: false, ungreedy: false, no_auto_capture: false, ..flags };
+        }
+        loop {
+            let Some(b) = self.peek() else { return error("missing closing parenthesis", self.pat.len()) };
+            self.pos += 1;
+            match b {
+                b'i' => flags.caseless = on,
+                b'm' => flags.multiline = on,
+                b's' => flags.dotall = on,
+                b'x' => flags.extended = on,
+                b'U' => flags.ungreedy = on,
+                b'n' => flags.no_auto_capture = on,
+                b'J' => flags.dupnames = on,
+                b'-' if on => on = false,
+                b')' => {
+                    self.flags = flags;
+                    return Ok(None);
+                }
+                b':' => {
+                    let saved = self.flags;
+                    self.flags = flags;
+                    let body = self.group_body(false);
+                    self.flags = saved;
+                    return Ok(Some((Node::Group(Box::new(body?), None), true)));
+                }
+                _ => return error("unrecognized character after (? or (?-", self.pos - 1),
+            }
+        }
+    }
+
+    /// After `(?(`.
+    fn conditional(&mut self) -> Result<Option<(Node, bool)>, CompileError> {
+        let at = self.pos;
+        let condition = if matches!(self.peek(), Some(b'?')) && matches!(self.peek_at(1), Some(b'=' | b'!' | b'<')) {
+            self.pos += 1;
+            let (behind, negate) = match self.peek() {
+                Some(b'<') => {
+                    self.pos += 1;
+                    (true, self.eat(b'!') || !self.eat(b'='))
+                }
+                _ => (false, self.pat[self.pos] == b'!'),
+            };
+            if !behind {
+                self.pos += 1;
+            }
+            Condition::Look(Box::new(Node::Look(Box::new(self.group_body(false)?), behind, negate, at)))
+        } else if let Some(n) = self.number() {
+            if !self.eat(b')') {
+                return error("malformed number or name after (?(", self.pos);
+            }
+            Condition::Group(n as usize)
+        } else if self.pat[self.pos..].starts_with(b"DEFINE)") {
+            self.pos += 7;
+            Condition::Define
+        } else {
+            let end = match self.peek() {
+                Some(b'<') => Some(b'>'),
+                Some(b'\'') => Some(b'\''),
+                _ => None,
+            };
+            if end.is_some() {
+                self.pos += 1;
+            }
+            let name = self.name(end.unwrap_or(b')'))?;
+            if end.is_some() && !self.eat(b')') {
+                return error("malformed number or name after (?(", self.pos);
+            }
+            Condition::Named(name, at)
+        };
+        let saved = self.flags;
+        let yes = self.sequence()?;
+        let no = if self.eat(b'|') { self.sequence()? } else { Node::Empty };
+        self.flags = saved;
+        if self.peek() == Some(b'|') {
+            return error("conditional subpattern contains more than two branches", self.pos);
+        }
+        if !self.eat(b')') {
+            return error("missing closing parenthesis", self.pat.len());
+        }
+        Ok(Some((Node::Cond(condition, Box::new(yes), Box::new(no)), true)))
+    }
+
+    /// `(*VERB)`. Only failing, and the start-of-pattern settings, which
+    /// are read before parsing, are supported.
+    fn verb(&mut self, at: usize) -> Result<Option<(Node, bool)>, CompileError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b != b')') {
+            self.pos += 1;
+        }
+        let name = &self.pat[start..self.pos];
+        if !self.eat(b')') {
+            return error("(*VERB) not recognized or malformed", self.pos);
+        }
+        match name {
+            b"FAIL" | b"F" => Ok(Some((Node::Fail, false))),
+            _ => error("(*VERB) not recognized or malformed", at + 2),
+        }
+    }
+}
+
+/// Counts the capture groups of a pattern ahead of parsing it.
+fn count_groups(pat: &[u8], extended: bool) -> usize {
+    let mut count = 0;
+    let mut i = 0;
+    let mut in_class = false;
+    while i < pat.len() {
+        match pat[i] {
+            b'\\' if pat.get(i + 1) == Some(&b'Q') => {
+                i += pat[i..].windows(2).position(|w| w == b"\\E").unwrap_or(pat.len() - i);
+            }
+            b'\\' => i += 1,
+            b'[' if !in_class => {
+                in_class = true;
+                if pat.get(i + 1) == Some(&b'^') {
+                    i += 1;
+                }
+                if pat.get(i + 1) == Some(&b']') {
+                    i += 1;
+                }
+            }
+            b']' if in_class => in_class = false,
+            b'#' if extended && !in_class => {
+                while i < pat.len() && pat[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'(' if !in_class => match pat.get(i + 1) {
+                Some(b'?') => match pat.get(i + 2) {
+                    Some(b'<') if !matches!(pat.get(i + 3), Some(b'=' | b'!')) => count += 1,
+                    Some(b'\'') => count += 1,
+                    Some(b'P') if pat.get(i + 3) == Some(&b'<') => count += 1,
+                    _ => {}
+                },
+                Some(b'*') => {}
+                _ => count += 1,
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    count
+}
+
+// -- Compiler ------------------------------------------------
+
+/// The fixed length in characters of what a node matches, if it has one.
+fn fixed_length(node: &Node) -> Option<usize> {
+    match node {
+        Node::Empty | Node::Assert(_) | Node::Look(..) | Node::Keep | Node::Fail => Some(0),
+        Node::Char(..) | Node::Any(_) | Node::Class(_) => Some(1),
+        Node::Group(inner, _) | Node::Atomic(inner) => fixed_length(inner),
+        Node::Concat(items) => items.iter().map(fixed_length).sum(),
+        Node::Alt(branches) => {
+            let first = fixed_length(&branches[0])?;
+            branches[1..].iter().all(|b| fixed_length(b) == Some(first)).then_some(first)
+        }
+        Node::Repeat(inner, min, max, _) if min == max => Some(fixed_length(inner)? * *min as usize),
+        Node::Cond(_, yes, no) => {
+            let len = fixed_length(yes)?;
+            (fixed_length(no)? == len).then_some(len)
+        }
+        _ => None,
+    }
+}
+
+/// Whether a node can match without consuming anything.
+fn nullable(node: &Node) -> bool {
+    match node {
+        Node::Char(..) | Node::Any(_) | Node::Class(_) => false,
+        Node::Group(inner, _) | Node::Atomic(inner) => nullable(inner),
+        Node::Concat(items) => items.iter().all(nullable),
+        Node::Alt(branches) => branches.iter().any(nullable),
+        Node::Repeat(inner, min, _, _) => *min == 0 || nullable(inner),
+        _ => true,
+    }
+}
+
+struct Compiler<'a> {
+    prog: Vec<Inst>,
+    regs: usize,
+    utf: bool,
+    captures: usize,
+    names: &'a [(Rc<[u8]>, usize)],
+}
+
+impl Compiler<'_> {
+    fn emit(&mut self, inst: Inst) -> Result<usize, CompileError> {
+        if self.prog.len() >= MAX_PROGRAM {
+            return error("regular expression is too large", 0);
+        }
+        self.prog.push(inst);
+        Ok(self.prog.len() - 1)
+    }
+
+    fn named_groups(&self, name: &[u8], at: usize) -> Result<Box<[usize]>, CompileError> {
+        let groups: Box<[usize]> = self.names.iter().filter(|(n, _)| &**n == name).map(|(_, g)| *g).collect();
+        if groups.is_empty() {
+            return error("reference to non-existent subpattern", at);
+        }
+        Ok(groups)
+    }
+
+    fn group(&self, group: usize, at: usize) -> Result<usize, CompileError> {
+        if group > self.captures {
+            return error("reference to non-existent subpattern", at);
+        }
+        Ok(group)
+    }
+
+    fn single(&self, node: &Node) -> Option<Single> {
+        Some(match node {
+            Node::Char(c, true) if case_variants(*c, self.utf).iter().any(Option::is_some) => Single::Fold(fold(*c, self.utf)),
+            Node::Char(c, _) => Single::Char(*c),
+            Node::Any(false) => Single::Any,
+            Node::Any(true) => Single::AnyNewline,
+            Node::Class(class) => Single::Class(class.clone()),
+            _ => return None,
+        })
+    }
+
+    /// Compiles a body that ends with `Succeed`, for assertions and
+    /// atomic groups, after the instruction `at` that runs it.
+    fn sub_program(&mut self, node: &Node) -> Result<usize, CompileError> {
+        self.node(node)?;
+        self.emit(Inst::Succeed)?;
+        Ok(self.prog.len())
+    }
+
+    fn look(&mut self, node: &Node, behind: bool, negate: bool, at: usize, no: Option<usize>) -> Result<usize, CompileError> {
+        let length = match behind {
+            true => Some(fixed_length(node).ok_or(CompileError { message: "lookbehind assertion is not fixed length", offset: at })?),
+            false => None,
+        };
+        let look = self.emit(Inst::Fail)?;
+        let end = self.sub_program(node)?;
+        self.prog[look] = Inst::Look { behind: length, negate, end, no };
+        Ok(look)
+    }
+
+    /// Lookbehind branches may differ in length, so each is checked on
+    /// its own.
+    fn lookbehind(&mut self, node: &Node, negate: bool, at: usize) -> Result<(), CompileError> {
+        let Node::Alt(branches) = node else {
+            self.look(node, true, negate, at, None)?;
+            return Ok(());
+        };
+        if fixed_length(node).is_some() {
+            self.look(node, true, negate, at, None)?;
+            return Ok(());
+        }
+        if negate {
+            for branch in branches {
+                self.look(branch, true, true, at, None)?;
+            }
+            return Ok(());
+        }
+        let mut jumps = Vec::new();
+        for (i, branch) in branches.iter().enumerate() {
+            let split = (i + 1 < branches.len()).then(|| self.emit(Inst::Fail)).transpose()?;
+            self.look(branch, true, false, at, None)?;
+            if let Some(split) = split {
+                jumps.push(self.emit(Inst::Fail)?);
+                self.prog[split] = Inst::Split(split + 1, self.prog.len());
+            }
+        }
+        let end = self.prog.len();
+        for jump in jumps {
+            self.prog[jump] = Inst::Jmp(end);
+        }
+        Ok(())
+    }
+
+    fn repeat(&mut self, node: &Node, min: u32, max: u32, greed: Greed) -> Result<(), CompileError> {
+        if greed == Greed::Possessive {
+            let atomic = self.emit(Inst::Fail)?;
+            self.repeat(node, min, max, Greed::Greedy)?;
+            self.emit(Inst::Succeed)?;
+            self.prog[atomic] = Inst::Atomic { end: self.prog.len() };
+            return Ok(());
+        }
+        if let Some(item) = self.single(node) {
+            self.emit(Inst::Repeat { item, min, max, greed })?;
+            return Ok(());
+        }
+        for _ in 0..min {
+            self.node(node)?;
+        }
+        let split = |take: usize, skip: usize| match greed {
+            Greed::Lazy => Inst::Split(skip, take),
+            _ => Inst::Split(take, skip),
+        };
+        if max == MANY {
+            let empty = nullable(node);
+            let reg = self.regs;
+            if empty {
+                self.regs += 1;
+            }
+            let top = self.emit(Inst::Fail)?;
+            if empty {
+                self.emit(Inst::SetReg(reg))?;
+            }
+            self.node(node)?;
+            let progress = if empty { Some(self.emit(Inst::Fail)?) } else { None };
+            self.emit(Inst::Jmp(top))?;
+            let end = self.prog.len();
+            self.prog[top] = split(top + 1, end);
+            if let Some(progress) = progress {
+                self.prog[progress] = Inst::Progress(reg, end);
+            }
+            return Ok(());
+        }
+        let mut splits = Vec::new();
+        for _ in min..max {
+            splits.push(self.emit(Inst::Fail)?);
+            self.node(node)?;
+        }
+        let end = self.prog.len();
+        for s in splits {
+            self.prog[s] = split(s + 1, end);
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), CompileError> {
+        match node {
+            Node::Empty => {}
+            Node::Char(..) | Node::Any(_) | Node::Class(_) => {
+                let item = self.single(node).expect("a single character");
+                self.emit(Inst::One(item))?;
+            }
+            Node::Assert(a) => {
+                self.emit(Inst::Assert(*a))?;
+            }
+            Node::Group(inner, None) => self.node(inner)?,
+            Node::Group(inner, Some(n)) => {
+                self.emit(Inst::Save(2 * n))?;
+                self.node(inner)?;
+                self.emit(Inst::Save(2 * n + 1))?;
+            }
+            Node::Concat(items) => {
+                for item in items {
+                    self.node(item)?;
+                }
+            }
+            Node::Alt(branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.emit(Inst::Fail)?;
+                        self.node(branch)?;
+                        jumps.push(self.emit(Inst::Fail)?);
+                        self.prog[split] = Inst::Split(split + 1, self.prog.len());
+                    } else {
+                        self.node(branch)?;
+                    }
+                }
+                let end = self.prog.len();
+                for jump in jumps {
+                    self.prog[jump] = Inst::Jmp(end);
+                }
+            }
+            Node::Repeat(inner, min, max, greed) => self.repeat(inner, *min, *max, *greed)?,
+            Node::Backref(n, caseless, at) => {
+                let group = self.group(*n, *at)?;
+                self.emit(Inst::Backref { groups: Box::new([group]), caseless: *caseless })?;
+            }
+            Node::NamedBackref(name, caseless, at) => {
+                let groups = self.named_groups(name, *at)?;
+                self.emit(Inst::Backref { groups, caseless: *caseless })?;
+            }
+            Node::Look(inner, false, negate, at) => {
+                self.look(inner, false, *negate, *at, None)?;
+            }
+            Node::Look(inner, true, negate, at) => self.lookbehind(inner, *negate, *at)?,
+            Node::Atomic(inner) => {
+                let atomic = self.emit(Inst::Fail)?;
+                let end = self.sub_program(inner)?;
+                self.prog[atomic] = Inst::Atomic { end };
+            }
+            Node::Cond(condition, yes, no) => {
+                let test = match condition {
+                    Condition::Group(n) => Some(self.emit(Inst::IfGroup { groups: Box::new([self.group(*n, 0)?]), no: 0 })?),
+                    Condition::Named(name, at) => {
+                        let groups = self.named_groups(name, *at)?;
+                        Some(self.emit(Inst::IfGroup { groups, no: 0 })?)
+                    }
+                    Condition::Define => None,
+                    Condition::Look(look) => {
+                        let Node::Look(inner, behind, negate, at) = &**look else { unreachable!() };
+                        Some(self.look(inner, *behind, *negate, *at, Some(0))?)
+                    }
+                };
+                let Some(test) = test else {
+                    let skip = self.emit(Inst::Fail)?;
+                    self.node(yes)?;
+                    self.prog[skip] = Inst::Jmp(self.prog.len());
+                    return Ok(());
+                };
+                self.node(yes)?;
+                let jump = self.emit(Inst::Fail)?;
+                let no_start = self.prog.len();
+                self.node(no)?;
+                self.prog[jump] = Inst::Jmp(self.prog.len());
+                match &mut self.prog[test] {
+                    Inst::IfGroup { no, .. } => *no = no_start,
+                    Inst::Look { no, .. } => *no = Some(no_start),
+                    _ => unreachable!(),
+                }
+            }
+            Node::Call(n, at) => {
+                let group = self.group(*n, *at)?;
+                self.emit(Inst::Call(group))?;
+            }
+            Node::NamedCall(name, at) => {
+                let group = self.named_groups(name, *at)?[0];
+                self.emit(Inst::Call(group))?;
+            }
+            Node::Keep => {
+                self.emit(Inst::Keep)?;
+            }
+            Node::Fail => {
+                self.emit(Inst::Fail)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The groups a pattern calls as subroutines, and the group nodes by
+/// number.
+fn collect_groups<'n>(node: &'n Node, names: &[(Rc<[u8]>, usize)], groups: &mut Vec<Option<&'n Node>>, called: &mut Vec<usize>) {
+    if let Node::Group(inner, Some(i)) = node
+        && let Some(slot) = groups.get_mut(*i)
+    {
+        *slot = Some(inner);
+    }
+    let mut visit = |n: &'n Node| collect_groups(n, names, groups, called);
+    match node {
+        Node::Group(inner, _) => visit(inner),
+        Node::Concat(items) | Node::Alt(items) => items.iter().for_each(visit),
+        Node::Repeat(inner, ..) | Node::Look(inner, ..) | Node::Atomic(inner) => visit(inner),
+        Node::Cond(condition, yes, no) => {
+            if let Condition::Look(look) = condition {
+                visit(look);
+            }
+            visit(yes);
+            visit(no);
+        }
+        Node::Call(n, _) => called.push(*n),
+        Node::NamedCall(name, _) => called.extend(names.iter().find(|(n, _)| n == name).map(|(_, g)| *g)),
+        _ => {}
+    }
+}
+
+/// Reads the `(*UTF)` style settings at the start of a pattern.
+fn leading_settings(pat: &[u8], options: &mut Options) -> usize {
+    let mut pos = 0;
+    loop {
+        let rest = &pat[pos..];
+        let Some(end) = rest.strip_prefix(b"(*").and_then(|r| r.iter().position(|&b| b == b')')) else { return pos };
+        let name = &rest[2..2 + end];
+        match name {
+            b"UTF" | b"UTF8" | b"UCP" => options.utf = true,
+            b"NO_AUTO_POSSESS" | b"NO_START_OPT" | b"NO_DOTSTAR_ANCHOR" | b"NO_JIT" | b"NOTEMPTY" | b"LF" | b"BSR_UNICODE" => {}
+            _ => return pos,
+        }
+        pos += end + 3;
+    }
+}
+
+impl Regex {
+    pub(crate) fn new(pattern: &[u8], mut options: Options) -> Result<Regex, CompileError> {
+        let start = leading_settings(pattern, &mut options);
+        if options.utf
+            && let Err(e) = std::str::from_utf8(pattern)
+        {
+            return error("UTF-8 error: isolated byte with 0x80 bit set", e.valid_up_to());
+        }
+        let mut p = Parser {
+            pat: pattern,
+            pos: start,
+            utf: options.utf,
+            flags: Flags {
+                caseless: options.caseless,
+                multiline: options.multiline,
+                dotall: options.dotall,
+                extended: options.extended,
+                ungreedy: options.ungreedy,
+                no_auto_capture: options.no_auto_capture,
+                dupnames: options.dupnames,
+                dollar_endonly: options.dollar_endonly,
+            },
+            groups: 0,
+            total_groups: count_groups(pattern, options.extended),
+            names: Vec::new(),
+        };
+        let node = p.alternation(false)?;
+        if p.pos < pattern.len() {
+            return error("unmatched closing parenthesis", p.pos);
+        }
+        let mut c = Compiler { prog: Vec::new(), regs: 0, utf: options.utf, captures: p.groups, names: &p.names };
+        c.emit(Inst::Save(0))?;
+        c.node(&node)?;
+        c.emit(Inst::Save(1))?;
+        c.emit(Inst::Match)?;
+        let mut groups = vec![None; p.groups + 1];
+        let mut called = Vec::new();
+        collect_groups(&node, &p.names, &mut groups, &mut called);
+        groups[0] = Some(&node);
+        let mut subs = vec![None; p.groups + 1];
+        called.sort_unstable();
+        called.dedup();
+        for group in called {
+            if let Some(Some(body)) = groups.get(group) {
+                subs[group] = Some(c.prog.len());
+                c.sub_program(body)?;
+            }
+        }
+        let (prog, regs) = (c.prog, c.regs);
+        let mut names = vec![None; p.groups + 1];
+        for (name, group) in p.names {
+            names[group].get_or_insert(name);
+        }
+        let mut first = &node;
+        loop {
+            match first {
+                Node::Concat(items) if !items.is_empty() => first = &items[0],
+                Node::Group(inner, _) => first = inner,
+                _ => break,
+            }
+        }
+        let first_byte = match first {
+            Node::Char(c, false) if *c < 0x80 || !options.utf => u8::try_from(*c).ok(),
+            _ => None,
+        };
+        Ok(Regex {
+            prog,
+            subs,
+            regs,
+            captures: p.groups,
+            names,
+            utf: options.utf,
+            anchored: options.anchored,
+            start_only: matches!(first, Node::Assert(Assertion::StartText)),
+            first_byte,
+        })
+    }
+
+    /// Finds the first match at or after `start`, returning the start and
+    /// end of each group; `limit` caps the backtracking. `anchored` only
+    /// tries `start`, and `not_empty_at_start` rejects an empty match
+    /// there, as PCRE's options of those names do.
+    pub(crate) fn find_at(&self, subject: &[u8], start: usize, anchored: bool, not_empty_at_start: bool, limit: u64) -> Result<Option<Vec<Option<usize>>>, MatchError> {
+        let mut m = Matcher {
+            re: self,
+            subject,
+            search_start: start,
+            match_start: start,
+            not_empty_at_start,
+            slots: vec![None; 2 * (self.captures + 1)],
+            regs: vec![usize::MAX; self.regs],
+            stack: Vec::new(),
+            steps: 0,
+            limit,
+            depth: 0,
+        };
+        let anchored = anchored || self.anchored || self.start_only;
+        let mut at = start;
+        loop {
+            if !anchored && let Some(b) = self.first_byte {
+                match subject[at..].iter().position(|&c| c == b) {
+                    Some(i) => at += i,
+                    None => return Ok(None),
+                }
+            }
+            m.match_start = at;
+            if m.run(0, at)?.is_some() {
+                return Ok(Some(m.slots));
+            }
+            m.stack.clear();
+            m.slots.fill(None);
+            if anchored || at >= subject.len() {
+                return Ok(None);
+            }
+            at += if self.utf { utf8_len(subject[at]) } else { 1 };
+        }
+    }
+}
+
+// -- Matcher -------------------------------------------------
+
+enum Backtrack {
+    Retry { pc: usize, pos: usize },
+    Slot { slot: usize, old: Option<usize> },
+    Reg { reg: usize, old: usize },
+    /// A greedy repeat at `pos` that can give back characters down to
+    /// `min`, going on at `next`.
+    Greedy { next: usize, min: usize, pos: usize },
+    /// A lazy repeat, instruction `at`, that can take another character
+    /// after `count`.
+    Lazy { at: usize, pos: usize, count: u32 },
+}
+
+struct Matcher<'r, 's> {
+    re: &'r Regex,
+    subject: &'s [u8],
+    search_start: usize,
+    match_start: usize,
+    not_empty_at_start: bool,
+    slots: Vec<Option<usize>>,
+    regs: Vec<usize>,
+    stack: Vec<Backtrack>,
+    steps: u64,
+    limit: u64,
+    depth: usize,
+}
+
+impl Matcher<'_, '_> {
+    fn char_at(&self, pos: usize) -> Option<(u32, usize)> {
+        decode(self.subject, pos, self.re.utf)
+    }
+
+    fn char_before(&self, pos: usize) -> Option<u32> {
+        (pos > 0).then(|| self.char_at(self.prev(pos)).expect("a character").0)
+    }
+
+    fn prev(&self, mut pos: usize) -> usize {
+        pos -= 1;
+        if self.re.utf {
+            while pos > 0 && self.subject[pos] & 0xc0 == 0x80 {
+                pos -= 1;
+            }
+        }
+        pos
+    }
+
+    fn single(&self, item: &Single, pos: usize) -> Option<usize> {
+        let (c, len) = self.char_at(pos)?;
+        let hit = match item {
+            Single::Char(x) => c == *x,
+            Single::Fold(x) => fold(c, self.re.utf) == *x,
+            Single::Any => c != 0x0a,
+            Single::AnyNewline => true,
+            Single::Class(class) => class.matches(c, self.re.utf),
+        };
+        hit.then_some(pos + len)
+    }
+
+    fn is_word_at(&self, pos: usize) -> bool {
+        self.char_at(pos).is_some_and(|(c, _)| is_word(c, self.re.utf))
+    }
+
+    fn assert(&self, a: Assertion, pos: usize) -> bool {
+        let len = self.subject.len();
+        match a {
+            Assertion::StartText => pos == 0,
+            Assertion::StartLine => pos == 0 || (self.subject[pos - 1] == b'\n' && pos < len),
+            Assertion::EndText => pos == len,
+            Assertion::EndTextNewline => pos == len || (pos + 1 == len && self.subject[pos] == b'\n'),
+            Assertion::EndLine => pos == len || self.subject[pos] == b'\n',
+            Assertion::WordBoundary | Assertion::NotWordBoundary => {
+                let before = self.char_before(pos).is_some_and(|c| is_word(c, self.re.utf));
+                (before != self.is_word_at(pos)) == matches!(a, Assertion::WordBoundary)
+            }
+            Assertion::SearchStart => pos == self.search_start,
+        }
+    }
+
+    fn set_slot(&mut self, slot: usize, v: Option<usize>) {
+        self.stack.push(Backtrack::Slot { slot, old: self.slots[slot] });
+        self.slots[slot] = v;
+    }
+
+    fn tick(&mut self) -> Result<(), MatchError> {
+        self.steps += 1;
+        if self.steps > self.limit {
+            return Err(MatchError::BacktrackLimit);
+        }
+        Ok(())
+    }
+
+    /// Pops back to the latest choice above `base`, undoing captures on
+    /// the way.
+    fn backtrack(&mut self, base: usize) -> Result<Option<(usize, usize)>, MatchError> {
+        while self.stack.len() > base {
+            match self.stack.pop().expect("checked") {
+                Backtrack::Slot { slot, old } => self.slots[slot] = old,
+                Backtrack::Reg { reg, old } => self.regs[reg] = old,
+                Backtrack::Retry { pc, pos } => {
+                    self.tick()?;
+                    return Ok(Some((pc, pos)));
+                }
+                Backtrack::Greedy { next, min, pos } => {
+                    self.tick()?;
+                    let pos = self.prev(pos);
+                    if pos > min {
+                        self.stack.push(Backtrack::Greedy { next, min, pos });
+                    }
+                    return Ok(Some((next, pos)));
+                }
+                Backtrack::Lazy { at, pos, count } => {
+                    self.tick()?;
+                    let Inst::Repeat { item, max, .. } = &self.re.prog[at] else { unreachable!() };
+                    if count < *max
+                        && let Some(pos) = self.single(item, pos)
+                    {
+                        self.stack.push(Backtrack::Lazy { at, pos, count: count + 1 });
+                        return Ok(Some((at + 1, pos)));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Undoes everything above `base`.
+    fn unwind(&mut self, base: usize) {
+        while self.stack.len() > base {
+            match self.stack.pop().expect("checked") {
+                Backtrack::Slot { slot, old } => self.slots[slot] = old,
+                Backtrack::Reg { reg, old } => self.regs[reg] = old,
+                _ => {}
+            }
+        }
+    }
+
+    /// Runs a body ending in `Succeed` to its first match, which is
+    /// final: its choices are dropped, but not how to undo its captures.
+    fn sub(&mut self, pc: usize, pos: usize) -> Result<Option<usize>, MatchError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(MatchError::RecursionLimit);
+        }
+        let base = self.stack.len();
+        let end = self.run(pc, pos);
+        self.depth -= 1;
+        if let Ok(Some(_)) = end {
+            let undo: Vec<Backtrack> = self.stack.drain(base..).filter(|b| matches!(b, Backtrack::Slot { .. } | Backtrack::Reg { .. })).collect();
+            self.stack.extend(undo);
+        }
+        end
+    }
+
+    fn backref(&self, groups: &[usize], caseless: bool, pos: usize) -> Option<usize> {
+        let group = groups.iter().find(|&&g| self.slots[2 * g].is_some() && self.slots[2 * g + 1].is_some())?;
+        let (start, end) = (self.slots[2 * group]?, self.slots[2 * group + 1]?);
+        let text = &self.subject[start..end.max(start)];
+        if !caseless {
+            return self.subject[pos..].starts_with(text).then_some(pos + text.len());
+        }
+        let (mut i, mut at) = (0, pos);
+        while i < text.len() {
+            let (a, len) = decode(text, i, self.re.utf)?;
+            let (b, blen) = self.char_at(at)?;
+            if fold(a, self.re.utf) != fold(b, self.re.utf) {
+                return None;
+            }
+            i += len;
+            at += blen;
+        }
+        Some(at)
+    }
+
+    fn run(&mut self, mut pc: usize, mut pos: usize) -> Result<Option<usize>, MatchError> {
+        let base = self.stack.len();
+        let re = self.re;
+        loop {
+            let ok = match &re.prog[pc] {
+                Inst::One(item) => match self.single(item, pos) {
+                    Some(next) => {
+                        pos = next;
+                        pc += 1;
+                        true
+                    }
+                    None => false,
+                },
+                Inst::Repeat { item, min, max, greed } => {
+                    let mut count = 0;
+                    let mut ok = true;
+                    while count < *min {
+                        match self.single(item, pos) {
+                            Some(next) => pos = next,
+                            None => {
+                                ok = false;
+                                break;
+                            }
+                        }
+                        count += 1;
+                    }
+                    if ok {
+                        if *greed == Greed::Lazy {
+                            if count < *max {
+                                self.stack.push(Backtrack::Lazy { at: pc, pos, count });
+                            }
+                        } else {
+                            let least = pos;
+                            while count < *max
+                                && let Some(next) = self.single(item, pos)
+                            {
+                                pos = next;
+                                count += 1;
+                            }
+                            if pos > least && *greed == Greed::Greedy {
+                                self.stack.push(Backtrack::Greedy { next: pc + 1, min: least, pos });
+                            }
+                        }
+                        pc += 1;
+                    }
+                    ok
+                }
+                Inst::Assert(a) => {
+                    pc += 1;
+                    self.assert(*a, pos)
+                }
+                Inst::Split(first, second) => {
+                    self.stack.push(Backtrack::Retry { pc: *second, pos });
+                    pc = *first;
+                    true
+                }
+                Inst::Jmp(target) => {
+                    pc = *target;
+                    true
+                }
+                Inst::Save(slot) => {
+                    self.set_slot(*slot, Some(pos));
+                    pc += 1;
+                    true
+                }
+                Inst::SetReg(reg) => {
+                    self.stack.push(Backtrack::Reg { reg: *reg, old: self.regs[*reg] });
+                    self.regs[*reg] = pos;
+                    pc += 1;
+                    true
+                }
+                Inst::Progress(reg, exit) => {
+                    pc = if self.regs[*reg] == pos { *exit } else { pc + 1 };
+                    true
+                }
+                Inst::Backref { groups, caseless } => match self.backref(groups, *caseless, pos) {
+                    Some(next) => {
+                        pos = next;
+                        pc += 1;
+                        true
+                    }
+                    None => false,
+                },
+                Inst::Look { behind, negate, end, no } => {
+                    let base = self.stack.len();
+                    let from = match behind {
+                        None => Some(pos),
+                        Some(n) => {
+                            let mut at = pos;
+                            (0..*n).all(|_| {
+                                let ok = at > 0;
+                                if ok {
+                                    at = self.prev(at);
+                                }
+                                ok
+                            }).then_some(at)
+                        }
+                    };
+                    let found = match from {
+                        Some(from) => self.sub(pc + 1, from)?,
+                        None => None,
+                    };
+                    let matched = found.is_some_and(|e| behind.is_none() || e == pos);
+                    if found.is_some() && (*negate || !matched) {
+                        self.unwind(base);
+                    }
+                    if matched != *negate {
+                        pc = *end;
+                        true
+                    } else if let Some(no) = no {
+                        pc = *no;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                Inst::Atomic { end } => match self.sub(pc + 1, pos)? {
+                    Some(next) => {
+                        pos = next;
+                        pc = *end;
+                        true
+                    }
+                    None => false,
+                },
+                Inst::IfGroup { groups, no } => {
+                    pc = if groups.iter().any(|&g| self.slots[2 * g + 1].is_some()) { pc + 1 } else { *no };
+                    true
+                }
+                Inst::Call(group) => {
+                    let base = self.stack.len();
+                    let start = re.subs[*group].expect("called groups are compiled");
+                    match self.sub(start, pos)? {
+                        Some(next) => {
+                            self.unwind(base);
+                            pos = next;
+                            pc += 1;
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                Inst::Keep => {
+                    self.set_slot(0, Some(pos));
+                    pc += 1;
+                    true
+                }
+                Inst::Fail => false,
+                Inst::Succeed => return Ok(Some(pos)),
+                Inst::Match => {
+                    if !(self.not_empty_at_start && pos == self.match_start && self.match_start == self.search_start) {
+                        return Ok(Some(pos));
+                    }
+                    false
+                }
+            };
+            if !ok {
+                match self.backtrack(base)? {
+                    Some((next_pc, next_pos)) => {
+                        pc = next_pc;
+                        pos = next_pos;
+                    }
+                    None => return Ok(None),
+                }
+            }
+        }
+    }
+}
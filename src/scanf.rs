@@ -0,0 +1,304 @@
+use crate::array::PhpArray;
+use crate::extension::{arg_str, set_ref, NativeFunction};
+use crate::value::Value;
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+pub(crate) const FUNCTIONS: &[NativeFunction] =
+    &[NativeFunction { name: "sscanf", params: &["string string", "string format", "mixed &...vars"], func: sscanf }];
+
+fn is_space(c: u8) -> bool {
+    c == b' ' || (b'\t'..=b'\r').contains(&c)
+}
+
+/// One conversion of a format.
+struct Conversion {
+    /// Where the value goes: an `n$` position or the next one in turn.
+    index: Option<usize>,
+    suppress: bool,
+    width: Option<usize>,
+    kind: u8,
+    /// The bytes a `%[...]` set matches.
+    set: [bool; 256],
+}
+
+/// Parses the conversion after a `%`, which is at `pos`.
+fn conversion(format: &[u8], pos: &mut usize) -> VmResult<Conversion> {
+    let digits = |pos: usize| format[pos..].iter().take_while(|c| c.is_ascii_digit()).count();
+    let mut index = None;
+    let n = digits(*pos);
+    if n > 0 && format.get(*pos + n) == Some(&b'$') {
+        let position: usize = std::str::from_utf8(&format[*pos..*pos + n]).unwrap().parse().unwrap_or(0);
+        if position == 0 {
+            return Err(VmError::value_error("\"%n$\" argument index out of range"));
+        }
+        index = Some(position - 1);
+        *pos += n + 1;
+    }
+    let suppress = format.get(*pos) == Some(&b'*');
+    if suppress {
+        *pos += 1;
+    }
+    let n = digits(*pos);
+    let width = match n {
+        0 => None,
+        n => std::str::from_utf8(&format[*pos..*pos + n]).unwrap().parse().ok().filter(|w| *w > 0),
+    };
+    *pos += n;
+    if matches!(format.get(*pos), Some(b'l' | b'L' | b'h')) {
+        *pos += 1;
+    }
+    let Some(&kind) = format.get(*pos) else {
+        return Err(VmError::value_error("Bad scan conversion character \"\""));
+    };
+    *pos += 1;
+    let mut set = [false; 256];
+    match kind {
+        b'd' | b'i' | b'o' | b'x' | b'X' | b'u' | b'f' | b'e' | b'E' | b'g' | b's' | b'c' | b'n' => {}
+        b'[' => {
+            let negate = format.get(*pos) == Some(&b'^');
+            if negate {
+                *pos += 1;
+            }
+            let start = *pos;
+            loop {
+                let Some(&c) = format.get(*pos) else {
+                    return Err(VmError::value_error("Unmatched [ in format string"));
+                };
+                if c == b']' && *pos > start {
+                    *pos += 1;
+                    break;
+                }
+                if format.get(*pos + 1) == Some(&b'-') && format.get(*pos + 2).is_some_and(|e| *e != b']') {
+                    let (a, b) = (c.min(format[*pos + 2]), c.max(format[*pos + 2]));
+                    for x in a..=b {
+                        set[x as usize] = true;
+                    }
+                    *pos += 3;
+                } else {
+                    set[c as usize] = true;
+                    *pos += 1;
+                }
+            }
+            if negate {
+                for x in set.iter_mut() {
+                    *x = !*x;
+                }
+            }
+        }
+        c => return Err(VmError::value_error(format!("Bad scan conversion character \"{}\"", c as char))),
+    }
+    Ok(Conversion { index, suppress, width, kind, set })
+}
+
+/// The length of the number at the start of `s` in `base`, with an
+/// optional sign; for base 0 the prefix picks the base, as in `strtol()`.
+fn scan_int(s: &[u8], base: u32) -> (usize, u32) {
+    let mut i = usize::from(matches!(s.first(), Some(b'+' | b'-')));
+    let mut base = base;
+    if (base == 0 || base == 16) && s.get(i) == Some(&b'0') && matches!(s.get(i + 1), Some(b'x' | b'X')) {
+        if s.get(i + 2).is_some_and(u8::is_ascii_hexdigit) {
+            i += 2;
+            base = 16;
+        }
+    } else if base == 0 {
+        base = if s.get(i) == Some(&b'0') { 8 } else { 10 };
+    }
+    let digits = s[i..].iter().take_while(|c| (**c as char).is_digit(base)).count();
+    if digits == 0 {
+        return (0, base);
+    }
+    (i + digits, base)
+}
+
+/// The length of the float at the start of `s`.
+fn scan_float(s: &[u8]) -> usize {
+    let mut i = usize::from(matches!(s.first(), Some(b'+' | b'-')));
+    let int_digits = s[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+    i += int_digits;
+    let mut frac_digits = 0;
+    if s.get(i) == Some(&b'.') {
+        frac_digits = s[i + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        i += 1 + frac_digits;
+    }
+    if int_digits + frac_digits == 0 {
+        return 0;
+    }
+    if matches!(s.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(s.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        let exp_digits = s[j..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if exp_digits > 0 {
+            i = j + exp_digits;
+        }
+    }
+    i
+}
+
+/// Parses an integer the way `%d` and friends store it: as an int, or
+/// as a string if it does not fit.
+fn int_value(text: &[u8], base: u32, unsigned: bool) -> Value {
+    let (negative, digits) = match text.first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let digits = match base {
+        16 => digits.strip_prefix(b"0x").or_else(|| digits.strip_prefix(b"0X")).unwrap_or(digits),
+        _ => digits,
+    };
+    let magnitude = std::str::from_utf8(digits).ok().and_then(|d| u64::from_str_radix(d, base).ok());
+    let n = match (magnitude, negative) {
+        (Some(m), false) if m <= i64::MAX as u64 => m as i64,
+        (Some(m), true) if m <= i64::MAX as u64 + 1 => (m as i64).wrapping_neg(),
+        _ => return Value::from(text.to_vec()),
+    };
+    match unsigned && n < 0 {
+        true => Value::from((n as u64).to_string()),
+        false => Value::Int(n),
+    }
+}
+
+/// `sscanf(string $string, string $format, mixed &...$vars): array|int|null`
+///
+/// Without variables, returns the values as an array, with nulls for
+/// conversions that were not reached; with them, assigns them and
+/// returns how many were. Input that ends before the first conversion
+/// gives null, or -1 with variables.
+fn sscanf(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (input, format) = (arg_str(&args, 0), arg_str(&args, 1));
+    let vars = &args[2.min(args.len())..];
+    let mut conversions = Vec::new();
+    let mut pos = 0;
+    while pos < format.len() {
+        if format[pos] == b'%' && format.get(pos + 1) != Some(&b'%') {
+            pos += 1;
+            conversions.push(conversion(format, &mut pos)?);
+        } else {
+            pos += if format[pos] == b'%' { 2 } else { 1 };
+        }
+    }
+    let explicit = conversions.iter().filter(|c| !c.suppress && c.index.is_some()).count();
+    let stored: Vec<&Conversion> = conversions.iter().filter(|c| !c.suppress).collect();
+    if explicit > 0 && explicit < stored.len() {
+        return Err(VmError::value_error("cannot mix \"%\" and \"%n$\" conversion specifiers"));
+    }
+    let total = stored.iter().enumerate().map(|(i, c)| c.index.unwrap_or(i) + 1).max().unwrap_or(0);
+    if explicit > 0 && stored.iter().any(|c| c.index.is_some_and(|i| !vars.is_empty() && i >= vars.len())) {
+        return Err(VmError::value_error("\"%n$\" argument index out of range"));
+    }
+    if !vars.is_empty() && vars.len() != total {
+        return Err(VmError::value_error("Different numbers of variable names and field specifiers"));
+    }
+    let mut values = vec![Value::Null; total];
+    let (mut s, mut f) = (0, 0);
+    let mut next = 0;
+    let mut converted = 0;
+    let mut underflow = false;
+    'scan: while f < format.len() {
+        let c = format[f];
+        if is_space(c) {
+            while s < input.len() && is_space(input[s]) {
+                s += 1;
+            }
+            f += 1;
+            continue;
+        }
+        if c != b'%' || format.get(f + 1) == Some(&b'%') {
+            f += if c == b'%' { 2 } else { 1 };
+            match input.get(s) {
+                None => {
+                    underflow = true;
+                    break;
+                }
+                Some(&d) if d != c => break,
+                Some(_) => s += 1,
+            }
+            continue;
+        }
+        f += 1;
+        let conv = conversion(format, &mut f)?;
+        let slot = match conv.suppress {
+            true => None,
+            false => Some(conv.index.unwrap_or_else(|| {
+                next += 1;
+                next - 1
+            })),
+        };
+        if conv.kind == b'n' {
+            if let Some(i) = slot {
+                values[i] = Value::Int(s as i64);
+            }
+            continue;
+        }
+        if conv.kind != b'c' && conv.kind != b'[' {
+            while s < input.len() && is_space(input[s]) {
+                s += 1;
+            }
+        }
+        if s >= input.len() {
+            underflow = true;
+            break;
+        }
+        let rest = &input[s..];
+        let limit = conv.width.unwrap_or(usize::MAX).min(rest.len());
+        let window = &rest[..limit];
+        let (len, value) = match conv.kind {
+            b'c' => (1, Value::from(vec![rest[0]])),
+            b's' => {
+                let len = window.iter().take_while(|c| !is_space(**c)).count();
+                (len, Value::from(window[..len].to_vec()))
+            }
+            b'[' => {
+                let len = window.iter().take_while(|c| conv.set[**c as usize]).count();
+                if len == 0 {
+                    break 'scan;
+                }
+                (len, Value::from(window[..len].to_vec()))
+            }
+            b'f' | b'e' | b'E' | b'g' => {
+                let len = scan_float(window);
+                if len == 0 {
+                    break 'scan;
+                }
+                let text = std::str::from_utf8(&window[..len]).unwrap();
+                (len, Value::Float(text.parse().unwrap_or(0.0)))
+            }
+            kind => {
+                let base = match kind {
+                    b'i' => 0,
+                    b'o' => 8,
+                    b'x' | b'X' => 16,
+                    _ => 10,
+                };
+                let (len, base) = scan_int(window, base);
+                if len == 0 {
+                    break 'scan;
+                }
+                (len, int_value(&window[..len], base, kind == b'u'))
+            }
+        };
+        s += len;
+        if let Some(i) = slot {
+            values[i] = value;
+            converted += 1;
+        }
+    }
+    if underflow && converted == 0 {
+        return Ok(if vars.is_empty() { Value::Null } else { Value::Int(-1) });
+    }
+    if vars.is_empty() {
+        let mut arr = PhpArray::new();
+        for v in values {
+            arr.push(v).map_err(VmError::error)?;
+        }
+        return Ok(Value::from(arr));
+    }
+    for (i, v) in values.into_iter().enumerate() {
+        if !matches!(v, Value::Null) {
+            set_ref(vars, i, v);
+        }
+    }
+    Ok(Value::Int(converted))
+}
@@ -0,0 +1,362 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::ClassKind;
+use crate::class::{Internal, Object};
+use crate::compiler::ClassOperand;
+use crate::limits::Growth;
+use crate::value::{format_float, Value};
+use crate::vm::{expect_args, Callee, NativeFn, Vm, VmError, VmResult};
+
+pub const FUNCTIONS: &[(&str, &[&str], NativeFn)] = &[
+    ("serialize", &["value"], serialize),
+    ("unserialize", &["data", "options"], unserialize),
+];
+
+/// Writes PHP's serialization format. Values are numbered as they are
+/// written so an object met again is written as a back-reference.
+struct Serializer {
+    out: Vec<u8>,
+    count: usize,
+    objects: Vec<(*const RefCell<Object>, usize)>,
+    growth: Growth,
+}
+
+impl Serializer {
+    fn string(&mut self, s: &[u8]) {
+        self.out.extend_from_slice(format!("s:{}:\"", s.len()).as_bytes());
+        self.out.extend_from_slice(s);
+        self.out.extend_from_slice(b"\";");
+    }
+
+    fn key(&mut self, k: &ArrayKey) {
+        match k {
+            ArrayKey::Int(n) => self.out.extend_from_slice(format!("i:{};", n).as_bytes()),
+            ArrayKey::Str(s) => self.string(s),
+        }
+    }
+
+    fn entries(&mut self, vm: &mut Vm, arr: &PhpArray) -> VmResult<()> {
+        self.out.extend_from_slice(format!("{}:{{", arr.len()).as_bytes());
+        for (k, v) in arr.iter() {
+            self.key(k);
+            self.value(vm, v)?;
+        }
+        self.out.push(b'}');
+        Ok(())
+    }
+
+    fn value(&mut self, vm: &mut Vm, v: &Value) -> VmResult<()> {
+        self.growth.grow_to(vm, self.out.len())?;
+        vm.check_stack()?;
+        let obj = match v.deref() {
+            Value::Object(o) => o,
+            v => {
+                self.count += 1;
+                match v {
+                    Value::Null => self.out.extend_from_slice(b"N;"),
+                    Value::Bool(b) => self.out.extend_from_slice(if b { b"b:1;" } else { b"b:0;" }),
+                    Value::Int(n) => self.out.extend_from_slice(format!("i:{};", n).as_bytes()),
+                    Value::Float(f) => self.out.extend_from_slice(format!("d:{};", format_float(f, -1)).as_bytes()),
+                    Value::Str(s) => self.string(&s),
+                    Value::Resource(_) => self.out.extend_from_slice(b"i:0;"),
+                    Value::Array(arr) => {
+                        self.out.extend_from_slice(b"a:");
+                        self.entries(vm, &arr)?;
+                    }
+                    Value::Object(_) | Value::Ref(_) => unreachable!(),
+                }
+                return Ok(());
+            }
+        };
+        if let Some((_, n)) = self.objects.iter().find(|(p, _)| *p == Rc::as_ptr(&obj)) {
+            self.out.extend_from_slice(format!("r:{};", n).as_bytes());
+            return Ok(());
+        }
+        self.count += 1;
+        self.objects.push((Rc::as_ptr(&obj), self.count));
+        let class = obj.borrow().class.clone();
+        if matches!(obj.borrow().internal, Internal::Closure(_) | Internal::Generator(_) | Internal::Fiber(_)) {
+            return Err(VmError::Error {
+                class: "Exception",
+                message: format!("Serialization of '{}' is not allowed", class.name),
+            });
+        }
+        if class.kind == ClassKind::Enum {
+            let case = obj.borrow().props.get(&ArrayKey::from("name")).cloned().unwrap_or(Value::Null);
+            let name = format!("{}:{}", class.name, String::from_utf8_lossy(&case.scalar_to_bytes().unwrap_or_default()));
+            self.out.extend_from_slice(format!("E:{}:\"{}\";", name.len(), name).as_bytes());
+            return Ok(());
+        }
+        let props = match vm.magic_serialize(&obj)? {
+            Some(arr) => arr,
+            None => Rc::new(obj.borrow().to_array()),
+        };
+        self.out.extend_from_slice(format!("O:{}:\"{}\":", class.name.len(), class.name).as_bytes());
+        self.entries(vm, &props)
+    }
+}
+
+/// `serialize(mixed $value): string`
+fn serialize(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("serialize", &args, 1, 1)?;
+    let mut s = Serializer { out: Vec::new(), count: 0, objects: Vec::new(), growth: Growth::new() };
+    s.value(vm, &args[0])?;
+    Ok(Value::from(s.out))
+}
+
+/// Reads the serialization format. Errors are the offset where the input
+/// stopped making sense.
+struct Unserializer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Every value read so far, for `r:` back-references.
+    values: Vec<Value>,
+    /// `None` if all classes may be instantiated.
+    allowed_classes: Option<Vec<String>>,
+}
+
+type Parse<T> = Result<T, usize>;
+
+impl Unserializer<'_> {
+    fn expect(&mut self, b: u8) -> Parse<()> {
+        if self.data.get(self.pos) == Some(&b) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.pos)
+        }
+    }
+
+    /// Reads up to the terminator, which is consumed.
+    fn until(&mut self, end: u8) -> Parse<&[u8]> {
+        let start = self.pos;
+        let len = self.data[start..].iter().position(|&b| b == end).ok_or(start)?;
+        self.pos = start + len + 1;
+        Ok(&self.data[start..start + len])
+    }
+
+    fn int(&mut self, end: u8) -> Parse<i64> {
+        let start = self.pos;
+        let text = self.until(end)?;
+        std::str::from_utf8(text).ok().and_then(|t| t.strip_prefix('+').unwrap_or(t).parse().ok()).ok_or(start)
+    }
+
+    fn len(&mut self, end: u8) -> Parse<usize> {
+        let start = self.pos;
+        usize::try_from(self.int(end)?).map_err(|_| start)
+    }
+
+    /// `<len>:"<bytes>"`
+    fn quoted(&mut self) -> Parse<Vec<u8>> {
+        let len = self.len(b':')?;
+        self.expect(b'"')?;
+        let start = self.pos;
+        let bytes = self.data.get(start..start + len).ok_or(start)?.to_vec();
+        self.pos += len;
+        self.expect(b'"')?;
+        Ok(bytes)
+    }
+
+    fn key(&mut self) -> Parse<ArrayKey> {
+        let start = self.pos;
+        let tag = *self.data.get(self.pos).ok_or(start)?;
+        self.pos += 1;
+        self.expect(b':')?;
+        match tag {
+            b'i' => Ok(ArrayKey::Int(self.int(b';')?)),
+            b's' => {
+                let s = self.quoted()?;
+                self.expect(b';')?;
+                Ok(ArrayKey::from_bytes(&s))
+            }
+            _ => Err(start),
+        }
+    }
+
+    /// `<count>:{<key><value>...}`
+    fn entries(&mut self, vm: &mut Vm) -> VmResult<Parse<PhpArray>> {
+        let count = match self.len(b':').and_then(|n| self.expect(b'{').map(|_| n)) {
+            Ok(n) => n,
+            Err(at) => return Ok(Err(at)),
+        };
+        let mut arr = PhpArray::new();
+        for _ in 0..count {
+            let k = match self.key() {
+                Ok(k) => k,
+                Err(at) => return Ok(Err(at)),
+            };
+            match self.value(vm)? {
+                Ok(v) => arr.insert(k, v),
+                Err(at) => return Ok(Err(at)),
+            }
+        }
+        Ok(self.expect(b'}').map(|_| arr))
+    }
+
+    fn value(&mut self, vm: &mut Vm) -> VmResult<Parse<Value>> {
+        vm.check_stack()?;
+        let start = self.pos;
+        let Some(&tag) = self.data.get(self.pos) else { return Ok(Err(start)) };
+        self.pos += 1;
+        if tag == b'N' {
+            self.values.push(Value::Null);
+            return Ok(self.expect(b';').map(|_| Value::Null));
+        }
+        if let Err(at) = self.expect(b':') {
+            return Ok(Err(at));
+        }
+        let scalar = match tag {
+            b'b' => match self.until(b';') {
+                Ok(b"0") => Ok(Value::Bool(false)),
+                Ok(b"1") => Ok(Value::Bool(true)),
+                _ => Err(start),
+            },
+            b'i' => self.int(b';').map(Value::Int),
+            b'd' => self.until(b';').and_then(|t| parse_float(t).map(Value::Float).ok_or(start)),
+            b's' => self.quoted().and_then(|s| self.expect(b';').map(|_| Value::from(s))),
+            b'r' | b'R' => self
+                .len(b';')
+                .and_then(|n| n.checked_sub(1).and_then(|i| self.values.get(i)).cloned().ok_or(start)),
+            b'a' | b'O' | b'E' => return self.compound(vm, tag, start),
+            _ => Err(start),
+        };
+        if let Ok(v) = &scalar
+            && !matches!(tag, b'r' | b'R')
+        {
+            self.values.push(v.clone());
+        }
+        Ok(scalar)
+    }
+
+    fn compound(&mut self, vm: &mut Vm, tag: u8, start: usize) -> VmResult<Parse<Value>> {
+        match tag {
+            b'a' => {
+                let slot = self.values.len();
+                self.values.push(Value::Null);
+                let arr = match self.entries(vm)? {
+                    Ok(arr) => Value::from(arr),
+                    Err(at) => return Ok(Err(at)),
+                };
+                self.values[slot] = arr.clone();
+                Ok(Ok(arr))
+            }
+            b'E' => {
+                let name = match self.quoted().and_then(|n| self.expect(b';').map(|_| n)) {
+                    Ok(n) => String::from_utf8_lossy(&n).into_owned(),
+                    Err(at) => return Ok(Err(at)),
+                };
+                let Some((class, case)) = name.split_once(':') else { return Ok(Err(start)) };
+                let is_case = match vm.lookup_class(class) {
+                    Ok(c) => c.kind == ClassKind::Enum && c.find_constant(case).is_some_and(|k| k.is_case),
+                    Err(_) => false,
+                };
+                let v = match is_case {
+                    true => Some(vm.class_constant(&ClassOperand::Named(class.into()), case)?),
+                    false => None,
+                };
+                let Some(v) = v else {
+                    vm.warn(&format!("unserialize(): Undefined constant {}::{}", class, case));
+                    return Ok(Err(start));
+                };
+                self.values.push(v.clone());
+                Ok(Ok(v))
+            }
+            _ => {
+                let name = match self.quoted().and_then(|n| self.expect(b':').map(|_| n)) {
+                    Ok(n) => String::from_utf8_lossy(&n).into_owned(),
+                    Err(at) => return Ok(Err(at)),
+                };
+                let allowed = self.allowed_classes.as_ref().is_none_or(|a| a.iter().any(|c| c.eq_ignore_ascii_case(&name)));
+                let class = match allowed {
+                    true => vm.lookup_class(&name).ok(),
+                    false => None,
+                };
+                let incomplete = class.is_none();
+                let class = match class {
+                    Some(c) => c,
+                    None => vm.lookup_class("__PHP_Incomplete_Class")?,
+                };
+                let obj = vm.instantiate(&class)?;
+                let slot = self.values.len();
+                self.values.push(Value::Object(obj.clone()));
+                let props = match self.entries(vm)? {
+                    Ok(props) => props,
+                    Err(at) => return Ok(Err(at)),
+                };
+                if incomplete {
+                    let mut o = obj.borrow_mut();
+                    o.props.insert(ArrayKey::from("__PHP_Incomplete_Class_Name"), Value::from(name));
+                    for (k, v) in props.iter() {
+                        o.props.insert(k.clone(), v.clone());
+                    }
+                } else if let Some(m) = class.find_method("__unserialize").cloned() {
+                    let callee = vm.method_callee(&m, Some(obj.clone()), class)?;
+                    vm.call(callee, vec![Value::from(props)])?;
+                } else {
+                    let mut o = obj.borrow_mut();
+                    for (k, v) in props.iter() {
+                        o.props.insert(unmangle(k), v.clone());
+                    }
+                }
+                self.values[slot] = Value::Object(obj.clone());
+                Ok(Ok(Value::Object(obj)))
+            }
+        }
+    }
+}
+
+/// A property name as `(array)` mangles it, back to the plain name.
+fn unmangle(k: &ArrayKey) -> ArrayKey {
+    match k {
+        ArrayKey::Str(s) if s.first() == Some(&0) => match s[1..].iter().position(|&b| b == 0) {
+            Some(i) => ArrayKey::Str(Rc::new(s[i + 2..].to_vec())),
+            None => k.clone(),
+        },
+        _ => k.clone(),
+    }
+}
+
+fn parse_float(text: &[u8]) -> Option<f64> {
+    match text {
+        b"INF" => Some(f64::INFINITY),
+        b"-INF" => Some(f64::NEG_INFINITY),
+        b"NAN" => Some(f64::NAN),
+        _ => std::str::from_utf8(text).ok()?.parse().ok(),
+    }
+}
+
+/// `unserialize(string $data, array $options = []): mixed`; only the
+/// `allowed_classes` option is supported.
+fn unserialize(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    expect_args("unserialize", &args, 1, 2)?;
+    let mut args = args.into_iter();
+    let data = vm.coerce_arg(args.next().unwrap(), "string", "unserialize", 1, "data")?;
+    let data = data.scalar_to_bytes().unwrap();
+    let allowed_classes = match args.next() {
+        Some(Value::Array(options)) => match options.get(&ArrayKey::from("allowed_classes")).map(Value::deref) {
+            Some(Value::Array(names)) => {
+                Some(names.iter().map(|(_, n)| String::from_utf8_lossy(&n.scalar_to_bytes().unwrap_or_default()).into_owned()).collect())
+            }
+            Some(Value::Bool(false)) => Some(Vec::new()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let mut u = Unserializer { data: &data, pos: 0, values: Vec::new(), allowed_classes };
+    match u.value(vm)? {
+        Ok(v) => {
+            if u.pos < data.len() {
+                vm.warn(&format!("unserialize(): Extra data starting at offset {} of {} bytes", u.pos, data.len()));
+            }
+            Ok(v)
+        }
+        Err(at) => {
+            if !data.is_empty() {
+                vm.warn(&format!("unserialize(): Error at offset {} of {} bytes", at, data.len()));
+            }
+            Ok(Value::Bool(false))
+        }
+    }
+}
@@ -0,0 +1,1235 @@
+use std::collections::HashMap;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::extension::{arg_bool, arg_int, arg_str, opt_int, opt_str, set_ref, NativeFunction};
+use crate::limits::Growth;
+use crate::value::Value;
+use crate::vm::{Callee, Vm, VmError, VmResult};
+
+/// String functions. Strings are byte strings: lengths and offsets count
+/// bytes, and case conversions only touch ASCII letters, as in PHP 8.
+pub(crate) const FUNCTIONS: &[NativeFunction] = &[
+    NativeFunction { name: "strlen", params: &["string string"], func: strlen },
+    NativeFunction { name: "substr", params: &["string string", "int offset", "?int length = null"], func: substr },
+    NativeFunction { name: "substr_count", params: &["string haystack", "string needle", "int offset = 0", "?int length = null"], func: substr_count },
+    NativeFunction { name: "strpos", params: &["string haystack", "string needle", "int offset = 0"], func: strpos },
+    NativeFunction { name: "stripos", params: &["string haystack", "string needle", "int offset = 0"], func: stripos },
+    NativeFunction { name: "strrpos", params: &["string haystack", "string needle", "int offset = 0"], func: strrpos },
+    NativeFunction { name: "strripos", params: &["string haystack", "string needle", "int offset = 0"], func: strripos },
+    NativeFunction { name: "strstr", params: &["string haystack", "string needle", "bool before_needle = false"], func: strstr },
+    NativeFunction { name: "stristr", params: &["string haystack", "string needle", "bool before_needle = false"], func: stristr },
+    NativeFunction { name: "strrchr", params: &["string haystack", "string needle", "bool before_needle = false"], func: strrchr },
+    NativeFunction { name: "str_contains", params: &["string haystack", "string needle"], func: str_contains },
+    NativeFunction { name: "str_starts_with", params: &["string haystack", "string needle"], func: str_starts_with },
+    NativeFunction { name: "str_ends_with", params: &["string haystack", "string needle"], func: str_ends_with },
+    NativeFunction {
+        name: "str_replace",
+        params: &["array|string search", "array|string replace", "string|array subject", "&count = null"],
+        func: str_replace,
+    },
+    NativeFunction {
+        name: "str_ireplace",
+        params: &["array|string search", "array|string replace", "string|array subject", "&count = null"],
+        func: str_ireplace,
+    },
+    NativeFunction { name: "strtr", params: &["string string", "array|string from", "?string to = null"], func: strtr },
+    NativeFunction { name: "explode", params: &["string separator", "string string", "int limit = PHP_INT_MAX"], func: explode },
+    NativeFunction { name: "implode", params: &["array|string separator", "?array array = null"], func: implode },
+    NativeFunction { name: "join", params: &["array|string separator", "?array array = null"], func: implode },
+    NativeFunction { name: "trim", params: &["string string", "string characters = \" \\n\\r\\t\\v\\x00\""], func: trim },
+    NativeFunction { name: "ltrim", params: &["string string", "string characters = \" \\n\\r\\t\\v\\x00\""], func: ltrim },
+    NativeFunction { name: "rtrim", params: &["string string", "string characters = \" \\n\\r\\t\\v\\x00\""], func: rtrim },
+    NativeFunction { name: "chop", params: &["string string", "string characters = \" \\n\\r\\t\\v\\x00\""], func: rtrim },
+    NativeFunction {
+        name: "str_pad",
+        params: &["string string", "int length", "string pad_string = \" \"", "int pad_type = STR_PAD_RIGHT"],
+        func: str_pad,
+    },
+    NativeFunction { name: "str_repeat", params: &["string string", "int times"], func: str_repeat },
+    NativeFunction { name: "strtolower", params: &["string string"], func: strtolower },
+    NativeFunction { name: "strtoupper", params: &["string string"], func: strtoupper },
+    NativeFunction { name: "ucfirst", params: &["string string"], func: ucfirst },
+    NativeFunction { name: "lcfirst", params: &["string string"], func: lcfirst },
+    NativeFunction { name: "ucwords", params: &["string string", "string separators = \" \\t\\r\\n\\f\\v\""], func: ucwords },
+    NativeFunction {
+        name: "wordwrap",
+        params: &["string string", "int width = 75", "string break = \"\\n\"", "bool cut_long_words = false"],
+        func: wordwrap,
+    },
+    NativeFunction { name: "nl2br", params: &["string string", "bool use_xhtml = true"], func: nl2br },
+    NativeFunction {
+        name: "htmlspecialchars",
+        params: &[
+            "string string",
+            "int flags = ENT_QUOTES | ENT_SUBSTITUTE | ENT_HTML401",
+            "?string encoding = null",
+            "bool double_encode = true",
+        ],
+        func: htmlspecialchars,
+    },
+    NativeFunction { name: "strcmp", params: &["string string1", "string string2"], func: strcmp },
+    NativeFunction { name: "strcasecmp", params: &["string string1", "string string2"], func: strcasecmp },
+    NativeFunction { name: "strncmp", params: &["string string1", "string string2", "int length"], func: strncmp },
+    NativeFunction { name: "strncasecmp", params: &["string string1", "string string2", "int length"], func: strncasecmp },
+    NativeFunction { name: "strnatcmp", params: &["string string1", "string string2"], func: strnatcmp },
+    NativeFunction { name: "strnatcasecmp", params: &["string string1", "string string2"], func: strnatcasecmp },
+    NativeFunction { name: "str_split", params: &["string string", "int length = 1"], func: str_split },
+    NativeFunction { name: "strrev", params: &["string string"], func: strrev },
+    NativeFunction { name: "similar_text", params: &["string string1", "string string2", "&percent = null"], func: similar_text },
+    NativeFunction {
+        name: "levenshtein",
+        params: &[
+            "string string1",
+            "string string2",
+            "int insertion_cost = 1",
+            "int replacement_cost = 1",
+            "int deletion_cost = 1",
+        ],
+        func: levenshtein,
+    },
+    NativeFunction { name: "ord", params: &["string character"], func: ord },
+    NativeFunction { name: "chr", params: &["int codepoint"], func: chr },
+];
+
+const STR_PAD_LEFT: i64 = 0;
+const STR_PAD_RIGHT: i64 = 1;
+const STR_PAD_BOTH: i64 = 2;
+
+const ENT_HTML_QUOTE_SINGLE: i64 = 1;
+const ENT_HTML_QUOTE_DOUBLE: i64 = 2;
+const ENT_IGNORE: i64 = 4;
+const ENT_SUBSTITUTE: i64 = 8;
+const ENT_HTML401: i64 = 0;
+const ENT_XML1: i64 = 16;
+const ENT_DOCTYPE: i64 = 48;
+
+pub(crate) const CONSTANTS: &[(&str, i64)] = &[
+    ("STR_PAD_LEFT", STR_PAD_LEFT),
+    ("STR_PAD_RIGHT", STR_PAD_RIGHT),
+    ("STR_PAD_BOTH", STR_PAD_BOTH),
+    ("ENT_NOQUOTES", 0),
+    ("ENT_COMPAT", ENT_HTML_QUOTE_DOUBLE),
+    ("ENT_QUOTES", ENT_HTML_QUOTE_DOUBLE | ENT_HTML_QUOTE_SINGLE),
+    ("ENT_IGNORE", ENT_IGNORE),
+    ("ENT_SUBSTITUTE", ENT_SUBSTITUTE),
+    ("ENT_HTML401", ENT_HTML401),
+    ("ENT_XML1", ENT_XML1),
+    ("ENT_XHTML", 32),
+    ("ENT_HTML5", 48),
+];
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return (from <= haystack.len()).then_some(from);
+    }
+    haystack.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|i| i + from)
+}
+
+/// The start of the last occurrence of `needle` lying within `haystack`.
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(haystack.len());
+    }
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+fn lower(s: &[u8]) -> Vec<u8> {
+    s.to_ascii_lowercase()
+}
+
+/// `strlen(string $string): int`
+fn strlen(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(arg_str(&args, 0).len() as i64))
+}
+
+/// `substr(string $string, int $offset, ?int $length = null): string`
+fn substr(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = arg_str(&args, 0);
+    let len = s.len() as i64;
+    let mut offset = arg_int(&args, 1, 0);
+    if offset > len {
+        return Ok(Value::from(""));
+    }
+    if offset < 0 {
+        offset = (len + offset).max(0);
+    }
+    let rest = len - offset;
+    let length = match opt_int(&args, 2) {
+        None => rest,
+        Some(l) if l < 0 => (rest + l).max(0),
+        Some(l) => l.min(rest),
+    };
+    Ok(Value::from(s[offset as usize..(offset + length) as usize].to_vec()))
+}
+
+/// `substr_count(string $haystack, string $needle, int $offset = 0, ?int $length = null): int`
+fn substr_count(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (haystack, needle) = (arg_str(&args, 0), arg_str(&args, 1));
+    if needle.is_empty() {
+        return Err(VmError::value_error("substr_count(): Argument #2 ($needle) cannot be empty"));
+    }
+    let len = haystack.len() as i64;
+    let mut offset = arg_int(&args, 2, 0);
+    if offset < 0 {
+        offset += len;
+    }
+    if offset < 0 || offset > len {
+        return Err(VmError::value_error(
+            "substr_count(): Argument #3 ($offset) must be contained in argument #1 ($haystack)",
+        ));
+    }
+    let mut end = len;
+    if let Some(mut length) = opt_int(&args, 3) {
+        if length < 0 {
+            length += len - offset;
+        }
+        if length < 0 || length > len - offset {
+            return Err(VmError::value_error(
+                "substr_count(): Argument #4 ($length) must be contained in argument #1 ($haystack)",
+            ));
+        }
+        end = offset + length;
+    }
+    let haystack = &haystack[offset as usize..end as usize];
+    let (mut count, mut pos) = (0, 0);
+    while let Some(i) = find(haystack, needle, pos) {
+        count += 1;
+        pos = i + needle.len();
+    }
+    Ok(Value::Int(count))
+}
+
+/// The offset argument of the `strpos()` family, made absolute.
+fn search_offset(func: &str, offset: i64, len: usize) -> VmResult<usize> {
+    let len = len as i64;
+    let abs = if offset < 0 { offset + len } else { offset };
+    if abs < 0 || abs > len {
+        return Err(VmError::value_error(format!(
+            "{}(): Argument #3 ($offset) must be contained in argument #1 ($haystack)",
+            func
+        )));
+    }
+    Ok(abs as usize)
+}
+
+fn position(found: Option<usize>) -> Value {
+    found.map_or(Value::Bool(false), |i| Value::Int(i as i64))
+}
+
+fn find_from(func: &str, args: &[Value], fold: bool) -> VmResult<Value> {
+    let (mut haystack, mut needle) = (arg_str(args, 0).to_vec(), arg_str(args, 1).to_vec());
+    let offset = search_offset(func, arg_int(args, 2, 0), haystack.len())?;
+    if fold {
+        (haystack, needle) = (lower(&haystack), lower(&needle));
+    }
+    Ok(position(find(&haystack, &needle, offset)))
+}
+
+/// `strpos(string $haystack, string $needle, int $offset = 0): int|false`
+fn strpos(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    find_from("strpos", &args, false)
+}
+
+/// `stripos(string $haystack, string $needle, int $offset = 0): int|false`
+fn stripos(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    find_from("stripos", &args, true)
+}
+
+/// A non-negative offset starts the search there; a negative one ends it
+/// that far from the end, so the match must start at or before it.
+fn rfind_from(func: &str, args: &[Value], fold: bool) -> VmResult<Value> {
+    let (mut haystack, mut needle) = (arg_str(args, 0).to_vec(), arg_str(args, 1).to_vec());
+    let offset = arg_int(args, 2, 0);
+    search_offset(func, offset, haystack.len())?;
+    if fold {
+        (haystack, needle) = (lower(&haystack), lower(&needle));
+    }
+    let len = haystack.len();
+    let (start, end) = if offset >= 0 {
+        (offset as usize, len)
+    } else if offset.unsigned_abs() < needle.len() as u64 {
+        (0, len)
+    } else {
+        (0, (len as i64 + offset) as usize + needle.len())
+    };
+    Ok(position(rfind(&haystack[start..end], &needle).map(|i| i + start)))
+}
+
+/// `strrpos(string $haystack, string $needle, int $offset = 0): int|false`
+fn strrpos(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    rfind_from("strrpos", &args, false)
+}
+
+/// `strripos(string $haystack, string $needle, int $offset = 0): int|false`
+fn strripos(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    rfind_from("strripos", &args, true)
+}
+
+/// The part of the haystack from `pos` on, or before it.
+fn split_at(haystack: &[u8], pos: Option<usize>, before: bool) -> Value {
+    match pos {
+        None => Value::Bool(false),
+        Some(i) if before => Value::from(haystack[..i].to_vec()),
+        Some(i) => Value::from(haystack[i..].to_vec()),
+    }
+}
+
+/// `strstr(string $haystack, string $needle, bool $before_needle = false): string|false`
+fn strstr(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let haystack = arg_str(&args, 0);
+    let pos = find(haystack, arg_str(&args, 1), 0);
+    Ok(split_at(haystack, pos, arg_bool(&args, 2, false)))
+}
+
+/// `stristr(string $haystack, string $needle, bool $before_needle = false): string|false`
+fn stristr(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let haystack = arg_str(&args, 0);
+    let pos = find(&lower(haystack), &lower(arg_str(&args, 1)), 0);
+    Ok(split_at(haystack, pos, arg_bool(&args, 2, false)))
+}
+
+/// `strrchr(string $haystack, string $needle, bool $before_needle = false): string|false`
+///
+/// Only the first byte of the needle is searched for.
+fn strrchr(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let haystack = arg_str(&args, 0);
+    let c = arg_str(&args, 1).first().copied().unwrap_or(0);
+    let pos = haystack.iter().rposition(|b| *b == c);
+    Ok(split_at(haystack, pos, arg_bool(&args, 2, false)))
+}
+
+/// `str_contains(string $haystack, string $needle): bool`
+fn str_contains(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(find(arg_str(&args, 0), arg_str(&args, 1), 0).is_some()))
+}
+
+/// `str_starts_with(string $haystack, string $needle): bool`
+fn str_starts_with(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(arg_str(&args, 0).starts_with(arg_str(&args, 1))))
+}
+
+/// `str_ends_with(string $haystack, string $needle): bool`
+fn str_ends_with(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Bool(arg_str(&args, 0).ends_with(arg_str(&args, 1))))
+}
+
+/// Replaces every occurrence of `search`, left to right, counting them.
+/// What the result grows by is added to `growth`.
+fn replace_all(
+    vm: &mut Vm,
+    growth: &mut Growth,
+    subject: &[u8],
+    search: &[u8],
+    replace: &[u8],
+    fold: bool,
+    count: &mut i64,
+) -> VmResult<Vec<u8>> {
+    if search.is_empty() {
+        return Ok(subject.to_vec());
+    }
+    let (haystack, needle) = match fold {
+        true => (lower(subject), lower(search)),
+        false => (subject.to_vec(), search.to_vec()),
+    };
+    let mut out = Vec::with_capacity(subject.len());
+    let mut pos = 0;
+    while let Some(i) = find(&haystack, &needle, pos) {
+        growth.add(vm, replace.len().saturating_sub(needle.len()))?;
+        out.extend_from_slice(&subject[pos..i]);
+        out.extend_from_slice(replace);
+        pos = i + needle.len();
+        *count += 1;
+    }
+    out.extend_from_slice(&subject[pos..]);
+    Ok(out)
+}
+
+fn replace_in(
+    vm: &mut Vm,
+    growth: &mut Growth,
+    subject: Vec<u8>,
+    pairs: &[(Vec<u8>, Vec<u8>)],
+    fold: bool,
+    count: &mut i64,
+) -> VmResult<Vec<u8>> {
+    let mut subject = subject;
+    for (search, replace) in pairs {
+        subject = replace_all(vm, growth, &subject, search, replace, fold, count)?;
+    }
+    Ok(subject)
+}
+
+fn str_replace_impl(vm: &mut Vm, func: &str, args: &[Value], fold: bool) -> VmResult<Value> {
+    let pairs = match (&args[0], &args[1]) {
+        (Value::Array(search), replace) => {
+            let mut replacements = match replace {
+                Value::Array(r) => r.iter().map(|(_, v)| v.deref()).collect(),
+                _ => Vec::new(),
+            }
+            .into_iter();
+            let mut pairs = Vec::new();
+            for (_, s) in search.iter() {
+                let s = vm.stringify(&s.deref())?;
+                let r = match replace {
+                    Value::Array(_) => match replacements.next() {
+                        Some(r) => vm.stringify(&r)?,
+                        None => Vec::new(),
+                    },
+                    r => arg_str(std::slice::from_ref(r), 0).to_vec(),
+                };
+                pairs.push((s, r));
+            }
+            pairs
+        }
+        (Value::Str(search), Value::Str(replace)) => vec![(search.to_vec(), replace.to_vec())],
+        _ => {
+            return Err(VmError::type_error(format!(
+                "{}(): Argument #2 ($replace) must be of type string when argument #1 ($search) is a string",
+                func
+            )));
+        }
+    };
+    let mut count = 0;
+    let mut growth = Growth::new();
+    let result = match &args[2] {
+        Value::Array(subject) => {
+            let mut out = PhpArray::new();
+            for (k, v) in subject.iter() {
+                let v = match v.deref() {
+                    v @ (Value::Array(_) | Value::Object(_)) => v,
+                    v => {
+                        let v = vm.stringify(&v)?;
+                        Value::from(replace_in(vm, &mut growth, v, &pairs, fold, &mut count)?)
+                    }
+                };
+                out.insert(k.clone(), v);
+            }
+            Value::from(out)
+        }
+        subject => {
+            let subject = arg_str(std::slice::from_ref(subject), 0).to_vec();
+            Value::from(replace_in(vm, &mut growth, subject, &pairs, fold, &mut count)?)
+        }
+    };
+    set_ref(args, 3, Value::Int(count));
+    Ok(result)
+}
+
+/// `str_replace(array|string $search, array|string $replace, string|array $subject, &$count = null): string|array`
+fn str_replace(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    str_replace_impl(vm, "str_replace", &args, false)
+}
+
+/// `str_ireplace(array|string $search, array|string $replace, string|array $subject, &$count = null): string|array`
+fn str_ireplace(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    str_replace_impl(vm, "str_ireplace", &args, true)
+}
+
+/// `strtr(string $string, array|string $from, ?string $to = null): string`
+///
+/// With an array, the longest key matching at each position is replaced
+/// and replacements are not searched again.
+fn strtr(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = arg_str(&args, 0);
+    let from = match (&args[1], opt_str(&args, 2)) {
+        (Value::Str(from), Some(to)) => {
+            let mut map: [u8; 256] = std::array::from_fn(|i| i as u8);
+            for (f, t) in from.iter().zip(to) {
+                map[*f as usize] = *t;
+            }
+            return Ok(Value::from(s.iter().map(|b| map[*b as usize]).collect::<Vec<u8>>()));
+        }
+        (Value::Array(from), None) => from.clone(),
+        (_, Some(_)) => {
+            return Err(VmError::type_error("strtr(): Argument #2 ($from) must be of type string, array given"));
+        }
+        (_, None) => {
+            return Err(VmError::type_error("strtr(): Argument #2 ($from) must be of type array, string given"));
+        }
+    };
+    let mut map = HashMap::new();
+    for (k, v) in from.iter() {
+        let key = match k {
+            ArrayKey::Int(n) => n.to_string().into_bytes(),
+            ArrayKey::Str(s) => s.to_vec(),
+        };
+        if !key.is_empty() {
+            map.insert(key, vm.stringify(&v.deref())?);
+        }
+    }
+    let (Some(min), Some(max)) = (map.keys().map(Vec::len).min(), map.keys().map(Vec::len).max()) else {
+        return Ok(Value::from(s.to_vec()));
+    };
+    let mut out = Vec::with_capacity(s.len());
+    let mut growth = Growth::new();
+    let mut i = 0;
+    'scan: while i < s.len() {
+        for len in (min..=max.min(s.len() - i)).rev() {
+            if let Some(r) = map.get(&s[i..i + len]) {
+                growth.add(vm, r.len().saturating_sub(len))?;
+                out.extend_from_slice(r);
+                i += len;
+                continue 'scan;
+            }
+        }
+        out.push(s[i]);
+        i += 1;
+    }
+    Ok(Value::from(out))
+}
+
+fn list(items: impl IntoIterator<Item = Vec<u8>>) -> VmResult<Value> {
+    let mut arr = PhpArray::new();
+    for item in items {
+        arr.push(Value::from(item)).map_err(VmError::error)?;
+    }
+    Ok(Value::from(arr))
+}
+
+/// `explode(string $separator, string $string, int $limit = PHP_INT_MAX): array`
+fn explode(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (sep, s) = (arg_str(&args, 0), arg_str(&args, 1));
+    // A limit of 0 is taken as 1.
+    let limit = match arg_int(&args, 2, i64::MAX) {
+        0 => 1,
+        limit => limit,
+    };
+    if sep.is_empty() {
+        return Err(VmError::value_error("explode(): Argument #1 ($separator) cannot be empty"));
+    }
+    if s.is_empty() {
+        return list((limit >= 0).then(Vec::new));
+    }
+    let mut pieces = Vec::new();
+    let mut growth = Growth::new();
+    let mut pos = 0;
+    while let Some(i) = find(s, sep, pos) {
+        if limit > 0 && pieces.len() as i64 >= limit - 1 {
+            break;
+        }
+        growth.add_strings(vm, 1, i - pos)?;
+        pieces.push(s[pos..i].to_vec());
+        pos = i + sep.len();
+    }
+    pieces.push(s[pos..].to_vec());
+    if limit < 0 {
+        pieces.truncate(pieces.len().saturating_sub(limit.unsigned_abs() as usize));
+    }
+    list(pieces)
+}
+
+/// `implode(array|string $separator, ?array $array = null): string`
+///
+/// The legacy form `implode(array $array)` joins with nothing.
+fn implode(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (sep, pieces) = match (&args[0], args.get(1)) {
+        (Value::Array(pieces), None | Some(Value::Null)) => (Vec::new(), pieces.clone()),
+        (v, None | Some(Value::Null)) => {
+            return Err(VmError::type_error(format!(
+                "{}(): Argument #1 ($pieces) must be of type array, {} given",
+                callee.func.name,
+                v.type_name()
+            )));
+        }
+        (Value::Str(sep), Some(Value::Array(pieces))) => (sep.to_vec(), pieces.clone()),
+        (v, _) => {
+            return Err(VmError::type_error(format!(
+                "{}(): Argument #1 ($separator) must be of type string, {} given",
+                callee.func.name,
+                v.type_name()
+            )));
+        }
+    };
+    let mut out = Vec::new();
+    for (i, (_, v)) in pieces.iter().enumerate() {
+        if i > 0 {
+            out.extend_from_slice(&sep);
+        }
+        out.extend(vm.stringify(&v.deref())?);
+    }
+    Ok(Value::from(out))
+}
+
+/// The set of bytes a character list names; `a..z` is a range.
+fn char_mask(vm: &mut Vm, func: &str, chars: &[u8]) -> [bool; 256] {
+    let mut mask = [false; 256];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if i + 3 < chars.len() && chars[i + 1] == b'.' && chars[i + 2] == b'.' && chars[i + 3] >= c {
+            for b in c..=chars[i + 3] {
+                mask[b as usize] = true;
+            }
+            i += 4;
+            continue;
+        }
+        if i + 1 < chars.len() && c == b'.' && chars[i + 1] == b'.' {
+            let problem = if i == 0 {
+                "no character to the left of '..'"
+            } else if i + 2 >= chars.len() {
+                "no character to the right of '..'"
+            } else if chars[i - 1] > chars[i + 2] {
+                "'..'-range needs to be incrementing"
+            } else {
+                ""
+            };
+            let message = match problem {
+                "" => format!("{}(): Invalid '..'-range", func),
+                problem => format!("{}(): Invalid '..'-range, {}", func, problem),
+            };
+            vm.warn(&message);
+        } else {
+            mask[c as usize] = true;
+        }
+        i += 1;
+    }
+    mask
+}
+
+const TRIM_CHARS: &[u8] = b" \n\r\t\x0b\0";
+
+fn trim_impl(vm: &mut Vm, func: &str, args: &[Value], left: bool, right: bool) -> VmResult<Value> {
+    let s = arg_str(args, 0);
+    let mask = char_mask(vm, func, opt_str(args, 1).unwrap_or(TRIM_CHARS));
+    let mut start = 0;
+    let mut end = s.len();
+    if left {
+        while start < end && mask[s[start] as usize] {
+            start += 1;
+        }
+    }
+    if right {
+        while end > start && mask[s[end - 1] as usize] {
+            end -= 1;
+        }
+    }
+    Ok(Value::from(s[start..end].to_vec()))
+}
+
+/// `trim(string $string, string $characters = " \n\r\t\v\x00"): string`
+fn trim(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    trim_impl(vm, "trim", &args, true, true)
+}
+
+/// `ltrim(string $string, string $characters = " \n\r\t\v\x00"): string`
+fn ltrim(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    trim_impl(vm, "ltrim", &args, true, false)
+}
+
+/// `rtrim(string $string, string $characters = " \n\r\t\v\x00"): string`
+fn rtrim(vm: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    trim_impl(vm, &callee.func.name, &args, false, true)
+}
+
+/// `str_pad(string $string, int $length, string $pad_string = " ", int $pad_type = STR_PAD_RIGHT): string`
+fn str_pad(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = arg_str(&args, 0);
+    let length = arg_int(&args, 1, 0);
+    if length <= s.len() as i64 {
+        return Ok(Value::from(s.to_vec()));
+    }
+    let pad = opt_str(&args, 2).unwrap_or(b" ");
+    if pad.is_empty() {
+        return Err(VmError::value_error("str_pad(): Argument #3 ($pad_string) must be a non-empty string"));
+    }
+    let pad_type = arg_int(&args, 3, STR_PAD_RIGHT);
+    let missing = length as usize - s.len();
+    let left = match pad_type {
+        STR_PAD_LEFT => missing,
+        STR_PAD_RIGHT => 0,
+        STR_PAD_BOTH => missing / 2,
+        _ => {
+            return Err(VmError::value_error(
+                "str_pad(): Argument #4 ($pad_type) must be STR_PAD_LEFT, STR_PAD_RIGHT, or STR_PAD_BOTH",
+            ));
+        }
+    };
+    vm.reserve(length as usize)?;
+    let mut out = Vec::with_capacity(length as usize);
+    out.extend(pad.iter().cycle().take(left));
+    out.extend_from_slice(s);
+    out.extend(pad.iter().cycle().take(missing - left));
+    Ok(Value::from(out))
+}
+
+/// `str_repeat(string $string, int $times): string`
+fn str_repeat(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = arg_str(&args, 0);
+    let times = arg_int(&args, 1, 0);
+    if times < 0 {
+        return Err(VmError::value_error("str_repeat(): Argument #2 ($times) must be greater than or equal to 0"));
+    }
+    let Some(len) = s.len().checked_mul(times as usize).filter(|len| *len <= isize::MAX as usize - 32) else {
+        return Err(VmError::Fatal(format!(
+            "Possible integer overflow in memory allocation ({} * {} + 32)",
+            s.len(),
+            times
+        )));
+    };
+    vm.reserve(len)?;
+    Ok(Value::from(s.repeat(times as usize)))
+}
+
+/// `strtolower(string $string): string`
+fn strtolower(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(lower(arg_str(&args, 0))))
+}
+
+/// `strtoupper(string $string): string`
+fn strtoupper(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(arg_str(&args, 0).to_ascii_uppercase()))
+}
+
+/// `ucfirst(string $string): string`
+fn ucfirst(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut s = arg_str(&args, 0).to_vec();
+    if let Some(c) = s.first_mut() {
+        c.make_ascii_uppercase();
+    }
+    Ok(Value::from(s))
+}
+
+/// `lcfirst(string $string): string`
+fn lcfirst(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let mut s = arg_str(&args, 0).to_vec();
+    if let Some(c) = s.first_mut() {
+        c.make_ascii_lowercase();
+    }
+    Ok(Value::from(s))
+}
+
+/// `ucwords(string $string, string $separators = " \t\r\n\f\v"): string`
+fn ucwords(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let separators = opt_str(&args, 1).unwrap_or(b" \t\r\n\x0c\x0b");
+    let mut s = arg_str(&args, 0).to_vec();
+    let mut word_start = true;
+    for c in s.iter_mut() {
+        if word_start {
+            c.make_ascii_uppercase();
+        }
+        word_start = separators.contains(c);
+    }
+    Ok(Value::from(s))
+}
+
+/// `wordwrap(string $string, int $width = 75, string $break = "\n", bool $cut_long_words = false): string`
+///
+/// Follows PHP's algorithm: lines break at the last space before the
+/// width, existing breaks restart the count, and long words are cut only
+/// if asked.
+fn wordwrap(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let text = arg_str(&args, 0);
+    let width = arg_int(&args, 1, 75);
+    let brk = opt_str(&args, 2).unwrap_or(b"\n");
+    let cut = arg_bool(&args, 3, false);
+    if text.is_empty() {
+        return Ok(Value::from(""));
+    }
+    if brk.is_empty() {
+        return Err(VmError::value_error("wordwrap(): Argument #3 ($break) cannot be empty"));
+    }
+    if width == 0 && cut {
+        return Err(VmError::value_error(
+            "wordwrap(): Argument #4 ($cut_long_words) cannot be true when argument #2 ($width) is 0",
+        ));
+    }
+    let len = text.len() as i64;
+    let (mut last_start, mut last_space) = (0i64, 0i64);
+    if brk.len() == 1 && !cut {
+        let mut out = text.to_vec();
+        for current in 0..len {
+            let c = text[current as usize];
+            if c == brk[0] {
+                last_start = current + 1;
+                last_space = current + 1;
+            } else if c == b' ' {
+                if current - last_start >= width {
+                    out[current as usize] = brk[0];
+                    last_start = current + 1;
+                }
+                last_space = current;
+            } else if current - last_start >= width && last_start != last_space {
+                out[last_space as usize] = brk[0];
+                last_start = last_space + 1;
+            }
+        }
+        return Ok(Value::from(out));
+    }
+    let mut out = Vec::with_capacity(text.len());
+    let mut growth = Growth::new();
+    let slice = |from: i64, to: i64| &text[from as usize..to as usize];
+    let mut current = 0;
+    while current < len {
+        let c = text[current as usize];
+        if c == brk[0] && current + (brk.len() as i64) < len && text[current as usize..].starts_with(brk) {
+            out.extend_from_slice(slice(last_start, current + brk.len() as i64));
+            current += brk.len() as i64 - 1;
+            last_start = current + 1;
+            last_space = current + 1;
+        } else if c == b' ' {
+            if current - last_start >= width {
+                growth.add(vm, brk.len())?;
+                out.extend_from_slice(slice(last_start, current));
+                out.extend_from_slice(brk);
+                last_start = current + 1;
+            }
+            last_space = current;
+        } else if current - last_start >= width && cut && last_start >= last_space {
+            growth.add(vm, brk.len())?;
+            out.extend_from_slice(slice(last_start, current));
+            out.extend_from_slice(brk);
+            last_start = current;
+            last_space = current;
+        } else if current - last_start >= width && last_start < last_space {
+            growth.add(vm, brk.len())?;
+            out.extend_from_slice(slice(last_start, last_space));
+            out.extend_from_slice(brk);
+            last_space += 1;
+            last_start = last_space;
+        }
+        current += 1;
+    }
+    if last_start != current {
+        out.extend_from_slice(slice(last_start, current));
+    }
+    Ok(Value::from(out))
+}
+
+/// `nl2br(string $string, bool $use_xhtml = true): string`
+fn nl2br(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = arg_str(&args, 0);
+    let br: &[u8] = if arg_bool(&args, 1, true) { b"<br />" } else { b"<br>" };
+    let breaks = s.iter().filter(|c| **c == b'\r' || **c == b'\n').count();
+    let len = s.len() + breaks * br.len();
+    vm.reserve(len)?;
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    while i < s.len() {
+        let c = s[i];
+        if c == b'\r' || c == b'\n' {
+            out.extend_from_slice(br);
+            out.push(c);
+            if let Some(&next) = s.get(i + 1)
+                && (next == b'\r' || next == b'\n')
+                && next != c
+            {
+                out.push(next);
+                i += 1;
+            }
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    Ok(Value::from(out))
+}
+
+/// The length of the UTF-8 sequence starting `s`, or how many bytes to
+/// skip as invalid, as PHP's entity functions decide it.
+fn next_utf8(s: &[u8]) -> Result<usize, usize> {
+    let trail = |i: usize| s.get(i).is_some_and(|b| (0x80..=0xbf).contains(b));
+    let lead = |i: usize| s.get(i).is_some_and(|b| *b < 0x80 || (0xc2..=0xf4).contains(b));
+    let bits = |i: usize| (s[i] & 0x3f) as u32;
+    let c = s[0];
+    match c {
+        0x00..=0x7f => Ok(1),
+        0xc2..=0xdf => {
+            if s.len() < 2 {
+                return Err(1);
+            }
+            if !trail(1) {
+                return Err(if lead(1) { 1 } else { 2 });
+            }
+            Ok(2)
+        }
+        0xe0..=0xef => {
+            if s.len() < 3 || !trail(1) || !trail(2) {
+                return Err(if s.len() < 2 || lead(1) {
+                    1
+                } else if s.len() < 3 || lead(2) {
+                    2
+                } else {
+                    3
+                });
+            }
+            let cp = ((c & 0x0f) as u32) << 12 | bits(1) << 6 | bits(2);
+            if cp < 0x800 || (0xd800..=0xdfff).contains(&cp) {
+                return Err(3);
+            }
+            Ok(3)
+        }
+        0xf0..=0xf4 => {
+            if s.len() < 4 || !trail(1) || !trail(2) || !trail(3) {
+                return Err(if s.len() < 2 || lead(1) {
+                    1
+                } else if s.len() < 3 || lead(2) {
+                    2
+                } else if s.len() < 4 || lead(3) {
+                    3
+                } else {
+                    4
+                });
+            }
+            let cp = ((c & 0x07) as u32) << 18 | bits(1) << 12 | bits(2) << 6 | bits(3);
+            if !(0x10000..=0x10ffff).contains(&cp) {
+                return Err(4);
+            }
+            Ok(4)
+        }
+        _ => Err(1),
+    }
+}
+
+/// The length of the entity after a `&`, including its `;`, if it is
+/// one `htmlspecialchars()` leaves alone when not double encoding.
+/// Named entities are not checked against the HTML tables, except that
+/// XML only has its five.
+fn entity_len(s: &[u8], doctype: i64) -> Option<usize> {
+    if let Some(rest) = s.strip_prefix(b"#") {
+        let (hex, digits) = match rest.first() {
+            Some(b'x' | b'X') => (true, &rest[1..]),
+            _ => (false, rest),
+        };
+        let n = digits.iter().take_while(|b| if hex { b.is_ascii_hexdigit() } else { b.is_ascii_digit() }).count();
+        if n == 0 || digits.get(n) != Some(&b';') {
+            return None;
+        }
+        let text = std::str::from_utf8(&digits[..n]).ok()?;
+        let code = u64::from_str_radix(text, if hex { 16 } else { 10 }).unwrap_or(u64::MAX);
+        return (code <= 0x10ffff).then_some(s.len() - digits.len() + n + 1);
+    }
+    let n = s.iter().take_while(|b| b.is_ascii_alphanumeric()).count();
+    if n == 0 || s.get(n) != Some(&b';') {
+        return None;
+    }
+    if doctype == ENT_XML1 && ![&b"amp"[..], b"lt", b"gt", b"quot", b"apos"].contains(&&s[..n]) {
+        return None;
+    }
+    Some(n + 1)
+}
+
+/// `htmlspecialchars(string $string, int $flags = ENT_QUOTES | ENT_SUBSTITUTE | ENT_HTML401, ?string $encoding = null, bool $double_encode = true): string`
+fn htmlspecialchars(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let s = arg_str(&args, 0);
+    let flags = arg_int(&args, 1, ENT_HTML_QUOTE_DOUBLE | ENT_HTML_QUOTE_SINGLE | ENT_SUBSTITUTE);
+    let double_encode = arg_bool(&args, 3, true);
+    let doctype = flags & ENT_DOCTYPE;
+    let utf8 = match opt_str(&args, 2).map(String::from_utf8_lossy) {
+        None => true,
+        Some(e) if e.is_empty() || e.eq_ignore_ascii_case("utf-8") || e.eq_ignore_ascii_case("utf8") => true,
+        Some(e) if SINGLE_BYTE_CHARSETS.iter().any(|c| e.eq_ignore_ascii_case(c)) => false,
+        Some(e) => {
+            vm.warn(&format!("htmlspecialchars(): Charset \"{}\" is not supported, assuming UTF-8", e));
+            true
+        }
+    };
+    // At most an entity per special character, or U+FFFD per invalid byte.
+    let extra: usize = s
+        .iter()
+        .map(|c| match c {
+            b'&' | b'<' | b'>' | b'"' | b'\'' => 5,
+            0x80.. => 2,
+            _ => 0,
+        })
+        .sum();
+    vm.reserve(s.len() + extra)?;
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        if utf8 && let Err(skip) = next_utf8(&s[i..]) {
+            i += skip;
+            if flags & ENT_IGNORE != 0 {
+                continue;
+            }
+            if flags & ENT_SUBSTITUTE != 0 {
+                out.extend_from_slice("\u{fffd}".as_bytes());
+                continue;
+            }
+            return Ok(Value::from(""));
+        }
+        let c = s[i];
+        i += 1;
+        match c {
+            b'&' if !double_encode && let Some(n) = entity_len(&s[i..], doctype) => {
+                out.push(b'&');
+                out.extend_from_slice(&s[i..i + n]);
+                i += n;
+            }
+            b'&' => out.extend_from_slice(b"&amp;"),
+            b'<' => out.extend_from_slice(b"&lt;"),
+            b'>' => out.extend_from_slice(b"&gt;"),
+            b'"' if flags & ENT_HTML_QUOTE_DOUBLE != 0 => out.extend_from_slice(b"&quot;"),
+            b'\'' if flags & ENT_HTML_QUOTE_SINGLE != 0 => {
+                out.extend_from_slice(if doctype == ENT_HTML401 { b"&#039;" } else { b"&apos;" })
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(Value::from(out))
+}
+
+/// Charsets `htmlspecialchars()` passes through without validation.
+const SINGLE_BYTE_CHARSETS: &[&str] = &[
+    "iso-8859-1",
+    "iso8859-1",
+    "latin1",
+    "iso-8859-5",
+    "iso8859-5",
+    "iso-8859-15",
+    "iso8859-15",
+    "cp1252",
+    "windows-1252",
+    "1252",
+    "cp1251",
+    "windows-1251",
+    "win-1251",
+    "koi8-r",
+    "koi8-ru",
+    "koi8r",
+    "cp866",
+    "866",
+    "ibm866",
+    "macroman",
+];
+
+fn ordering(o: std::cmp::Ordering) -> Value {
+    Value::Int(o as i64)
+}
+
+/// `strcmp(string $string1, string $string2): int`
+fn strcmp(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(ordering(arg_str(&args, 0).cmp(arg_str(&args, 1))))
+}
+
+/// `strcasecmp(string $string1, string $string2): int`
+fn strcasecmp(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(ordering(lower(arg_str(&args, 0)).cmp(&lower(arg_str(&args, 1)))))
+}
+
+fn prefix_cmp(func: &str, args: &[Value], fold: bool) -> VmResult<Value> {
+    let length = arg_int(args, 2, 0);
+    if length < 0 {
+        return Err(VmError::value_error(format!(
+            "{}(): Argument #3 ($length) must be greater than or equal to 0",
+            func
+        )));
+    }
+    let prefix = |s: &[u8]| {
+        let s = &s[..s.len().min(length as usize)];
+        if fold { lower(s) } else { s.to_vec() }
+    };
+    Ok(ordering(prefix(arg_str(args, 0)).cmp(&prefix(arg_str(args, 1)))))
+}
+
+/// `strncmp(string $string1, string $string2, int $length): int`
+fn strncmp(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    prefix_cmp("strncmp", &args, false)
+}
+
+/// `strncasecmp(string $string1, string $string2, int $length): int`
+fn strncasecmp(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    prefix_cmp("strncasecmp", &args, true)
+}
+
+/// Compares runs of digits: the longer run wins, then the first
+/// differing digit.
+fn compare_right(a: &[u8], ap: &mut usize, b: &[u8], bp: &mut usize) -> i64 {
+    let mut bias = 0;
+    loop {
+        let da = a.get(*ap).is_some_and(u8::is_ascii_digit);
+        let db = b.get(*bp).is_some_and(u8::is_ascii_digit);
+        match (da, db) {
+            (false, false) => return bias,
+            (false, true) => return -1,
+            (true, false) => return 1,
+            _ if bias == 0 => bias = (a[*ap].cmp(&b[*bp])) as i64,
+            _ => {}
+        }
+        *ap += 1;
+        *bp += 1;
+    }
+}
+
+/// Compares runs of digits with leading zeros as fractions: the first
+/// differing digit wins.
+fn compare_left(a: &[u8], ap: &mut usize, b: &[u8], bp: &mut usize) -> i64 {
+    loop {
+        let da = a.get(*ap).is_some_and(u8::is_ascii_digit);
+        let db = b.get(*bp).is_some_and(u8::is_ascii_digit);
+        match (da, db) {
+            (false, false) => return 0,
+            (false, true) => return -1,
+            (true, false) => return 1,
+            _ if a[*ap] != b[*bp] => return (a[*ap].cmp(&b[*bp])) as i64,
+            _ => {}
+        }
+        *ap += 1;
+        *bp += 1;
+    }
+}
+
+/// Natural order comparison, a port of PHP's `strnatcmp_ex()`: runs of
+/// digits compare as numbers and whitespace is skipped.
+pub(crate) fn natural_compare(a: &[u8], b: &[u8], fold: bool) -> i64 {
+    if a.is_empty() || b.is_empty() {
+        return (a.len().cmp(&b.len())) as i64;
+    }
+    let at = |s: &[u8], i: usize| s.get(i).copied().unwrap_or(0);
+    let space = |c: u8| c == b' ' || (b'\t'..=b'\r').contains(&c);
+    let (mut ap, mut bp) = (0, 0);
+    let mut leading = true;
+    loop {
+        let (mut ca, mut cb) = (at(a, ap), at(b, bp));
+        while leading && ca == b'0' && ap + 1 < a.len() && a[ap + 1].is_ascii_digit() {
+            ap += 1;
+            ca = a[ap];
+        }
+        while leading && cb == b'0' && bp + 1 < b.len() && b[bp + 1].is_ascii_digit() {
+            bp += 1;
+            cb = b[bp];
+        }
+        leading = false;
+        while space(ca) {
+            ap += 1;
+            ca = at(a, ap);
+        }
+        while space(cb) {
+            bp += 1;
+            cb = at(b, bp);
+        }
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let result = if ca == b'0' || cb == b'0' {
+                compare_left(a, &mut ap, b, &mut bp)
+            } else {
+                compare_right(a, &mut ap, b, &mut bp)
+            };
+            if result != 0 {
+                return result;
+            }
+            match (ap == a.len(), bp == b.len()) {
+                (true, true) => return 0,
+                (true, false) => return -1,
+                (false, true) => return 1,
+                _ => {}
+            }
+            (ca, cb) = (a[ap], b[bp]);
+        }
+        if fold {
+            (ca, cb) = (ca.to_ascii_uppercase(), cb.to_ascii_uppercase());
+        }
+        if ca != cb {
+            return (ca.cmp(&cb)) as i64;
+        }
+        ap += 1;
+        bp += 1;
+        match (ap >= a.len(), bp >= b.len()) {
+            (true, true) => return 0,
+            (true, false) => return -1,
+            (false, true) => return 1,
+            _ => {}
+        }
+    }
+}
+
+/// `strnatcmp(string $string1, string $string2): int`
+fn strnatcmp(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(natural_compare(arg_str(&args, 0), arg_str(&args, 1), false)))
+}
+
+/// `strnatcasecmp(string $string1, string $string2): int`
+fn strnatcasecmp(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(natural_compare(arg_str(&args, 0), arg_str(&args, 1), true)))
+}
+
+/// `str_split(string $string, int $length = 1): array`
+fn str_split(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let length = arg_int(&args, 1, 1);
+    if length < 1 {
+        return Err(VmError::value_error("str_split(): Argument #2 ($length) must be greater than 0"));
+    }
+    let s = arg_str(&args, 0);
+    Growth::new().add_strings(vm, s.len().div_ceil(length as usize), s.len())?;
+    list(s.chunks(length as usize).map(<[u8]>::to_vec))
+}
+
+/// `strrev(string $string): string`
+fn strrev(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(arg_str(&args, 0).iter().rev().copied().collect::<Vec<u8>>()))
+}
+
+/// Counts the common bytes the way PHP does: the first longest common
+/// substring, plus the common bytes left and right of it.
+fn similar_chars(vm: &mut Vm, a: &[u8], b: &[u8]) -> VmResult<usize> {
+    let (mut pos1, mut pos2, mut max, mut count) = (0, 0, 0, 0);
+    for i in 0..a.len() {
+        let mut work = b.len();
+        for j in 0..b.len() {
+            let l = a[i..].iter().zip(&b[j..]).take_while(|(x, y)| x == y).count();
+            work += l;
+            if l > max {
+                (max, pos1, pos2) = (l, i, j);
+                count += 1;
+            }
+        }
+        vm.tick(work as u64)?;
+    }
+    if max == 0 {
+        return Ok(0);
+    }
+    let mut sum = max;
+    if pos1 > 0 && pos2 > 0 && count > 1 {
+        sum += similar_chars(vm, &a[..pos1], &b[..pos2])?;
+    }
+    if pos1 + max < a.len() && pos2 + max < b.len() {
+        sum += similar_chars(vm, &a[pos1 + max..], &b[pos2 + max..])?;
+    }
+    Ok(sum)
+}
+
+/// `similar_text(string $string1, string $string2, &$percent = null): int`
+fn similar_text(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b) = (arg_str(&args, 0), arg_str(&args, 1));
+    if a.is_empty() && b.is_empty() {
+        set_ref(&args, 2, Value::Float(0.0));
+        return Ok(Value::Int(0));
+    }
+    let sim = similar_chars(vm, a, b)?;
+    set_ref(&args, 2, Value::Float(sim as f64 * 200.0 / (a.len() + b.len()) as f64));
+    Ok(Value::Int(sim as i64))
+}
+
+/// `levenshtein(string $string1, string $string2, int $insertion_cost = 1, int $replacement_cost = 1, int $deletion_cost = 1): int`
+fn levenshtein(vm: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let (a, b) = (arg_str(&args, 0), arg_str(&args, 1));
+    let (ins, rep, del) = (arg_int(&args, 2, 1), arg_int(&args, 3, 1), arg_int(&args, 4, 1));
+    if a.is_empty() {
+        return Ok(Value::Int((b.len() as i64).wrapping_mul(ins)));
+    }
+    if b.is_empty() {
+        return Ok(Value::Int((a.len() as i64).wrapping_mul(del)));
+    }
+    let mut prev: Vec<i64> = (0..=b.len() as i64).map(|i| i.wrapping_mul(ins)).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for ca in a {
+        cur[0] = prev[0].wrapping_add(del);
+        for (j, cb) in b.iter().enumerate() {
+            let replace = prev[j].wrapping_add(if ca == cb { 0 } else { rep });
+            let delete = prev[j + 1].wrapping_add(del);
+            let insert = cur[j].wrapping_add(ins);
+            cur[j + 1] = replace.min(delete).min(insert);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+        vm.tick(b.len() as u64)?;
+    }
+    Ok(Value::Int(prev[b.len()]))
+}
+
+/// `ord(string $character): int`
+fn ord(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::Int(arg_str(&args, 0).first().copied().unwrap_or(0).into()))
+}
+
+/// `chr(int $codepoint): string`
+fn chr(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from(vec![arg_int(&args, 0, 0) as u8]))
+}
@@ -21,6 +21,46 @@ pub enum Token {
     True,
     False,
     Null,
+    Class,
+    Interface,
+    Trait,
+    Extends,
+    Implements,
+    Abstract,
+    Final,
+    Public,
+    Protected,
+    Private,
+    Static,
+    Var,
+    Const,
+    New,
+    Use,
+    Insteadof,
+    As,
+    Case,
+    Fn,
+    Declare,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Break,
+    Continue,
+    Namespace,
+    Include,
+    IncludeOnce,
+    Require,
+    RequireOnce,
+    Global,
+    Unset,
+    Isset,
+    Empty,
+    Instanceof,
+    Foreach,
+    Yield,
+    YieldFrom, // `yield from`
+    Clone,
 
     // Operators
     Plus,
@@ -41,6 +81,15 @@ pub enum Token {
     And, // &&
     Or,  // ||
     Not, // !
+    Ampersand, // &
+    Pipe,      // |
+    Question,  // ?
+    IntCast,    // (int)
+    FloatCast,  // (float)
+    StringCast, // (string)
+    BoolCast,   // (bool)
+    ArrayCast,  // (array)
+    ObjectCast, // (object)
 
     // Delimiters
     OpenParen,
@@ -51,7 +100,12 @@ pub enum Token {
     CloseBracket,
     Semicolon,
     Comma,
-    Arrow,
+    Arrow,          // =>
+    ObjectOperator, // ->
+    DoubleColon,    // ::
+    Colon,
+    Ellipsis,       // ...
+    Backslash,      // \ in group `use` (`use A\{B, C}`)
 
     // Special
     OpenTag, // <?php
@@ -64,3 +118,63 @@ pub struct Spanned {
     pub line: usize,
     pub col: usize,
 }
+
+impl Token {
+    /// Keywords that may still be used as member names (`$obj->list`,
+    /// `Foo::class`, `function new()` inside a class body).
+    pub fn keyword_name(&self) -> Option<&'static str> {
+        Some(match self {
+            Token::Echo => "echo",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::Elseif => "elseif",
+            Token::While => "while",
+            Token::For => "for",
+            Token::Function => "function",
+            Token::Return => "return",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Null => "null",
+            Token::Class => "class",
+            Token::Interface => "interface",
+            Token::Trait => "trait",
+            Token::Extends => "extends",
+            Token::Implements => "implements",
+            Token::Abstract => "abstract",
+            Token::Final => "final",
+            Token::Public => "public",
+            Token::Protected => "protected",
+            Token::Private => "private",
+            Token::Static => "static",
+            Token::Var => "var",
+            Token::Const => "const",
+            Token::New => "new",
+            Token::Use => "use",
+            Token::Insteadof => "insteadof",
+            Token::As => "as",
+            Token::Case => "case",
+            Token::Fn => "fn",
+            Token::Declare => "declare",
+            Token::Try => "try",
+            Token::Catch => "catch",
+            Token::Finally => "finally",
+            Token::Throw => "throw",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::Namespace => "namespace",
+            Token::Include => "include",
+            Token::IncludeOnce => "include_once",
+            Token::Require => "require",
+            Token::RequireOnce => "require_once",
+            Token::Global => "global",
+            Token::Unset => "unset",
+            Token::Isset => "isset",
+            Token::Empty => "empty",
+            Token::Instanceof => "instanceof",
+            Token::Foreach => "foreach",
+            Token::Yield => "yield",
+            Token::Clone => "clone",
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,454 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, PhpArray};
+use crate::ast::ClassKind;
+use crate::class::Object;
+
+pub type ObjRef = Rc<RefCell<Object>>;
+
+/// The standard streams scripts can write to, PHP's `STDOUT` and
+/// `STDERR` resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    /// The resource number PHP's command line gives the stream.
+    pub fn id(self) -> i64 {
+        match self {
+            Stream::Stdout => 2,
+            Stream::Stderr => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Rc<Vec<u8>>),
+    Array(Rc<PhpArray>),
+    Object(ObjRef),
+    /// A resource; the standard streams are the only ones.
+    Resource(Stream),
+    /// A PHP reference. Only found in storage slots (variables, captured
+    /// closure variables), never on the operand stack.
+    Ref(Rc<RefCell<Value>>),
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(Rc::new(s.as_bytes().to_vec()))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(Rc::new(s.into_bytes()))
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(s: Vec<u8>) -> Self {
+        Value::Str(Rc::new(s))
+    }
+}
+
+impl From<PhpArray> for Value {
+    fn from(a: PhpArray) -> Self {
+        Value::Array(Rc::new(a))
+    }
+}
+
+/// Result of interpreting a string as a number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numeric {
+    Int(i64),
+    Float(f64),
+}
+
+impl Numeric {
+    pub fn to_value(self) -> Value {
+        match self {
+            Numeric::Int(n) => Value::Int(n),
+            Numeric::Float(f) => Value::Float(f),
+        }
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::Int(_) => "int".to_string(),
+            Value::Float(_) => "float".to_string(),
+            Value::Str(_) => "string".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Object(o) => o.borrow().class.name.to_string(),
+            Value::Resource(_) => "resource".to_string(),
+            Value::Ref(r) => r.borrow().type_name(),
+        }
+    }
+
+    /// The value itself, or the referenced value for a `Ref`.
+    pub fn deref(&self) -> Value {
+        match self {
+            Value::Ref(r) => r.borrow().clone(),
+            v => v.clone(),
+        }
+    }
+
+    pub fn to_bool(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Int(n) => *n != 0,
+            Value::Float(f) => *f != 0.0,
+            Value::Str(s) => !(s.is_empty() || s.as_slice() == b"0"),
+            Value::Array(a) => !a.is_empty(),
+            Value::Object(_) | Value::Resource(_) => true,
+            Value::Ref(r) => r.borrow().to_bool(),
+        }
+    }
+
+    /// String conversion for scalars. Arrays and objects need the VM
+    /// (warnings, `__toString`) and are handled there.
+    pub fn scalar_to_bytes(&self) -> Option<Vec<u8>> {
+        Some(match self {
+            Value::Null | Value::Bool(false) => Vec::new(),
+            Value::Bool(true) => b"1".to_vec(),
+            Value::Int(n) => n.to_string().into_bytes(),
+            Value::Float(f) => format_float(*f, 14).into_bytes(),
+            Value::Str(s) => s.as_ref().clone(),
+            Value::Resource(r) => format!("Resource id #{}", r.id()).into_bytes(),
+            Value::Array(_) | Value::Object(_) => return None,
+            Value::Ref(r) => return r.borrow().scalar_to_bytes(),
+        })
+    }
+
+    /// Numeric value without diagnostics, as used by comparisons and
+    /// explicit casts: non-numeric strings yield their leading number or 0.
+    pub fn to_numeric(&self) -> Numeric {
+        match self {
+            Value::Null => Numeric::Int(0),
+            Value::Bool(b) => Numeric::Int(*b as i64),
+            Value::Int(n) => Numeric::Int(*n),
+            Value::Float(f) => Numeric::Float(*f),
+            Value::Str(s) => parse_numeric_prefix(s).map(|(n, _)| n).unwrap_or(Numeric::Int(0)),
+            Value::Array(a) => Numeric::Int(!a.is_empty() as i64),
+            Value::Object(_) => Numeric::Int(1),
+            Value::Resource(r) => Numeric::Int(r.id()),
+            Value::Ref(r) => r.borrow().to_numeric(),
+        }
+    }
+
+    /// Numeric strings out of the int range saturate, where floats wrap.
+    pub fn to_int(&self) -> i64 {
+        match (self, self.to_numeric()) {
+            (Value::Ref(r), _) => r.borrow().to_int(),
+            (_, Numeric::Int(n)) => n,
+            (Value::Str(_), Numeric::Float(f)) => float_to_int_cap(f),
+            (_, Numeric::Float(f)) => float_to_int(f),
+        }
+    }
+
+    /// Converts a value used as an array key. Returns `None` for arrays
+    /// and objects ("Illegal offset type").
+    pub fn to_array_key(&self) -> Option<ArrayKey> {
+        Some(match self {
+            Value::Null => ArrayKey::Str(Rc::new(Vec::new())),
+            Value::Bool(b) => ArrayKey::Int(*b as i64),
+            Value::Int(n) => ArrayKey::Int(*n),
+            Value::Float(f) => ArrayKey::Int(float_to_int(*f)),
+            Value::Str(s) => ArrayKey::from_bytes(s),
+            Value::Resource(r) => ArrayKey::Int(r.id()),
+            Value::Array(_) | Value::Object(_) => return None,
+            Value::Ref(r) => return r.borrow().to_array_key(),
+        })
+    }
+}
+
+/// PHP's float-to-int conversion: out-of-range values wrap modulo 2^64,
+/// NaN and infinities become 0.
+pub fn float_to_int(f: f64) -> i64 {
+    if !f.is_finite() {
+        return 0;
+    }
+    if (-9.223_372_036_854_776e18..9.223_372_036_854_776e18).contains(&f) {
+        return f as i64;
+    }
+    let two64 = 18446744073709551616.0;
+    let mut m = f.trunc() % two64;
+    if m < 0.0 {
+        m += two64;
+    }
+    m as u64 as i64
+}
+
+/// The conversion PHP uses for numeric strings: out-of-range values
+/// saturate at `PHP_INT_MAX`/`PHP_INT_MIN`, NaN and infinities become 0.
+pub fn float_to_int_cap(f: f64) -> i64 {
+    if !f.is_finite() {
+        return 0;
+    }
+    f as i64
+}
+
+fn is_ws(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c')
+}
+
+/// Parses the longest numeric prefix of `s` (after leading whitespace).
+/// The flag is true when the whole string is numeric, allowing trailing
+/// whitespace as PHP 8 does.
+pub fn parse_numeric_prefix(s: &[u8]) -> Option<(Numeric, bool)> {
+    let mut i = 0;
+    while i < s.len() && is_ws(s[i]) {
+        i += 1;
+    }
+    let start = i;
+    if i < s.len() && (s[i] == b'+' || s[i] == b'-') {
+        i += 1;
+    }
+    let int_start = i;
+    while i < s.len() && s[i].is_ascii_digit() {
+        i += 1;
+    }
+    let mut is_float = false;
+    let mut digits = i - int_start;
+    if i < s.len() && s[i] == b'.' {
+        let mut j = i + 1;
+        while j < s.len() && s[j].is_ascii_digit() {
+            j += 1;
+        }
+        if digits > 0 || j > i + 1 {
+            digits += j - i - 1;
+            is_float = true;
+            i = j;
+        }
+    }
+    if digits == 0 {
+        return None;
+    }
+    if i < s.len() && (s[i] == b'e' || s[i] == b'E') {
+        let mut j = i + 1;
+        if j < s.len() && (s[j] == b'+' || s[j] == b'-') {
+            j += 1;
+        }
+        if j < s.len() && s[j].is_ascii_digit() {
+            while j < s.len() && s[j].is_ascii_digit() {
+                j += 1;
+            }
+            is_float = true;
+            i = j;
+        }
+    }
+    let text = std::str::from_utf8(&s[start..i]).unwrap();
+    let mut end = i;
+    while end < s.len() && is_ws(s[end]) {
+        end += 1;
+    }
+    let whole = end == s.len();
+    let n = if is_float {
+        Numeric::Float(text.parse().unwrap_or(0.0))
+    } else {
+        match text.parse::<i64>() {
+            Ok(n) => Numeric::Int(n),
+            Err(_) => Numeric::Float(text.parse().unwrap_or(0.0)),
+        }
+    };
+    Some((n, whole))
+}
+
+/// Returns the number if `s` is a fully numeric string.
+pub fn numeric_string(s: &[u8]) -> Option<Numeric> {
+    match parse_numeric_prefix(s) {
+        Some((n, true)) => Some(n),
+        _ => None,
+    }
+}
+
+/// Formats a float like PHP's `zend_gcvt`. `precision` is the number of
+/// significant digits (`precision` ini, 14 by default), or -1 for the
+/// shortest representation that round-trips (`serialize_precision`).
+pub fn format_float(f: f64, precision: i32) -> String {
+    if f.is_nan() {
+        return "NAN".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+    let (ndigit, sci) = if precision < 0 {
+        (17, format!("{:e}", f.abs()))
+    } else {
+        let p = precision.max(1) as usize;
+        (p as i32, format!("{:.*e}", p - 1, f.abs()))
+    };
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let mut digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    while digits.len() > 1 && digits.ends_with('0') {
+        digits.pop();
+    }
+    let mut out = String::new();
+    if f.is_sign_negative() {
+        out.push('-');
+    }
+    if digits == "0" {
+        out.push('0');
+        return out;
+    }
+    let decpt = exp + 1;
+    if decpt < -3 || decpt > ndigit {
+        // exponential format, e.g. 1.0E+25
+        out.push_str(&digits[..1]);
+        out.push('.');
+        if digits.len() > 1 {
+            out.push_str(&digits[1..]);
+        } else {
+            out.push('0');
+        }
+        out.push('E');
+        let e = decpt - 1;
+        out.push(if e < 0 { '-' } else { '+' });
+        out.push_str(&e.abs().to_string());
+    } else if decpt <= 0 {
+        out.push_str("0.");
+        for _ in 0..-decpt {
+            out.push('0');
+        }
+        out.push_str(&digits);
+    } else {
+        let decpt = decpt as usize;
+        if digits.len() <= decpt {
+            out.push_str(&digits);
+            for _ in digits.len()..decpt {
+                out.push('0');
+            }
+        } else {
+            out.push_str(&digits[..decpt]);
+            out.push('.');
+            out.push_str(&digits[decpt..]);
+        }
+    }
+    out
+}
+
+// -- Comparison ---------------------------------------------
+
+fn compare_numeric(a: Numeric, b: Numeric) -> Ordering {
+    match (a, b) {
+        (Numeric::Int(x), Numeric::Int(y)) => x.cmp(&y),
+        (x, y) => {
+            let fx = match x { Numeric::Int(n) => n as f64, Numeric::Float(f) => f };
+            let fy = match y { Numeric::Int(n) => n as f64, Numeric::Float(f) => f };
+            fx.partial_cmp(&fy).unwrap_or(Ordering::Less)
+        }
+    }
+}
+
+/// Comparison of two strings: numerically when both are numeric strings,
+/// byte-wise otherwise.
+pub fn compare_strings(a: &[u8], b: &[u8]) -> Ordering {
+    match (numeric_string(a), numeric_string(b)) {
+        (Some(x), Some(y)) => compare_numeric(x, y),
+        _ => a.cmp(b),
+    }
+}
+
+fn number_to_bytes(n: Numeric) -> Vec<u8> {
+    n.to_value().scalar_to_bytes().unwrap()
+}
+
+/// PHP 8's `<=>`. `None` means the operands are uncomparable, which PHP
+/// treats as "greater" for `<=>` and as unequal for `==`.
+pub fn loose_compare(a: &Value, b: &Value) -> Option<Ordering> {
+    use Value::*;
+    Some(match (a, b) {
+        (Ref(r), _) => return loose_compare(&r.borrow(), b),
+        (_, Ref(r)) => return loose_compare(a, &r.borrow()),
+        (Null, Null) => Ordering::Equal,
+        (Null, Str(s)) => b"".as_slice().cmp(s.as_slice()),
+        (Str(s), Null) => s.as_slice().cmp(b"".as_slice()),
+        (Bool(_), _) | (_, Bool(_)) | (Null, _) | (_, Null) => a.to_bool().cmp(&b.to_bool()),
+        (Resource(r), _) => return loose_compare(&Int(r.id()), b),
+        (_, Resource(r)) => return loose_compare(a, &Int(r.id())),
+        (Int(_) | Float(_), Int(_) | Float(_)) => {
+            let (x, y) = (a.to_numeric(), b.to_numeric());
+            if let (Numeric::Float(f), _) | (_, Numeric::Float(f)) = (x, y)
+                && f.is_nan() {
+                    return None;
+                }
+            compare_numeric(x, y)
+        }
+        (Str(x), Str(y)) => compare_strings(x, y),
+        (Int(_) | Float(_), Str(s)) => match numeric_string(s) {
+            Some(n) => compare_numeric(a.to_numeric(), n),
+            None => number_to_bytes(a.to_numeric()).as_slice().cmp(s.as_slice()),
+        },
+        (Str(s), Int(_) | Float(_)) => match numeric_string(s) {
+            Some(n) => compare_numeric(n, b.to_numeric()),
+            None => s.as_slice().cmp(number_to_bytes(b.to_numeric()).as_slice()),
+        },
+        (Array(x), Array(y)) => {
+            if x.len() != y.len() {
+                return Some(x.len().cmp(&y.len()));
+            }
+            for (k, v) in x.iter() {
+                let w = y.get(k)?;
+                match loose_compare(v, w)? {
+                    Ordering::Equal => {}
+                    o => return Some(o),
+                }
+            }
+            Ordering::Equal
+        }
+        (Array(_), _) => Ordering::Greater,
+        (_, Array(_)) => Ordering::Less,
+        (Object(x), Object(y)) => {
+            if Rc::ptr_eq(x, y) {
+                return Some(Ordering::Equal);
+            }
+            let (x, y) = (x.borrow(), y.borrow());
+            // enum cases are only equal to themselves
+            if !Rc::ptr_eq(&x.class, &y.class) || x.class.kind == ClassKind::Enum {
+                return None;
+            }
+            return loose_compare(&Array(Rc::new(x.props.clone())), &Array(Rc::new(y.props.clone())));
+        }
+        (Object(_), _) => Ordering::Greater,
+        (_, Object(_)) => Ordering::Less,
+    })
+}
+
+pub fn loose_equals(a: &Value, b: &Value) -> bool {
+    loose_compare(a, b) == Some(Ordering::Equal)
+}
+
+pub fn strict_equals(a: &Value, b: &Value) -> bool {
+    use Value::*;
+    match (a, b) {
+        (Ref(r), _) => strict_equals(&r.borrow(), b),
+        (_, Ref(r)) => strict_equals(a, &r.borrow()),
+        (Null, Null) => true,
+        (Bool(x), Bool(y)) => x == y,
+        (Int(x), Int(y)) => x == y,
+        (Float(x), Float(y)) => x == y,
+        (Str(x), Str(y)) => x == y,
+        (Array(x), Array(y)) => {
+            x.len() == y.len()
+                && x.iter().zip(y.iter()).all(|((k1, v1), (k2, v2))| k1 == k2 && strict_equals(v1, v2))
+        }
+        (Object(x), Object(y)) => Rc::ptr_eq(x, y),
+        (Resource(x), Resource(y)) => x == y,
+        _ => false,
+    }
+}
@@ -0,0 +1,2782 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::array::{ArrayKey, OrderedMap, PhpArray};
+use crate::ast::{ClassKind, IncludeKind, Visibility};
+use crate::closure;
+use crate::constants::{self, constant_key};
+use crate::autoload::{self, Composer};
+use crate::exception;
+use crate::extension;
+use crate::fiber::{self, ActiveFiber};
+use crate::generator;
+use crate::iterator::Foreach;
+use crate::serialize;
+use crate::include;
+use crate::limits::{Limits, StackScope, Usage};
+use crate::class::{self, kind_name, visibility_name, Class, Lazy, LinkInputs, Method, Object, ObjectStore};
+use crate::ast::TypeHint;
+use crate::compiler::{self, type_members, ClassDef, ClassOperand, Function, LBase, LStep, LValue, Op, Param, Script};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::math::MtRand;
+use crate::preg::PregState;
+use crate::value::{format_float, numeric_string, parse_numeric_prefix, strict_equals, Numeric, ObjRef, Value};
+
+/// Built-in classes and interfaces that are written in PHP.
+const PRELUDE: &str = include_str!("prelude.php");
+
+#[derive(Debug)]
+pub enum VmError {
+    /// An uncatchable fatal error (E_ERROR / E_COMPILE_ERROR).
+    Fatal(String),
+    /// An engine error that PHP throws as an `Error` (or subclass).
+    Error { class: &'static str, message: String },
+    /// A thrown exception object.
+    Exception(ObjRef),
+}
+
+pub type VmResult<T> = Result<T, VmError>;
+
+impl VmError {
+    pub fn error(message: impl Into<String>) -> Self {
+        VmError::Error { class: "Error", message: message.into() }
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        VmError::Error { class: "TypeError", message: message.into() }
+    }
+
+    pub fn value_error(message: impl Into<String>) -> Self {
+        VmError::Error { class: "ValueError", message: message.into() }
+    }
+}
+
+/// A Rust function callable from PHP. It receives the callee (for
+/// `$this` and the class context) and the evaluated arguments.
+pub type NativeFn = fn(&mut Vm, &Callee, Vec<Value>) -> VmResult<Value>;
+
+/// Checks the argument count of a native function, throwing
+/// `ArgumentCountError` like PHP's internal functions do.
+pub fn expect_args(func: &str, args: &[Value], min: usize, max: usize) -> VmResult<()> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let (bound, n) = match (args.len() < min, min == max) {
+        (_, true) => ("exactly", min),
+        (true, false) => ("at least", min),
+        (false, false) => ("at most", max),
+    };
+    Err(VmError::Error {
+        class: "ArgumentCountError",
+        message: format!(
+            "{}() expects {} {} argument{}, {} given",
+            func,
+            bound,
+            n,
+            if n == 1 { "" } else { "s" },
+            args.len()
+        ),
+    })
+}
+
+/// A function together with the context it runs in. Closures are
+/// objects wrapping one.
+#[derive(Debug, Clone)]
+pub struct Callee {
+    pub func: Rc<Function>,
+    pub this: Option<ObjRef>,
+    /// Class the code belongs to (`self`, private access).
+    pub scope: Option<Rc<Class>>,
+    /// Late static binding class (`static`).
+    pub static_class: Option<Rc<Class>>,
+    /// Variables captured by a closure.
+    pub bound: Vec<(Rc<str>, Value)>,
+    /// For `__call()` and `__callStatic()`: the method that was called,
+    /// which they receive with the arguments in an array.
+    pub magic_name: Option<Rc<str>>,
+}
+
+impl Callee {
+    pub fn function(func: Rc<Function>) -> Callee {
+        Callee { func, this: None, scope: None, static_class: None, bound: Vec::new(), magic_name: None }
+    }
+
+    /// Code run in a class without an object, like constant initializers.
+    fn in_class(func: Rc<Function>, class: Rc<Class>) -> Callee {
+        Callee {
+            func,
+            this: None,
+            scope: Some(class.clone()),
+            static_class: Some(class),
+            bound: Vec::new(),
+            magic_name: None,
+        }
+    }
+}
+
+/// A call being set up: created by an Init*/New instruction, filled by
+/// Send, and started by DoCall.
+struct PendingCall {
+    /// `None` for `new` on a class without a constructor.
+    callee: Option<Callee>,
+    args: Vec<Value>,
+    named: Vec<(Rc<str>, Value)>,
+}
+
+pub(crate) struct Frame {
+    pub(crate) func: Rc<Function>,
+    pub(crate) ip: usize,
+    stack: Vec<Value>,
+    pub(crate) vars: Vars,
+    calls: Vec<PendingCall>,
+    /// Class the running code belongs to (`self`, private access).
+    pub(crate) scope: Option<Rc<Class>>,
+    /// Late static binding class (`static`).
+    pub(crate) static_class: Option<Rc<Class>>,
+    /// What each running `finally` block resumes when it completes.
+    pending: Vec<Pending>,
+    /// How an included file was loaded.
+    include: Option<IncludeKind>,
+    /// An included file, running in the variable scope of the frame below.
+    shares_scope: bool,
+    /// The running `foreach` loops, by slot.
+    pub(crate) iters: Vec<Option<Foreach>>,
+}
+
+impl Frame {
+    /// The values the frame holds: variables, operands and the arguments
+    /// of calls being set up.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Value> {
+        let calls = self.calls.iter().flat_map(|c| c.args.iter().chain(c.named.iter().map(|(_, v)| v)));
+        self.vars.values().chain(&self.stack).chain(calls)
+    }
+}
+
+/// A variable scope. Like PHP's symbol tables it keeps the variables in
+/// the order they were first set, which shutdown relies on.
+pub(crate) type Vars = OrderedMap<Rc<str>, Value>;
+
+/// How control left a `try` statement whose `finally` block runs first.
+enum Pending {
+    Normal,
+    Throw(ObjRef),
+    Return(Value),
+    Jump(usize),
+}
+
+enum Flow {
+    Next,
+    Return(Value),
+    /// A generator suspends; its frame stays on top.
+    Yield,
+}
+
+/// Where an assignment is currently writing while walking an `LValue`.
+enum Container {
+    Var(Rc<str>),
+    Cell(Rc<RefCell<Value>>),
+    Prop(ObjRef, Rc<str>),
+}
+
+enum Walk {
+    Done,
+    /// The path continues inside an object at the given step.
+    Object(ObjRef, usize),
+    /// The path continues inside a referenced value at the given step.
+    Cell(Rc<RefCell<Value>>, usize),
+}
+
+/// What an assignment path does at its end.
+enum Store<'a> {
+    Value(&'a Value),
+    /// Turns the place into a reference and hands out its cell.
+    Ref(Option<Rc<RefCell<Value>>>),
+    /// Makes the place a reference to the cell.
+    Bind(Rc<RefCell<Value>>),
+}
+
+pub struct Vm {
+    pub(crate) functions: HashMap<String, Rc<Function>>,
+    classes: HashMap<String, Rc<Class>>,
+    /// Global constants, by `constant_key`.
+    pub(crate) constants: HashMap<String, Value>,
+    pub(crate) frames: Vec<Frame>,
+    out: Box<dyn Write>,
+    /// File and line where the last error was raised.
+    pub(crate) error_pos: Option<(Rc<str>, usize)>,
+    /// Canonical paths of the files loaded so far, for `include_once`.
+    pub(crate) included: HashSet<String>,
+    pub(crate) include_path: String,
+    /// Callables registered with `spl_autoload_register()`, in call order.
+    pub(crate) autoloaders: Vec<Value>,
+    /// Classes being autoloaded, so a loader is not re-entered for them.
+    pub(crate) autoloading: HashSet<String>,
+    pub(crate) composer: Option<Composer>,
+    /// `static` variables, by function and name.
+    statics: HashMap<(*const Function, Rc<str>), Rc<RefCell<Value>>>,
+    /// The running fibers, innermost last. `frames` holds the innermost
+    /// one's call stack, each of them that of the code it switched from.
+    pub(crate) fibers: Vec<ActiveFiber>,
+    /// Magic property methods running, by object, property and method, so
+    /// they access the property itself.
+    pub(crate) magic_guards: HashSet<(usize, Rc<str>, &'static str)>,
+    /// ini settings registered by extensions, with their current values.
+    pub(crate) ini: HashMap<String, String>,
+    /// The values the ini settings had when registered, for `ini_restore()`.
+    pub(crate) ini_defaults: HashMap<String, String>,
+    /// Names of the registered extensions, in registration order.
+    pub(crate) extensions: Vec<&'static str>,
+    /// The global variables between runs: a script starts with them and
+    /// leaves its own behind.
+    pub(crate) globals: Vars,
+    pub(crate) limits: Limits,
+    /// What the running request used of the limits.
+    pub(crate) usage: Usage,
+    /// The `JSON_ERROR_*` code of the last `json_encode()` or
+    /// `json_decode()`.
+    pub(crate) json_error: i64,
+    /// Compiled `preg_*` patterns and the last error.
+    pub(crate) preg: PregState,
+    /// The generator behind `mt_rand()` and `rand()`.
+    pub(crate) mt_rand: MtRand,
+    pub(crate) objects: Rc<ObjectStore>,
+}
+
+/// Objects outliving the VM, e.g. values handed to Rust, are freed
+/// without their destructor.
+impl Drop for Vm {
+    fn drop(&mut self) {
+        self.objects.close();
+    }
+}
+
+impl Vm {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        let mut vm = Vm {
+            functions: HashMap::new(),
+            classes: HashMap::new(),
+            constants: HashMap::new(),
+            frames: Vec::new(),
+            out,
+            error_pos: None,
+            included: HashSet::new(),
+            include_path: ".".to_string(),
+            autoloaders: Vec::new(),
+            autoloading: HashSet::new(),
+            composer: None,
+            statics: HashMap::new(),
+            fibers: Vec::new(),
+            magic_guards: HashSet::new(),
+            ini: HashMap::new(),
+            ini_defaults: HashMap::new(),
+            extensions: Vec::new(),
+            globals: Vars::default(),
+            limits: Limits::default(),
+            usage: Usage::new(&Limits::default()),
+            json_error: 0,
+            preg: PregState::default(),
+            mt_rand: MtRand::default(),
+            objects: Rc::default(),
+        };
+        vm.define_builtin_constants();
+        for (name, params, f) in include::FUNCTIONS.iter().chain(autoload::FUNCTIONS).chain(constants::FUNCTIONS).chain(serialize::FUNCTIONS) {
+            vm.register_native(name, params, *f);
+        }
+        vm.load_prelude();
+        vm.register_extension(extension::core()).expect("core extension registers");
+        vm.register_extension(extension::standard()).expect("standard extension registers");
+        vm.register_extension(extension::json()).expect("json extension registers");
+        vm.register_extension(extension::pcre()).expect("pcre extension registers");
+        vm.register_extension(extension::bcmath()).expect("bcmath extension registers");
+        vm
+    }
+
+    fn register_native(&mut self, name: &str, params: &[&str], f: NativeFn) {
+        if !self.limits.allows_function(name) {
+            return;
+        }
+        self.functions.insert(name.to_ascii_lowercase(), Rc::new(Function::native(name, params, f)));
+    }
+
+    fn load_prelude(&mut self) {
+        let tokens = Lexer::new(PRELUDE).tokenize().expect("prelude lexes");
+        let ast = Parser::new(tokens).parse().expect("prelude parses");
+        let script = compiler::compile_script(&ast, "prelude.php").expect("prelude compiles");
+        if self.run_script(&script).is_err() {
+            panic!("prelude failed to run");
+        }
+        self.declare_class(&Rc::new(closure::class_def())).expect("Closure class links");
+        self.declare_class(&Rc::new(generator::class_def())).expect("Generator class links");
+        self.declare_class(&Rc::new(fiber::class_def())).expect("Fiber class links");
+        for def in exception::class_defs() {
+            self.declare_class(&Rc::new(def)).expect("exception classes link");
+        }
+    }
+
+    pub fn error_pos(&self) -> Option<(Rc<str>, usize)> {
+        self.error_pos.clone()
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.out.flush();
+    }
+
+    /// Declares the script's top-level functions and classes, then runs it
+    /// in the global variable scope.
+    pub fn run_script(&mut self, script: &Script) -> VmResult<()> {
+        self.included.insert(script.main.file.to_string());
+        let result = self.with_globals(|vm| {
+            vm.declare_hoisted(script)?;
+            let base = vm.frames.len();
+            vm.push_included(None, script.main.clone(), true);
+            vm.run(base)?;
+            vm.run_destructors()
+        });
+        if result.is_err() && self.error_pos.is_none() {
+            self.error_pos = Some((script.main.file.clone(), 0));
+        }
+        result
+    }
+
+    /// Runs `f` on top of a frame holding the global variables, unless
+    /// code is running already.
+    fn with_globals<T>(&mut self, f: impl FnOnce(&mut Self) -> VmResult<T>) -> VmResult<T> {
+        if !self.frames.is_empty() {
+            return f(self);
+        }
+        let _stack = StackScope::enter(self.limits.max_stack_size);
+        self.start_request();
+        let root = Rc::new(Function::constant(Value::Null));
+        self.push_frame(Callee::function(root), Vec::new(), Vec::new())?;
+        self.frames[0].vars = std::mem::take(&mut self.globals);
+        let result = f(self);
+        while self.frames.len() > 1 {
+            self.pop_frame();
+        }
+        self.globals = self.frames.pop().unwrap().vars;
+        result
+    }
+
+    /// Calls a function, or a `Class::method` static method, by name from
+    /// Rust. Outside of a running script it sees the global variables.
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> VmResult<Value> {
+        self.with_globals(|vm| {
+            let callee = match vm.functions.get(&name.strip_prefix('\\').unwrap_or(name).to_ascii_lowercase()) {
+                Some(func) => Callee::function(func.clone()),
+                None if !name.contains("::") => {
+                    return Err(VmError::error(format!("Call to undefined function {}()", name)));
+                }
+                None => vm.resolve_callable(&Value::from(name))?,
+            };
+            vm.call(callee, args)
+        })
+    }
+
+    /// The value of a global variable.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        let vars = match self.frames.is_empty() {
+            true => &self.globals,
+            false => &self.main_frames()[self.global_frame()].vars,
+        };
+        vars.get(name).map(Value::deref)
+    }
+
+    /// Assigns a global variable, through the reference it may be.
+    pub fn set_global(&mut self, name: &str, v: Value) {
+        let vars = match self.frames.is_empty() {
+            true => &mut self.globals,
+            false => {
+                let i = self.global_frame();
+                &mut self.main_frames_mut()[i].vars
+            }
+        };
+        match vars.get(name) {
+            Some(Value::Ref(cell)) => *cell.borrow_mut() = v,
+            _ => {
+                vars.insert(name.into(), v);
+            }
+        }
+    }
+
+    /// The cells of `static` variables and static properties.
+    pub(crate) fn static_cells(&self) -> Vec<Rc<RefCell<Value>>> {
+        let props = self.classes.values().flat_map(|c| c.static_props.iter().map(|p| p.cell.clone()));
+        self.statics.values().cloned().chain(props).collect()
+    }
+
+    /// Ends the request as PHP does: global variables holding the last
+    /// reference to an object are released, the last one set first, until
+    /// there are no more; then the objects still alive are destructed,
+    /// oldest first, and the other globals released.
+    pub fn shutdown(&mut self) -> VmResult<()> {
+        self.with_globals(|vm| {
+            loop {
+                let before = vm.frames[0].vars.len();
+                let names: Vec<Rc<str>> = vm.frames[0].vars.keys().rev().cloned().collect();
+                for name in names {
+                    if let Some(Value::Object(o)) = vm.frames[0].vars.get(&name)
+                        && Rc::strong_count(o) == 1
+                    {
+                        vm.frames[0].vars.remove(&name);
+                        vm.run_destructors()?;
+                    }
+                }
+                if vm.frames[0].vars.len() == before {
+                    break;
+                }
+            }
+            for obj in vm.objects.destructible() {
+                if !std::mem::replace(&mut obj.borrow_mut().destructed, true) {
+                    vm.destruct(obj)?;
+                }
+            }
+            Ok(())
+        })?;
+        drop(std::mem::take(&mut self.globals));
+        self.run_destructors()
+    }
+
+    /// PHP binds unconditional top-level declarations before execution.
+    /// Classes are bound once everything they extend is available; the
+    /// rest are declared when their statement runs.
+    pub(crate) fn declare_hoisted(&mut self, script: &Script) -> VmResult<()> {
+        for f in &script.functions {
+            self.declare_function(f)?;
+        }
+        let mut pending: Vec<&Rc<ClassDef>> = script.classes.iter().collect();
+        loop {
+            let before = pending.len();
+            let mut rest = Vec::new();
+            for def in pending {
+                let ready = def
+                    .parent
+                    .iter()
+                    .chain(def.interfaces.iter())
+                    .chain(def.trait_uses.iter().flat_map(|u| u.traits.iter()))
+                    .all(|n| self.classes.contains_key(&n.to_ascii_lowercase()));
+                if ready {
+                    if let Err(e) = self.declare_class(def) {
+                        self.error_pos.get_or_insert((script.main.file.clone(), def.line));
+                        return Err(e);
+                    }
+                } else {
+                    rest.push(def);
+                }
+            }
+            pending = rest;
+            if pending.len() == before {
+                return Ok(());
+            }
+        }
+    }
+
+    // -- Frames -----------------------------------------
+
+    pub(crate) fn frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    pub(crate) fn frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    pub(crate) fn pop(&mut self) -> Value {
+        self.frame_mut().stack.pop().expect("operand stack underflow")
+    }
+
+    pub(crate) fn push(&mut self, v: Value) {
+        if self.usage.metering {
+            self.usage.charge(&v, &self.limits);
+        }
+        self.frame_mut().stack.push(v);
+    }
+
+    /// The whole call stack, outermost frame first, through the fibers
+    /// running.
+    pub(crate) fn all_frames(&self) -> impl DoubleEndedIterator<Item = &Frame> {
+        self.fibers.iter().flat_map(|f| f.caller_frames()).chain(&self.frames)
+    }
+
+    /// The call stack holding the script's frame: that of the code that
+    /// switched to the outermost fiber, while fibers run.
+    fn main_frames(&self) -> &[Frame] {
+        self.fibers.first().map_or(&self.frames, |f| f.caller_frames())
+    }
+
+    fn main_frames_mut(&mut self) -> &mut [Frame] {
+        match self.fibers.first_mut() {
+            Some(f) => f.caller_frames_mut(),
+            None => &mut self.frames,
+        }
+    }
+
+    /// The innermost frame running user code.
+    fn user_frame(&self) -> Option<&Frame> {
+        self.all_frames().rev().find(|f| !f.func.builtin)
+    }
+
+    fn current_line(&self) -> usize {
+        self.user_frame().map_or(0, frame_line)
+    }
+
+    pub(crate) fn current_file(&self) -> Rc<str> {
+        match self.user_frame() {
+            Some(f) => f.func.file.clone(),
+            None => "".into(),
+        }
+    }
+
+    /// Starts a call to a user function. Calling a generator function
+    /// instead returns the generator, without running any of its code.
+    pub(crate) fn push_frame(
+        &mut self,
+        callee: Callee,
+        args: Vec<Value>,
+        named: Vec<(Rc<str>, Value)>,
+    ) -> VmResult<Option<Value>> {
+        let (args, named) = match &callee.magic_name {
+            Some(name) => {
+                let mut packed = PhpArray::new();
+                for v in args {
+                    packed.push(v).map_err(VmError::error)?;
+                }
+                for (k, v) in named {
+                    packed.insert(ArrayKey::from(&*k), v);
+                }
+                (vec![Value::from(&**name), Value::from(packed)], Vec::new())
+            }
+            None => (args, named),
+        };
+        let mut vars = self.bind_args(&callee, args, named)?;
+        let Callee { func, this, scope, static_class, bound, .. } = callee;
+        for (name, v) in bound {
+            vars.insert(name, v);
+        }
+        if let Some(this) = this {
+            vars.insert("this".into(), Value::Object(this));
+        }
+        let frame = Frame {
+            func,
+            ip: 0,
+            stack: Vec::new(),
+            vars,
+            calls: Vec::new(),
+            scope,
+            static_class,
+            pending: Vec::new(),
+            include: None,
+            shares_scope: false,
+            iters: Vec::new(),
+        };
+        if frame.func.is_generator {
+            return self.new_generator(frame).map(Some);
+        }
+        if let Some(max) = self.limits.max_call_depth
+            && self.all_frames().count() >= max
+        {
+            return Err(VmError::Fatal(format!("Maximum function nesting level of '{}' reached, aborting!", max)));
+        }
+        if self.usage.metering {
+            self.usage.charge_frame(&frame, &self.limits);
+        }
+        self.frames.push(frame);
+        Ok(None)
+    }
+
+    /// Starts a script's code, or an included file's, in the current
+    /// variable scope or in an empty one.
+    pub(crate) fn push_included(&mut self, kind: Option<IncludeKind>, main: Rc<Function>, shares_scope: bool) {
+        let (vars, scope, static_class) = match self.frames.last_mut() {
+            Some(frame) if shares_scope => {
+                (std::mem::take(&mut frame.vars), frame.scope.clone(), frame.static_class.clone())
+            }
+            _ => Default::default(),
+        };
+        self.frames.push(Frame {
+            func: main,
+            ip: 0,
+            stack: Vec::new(),
+            vars,
+            calls: Vec::new(),
+            scope,
+            static_class,
+            pending: Vec::new(),
+            include: kind,
+            shares_scope,
+            iters: Vec::new(),
+        });
+    }
+
+    /// The frame holding the global variables: the script's, or that of
+    /// a file it included, which has them while it runs. The frame below
+    /// the script only keeps them for it.
+    fn global_frame(&self) -> usize {
+        self.main_frames().iter().skip(1).take_while(|f| f.shares_scope).count()
+    }
+
+    /// Pops a frame; an included file hands the variable scope back.
+    fn pop_frame(&mut self) {
+        let frame = self.frames.pop().unwrap();
+        if frame.shares_scope {
+            self.frame_mut().vars = frame.vars;
+        }
+    }
+
+    /// The call stack as exceptions record it: innermost call first, each
+    /// with the file and line it was made from.
+    pub(crate) fn backtrace(&self) -> PhpArray {
+        let frames: Vec<&Frame> = self.all_frames().filter(|f| !f.func.builtin).collect();
+        let mut trace = PhpArray::new();
+        for (i, pair) in frames.windows(2).rev().enumerate() {
+            let (caller, frame) = (pair[0], pair[1]);
+            let mut entry = PhpArray::new();
+            entry.insert(ArrayKey::from("file"), Value::from(&*caller.func.file));
+            entry.insert(ArrayKey::from("line"), Value::Int(frame_line(caller) as i64));
+            if let Some(kind) = frame.include {
+                entry.insert(ArrayKey::from("function"), Value::from(kind.name()));
+                let mut args = PhpArray::new();
+                let _ = args.push(Value::from(&*frame.func.file));
+                entry.insert(ArrayKey::from("args"), Value::from(args));
+                trace.insert(ArrayKey::Int(i as i64), Value::from(entry));
+                continue;
+            }
+            entry.insert(ArrayKey::from("function"), Value::from(&*frame.func.name));
+            if let Some(scope) = &frame.scope {
+                let call_type = if frame.vars.contains_key("this") { "->" } else { "::" };
+                entry.insert(ArrayKey::from("class"), Value::from(&*scope.name));
+                entry.insert(ArrayKey::from("type"), Value::from(call_type));
+            }
+            let mut args = PhpArray::new();
+            for p in &frame.func.params {
+                match frame.vars.get(&p.name).map(Value::deref) {
+                    Some(Value::Array(rest)) if p.variadic => {
+                        for (_, v) in rest.iter() {
+                            let _ = args.push(v.deref());
+                        }
+                    }
+                    Some(v) => {
+                        let _ = args.push(v);
+                    }
+                    None => {}
+                }
+            }
+            entry.insert(ArrayKey::from("args"), Value::from(args));
+            trace.insert(ArrayKey::Int(i as i64), Value::from(entry));
+        }
+        trace
+    }
+
+    /// Binds the arguments of a call to the parameters of a user
+    /// function, checking their types. Optional parameters that were not
+    /// passed are left unset; the function body starts by initializing
+    /// them.
+    fn bind_args(
+        &mut self,
+        callee: &Callee,
+        args: Vec<Value>,
+        named: Vec<(Rc<str>, Value)>,
+    ) -> VmResult<Vars> {
+        let func = callee.func.clone();
+        let strict = self.caller_is_strict();
+        let fixed = func.params.len() - func.is_variadic() as usize;
+        let passed = args.len();
+        let mut vars = Vars::default();
+        let mut rest = PhpArray::new();
+        for (i, arg) in args.into_iter().enumerate() {
+            if i < fixed {
+                let v = self.bind_arg(callee, i, i + 1, arg, strict)?;
+                vars.insert(func.params[i].name.clone(), v);
+            } else if func.is_variadic() {
+                let v = self.bind_arg(callee, fixed, i + 1, arg, strict)?;
+                rest.push(v).map_err(VmError::error)?;
+            }
+        }
+        let named_count = named.len();
+        for (name, arg) in named {
+            match func.params[..fixed].iter().position(|p| p.name == name) {
+                Some(_) if vars.contains_key(&name) => {
+                    return Err(VmError::error(format!("Named parameter ${} overwrites previous argument", name)));
+                }
+                Some(i) => {
+                    let v = self.bind_arg(callee, i, i + 1, arg, strict)?;
+                    vars.insert(name, v);
+                }
+                None if func.is_variadic() => {
+                    let v = self.bind_arg(callee, fixed, fixed + 1, arg, strict)?;
+                    rest.insert(ArrayKey::from(&*name), v);
+                }
+                None => return Err(VmError::error(format!("Unknown named parameter ${}", name))),
+            }
+        }
+        let required = func.required_params();
+        if let Some(missing) = func.params[..required].iter().position(|p| !vars.contains_key(&p.name)) {
+            let name = function_name(&func, callee.scope.as_ref());
+            let message = if named_count == 0 {
+                let passed_in = match self.user_frame() {
+                    Some(_) => format!(" in {} on line {}", self.current_file(), self.current_line()),
+                    None => String::new(),
+                };
+                let bound = if required == func.params.len() && !func.is_variadic() { "exactly" } else { "at least" };
+                format!(
+                    "Too few arguments to function {}(), {} passed{} and {} {} expected",
+                    name, passed, passed_in, bound, required
+                )
+            } else {
+                format!("{}(): Argument #{} (${}) not passed", name, missing + 1, func.params[missing].name)
+            };
+            self.error_at_declaration(&func);
+            return Err(VmError::Error { class: "ArgumentCountError", message });
+        }
+        if func.is_variadic() {
+            vars.insert(func.params[fixed].name.clone(), Value::from(rest));
+        }
+        Ok(vars)
+    }
+
+    /// Binds one argument to parameter `i`; `n` is the argument number
+    /// shown in errors.
+    fn bind_arg(&mut self, callee: &Callee, i: usize, n: usize, arg: Value, strict: bool) -> VmResult<Value> {
+        let param = &callee.func.params[i];
+        if !param.by_ref {
+            return self.check_param(callee, i, n, arg.deref(), strict, true);
+        }
+        let cell = match arg {
+            Value::Ref(cell) => cell,
+            v => Rc::new(RefCell::new(v)),
+        };
+        if param.ty.is_some() {
+            let v = cell.borrow().clone();
+            let v = self.check_param(callee, i, n, v, strict, true)?;
+            *cell.borrow_mut() = v;
+        }
+        Ok(Value::Ref(cell))
+    }
+
+    /// Checks a value for parameter `i` against its declared type.
+    fn check_param(
+        &mut self,
+        callee: &Callee,
+        i: usize,
+        n: usize,
+        v: Value,
+        strict: bool,
+        from_caller: bool,
+    ) -> VmResult<Value> {
+        let param = &callee.func.params[i];
+        let Some(ty) = &param.ty else { return Ok(v) };
+        if let Some(v) = self.coerce_to_type(v.clone(), ty, callee.scope.as_ref(), callee.static_class.as_ref(), strict)? {
+            return Ok(v);
+        }
+        let called_in = match self.user_frame() {
+            Some(_) if from_caller => format!(", called in {} on line {}", self.current_file(), self.current_line()),
+            _ => String::new(),
+        };
+        let message = format!(
+            "{}(): Argument #{} (${}) must be of type {}, {} given{}",
+            function_name(&callee.func, callee.scope.as_ref()),
+            n,
+            param.name,
+            ty,
+            v.type_name(),
+            called_in
+        );
+        if from_caller {
+            self.error_at_declaration(&callee.func);
+        }
+        Err(VmError::type_error(message))
+    }
+
+    /// Errors about a call's arguments are reported at the function's
+    /// declaration.
+    fn error_at_declaration(&mut self, func: &Function) {
+        if !func.builtin {
+            self.error_pos = Some((func.file.clone(), func.lines.first().copied().unwrap_or(0)));
+        }
+    }
+
+    /// Maps named arguments of a call to a native function to positions.
+    fn native_args(&mut self, callee: &Callee, mut args: Vec<Value>, mut named: Vec<(Rc<str>, Value)>) -> VmResult<Vec<Value>> {
+        let params = &callee.func.params;
+        let position = |name: &str| params.iter().position(|p| &*p.name == name);
+        named.sort_by_key(|(name, _)| position(name));
+        for (name, v) in named {
+            match position(&name) {
+                Some(i) if i == args.len() => args.push(v),
+                Some(i) if i < args.len() => {
+                    return Err(VmError::error(format!("Named parameter ${} overwrites previous argument", name)));
+                }
+                Some(_) => {
+                    return Err(VmError::Error {
+                        class: "ArgumentCountError",
+                        message: format!(
+                            "{}(): Argument #{} (${}) not passed",
+                            function_name(&callee.func, callee.scope.as_ref()),
+                            args.len() + 1,
+                            params[args.len()].name
+                        ),
+                    });
+                }
+                None => return Err(VmError::error(format!("Unknown named parameter ${}", name))),
+            }
+        }
+        Ok(args)
+    }
+
+    /// Runs until the frame at depth `base` returns, yielding its value.
+    /// Returns early, leaving frames above `base`, when a generator
+    /// suspends.
+    pub(crate) fn run(&mut self, base: usize) -> VmResult<Value> {
+        loop {
+            let result = if self.usage.step() {
+                self.check_usage().map(|_| Flow::Next)
+            } else if self.objects.destruct_pending() {
+                self.run_destructors().map(|_| Flow::Next)
+            } else {
+                let frame = self.frame_mut();
+                let func = frame.func.clone();
+                let ip = frame.ip;
+                frame.ip += 1;
+                self.exec(&func.code[ip])
+            };
+            match result {
+                Ok(Flow::Next) => {}
+                Ok(Flow::Return(v)) => {
+                    self.pop_frame();
+                    if self.frames.len() == base {
+                        return Ok(v);
+                    }
+                    self.push(v);
+                }
+                Ok(Flow::Yield) => return Ok(Value::Null),
+                Err(e) => {
+                    match self.throwable(e) {
+                        VmError::Exception(obj) => self.throw(base, obj)?,
+                        e => {
+                            if self.error_pos.is_none() {
+                                self.error_pos = Some((self.current_file(), self.current_line()));
+                            }
+                            while self.frames.len() > base {
+                                self.pop_frame();
+                            }
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Turns an engine error into the `Error` object PHP throws for it.
+    pub(crate) fn throwable(&mut self, e: VmError) -> VmError {
+        match e {
+            VmError::Error { class, message } => match self.create_exception(class, &message) {
+                Ok(obj) => VmError::Exception(obj),
+                Err(e) => e,
+            },
+            e => e,
+        }
+    }
+
+    /// Unwinds the frames above `base` to the innermost `catch` or
+    /// `finally` block for the exception, or returns it if there is none.
+    pub(crate) fn throw(&mut self, base: usize, mut e: ObjRef) -> VmResult<()> {
+        loop {
+            match self.unwind(Pending::Throw(e)) {
+                None => return Ok(()),
+                Some(Pending::Throw(obj)) => e = obj,
+                Some(_) => unreachable!(),
+            }
+            self.pop_frame();
+            if self.frames.len() == base {
+                return Err(VmError::Exception(e));
+            }
+        }
+    }
+
+    /// Routes an exception, `return` or jump out of the current
+    /// instruction through the `try` statements around it: exceptions go
+    /// to the first `catch` clauses, everything goes through `finally`
+    /// blocks. Leaving a `finally` block abruptly discards what it would
+    /// have resumed. Returns the action if it leaves the frame.
+    fn unwind(&mut self, action: Pending) -> Option<Pending> {
+        let func = self.frame().func.clone();
+        let ip = self.frame().ip - 1;
+        for tb in &func.try_blocks {
+            if !(tb.start..tb.end).contains(&ip) {
+                continue;
+            }
+            if let Pending::Jump(target) = action
+                && (tb.start..tb.end).contains(&target)
+            {
+                break;
+            }
+            let frame = self.frame_mut();
+            if ip >= tb.finally_start {
+                // An exception thrown by a `finally` block run for another
+                // one chains it as its previous.
+                if let (Some(Pending::Throw(previous)), Pending::Throw(e)) = (frame.pending.pop(), &action) {
+                    exception::set_previous(e, previous);
+                }
+                continue;
+            }
+            frame.stack.clear();
+            frame.calls.clear();
+            match action {
+                Pending::Throw(e) if tb.has_catch && ip < tb.catch_start => {
+                    frame.stack.push(Value::Object(e));
+                    frame.ip = tb.catch_start;
+                    return None;
+                }
+                action if tb.has_finally => {
+                    frame.pending.push(action);
+                    frame.ip = tb.finally_start;
+                    return None;
+                }
+                _ => {}
+            }
+        }
+        match action {
+            Pending::Jump(target) => {
+                self.frame_mut().ip = target;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    /// Makes the frame on top return from where it is suspended, running
+    /// the `finally` blocks around it. A `yield` in one of them ends it.
+    pub(crate) fn return_from(&mut self, base: usize) -> VmResult<()> {
+        let result = match self.unwind(Pending::Return(Value::Null)) {
+            None => self.run(base).map(|_| ()),
+            Some(_) => Ok(()),
+        };
+        while self.frames.len() > base {
+            self.pop_frame();
+        }
+        result
+    }
+
+    /// Continues with an action that may pass through `finally` blocks.
+    fn leave(&mut self, action: Pending) -> Flow {
+        match self.unwind(action) {
+            Some(Pending::Return(v)) => Flow::Return(v),
+            _ => Flow::Next,
+        }
+    }
+
+    /// Calls a function from Rust and returns its result.
+    pub fn call(&mut self, callee: Callee, args: Vec<Value>) -> VmResult<Value> {
+        self.check_stack()?;
+        if let Some(native) = callee.func.native {
+            let args = match callee.func.checks_args {
+                true => self.check_native_args(&callee, args)?,
+                false => args,
+            };
+            return native(self, &callee, args);
+        }
+        let base = self.frames.len();
+        if let Some(generator) = self.push_frame(callee, args, Vec::new())? {
+            return Ok(generator);
+        }
+        self.run(base)
+    }
+
+    // -- Diagnostics ------------------------------------
+
+    pub fn warn(&mut self, message: &str) {
+        self.diagnostic("Warning", message);
+    }
+
+    pub fn deprecated(&mut self, message: &str) {
+        self.diagnostic("Deprecated", message);
+    }
+
+    pub fn diagnostic(&mut self, level: &str, message: &str) {
+        let text = format!(
+            "\n{}: {} in {} on line {}\n",
+            level,
+            message,
+            self.current_file(),
+            self.current_line()
+        );
+        self.echo(text.as_bytes());
+    }
+
+    pub fn echo(&mut self, bytes: &[u8]) {
+        let allowed = self.charge_output(bytes.len());
+        let _ = self.out.write_all(&bytes[..allowed]);
+    }
+
+    // -- Conversions ------------------------------------
+
+    pub fn stringify(&mut self, v: &Value) -> VmResult<Vec<u8>> {
+        if let Some(b) = v.scalar_to_bytes() {
+            return Ok(b);
+        }
+        match v {
+            Value::Array(_) => {
+                self.warn("Array to string conversion");
+                Ok(b"Array".to_vec())
+            }
+            Value::Object(o) => match self.object_to_string(o)? {
+                Some(s) => Ok(s),
+                None => Err(VmError::error(format!(
+                    "Object of class {} could not be converted to string",
+                    o.borrow().class.name
+                ))),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Coerces an argument for a scalar `int` or `string` parameter of a
+    /// built-in function, following the non-strict rules.
+    pub(crate) fn coerce_arg(&mut self, v: Value, ty: &str, func: &str, n: usize, param: &str) -> VmResult<Value> {
+        let mismatch = |v: &Value| {
+            VmError::type_error(format!(
+                "{}(): Argument #{} (${}) must be of type {}, {} given",
+                func,
+                n,
+                param,
+                ty,
+                v.type_name()
+            ))
+        };
+        if self.caller_is_strict() {
+            return match (ty, &v) {
+                ("int", Value::Int(_)) | ("string", Value::Str(_)) | ("bool", Value::Bool(_)) => Ok(v),
+                _ => Err(mismatch(&v)),
+            };
+        }
+        if let Value::Null = v {
+            self.deprecated(&format!(
+                "{}(): Passing null to parameter #{} (${}) of type {} is deprecated",
+                func, n, param, ty
+            ));
+        }
+        match ty {
+            "int" => match v {
+                Value::Int(_) => Ok(v),
+                Value::Null | Value::Bool(_) => Ok(Value::Int(v.to_int())),
+                Value::Float(f) => self.float_arg_to_int(f).ok_or_else(|| mismatch(&v)),
+                Value::Str(ref s) => {
+                    let n = match parse_numeric_prefix(s) {
+                        Some((n, true)) => n,
+                        Some((n, false)) => {
+                            self.warn("A non-numeric value encountered");
+                            n
+                        }
+                        None => return Err(mismatch(&v)),
+                    };
+                    match n {
+                        Numeric::Int(i) => Ok(Value::Int(i)),
+                        Numeric::Float(f) => self.float_arg_to_int(f).ok_or_else(|| mismatch(&v)),
+                    }
+                }
+                _ => Err(mismatch(&v)),
+            },
+            "bool" => match v {
+                Value::Array(_) | Value::Object(_) | Value::Resource(_) => Err(mismatch(&v)),
+                _ => Ok(Value::Bool(v.to_bool())),
+            },
+            _ => match v {
+                Value::Str(_) => Ok(v),
+                Value::Object(ref o) => match self.object_to_string(o)? {
+                    Some(s) => Ok(Value::from(s)),
+                    None => Err(mismatch(&v)),
+                },
+                Value::Array(_) | Value::Resource(_) => Err(mismatch(&v)),
+                _ => Ok(Value::from(v.scalar_to_bytes().unwrap())),
+            },
+        }
+    }
+
+    /// Float to `int` argument: fractional values are accepted with a
+    /// deprecation, values out of range are rejected.
+    fn float_arg_to_int(&mut self, f: f64) -> Option<Value> {
+        if !f.is_finite() || f < i64::MIN as f64 || f >= i64::MAX as f64 {
+            return None;
+        }
+        if f.fract() != 0.0 {
+            self.deprecated(&format!("Implicit conversion from float {} to int loses precision", format_float(f, -1)));
+        }
+        Some(Value::Int(f as i64))
+    }
+
+    /// Whether the innermost user code was declared with strict types.
+    pub(crate) fn caller_is_strict(&self) -> bool {
+        self.user_frame().is_some_and(|f| f.func.strict)
+    }
+
+    /// Checks a value against a declared type. Scalars are coerced the way
+    /// PHP does unless `strict`, where only int to float widening applies.
+    /// `None` if the value does not fit.
+    pub(crate) fn coerce_to_type(
+        &mut self,
+        v: Value,
+        ty: &TypeHint,
+        scope: Option<&Rc<Class>>,
+        static_class: Option<&Rc<Class>>,
+        strict: bool,
+    ) -> VmResult<Option<Value>> {
+        if self.has_type(&v, ty, scope, static_class) {
+            return Ok(Some(v));
+        }
+        let members = type_members(ty);
+        let scalars: Vec<&str> =
+            ["int", "float", "string", "bool"].into_iter().filter(|s| members.iter().any(|m| m == s)).collect();
+        if let Value::Int(i) = v
+            && scalars.contains(&"float")
+        {
+            return Ok(Some(Value::Float(i as f64)));
+        }
+        if strict || scalars.is_empty() {
+            return Ok(None);
+        }
+        if let Value::Object(o) = &v {
+            if scalars.contains(&"string") && let Some(s) = self.object_to_string(o)? {
+                return Ok(Some(Value::from(s)));
+            }
+            return Ok(None);
+        }
+        Ok(self.coerce_scalar(&v, &scalars))
+    }
+
+    pub(crate) fn has_type(&mut self, v: &Value, ty: &TypeHint, scope: Option<&Rc<Class>>, static_class: Option<&Rc<Class>>) -> bool {
+        let instance_of = |class: Option<&Rc<Class>>| match (v, class) {
+            (Value::Object(o), Some(c)) => o.borrow().class.is_subclass_of(&c.name),
+            _ => false,
+        };
+        match ty {
+            TypeHint::Nullable(t) => matches!(v, Value::Null) || self.has_type(v, t, scope, static_class),
+            TypeHint::Union(ts) => ts.iter().any(|t| self.has_type(v, t, scope, static_class)),
+            TypeHint::Intersection(ts) => ts.iter().all(|t| self.has_type(v, t, scope, static_class)),
+            TypeHint::Named(name) => match name.to_ascii_lowercase().as_str() {
+                "mixed" => true,
+                "null" | "void" => matches!(v, Value::Null),
+                "int" => matches!(v, Value::Int(_)),
+                "float" => matches!(v, Value::Float(_)),
+                "string" => matches!(v, Value::Str(_)),
+                "bool" => matches!(v, Value::Bool(_)),
+                "false" => matches!(v, Value::Bool(false)),
+                "true" => matches!(v, Value::Bool(true)),
+                "array" => matches!(v, Value::Array(_)),
+                "object" => matches!(v, Value::Object(_)),
+                "iterable" => match v {
+                    Value::Array(_) => true,
+                    Value::Object(o) => o.borrow().class.is_subclass_of("Traversable"),
+                    _ => false,
+                },
+                "callable" => self.resolve_callable(v).is_ok(),
+                "never" => false,
+                "self" => instance_of(scope),
+                "parent" => instance_of(scope.and_then(|s| s.parent.as_ref())),
+                "static" => instance_of(static_class),
+                _ => matches!(v, Value::Object(o) if o.borrow().class.is_subclass_of(name)),
+            },
+        }
+    }
+
+    /// Non-strict coercion of a scalar to the first of `targets` (in
+    /// int, float, string, bool order) that accepts it.
+    fn coerce_scalar(&mut self, v: &Value, targets: &[&str]) -> Option<Value> {
+        let has = |t: &str| targets.contains(&t);
+        match v {
+            Value::Str(s) => {
+                if (has("int") || has("float"))
+                    && let Some((n, whole)) = parse_numeric_prefix(s)
+                {
+                    if !whole {
+                        self.warn("A non-numeric value encountered");
+                    }
+                    return match n {
+                        Numeric::Int(i) if has("int") => Some(Value::Int(i)),
+                        Numeric::Int(i) => Some(Value::Float(i as f64)),
+                        Numeric::Float(f) if has("float") => Some(Value::Float(f)),
+                        Numeric::Float(f) => self.float_arg_to_int(f),
+                    };
+                }
+                has("bool").then(|| Value::Bool(v.to_bool()))
+            }
+            Value::Int(i) if has("string") => Some(Value::from(i.to_string().as_str())),
+            Value::Float(f) => {
+                if has("int")
+                    && (f.fract() == 0.0 || !has("string"))
+                    && let Some(v) = self.float_arg_to_int(*f)
+                {
+                    return Some(v);
+                }
+                if has("string") {
+                    return Some(Value::from(v.scalar_to_bytes().unwrap()));
+                }
+                has("bool").then(|| Value::Bool(v.to_bool()))
+            }
+            Value::Int(_) => has("bool").then(|| Value::Bool(v.to_bool())),
+            Value::Bool(b) => {
+                if has("int") {
+                    Some(Value::Int(*b as i64))
+                } else if has("float") {
+                    Some(Value::Float(*b as i64 as f64))
+                } else {
+                    has("string").then(|| Value::from(v.scalar_to_bytes().unwrap()))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks a returned value against the running function's return type.
+    fn check_return(&mut self, v: Value) -> VmResult<Value> {
+        let frame = self.frame();
+        let func = frame.func.clone();
+        let (scope, static_class) = (frame.scope.clone(), frame.static_class.clone());
+        // the return the compiler appends at the end of every body
+        let implicit = frame.ip == func.code.len();
+        let ty = func.return_type.as_ref().unwrap();
+        let members = type_members(ty);
+        if members == ["void"] {
+            return Ok(v);
+        }
+        let name = function_name(&func, scope.as_ref());
+        if members == ["never"] {
+            return Err(VmError::type_error(format!("{}(): never-returning function must not implicitly return", name)));
+        }
+        if let Some(v) = self.coerce_to_type(v.clone(), ty, scope.as_ref(), static_class.as_ref(), func.strict)? {
+            return Ok(v);
+        }
+        let given = if implicit { "none".to_string() } else { v.type_name() };
+        Err(VmError::type_error(format!("{}(): Return value must be of type {}, {} returned", name, ty, given)))
+    }
+
+    // -- Instructions -----------------------------------
+
+    fn exec(&mut self, op: &Op) -> VmResult<Flow> {
+        match op {
+            Op::Const(v) => self.push(v.clone()),
+            Op::LoadVar(name) => {
+                let v = self.load_var(name)?;
+                self.push(v);
+            }
+            Op::Assign(lv) => {
+                let value = self.pop();
+                self.assign(lv, value.clone())?;
+                self.push(value);
+            }
+            Op::AssignRef(target, source) => {
+                let cell = self.make_ref(source)?;
+                self.store(target, &mut Store::Bind(cell.clone()))?;
+                let v = cell.borrow().clone();
+                self.push(v);
+            }
+            Op::BindGlobal(name) => {
+                let globals = self.global_frame();
+                let cell = ref_cell(self.main_frames_mut()[globals].vars.entry_or(name.clone(), Value::Null));
+                self.frame_mut().vars.insert(name.clone(), Value::Ref(cell));
+            }
+            Op::BindStatic(name, skip) => {
+                let key = (Rc::as_ptr(&self.frame().func), name.clone());
+                let cell = match self.statics.get(&key).cloned() {
+                    Some(cell) => {
+                        self.frame_mut().ip = *skip;
+                        cell
+                    }
+                    None => self.statics.entry(key).or_default().clone(),
+                };
+                self.frame_mut().vars.insert(name.clone(), Value::Ref(cell));
+            }
+            Op::Unset(lv) => self.unset(lv)?,
+            Op::Isset(lv) => {
+                let v = self.fetch_quiet(lv, false)?;
+                self.push(Value::Bool(v.is_some_and(|v| !matches!(v, Value::Null))));
+            }
+            Op::Empty(lv) => {
+                let v = self.fetch_quiet(lv, true)?;
+                self.push(Value::Bool(!v.is_some_and(|v| v.to_bool())));
+            }
+            Op::Pop => {
+                self.pop();
+            }
+            Op::Binary(op) => {
+                let b = self.pop();
+                let a = self.pop();
+                let v = self.binary_op(*op, a, b)?;
+                self.push(v);
+            }
+            Op::Unary(op) => {
+                let a = self.pop();
+                let v = self.unary_op(*op, a)?;
+                self.push(v);
+            }
+            Op::Jump(t) => self.frame_mut().ip = *t,
+            Op::JumpIfFalse(t) => {
+                if !self.pop().to_bool() {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::JumpIfTrue(t) => {
+                if self.pop().to_bool() {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::Echo => {
+                let v = self.pop();
+                let bytes = self.stringify(&v)?;
+                self.echo(&bytes);
+            }
+
+            Op::NewArray => self.push(Value::Array(Rc::new(PhpArray::new()))),
+            Op::ArrayPush => {
+                let v = self.pop();
+                let Some(Value::Array(arr)) = self.frame_mut().stack.last_mut() else { unreachable!() };
+                Rc::make_mut(arr).push(v).map_err(VmError::error)?;
+            }
+            Op::ArrayInsert => {
+                let v = self.pop();
+                let k = self.pop();
+                let key = self.array_key(&k, "array")?;
+                let Some(Value::Array(arr)) = self.frame_mut().stack.last_mut() else { unreachable!() };
+                Rc::make_mut(arr).insert(key, v);
+            }
+            Op::ArrayUnpack => {
+                let v = self.pop();
+                let Some(pairs) = self.unpack_pairs(v, "array")? else {
+                    return Err(VmError::error("Only arrays and Traversables can be unpacked"));
+                };
+                let Some(Value::Array(arr)) = self.frame_mut().stack.last_mut() else { unreachable!() };
+                let arr = Rc::make_mut(arr);
+                for (k, v) in pairs {
+                    match k {
+                        ArrayKey::Int(_) => arr.push(v).map_err(VmError::error)?,
+                        ArrayKey::Str(_) => arr.insert(k, v),
+                    }
+                }
+            }
+            Op::ArrayRef(lv, has_key) => {
+                let cell = self.make_ref(lv)?;
+                let key = match has_key {
+                    true => {
+                        let k = self.pop();
+                        Some(self.array_key(&k, "array")?)
+                    }
+                    false => None,
+                };
+                let Some(Value::Array(arr)) = self.frame_mut().stack.last_mut() else { unreachable!() };
+                let arr = Rc::make_mut(arr);
+                match key {
+                    Some(k) => arr.insert(k, Value::Ref(cell)),
+                    None => arr.push(Value::Ref(cell)).map_err(VmError::error)?,
+                }
+            }
+            Op::FetchListDim => {
+                let key = self.pop();
+                let v = match self.pop() {
+                    container @ Value::Array(_) => self.fetch_dim(&container, &key)?,
+                    _ => Value::Null,
+                };
+                self.push(v);
+            }
+            Op::FetchDim => {
+                let key = self.pop();
+                let container = self.pop();
+                let v = self.fetch_dim(&container, &key)?;
+                self.push(v);
+            }
+            Op::FetchProp(name) => {
+                let obj = self.pop();
+                let v = self.fetch_prop(&obj, name)?;
+                self.push(v);
+            }
+            Op::FetchStaticProp(class, name) => {
+                let cell = self.static_prop_cell(class, name)?;
+                let v = cell.borrow().clone();
+                self.push(v);
+            }
+            Op::FetchClassConst(class, name) => {
+                let v = self.class_constant(class, name)?;
+                self.push(v);
+            }
+            Op::FetchConst(name, fallback) => {
+                let v = match self.constants.get(&constant_key(name)) {
+                    Some(v) => v.clone(),
+                    None => match fallback.as_ref().and_then(|f| self.constants.get(&constant_key(f))) {
+                        Some(v) => v.clone(),
+                        None => return Err(VmError::error(format!("Undefined constant \"{}\"", name))),
+                    },
+                };
+                self.push(v);
+            }
+
+            Op::InitCall(name, fallback) => {
+                let func = match self.lookup_function(name) {
+                    Ok(f) => f,
+                    Err(e) => match fallback.as_ref().and_then(|g| self.lookup_function(g).ok()) {
+                        Some(f) => f,
+                        None => return Err(e),
+                    },
+                };
+                self.begin_call(Some(Callee::function(func)));
+            }
+            Op::InitMethodCall(name) => {
+                let obj = self.pop();
+                let callee = self.method_callee_of(obj, name)?;
+                self.begin_call(Some(callee));
+            }
+            Op::InitStaticCall(class_op, name) => {
+                let forwarding = matches!(class_op, ClassOperand::Named(n)
+                    if matches!(n.to_ascii_lowercase().as_str(), "self" | "parent" | "static"));
+                let class = self.resolve_class_operand(class_op)?;
+                let callee = self.static_callee(class, name, forwarding)?;
+                self.begin_call(Some(callee));
+            }
+            Op::InitDynamicCall => {
+                let v = self.pop();
+                let callee = self.resolve_callable(&v)?;
+                self.begin_call(Some(callee));
+            }
+            Op::New(class) => {
+                let class = self.resolve_class_operand(class)?;
+                let obj = self.instantiate(&class)?;
+                let callee = self.constructor_callee(&obj)?;
+                self.push(Value::Object(obj));
+                self.begin_call(callee);
+            }
+            Op::InstanceOf(class) => {
+                // unknown classes are not an error, and not autoloaded
+                let name: Rc<str> = match class {
+                    ClassOperand::Named(n) if ["self", "static", "parent"].iter().any(|k| n.eq_ignore_ascii_case(k)) => {
+                        self.resolve_class(n)?.name.clone()
+                    }
+                    ClassOperand::Named(n) => n.clone(),
+                    ClassOperand::Dynamic => match self.pop() {
+                        Value::Object(o) => o.borrow().class.name.clone(),
+                        Value::Str(s) => String::from_utf8_lossy(&s).trim_start_matches('\\').into(),
+                        _ => return Err(VmError::error("Class name must be a valid object or a string")),
+                    },
+                };
+                let v = match self.pop() {
+                    Value::Object(o) => o.borrow().class.is_subclass_of(&name),
+                    _ => false,
+                };
+                self.push(Value::Bool(v));
+            }
+            Op::Send(name) => {
+                let v = self.pop();
+                if self.pending_param(name.as_deref()).is_some_and(|p| p.by_ref && !p.prefer_ref) {
+                    self.diagnostic("Notice", "Only variables should be passed by reference");
+                }
+                self.send_arg(name.clone(), v)?;
+            }
+            Op::SendRef(lv, name) => {
+                let cell = self.make_ref(lv)?;
+                self.send_arg(name.clone(), Value::Ref(cell))?;
+            }
+            Op::SendUnpack => {
+                let v = self.pop();
+                let Some(pairs) = self.unpack_pairs(v, "argument")? else {
+                    return Err(VmError::type_error("Only arrays and Traversables can be unpacked"));
+                };
+                for (k, v) in pairs {
+                    match k {
+                        ArrayKey::Int(_) if !self.frame().calls.last().unwrap().named.is_empty() => {
+                            return Err(VmError::error(
+                                "Cannot use positional argument after named argument during unpacking",
+                            ));
+                        }
+                        ArrayKey::Int(_) => self.send_arg(None, v)?,
+                        ArrayKey::Str(name) => {
+                            let name = String::from_utf8_lossy(&name);
+                            self.send_arg(Some(name.as_ref().into()), v)?;
+                        }
+                    }
+                }
+            }
+            Op::JumpIfArgByVal(name, t) => {
+                if !self.pending_param(name.as_deref()).is_some_and(|p| p.by_ref) {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::DoCall => {
+                let call = self.frame_mut().calls.pop().unwrap();
+                match call.callee {
+                    Some(callee) if callee.func.native.is_some() => {
+                        let args = self.native_args(&callee, call.args, call.named)?;
+                        let v = self.call(callee, args)?;
+                        self.push(v);
+                    }
+                    Some(callee) => {
+                        if let Some(generator) = self.push_frame(callee, call.args, call.named)? {
+                            self.push(generator);
+                        }
+                    }
+                    None => self.push(Value::Null),
+                }
+            }
+            Op::ClosureFromCall => {
+                let call = self.frame_mut().calls.pop().unwrap();
+                let callee = call.callee.unwrap();
+                let is_static = callee.this.is_none() && callee.scope.is_some();
+                let closure = self.new_closure(callee, is_static)?;
+                self.push(closure);
+            }
+            Op::Closure(def) => {
+                let v = self.create_closure(def)?;
+                self.push(v);
+            }
+            Op::Include(kind) => {
+                let path = self.pop();
+                let path = String::from_utf8_lossy(&self.stringify(&path)?).into_owned();
+                if let Some(v) = self.include(*kind, &path)? {
+                    self.push(v);
+                }
+            }
+            Op::Throw => match self.pop().deref() {
+                Value::Object(o) if o.borrow().class.is_subclass_of("Throwable") => {
+                    return Err(VmError::Exception(o));
+                }
+                _ => return Err(VmError::error("Can only throw objects")),
+            },
+            Op::Catch(classes, t) => {
+                let Some(Value::Object(e)) = self.frame().stack.last() else { unreachable!() };
+                let class = e.borrow().class.clone();
+                if !classes.iter().any(|c| class.is_subclass_of(c)) {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::EnterFinally(t) => {
+                let frame = self.frame_mut();
+                frame.pending.push(Pending::Normal);
+                frame.ip = *t;
+            }
+            Op::FinallyEnd => {
+                let action = match self.frame().pending.last().unwrap() {
+                    Pending::Normal => None,
+                    Pending::Throw(e) => return Err(VmError::Exception(e.clone())),
+                    Pending::Return(v) => Some(Pending::Return(v.clone())),
+                    Pending::Jump(t) => Some(Pending::Jump(*t)),
+                };
+                match action {
+                    None => {
+                        self.frame_mut().pending.pop();
+                    }
+                    Some(action) => return Ok(self.leave(action)),
+                }
+            }
+            Op::Leave(t) => return Ok(self.leave(Pending::Jump(*t))),
+            Op::Return => {
+                let v = self.pop();
+                let func = &self.frame().func;
+                let v = match func.return_type {
+                    Some(_) if !func.is_generator => self.check_return(v)?,
+                    _ => v,
+                };
+                if self.frame().func.try_blocks.iter().any(|t| t.has_finally) {
+                    return Ok(self.leave(Pending::Return(v)));
+                }
+                return Ok(Flow::Return(v));
+            }
+            Op::JumpIfPassed(name, t) => {
+                if self.frame().vars.contains_key(name) {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::InitParam(i) => {
+                let v = self.pop();
+                let frame = self.frame();
+                let callee = Callee {
+                    func: frame.func.clone(),
+                    this: None,
+                    scope: frame.scope.clone(),
+                    static_class: frame.static_class.clone(),
+                    bound: Vec::new(),
+                    magic_name: None,
+                };
+                let v = self.check_param(&callee, *i, *i + 1, v, callee.func.strict, false)?;
+                let param = &callee.func.params[*i];
+                let v = if param.by_ref { Value::Ref(Rc::new(RefCell::new(v))) } else { v };
+                self.frame_mut().vars.insert(param.name.clone(), v);
+            }
+
+            Op::DeclareFunction(f) => self.declare_function(f)?,
+            Op::DeclareClass(def) => self.declare_class(def)?,
+            Op::Yield(_) | Op::YieldFrom => return Ok(Flow::Yield),
+            Op::Clone => {
+                let v = self.pop();
+                let v = self.clone_object(v)?;
+                self.push(v);
+            }
+            Op::IterInit(slot, by_ref, t) => {
+                let v = self.pop();
+                if !self.foreach_init(*slot, v, *by_ref)? {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::IterInitRef(slot, lv, t) => {
+                let cell = self.make_ref(lv)?;
+                if !self.foreach_init_ref(*slot, cell)? {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::IterFetch(slot, t, want_key) => {
+                if !self.foreach_fetch(*slot, *want_key)? {
+                    self.frame_mut().ip = *t;
+                }
+            }
+            Op::IterValue(slot) => {
+                let v = self.foreach_value(*slot);
+                self.push(v);
+            }
+            Op::IterKey(slot) => {
+                let k = self.foreach_key(*slot);
+                self.push(k);
+            }
+            Op::IterBind(slot, lv) => {
+                let cell = self.foreach_cell(*slot);
+                self.store(lv, &mut Store::Bind(cell))?;
+            }
+            Op::IterFree(slot) => {
+                if let Some(f) = self.frame_mut().iters.get_mut(*slot) {
+                    *f = None;
+                }
+            }
+            Op::DeclareConst(name) => {
+                let v = self.pop();
+                self.define_constant(name, v);
+            }
+
+            Op::NewEnumCase(name) => {
+                let v = self.new_enum_case(name)?;
+                self.push(v);
+            }
+            Op::EnumCases => {
+                let class = self.frame().scope.clone().unwrap();
+                let mut cases = PhpArray::new();
+                for case in self.enum_cases(&class)? {
+                    cases.push(case).map_err(VmError::error)?;
+                }
+                self.push(Value::from(cases));
+            }
+            Op::EnumFrom(try_from) => {
+                let v = self.pop();
+                let v = self.enum_from(v, *try_from)?;
+                self.push(v);
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn begin_call(&mut self, callee: Option<Callee>) {
+        self.frame_mut().calls.push(PendingCall { callee, args: Vec::new(), named: Vec::new() });
+    }
+
+    /// The parameter the next argument of the call being set up binds to.
+    fn pending_param(&self, name: Option<&str>) -> Option<&Param> {
+        let call = self.frame().calls.last()?;
+        let func = &call.callee.as_ref()?.func;
+        match name {
+            Some(name) => func.params.iter().find(|p| &*p.name == name),
+            None => func.params.get(call.args.len()).or_else(|| func.params.last().filter(|p| p.variadic)),
+        }
+    }
+
+    fn send_arg(&mut self, name: Option<Rc<str>>, v: Value) -> VmResult<()> {
+        let call = self.frame_mut().calls.last_mut().unwrap();
+        match name {
+            Some(name) => {
+                if call.named.iter().any(|(n, _)| *n == name) {
+                    return Err(VmError::error(format!("Named parameter ${} overwrites previous argument", name)));
+                }
+                call.named.push((name, v));
+            }
+            None => call.args.push(v),
+        }
+        Ok(())
+    }
+
+    // -- Variables --------------------------------------
+
+    fn load_var(&mut self, name: &Rc<str>) -> VmResult<Value> {
+        if let Some(v) = self.frame().vars.get(name) {
+            return Ok(v.deref());
+        }
+        if &**name == "this" {
+            return Err(VmError::error("Using $this when not in object context"));
+        }
+        self.warn(&format!("Undefined variable ${}", name));
+        Ok(Value::Null)
+    }
+
+    fn array_key(&mut self, key: &Value, container: &str) -> VmResult<ArrayKey> {
+        key.to_array_key().ok_or_else(|| {
+            VmError::type_error(format!("Cannot access offset of type {} on {}", key.type_name(), container))
+        })
+    }
+
+    fn fetch_dim(&mut self, container: &Value, key: &Value) -> VmResult<Value> {
+        match container {
+            Value::Array(arr) => {
+                let k = self.array_key(key, "array")?;
+                match arr.get(&k) {
+                    Some(v) => Ok(v.deref()),
+                    None => {
+                        self.warn(&undefined_key_message(&k));
+                        Ok(Value::Null)
+                    }
+                }
+            }
+            Value::Str(s) => {
+                let n = match key {
+                    Value::Int(n) => *n,
+                    Value::Str(k) => match numeric_string(k) {
+                        Some(Numeric::Int(n)) => n,
+                        _ => {
+                            return Err(VmError::type_error(format!(
+                                "Cannot access offset of type {} on string",
+                                key.type_name()
+                            )));
+                        }
+                    },
+                    other => other.to_int(),
+                };
+                let i = if n < 0 { n + s.len() as i64 } else { n };
+                if i < 0 || i >= s.len() as i64 {
+                    self.warn(&format!("Uninitialized string offset {}", n));
+                    return Ok(Value::from(""));
+                }
+                Ok(Value::from(vec![s[i as usize]]))
+            }
+            Value::Object(o) => Err(VmError::error(format!(
+                "Cannot use object of type {} as array",
+                o.borrow().class.name
+            ))),
+            other => {
+                self.warn(&format!(
+                    "Trying to access array offset on value of type {}",
+                    other.type_name()
+                ));
+                Ok(Value::Null)
+            }
+        }
+    }
+
+    // -- Assignment -------------------------------------
+
+    fn assign(&mut self, lv: &LValue, value: Value) -> VmResult<()> {
+        self.store(lv, &mut Store::Value(&value))
+    }
+
+    /// The reference cell of a place, which becomes a reference.
+    fn make_ref(&mut self, lv: &LValue) -> VmResult<Rc<RefCell<Value>>> {
+        let mut store = Store::Ref(None);
+        self.store(lv, &mut store)?;
+        let Store::Ref(Some(cell)) = store else { unreachable!("reference path ended without a place") };
+        Ok(cell)
+    }
+
+    /// Takes the keys of a place's `Dim` steps off the stack.
+    fn pop_keys(&mut self, lv: &LValue) -> std::vec::IntoIter<Value> {
+        let dims = lv.steps.iter().filter(|s| matches!(s, LStep::Dim)).count();
+        let at = self.frame().stack.len() - dims;
+        self.frame_mut().stack.split_off(at).into_iter()
+    }
+
+    /// `unset()` of a place. Missing variables, keys and properties on
+    /// the way are left alone rather than created.
+    fn unset(&mut self, lv: &LValue) -> VmResult<()> {
+        let mut keys = self.pop_keys(lv);
+        let mut container = match &lv.base {
+            LBase::Var(name) if lv.steps.is_empty() => {
+                self.frame_mut().vars.remove(name);
+                return Ok(());
+            }
+            LBase::Var(name) => match self.frame().vars.get(name) {
+                Some(Value::Ref(cell)) => Container::Cell(cell.clone()),
+                Some(_) => Container::Var(name.clone()),
+                None => return Ok(()),
+            },
+            LBase::StaticProp(class, name) if lv.steps.is_empty() => {
+                let class = self.resolve_class_operand(class)?;
+                return Err(VmError::error(format!("Attempt to unset static property {}::${}", class.name, name)));
+            }
+            LBase::StaticProp(class, name) => Container::Cell(self.static_prop_cell(class, name)?),
+            LBase::Stack => Container::Cell(Rc::new(RefCell::new(self.pop()))),
+        };
+        let mut step = 0;
+        loop {
+            let steps = &lv.steps[step..];
+            let result = match &container {
+                Container::Var(name) => match self.frame_mut().vars.get_mut(name) {
+                    Some(slot) => unset_walk(slot, steps, &mut keys)?,
+                    None => Walk::Done,
+                },
+                Container::Cell(cell) => unset_walk(&mut cell.borrow_mut(), steps, &mut keys)?,
+                Container::Prop(obj, name) => match obj.borrow_mut().props.get_mut(&ArrayKey::from(&**name)) {
+                    Some(slot) => unset_walk(slot, steps, &mut keys)?,
+                    None => Walk::Done,
+                },
+            };
+            match result {
+                Walk::Done => return Ok(()),
+                Walk::Cell(cell, s) => {
+                    container = Container::Cell(cell);
+                    step += s;
+                }
+                Walk::Object(obj, s) => {
+                    step += s;
+                    let LStep::Prop(name) = &lv.steps[step] else {
+                        let class = obj.borrow().class.name.clone();
+                        return Err(VmError::error(format!("Cannot use object of type {} as array", class)));
+                    };
+                    if step + 1 == lv.steps.len() {
+                        if self.is_magic_prop(&obj, name)
+                            && self.call_magic_prop(&obj, "__unset", name, None)?.is_some()
+                        {
+                            return Ok(());
+                        }
+                        self.check_prop_access(&obj, name)?;
+                        obj.borrow_mut().props.remove(&ArrayKey::from(&**name));
+                        return Ok(());
+                    }
+                    self.check_prop_access(&obj, name)?;
+                    container = Container::Prop(obj, name.clone());
+                    step += 1;
+                }
+            }
+        }
+    }
+
+    /// Reads a place for `isset()` and `empty()`: `None` when something on
+    /// the way is missing, which is not warned about.
+    /// Magic properties are checked with `__isset()`; `__get()` is only
+    /// called when the value is needed.
+    fn fetch_quiet(&mut self, lv: &LValue, need_value: bool) -> VmResult<Option<Value>> {
+        let mut keys = self.pop_keys(lv);
+        let mut v = match &lv.base {
+            LBase::Var(name) => match self.frame().vars.get(name) {
+                Some(v) => v.deref(),
+                None => return Ok(None),
+            },
+            LBase::StaticProp(class, name) => match self.static_prop_cell(class, name) {
+                Ok(cell) => cell.borrow().clone(),
+                Err(VmError::Error { .. }) => return Ok(None),
+                Err(e) => return Err(e),
+            },
+            LBase::Stack => self.pop(),
+        };
+        for (i, step) in lv.steps.iter().enumerate() {
+            v = match (step, v) {
+                (LStep::Dim, container) => {
+                    let key = keys.next().unwrap();
+                    match container {
+                        Value::Array(arr) => {
+                            let k = key.to_array_key().ok_or_else(|| {
+                                VmError::type_error(format!(
+                                    "Cannot access offset of type {} in isset or empty",
+                                    key.type_name()
+                                ))
+                            })?;
+                            match arr.get(&k) {
+                                Some(v) => v.deref(),
+                                None => return Ok(None),
+                            }
+                        }
+                        Value::Str(s) => {
+                            let n = match &key {
+                                Value::Int(n) => *n,
+                                Value::Float(_) | Value::Bool(_) => key.to_int(),
+                                Value::Str(k) => match numeric_string(k) {
+                                    Some(Numeric::Int(n)) => n,
+                                    _ => return Ok(None),
+                                },
+                                _ => return Ok(None),
+                            };
+                            let i = if n < 0 { n + s.len() as i64 } else { n };
+                            match s.get(usize::try_from(i).unwrap_or(usize::MAX)) {
+                                Some(&b) => Value::from(vec![b]),
+                                None => return Ok(None),
+                            }
+                        }
+                        Value::Object(o) => {
+                            let class = o.borrow().class.name.clone();
+                            return Err(VmError::error(format!("Cannot use object of type {} as array", class)));
+                        }
+                        _ => return Ok(None),
+                    }
+                }
+                (LStep::Prop(name), Value::Object(o)) if self.is_magic_prop(&o, name) => {
+                    match self.call_magic_prop(&o, "__isset", name, None)? {
+                        Some(set) if set.to_bool() => {}
+                        _ => return Ok(None),
+                    }
+                    if i + 1 == lv.steps.len() && !need_value {
+                        return Ok(Some(Value::Bool(true)));
+                    }
+                    match self.call_magic_prop(&o, "__get", name, None)? {
+                        Some(v) => v,
+                        None => return Ok(None),
+                    }
+                }
+                (LStep::Prop(name), Value::Object(o)) => {
+                    if self.check_prop_access(&o, name).is_err() {
+                        return Ok(None);
+                    }
+                    let found = o.borrow().props.get(&ArrayKey::from(&**name)).map(Value::deref);
+                    match found {
+                        Some(v) => v,
+                        None => return Ok(None),
+                    }
+                }
+                (LStep::Prop(_), _) => return Ok(None),
+                (LStep::Append, _) => unreachable!("[] cannot be read"),
+            };
+        }
+        Ok(Some(v))
+    }
+
+    fn store(&mut self, lv: &LValue, store: &mut Store) -> VmResult<()> {
+        let mut keys = self.pop_keys(lv);
+
+        let mut step = 0;
+        let mut container = match &lv.base {
+            LBase::Var(name) => Container::Var(name.clone()),
+            LBase::StaticProp(class, name) => Container::Cell(self.static_prop_cell(class, name)?),
+            LBase::Stack => match self.pop() {
+                Value::Object(o) => {
+                    let (c, s) = self.enter_object(o, &lv.steps, 0, store)?;
+                    match c {
+                        Some(c) => {
+                            step = s;
+                            c
+                        }
+                        None => return Ok(()),
+                    }
+                }
+                other => {
+                    let LStep::Prop(name) = &lv.steps[0] else { unreachable!() };
+                    return Err(VmError::error(format!(
+                        "Attempt to assign property \"{}\" on {}",
+                        name,
+                        other.type_name()
+                    )));
+                }
+            },
+        };
+
+        loop {
+            let steps = &lv.steps[step..];
+            if let (Container::Var(name), Store::Bind(cell), true) = (&container, &*store, steps.is_empty()) {
+                self.frame_mut().vars.insert(name.clone(), Value::Ref(cell.clone()));
+                return Ok(());
+            }
+            // writes to a reference variable go to the referenced value
+            if let Container::Var(name) = &container
+                && let Some(Value::Ref(cell)) = self.frame().vars.get(name)
+            {
+                container = Container::Cell(cell.clone());
+            }
+            match (&container, &mut *store, steps.is_empty()) {
+                (Container::Cell(cell), Store::Ref(out), true) => {
+                    *out = Some(cell.clone());
+                    return Ok(());
+                }
+                (Container::Cell(_), Store::Bind(_), true) => {
+                    let LBase::StaticProp(_, name) = &lv.base else { unreachable!() };
+                    return Err(VmError::error(format!("Cannot bind static property ${} by reference", name)));
+                }
+                _ => {}
+            }
+            let mut warnings = Vec::new();
+            let result = match &container {
+                Container::Var(name) => {
+                    let slot = self.frame_mut().vars.entry_or(name.clone(), Value::Null);
+                    walk(slot, steps, &mut keys, store, &mut warnings)
+                }
+                Container::Cell(cell) => walk(&mut cell.borrow_mut(), steps, &mut keys, store, &mut warnings),
+                Container::Prop(obj, name) => {
+                    let mut o = obj.borrow_mut();
+                    walk(o.props.entry(ArrayKey::from(&**name)), steps, &mut keys, store, &mut warnings)
+                }
+            };
+            for (level, message) in warnings {
+                self.diagnostic(level, &message);
+            }
+            match result? {
+                Walk::Done => return Ok(()),
+                Walk::Cell(cell, s) => {
+                    container = Container::Cell(cell);
+                    step += s;
+                }
+                Walk::Object(obj, s) => {
+                    match self.enter_object(obj, &lv.steps, step + s, store)? {
+                        (Some(c), s) => {
+                            container = c;
+                            step = s;
+                        }
+                        (None, _) => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Continues an assignment path at an object. Returns the property
+    /// container to keep walking, or `None` if the value was written.
+    fn enter_object(
+        &mut self,
+        obj: ObjRef,
+        steps: &[LStep],
+        step: usize,
+        store: &mut Store,
+    ) -> VmResult<(Option<Container>, usize)> {
+        let name = match &steps[step] {
+            LStep::Prop(name) => name.clone(),
+            _ => {
+                return Err(VmError::error(format!(
+                    "Cannot use object of type {} as array",
+                    obj.borrow().class.name
+                )));
+            }
+        };
+        if self.is_magic_prop(&obj, &name) {
+            if step + 1 == steps.len() {
+                if let Store::Value(v) = &*store {
+                    let v = (*v).clone();
+                    if self.call_magic_prop(&obj, "__set", &name, Some(v))?.is_some() {
+                        return Ok((None, step));
+                    }
+                }
+            } else if let Some(v) = self.call_magic_prop(&obj, "__get", &name, None)? {
+                return match v {
+                    Value::Object(inner) => self.enter_object(inner, steps, step + 1, store),
+                    v => {
+                        let class = obj.borrow().class.name.clone();
+                        self.diagnostic(
+                            "Notice",
+                            &format!("Indirect modification of overloaded property {}::${} has no effect", class, name),
+                        );
+                        Ok((Some(Container::Cell(Rc::new(RefCell::new(v)))), step + 1))
+                    }
+                };
+            }
+        }
+        self.check_prop_access(&obj, &name)?;
+        let class = obj.borrow().class.clone();
+        if class.kind == ClassKind::Enum {
+            return Err(VmError::error(match class.find_prop(&name) {
+                Some(_) => format!("Cannot modify readonly property {}::${}", class.name, name),
+                None => format!("Cannot create dynamic property {}::${}", class.name, name),
+            }));
+        }
+        if step + 1 == steps.len() {
+            match store {
+                Store::Value(v) => self.write_prop(&obj, &name, (*v).clone()),
+                Store::Ref(out) => {
+                    self.warn_dynamic_prop(&obj, &name);
+                    *out = Some(ref_cell(obj.borrow_mut().props.entry(ArrayKey::from(&*name))));
+                }
+                Store::Bind(cell) => {
+                    self.warn_dynamic_prop(&obj, &name);
+                    obj.borrow_mut().props.insert(ArrayKey::from(&*name), Value::Ref(cell.clone()));
+                }
+            }
+            return Ok((None, step));
+        }
+        Ok((Some(Container::Prop(obj, name)), step + 1))
+    }
+
+    fn write_prop(&mut self, obj: &ObjRef, name: &str, value: Value) {
+        self.warn_dynamic_prop(obj, name);
+        match obj.borrow_mut().props.entry(ArrayKey::from(name)) {
+            Value::Ref(cell) => *cell.borrow_mut() = value,
+            slot => *slot = value,
+        }
+    }
+
+    fn warn_dynamic_prop(&mut self, obj: &ObjRef, name: &str) {
+        let dynamic = {
+            let o = obj.borrow();
+            !o.props.contains_key(&ArrayKey::from(name)) && o.class.find_prop(name).is_none() && &*o.class.name != "stdClass"
+        };
+        if dynamic {
+            let class = obj.borrow().class.name.clone();
+            self.deprecated(&format!("Creation of dynamic property {}::${} is deprecated", class, name));
+        }
+    }
+
+    // -- Classes ----------------------------------------
+
+    fn declare_function(&mut self, f: &Rc<Function>) -> VmResult<()> {
+        let key = f.name.to_ascii_lowercase();
+        if let Some(existing) = self.functions.get(&key) {
+            if Rc::ptr_eq(existing, f) {
+                return Ok(());
+            }
+            return Err(VmError::Fatal(format!(
+                "Cannot redeclare {}() (previously declared in {}:{})",
+                f.name,
+                existing.file,
+                existing.lines.first().copied().unwrap_or(0)
+            )));
+        }
+        self.functions.insert(key, f.clone());
+        Ok(())
+    }
+
+    pub(crate) fn lookup_function(&self, name: &str) -> VmResult<Rc<Function>> {
+        let name = name.strip_prefix('\\').unwrap_or(name);
+        self.functions
+            .get(&name.to_ascii_lowercase())
+            .cloned()
+            .ok_or_else(|| VmError::error(format!("Call to undefined function {}()", name)))
+    }
+
+    pub(crate) fn declare_class(&mut self, def: &Rc<ClassDef>) -> VmResult<()> {
+        let key = def.name.to_ascii_lowercase();
+        if let Some(existing) = self.classes.get(&key) {
+            if Rc::ptr_eq(&existing.decl, def) {
+                return Ok(());
+            }
+            return Err(VmError::Fatal(format!(
+                "Cannot declare {} {}, because the name is already in use",
+                kind_name(def.kind),
+                def.name
+            )));
+        }
+
+        let parent = match &def.parent {
+            Some(p) => Some(self.load_class(p, "Class")?),
+            None => None,
+        };
+        let mut interfaces = Vec::new();
+        for i in &def.interfaces {
+            interfaces.push(self.load_class(i, "Interface")?);
+        }
+        let mut traits = Vec::new();
+        for t in def.trait_uses.iter().flat_map(|u| u.traits.iter()) {
+            traits.push(self.load_class(t, "Trait")?);
+        }
+
+        if def.kind == ClassKind::Enum {
+            let base = if def.backing_type.is_some() { "BackedEnum" } else { "UnitEnum" };
+            interfaces.push(self.lookup_class(base)?);
+        }
+
+        let class = class::link(def, LinkInputs { parent, interfaces, traits }).map_err(VmError::Fatal)?;
+        if class.kind != ClassKind::Interface
+            && class.is_subclass_of("Throwable")
+            && !class.is_subclass_of("Exception")
+            && !class.is_subclass_of("Error")
+        {
+            return Err(VmError::Fatal(format!(
+                "Class {} cannot implement interface Throwable, extend Exception or Error instead",
+                class.name
+            )));
+        }
+        let class = Rc::new(class);
+        self.classes.insert(key, class.clone());
+
+        for prop in class.static_props.iter().filter(|p| p.class == class.name) {
+            if let Some(init) = &prop.init {
+                let v = self.call(Callee::in_class(init.clone(), class.clone()), Vec::new())?;
+                *prop.cell.borrow_mut() = v;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn lookup_class(&mut self, name: &str) -> VmResult<Rc<Class>> {
+        self.load_class(name, "Class")
+    }
+
+    /// Finds a declared class, without autoloading it.
+    pub(crate) fn find_class(&self, name: &str) -> Option<Rc<Class>> {
+        let name = name.strip_prefix('\\').unwrap_or(name);
+        self.classes.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    /// Finds a class, autoloading it when it is not declared yet. `what`
+    /// names the kind expected in the error.
+    fn load_class(&mut self, name: &str, what: &str) -> VmResult<Rc<Class>> {
+        let name = name.strip_prefix('\\').unwrap_or(name);
+        if let Some(class) = self.find_class(name) {
+            return Ok(class);
+        }
+        self.autoload(name)?;
+        self.find_class(name).ok_or_else(|| VmError::error(format!("{} \"{}\" not found", what, name)))
+    }
+
+    /// Resolves a class name, including `self`, `parent` and `static`.
+    pub(crate) fn resolve_class(&mut self, name: &str) -> VmResult<Rc<Class>> {
+        match name.to_ascii_lowercase().as_str() {
+            "self" => self
+                .frame()
+                .scope
+                .clone()
+                .ok_or_else(|| VmError::error("Cannot use \"self\" when no class scope is active")),
+            "static" => self
+                .frame()
+                .static_class
+                .clone()
+                .ok_or_else(|| VmError::error("Cannot use \"static\" when no class scope is active")),
+            "parent" => {
+                let scope = self
+                    .frame()
+                    .scope
+                    .clone()
+                    .ok_or_else(|| VmError::error("Cannot use \"parent\" when no class scope is active"))?;
+                scope
+                    .parent
+                    .clone()
+                    .ok_or_else(|| VmError::error("Cannot use \"parent\" when current class scope has no parent"))
+            }
+            _ => self.lookup_class(name),
+        }
+    }
+
+    fn resolve_class_operand(&mut self, class: &ClassOperand) -> VmResult<Rc<Class>> {
+        match class {
+            ClassOperand::Named(name) => self.resolve_class(name),
+            ClassOperand::Dynamic => match self.pop() {
+                Value::Object(o) => Ok(o.borrow().class.clone()),
+                Value::Str(s) => self.lookup_class(&String::from_utf8_lossy(&s)),
+                _ => Err(VmError::error("Cannot use value as class name")),
+            },
+        }
+    }
+
+    /// Whether code in the current scope may see a member declared in
+    /// `declaring` with the given visibility.
+    pub(crate) fn can_access(&self, visibility: Visibility, declaring: &str) -> bool {
+        let scope = self.frame().scope.as_ref();
+        match visibility {
+            Visibility::Public => true,
+            Visibility::Private => scope.is_some_and(|s| s.name.eq_ignore_ascii_case(declaring)),
+            Visibility::Protected => scope.is_some_and(|s| {
+                s.is_subclass_of(declaring)
+                    || self
+                        .classes
+                        .get(&declaring.to_ascii_lowercase())
+                        .is_some_and(|d| d.is_subclass_of(&s.name))
+            }),
+        }
+    }
+
+    pub(crate) fn scope_description(&self) -> String {
+        match &self.frame().scope {
+            Some(s) => format!("scope {}", s.name),
+            None => "global scope".to_string(),
+        }
+    }
+
+    fn eval_lazy(&mut self, cell: &RefCell<Lazy>, scope: Rc<Class>, what: &str) -> VmResult<Value> {
+        let thunk = match &*cell.borrow() {
+            Lazy::Done(v) => return Ok(v.clone()),
+            Lazy::Evaluating => {
+                return Err(VmError::error(format!("Cannot declare self-referencing constant {}", what)));
+            }
+            Lazy::Pending(f) => f.clone(),
+        };
+        *cell.borrow_mut() = Lazy::Evaluating;
+        match self.call(Callee::in_class(thunk.clone(), scope), Vec::new()) {
+            Ok(v) => {
+                *cell.borrow_mut() = Lazy::Done(v.clone());
+                Ok(v)
+            }
+            Err(e) => {
+                *cell.borrow_mut() = Lazy::Pending(thunk);
+                Err(e)
+            }
+        }
+    }
+
+    pub(crate) fn class_constant(&mut self, class: &ClassOperand, name: &str) -> VmResult<Value> {
+        if name.eq_ignore_ascii_case("class") {
+            if let ClassOperand::Named(n) = class
+                && !matches!(n.to_ascii_lowercase().as_str(), "self" | "static" | "parent") {
+                    return Ok(Value::from(&**n));
+                }
+            let c = self.resolve_class_operand(class)?;
+            return Ok(Value::from(&*c.name));
+        }
+        let c = self.resolve_class_operand(class)?;
+        let constant = c
+            .find_constant(name)
+            .cloned()
+            .ok_or_else(|| VmError::error(format!("Undefined constant {}::{}", c.name, name)))?;
+        if !self.can_access(constant.visibility, &constant.class) {
+            return Err(VmError::error(format!(
+                "Cannot access {} constant {}::{}",
+                visibility_name(constant.visibility),
+                c.name,
+                name
+            )));
+        }
+        let scope = self.lookup_class(&constant.class)?;
+        let what = format!("{}::{}", constant.class, name);
+        self.eval_lazy(&constant.value, scope, &what)
+    }
+
+    fn static_prop_cell(&mut self, class: &ClassOperand, name: &str) -> VmResult<Rc<RefCell<Value>>> {
+        let c = self.resolve_class_operand(class)?;
+        let prop = c
+            .find_static_prop(name)
+            .cloned()
+            .ok_or_else(|| VmError::error(format!("Access to undeclared static property {}::${}", c.name, name)))?;
+        if !self.can_access(prop.visibility, &prop.class) {
+            return Err(VmError::error(format!(
+                "Cannot access {} property {}::${}",
+                visibility_name(prop.visibility),
+                c.name,
+                name
+            )));
+        }
+        Ok(prop.cell.clone())
+    }
+
+    // -- Objects ----------------------------------------
+
+    pub(crate) fn instantiate(&mut self, class: &Rc<Class>) -> VmResult<ObjRef> {
+        let what = match class.kind {
+            ClassKind::Interface => Some("interface"),
+            ClassKind::Trait => Some("trait"),
+            ClassKind::Enum => Some("enum"),
+            ClassKind::Class if class.is_abstract => Some("abstract class"),
+            ClassKind::Class => None,
+        };
+        if let Some(what) = what {
+            return Err(VmError::error(format!("Cannot instantiate {} {}", what, class.name)));
+        }
+        if &*class.name == "Closure" {
+            return Err(VmError::error("Instantiation of class Closure is not allowed"));
+        }
+        if &*class.name == "Generator" {
+            return Err(VmError::error(
+                "The \"Generator\" class is reserved for internal use and cannot be manually instantiated",
+            ));
+        }
+        let mut props = PhpArray::new();
+        for p in &class.props {
+            let scope = self.lookup_class(&p.class)?;
+            let what = format!("{}::${}", p.class, p.name);
+            let v = self.eval_lazy(&p.default, scope, &what)?;
+            props.insert(ArrayKey::from(&*p.name), v);
+        }
+        if class.is_subclass_of("Throwable") {
+            props.insert(ArrayKey::from("file"), Value::from(&*self.current_file()));
+            props.insert(ArrayKey::from("line"), Value::Int(self.current_line() as i64));
+            props.insert(ArrayKey::from("trace"), Value::from(self.backtrace()));
+        }
+        Ok(Object::new(&self.objects, class.clone(), props))
+    }
+
+    fn constructor_callee(&mut self, obj: &ObjRef) -> VmResult<Option<Callee>> {
+        let class = obj.borrow().class.clone();
+        let Some(ctor) = class.find_method("__construct").cloned() else {
+            return Ok(None);
+        };
+        if !self.can_access(ctor.visibility, &ctor.class) {
+            return Err(VmError::error(format!(
+                "Call to {} {}::__construct() from {}",
+                visibility_name(ctor.visibility),
+                class.name,
+                self.scope_description()
+            )));
+        }
+        self.method_callee(&ctor, Some(obj.clone()), class).map(Some)
+    }
+
+    pub(crate) fn method_callee(&mut self, method: &Rc<Method>, this: Option<ObjRef>, static_class: Rc<Class>) -> VmResult<Callee> {
+        if method.is_abstract {
+            return Err(VmError::error(format!("Cannot call abstract method {}::{}()", method.class, method.name)));
+        }
+        let scope = self.lookup_class(&method.class)?;
+        Ok(Callee {
+            func: method.func.clone(),
+            this: if method.is_static { None } else { this },
+            scope: Some(scope),
+            static_class: Some(static_class),
+            bound: Vec::new(),
+            magic_name: None,
+        })
+    }
+
+    /// Finds the method a call from the current scope resolves to: a
+    /// private method of the calling class takes precedence.
+    fn find_method(&self, class: &Rc<Class>, name: &str) -> Option<Rc<Method>> {
+        if let Some(scope) = &self.frame().scope
+            && class.is_subclass_of(&scope.name)
+                && let Some(m) = scope.find_method(name)
+                    && m.visibility == Visibility::Private && m.class.eq_ignore_ascii_case(&scope.name) {
+                        return Some(m.clone());
+                    }
+        class.find_method(name).cloned()
+    }
+
+    fn check_method_access(&self, method: &Method, class: &Class) -> VmResult<()> {
+        if self.can_access(method.visibility, &method.class) {
+            return Ok(());
+        }
+        Err(VmError::error(format!(
+            "Call to {} method {}::{}() from {}",
+            visibility_name(method.visibility),
+            class.name,
+            method.name,
+            self.scope_description()
+        )))
+    }
+
+    pub(crate) fn method_callee_of(&mut self, obj: Value, name: &str) -> VmResult<Callee> {
+        let Value::Object(obj) = obj else {
+            return Err(VmError::error(format!(
+                "Call to a member function {}() on {}",
+                name,
+                obj.type_name()
+            )));
+        };
+        let class = obj.borrow().class.clone();
+        let method = self.find_method(&class, name);
+        if let Some(m) = &method
+            && self.check_method_access(m, &class).is_ok()
+        {
+            return self.method_callee(m, Some(obj), class);
+        }
+        if let Some(call) = class.find_method("__call").cloned() {
+            return self.magic_callee(&call, Some(obj), class, name);
+        }
+        match method {
+            Some(m) => Err(self.check_method_access(&m, &class).unwrap_err()),
+            None => Err(VmError::error(format!("Call to undefined method {}::{}()", class.name, name))),
+        }
+    }
+
+    /// A call of an undefined or inaccessible method that goes to
+    /// `__call()` or `__callStatic()`.
+    fn magic_callee(&mut self, magic: &Rc<Method>, this: Option<ObjRef>, class: Rc<Class>, name: &str) -> VmResult<Callee> {
+        let mut callee = self.method_callee(magic, this, class)?;
+        callee.magic_name = Some(name.into());
+        Ok(callee)
+    }
+
+    /// `Class::method()`. Forwarding calls (`self::`, `parent::`,
+    /// `static::`) keep the caller's late static binding class.
+    pub(crate) fn static_callee(&mut self, class: Rc<Class>, name: &str, forwarding: bool) -> VmResult<Callee> {
+        let method = match self.find_method(&class, name) {
+            Some(m) if self.check_method_access(&m, &class).is_ok() => m,
+            method => {
+                // `__call()` when there is a compatible `$this`, else `__callStatic()`
+                let this = match self.frame().vars.get("this") {
+                    Some(Value::Object(o)) if o.borrow().class.is_subclass_of(&class.name) => Some(o.clone()),
+                    _ => None,
+                };
+                if let Some(this) = this
+                    && let Some(call) = class.find_method("__call").cloned()
+                {
+                    let static_class = this.borrow().class.clone();
+                    return self.magic_callee(&call, Some(this), static_class, name);
+                }
+                if let Some(call) = class.find_method("__callStatic").cloned() {
+                    return self.magic_callee(&call, None, class, name);
+                }
+                return match method {
+                    Some(m) => Err(self.check_method_access(&m, &class).unwrap_err()),
+                    None => Err(VmError::error(format!("Call to undefined method {}::{}()", class.name, name))),
+                };
+            }
+        };
+
+        let this = match self.frame().vars.get("this") {
+            Some(Value::Object(o)) if o.borrow().class.is_subclass_of(&method.class) => Some(o.clone()),
+            _ => None,
+        };
+        if method.is_static {
+            let static_class = if forwarding {
+                self.frame().static_class.clone().unwrap_or(class)
+            } else {
+                class
+            };
+            return self.method_callee(&method, None, static_class);
+        }
+        match this {
+            Some(this) => {
+                let static_class = this.borrow().class.clone();
+                self.method_callee(&method, Some(this), static_class)
+            }
+            None => Err(VmError::error(format!(
+                "Non-static method {}::{}() cannot be called statically",
+                method.class, method.name
+            ))),
+        }
+    }
+
+    /// Checks visibility of a declared property before reading or writing.
+    fn check_prop_access(&self, obj: &ObjRef, name: &str) -> VmResult<()> {
+        let o = obj.borrow();
+        if let Some(info) = o.class.find_prop(name)
+            && !self.can_access(info.visibility, &info.class) {
+                return Err(VmError::error(format!(
+                    "Cannot access {} property {}::${}",
+                    visibility_name(info.visibility),
+                    o.class.name,
+                    name
+                )));
+            }
+        Ok(())
+    }
+
+    fn fetch_prop(&mut self, obj: &Value, name: &str) -> VmResult<Value> {
+        let Value::Object(obj) = obj else {
+            self.warn(&format!("Attempt to read property \"{}\" on {}", name, obj.type_name()));
+            return Ok(Value::Null);
+        };
+        if self.is_magic_prop(obj, name)
+            && let Some(v) = self.call_magic_prop(obj, "__get", name, None)?
+        {
+            return Ok(v);
+        }
+        self.check_prop_access(obj, name)?;
+        let v = obj.borrow().props.get(&ArrayKey::from(name)).map(Value::deref);
+        match v {
+            Some(v) => Ok(v),
+            None => {
+                let class = obj.borrow().class.name.clone();
+                self.warn(&format!("Undefined property: {}::${}", class, name));
+                Ok(Value::Null)
+            }
+        }
+    }
+
+    // -- Enums ------------------------------------------
+
+    /// Creates the singleton object of an enum case; runs as the case
+    /// constant's initializer, in the enum's scope.
+    fn new_enum_case(&mut self, name: &str) -> VmResult<Value> {
+        let class = self.frame().scope.clone().unwrap();
+        let mut props = PhpArray::new();
+        props.insert(ArrayKey::from("name"), Value::from(name));
+        if let Some(ty) = &class.decl.backing_type {
+            let v = self.pop();
+            let matches = matches!((&**ty, &v), ("int", Value::Int(_)) | ("string", Value::Str(_)));
+            if !matches {
+                return Err(VmError::Fatal(format!(
+                    "Enum case type {} does not match enum backing type {}",
+                    v.type_name(),
+                    ty
+                )));
+            }
+            props.insert(ArrayKey::from("value"), v);
+        }
+        Ok(Value::Object(Object::new(&self.objects, class, props)))
+    }
+
+    /// The case objects of an enum, in declaration order.
+    fn enum_cases(&mut self, class: &Rc<Class>) -> VmResult<Vec<Value>> {
+        let consts: Vec<_> = class.constants.iter().filter(|c| c.is_case).cloned().collect();
+        let mut cases = Vec::new();
+        for c in consts {
+            let what = format!("{}::{}", class.name, c.name);
+            cases.push(self.eval_lazy(&c.value, class.clone(), &what)?);
+        }
+        Ok(cases)
+    }
+
+    fn enum_from(&mut self, v: Value, try_from: bool) -> VmResult<Value> {
+        let class = self.frame().scope.clone().unwrap();
+        let ty = class.decl.backing_type.clone().unwrap();
+        let func = format!("{}::{}", class.name, if try_from { "tryFrom" } else { "from" });
+        let v = self.coerce_arg(v, &ty, &func, 1, "value")?;
+        for case in self.enum_cases(&class)? {
+            let Value::Object(o) = &case else { unreachable!() };
+            let found = o.borrow().props.get(&ArrayKey::from("value")).is_some_and(|w| strict_equals(&v, w));
+            if found {
+                return Ok(case);
+            }
+        }
+        if try_from {
+            return Ok(Value::Null);
+        }
+        let shown = match &v {
+            Value::Str(s) => format!("\"{}\"", String::from_utf8_lossy(s)),
+            other => String::from_utf8_lossy(&other.scalar_to_bytes().unwrap()).into_owned(),
+        };
+        Err(VmError::Error {
+            class: "ValueError",
+            message: format!("{} is not a valid backing value for enum {}", shown, class.name),
+        })
+    }
+}
+
+/// Walks the array dimensions of an assignment path inside `slot` and
+/// stores `value` at the end, creating arrays as needed. Stops at the
+/// first object, which the caller continues with.
+fn walk(
+    mut slot: &mut Value,
+    steps: &[LStep],
+    keys: &mut impl Iterator<Item = Value>,
+    store: &mut Store,
+    warnings: &mut Vec<(&'static str, String)>,
+) -> VmResult<Walk> {
+    for (i, step) in steps.iter().enumerate() {
+        if let Value::Ref(cell) = slot {
+            return Ok(Walk::Cell(cell.clone(), i));
+        }
+        if let LStep::Prop(name) = step {
+            return match slot {
+                Value::Object(o) => Ok(Walk::Object(o.clone(), i)),
+                other => Err(VmError::error(format!(
+                    "Attempt to assign property \"{}\" on {}",
+                    name,
+                    other.type_name()
+                ))),
+            };
+        }
+        let key = match step {
+            LStep::Dim => Some(keys.next().unwrap()),
+            _ => None,
+        };
+        match slot {
+            Value::Null => *slot = Value::Array(Rc::new(PhpArray::new())),
+            Value::Bool(false) => {
+                warnings.push(("Deprecated", "Automatic conversion of false to array is deprecated".to_string()));
+                *slot = Value::Array(Rc::new(PhpArray::new()));
+            }
+            _ => {}
+        }
+        slot = match slot {
+            Value::Array(arr) => {
+                let arr = Rc::make_mut(arr);
+                match key {
+                    Some(k) => {
+                        let k = k.to_array_key().ok_or_else(|| {
+                            VmError::type_error(format!("Cannot access offset of type {} on array", k.type_name()))
+                        })?;
+                        arr.entry(k)
+                    }
+                    None => arr.push_slot().map_err(VmError::error)?,
+                }
+            }
+            Value::Object(o) => return Ok(Walk::Object(o.clone(), i)),
+            Value::Str(s) if i + 1 == steps.len() => {
+                let Some(k) = key else {
+                    return Err(VmError::error("[] operator not supported for strings"));
+                };
+                let Store::Value(value) = store else {
+                    return Err(VmError::error("Cannot create references to/from string offsets"));
+                };
+                assign_string_offset(s, &k, value, warnings)?;
+                return Ok(Walk::Done);
+            }
+            Value::Str(_) => return Err(VmError::error("Cannot use string offset as an array")),
+            _ => return Err(VmError::error("Cannot use a scalar value as an array")),
+        };
+    }
+    match store {
+        Store::Value(v) => match slot {
+            Value::Ref(cell) => *cell.borrow_mut() = (*v).clone(),
+            slot => *slot = (*v).clone(),
+        },
+        Store::Ref(out) => *out = Some(ref_cell(slot)),
+        Store::Bind(cell) => *slot = Value::Ref(cell.clone()),
+    }
+    Ok(Walk::Done)
+}
+
+/// Follows an `unset()` path through arrays, stopping where it continues
+/// in an object or referenced value; the last step removes the element.
+fn unset_walk(mut slot: &mut Value, steps: &[LStep], keys: &mut impl Iterator<Item = Value>) -> VmResult<Walk> {
+    for (i, step) in steps.iter().enumerate() {
+        if let Value::Ref(cell) = slot {
+            return Ok(Walk::Cell(cell.clone(), i));
+        }
+        if let LStep::Prop(_) = step {
+            return Ok(match slot {
+                Value::Object(o) => Walk::Object(o.clone(), i),
+                _ => Walk::Done,
+            });
+        }
+        let key = keys.next().unwrap();
+        let last = i + 1 == steps.len();
+        slot = match slot {
+            Value::Array(arr) => {
+                let k = key.to_array_key().ok_or_else(|| {
+                    VmError::type_error(format!("Cannot unset offset of type {} on array", key.type_name()))
+                })?;
+                if !arr.contains_key(&k) {
+                    return Ok(Walk::Done);
+                }
+                let arr = Rc::make_mut(arr);
+                if last {
+                    arr.remove(&k);
+                    return Ok(Walk::Done);
+                }
+                arr.get_mut(&k).unwrap()
+            }
+            Value::Object(o) => return Ok(Walk::Object(o.clone(), i)),
+            Value::Str(_) => return Err(VmError::error("Cannot unset string offsets")),
+            _ => return Ok(Walk::Done),
+        };
+    }
+    Ok(Walk::Done)
+}
+
+/// The reference cell of a slot, turning the slot into a reference first.
+pub(crate) fn ref_cell(slot: &mut Value) -> Rc<RefCell<Value>> {
+    match slot {
+        Value::Ref(cell) => cell.clone(),
+        other => {
+            let cell = Rc::new(RefCell::new(std::mem::take(other)));
+            *other = Value::Ref(cell.clone());
+            cell
+        }
+    }
+}
+
+fn assign_string_offset(
+    s: &mut Rc<Vec<u8>>,
+    key: &Value,
+    value: &Value,
+    warnings: &mut Vec<(&'static str, String)>,
+) -> VmResult<()> {
+    let n = key.to_int();
+    let i = if n < 0 { n + s.len() as i64 } else { n };
+    if i < 0 {
+        return Err(VmError::error(format!("Illegal string offset {}", n)));
+    }
+    let bytes = value.scalar_to_bytes().unwrap_or_default();
+    if bytes.is_empty() {
+        return Err(VmError::error("Cannot assign an empty string to a string offset"));
+    }
+    if bytes.len() > 1 {
+        warnings.push(("Warning", "Only the first byte will be assigned to the string offset".to_string()));
+    }
+    let s = Rc::make_mut(s);
+    let i = i as usize;
+    if i >= s.len() {
+        s.resize(i + 1, b' ');
+    }
+    s[i] = bytes[0];
+    Ok(())
+}
+
+fn undefined_key_message(k: &ArrayKey) -> String {
+    match k {
+        ArrayKey::Int(n) => format!("Undefined array key {}", n),
+        ArrayKey::Str(s) => format!("Undefined array key \"{}\"", String::from_utf8_lossy(s)),
+    }
+}
+
+/// The line a frame is running.
+fn frame_line(f: &Frame) -> usize {
+    f.func.lines[f.ip.saturating_sub(1).min(f.func.lines.len() - 1)]
+}
+
+/// A function's name as PHP shows it in errors: `foo`, `A::foo` or
+/// `A::{closure}`.
+fn function_name(func: &Function, scope: Option<&Rc<Class>>) -> String {
+    match scope {
+        Some(c) => format!("{}::{}", c.name, func.name),
+        None => func.name.to_string(),
+    }
+}
@@ -0,0 +1,47 @@
+//! Destructors: each engine runs its own, and shutdown runs them in
+//! PHP's order.
+
+use phpvm::Engine;
+
+fn output(engine: &mut Engine) -> String {
+    String::from_utf8(engine.take_output()).unwrap()
+}
+
+const NOISY: &str = "<?php class Noisy { public $name; public $peer; \
+    public function __construct($name) { $this->name = $name; } \
+    public function __destruct() { echo 'bye ', $this->name, ' '; } }";
+
+#[test]
+fn destructors_run_in_their_own_engine() {
+    let mut a = Engine::capturing();
+    let mut b = Engine::capturing();
+    a.run_source(NOISY, "a.php").unwrap();
+    b.run_source("<?php $n = new stdClass(); var_dump($n);", "b.php").unwrap();
+    a.run_source("<?php $kept = new Noisy('a'); $kept = null; echo 'a done ';", "a.php").unwrap();
+    b.run_source("<?php echo 'b done';", "b.php").unwrap();
+    assert_eq!(output(&mut a), "bye a a done ");
+    assert_eq!(output(&mut b), "object(stdClass)#1 (0) {\n}\nb done");
+
+    a.run_source("<?php $kept = new Noisy('a');", "a.php").unwrap();
+    let kept = a.vm().global("kept").unwrap();
+    drop(a);
+    drop(kept);
+    let mut c = Engine::capturing();
+    c.run_source("<?php echo 'c runs';", "c.php").unwrap();
+    assert_eq!(output(&mut c), "c runs");
+}
+
+#[test]
+fn shutdown_destructs_globals_then_survivors() {
+    let mut engine = Engine::capturing();
+    engine.run_source(NOISY, "n.php").unwrap();
+    engine
+        .run_source(
+            "<?php $a = new Noisy('a'); $b = new Noisy('b'); $a->peer = $b; $b->peer = $a; \
+             $c = new Noisy('c'); $d = new Noisy('d'); $shared = $d; $e = new Noisy('e');",
+            "s.php",
+        )
+        .unwrap();
+    engine.shutdown().unwrap();
+    assert_eq!(output(&mut engine), "bye e bye c bye a bye b bye d ");
+}
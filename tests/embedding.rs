@@ -0,0 +1,68 @@
+//! The embedding API: running PHP from Rust and exchanging values.
+
+use std::collections::HashMap;
+
+use phpvm::{Engine, Error, IntoPhp, Value};
+
+fn output(engine: &mut Engine) -> String {
+    String::from_utf8(engine.take_output()).unwrap()
+}
+
+#[test]
+fn scripts_share_state_between_runs() {
+    let mut engine = Engine::capturing();
+    engine.run_source("<?php function twice($x) { return $x * 2; } $seen = 1;", "a.php").unwrap();
+    engine.run_source("<?php echo twice(21), ' ', $seen;", "b.php").unwrap();
+    assert_eq!(output(&mut engine), "42 1");
+}
+
+#[test]
+fn calls_functions_and_converts_values() {
+    let mut engine = Engine::capturing();
+    engine
+        .run_source("<?php function stats(array $xs) { return ['n' => count($xs), 'sum' => array_sum($xs)]; }", "lib.php")
+        .unwrap();
+    let stats: HashMap<String, i64> = engine.call_as("stats", vec![vec![2i64, 5].into_php()]).unwrap();
+    assert_eq!(stats["n"], 2);
+    assert_eq!(stats["sum"], 7);
+    let upper: String = engine.call_as("strtoupper", vec![Value::from("abc")]).unwrap();
+    assert_eq!(upper, "ABC");
+    let err = engine.call_as::<i64>("strtoupper", vec![Value::from("abc")]).unwrap_err();
+    assert!(matches!(err, Error::Conversion(_)), "{}", err);
+}
+
+#[test]
+fn globals_pass_both_ways() {
+    let mut engine = Engine::capturing();
+    engine.set_global("names", vec!["ann", "bob"]);
+    engine.run_source("<?php $count = count($names); $joined = implode('+', $names);", "g.php").unwrap();
+    assert_eq!(engine.global::<i64>("count").unwrap(), Some(2));
+    assert_eq!(engine.global::<String>("joined").unwrap().as_deref(), Some("ann+bob"));
+    assert_eq!(engine.global::<i64>("missing").unwrap(), None);
+}
+
+#[test]
+fn errors_are_reported_not_raised() {
+    let mut engine = Engine::capturing();
+    match engine.run_source("<?php echo 'x'; throw new LogicException('bad');", "t.php") {
+        Err(Error::Uncaught { class, message, line, .. }) => {
+            assert_eq!((class.as_str(), message.as_str(), line), ("LogicException", "bad", 1));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(matches!(engine.run_source("<?php echo (;", "p.php"), Err(Error::Parse { .. })));
+    assert!(matches!(engine.run_source("<?php break;", "c.php"), Err(Error::Compile { .. })));
+    engine.run_source("<?php echo 'still usable';", "after.php").unwrap();
+    assert_eq!(output(&mut engine), "xstill usable");
+}
+
+#[test]
+fn engines_are_independent() {
+    let mut a = Engine::capturing();
+    let mut b = Engine::capturing();
+    a.run_source("<?php const WHO = 'a'; function who() { return WHO; }", "a.php").unwrap();
+    b.run_source("<?php const WHO = 'b'; function who() { return WHO; }", "b.php").unwrap();
+    a.run_source("<?php echo who();", "a.php").unwrap();
+    b.run_source("<?php echo who();", "b.php").unwrap();
+    assert_eq!((output(&mut a), output(&mut b)), ("a".to_string(), "b".to_string()));
+}
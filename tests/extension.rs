@@ -0,0 +1,133 @@
+//! Exposing Rust functions, classes, constants and settings to PHP.
+
+use std::rc::Rc;
+
+use phpvm::{
+    ArrayKey, Callee, ClassDef, ClassKind, ConstDef, Engine, Extension, Function, MethodDef, NativeFn, NativeFunction,
+    PropDef, TraitUseDef, Value, Visibility, Vm, VmResult,
+};
+
+fn cents(_: &mut Vm, _: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let amount = match &args[0] {
+        Value::Float(f) => *f,
+        v => v.to_int() as f64,
+    };
+    let factor = args.get(1).map_or(100, Value::to_int);
+    Ok(Value::Int((amount * factor as f64).round() as i64))
+}
+
+fn money() -> Extension {
+    Extension {
+        name: "money",
+        functions: vec![NativeFunction { name: "to_cents", params: &["float amount", "int factor = 100"], func: cents }],
+        classes: vec![labelled(), rate()],
+        constants: vec![("MONEY_CURRENCY", Value::from("EUR"))],
+        ini: vec![("money.rounding", "half_up")],
+    }
+}
+
+fn label(_: &mut Vm, _: &Callee, _: Vec<Value>) -> VmResult<Value> {
+    Ok(Value::from("rate"))
+}
+
+/// `Rate::apply(int $cents)`: the cents scaled by `$this->percent`.
+fn apply(_: &mut Vm, callee: &Callee, args: Vec<Value>) -> VmResult<Value> {
+    let this = callee.this.as_ref().unwrap().borrow();
+    let percent = this.props.get(&ArrayKey::from("percent")).map_or(0, Value::to_int);
+    Ok(Value::Int(args[0].to_int() * percent / 100))
+}
+
+fn method(name: &str, params: &[&str], f: NativeFn) -> MethodDef {
+    MethodDef {
+        name: name.into(),
+        func: Rc::new(Function::native(name, params, f)),
+        visibility: Visibility::Public,
+        is_static: false,
+        is_abstract: false,
+        is_final: false,
+    }
+}
+
+fn class(name: &str, kind: ClassKind) -> ClassDef {
+    ClassDef {
+        name: name.into(),
+        kind,
+        is_abstract: false,
+        is_final: false,
+        parent: None,
+        interfaces: Vec::new(),
+        backing_type: None,
+        constants: Vec::new(),
+        props: Vec::new(),
+        methods: Vec::new(),
+        trait_uses: Vec::new(),
+        line: 0,
+    }
+}
+
+fn labelled() -> ClassDef {
+    ClassDef { methods: vec![method("label", &[], label)], ..class("Labelled", ClassKind::Trait) }
+}
+
+fn rate() -> ClassDef {
+    ClassDef {
+        constants: vec![ConstDef {
+            name: "DEFAULT".into(),
+            value: Rc::new(Function::constant(Value::Int(20))),
+            visibility: Visibility::Public,
+            is_final: false,
+            is_case: false,
+        }],
+        props: vec![PropDef {
+            name: "percent".into(),
+            default: Some(Rc::new(Function::constant(Value::Int(20)))),
+            visibility: Visibility::Public,
+            is_static: false,
+        }],
+        methods: vec![method("apply", &["cents"], apply)],
+        trait_uses: vec![TraitUseDef { traits: vec!["Labelled".into()], adaptations: Vec::new() }],
+        ..class("Rate", ClassKind::Class)
+    }
+}
+
+fn run(source: &str) -> String {
+    let mut engine = Engine::capturing();
+    engine.vm().register_extension(money()).unwrap();
+    if let Err(e) = engine.run_source(source, "ext.php") {
+        return format!("{}", e);
+    }
+    String::from_utf8(engine.take_output()).unwrap()
+}
+
+#[test]
+fn functions_constants_and_ini_are_visible() {
+    let out = run(
+        "<?php echo to_cents(1.234), ' ', to_cents('2', 10), ' ', MONEY_CURRENCY, ' ', ini_get('money.rounding'), ' ';
+        var_dump(extension_loaded('money'));",
+    );
+    assert_eq!(out, "123 20 EUR half_up bool(true)\n");
+}
+
+#[test]
+fn ini_restore_uses_each_extensions_defaults() {
+    let out = run("<?php foreach (['money.rounding', 'bcmath.scale', 'pcre.jit'] as $name) {
+            ini_set($name, '7'); echo ini_get($name), ' '; ini_restore($name); echo ini_get($name), ' ';
+        }
+        bcscale(4); ini_restore('bcmath.scale'); echo bcadd('1', '2');");
+    assert_eq!(out, "7 half_up 7 0 7 1 3");
+}
+
+#[test]
+fn arguments_are_checked_like_internal_functions() {
+    let out = run("<?php try { to_cents('abc'); } catch (TypeError $e) { echo $e->getMessage(); }");
+    assert_eq!(out, "to_cents(): Argument #1 ($amount) must be of type float, string given");
+    let out = run("<?php try { to_cents(); } catch (ArgumentCountError $e) { echo $e->getMessage(); }");
+    assert_eq!(out, "to_cents() expects at least 1 argument, 0 given");
+}
+
+#[test]
+fn classes_have_constants_properties_and_traits() {
+    let out = run("<?php $r = new Rate(); echo Rate::DEFAULT, ' ', $r->apply(250), ' ', $r->label(), ' ';
+        $r->percent = 50; echo $r->apply(250);");
+    assert_eq!(out, "20 50 rate 125");
+}
@@ -0,0 +1,50 @@
+//! Runs every `tests/php/*.php` script and compares what it prints, and
+//! the error it ends with if any, to the `.out` file next to it. Paths
+//! under `tests/php` are printed relative to it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use phpvm::Engine;
+
+fn run(script: &Path) -> String {
+    let mut engine = Engine::capturing();
+    let result = engine.run_file(&script.display().to_string()).and_then(|_| engine.shutdown());
+    let mut out = String::from_utf8_lossy(&engine.take_output()).into_owned();
+    if let Err(e) = result {
+        out.push_str(&format!("{}\n", e));
+    }
+    let dir = fs::canonicalize(script.parent().unwrap()).unwrap();
+    out.replace(&format!("{}/", dir.display()), "")
+}
+
+fn scripts() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/php");
+    let mut scripts: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "php"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+#[test]
+fn php_scripts() {
+    let mut failures = Vec::new();
+    for script in scripts() {
+        let expected_path = script.with_extension("out");
+        let expected = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|e| panic!("{}: {}", expected_path.display(), e));
+        let actual = run(&script);
+        if actual != expected {
+            failures.push(format!(
+                "{}\n--- expected\n{}--- actual\n{}",
+                script.display(),
+                expected,
+                actual
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{} script(s) failed:\n\n{}", failures.len(), failures.join("\n"));
+}
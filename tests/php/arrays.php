@@ -0,0 +1,57 @@
+<?php
+$people = [
+    ['name' => 'ann', 'age' => 30],
+    ['name' => 'bob', 'age' => 25],
+    ['name' => 'cy', 'age' => 30],
+];
+usort($people, fn($a, $b) => $a['age'] - $b['age']);
+echo implode(',', array_column($people, 'name')), "\n";
+
+$scores = ['b' => 2, 'a' => 3, 'c' => 1];
+asort($scores);
+echo implode(',', array_keys($scores)), ' ';
+arsort($scores);
+echo implode(',', array_keys($scores)), ' ';
+ksort($scores);
+echo implode(',', array_keys($scores)), "\n";
+
+$mixed = [3, '10', 2.5, '1'];
+sort($mixed);
+var_export($mixed);
+echo "\n";
+$nat = ['img12.png', 'img10.png', 'IMG2.png', 'img1.png'];
+natcasesort($nat);
+echo implode(',', $nat), "\n";
+
+$data = [3, 1, 2];
+$other = ['c', 'a', 'b'];
+array_multisort($data, $other);
+echo implode(',', $data), ' ', implode(',', $other), "\n";
+
+echo implode(',', array_map(null, [1, 2], ['a', 'b'])[1]), ' ';
+echo implode(',', array_filter([1, 0, 2, null, 3])), ' ';
+echo implode(',', array_keys(array_filter(['a' => 1, 'b' => 2, 'c' => 3], fn($k) => $k != 'b', ARRAY_FILTER_USE_KEY))), "\n";
+echo array_reduce([1, 2, 3, 4], fn($carry, $x) => $carry + $x, 10), ' ', array_sum([1, 2.5]), ' ', array_product([2, 3]), "\n";
+print_r(array_slice(['a' => 1, 5 => 2, 3, 4], 1, 2));
+print_r(array_splice($data, 1, 1, ['x', 'y']));
+echo implode(',', $data), "\n";
+print_r(array_merge(['a' => 1, 2], ['a' => 3, 4]));
+print_r(['a' => 1, 2] + ['a' => 3, 5, 6]);
+print_r(array_combine(['x', 'y'], [1, 2]));
+print_r(array_flip(['a', 'b']));
+print_r(array_unique([1, '1', 2, 2.0, 'a', 'A']));
+print_r(array_count_values(['a', 'b', 'a', 1, '1']));
+print_r(array_diff_key(['a' => 1, 'b' => 2], ['a' => 9]));
+print_r(array_intersect([1, 2, 3, 4], [2, 4, 6]));
+print_r(array_fill_keys(['a', 'b'], 0));
+print_r(array_chunk([1, 2, 3], 2, true));
+print_r(range('a', 'e', 2));
+print_r(range(5, 1, 2));
+var_dump(in_array('1e1', ['10']), in_array(0, ['a'], true), array_search('b', ['a', 'b']));
+echo implode(',', array_pad([1], -3, 0)), ' ', implode(',', array_reverse(['x' => 1, 2, 3], true)), "\n";
+$stack = [1, 2];
+array_push($stack, 3, 4);
+array_unshift($stack, 0);
+echo array_pop($stack), array_shift($stack), ' ', implode(',', $stack), ' ', count([1, [2, 3]], COUNT_RECURSIVE), "\n";
+array_walk($stack, function (&$v, $k, $prefix) { $v = $prefix . $k . $v; }, '#');
+echo implode(',', $stack), ' ', max([1, 5, 3]), ' ', min(2, 8), "\n";
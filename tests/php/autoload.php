@@ -0,0 +1,24 @@
+<?php
+spl_autoload_register(function ($class) {
+    echo 'autoload ', $class, "\n";
+    $prefix = 'Shop\\';
+    if (strncmp($class, $prefix, strlen($prefix)) !== 0) {
+        return;
+    }
+    $relative = str_replace('\\', '/', substr($class, strlen($prefix)));
+    if (in_array($relative, ['Cart', 'Item'])) {
+        require __DIR__ . '/fixtures/src/Shop/' . $relative . '.php';
+    }
+});
+
+$cart = new Shop\Cart();
+$cart->add('apple')->add('pear');
+echo count($cart), "\n";
+var_dump(class_exists('Shop\Missing'));
+var_dump(class_exists('Shop\Other', false));
+var_dump(class_exists('Shop\Item'));
+try {
+    new Nowhere\Thing();
+} catch (Error $e) {
+    echo $e->getMessage(), "\n";
+}
@@ -0,0 +1,21 @@
+<?php
+var_dump((int) '12abc', (int) 3.99, (int) '1e3', (int) null, (int) true);
+var_dump((float) '1.5xyz', (float) '.5', (string) 1.0, (string) false, (bool) '0', (bool) '0.0', (bool) []);
+var_dump((array) 'str', (array) null);
+$o = (object) ['a' => 1, 'b' => [2]];
+echo $o->a, ' ', $o->b[0], "\n";
+var_dump((array) $o);
+var_dump((object) 5);
+
+interface Shape {}
+class Circle implements Shape {}
+class Ring extends Circle {}
+$r = new Ring();
+$name = 'Shape';
+var_dump($r instanceof Circle, $r instanceof Shape, $r instanceof $name, $r instanceof stdClass);
+$nothing = null;
+var_dump($nothing instanceof Circle);
+var_dump(new Circle() instanceof $r);
+var_dump(! $r instanceof Circle);
+var_dump((int) '  42', (int) '-0', (float) 'abc', (string) 0.1, (int) 99999999999999999999.0);
+var_dump((int) '9999999999999999999', (int) '-9999999999999999999', (int) '1e30', (int) ' 12abc');
@@ -0,0 +1,41 @@
+<?php
+$factor = 3;
+$times = function ($x) use ($factor) { return $x * $factor; };
+$factor = 10;
+echo $times(2), "\n";
+
+$count = 0;
+$inc = function () use (&$count) { $count = $count + 1; };
+$inc(); $inc();
+echo $count, "\n";
+
+$add = fn($x) => fn($y) => $x + $y + $factor;
+echo $add(1)(2), "\n";
+
+class Counter {
+    private $n = 5;
+    public function getAdder() { return fn($by) => $this->n + $by; }
+    public function bump($by) { return $this->n + $by; }
+    public static function twice($x) { return $x * 2; }
+}
+$c = new Counter();
+echo $c->getAdder()(1), "\n";
+
+$bump = $c->bump(...);
+$strlen = strlen(...);
+$twice = Counter::twice(...);
+echo $bump(10), ' ', $strlen('abcd'), ' ', $twice(21), "\n";
+echo implode(',', array_map(fn($x) => $x * $x, [1, 2, 3])), "\n";
+
+$peek = function () { return $this->n; };
+echo Closure::bind($peek, $c, Counter::class)(), "\n";
+echo $peek->call($c), "\n";
+
+function make_counter() {
+    $n = 0;
+    return function () use (&$n) { $n = $n + 1; return $n; };
+}
+$next = make_counter();
+$next();
+echo $next(), "\n";
+var_dump($times instanceof Closure);
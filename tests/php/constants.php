@@ -0,0 +1,27 @@
+<?php
+namespace Config;
+
+const LIMIT = 10;
+const DOUBLE = LIMIT * 2;
+define('GLOBAL_NAME', 'app');
+define('Config\\DEFINED_IN_NS', true);
+
+class Paths {
+    const ROOT = '/srv';
+    const LOGS = self::ROOT . '/logs';
+    public function where() { return __CLASS__ . '::' . __FUNCTION__ . ' ' . __METHOD__; }
+}
+
+echo LIMIT, ' ', DOUBLE, ' ', \GLOBAL_NAME, ' ', GLOBAL_NAME, "\n";
+var_dump(DEFINED_IN_NS, defined('Config\\LIMIT'), defined('NOPE'));
+echo constant('Config\\Paths::LOGS'), ' ', Paths::LOGS, "\n";
+echo (new Paths())->where(), "\n";
+echo __LINE__, ' ', __NAMESPACE__, ' ', substr(__FILE__, -13), ' ', substr(__DIR__, -5), "\n";
+echo PHP_INT_MAX, ' ', PHP_INT_SIZE, ' ', M_PI, ' ', E_ALL, "\n";
+var_dump(define('GLOBAL_NAME', 'again'));
+try {
+    echo constant('MISSING');
+} catch (\Error $e) {
+    echo $e->getMessage(), "\n";
+}
+echo UNDEFINED_CONSTANT;
@@ -0,0 +1,25 @@
+<?php
+class Account {
+    public $owner = 'ann';
+    protected $balance = 10.5;
+    private $pin = null;
+    public $tags = ['a' => true, 'b' => [1]];
+}
+$acct = new Account();
+var_dump(1, -0.0, 1.0, 0.1 + 0.2, 'str', null, false, [], [1 => ['x']]);
+var_dump($acct);
+print_r($acct);
+echo "\n";
+print_r([1, ['nested' => null, 'f' => 1.0]]);
+echo print_r('returned', true), "\n";
+var_export([1, 'k' => [true, null], 'q' => "it's", 'f' => 1.0, 'n' => -0.5]);
+echo "\n";
+var_export($acct);
+echo "\n";
+var_export(new stdClass());
+echo "\n";
+$o = new stdClass();
+$o->self = $o;
+var_dump($o);
+print_r($o);
+echo "\n";
@@ -0,0 +1,26 @@
+<?php
+[$a, $b] = [1, 2];
+[$a, $b] = [$b, $a];
+echo $a, $b, "\n";
+
+list($x, , $z) = ['x', 'y', 'z'];
+echo $x, $z, "\n";
+
+['id' => $id, 'tags' => [$first]] = ['id' => 7, 'tags' => ['php', 'rust']];
+echo $id, ' ', $first, "\n";
+
+$rows = [[1, 'one'], [2, 'two']];
+foreach ($rows as [$n, $word]) {
+    echo $n, '=', $word, "\n";
+}
+
+$pair = [1, 2];
+[$p, &$q] = $pair;
+$q = 20;
+echo implode(',', $pair), "\n";
+
+$merged = [...[1, 2], ...['a' => 'b'], 3];
+var_dump($merged);
+
+[$m, $n] = [1];
+var_dump($n);
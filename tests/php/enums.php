@@ -0,0 +1,36 @@
+<?php
+enum Suit: string {
+    case Hearts = 'H';
+    case Spades = 'S';
+
+    const Wild = self::Spades;
+
+    public function color() {
+        if ($this === Suit::Hearts) {
+            return 'Red';
+        }
+        return 'Black';
+    }
+
+    public static function fromChar($c) { return self::from($c); }
+}
+
+enum Status { case Active; case Inactive; }
+
+echo Suit::Hearts->value, ' ', Suit::Hearts->name, ' ', Suit::Hearts->color(), "\n";
+var_dump(Suit::from('S') === Suit::Spades);
+var_dump(Suit::tryFrom('X'));
+var_dump(Suit::Wild);
+echo count(Suit::cases()), ' ', Status::cases()[1]->name, "\n";
+var_dump(Status::Active instanceof UnitEnum, Suit::Hearts instanceof BackedEnum);
+var_dump(Status::Active === Status::Active, Status::Active == Status::Inactive);
+try {
+    Suit::fromChar('Z');
+} catch (ValueError $e) {
+    echo $e::class, ': ', $e->getMessage(), "\n";
+}
+try {
+    new Status();
+} catch (Error $e) {
+    echo $e->getMessage(), "\n";
+}
@@ -0,0 +1,70 @@
+<?php
+class AppException extends RuntimeException {}
+
+function risky($n) {
+    if ($n > 1) {
+        throw new AppException('too big: ' . $n, 42);
+    }
+    return $n;
+}
+
+try {
+    risky(5);
+} catch (InvalidArgumentException | AppException $e) {
+    echo $e::class, ' ', $e->getMessage(), ' ', $e->getCode(), ' ', $e->getLine(), "\n";
+} finally {
+    echo "finally 1\n";
+}
+
+function withFinally() {
+    try {
+        return 'try';
+    } finally {
+        echo "cleanup\n";
+    }
+}
+echo withFinally(), "\n";
+
+for ($i = 0; $i < 3; $i = $i + 1) {
+    try {
+        if ($i == 1) {
+            break;
+        }
+        echo 'loop ', $i, "\n";
+    } finally {
+        echo 'finally loop ', $i, "\n";
+    }
+}
+
+try {
+    try {
+        throw new LogicException('inner');
+    } catch (LogicException $e) {
+        throw new RuntimeException('outer', 0, $e);
+    }
+} catch (Exception $e) {
+    echo $e->getMessage(), ' <- ', $e->getPrevious()->getMessage(), "\n";
+}
+
+try {
+    intdiv(1, 0);
+} catch (DivisionByZeroError $e) {
+    echo $e::class, ': ', $e->getMessage(), "\n";
+}
+var_dump(new AppException() instanceof Throwable);
+
+function cleanup() {
+    try {
+        throw new RuntimeException('first');
+    } finally {
+        throw new LogicException('second');
+    }
+}
+try {
+    cleanup();
+} catch (LogicException $e) {
+    echo $e->getMessage(), ' after ', $e->getPrevious()->getMessage(), "\n";
+}
+
+function thrower() { throw new DomainException('nobody catches me'); }
+thrower();
@@ -0,0 +1,91 @@
+<?php
+$fiber = new Fiber(function ($start) {
+    echo 'started with ', $start, "\n";
+    $x = Fiber::suspend('first');
+    echo 'resumed with ', $x, "\n";
+    $y = Fiber::suspend('second');
+    return $x . $y;
+});
+$v = $fiber->start('go');
+echo 'suspended: ', $v, "\n";
+$v = $fiber->resume('A');
+echo 'suspended: ', $v, "\n";
+var_dump($fiber->isSuspended(), $fiber->isTerminated());
+$fiber->resume('B');
+var_dump($fiber->isTerminated(), $fiber->getReturn());
+
+function nested() { return Fiber::suspend('from nested') . '!'; }
+$f = new Fiber(function () { return nested(); });
+echo $f->start(), "\n";
+$f->resume('ok');
+echo $f->getReturn(), "\n";
+
+$thrower = new Fiber(function () {
+    try {
+        Fiber::suspend();
+    } catch (LogicException $e) {
+        echo 'fiber caught ', $e->getMessage(), "\n";
+        Fiber::suspend('after catch');
+    }
+});
+$thrower->start();
+echo $thrower->throw(new LogicException('injected')), "\n";
+
+$failing = new Fiber(function () { throw new RuntimeException('escaped'); });
+try {
+    $failing->start();
+} catch (RuntimeException $e) {
+    echo 'main caught ', $e->getMessage(), "\n";
+}
+try {
+    $fiber->resume();
+} catch (FiberError $e) {
+    echo $e->getMessage(), "\n";
+}
+try {
+    Fiber::suspend();
+} catch (FiberError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(Fiber::getCurrent());
+
+class Lazy {
+    public function __get($name) {
+        return Fiber::suspend('get ' . $name) . '!';
+    }
+}
+function suspendingGenerator() {
+    yield Fiber::suspend('in generator');
+}
+$nested = new Fiber(function () {
+    $mapped = array_map(function ($v) { return Fiber::suspend($v) * 10; }, [1, 2]);
+    echo 'mapped ', implode(',', $mapped), "\n";
+    $sorted = [3, 1, 2];
+    $asked = false;
+    usort($sorted, function ($x, $y) use (&$asked) {
+        if (!$asked) {
+            $asked = true;
+            Fiber::suspend('compare');
+        }
+        return $x - $y;
+    });
+    echo 'sorted ', implode(',', $sorted), "\n";
+    $lazy = new Lazy();
+    echo 'magic ', $lazy->name, "\n";
+    foreach (suspendingGenerator() as $v) {
+        echo 'generated ', $v, "\n";
+    }
+});
+$v = $nested->start();
+foreach ([2, 3, null, 'value', 'yielded'] as $input) {
+    echo 'suspended with ', $v, "\n";
+    $v = $nested->resume($input);
+}
+var_dump($nested->isTerminated());
+$abandoned = new Fiber(function () {
+    array_map(function ($v) { return Fiber::suspend($v); }, [1]);
+    echo "not resumed\n";
+});
+$abandoned->start();
+unset($abandoned);
+echo "released\n";
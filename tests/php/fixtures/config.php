@@ -0,0 +1,3 @@
+<?php
+$loaded = $loaded + 1;
+return ['name' => 'shop', 'debug' => false];
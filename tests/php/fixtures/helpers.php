@@ -0,0 +1,2 @@
+<?php
+function helper_greeting() { return 'hello from ' . substr(__FILE__, strrpos(__FILE__, '/') + 1); }
@@ -0,0 +1,8 @@
+<?php
+namespace Shop;
+
+class Cart implements \Countable {
+    private $items = [];
+    public function add($item) { $this->items[] = new Item($item); return $this; }
+    public function count(): int { return count($this->items); }
+}
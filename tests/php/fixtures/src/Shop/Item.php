@@ -0,0 +1,7 @@
+<?php
+namespace Shop;
+
+class Item {
+    public $name;
+    public function __construct($name) { $this->name = $name; }
+}
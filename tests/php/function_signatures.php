@@ -0,0 +1,44 @@
+<?php
+declare(strict_types=0);
+
+function greet(string $greeting, string $name = 'world', string ...$rest): string {
+    $all = $greeting . ', ' . $name;
+    foreach ($rest as $other) {
+        $all = $all . ' & ' . $other;
+    }
+    return $all;
+}
+echo greet('Hi'), "\n";
+echo greet('Hi', 'ann', 'bob', 'cy'), "\n";
+echo greet(name: 'dee', greeting: 'Yo'), "\n";
+
+function addOne(array &$list, int $n = 1): void { $list[] = $n; }
+$a = [];
+addOne($a);
+addOne($a, 5);
+echo implode(',', $a), "\n";
+
+function sum(int ...$nums) { return array_sum($nums); }
+echo sum(1, 2, 3), ' ', sum(...[4, 5]), "\n";
+
+function opts(int $a, ?int $b = null, int|string $c = 'c') { var_dump($a, $b, $c); }
+opts(1, c: 7);
+opts('5');
+
+function typed(int $x): int { return $x; }
+try {
+    typed('abc');
+} catch (TypeError $e) {
+    echo $e->getMessage(), "\n";
+}
+try {
+    greet();
+} catch (ArgumentCountError $e) {
+    echo $e->getMessage(), "\n";
+}
+echo greet('a', extra: 'x'), "\n";
+try {
+    typed(nope: 1);
+} catch (Error $e) {
+    echo $e->getMessage(), "\n";
+}
@@ -0,0 +1,93 @@
+<?php
+function countTo($n) {
+    for ($i = 1; $i <= $n; $i = $i + 1) {
+        yield $i;
+    }
+    return 'done';
+}
+$gen = countTo(3);
+foreach ($gen as $k => $v) {
+    echo $k, '=>', $v, ' ';
+}
+echo $gen->getReturn(), "\n";
+
+function keyed() {
+    yield 'a' => 1;
+    yield 'b' => 2;
+}
+function outer() {
+    yield 0;
+    $r = yield from countTo(2);
+    yield from keyed();
+    yield $r;
+}
+foreach (outer() as $k => $v) {
+    echo $k, ':', $v, ' ';
+}
+echo "\n";
+
+function logger() {
+    $received = [];
+    while (true) {
+        $line = yield count($received);
+        if ($line === null) {
+            return $received;
+        }
+        $received[] = $line;
+    }
+}
+$log = logger();
+echo $log->current(), ' ';
+echo $log->send('first'), ' ';
+echo $log->send('second'), "\n";
+
+function guarded() {
+    try {
+        yield 1;
+        yield 2;
+    } catch (Exception $e) {
+        echo 'caught ', $e->getMessage(), "\n";
+        yield 99;
+    } finally {
+        echo "generator finally\n";
+    }
+}
+$g = guarded();
+$g->current();
+echo $g->throw(new Exception('boom')), "\n";
+$g->next();
+var_dump($g->valid());
+var_dump($g instanceof Traversable, $g instanceof Iterator);
+
+$lazy = (function () { echo "started\n"; yield 1; })();
+echo "before\n";
+$lazy->current();
+try {
+    foreach ($gen as $v) {}
+} catch (Exception $e) {
+    echo $e->getMessage(), "\n";
+}
+
+function released($how) {
+    try {
+        echo $how, ' start ';
+        yield 1;
+        yield 2;
+    } finally {
+        echo $how, ' finally ';
+    }
+}
+$g = released('unset');
+$g->current();
+unset($g);
+echo "after unset\n";
+foreach (released('break') as $v) {
+    break;
+}
+echo "after break\n";
+function firstReleased() {
+    foreach (released('return') as $v) {
+        return $v;
+    }
+}
+echo firstReleased(), " after return\n";
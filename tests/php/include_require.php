@@ -0,0 +1,10 @@
+<?php
+$loaded = 0;
+$config = require __DIR__ . '/fixtures/config.php';
+echo $config['name'], ' ', $loaded, "\n";
+$again = include __DIR__ . '/fixtures/config.php';
+echo $loaded, "\n";
+var_dump(require_once __DIR__ . '/fixtures/helpers.php');
+var_dump(require_once __DIR__ . '/fixtures/helpers.php');
+echo helper_greeting(), "\n";
+var_dump(include __DIR__ . '/fixtures/missing.php');
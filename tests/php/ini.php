@@ -0,0 +1,7 @@
+<?php
+echo ini_get('precision'), ' ', ini_get('default_charset'), ' ', (int) (ini_get('no.such.option') === false), "\n";
+echo ini_set('precision', 10), ' ', ini_get('precision'), "\n";
+ini_restore('precision');
+echo ini_get('precision'), ' ', (int) (ini_set('no.such.option', 1) === false), "\n";
+echo (int) extension_loaded('core'), (int) extension_loaded('missing'), ' ', get_loaded_extensions()[0], "\n";
+ini_get();
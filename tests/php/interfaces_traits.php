@@ -0,0 +1,45 @@
+<?php
+interface HasName { const PREFIX = 'n:'; public function name(); }
+interface Greets extends HasName { public function greet($who); }
+
+abstract class Base implements Greets {
+    abstract protected function suffix();
+    public function greet($who) { return 'Hello ' . $who . ' from ' . $this->name() . $this->suffix(); }
+}
+
+trait Loud {
+    public function shout() { return 'LOUD'; }
+    public static function make() { return new static(); }
+    abstract public function name();
+}
+trait Quiet {
+    public function shout() { return 'quiet'; }
+    public function whisper() { return 'psst'; }
+}
+
+final class Person extends Base {
+    use Loud, Quiet {
+        Loud::shout insteadof Quiet;
+        Quiet::shout as hush;
+        whisper as protected;
+    }
+    public function name() { return self::PREFIX . 'ann'; }
+    protected function suffix() { return '!'; }
+}
+
+$p = Person::make();
+echo $p->greet('bob'), "\n";
+echo $p->shout(), ' ', $p->hush(), "\n";
+var_dump($p instanceof HasName, $p instanceof Greets, $p instanceof Base);
+var_dump($p instanceof Quiet);
+try {
+    $p->whisper();
+} catch (Error $e) {
+    echo $e->getMessage(), "\n";
+}
+try {
+    new Base();
+} catch (Error $e) {
+    echo $e->getMessage(), "\n";
+}
+
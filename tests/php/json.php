@@ -0,0 +1,31 @@
+<?php
+$data = ['name' => 'Zoë', 'tags' => ['a', 'b'], 'price' => 9.90, 'ok' => true, 'none' => null, 'url' => 'a/b', 'empty' => [], 'obj' => new stdClass()];
+echo json_encode($data), "\n";
+echo json_encode($data, JSON_UNESCAPED_UNICODE + JSON_UNESCAPED_SLASHES + JSON_PRESERVE_ZERO_FRACTION), "\n";
+echo json_encode([1 => 'a', 2 => 'b']), ' ', json_encode([0 => 'a', 1 => 'b']), ' ', json_encode([], JSON_FORCE_OBJECT), "\n";
+echo json_encode(['a' => [1, 2], 'b' => []], JSON_PRETTY_PRINT), "\n";
+echo json_encode('<tag> & "quotes" \'apos\''), ' ', json_encode('<>&\'"', JSON_HEX_TAG + JSON_HEX_AMP + JSON_HEX_APOS + JSON_HEX_QUOT), "\n";
+echo json_encode(0.1 + 0.2), ' ', json_encode(10.0), ' ', json_encode('123', JSON_NUMERIC_CHECK), "\n";
+
+class Item implements JsonSerializable {
+    private $id;
+    public $visible = 'yes';
+    public function __construct($id) { $this->id = $id; }
+    public function jsonSerialize(): mixed { return ['id' => $this->id]; }
+}
+class Plain { public $a = 1; protected $b = 2; private $c = 3; }
+echo json_encode([new Item(7), new Plain()]), "\n";
+
+var_dump(json_decode('{"a":{"b":[1,2.5,"x",null,true]},"big":12345678901234567890}'));
+var_dump(json_decode('{"a":{"b":[1]}}', true));
+var_dump(json_decode('[1,2', true), json_last_error(), json_last_error_msg());
+var_dump(json_decode('"é😀"'));
+var_dump(json_decode('[[1]]', true, 1), json_last_error_msg());
+try {
+    json_decode('{', false, 512, JSON_THROW_ON_ERROR);
+} catch (JsonException $e) {
+    echo $e->getMessage(), ' ', $e->getCode(), "\n";
+}
+$bad = chr(177) . '1';
+var_dump(json_encode($bad), json_last_error_msg());
+echo json_encode($bad, JSON_INVALID_UTF8_SUBSTITUTE), ' ', json_encode($bad, JSON_INVALID_UTF8_IGNORE), "\n";
@@ -0,0 +1,59 @@
+<?php
+class Bag {
+    private $data = [];
+    public function __get($name) {
+        echo 'get ', $name, "\n";
+        if (isset($this->data[$name])) {
+            return $this->data[$name];
+        }
+        return null;
+    }
+    public function __set($name, $value) { echo 'set ', $name, "\n"; $this->data[$name] = $value; }
+    public function __isset($name) { return isset($this->data[$name]); }
+    public function __unset($name) { echo 'unset ', $name, "\n"; unset($this->data[$name]); }
+    public function __call($name, $args) { return $name . '(' . implode(',', $args) . ')'; }
+    public static function __callStatic($name, $args) { return 'static ' . $name; }
+    public function __toString() { return 'Bag[' . implode(',', array_keys($this->data)) . ']'; }
+    public function __invoke($x) { return $x * 2; }
+}
+
+$b = new Bag();
+$b->color = 'red';
+echo $b->color, "\n";
+var_dump(isset($b->color), isset($b->size), empty($b->color));
+unset($b->color);
+echo $b->anything(1, 2), ' ', Bag::build(), "\n";
+$b->size = 3;
+echo $b, ' ', strlen($b), ' ', $b(21), "\n";
+echo implode(',', array_map($b, [1, 2])), "\n";
+
+class Node {
+    public $children = [];
+    public $id;
+    public function __construct($id) { $this->id = $id; }
+    public function __clone() {
+        foreach ($this->children as $k => $child) {
+            $this->children[$k] = clone $child;
+        }
+        $this->id = $this->id . "'";
+    }
+    public function __destruct() { echo 'destroy ', $this->id, "\n"; }
+}
+$root = new Node('root');
+$root->children[] = new Node('leaf');
+$copy = clone $root;
+$copy->children[0]->id = 'changed';
+echo $root->children[0]->id, ' ', $copy->id, "\n";
+unset($copy);
+echo "after unset\n";
+
+class Money {
+    private $cents;
+    public function __construct($cents) { $this->cents = $cents; }
+    public function __serialize(): array { return ['c' => $this->cents]; }
+    public function __unserialize(array $data): void { $this->cents = $data['c'] * 10; }
+    public function cents() { return $this->cents; }
+}
+$m = serialize(new Money(5));
+echo $m, ' ', unserialize($m)->cents(), "\n";
+echo "end\n";
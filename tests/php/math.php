@@ -0,0 +1,33 @@
+<?php
+var_dump(abs(-5), abs(-2.5), intdiv(7, 2), 7 % -3, fmod(7.5, 2), max(1, '3', 2.5), min([4, 2, 8]), max('apple', 'banana'));
+var_dump(round(2.5), round(-2.5), round(1.955, 2), round(1234.5678, -2), round(5.045, 2), round(5.055, 2), floor(-1.5), ceil(-1.5));
+var_dump(sqrt(16), pow(2, 10), pow(2, -1), pow(2, 63), pi(), M_PI, exp(1), log(M_E), log(8, 2), log10(1000));
+var_dump(is_nan(sqrt(-1)), is_infinite(log(0)), is_finite(1.0), hypot(3, 4), deg2rad(180), fdiv(1, 0), intdiv(PHP_INT_MIN, 1));
+var_dump(base_convert('ff', 16, 2), bindec('1010'), decbin(10), hexdec('ff'), dechex(255), octdec('17'), decoct(15));
+var_dump(number_format(1234567.891), number_format(1234567.891, 2), number_format(1234.5, 2, ',', '.'), number_format(-0.4));
+try {
+    intdiv(1, 0);
+} catch (DivisionByZeroError $e) {
+    echo $e->getMessage(), "\n";
+}
+
+mt_srand(1);
+echo mt_rand(), ' ', mt_rand(), "\n";
+mt_srand(12345678);
+echo mt_rand(), ' ', mt_rand(), ' ', mt_rand(1, 100), "\n";
+mt_srand(12345678, MT_RAND_PHP);
+echo mt_rand(), ' ', mt_rand(), ' ', mt_rand(), "\n";
+echo mt_getrandmax(), ' ', getrandmax(), "\n";
+$r = random_int(10, 20);
+var_dump($r >= 10 && $r <= 20);
+
+echo bcadd('1234567890123456789', '987654321987654321'), ' ', bcsub('1', '2.5', 2), ' ', bcmul('-1.5', '1.5', 3), "\n";
+echo bcdiv('1', '3', 10), ' ', bcmod('10', '3'), ' ', bcpow('2', '100'), ' ', bcsqrt('2', 8), "\n";
+echo bccomp('1.001', '1.0001', 3), ' ', bccomp('-1', '1'), ' ', bcpowmod('4', '13', '497'), "\n";
+bcscale(4);
+echo bcdiv('22', '7'), ' ', bcadd('0.1', '0.2'), "\n";
+try {
+    bcdiv('1', '0');
+} catch (DivisionByZeroError $e) {
+    echo $e->getMessage(), "\n";
+}
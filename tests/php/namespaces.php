@@ -0,0 +1,22 @@
+<?php
+namespace App\Util {
+    const VERSION = '1.2';
+    function strlen($s) { return 'shadowed ' . \strlen($s); }
+    class Str {
+        public static function upper($s) { return strtoupper($s); }
+    }
+}
+
+namespace App {
+    use App\Util\Str;
+    use App\Util\Str as S;
+    use function App\Util\strlen;
+    use const App\Util\VERSION;
+
+    echo Str::upper('abc'), ' ', S::class, "\n";
+    echo strlen('abcd'), ' ', \strlen('abcd'), "\n";
+    echo VERSION, ' ', __NAMESPACE__, "\n";
+    echo namespace\Util\Str::upper('x'), "\n";
+    var_dump(strtoupper('fallback'));
+    echo \App\Util\Str::class, "\n";
+}
@@ -0,0 +1,25 @@
+<?php
+var_dump(preg_match('/(\d+)-(\d+)/', 'call 555-1234 now', $m), $m);
+preg_match('/(?<year>\d{4})-(?<month>\d\d)/', 'on 2024-05-17', $m, PREG_OFFSET_CAPTURE);
+print_r($m);
+echo preg_match_all('/\b(\w)(\w*)\b/', 'the quick brown fox', $m), "\n";
+echo implode(',', $m[1]), ' ', implode(',', $m[2]), "\n";
+preg_match_all('/(\w)=(\d)/', 'a=1 b=2', $m, PREG_SET_ORDER);
+echo json_encode($m), "\n";
+echo preg_replace('/\s+/', ' ', "a  b \t\n c"), "\n";
+echo preg_replace('/(\w+) (\w+)/', '$2 ${1}!', 'hello world'), "\n";
+echo preg_replace(['/a/', '/b/'], ['b', 'c'], 'ab'), "\n";
+echo preg_replace('/x/i', 'y', 'XxX', 2, $count), ' ', $count, "\n";
+echo preg_replace_callback('/\d+/', function ($m) { return $m[0] * 2; }, 'a1 b22 c333'), "\n";
+print_r(preg_split('/[\s,]+/', "a, b  c,d"));
+print_r(preg_split('//', 'abc', -1, PREG_SPLIT_NO_EMPTY));
+print_r(preg_split('/(-)/', 'a-b-c', 2, PREG_SPLIT_DELIM_CAPTURE));
+echo preg_quote('Hello.World?(1+1)/2', '/'), "\n";
+print_r(preg_grep('/^\d+$/', ['1', 'a', '22', 'b3']));
+print_r(preg_grep('/^\d+$/', ['1', 'a', '22'], PREG_GREP_INVERT));
+var_dump(preg_match('/^\p{Lu}\w+$/u', 'Ärger'), preg_match('/colou?r/', 'The color'), preg_match('/^abc$/m', "x\nabc\ny"));
+var_dump(preg_match('/(a)(b)?(c)/', 'ac', $m), $m);
+var_dump(preg_match('/(a)(b)?(c)/', 'ac', $m, PREG_UNMATCHED_AS_NULL), $m);
+var_dump(preg_match('/(?i)HELLO/', 'say hello'), preg_match('/a.c/s', "a\nc"), preg_match('/^(?:(\d+)|(\w+))$/', 'abc', $m), $m);
+var_dump(preg_match('/(/', 'x'));
+echo preg_last_error(), ' ', preg_last_error_msg(), "\n";
@@ -0,0 +1,38 @@
+<?php
+printf("%d|%5d|%-5d|%05d|%+d|%x|%X|%o|%b|%c\n", 42, 42, 42, 42, 42, 255, 255, 8, 5, 65);
+printf("%.2f|%10.3f|%-10.1f|%e|%.3E|%g|%G\n", 3.14159, 2.5, 1.26, 1234.5678, 0.000123, 0.00001234, 100000000000000000000.0);
+printf("%s|%10s|%-10s|%'*10s|%.2s|%u\n", 'str', 'right', 'left', 'pad', 'truncate', -1);
+printf('%1$s %2$s %1$s|%%' . "\n", 'a', 'b');
+echo sprintf('%08.3f', -3.14159), ' ', sprintf('%5.1e', 12345.678), ' ', sprintf("%'.10d", 42), "\n";
+echo vsprintf('%2$s-%1$s', ['x', 'y']), ' ', sprintf('%F', 1.5), "\n";
+$n = printf('%s', "len\n");
+echo $n, "\n";
+vprintf("%s and %s\n", ['this', 'that']);
+var_dump(sscanf('age: 25 name: Bob', 'age: %d name: %s'));
+$parsed = sscanf('12 apples', '%d %s', $qty, $what);
+echo $parsed, ' ', $qty, ' ', $what, "\n";
+try {
+    sprintf('%d %d', 1);
+} catch (ArgumentCountError $e) {
+    echo $e->getMessage(), "\n";
+}
+try {
+    sprintf('%z', 1);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+$n = fprintf(STDOUT, "%s has %d items\n", 'cart', 3);
+echo $n, "\n";
+vfprintf(STDOUT, "%05.1f%%\n", [42.5]);
+var_dump(STDOUT, STDERR);
+echo STDOUT, "\n";
+try {
+    fprintf(STDOUT, '%d %d', 1);
+} catch (ArgumentCountError $e) {
+    echo $e->getMessage(), "\n";
+}
+try {
+    fprintf('out', 'x');
+} catch (TypeError $e) {
+    echo $e->getMessage(), "\n";
+}
@@ -0,0 +1,47 @@
+<?php
+$total = 10;
+function addToTotal($n) {
+    global $total;
+    $total = $total + $n;
+}
+addToTotal(5);
+echo $total, "\n";
+
+function counter() {
+    static $calls = 0;
+    $calls = $calls + 1;
+    return $calls;
+}
+counter(); counter();
+echo counter(), "\n";
+
+$a = [1, 2, 3];
+$b = &$a[1];
+$copy = $a;
+$b = 20;
+echo implode(',', $a), ' ', implode(',', $copy), "\n";
+
+$list = [1, 2, 3];
+foreach ($list as &$v) {
+    $v = $v * 2;
+}
+unset($v);
+echo implode(',', $list), "\n";
+
+$x = 1;
+$y = &$x;
+unset($y);
+$y = 5;
+echo $x, "\n";
+
+$arr = ['k' => null, 'z' => 0];
+var_dump(isset($arr['k']), isset($arr['z']), isset($arr['nope']['deeper']));
+var_dump(empty($arr['z']), empty($arr['nope']), empty($undefined));
+unset($arr['k']);
+echo implode(',', array_keys($arr)), "\n";
+
+function setFirst(array &$data, $v) { $data[0] = $v; }
+$data = [1];
+setFirst($data, 99);
+echo $data[0], "\n";
+echo $undefinedVar;
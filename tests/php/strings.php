@@ -0,0 +1,24 @@
+<?php
+echo strtoupper('héllo'), ' ', ucwords('hello big world'), ' ', lcfirst('ABC'), "\n";
+echo str_pad('7', 3, '0', STR_PAD_LEFT), ' ', str_repeat('=', 5), ' ', strrev('abc'), "\n";
+var_dump(strpos('hello', 'l'), strrpos('hello', 'l'), stripos('HeLLo', 'll'), strpos('abc', 'z'));
+echo substr('abcdef', -3, 2), ' ', substr_count('hello hello', 'll'), ' ', ucfirst(trim('  x  ')), "\n";
+echo implode('|', explode(',', 'a,b,,c', -1)), ' ', implode('|', str_split('abcde', 2)), "\n";
+echo str_replace(['a', 'b'], ['b', 'c'], 'ab', $count), ' ', $count, "\n";
+echo wordwrap('The quick brown fox', 10, "\n", true), "\n";
+echo nl2br("a\nb"), ' ', htmlspecialchars('<a href="x">T&C</a>'), "\n";
+echo number_format(1234567.891, 2), ' ', number_format(1234.5), ' ', number_format(0.5), "\n";
+echo strcmp('a', 'b'), ' ', strcasecmp('HELLO', 'hello'), ' ', strncmp('abcd', 'abef', 2), ' ', strnatcmp('img12', 'img10'), "\n";
+echo similar_text('World', 'Word'), ' ', levenshtein('kitten', 'sitting'), "\n";
+echo ltrim('xxhixx', 'x'), ' ', rtrim('1.500', '0'), ' ', trim('[a]', '[]'), "\n";
+echo strtr('Hi all', ['Hi' => 'Hello', 'all' => 'everyone']), ' ', strtr('abc', 'ab', 'xy'), "\n";
+echo strstr('user@example.com', '@'), ' ', strstr('user@example.com', '@', true), ' ', strrchr('a/b/c', '/'), ' ', stristr('HayStack', 'st'), "\n";
+echo ucwords('hello_world-foo', '_-'), ' ', str_ireplace('WORLD', 'there', 'Hello world'), ' ', strripos('aXbx', 'x'), "\n";
+var_dump(str_starts_with('abc', 'ab'), str_ends_with('abc', 'bc'), str_contains('abc', ''));
+echo ord('A'), ' ', chr(97), ' ', strnatcasecmp('File2', 'file10'), ' ', strncasecmp('Hello', 'help', 3), "\n";
+echo implode(',', str_split('abc')), ' ', join('-', [1, 2]), ' ', chop("line\n"), '|', strlen(''), "\n";
+try {
+    str_repeat('x', -1);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
@@ -0,0 +1,80 @@
+//! Runs the `tests/phpt/**/*.phpt` files, tests in the format of PHP's own
+//! test suite, with the cases and expected output taken from it. Only the
+//! `--TEST--`, `--FILE--` and `--EXPECT--` sections are supported, and as
+//! in PHP's runner the output and expectation are compared trimmed. The
+//! lexer takes scripts that are PHP to the end, so the closing `?>` the
+//! files end with is dropped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use phpvm::Engine;
+
+struct Phpt {
+    file: String,
+    expect: String,
+}
+
+fn parse(path: &Path) -> Phpt {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let mut sections: Vec<(&str, String)> = Vec::new();
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed.len() > 4 && trimmed.starts_with("--") && trimmed.ends_with("--") {
+            sections.push((&trimmed[2..trimmed.len() - 2], String::new()));
+        } else if let Some((_, body)) = sections.last_mut() {
+            body.push_str(line);
+        }
+    }
+    let mut section = |name: &str| {
+        let i = sections.iter().position(|(n, _)| *n == name);
+        let i = i.unwrap_or_else(|| panic!("{}: no --{}-- section", path.display(), name));
+        std::mem::take(&mut sections[i].1)
+    };
+    let file = section("FILE");
+    let file = file.trim_end().strip_suffix("?>").unwrap_or(&file).to_string();
+    Phpt { file, expect: section("EXPECT") }
+}
+
+fn run(path: &Path, source: &str) -> String {
+    let mut engine = Engine::capturing();
+    let file = path.display().to_string();
+    let result = engine.run_source(source, &file).and_then(|_| engine.shutdown());
+    let mut out = String::from_utf8_lossy(&engine.take_output()).into_owned();
+    if let Err(e) = result {
+        out.push_str(&format!("{}\n", e));
+    }
+    out
+}
+
+fn collect(dir: &Path, tests: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, tests);
+        } else if path.extension().is_some_and(|ext| ext == "phpt") {
+            tests.push(path);
+        }
+    }
+}
+
+#[test]
+fn phpt_files() {
+    let mut tests = Vec::new();
+    collect(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/phpt"), &mut tests);
+    tests.sort();
+    let mut failures = Vec::new();
+    for path in &tests {
+        let test = parse(path);
+        let actual = run(path, &test.file);
+        if actual.trim() != test.expect.trim() {
+            failures.push(format!(
+                "{}\n--- expected\n{}\n--- actual\n{}\n",
+                path.display(),
+                test.expect.trim(),
+                actual.trim()
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{} of {} phpt file(s) failed:\n\n{}", failures.len(), tests.len(), failures.join("\n"));
+}
@@ -0,0 +1,11 @@
+--TEST--
+debug_zval_dump() of scalars
+--FILE--
+<?php
+debug_zval_dump(1, 1.5, true, null);
+?>
+--EXPECT--
+int(1)
+float(1.5)
+bool(true)
+NULL
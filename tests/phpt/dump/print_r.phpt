@@ -0,0 +1,68 @@
+--TEST--
+print_r() of values, nested arrays, objects and its return mode
+--FILE--
+<?php
+class Point {
+    public $x = 1;
+    protected $y = 2.5;
+    private $z = 'z';
+    public $n;
+}
+print_r(1);
+echo "|";
+print_r(1.0);
+echo "|";
+print_r(true);
+echo "|";
+print_r(false);
+echo "|";
+print_r(null);
+echo "|";
+print_r("str");
+echo "\n";
+print_r([1, 'a' => [true, null, 1.5], 'e' => []]);
+print_r(new Point());
+$o = new stdClass();
+$o->self = $o;
+print_r($o);
+$s = print_r([1], true);
+var_dump($s);
+var_dump(print_r("x", true));
+var_dump(print_r("x"));
+?>
+--EXPECT--
+1|1|1|||str
+Array
+(
+    [0] => 1
+    [a] => Array
+        (
+            [0] => 1
+            [1] => 
+            [2] => 1.5
+        )
+
+    [e] => Array
+        (
+        )
+
+)
+Point Object
+(
+    [x] => 1
+    [y:protected] => 2.5
+    [z:Point:private] => z
+    [n] => 
+)
+stdClass Object
+(
+    [self] => stdClass Object
+ *RECURSION*
+)
+string(23) "Array
+(
+    [0] => 1
+)
+"
+string(1) "x"
+xbool(true)
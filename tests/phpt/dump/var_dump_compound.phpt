@@ -0,0 +1,73 @@
+--TEST--
+var_dump() of arrays, objects, handles (enum cases take one) and recursion
+--FILE--
+<?php
+class Point {
+    public $x = 1;
+    protected $y = 2.5;
+    private $z = 'z';
+    public $n;
+}
+enum Suit: string {
+    case Hearts = 'H';
+}
+var_dump([]);
+var_dump([1 => ['x'], 'k' => [], -5 => null]);
+var_dump(new Point());
+var_dump(Suit::Hearts);
+$a = new stdClass();
+var_dump($a);
+unset($a);
+$b = new stdClass();
+$c = new stdClass();
+var_dump($b, $c);
+$o = new stdClass();
+$o->self = $o;
+$o->list = [1];
+var_dump($o);
+$arr = [1];
+$arr[] = &$arr;
+var_dump(count($arr));
+?>
+--EXPECT--
+array(0) {
+}
+array(3) {
+  [1]=>
+  array(1) {
+    [0]=>
+    string(1) "x"
+  }
+  ["k"]=>
+  array(0) {
+  }
+  [-5]=>
+  NULL
+}
+object(Point)#1 (4) {
+  ["x"]=>
+  int(1)
+  ["y":protected]=>
+  float(2.5)
+  ["z":"Point":private]=>
+  string(1) "z"
+  ["n"]=>
+  NULL
+}
+enum(Suit::Hearts)
+object(stdClass)#2 (0) {
+}
+object(stdClass)#2 (0) {
+}
+object(stdClass)#3 (0) {
+}
+object(stdClass)#4 (2) {
+  ["self"]=>
+  *RECURSION*
+  ["list"]=>
+  array(1) {
+    [0]=>
+    int(1)
+  }
+}
+int(2)
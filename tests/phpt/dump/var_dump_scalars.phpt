@@ -0,0 +1,35 @@
+--TEST--
+var_dump() of scalars, with floats at serialize_precision -1
+--FILE--
+<?php
+var_dump(1, -7, true, false, null);
+var_dump(1.0, -0.0, 0.5, 0.1 + 0.2, 1.0 / 3.0);
+var_dump(1.0 / 100000.0, 100000000000000000000.0, 123456789012345678.0);
+var_dump(PHP_INT_MAX + 1, (float) PHP_INT_MAX);
+var_dump(INF, -INF, NAN);
+var_dump("", "abc", "multi\nline", 'it\'s');
+?>
+--EXPECT--
+int(1)
+int(-7)
+bool(true)
+bool(false)
+NULL
+float(1)
+float(-0)
+float(0.5)
+float(0.30000000000000004)
+float(0.3333333333333333)
+float(1.0E-5)
+float(1.0E+20)
+float(1.2345678901234568E+17)
+float(9.223372036854776E+18)
+float(9.223372036854776E+18)
+float(INF)
+float(-INF)
+float(NAN)
+string(0) ""
+string(3) "abc"
+string(10) "multi
+line"
+string(4) "it's"
@@ -0,0 +1,98 @@
+--TEST--
+var_export() of values, arrays and objects as PHP code
+--FILE--
+<?php
+class Point {
+    public $x = 1;
+    protected $y = 2.5;
+    private $z = 'z';
+}
+enum Suit: string {
+    case Hearts = 'H';
+}
+var_export(1);
+echo "\n";
+var_export(1.0);
+echo "\n";
+var_export(-0.0);
+echo "\n";
+var_export(0.1 + 0.2);
+echo "\n";
+var_export(true);
+echo "\n";
+var_export(null);
+echo "\n";
+var_export("it's a \\ test");
+echo "\n";
+var_export([1, 'a' => [true, null], 'b' => 1.5, 5 => "x"]);
+echo "\n";
+var_export([]);
+echo "\n";
+var_export(new Point());
+echo "\n";
+$o = new stdClass();
+$o->a = 1;
+$o->b = [2];
+var_export($o);
+echo "\n";
+var_export(['o' => $o, 'e' => new stdClass()]);
+echo "\n";
+var_export(Suit::Hearts);
+echo "\n";
+var_export([Suit::Hearts]);
+echo "\n";
+var_dump(var_export([1 => 2], true));
+?>
+--EXPECT--
+1
+1.0
+-0.0
+0.30000000000000004
+true
+NULL
+'it\'s a \\ test'
+array (
+  0 => 1,
+  'a' => 
+  array (
+    0 => true,
+    1 => NULL,
+  ),
+  'b' => 1.5,
+  5 => 'x',
+)
+array (
+)
+\Point::__set_state(array(
+   'x' => 1,
+   'y' => 2.5,
+   'z' => 'z',
+))
+(object) array(
+   'a' => 1,
+   'b' => 
+  array (
+    0 => 2,
+  ),
+)
+array (
+  'o' => 
+  (object) array(
+     'a' => 1,
+     'b' => 
+    array (
+      0 => 2,
+    ),
+  ),
+  'e' => 
+  (object) array(
+  ),
+)
+\Suit::Hearts
+array (
+  0 => 
+  \Suit::Hearts,
+)
+string(19) "array (
+  1 => 2,
+)"
@@ -0,0 +1,86 @@
+--TEST--
+bcmath arithmetic, truncation to scale, comparison and bcscale()
+--FILE--
+<?php
+var_dump(bcadd("1", "2"));
+var_dump(bcadd("-1.5", "1.25", 2));
+var_dump(bcadd("99999999999999999999", "1"));
+var_dump(bcadd("0.0001", "-0.0001", 4));
+var_dump(bcsub("1", "2", 3));
+var_dump(bcmul("2", "3.5", 1));
+var_dump(bcmul("1.34747474747", "35", 3));
+var_dump(bcmul("-0.1", "0.1", 1));
+var_dump(bcdiv("1", "3", 5));
+var_dump(bcdiv("-1", "3", 3));
+var_dump(bcdiv("10", "4"));
+var_dump(bcdiv("105", "6.55957", 3));
+try {
+    bcdiv("1", "0");
+} catch (DivisionByZeroError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(bcmod("10", "3"));
+var_dump(bcmod("-10", "3"));
+var_dump(bcmod("5.7", "1.3", 1));
+try {
+    bcmod("1", "0");
+} catch (DivisionByZeroError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(bcpow("4.2", "3", 2));
+var_dump(bcpow("2", "64"));
+var_dump(bcpow("2", "-2", 4));
+var_dump(bcpow("5", "0"));
+var_dump(bcsqrt("2", 3));
+var_dump(bcsqrt("0.0004", 4));
+var_dump(bcsqrt("16"));
+var_dump(bcpowmod("4", "3", "5"));
+var_dump(bcpowmod("2", "100", "1000000007"));
+var_dump(bccomp("1.001", "1", 2));
+var_dump(bccomp("1.001", "1", 3));
+var_dump(bccomp("-1", "1"));
+try {
+    bcadd("1e5", "1");
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(bcscale(3));
+var_dump(bcscale());
+var_dump(bcadd("1", "2"));
+var_dump(bcdiv("2", "3"));
+?>
+--EXPECT--
+string(1) "3"
+string(5) "-0.25"
+string(21) "100000000000000000000"
+string(6) "0.0000"
+string(6) "-1.000"
+string(3) "7.0"
+string(6) "47.161"
+string(3) "0.0"
+string(7) "0.33333"
+string(6) "-0.333"
+string(1) "2"
+string(6) "16.007"
+Division by zero
+string(1) "1"
+string(2) "-1"
+string(3) "0.5"
+Modulo by zero
+string(5) "74.08"
+string(20) "18446744073709551616"
+string(6) "0.2500"
+string(1) "1"
+string(5) "1.414"
+string(6) "0.0200"
+string(1) "4"
+string(1) "4"
+string(9) "976371285"
+int(0)
+int(1)
+int(-1)
+bcadd(): Argument #1 ($num1) is not well-formed
+int(0)
+int(3)
+string(5) "3.000"
+string(5) "0.666"
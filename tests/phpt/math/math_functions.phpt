@@ -0,0 +1,78 @@
+--TEST--
+abs(), floor(), ceil(), intdiv(), fmod(), pow(), sqrt(), min()/max() and base conversion
+--FILE--
+<?php
+var_dump(abs(-5), abs(-5.5), abs(PHP_INT_MIN));
+var_dump(floor(-1.5), ceil(-1.5), ceil(4), floor(5));
+var_dump(intdiv(7, 2), intdiv(-7, 2));
+try {
+    intdiv(PHP_INT_MIN, -1);
+} catch (ArithmeticError $e) {
+    echo $e->getMessage(), "\n";
+}
+try {
+    intdiv(1, 0);
+} catch (DivisionByZeroError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(fmod(10, 3), fmod(-10, 3), fmod(5.5, 1.5));
+var_dump(pow(2, 10), pow(2, -1), pow(2, 63), pow("3", "2"), pow(-2, 3));
+var_dump(sqrt(16), sqrt(-1));
+var_dump(max("apple", "banana"), max([1, 2, 3], [1, 2, 4]), max("10", 9), max("abc", 0), min("abc", 0));
+var_dump(max(1, 2.5), min(array(3, -1, 2)));
+try {
+    max([]);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(base_convert("ff", 16, 2), base_convert("zz", 36, 10), base_convert("A37334", 16, 2));
+var_dump(bindec("111"), hexdec("ff"), octdec("777"));
+var_dump(dechex(255), decbin(10), decoct(8), dechex(-1));
+?>
+--EXPECT--
+int(5)
+float(5.5)
+float(9.223372036854776E+18)
+float(-2)
+float(-1)
+float(4)
+float(5)
+int(3)
+int(-3)
+Division of PHP_INT_MIN by -1 is not an integer
+Division by zero
+float(1)
+float(-1)
+float(1)
+int(1024)
+float(0.5)
+float(9.223372036854776E+18)
+int(9)
+int(-8)
+float(4)
+float(NAN)
+string(6) "banana"
+array(3) {
+  [0]=>
+  int(1)
+  [1]=>
+  int(2)
+  [2]=>
+  int(4)
+}
+string(2) "10"
+string(3) "abc"
+int(0)
+float(2.5)
+int(-1)
+max(): Argument #1 ($value) must contain at least one element
+string(8) "11111111"
+string(4) "1295"
+string(24) "101000110111001100110100"
+int(7)
+int(255)
+int(511)
+string(2) "ff"
+string(4) "1010"
+string(2) "10"
+string(16) "ffffffffffffffff"
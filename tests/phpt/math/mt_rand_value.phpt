@@ -0,0 +1,85 @@
+--TEST--
+mt_rand() sequences after mt_srand() in both modes, their sums, and ranges
+--FILE--
+<?php
+mt_srand(12345678);
+for ($i = 0; $i < 16; $i = $i + 1) {
+    echo mt_rand(), PHP_EOL;
+}
+echo PHP_EOL;
+$x = 0;
+for ($i = 0; $i < 1024; $i = $i + 1) {
+    $x = $x + mt_rand();
+}
+echo $x, PHP_EOL;
+echo PHP_EOL;
+mt_srand(12345678, MT_RAND_PHP);
+for ($i = 0; $i < 16; $i = $i + 1) {
+    echo mt_rand(), PHP_EOL;
+}
+echo PHP_EOL;
+$x = 0;
+for ($i = 0; $i < 1024; $i = $i + 1) {
+    $x = $x + mt_rand();
+}
+echo $x, PHP_EOL;
+echo PHP_EOL;
+mt_srand(42);
+echo mt_rand(1, 100), " ", mt_rand(1, 100), " ", mt_rand(1, 100), " ", mt_rand(1, 100), " ", mt_rand(1, 100), PHP_EOL;
+echo mt_rand(-10, 10), " ", mt_rand(-10, 10), " ", mt_rand(-10, 10), " ", mt_rand(0, 1), PHP_EOL;
+mt_srand(42, MT_RAND_PHP);
+echo mt_rand(1, 100), " ", mt_rand(1, 100), " ", mt_rand(1, 100), " ", mt_rand(1, 100), " ", mt_rand(1, 100), PHP_EOL;
+mt_srand(1);
+echo mt_rand(), " ", mt_rand(), PHP_EOL;
+var_dump(mt_getrandmax());
+try {
+    mt_rand(10, 1);
+} catch (ValueError $e) {
+    echo $e->getMessage(), PHP_EOL;
+}
+?>
+--EXPECT--
+527860569
+1711027313
+1280820687
+688176834
+770499160
+412773096
+813703253
+898651287
+52508912
+757323740
+511765911
+274407457
+833082629
+1923803667
+1461450755
+1301698200
+
+1079836274018
+
+1614640687
+1711027313
+857485497
+688176834
+1386682158
+412773096
+813703253
+898651287
+2087374214
+1382556330
+1640700129
+1863374167
+1324097651
+1923803667
+676334965
+853386222
+
+1115268599555
+
+43 68 77 15 27
+7 9 -5 0
+64 80 96 82 28
+895547922 2141438069
+int(2147483647)
+mt_rand(): Argument #2 ($max) must be greater than or equal to argument #1 ($min)
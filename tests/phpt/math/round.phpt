@@ -0,0 +1,50 @@
+--TEST--
+round() with pre-rounding, negative precision and rounding modes
+--FILE--
+<?php
+var_dump(round(1.955, 2));
+var_dump(round(5.045, 2));
+var_dump(round(5.055, 2));
+var_dump(round(1.005, 2));
+var_dump(round(0.285, 2));
+var_dump(round(2.5));
+var_dump(round(-2.5));
+var_dump(round(1.45, 1));
+var_dump(round(-1.45, 1));
+var_dump(round(3.14159, 3));
+var_dump(round(1241757, -3));
+var_dump(round(5));
+var_dump(round(5.5));
+var_dump(round(9.5, 0, PHP_ROUND_HALF_EVEN));
+var_dump(round(8.5, 0, PHP_ROUND_HALF_EVEN));
+var_dump(round(9.5, 0, PHP_ROUND_HALF_ODD));
+var_dump(round(8.5, 0, PHP_ROUND_HALF_ODD));
+var_dump(round(9.5, 0, PHP_ROUND_HALF_DOWN));
+var_dump(round(-9.5, 0, PHP_ROUND_HALF_DOWN));
+var_dump(round(-1.55, 1, PHP_ROUND_HALF_EVEN));
+var_dump(round(1.55, 1, PHP_ROUND_HALF_DOWN));
+var_dump(round(1.54, 1, PHP_ROUND_HALF_UP));
+?>
+--EXPECT--
+float(1.96)
+float(5.05)
+float(5.06)
+float(1.01)
+float(0.29)
+float(3)
+float(-3)
+float(1.5)
+float(-1.5)
+float(3.142)
+float(1242000)
+float(5)
+float(6)
+float(10)
+float(8)
+float(9)
+float(9)
+float(9)
+float(-9)
+float(-1.6)
+float(1.5)
+float(1.5)
@@ -0,0 +1,31 @@
+--TEST--
+strtolower(), strtoupper(), ucfirst(), lcfirst() and ucwords()
+--FILE--
+<?php
+var_dump(strtolower("Mary Had A Little Lamb and She LOVED It So"));
+var_dump(strtoupper("Mary Had A Little Lamb and She LOVED It So"));
+var_dump(strtoupper("caf" . chr(233)) === "CAF" . chr(233));
+var_dump(ucfirst("hello world!"));
+var_dump(ucfirst("HELLO WORLD!"));
+var_dump(lcfirst("HelloWorld"));
+var_dump(ucwords("hello world!"));
+var_dump(ucwords(strtolower("HELLO WORLD!")));
+var_dump(ucwords("hello|world", "|"));
+var_dump(ucwords("hello_world-and friends"));
+var_dump(ucwords("hello  world\tagain\nnow"));
+var_dump(ucfirst(""));
+?>
+--EXPECT--
+string(42) "mary had a little lamb and she loved it so"
+string(42) "MARY HAD A LITTLE LAMB AND SHE LOVED IT SO"
+bool(true)
+string(12) "Hello world!"
+string(12) "HELLO WORLD!"
+string(10) "helloWorld"
+string(12) "Hello World!"
+string(12) "Hello World!"
+string(11) "Hello|World"
+string(23) "Hello_world-and Friends"
+string(22) "Hello  World	Again
+Now"
+string(0) ""
@@ -0,0 +1,54 @@
+--TEST--
+explode() limits and implode() argument forms
+--FILE--
+<?php
+var_dump(explode(",", "a,b,,c", -1));
+var_dump(explode(",", "a,b,c", 2));
+var_dump(explode(",", "a,b,c", 0));
+var_dump(explode(",", "a,b,c", -3));
+var_dump(explode(",", ""));
+var_dump(explode(", ", "x"));
+try {
+    explode("", "a");
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(implode(", ", array("a", "b", "c")));
+var_dump(implode(array("a", "b")));
+var_dump(implode("-", array(1, 2.5, true, false, null)));
+var_dump(implode(",", array()));
+?>
+--EXPECT--
+array(3) {
+  [0]=>
+  string(1) "a"
+  [1]=>
+  string(1) "b"
+  [2]=>
+  string(0) ""
+}
+array(2) {
+  [0]=>
+  string(1) "a"
+  [1]=>
+  string(3) "b,c"
+}
+array(1) {
+  [0]=>
+  string(5) "a,b,c"
+}
+array(0) {
+}
+array(1) {
+  [0]=>
+  string(0) ""
+}
+array(1) {
+  [0]=>
+  string(1) "x"
+}
+explode(): Argument #1 ($separator) cannot be empty
+string(7) "a, b, c"
+string(2) "ab"
+string(9) "1-2.5-1--"
+string(0) ""
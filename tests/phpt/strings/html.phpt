@@ -0,0 +1,26 @@
+--TEST--
+nl2br() and htmlspecialchars() flags and double encoding
+--FILE--
+<?php
+var_dump(nl2br("foo isn't\n bar"));
+var_dump(nl2br("a\nb", false));
+var_dump(nl2br("no breaks"));
+var_dump(htmlspecialchars("<a href='test'>Test</a>"));
+var_dump(htmlspecialchars("\"double\" & 'single'", ENT_NOQUOTES));
+var_dump(htmlspecialchars("\"double\" & 'single'", ENT_COMPAT));
+var_dump(htmlspecialchars("&amp; &"));
+var_dump(htmlspecialchars("&amp; &lt; &#039; &", ENT_QUOTES, 'UTF-8', false));
+var_dump(htmlspecialchars("plain"));
+?>
+--EXPECT--
+string(20) "foo isn't<br />
+ bar"
+string(7) "a<br>
+b"
+string(9) "no breaks"
+string(45) "&lt;a href=&#039;test&#039;&gt;Test&lt;/a&gt;"
+string(23) ""double" &amp; 'single'"
+string(33) "&quot;double&quot; &amp; 'single'"
+string(15) "&amp;amp; &amp;"
+string(23) "&amp; &lt; &#039; &amp;"
+string(5) "plain"
@@ -0,0 +1,38 @@
+--TEST--
+number_format() rounding, separators and no negative zero
+--FILE--
+<?php
+$number = 1234.56;
+var_dump(number_format($number));
+var_dump(number_format($number, 2, ',', ' '));
+var_dump(number_format(1234.5678, 2, '.', ''));
+var_dump(number_format(-1234.567, 2));
+var_dump(number_format(1000));
+var_dump(number_format(0.5));
+var_dump(number_format(1.005, 2));
+var_dump(number_format(0.129, 2));
+var_dump(number_format(-0.4));
+var_dump(number_format(-0.01, 1));
+var_dump(number_format(123456789, 0, '.', ' '));
+var_dump(number_format(1234.5, 3, ',', '.'));
+var_dump(number_format(1000000000000000.0, 2));
+$long = number_format(1.5, 70000);
+var_dump(strlen($long), substr($long, 0, 5), substr($long, -3));
+?>
+--EXPECT--
+string(5) "1,235"
+string(8) "1 234,56"
+string(7) "1234.57"
+string(9) "-1,234.57"
+string(5) "1,000"
+string(1) "1"
+string(4) "1.01"
+string(4) "0.13"
+string(1) "0"
+string(3) "0.0"
+string(11) "123 456 789"
+string(9) "1.234,500"
+string(24) "1,000,000,000,000,000.00"
+int(70002)
+string(5) "1.500"
+string(3) "000"
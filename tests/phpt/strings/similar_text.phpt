@@ -0,0 +1,33 @@
+--TEST--
+similar_text() and levenshtein()
+--FILE--
+<?php
+var_dump(similar_text("World", "Word"));
+$sim = similar_text("bafoobar", "barfoo", $percent);
+echo "similarity: ", $sim, " (", $percent, " %)\n";
+$sim = similar_text("barfoo", "bafoobar", $percent);
+echo "similarity: ", $sim, " (", $percent, " %)\n";
+var_dump(similar_text("", "", $percent), $percent);
+var_dump(levenshtein("kitten", "sitting"));
+var_dump(levenshtein("flaw", "lawn"));
+var_dump(levenshtein("", "abc"));
+var_dump(levenshtein("abc", ""));
+var_dump(levenshtein("same", "same"));
+var_dump(levenshtein("abc", "abd", 1, 5, 1));
+var_dump(levenshtein("abc", "abd", 1, 1, 1));
+var_dump(levenshtein("a", "", 1, 1, 7));
+?>
+--EXPECT--
+int(4)
+similarity: 5 (71.428571428571 %)
+similarity: 3 (42.857142857143 %)
+int(0)
+float(0)
+int(3)
+int(2)
+int(3)
+int(3)
+int(0)
+int(2)
+int(1)
+int(7)
@@ -0,0 +1,26 @@
+--TEST--
+str_contains(), str_starts_with() and str_ends_with()
+--FILE--
+<?php
+var_dump(str_contains("abc", "b"));
+var_dump(str_contains("abc", "B"));
+var_dump(str_contains("abc", ""));
+var_dump(str_contains("", ""));
+var_dump(str_starts_with("abc", "ab"));
+var_dump(str_starts_with("abc", ""));
+var_dump(str_starts_with("abc", "abcd"));
+var_dump(str_ends_with("abc", "bc"));
+var_dump(str_ends_with("abc", "abcd"));
+var_dump(str_ends_with("", ""));
+?>
+--EXPECT--
+bool(true)
+bool(false)
+bool(true)
+bool(true)
+bool(true)
+bool(true)
+bool(false)
+bool(true)
+bool(false)
+bool(true)
@@ -0,0 +1,35 @@
+--TEST--
+str_pad() and str_repeat()
+--FILE--
+<?php
+$input = "Alien";
+var_dump(str_pad($input, 10));
+var_dump(str_pad($input, 10, "-=", STR_PAD_LEFT));
+var_dump(str_pad($input, 10, "_", STR_PAD_BOTH));
+var_dump(str_pad($input, 6, "___"));
+var_dump(str_pad($input, 3, "*"));
+var_dump(str_pad("5", 3, "0", STR_PAD_LEFT));
+try {
+    str_pad($input, 10, "");
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(str_repeat("-=", 10));
+var_dump(str_repeat("x", 0));
+try {
+    str_repeat("x", -1);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+?>
+--EXPECT--
+string(10) "Alien     "
+string(10) "-=-=-Alien"
+string(10) "__Alien___"
+string(6) "Alien_"
+string(5) "Alien"
+string(3) "005"
+str_pad(): Argument #3 ($pad_string) must be a non-empty string
+string(20) "-=-=-=-=-=-=-=-=-=-="
+string(0) ""
+str_repeat(): Argument #2 ($times) must be greater than or equal to 0
@@ -0,0 +1,41 @@
+--TEST--
+str_replace() with string and array arguments and the count
+--FILE--
+<?php
+echo str_replace("%body%", "black", "<body text='%body%'>"), "\n";
+$vowels = array("a", "e", "i", "o", "u", "A", "E", "I", "O", "U");
+echo str_replace($vowels, "", "Hello World of PHP"), "\n";
+$phrase = "You should eat fruits, vegetables, and fiber every day.";
+$healthy = array("fruits", "vegetables", "fiber");
+$yummy = array("pizza", "beer", "ice cream");
+echo str_replace($healthy, $yummy, $phrase), "\n";
+$str = str_replace("ll", "", "good golly miss molly!", $count);
+echo $str, "\n", $count, "\n";
+$search = array('A', 'B', 'C', 'D', 'E');
+$replace = array('B', 'C', 'D', 'E', 'F');
+echo str_replace($search, $replace, 'A'), "\n";
+$letters = array('a', 'p');
+$fruit = array('apple', 'pear');
+echo str_replace($letters, $fruit, 'a p'), "\n";
+var_dump(str_replace(array("a", "b"), "x", array("k" => "abc", "bad"), $count));
+var_dump($count);
+var_dump(str_replace(array("a", "b", "c"), array("1"), "abc"));
+var_dump(str_ireplace("L", "1", "Hello"));
+?>
+--EXPECT--
+<body text='black'>
+Hll Wrld f PHP
+You should eat pizza, beer, and ice cream every day.
+good goy miss moy!
+2
+F
+apearpearle pear
+array(2) {
+  ["k"]=>
+  string(3) "xxc"
+  [0]=>
+  string(3) "xxd"
+}
+int(4)
+string(1) "1"
+string(5) "He11o"
@@ -0,0 +1,40 @@
+--TEST--
+str_split() and strrev()
+--FILE--
+<?php
+var_dump(str_split("abcdef", 4));
+var_dump(str_split("abc"));
+var_dump(str_split(""));
+var_dump(str_split("abc", 5));
+try {
+    str_split("abc", 0);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+var_dump(strrev("Hello world!"));
+var_dump(strrev(""));
+?>
+--EXPECT--
+array(2) {
+  [0]=>
+  string(4) "abcd"
+  [1]=>
+  string(2) "ef"
+}
+array(3) {
+  [0]=>
+  string(1) "a"
+  [1]=>
+  string(1) "b"
+  [2]=>
+  string(1) "c"
+}
+array(0) {
+}
+array(1) {
+  [0]=>
+  string(3) "abc"
+}
+str_split(): Argument #2 ($length) must be greater than 0
+string(12) "!dlrow olleH"
+string(0) ""
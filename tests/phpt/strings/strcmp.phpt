@@ -0,0 +1,42 @@
+--TEST--
+strcmp() family, including natural order comparison
+--FILE--
+<?php
+var_dump(strcmp("a", "b"));
+var_dump(strcmp("b", "a"));
+var_dump(strcmp("hello", "Hello"));
+var_dump(strcmp("abc", "abc"));
+var_dump(strcmp("ab", "abc"));
+var_dump(strcasecmp("Hello", "hELLO"));
+var_dump(strcasecmp("apple", "Banana"));
+var_dump(strncmp("abcd", "abef", 2));
+var_dump(strncmp("abcd", "abef", 3));
+var_dump(strncasecmp("ABc", "abd", 3));
+var_dump(strcmp("img12.png", "img2.png"));
+var_dump(strnatcmp("img12.png", "img2.png"));
+var_dump(strnatcmp("img12", "img10"));
+var_dump(strnatcasecmp("IMG2", "img10"));
+var_dump(strnatcmp("1.5", "1.10"));
+try {
+    strncmp("a", "b", -1);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+?>
+--EXPECT--
+int(-1)
+int(1)
+int(1)
+int(0)
+int(-1)
+int(0)
+int(-1)
+int(0)
+int(-1)
+int(-1)
+int(-1)
+int(1)
+int(1)
+int(-1)
+int(-1)
+strncmp(): Argument #3 ($length) must be greater than or equal to 0
@@ -0,0 +1,35 @@
+--TEST--
+strpos(), stripos() and strrpos() with positive and negative offsets
+--FILE--
+<?php
+$foo = "0123456789a123456789b123456789c";
+var_dump(strrpos($foo, '7', -5));
+var_dump(strrpos($foo, '7', 20));
+var_dump(strrpos($foo, '7', 28));
+var_dump(strpos("test string", "t"));
+var_dump(strpos("test string", "t", 1));
+var_dump(strpos("test string", "string"));
+var_dump(strpos("test string", "x"));
+var_dump(strpos("test string", "t", -5));
+var_dump(strpos("test string", ""));
+var_dump(stripos("TEST string", "st"));
+var_dump(stripos("abc", "C"));
+try {
+    strpos("abc", "a", 4);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+?>
+--EXPECT--
+int(17)
+int(27)
+bool(false)
+int(0)
+int(3)
+int(5)
+bool(false)
+int(6)
+int(0)
+int(2)
+int(2)
+strpos(): Argument #3 ($offset) must be contained in argument #1 ($haystack)
@@ -0,0 +1,28 @@
+--TEST--
+substr() with negative offsets and lengths
+--FILE--
+<?php
+var_dump(substr("abcdef", -1));
+var_dump(substr("abcdef", -2));
+var_dump(substr("abcdef", -3, 1));
+var_dump(substr("abcdef", 0, -1));
+var_dump(substr("abcdef", 2, -1));
+var_dump(substr("abcdef", 4, -4));
+var_dump(substr("abcdef", -3, -1));
+var_dump(substr("abc", 3));
+var_dump(substr("abc", 5));
+var_dump(substr("abc", 1, 0));
+var_dump(substr("abc", -5, 2));
+?>
+--EXPECT--
+string(1) "f"
+string(2) "ef"
+string(1) "d"
+string(5) "abcde"
+string(3) "cde"
+string(0) ""
+string(2) "de"
+string(0) ""
+string(0) ""
+string(0) ""
+string(2) "ab"
@@ -0,0 +1,28 @@
+--TEST--
+trim(), ltrim() and rtrim() with character lists and ranges
+--FILE--
+<?php
+$text = "\t\tThese are a few words :) ...  ";
+$hello = "Hello World";
+var_dump(trim($text));
+var_dump(trim($text, " \t."));
+var_dump(trim($hello, "Hdle"));
+var_dump(trim($hello, 'HdWr'));
+var_dump(trim("abcxyzcba", "a..c"));
+var_dump(ltrim("0012", "0"));
+var_dump(rtrim("1.500", "0"));
+var_dump(rtrim("line\n"));
+var_dump(chop("  both  "));
+var_dump(trim("[x]", "[]"));
+?>
+--EXPECT--
+string(28) "These are a few words :) ..."
+string(24) "These are a few words :)"
+string(5) "o Wor"
+string(9) "ello Worl"
+string(3) "xyz"
+string(2) "12"
+string(3) "1.5"
+string(4) "line"
+string(6) "  both"
+string(1) "x"
@@ -0,0 +1,38 @@
+--TEST--
+wordwrap() with and without cutting long words
+--FILE--
+<?php
+$text = "The quick brown fox sat over the lazy dog";
+echo wordwrap($text, 15, "<br />\n"), "\n";
+echo wordwrap("A very long woooooooooooord.", 8, "\n", true), "\n";
+echo wordwrap("A very long woooooooooooooooooord. and something", 8, "\n", false), "\n";
+var_dump(wordwrap("123456789", 3, "|", true));
+var_dump(wordwrap("short", 10));
+var_dump(wordwrap("", 10));
+var_dump(wordwrap("a b c", 0, "\n", false));
+try {
+    wordwrap("abc", 0, "\n", true);
+} catch (ValueError $e) {
+    echo $e->getMessage(), "\n";
+}
+?>
+--EXPECT--
+The quick brown<br />
+fox sat over<br />
+the lazy dog
+A very
+long
+wooooooo
+ooooord.
+A very
+long
+woooooooooooooooooord.
+and
+something
+string(11) "123|456|789"
+string(5) "short"
+string(0) ""
+string(5) "a
+b
+c"
+wordwrap(): Argument #4 ($cut_long_words) cannot be true when argument #2 ($width) is 0
@@ -0,0 +1,141 @@
+//! Resource limits for untrusted scripts.
+
+use std::time::Duration;
+
+use phpvm::{Engine, Error, Limits};
+
+fn run(limits: Limits, source: &str) -> (String, Result<(), Error>) {
+    let mut engine = Engine::capturing();
+    engine.vm().set_limits(limits);
+    let result = engine.run_source(source, "sandbox.php");
+    (String::from_utf8(engine.take_output()).unwrap(), result)
+}
+
+fn fatal(result: Result<(), Error>) -> String {
+    match result {
+        Err(Error::Fatal { message, .. }) => message,
+        other => panic!("expected a fatal error, got {:?}", other),
+    }
+}
+
+#[test]
+fn step_limit_stops_infinite_loops() {
+    let limits = Limits { max_steps: Some(10_000), ..Limits::default() };
+    let (_, result) = run(limits, "<?php while (true) {}");
+    assert_eq!(fatal(result), "Maximum execution steps of 10000 exceeded");
+}
+
+#[test]
+fn time_limit_stops_long_scripts() {
+    let limits = Limits { max_execution_time: Some(Duration::from_millis(50)), ..Limits::default() };
+    let (_, result) = run(limits, "<?php while (true) {}");
+    assert!(fatal(result).starts_with("Maximum execution time of 0.05 seconds exceeded"));
+}
+
+#[test]
+fn output_is_cut_off() {
+    let limits = Limits { max_output: Some(5), ..Limits::default() };
+    let (out, result) = run(limits, "<?php while (true) { echo 'abc'; }");
+    assert_eq!(out, "abcab");
+    assert_eq!(fatal(result), "Output size of 5 bytes exceeded");
+}
+
+#[test]
+fn memory_limit_stops_large_strings() {
+    let limits = Limits { memory_limit: Some(1 << 20), ..Limits::default() };
+    let (_, result) = run(limits, "<?php $s = str_repeat('x', 4000000);");
+    assert!(fatal(result).starts_with("Allowed memory size of 1048576 bytes exhausted"));
+}
+
+#[test]
+fn call_depth_is_limited() {
+    let limits = Limits { max_call_depth: Some(50), ..Limits::default() };
+    let (_, result) = run(limits, "<?php function f($n) { return f($n + 1); } f(0);");
+    assert_eq!(fatal(result), "Maximum function nesting level of '50' reached, aborting!");
+}
+
+#[test]
+fn disabled_functions_are_undefined() {
+    let limits = Limits { disable_functions: vec!["str_repeat".to_string()], ..Limits::default() };
+    let (_, result) = run(limits, "<?php echo strlen('ok'); str_repeat('x', 2);");
+    match result {
+        Err(Error::Uncaught { message, .. }) => assert_eq!(message, "Call to undefined function str_repeat()"),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn memory_limit_counts_call_frames() {
+    let limits = Limits { memory_limit: Some(8 << 20), ..Limits::default() };
+    let (_, result) = run(limits, "<?php function f($n) { return f($n + 1); } f(0);");
+    assert!(fatal(result).starts_with("Allowed memory size of 8388608 bytes exhausted"));
+}
+
+#[test]
+fn built_in_functions_stop_at_the_limits() {
+    let long = "$a = str_repeat('a', 200000); $b = str_repeat('b', 200000);";
+    for call in ["levenshtein($a, $b)", "similar_text($a, $b)", "bcpow('10', '100000000')"] {
+        let limits = Limits { max_execution_time: Some(Duration::from_millis(50)), ..Limits::default() };
+        let (_, result) = run(limits, &format!("<?php {} {};", long, call));
+        assert!(fatal(result).starts_with("Maximum execution time of 0.05 seconds exceeded"), "{}", call);
+    }
+    let limits = Limits { max_steps: Some(1_000_000), ..Limits::default() };
+    let (_, result) = run(limits, "<?php levenshtein(str_repeat('a', 2000), str_repeat('b', 2000));");
+    assert_eq!(fatal(result), "Maximum execution steps of 1000000 exceeded");
+}
+
+#[test]
+fn ini_shows_the_limits_in_force() {
+    let (out, _) = run(Limits::default(), "<?php echo ini_get('memory_limit'), ' ', ini_get('max_execution_time');");
+    assert_eq!(out, "-1 0");
+    let limits = Limits { memory_limit: Some(1 << 20), max_execution_time: Some(Duration::from_secs(2)), ..Limits::default() };
+    let (out, _) = run(limits, "<?php echo ini_get('memory_limit'), ' ', ini_get('max_execution_time');");
+    assert_eq!(out, "1048576 2");
+}
+
+#[test]
+fn bcmath_results_are_charged_before_they_are_built() {
+    for call in ["bcpow('10', '100000000')", "bcadd('1', '2', 2000000000)", "bcmul(str_repeat('9', 600000), '9')"] {
+        let limits = Limits { memory_limit: Some(1 << 20), ..Limits::default() };
+        let (_, result) = run(limits, &format!("<?php {};", call));
+        assert!(fatal(result).starts_with("Allowed memory size of 1048576 bytes exhausted"), "{}", call);
+    }
+    let limits = Limits { memory_limit: Some(1 << 20), ..Limits::default() };
+    let (out, _) = run(limits, "<?php echo bcpow('1', '1000000000'), ' ', strlen(bcpow('2', '10000'));");
+    assert_eq!(out, "1 3011");
+}
+
+#[test]
+fn number_format_reserves_its_decimals() {
+    let limits = Limits { memory_limit: Some(1 << 20), ..Limits::default() };
+    let (_, result) = run(limits, "<?php number_format(1.5, 2000000000);");
+    assert!(fatal(result).starts_with("Allowed memory size of 1048576 bytes exhausted"));
+}
+
+#[test]
+fn growing_results_are_charged_while_they_are_built() {
+    for call in [
+        "str_replace('a', str_repeat('b', 2000), str_repeat('a', 2000))",
+        "str_ireplace('A', str_repeat('b', 2000), str_repeat('a', 2000))",
+        "strtr(str_repeat('a', 2000), ['a' => str_repeat('b', 2000)])",
+        "preg_replace('/a/', str_repeat('b', 2000), str_repeat('a', 2000))",
+        "preg_replace('/a+/', str_repeat('$0', 2000), str_repeat('a', 2000))",
+        "preg_replace_callback('/a/', function ($m) { return str_repeat('b', 2000); }, str_repeat('a', 2000))",
+        "wordwrap(str_repeat('x ', 300000), 1, str_repeat('-', 100), true)",
+        "str_split(str_repeat('x', 100000))",
+        "explode(',', str_repeat(',', 100000))",
+        "preg_split('//', str_repeat('x', 100000))",
+        "preg_match_all('/x/', str_repeat('x', 100000), $m)",
+        "sprintf('%2000000d', 1)",
+        "htmlspecialchars(str_repeat('<', 300000))",
+        "nl2br(str_repeat(PHP_EOL, 300000))",
+        "var_export(array_fill(0, 20000, str_repeat('x', 100)), true)",
+        "print_r(array_fill(0, 20000, str_repeat('x', 100)), true)",
+        "serialize(array_fill(0, 20000, str_repeat('x', 100)))",
+        "json_encode(array_fill(0, 20000, str_repeat('x', 100)))",
+    ] {
+        let limits = Limits { memory_limit: Some(1 << 20), ..Limits::default() };
+        let (_, result) = run(limits, &format!("<?php {};", call));
+        assert!(fatal(result).starts_with("Allowed memory size of 1048576 bytes exhausted"), "{}", call);
+    }
+}